@@ -80,9 +80,10 @@ impl OpenUriPage {
                 }
                 .and_then(|r| r.response());
                 match response {
-                    Ok(_) => {
-                        self.success("Open URI request was successful");
-                    }
+                    Ok(response) => match response.choice() {
+                        Some(app_id) => self.success(&format!("Opened in {app_id}")),
+                        None => self.success("Open URI request was successful"),
+                    },
                     Err(err) => {
                         tracing::error!("Failed to open URI: {err}");
                         self.error("Request to open URI failed");
@@ -54,7 +54,7 @@ impl SecretPage {
 
         match secret::retrieve().await {
             Ok(key) => {
-                let key_str = format!("{key:?}")
+                let key_str = format!("{:?}", key.expose_secret())
                     .trim_start_matches('[')
                     .trim_end_matches(']')
                     .replace(',', " ");
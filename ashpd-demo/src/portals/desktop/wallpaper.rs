@@ -92,7 +92,7 @@ impl WallpaperPage {
             .identifier(identifier)
             .show_preview(show_preview)
             .set_on(set_on)
-            .build_uri(&uri)
+            .send_uri(&uri)
             .await
         {
             Err(err) => {
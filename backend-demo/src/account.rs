@@ -1,7 +1,7 @@
 use ashpd::{
     backend::{
         account::{AccountImpl, UserInformationOptions},
-        request::RequestImpl,
+        request::{RequestHandle, RequestImpl},
         Result,
     },
     desktop::account::UserInformation,
@@ -14,7 +14,7 @@ pub struct Account;
 
 #[async_trait]
 impl RequestImpl for Account {
-    async fn close(&self) {
+    async fn close(&self, _handle: RequestHandle) {
         tracing::debug!("IN Close()");
     }
 }
@@ -35,29 +35,33 @@ mod fdo_account {
     }
 }
 
-#[async_trait]
 impl AccountImpl for Account {
     async fn get_user_information(
         &self,
-        _app_id: Option<AppID>,
+        _handle: RequestHandle,
+        app_id: Option<AppID>,
         _window_identifier: Option<WindowIdentifierType>,
-        _options: UserInformationOptions,
+        options: UserInformationOptions,
     ) -> Result<UserInformation> {
-        // Retrieve current user information by using the
-        // `org.freedesktop.Accounts` interfaces.
-        let cnx = zbus::Connection::system().await?;
-        let uid = nix::unistd::Uid::current().as_raw();
-        let path = format!("/org/freedesktop/Accounts/User{}", uid);
-        let proxy = fdo_account::AccountsProxy::builder(&cnx)
-            .path(path)?
-            .build()
-            .await?;
-
-        let uri = format!("file://{}", proxy.icon_file().await?);
+        tracing::debug!(
+            "IN GetUserInformation({app_id:?}, reason: {:?})",
+            options.reason()
+        );
+        let uid = nix::unistd::Uid::current();
+        // Prefer the `org.freedesktop.Accounts` service and fall back to the
+        // passwd entry and `~/.face` on systems without it.
+        let (name, real_name, icon_file) = match accounts_service(uid).await {
+            Ok(info) => info,
+            Err(err) => {
+                tracing::debug!("org.freedesktop.Accounts is not available: {err}");
+                passwd_entry(uid)?
+            }
+        };
 
+        let uri = format!("file://{icon_file}");
         Ok(UserInformation::new(
-            &proxy.user_name().await?,
-            &proxy.real_name().await?,
+            &name,
+            &real_name,
             url::Url::parse(&uri).map_err(|e| {
                 ashpd::PortalError::Failed(format!(
                     "Failed to parse user avatar uri from `{uri}` with {e}"
@@ -66,3 +70,29 @@ impl AccountImpl for Account {
         ))
     }
 }
+
+async fn accounts_service(uid: nix::unistd::Uid) -> zbus::Result<(String, String, String)> {
+    let cnx = zbus::Connection::system().await?;
+    let path = format!("/org/freedesktop/Accounts/User{}", uid.as_raw());
+    let proxy = fdo_account::AccountsProxy::builder(&cnx)
+        .path(path)?
+        .build()
+        .await?;
+    Ok((
+        proxy.user_name().await?,
+        proxy.real_name().await?,
+        proxy.icon_file().await?,
+    ))
+}
+
+fn passwd_entry(uid: nix::unistd::Uid) -> Result<(String, String, String)> {
+    let user = nix::unistd::User::from_uid(uid)
+        .ok()
+        .flatten()
+        .ok_or_else(|| ashpd::PortalError::NotFound(format!("No passwd entry for {uid}")))?;
+    // The GECOS field is a comma separated list starting with the full name.
+    let gecos = user.gecos.to_string_lossy();
+    let real_name = gecos.split(',').next().unwrap_or_default().to_owned();
+    let icon_file = user.dir.join(".face").to_string_lossy().into_owned();
+    Ok((user.name, real_name, icon_file))
+}
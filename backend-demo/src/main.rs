@@ -23,43 +23,48 @@ async fn main() -> ashpd::Result<()> {
     // Enable debug with `RUST_LOG=ashpd_backend_demo=debug COMMAND`.
     tracing_subscriber::fmt::init();
 
-    let cnx = zbus::ConnectionBuilder::session()?
-        .name(NAME)?
-        .build()
-        .await?;
-    let object_server = cnx.object_server();
+    let backend = ashpd::backend::Backend::new(NAME).await?;
+    init_interfaces(&backend).await?;
 
-    let portal = ashpd::backend::account::AccountInterface::new(Account, cnx.clone());
-    tracing::debug!("Serving interface `org.freedesktop.impl.portal.Account`");
-    object_server
-        .at("/org/freedesktop/portal/desktop", portal)
-        .await?;
+    loop {
+        pending::<()>().await;
+    }
+}
 
-    let portal = ashpd::backend::screenshot::ScreenshotInterface::new(Screenshot, cnx.clone());
-    tracing::debug!("Serving interface `org.freedesktop.impl.portal.Screenshot`");
-    object_server
-        .at("/org/freedesktop/portal/desktop", portal)
-        .await?;
+/// Export every portal implemented by the demo on the backend's connection.
+async fn init_interfaces(backend: &ashpd::backend::Backend) -> ashpd::Result<()> {
+    let cnx = backend.connection();
 
-    let portal = ashpd::backend::secret::SecretInterface::new(Secret, cnx.clone());
-    tracing::debug!("Serving interface `org.freedesktop.impl.portal.Secret`");
-    object_server
-        .at("/org/freedesktop/portal/desktop", portal)
+    backend
+        .serve(ashpd::backend::account::AccountInterface::new(
+            Account,
+            cnx.clone(),
+        ))
         .await?;
-
-    let portal = ashpd::backend::settings::SettingsInterface::new(Settings::default(), cnx.clone());
-    tracing::debug!("Serving interface `org.freedesktop.impl.portal.Settings`");
-    object_server
-        .at("/org/freedesktop/portal/desktop", portal)
+    backend
+        .serve(ashpd::backend::screenshot::ScreenshotInterface::new(
+            Screenshot,
+            cnx.clone(),
+        ))
         .await?;
-
-    let portal = ashpd::backend::wallpaper::WallpaperInterface::new(Wallpaper, cnx.clone());
-    tracing::debug!("Serving interface `org.freedesktop.impl.portal.Wallpaper`");
-    object_server
-        .at("/org/freedesktop/portal/desktop", portal)
+    backend
+        .serve(ashpd::backend::secret::SecretInterface::new(
+            Secret,
+            cnx.clone(),
+        ))
+        .await?;
+    backend
+        .serve(ashpd::backend::settings::SettingsInterface::new(
+            Settings::default(),
+            cnx.clone(),
+        ))
+        .await?;
+    backend
+        .serve(ashpd::backend::wallpaper::WallpaperInterface::new(
+            Wallpaper,
+            cnx.clone(),
+        ))
         .await?;
 
-    loop {
-        pending::<()>().await;
-    }
+    Ok(())
 }
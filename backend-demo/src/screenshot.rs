@@ -1,6 +1,6 @@
 use ashpd::{
     backend::{
-        request::RequestImpl,
+        request::{RequestHandle, RequestImpl},
         screenshot::{ColorOptions, ScreenshotImpl, ScreenshotOptions},
         Result,
     },
@@ -14,7 +14,7 @@ pub struct Screenshot;
 
 #[async_trait]
 impl RequestImpl for Screenshot {
-    async fn close(&self) {
+    async fn close(&self, _handle: RequestHandle) {
         tracing::debug!("IN Close()");
     }
 }
@@ -23,6 +23,7 @@ impl RequestImpl for Screenshot {
 impl ScreenshotImpl for Screenshot {
     async fn screenshot(
         &self,
+        _handle: RequestHandle,
         _app_id: Option<AppID>,
         _window_identifier: Option<WindowIdentifierType>,
         _options: ScreenshotOptions,
@@ -34,6 +35,7 @@ impl ScreenshotImpl for Screenshot {
 
     async fn pick_color(
         &self,
+        _handle: RequestHandle,
         _app_id: Option<AppID>,
         _window_identifier: Option<WindowIdentifierType>,
         _options: ColorOptions,
@@ -1,7 +1,11 @@
 use std::collections::HashMap;
 
 use ashpd::{
-    backend::{request::RequestImpl, secret::SecretImpl, Result},
+    backend::{
+        request::{RequestHandle, RequestImpl},
+        secret::SecretImpl,
+        Result,
+    },
     zbus::zvariant::OwnedValue,
     AppID,
 };
@@ -12,7 +16,7 @@ pub struct Secret;
 
 #[async_trait]
 impl RequestImpl for Secret {
-    async fn close(&self) {
+    async fn close(&self, _handle: RequestHandle) {
         tracing::debug!("IN Close()");
     }
 }
@@ -21,6 +25,7 @@ impl RequestImpl for Secret {
 impl SecretImpl for Secret {
     async fn retrieve(
         &self,
+        _handle: RequestHandle,
         _app_id: AppID,
         _fd: std::os::fd::OwnedFd,
     ) -> Result<HashMap<String, OwnedValue>> {
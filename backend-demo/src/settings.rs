@@ -1,7 +1,10 @@
 use std::collections::HashMap;
 
 use ashpd::{
-    backend::{request::RequestImpl, settings::SettingsImpl},
+    backend::{
+        request::{RequestHandle, RequestImpl},
+        settings::SettingsImpl,
+    },
     desktop::settings::{ColorScheme, Namespace, APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY},
     zbus::zvariant::OwnedValue,
     PortalError,
@@ -15,12 +18,11 @@ pub struct Settings {
 
 #[async_trait]
 impl RequestImpl for Settings {
-    async fn close(&self) {
+    async fn close(&self, _handle: RequestHandle) {
         tracing::debug!("IN Close()");
     }
 }
 
-#[async_trait]
 impl SettingsImpl for Settings {
     async fn read_all(
         &self,
@@ -40,7 +42,7 @@ impl SettingsImpl for Settings {
         if namespace == APPEARANCE_NAMESPACE && key == COLOR_SCHEME_KEY {
             Ok(OwnedValue::from(self.color_scheme))
         } else {
-            Err(PortalError::Failed(format!(
+            Err(PortalError::NotFound(format!(
                 "Unsupported namespace=`{namespace}` & key=`{key}`"
             )))
         }
@@ -1,6 +1,6 @@
 use ashpd::{
     backend::{
-        request::RequestImpl,
+        request::{RequestHandle, RequestImpl},
         wallpaper::{WallpaperImpl, WallpaperOptions},
         Result,
     },
@@ -13,15 +13,15 @@ pub struct Wallpaper;
 
 #[async_trait]
 impl RequestImpl for Wallpaper {
-    async fn close(&self) {
+    async fn close(&self, _handle: RequestHandle) {
         tracing::debug!("IN Close()");
     }
 }
 
-#[async_trait]
 impl WallpaperImpl for Wallpaper {
     async fn with_uri(
         &self,
+        _handle: RequestHandle,
         _app_id: Option<AppID>,
         _window_identifier: Option<WindowIdentifierType>,
         _uri: url::Url,
@@ -9,6 +9,8 @@ use zbus::zvariant::Type;
 #[derive(Debug, Deserialize, Serialize, Type, PartialEq, Eq, Hash, Clone)]
 pub struct ActivationToken(String);
 
+crate::helpers::impl_value_conversions!(ActivationToken as str);
+
 impl From<String> for ActivationToken {
     fn from(value: String) -> Self {
         Self(value)
@@ -9,6 +9,8 @@ use zbus::zvariant::Type;
 #[derive(Debug, Serialize, Type, PartialEq, Eq, Hash, Clone)]
 pub struct AppID(String);
 
+crate::helpers::impl_value_conversions!(AppID as str);
+
 impl AppID {
     #[cfg(all(
         feature = "backend",
@@ -97,6 +99,8 @@ impl<'de> Deserialize<'de> for AppID {
 #[derive(Debug, Serialize, Deserialize, Type, PartialEq, Eq, Hash, Clone)]
 pub struct DocumentID(String);
 
+crate::helpers::impl_value_conversions!(DocumentID as str);
+
 impl From<&str> for DocumentID {
     fn from(value: &str) -> Self {
         Self(value.to_owned())
@@ -233,4 +237,20 @@ mod tests {
         assert!(!is_valid_app_id("conta|ns.invalid.characters"));
         assert!(!is_valid_app_id("contæins.inva_å_lid.characters"));
     }
+
+    #[test]
+    fn value_conversions() {
+        use zbus::zvariant::{OwnedValue, Value};
+
+        let app_id = AppID::from_str("org.example.App").unwrap();
+        let value = Value::from(app_id.clone());
+        assert_eq!(value, Value::from("org.example.App"));
+        assert_eq!(AppID::try_from(value).unwrap(), app_id);
+
+        let value = OwnedValue::from(app_id.clone());
+        assert_eq!(AppID::try_from(value).unwrap(), app_id);
+
+        assert!(AppID::try_from(Value::from("not an app id")).is_err());
+        assert!(AppID::try_from(Value::from(1u32)).is_err());
+    }
 }
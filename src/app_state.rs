@@ -0,0 +1,68 @@
+//! The state stored on behalf of an application under the XDG state
+//! directory, which is private to the application when it is sandboxed.
+//!
+//! The files are only readable by the current user and replaced atomically.
+//! The file system is accessed off the executor.
+
+use std::{io::ErrorKind, os::unix::fs::DirBuilderExt, path::PathBuf};
+
+use crate::{AppID, Error};
+
+/// The directory `name` of the application `app_id`,
+/// `$XDG_STATE_HOME/<app_id>/<name>`.
+pub(crate) fn app_dir(app_id: &AppID, name: &str) -> Result<PathBuf, Error> {
+    let state_dir = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(".local").join("state"))
+            .ok_or_else(|| {
+                std::io::Error::new(
+                    ErrorKind::NotFound,
+                    "Failed to find the XDG state directory, HOME is not set",
+                )
+            })?,
+    };
+    Ok(state_dir.join(app_id.as_ref()).join(name))
+}
+
+/// The content of the file at `path`, `None` if it doesn't exist.
+pub(crate) async fn read(path: PathBuf) -> Result<Option<Vec<u8>>, Error> {
+    crate::helpers::unblock(move || match std::fs::read(path) {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    })
+    .await
+}
+
+/// Replace the content of the file at `path` with `data`, creating its
+/// directory if needed.
+pub(crate) async fn write(path: PathBuf, data: Vec<u8>) -> Result<(), Error> {
+    crate::helpers::unblock(move || {
+        if let Some(dir) = path.parent() {
+            std::fs::DirBuilder::new()
+                .recursive(true)
+                .mode(0o700)
+                .create(dir)?;
+        }
+        crate::documents::atomic_save_private(&path, &data)?;
+        Ok(())
+    })
+    .await
+}
+
+/// Remove the file at `path`, if any.
+pub(crate) async fn remove(path: PathBuf) -> Result<(), Error> {
+    crate::helpers::unblock(move || match std::fs::remove_file(path) {
+        Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+        _ => Ok(()),
+    })
+    .await
+}
+
+/// Whether `path` is an existing directory.
+#[cfg(feature = "file_chooser")]
+pub(crate) async fn is_dir(path: &std::path::Path) -> bool {
+    let path = path.to_owned();
+    crate::helpers::unblock(move || path.is_dir()).await
+}
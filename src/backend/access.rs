@@ -1,27 +1,38 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use async_trait::async_trait;
 
 use crate::{
     backend::{
-        request::{Request, RequestImpl},
+        request::{Dispatcher, Request, RequestHandle, RequestImpl},
         MaybeAppID, MaybeWindowIdentifier, Result,
     },
     desktop::{file_chooser::Choice, request::Response, Icon},
-    zvariant::{self, DeserializeDict, OwnedObjectPath, SerializeDict},
-    AppID, WindowIdentifierType,
+    proxy::DESKTOP_PATH,
+    zvariant::{self, OwnedObjectPath, OwnedValue, SerializeDict},
+    AppID, PortalError, WindowIdentifierType,
 };
 
-#[derive(DeserializeDict, zvariant::Type)]
+#[derive(zvariant::Type, Default)]
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct AccessOptions {
     modal: Option<bool>,
     deny_label: Option<String>,
     grant_label: Option<String>,
     icon: Option<String>,
     choices: Option<Vec<Choice>>,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(AccessOptions {
+    modal => "modal",
+    deny_label => "deny_label",
+    grant_label => "grant_label",
+    icon => "icon",
+    choices => "choices",
+});
+
 impl AccessOptions {
     pub fn is_modal(&self) -> Option<bool> {
         self.modal
@@ -42,6 +53,11 @@ impl AccessOptions {
     pub fn choices(&self) -> &[Choice] {
         self.choices.as_deref().unwrap_or_default()
     }
+
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
 #[derive(SerializeDict, Debug, zvariant::Type, Default)]
@@ -51,6 +67,11 @@ pub struct AccessResponse {
 }
 
 impl AccessResponse {
+    /// The selected choices (key, value).
+    pub fn choices(&self) -> &[(String, String)] {
+        self.choices.as_deref().unwrap_or_default()
+    }
+
     /// Adds a selected choice (key, value).
     #[must_use]
     pub fn choice(mut self, key: &str, value: &str) -> Self {
@@ -61,10 +82,194 @@ impl AccessResponse {
     }
 }
 
+/// The strings and options of an access dialog, shown with
+/// [`Access::request`].
+///
+/// The constructors named after a resource use the same strings as
+/// xdg-desktop-portal does for the portals guarding it. The grant and deny
+/// labels are left to the frontend unless set.
+#[derive(Default)]
+pub struct AccessDialog {
+    title: String,
+    subtitle: String,
+    body: String,
+    options: AccessOptions,
+}
+
+impl AccessDialog {
+    pub fn new(
+        title: impl Into<String>,
+        subtitle: impl Into<String>,
+        body: impl Into<String>,
+    ) -> Self {
+        Self {
+            title: title.into(),
+            subtitle: subtitle.into(),
+            body: body.into(),
+            options: AccessOptions::default(),
+        }
+    }
+
+    /// Access to the cameras, for the Camera and Device portals.
+    pub fn camera(app_id: Option<&AppID>) -> Self {
+        Self::new(
+            "Turn On Camera?",
+            format!("{} wants to use the camera.", app_name(app_id)),
+            "Access to your camera can be changed at any time from the privacy settings.",
+        )
+        .icon("camera-web-symbolic")
+    }
+
+    /// Access to the microphones, for the Device portal.
+    pub fn microphone(app_id: Option<&AppID>) -> Self {
+        Self::new(
+            "Turn On Microphone?",
+            format!("{} wants to access recording devices.", app_name(app_id)),
+            "Access to your microphone can be changed at any time from the privacy settings.",
+        )
+        .icon("audio-input-microphone-symbolic")
+    }
+
+    /// Access to the speakers, for the Device portal.
+    pub fn speakers(app_id: Option<&AppID>) -> Self {
+        Self::new(
+            "Turn On Speakers?",
+            format!("{} wants to play sounds.", app_name(app_id)),
+            "Access to your speakers can be changed at any time from the privacy settings.",
+        )
+        .icon("audio-speakers-symbolic")
+    }
+
+    /// Access to the location, for the Location portal.
+    pub fn location(app_id: Option<&AppID>) -> Self {
+        Self::new(
+            "Turn On Location Services?",
+            format!("{} wants to use your location.", app_name(app_id)),
+            "Location access can be changed at any time from the privacy settings.",
+        )
+        .icon("find-location-symbolic")
+    }
+
+    #[must_use]
+    pub fn modal(mut self, modal: bool) -> Self {
+        self.options.modal = Some(modal);
+        self
+    }
+
+    #[must_use]
+    pub fn grant_label(mut self, label: impl Into<String>) -> Self {
+        self.options.grant_label = Some(label.into());
+        self
+    }
+
+    #[must_use]
+    pub fn deny_label(mut self, label: impl Into<String>) -> Self {
+        self.options.deny_label = Some(label.into());
+        self
+    }
+
+    #[must_use]
+    pub fn icon(mut self, icon: impl Into<String>) -> Self {
+        self.options.icon = Some(icon.into());
+        self
+    }
+
+    #[must_use]
+    pub fn choice(mut self, choice: Choice) -> Self {
+        self.options
+            .choices
+            .get_or_insert_with(Vec::new)
+            .push(choice);
+        self
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn subtitle(&self) -> &str {
+        &self.subtitle
+    }
+
+    pub fn body(&self) -> &str {
+        &self.body
+    }
+
+    pub fn options(&self) -> &AccessOptions {
+        &self.options
+    }
+}
+
+fn app_name(app_id: Option<&AppID>) -> &str {
+    app_id.map_or("An application", |app_id| app_id.as_ref())
+}
+
+/// Shows the access dialogs of the [`AccessImpl`] served by a backend, for
+/// the other portals it implements, like xdg-desktop-portal does before
+/// granting access to a device or the location.
+///
+/// Returned by [`Backend::access`](crate::backend::Backend::access).
+pub struct Access<'a> {
+    cnx: &'a zbus::Connection,
+}
+
+impl<'a> Access<'a> {
+    pub(crate) fn new(cnx: &'a zbus::Connection) -> Self {
+        Self { cnx }
+    }
+
+    /// Show `dialog` for the request at `handle`, made by `app_id`.
+    ///
+    /// Returns the selected choices if the user granted the access, `None`
+    /// if they denied it.
+    ///
+    /// Fails with [`PortalError::NotFound`] if the backend doesn't serve an
+    /// [`AccessInterface`].
+    pub async fn request(
+        self,
+        handle: RequestHandle,
+        app_id: Option<AppID>,
+        window_identifier: Option<WindowIdentifierType>,
+        dialog: AccessDialog,
+    ) -> Result<Option<AccessResponse>> {
+        let imp = match self
+            .cnx
+            .object_server()
+            .interface::<_, AccessInterface>(DESKTOP_PATH)
+            .await
+        {
+            Ok(iface_ref) => Arc::clone(&iface_ref.get().await.imp),
+            Err(_) => {
+                return Err(PortalError::NotFound(
+                    "The backend doesn't serve the Access portal".to_owned(),
+                ))
+            }
+        };
+        let response = imp
+            .access_dialog(
+                handle,
+                app_id,
+                window_identifier,
+                dialog.title,
+                dialog.subtitle,
+                dialog.body,
+                dialog.options,
+            )
+            .await;
+        match response {
+            Ok(response) => Ok(Some(response)),
+            Err(PortalError::Cancelled(_) | PortalError::NotAllowed(_)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+}
+
 #[async_trait]
 pub trait AccessImpl: RequestImpl {
+    #[allow(clippy::too_many_arguments)]
     async fn access_dialog(
         &self,
+        handle: RequestHandle,
         app_id: Option<AppID>,
         window_identifier: Option<WindowIdentifierType>,
         title: String,
@@ -77,22 +282,36 @@ pub trait AccessImpl: RequestImpl {
 pub struct AccessInterface {
     imp: Arc<dyn AccessImpl>,
     cnx: zbus::Connection,
+    dispatcher: Dispatcher,
+    version: u32,
 }
 
+impl_version!(AccessInterface, 1);
+
 impl AccessInterface {
     pub fn new(imp: impl AccessImpl + 'static, cnx: zbus::Connection) -> Self {
         Self {
             imp: Arc::new(imp),
             cnx,
+            dispatcher: Dispatcher::default(),
+            version: Self::VERSION,
         }
     }
+
+    /// Limit how many requests are handled at the same time, see
+    /// [`Dispatcher`].
+    #[must_use]
+    pub fn with_dispatcher(mut self, dispatcher: Dispatcher) -> Self {
+        self.dispatcher = dispatcher;
+        self
+    }
 }
 
 #[zbus::interface(name = "org.freedesktop.impl.portal.Access")]
 impl AccessInterface {
     #[dbus_interface(property(emits_changed_signal = "const"), name = "version")]
     fn version(&self) -> u32 {
-        1 // TODO: Is this correct?
+        self.version
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -112,10 +331,12 @@ impl AccessInterface {
         Request::spawn(
             "Access::AccessDialog",
             &self.cnx,
-            handle,
+            &self.dispatcher,
+            handle.clone(),
             Arc::clone(&self.imp),
             async move {
                 imp.access_dialog(
+                    handle.into(),
                     app_id.inner(),
                     window_identifier.inner(),
                     title,
@@ -129,3 +350,75 @@ impl AccessInterface {
         .await
     }
 }
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use super::*;
+    use crate::helpers::p2p_backend;
+
+    /// Grants the access to the applications in its list.
+    struct Gatekeeper(Vec<AppID>);
+
+    #[async_trait]
+    impl RequestImpl for Gatekeeper {
+        async fn close(&self, _handle: RequestHandle) {}
+    }
+
+    #[async_trait]
+    impl AccessImpl for Gatekeeper {
+        async fn access_dialog(
+            &self,
+            _handle: RequestHandle,
+            app_id: Option<AppID>,
+            _window_identifier: Option<WindowIdentifierType>,
+            title: String,
+            _subtitle: String,
+            _body: String,
+            options: AccessOptions,
+        ) -> Result<AccessResponse> {
+            assert_eq!(title, "Turn On Location Services?");
+            assert_eq!(options.grant_label(), None);
+            match app_id {
+                Some(app_id) if self.0.contains(&app_id) => {
+                    Ok(AccessResponse::default().choice("accuracy", "city"))
+                }
+                _ => Err(PortalError::Cancelled("Denied".to_owned())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn access_dialog() {
+        let (backend, _client) = p2p_backend().await;
+        let handle = RequestHandle::from(
+            OwnedObjectPath::try_from("/org/freedesktop/portal/desktop/request/0/access").unwrap(),
+        );
+        let allowed: AppID = "org.example.Allowed".parse().unwrap();
+        let denied: AppID = "org.example.Denied".parse().unwrap();
+        let request = |app_id: &AppID| {
+            backend.access().request(
+                handle.clone(),
+                Some(app_id.clone()),
+                None,
+                AccessDialog::location(Some(app_id)),
+            )
+        };
+
+        assert!(matches!(
+            request(&allowed).await,
+            Err(PortalError::NotFound(_))
+        ));
+
+        let iface = AccessInterface::new(
+            Gatekeeper(vec![allowed.clone()]),
+            backend.connection().clone(),
+        );
+        assert!(backend.serve(iface).await.unwrap());
+        let response = request(&allowed).await.unwrap().unwrap();
+        assert_eq!(
+            response.choices(),
+            [("accuracy".to_owned(), "city".to_owned())]
+        );
+        assert!(request(&denied).await.unwrap().is_none());
+    }
+}
@@ -1,58 +1,81 @@
-use std::sync::Arc;
-
-use async_trait::async_trait;
+use std::{collections::HashMap, future::Future, sync::Arc};
 
 use crate::{
     backend::{
-        request::{Request, RequestImpl},
+        request::{Dispatcher, Request, RequestHandle, RequestImpl},
         MaybeAppID, MaybeWindowIdentifier, Result,
     },
     desktop::{account::UserInformation, request::Response},
-    zvariant::{DeserializeDict, OwnedObjectPath, Type},
+    zvariant::{OwnedObjectPath, OwnedValue, Type},
     AppID, WindowIdentifierType,
 };
 
-#[derive(Debug, DeserializeDict, Type)]
+#[derive(Debug, Type)]
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct UserInformationOptions {
     reason: Option<String>,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(UserInformationOptions {
+    reason => "reason",
+});
+
 impl UserInformationOptions {
     pub fn reason(&self) -> Option<&str> {
         self.reason.as_deref()
     }
+
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
-#[async_trait]
 pub trait AccountImpl: RequestImpl {
-    async fn get_user_information(
+    fn get_user_information(
         &self,
+        handle: RequestHandle,
         app_id: Option<AppID>,
         window_identifier: Option<WindowIdentifierType>,
         options: UserInformationOptions,
-    ) -> Result<UserInformation>;
+    ) -> impl Future<Output = Result<UserInformation>> + Send;
 }
 
-pub struct AccountInterface {
-    imp: Arc<dyn AccountImpl>,
+pub struct AccountInterface<T> {
+    imp: Arc<T>,
     cnx: zbus::Connection,
+    dispatcher: Dispatcher,
+    version: u32,
 }
 
-impl AccountInterface {
-    pub fn new(imp: impl AccountImpl + 'static, cnx: zbus::Connection) -> Self {
+impl_version!(AccountInterface<T: AccountImpl>, 1);
+
+impl<T: AccountImpl + 'static> AccountInterface<T> {
+    pub fn new(imp: T, cnx: zbus::Connection) -> Self {
         Self {
             imp: Arc::new(imp),
             cnx,
+            dispatcher: Dispatcher::default(),
+            version: Self::VERSION,
         }
     }
+
+    /// Limit how many requests are handled at the same time, see
+    /// [`Dispatcher`].
+    #[must_use]
+    pub fn with_dispatcher(mut self, dispatcher: Dispatcher) -> Self {
+        self.dispatcher = dispatcher;
+        self
+    }
 }
 
 #[zbus::interface(name = "org.freedesktop.impl.portal.Account")]
-impl AccountInterface {
+impl<T: AccountImpl + 'static> AccountInterface<T> {
     #[zbus(property(emits_changed_signal = "const"), name = "version")]
     fn version(&self) -> u32 {
-        1
+        self.version
     }
 
     #[zbus(name = "GetUserInformation")]
@@ -69,11 +92,17 @@ impl AccountInterface {
         Request::spawn(
             "Account::GetUserInformation",
             &self.cnx,
-            handle,
+            &self.dispatcher,
+            handle.clone(),
             Arc::clone(&self.imp),
             async move {
-                imp.get_user_information(app_id.inner(), window_identifier.inner(), options)
-                    .await
+                imp.get_user_information(
+                    handle.into(),
+                    app_id.inner(),
+                    window_identifier.inner(),
+                    options,
+                )
+                .await
             },
         )
         .await
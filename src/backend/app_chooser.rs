@@ -1,20 +1,25 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use async_trait::async_trait;
 
 use crate::{
     backend::{
-        request::{Request, RequestImpl},
+        request::{Dispatcher, Request, RequestHandle, RequestImpl},
         MaybeAppID, MaybeWindowIdentifier,
     },
     desktop::Response,
-    zbus::object_server::{InterfaceRef, ObjectServer},
-    zvariant::{DeserializeDict, OwnedObjectPath, SerializeDict, Type},
-    ActivationToken, AppID, PortalError, WindowIdentifierType,
+    proxy::{Proxy, DESKTOP_PATH},
+    zbus::{
+        names::BusName,
+        object_server::{InterfaceRef, ObjectServer},
+    },
+    zvariant::{OwnedObjectPath, OwnedValue, SerializeDict, Type},
+    ActivationToken, AppID, Error, PortalError, WindowIdentifierType,
 };
 
-#[derive(Debug, DeserializeDict, Type)]
+#[derive(Debug, Type)]
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct ChooserOptions {
     last_choice: Option<AppID>,
     modal: Option<bool>,
@@ -22,8 +27,18 @@ pub struct ChooserOptions {
     uri: Option<url::Url>,
     filename: Option<String>,
     activation_token: Option<ActivationToken>,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(ChooserOptions {
+    last_choice => "last_choice",
+    modal => "modal",
+    content_type => "content_type",
+    uri => "uri",
+    filename => "filename",
+    activation_token => "activation_token",
+});
+
 impl ChooserOptions {
     pub fn last_choice(&self) -> Option<&AppID> {
         self.last_choice.as_ref()
@@ -48,6 +63,19 @@ impl ChooserOptions {
     pub fn activation_token(&self) -> Option<&ActivationToken> {
         self.activation_token.as_ref()
     }
+
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+
+    /// Drop the options introduced after `version` of the interface, which
+    /// the implementation didn't declare to support.
+    fn strip_newer(&mut self, version: u32) {
+        if version < 2 {
+            self.activation_token = None;
+        }
+    }
 }
 
 #[derive(Debug, SerializeDict, Type)]
@@ -79,6 +107,7 @@ impl Choice {
 pub trait AppChooserImpl: RequestImpl {
     async fn choose_application(
         &self,
+        handle: RequestHandle,
         app_id: Option<AppID>,
         parent_window: Option<WindowIdentifierType>,
         choices: Vec<AppID>,
@@ -95,22 +124,36 @@ pub trait AppChooserImpl: RequestImpl {
 pub struct AppChooserInterface {
     imp: Arc<dyn AppChooserImpl>,
     cnx: zbus::Connection,
+    dispatcher: Dispatcher,
+    version: u32,
 }
 
+impl_version!(AppChooserInterface, 2);
+
 impl AppChooserInterface {
     pub fn new(imp: impl AppChooserImpl + 'static, cnx: zbus::Connection) -> Self {
         Self {
             imp: Arc::new(imp),
             cnx,
+            dispatcher: Dispatcher::default(),
+            version: Self::VERSION,
         }
     }
+
+    /// Limit how many requests are handled at the same time, see
+    /// [`Dispatcher`].
+    #[must_use]
+    pub fn with_dispatcher(mut self, dispatcher: Dispatcher) -> Self {
+        self.dispatcher = dispatcher;
+        self
+    }
 }
 
 #[zbus::interface(name = "org.freedesktop.impl.portal.AppChooser")]
 impl AppChooserInterface {
     #[zbus(property(emits_changed_signal = "const"), name = "version")]
     fn version(&self) -> u32 {
-        2
+        self.version
     }
 
     #[dbus_interface(out_args("response", "results"))]
@@ -120,18 +163,26 @@ impl AppChooserInterface {
         app_id: MaybeAppID,
         parent_window: MaybeWindowIdentifier,
         choices: Vec<AppID>,
-        options: ChooserOptions,
+        mut options: ChooserOptions,
     ) -> Result<Response<Choice>, PortalError> {
         let imp = Arc::clone(&self.imp);
+        options.strip_newer(self.version);
 
         Request::spawn(
             "AppChooser::ChooseApplication",
             &self.cnx,
-            handle,
+            &self.dispatcher,
+            handle.clone(),
             Arc::clone(&self.imp),
             async move {
-                imp.choose_application(app_id.inner(), parent_window.inner(), choices, options)
-                    .await
+                imp.choose_application(
+                    handle.into(),
+                    app_id.inner(),
+                    parent_window.inner(),
+                    choices,
+                    options,
+                )
+                .await
             },
         )
         .await
@@ -146,7 +197,9 @@ impl AppChooserInterface {
         #[cfg(feature = "tracing")]
         tracing::debug!("AppChooser::UpdateChoices");
 
-        let iface_ref = server.interface::<_, Request>(handle).await?;
+        let iface_ref = server.interface::<_, Request>(&handle).await.map_err(|_| {
+            PortalError::NotFound(format!("No active request at {}", handle.as_str()))
+        })?;
         let response = self.imp.update_choices(iface_ref, choices).await;
 
         #[cfg(feature = "tracing")]
@@ -154,3 +207,206 @@ impl AppChooserInterface {
         response
     }
 }
+
+/// A client of an `org.freedesktop.impl.portal.AppChooser` backend.
+///
+/// The app chooser dialog stays open while the choices it lists change, e.g.
+/// when an application gets installed. This pushes the refreshed list of
+/// choices to the dialog opened for the request at `handle`.
+///
+/// ```rust,no_run
+/// use std::str::FromStr;
+///
+/// use ashpd::{
+///     backend::{app_chooser::AppChooserProxy, request::RequestHandle},
+///     zvariant::OwnedObjectPath,
+///     AppID,
+/// };
+///
+/// async fn run(handle: RequestHandle) -> ashpd::Result<()> {
+///     let connection = zbus::Connection::session().await?;
+///     let proxy = AppChooserProxy::new_with_connection(
+///         connection,
+///         "org.freedesktop.impl.portal.desktop.gnome",
+///     )
+///     .await?;
+///     proxy
+///         .update_choices(&handle, &[AppID::from_str("org.gnome.Epiphany")?])
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AppChooserProxy<'a>(Proxy<'a>);
+
+impl<'a> AppChooserProxy<'a> {
+    /// Create a new instance of [`AppChooserProxy`] for the backend owning
+    /// `destination`.
+    pub async fn new_with_connection<D>(
+        connection: zbus::Connection,
+        destination: D,
+    ) -> Result<AppChooserProxy<'a>, Error>
+    where
+        D: TryInto<BusName<'a>>,
+        D::Error: Into<zbus::Error>,
+    {
+        let proxy = Proxy::new_with_connection(
+            connection,
+            "org.freedesktop.impl.portal.AppChooser",
+            DESKTOP_PATH,
+            destination,
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
+    /// Replace the choices of the dialog opened for the request at `handle`.
+    ///
+    /// # Arguments
+    ///
+    /// * `handle` - The handle of the `ChooseApplication` request.
+    /// * `choices` - The refreshed list of choices.
+    ///
+    /// # Errors
+    ///
+    /// [`PortalError::InvalidArgument`] if `handle` is not the path of a
+    /// request, [`PortalError::NotFound`] if the request is not active
+    /// anymore, e.g. the dialog has been closed.
+    ///
+    /// # Specifications
+    ///
+    /// See also [`UpdateChoices`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.impl.portal.AppChooser.html#org-freedesktop-impl-portal-appchooser-updatechoices).
+    #[doc(alias = "UpdateChoices")]
+    pub async fn update_choices(
+        &self,
+        handle: &RequestHandle,
+        choices: &[AppID],
+    ) -> Result<(), Error> {
+        if handle.token().is_none() {
+            return Err(Error::Portal(PortalError::InvalidArgument(format!(
+                "{} is not a request handle",
+                handle.path().as_str()
+            ))));
+        }
+        self.0
+            .call::<()>("UpdateChoices", &(handle.path(), choices))
+            .await
+    }
+}
+
+impl<'a> std::ops::Deref for AppChooserProxy<'a> {
+    type Target = zbus::Proxy<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use super::*;
+    use crate::{backend::Result, helpers::serve_p2p};
+
+    struct Chooser {
+        started: std::sync::Mutex<Option<tokio::sync::oneshot::Sender<()>>>,
+        release: Arc<tokio::sync::Notify>,
+        choices: Arc<std::sync::Mutex<Vec<AppID>>>,
+    }
+
+    #[async_trait]
+    impl RequestImpl for Chooser {
+        async fn close(&self, _handle: RequestHandle) {}
+    }
+
+    #[async_trait]
+    impl AppChooserImpl for Chooser {
+        async fn choose_application(
+            &self,
+            _handle: RequestHandle,
+            _app_id: Option<AppID>,
+            _window_identifier: Option<WindowIdentifierType>,
+            choices: Vec<AppID>,
+            _options: ChooserOptions,
+        ) -> Result<Choice> {
+            if let Some(started) = self.started.lock().unwrap().take() {
+                started.send(()).unwrap();
+            }
+            self.release.notified().await;
+            Ok(Choice::new(choices[0].clone()))
+        }
+
+        async fn update_choices(
+            &self,
+            _request: InterfaceRef<Request>,
+            choices: Vec<AppID>,
+        ) -> Result<()> {
+            *self.choices.lock().unwrap() = choices;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn update_choices() {
+        let (started, started_rx) = tokio::sync::oneshot::channel();
+        let release = Arc::new(tokio::sync::Notify::new());
+        let updated = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let chooser = Chooser {
+            started: std::sync::Mutex::new(Some(started)),
+            release: Arc::clone(&release),
+            choices: Arc::clone(&updated),
+        };
+        let (_backend, chooser_proxy) =
+            serve_p2p(|cnx| AppChooserInterface::new(chooser, cnx)).await;
+        let client = chooser_proxy.connection().clone();
+
+        let handle = RequestHandle::from(
+            OwnedObjectPath::try_from("/org/freedesktop/portal/desktop/request/0/chooser").unwrap(),
+        );
+        let choices: Vec<AppID> = vec!["org.example.A".parse().unwrap()];
+        let choose = {
+            let client = client.clone();
+            let handle = handle.clone();
+            let choices = choices.clone();
+            tokio::spawn(async move {
+                client
+                    .call_method(
+                        Some("org.example.Portal"),
+                        DESKTOP_PATH,
+                        Some("org.freedesktop.impl.portal.AppChooser"),
+                        "ChooseApplication",
+                        &(
+                            handle.path(),
+                            "",
+                            "",
+                            choices,
+                            std::collections::HashMap::<&str, OwnedValue>::new(),
+                        ),
+                    )
+                    .await
+                    .unwrap();
+            })
+        };
+        started_rx.await.unwrap();
+
+        let proxy = AppChooserProxy::new_with_connection(client, "org.example.Portal")
+            .await
+            .unwrap();
+        let refreshed: Vec<AppID> = vec!["org.example.B".parse().unwrap()];
+        proxy.update_choices(&handle, &refreshed).await.unwrap();
+        assert_eq!(*updated.lock().unwrap(), refreshed);
+
+        release.notify_one();
+        choose.await.unwrap();
+        assert!(matches!(
+            proxy.update_choices(&handle, &choices).await,
+            Err(crate::Error::Portal(PortalError::NotFound(_)))
+        ));
+
+        let not_a_request =
+            RequestHandle::from(OwnedObjectPath::try_from("/org/example/chooser").unwrap());
+        assert!(matches!(
+            proxy.update_choices(&not_a_request, &choices).await,
+            Err(crate::Error::Portal(PortalError::InvalidArgument(_)))
+        ));
+    }
+}
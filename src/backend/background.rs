@@ -5,7 +5,7 @@ use enumflags2::{bitflags, BitFlags};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 
 use crate::{
-    backend::request::{Request, RequestImpl},
+    backend::request::{Dispatcher, Request, RequestHandle, RequestImpl},
     desktop::Response,
     zbus::SignalContext,
     zvariant::{OwnedObjectPath, SerializeDict, Type},
@@ -20,6 +20,8 @@ pub enum Activity {
     AllowInstance = 2,
 }
 
+crate::helpers::impl_value_conversions!(Activity as u32 { Forbid, Allow, AllowInstance });
+
 #[derive(Debug, SerializeDict, Type)]
 #[zvariant(signature = "dict")]
 pub struct Background {
@@ -40,6 +42,8 @@ pub enum AppState {
     Active = 2,
 }
 
+crate::helpers::impl_value_conversions!(AppState as u32 { Background, Running, Active });
+
 #[bitflags]
 #[derive(Deserialize_repr, PartialEq, Eq, Copy, Clone, Debug, Type)]
 #[repr(u32)]
@@ -47,12 +51,18 @@ pub enum AutoStartFlags {
     DBusActivation = 1,
 }
 
+crate::helpers::impl_value_conversions!(AutoStartFlags as u32 { DBusActivation });
+
 #[async_trait]
 pub trait BackgroundImpl: RequestImpl {
     async fn get_app_state(&self) -> Result<HashMap<AppID, AppState>, PortalError>;
 
-    async fn notify_background(&self, app_id: AppID, name: &str)
-        -> Result<Background, PortalError>;
+    async fn notify_background(
+        &self,
+        handle: RequestHandle,
+        app_id: AppID,
+        name: &str,
+    ) -> Result<Background, PortalError>;
 
     async fn enable_autostart(
         &self,
@@ -66,16 +76,30 @@ pub trait BackgroundImpl: RequestImpl {
 pub struct BackgroundInterface {
     imp: Arc<dyn BackgroundImpl>,
     cnx: zbus::Connection,
+    dispatcher: Dispatcher,
+    version: u32,
 }
 
+impl_version!(BackgroundInterface, 2);
+
 impl BackgroundInterface {
     pub fn new(imp: impl BackgroundImpl + 'static, cnx: zbus::Connection) -> Self {
         Self {
             imp: Arc::new(imp),
             cnx,
+            dispatcher: Dispatcher::default(),
+            version: Self::VERSION,
         }
     }
 
+    /// Limit how many requests are handled at the same time, see
+    /// [`Dispatcher`].
+    #[must_use]
+    pub fn with_dispatcher(mut self, dispatcher: Dispatcher) -> Self {
+        self.dispatcher = dispatcher;
+        self
+    }
+
     pub async fn changed(&self) -> zbus::Result<()> {
         let object_server = self.cnx.object_server();
         let iface_ref = object_server
@@ -89,7 +113,7 @@ impl BackgroundInterface {
 impl BackgroundInterface {
     #[zbus(property(emits_changed_signal = "const"), name = "version")]
     fn version(&self) -> u32 {
-        2
+        self.version
     }
 
     #[dbus_interface(out_args("apps"))]
@@ -116,9 +140,10 @@ impl BackgroundInterface {
         Request::spawn(
             "Background::NotifyBackground",
             &self.cnx,
-            handle,
+            &self.dispatcher,
+            handle.clone(),
             Arc::clone(&self.imp),
-            async move { imp.notify_background(app_id, &name).await },
+            async move { imp.notify_background(handle.into(), app_id, &name).await },
         )
         .await
     }
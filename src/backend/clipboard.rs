@@ -0,0 +1,279 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+
+use crate::{
+    backend::{request::RequestHandle, Result},
+    proxy::DESKTOP_PATH,
+    zbus::SignalContext,
+    zvariant::{ObjectPath, OwnedFd, OwnedObjectPath, OwnedValue, SerializeDict, Type},
+    MimeType,
+};
+
+/// The mime types all designating UTF-8 plain text, as offered by the
+/// different toolkits and X11 clients.
+const PLAIN_TEXT_MIME_TYPES: &[&str] = &[
+    "text/plain;charset=utf-8",
+    "text/plain",
+    "UTF8_STRING",
+    "STRING",
+    "TEXT",
+];
+
+#[derive(Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+#[non_exhaustive]
+pub struct SetSelectionOptions {
+    mime_types: Option<Vec<String>>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(SetSelectionOptions {
+    mime_types => "mime_types",
+});
+
+impl SetSelectionOptions {
+    /// The offered mime types, or X11 targets like `UTF8_STRING`.
+    pub fn mime_types(&self) -> &[String] {
+        self.mime_types.as_deref().unwrap_or_default()
+    }
+
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+}
+
+#[derive(SerializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+pub struct SelectionOwnerChanged {
+    mime_types: Option<Vec<String>>,
+    session_is_owner: Option<bool>,
+}
+
+impl SelectionOwnerChanged {
+    #[must_use]
+    pub fn mime_types(mut self, mime_types: impl IntoIterator<Item = impl ToString>) -> Self {
+        self.mime_types = Some(mime_types.into_iter().map(|m| m.to_string()).collect());
+        self
+    }
+
+    #[must_use]
+    pub fn session_is_owner(mut self, session_is_owner: impl Into<Option<bool>>) -> Self {
+        self.session_is_owner = session_is_owner.into();
+        self
+    }
+}
+
+/// Pick the first of the `accepted` mime types the selection is `offered`
+/// in, returning the offered one.
+///
+/// The different spellings of UTF-8 plain text, e.g. `UTF8_STRING` and
+/// `text/plain;charset=utf-8`, are considered equivalent, and accepted mime
+/// types like `image/*` match any offered subtype.
+pub fn negotiate_mime_type<'m>(offered: &'m [String], accepted: &[&str]) -> Option<&'m str> {
+    let is_plain_text = |mime_type: &str| {
+        PLAIN_TEXT_MIME_TYPES
+            .iter()
+            .any(|m| m.eq_ignore_ascii_case(mime_type))
+    };
+    let matches = |accepted: &str, offered: &str| match (
+        accepted.parse::<MimeType>(),
+        offered.parse::<MimeType>(),
+    ) {
+        (Ok(accepted), Ok(offered)) => !accepted.contains(';') && accepted.matches(&offered),
+        _ => false,
+    };
+    accepted.iter().find_map(|accepted| {
+        offered
+            .iter()
+            .find(|offered| {
+                offered.eq_ignore_ascii_case(accepted)
+                    || matches(accepted, offered)
+                    || (is_plain_text(offered) && is_plain_text(accepted))
+            })
+            .map(String::as_str)
+    })
+}
+
+#[async_trait]
+pub trait ClipboardImpl: Send + Sync {
+    async fn request_clipboard(&self, session: RequestHandle) -> Result<()>;
+
+    async fn set_selection(
+        &self,
+        session: RequestHandle,
+        options: SetSelectionOptions,
+    ) -> Result<()>;
+
+    /// Returns the file descriptor the session writes the selection, it was
+    /// asked through a `SelectionTransfer`, to.
+    async fn selection_write(&self, session: RequestHandle, serial: u32) -> Result<OwnedFd>;
+
+    async fn selection_write_done(
+        &self,
+        session: RequestHandle,
+        serial: u32,
+        success: bool,
+    ) -> Result<()>;
+
+    /// Returns the file descriptor the session reads the current selection
+    /// from.
+    async fn selection_read(&self, session: RequestHandle, mime_type: &str) -> Result<OwnedFd>;
+}
+
+pub struct ClipboardInterface {
+    imp: Arc<dyn ClipboardImpl>,
+    cnx: zbus::Connection,
+    version: u32,
+}
+
+impl_version!(ClipboardInterface, 1);
+
+impl ClipboardInterface {
+    pub fn new(imp: impl ClipboardImpl + 'static, cnx: zbus::Connection) -> Self {
+        Self {
+            imp: Arc::new(imp),
+            cnx,
+            version: Self::VERSION,
+        }
+    }
+
+    /// Notify `session` that the selection changed.
+    pub async fn selection_owner_changed(
+        &self,
+        session: &RequestHandle,
+        options: SelectionOwnerChanged,
+    ) -> zbus::Result<()> {
+        let object_server = self.cnx.object_server();
+        let iface_ref = object_server.interface::<_, Self>(DESKTOP_PATH).await?;
+        Self::emit_selection_owner_changed(iface_ref.signal_context(), session.path(), options)
+            .await
+    }
+
+    /// Ask `session`, the owner of the selection, to transfer it in
+    /// `mime_type`. The session replies with `SelectionWrite` using the same
+    /// `serial`.
+    pub async fn selection_transfer(
+        &self,
+        session: &RequestHandle,
+        mime_type: &str,
+        serial: u32,
+    ) -> zbus::Result<()> {
+        let object_server = self.cnx.object_server();
+        let iface_ref = object_server.interface::<_, Self>(DESKTOP_PATH).await?;
+        Self::emit_selection_transfer(
+            iface_ref.signal_context(),
+            session.path(),
+            mime_type,
+            serial,
+        )
+        .await
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.impl.portal.Clipboard")]
+impl ClipboardInterface {
+    #[zbus(property(emits_changed_signal = "const"), name = "version")]
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    async fn request_clipboard(
+        &self,
+        session_handle: OwnedObjectPath,
+        _options: HashMap<String, OwnedValue>,
+    ) -> Result<()> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Clipboard::RequestClipboard");
+
+        self.imp.request_clipboard(session_handle.into()).await
+    }
+
+    async fn set_selection(
+        &self,
+        session_handle: OwnedObjectPath,
+        options: SetSelectionOptions,
+    ) -> Result<()> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Clipboard::SetSelection");
+
+        self.imp.set_selection(session_handle.into(), options).await
+    }
+
+    #[dbus_interface(out_args("fd"))]
+    async fn selection_write(
+        &self,
+        session_handle: OwnedObjectPath,
+        serial: u32,
+    ) -> Result<OwnedFd> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Clipboard::SelectionWrite");
+
+        self.imp
+            .selection_write(session_handle.into(), serial)
+            .await
+    }
+
+    async fn selection_write_done(
+        &self,
+        session_handle: OwnedObjectPath,
+        serial: u32,
+        success: bool,
+    ) -> Result<()> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Clipboard::SelectionWriteDone");
+
+        self.imp
+            .selection_write_done(session_handle.into(), serial, success)
+            .await
+    }
+
+    #[dbus_interface(out_args("fd"))]
+    async fn selection_read(
+        &self,
+        session_handle: OwnedObjectPath,
+        mime_type: &str,
+    ) -> Result<OwnedFd> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Clipboard::SelectionRead");
+
+        self.imp
+            .selection_read(session_handle.into(), mime_type)
+            .await
+    }
+
+    #[zbus(signal, name = "SelectionOwnerChanged")]
+    async fn emit_selection_owner_changed(
+        signal_ctxt: &SignalContext<'_>,
+        session_handle: ObjectPath<'_>,
+        options: SelectionOwnerChanged,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal, name = "SelectionTransfer")]
+    async fn emit_selection_transfer(
+        signal_ctxt: &SignalContext<'_>,
+        session_handle: ObjectPath<'_>,
+        mime_type: &str,
+        serial: u32,
+    ) -> zbus::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mime_type_negotiation() {
+        let offered = ["image/png".to_owned(), "UTF8_STRING".to_owned()];
+        assert_eq!(
+            negotiate_mime_type(&offered, &["text/plain;charset=utf-8"]),
+            Some("UTF8_STRING")
+        );
+        assert_eq!(
+            negotiate_mime_type(&offered, &["image/jpeg", "image/*"]),
+            Some("image/png")
+        );
+        assert_eq!(negotiate_mime_type(&offered, &["text/html"]), None);
+    }
+}
@@ -0,0 +1,454 @@
+//! Enumerate the installed applications, as described by their desktop
+//! entries, to present them in an
+//! [`AppChooserImpl`](crate::backend::app_chooser::AppChooserImpl).
+//!
+//! The `.desktop` files are looked up in the `applications` directory of the
+//! XDG data directories, and the associations of the `mimeapps.list` files in
+//! the XDG config and data directories are applied, following the
+//! [Desktop Entry](https://specifications.freedesktop.org/desktop-entry-spec/latest/)
+//! and [Association between MIME types and applications](https://specifications.freedesktop.org/mime-apps-spec/latest/)
+//! specifications. The desktop specific `<desktop>-mimeapps.list` files, the
+//! localized keys and the mime types subclasses are not taken into account.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use ashpd::backend::desktop_entries::DesktopEntries;
+//!
+//! let entries = DesktopEntries::from_env();
+//! for entry in entries.for_content_type(&"text/plain".parse().unwrap()) {
+//!     println!(
+//!         "{}: {} (default: {})",
+//!         entry.id(),
+//!         entry.name(),
+//!         entry.is_default()
+//!     );
+//! }
+//! ```
+
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{AppID, MimeType};
+
+/// An application described by a desktop entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DesktopEntry {
+    id: String,
+    path: PathBuf,
+    name: String,
+    generic_name: Option<String>,
+    comment: Option<String>,
+    icon: Option<String>,
+    exec: Option<String>,
+    mime_types: Vec<MimeType>,
+    no_display: bool,
+    is_default: bool,
+}
+
+impl DesktopEntry {
+    /// The desktop file id, e.g. `org.gnome.TextEditor` for
+    /// `org.gnome.TextEditor.desktop`.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The desktop file id as an application id, the form of the choices
+    /// passed to the chooser. `None` for the ids not following the D-Bus
+    /// naming, e.g. `firefox`.
+    pub fn app_id(&self) -> Option<AppID> {
+        self.id.parse().ok()
+    }
+
+    /// The path of the `.desktop` file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The `Name` key.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The `GenericName` key, e.g. `Text Editor`.
+    pub fn generic_name(&self) -> Option<&str> {
+        self.generic_name.as_deref()
+    }
+
+    /// The `Comment` key.
+    pub fn comment(&self) -> Option<&str> {
+        self.comment.as_deref()
+    }
+
+    /// The `Icon` key, either an icon name or an absolute path.
+    pub fn icon(&self) -> Option<&str> {
+        self.icon.as_deref()
+    }
+
+    /// The `Exec` key, with its field codes.
+    pub fn exec(&self) -> Option<&str> {
+        self.exec.as_deref()
+    }
+
+    /// The mime types listed by the `MimeType` key.
+    pub fn mime_types(&self) -> &[MimeType] {
+        &self.mime_types
+    }
+
+    /// Whether the application is hidden from the menus, it might still be
+    /// offered to open files.
+    pub fn no_display(&self) -> bool {
+        self.no_display
+    }
+
+    /// Whether the application is the default one for the content type it
+    /// was looked up for.
+    pub fn is_default(&self) -> bool {
+        self.is_default
+    }
+}
+
+/// The `mimeapps.list` associations of a content type.
+#[derive(Debug, Default)]
+struct Associations {
+    defaults: Vec<String>,
+    added: Vec<String>,
+    removed: Vec<String>,
+}
+
+/// The installed desktop entries.
+#[derive(Debug, Clone)]
+pub struct DesktopEntries {
+    data_dirs: Vec<PathBuf>,
+    config_dirs: Vec<PathBuf>,
+}
+
+impl DesktopEntries {
+    /// Look the desktop entries up in the XDG directories of the environment,
+    /// `$XDG_DATA_HOME`, `$XDG_DATA_DIRS`, `$XDG_CONFIG_HOME` and
+    /// `$XDG_CONFIG_DIRS`.
+    pub fn from_env() -> Self {
+        let home = std::env::var_os("HOME").map(PathBuf::from);
+        let mut data_dirs = Vec::new();
+        data_dirs.extend(xdg_home("XDG_DATA_HOME", home.as_deref(), ".local/share"));
+        data_dirs.extend(xdg_dirs("XDG_DATA_DIRS", "/usr/local/share:/usr/share"));
+        let mut config_dirs = Vec::new();
+        config_dirs.extend(xdg_home("XDG_CONFIG_HOME", home.as_deref(), ".config"));
+        config_dirs.extend(xdg_dirs("XDG_CONFIG_DIRS", "/etc/xdg"));
+        Self::with_dirs(data_dirs, config_dirs)
+    }
+
+    /// Look the desktop entries up in the `applications` directory of
+    /// `data_dirs` and the `mimeapps.list` files of `config_dirs`, both in
+    /// decreasing order of precedence.
+    pub fn with_dirs(
+        data_dirs: impl IntoIterator<Item = impl Into<PathBuf>>,
+        config_dirs: impl IntoIterator<Item = impl Into<PathBuf>>,
+    ) -> Self {
+        Self {
+            data_dirs: data_dirs.into_iter().map(Into::into).collect(),
+            config_dirs: config_dirs.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// All the applications, sorted by desktop file id. The hidden entries
+    /// and the ones not of the `Application` type are skipped.
+    pub fn all(&self) -> Vec<DesktopEntry> {
+        let mut entries = HashMap::new();
+        for dir in &self.data_dirs {
+            collect_entries(&dir.join("applications"), "", &mut entries);
+        }
+        let mut entries = entries.into_values().flatten().collect::<Vec<_>>();
+        entries.sort_by(|a, b| a.id.cmp(&b.id));
+        entries
+    }
+
+    /// The application of the desktop file id `id`, if installed.
+    pub fn get(&self, id: &str) -> Option<DesktopEntry> {
+        self.all().into_iter().find(|entry| entry.id == id)
+    }
+
+    /// The applications handling `content_type`, the default ones first,
+    /// then the ones added by `mimeapps.list`, then the ones listing a
+    /// matching mime type in their entry.
+    pub fn for_content_type(&self, content_type: &MimeType) -> Vec<DesktopEntry> {
+        let associations = self.associations(content_type);
+        let mut candidates = self.all();
+        let mut handlers = Vec::new();
+        for id in associations.defaults.iter().chain(&associations.added) {
+            if let Some(position) = candidates.iter().position(|entry| &entry.id == id) {
+                let mut entry = candidates.remove(position);
+                entry.is_default = associations.defaults.contains(id);
+                handlers.push(entry);
+            }
+        }
+        handlers.extend(candidates.into_iter().filter(|entry| {
+            !associations.removed.contains(&entry.id)
+                && entry
+                    .mime_types
+                    .iter()
+                    .any(|mime_type| mime_type.matches(content_type))
+        }));
+        handlers
+    }
+
+    fn associations(&self, content_type: &MimeType) -> Associations {
+        let mut associations = Associations::default();
+        let lists = self
+            .config_dirs
+            .iter()
+            .map(|dir| dir.join("mimeapps.list"))
+            .chain(
+                self.data_dirs
+                    .iter()
+                    .map(|dir| dir.join("applications").join("mimeapps.list")),
+            );
+        for path in lists {
+            let Ok(contents) = fs::read_to_string(path) else {
+                continue;
+            };
+            let groups = parse_key_file(&contents);
+            let ids = |group: &str| {
+                groups
+                    .get(group)
+                    .and_then(|keys| {
+                        keys.iter().find_map(|(key, value)| {
+                            let mime_type = key.parse::<MimeType>().ok()?;
+                            (mime_type.essence() == content_type.essence()).then_some(value)
+                        })
+                    })
+                    .map(|value| split_list(value).collect::<Vec<_>>())
+                    .unwrap_or_default()
+            };
+            // The removed associations of a file apply to the ones of the
+            // files with a lower precedence only.
+            let removed = ids("Removed Associations");
+            for id in ids("Default Applications") {
+                if !associations.removed.contains(&id) && !associations.defaults.contains(&id) {
+                    associations.defaults.push(id);
+                }
+            }
+            for id in ids("Added Associations") {
+                if !associations.removed.contains(&id) && !associations.added.contains(&id) {
+                    associations.added.push(id);
+                }
+            }
+            associations.removed.extend(removed);
+        }
+        associations
+    }
+}
+
+fn xdg_home(var: &str, home: Option<&Path>, fallback: &str) -> Option<PathBuf> {
+    match std::env::var_os(var) {
+        Some(dir) if !dir.is_empty() => Some(PathBuf::from(dir)),
+        _ => home.map(|home| home.join(fallback)),
+    }
+}
+
+fn xdg_dirs(var: &str, fallback: &str) -> Vec<PathBuf> {
+    let dirs = std::env::var_os(var)
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| OsString::from(fallback));
+    std::env::split_paths(&dirs)
+        .filter(|dir| dir.is_absolute())
+        .collect()
+}
+
+/// Collect the entries of `dir`, the ones of its subdirectories having their
+/// id prefixed by the path of the subdirectory, e.g. `kde-konsole` for
+/// `kde/konsole.desktop`. The entries already found in a directory with a
+/// higher precedence are kept, including the hidden ones stored as `None` to
+/// shadow the others.
+fn collect_entries(dir: &Path, prefix: &str, entries: &mut HashMap<String, Option<DesktopEntry>>) {
+    let Ok(read_dir) = fs::read_dir(dir) else {
+        return;
+    };
+    for dir_entry in read_dir.flatten() {
+        let path = dir_entry.path();
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if path.is_dir() {
+            collect_entries(&path, &format!("{prefix}{file_name}-"), entries);
+        } else if let Some(stem) = file_name.strip_suffix(".desktop") {
+            let id = format!("{prefix}{stem}");
+            if entries.contains_key(&id) {
+                continue;
+            }
+            let entry = fs::read_to_string(&path)
+                .ok()
+                .and_then(|contents| parse_desktop_entry(&id, &path, &contents));
+            entries.insert(id, entry);
+        }
+    }
+}
+
+fn parse_desktop_entry(id: &str, path: &Path, contents: &str) -> Option<DesktopEntry> {
+    let groups = parse_key_file(contents);
+    let keys = groups.get("Desktop Entry")?;
+    let get = |key: &str| {
+        keys.iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| unescape(value))
+    };
+    let is_true = |key: &str| get(key).is_some_and(|value| value == "true");
+    if get("Type").as_deref() != Some("Application") || is_true("Hidden") {
+        return None;
+    }
+    Some(DesktopEntry {
+        id: id.to_owned(),
+        path: path.to_owned(),
+        name: get("Name")?,
+        generic_name: get("GenericName"),
+        comment: get("Comment"),
+        icon: get("Icon"),
+        exec: get("Exec"),
+        mime_types: get("MimeType")
+            .map(|value| {
+                split_list(&value)
+                    .filter_map(|mime_type| mime_type.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default(),
+        no_display: is_true("NoDisplay"),
+        is_default: false,
+    })
+}
+
+/// Parse the groups of a key file, keeping the order of the keys.
+fn parse_key_file(contents: &str) -> HashMap<String, Vec<(String, String)>> {
+    let mut groups = HashMap::<String, Vec<_>>::new();
+    let mut group = None;
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            group = Some(groups.entry(name.to_owned()).or_default());
+        } else if let (Some(keys), Some((key, value))) = (group.as_mut(), line.split_once('=')) {
+            keys.push((key.trim().to_owned(), value.trim().to_owned()));
+        }
+    }
+    groups
+}
+
+/// Split a `;` separated list, ignoring the empty items.
+fn split_list(value: &str) -> impl Iterator<Item = String> + '_ {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| item.strip_suffix(".desktop").unwrap_or(item).to_owned())
+}
+
+fn unescape(value: &str) -> String {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('s') => unescaped.push(' '),
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('r') => unescaped.push('\r'),
+            Some(c) => unescaped.push(c),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_type_handlers() {
+        let root =
+            std::env::temp_dir().join(format!("ashpd-desktop-entries-{}", std::process::id()));
+        let (home, system, config) = (root.join("home"), root.join("system"), root.join("config"));
+        let write = |path: PathBuf, contents: &str| {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        };
+        let entry = |name: &str, mime_types: &str| {
+            format!("[Desktop Entry]\nType=Application\nName={name}\nExec={name} %U\nMimeType={mime_types}\n")
+        };
+        write(
+            system.join("applications/org.example.Viewer.desktop"),
+            &entry("Viewer", "image/png;image/jpeg;"),
+        );
+        write(
+            system.join("applications/org.example.Editor.desktop"),
+            &entry("Editor", "image/*;"),
+        );
+        write(
+            system.join("applications/kde/paint.desktop"),
+            &entry("Paint", "image/png;"),
+        );
+        write(
+            system.join("applications/org.example.Hidden.desktop"),
+            &entry("Hidden", "image/png;"),
+        );
+        write(
+            home.join("applications/org.example.Hidden.desktop"),
+            "[Desktop Entry]\nType=Application\nName=Hidden\nHidden=true\n",
+        );
+        write(
+            system.join("applications/org.example.Notes.desktop"),
+            &entry("Notes\\sApp", "text/plain;"),
+        );
+        write(
+            config.join("mimeapps.list"),
+            "[Default Applications]\nimage/png=org.example.Editor.desktop;\n\n\
+             [Removed Associations]\nimage/png=kde-paint.desktop;\n",
+        );
+        write(
+            system.join("applications/mimeapps.list"),
+            "[Added Associations]\nimage/png=org.example.Notes.desktop;kde-paint.desktop;\n",
+        );
+
+        let entries = DesktopEntries::with_dirs([&home, &system], [&config]);
+        assert_eq!(entries.all().len(), 4);
+        assert_eq!(
+            entries.get("org.example.Notes").unwrap().name(),
+            "Notes App"
+        );
+        assert!(entries.get("org.example.Hidden").is_none());
+
+        let handlers = entries.for_content_type(&"image/png".parse().unwrap());
+        let ids = handlers.iter().map(DesktopEntry::id).collect::<Vec<_>>();
+        assert_eq!(
+            ids,
+            [
+                "org.example.Editor",
+                "org.example.Notes",
+                "org.example.Viewer"
+            ]
+        );
+        assert!(handlers[0].is_default());
+        assert!(!handlers[1].is_default());
+        assert_eq!(handlers[2].exec(), Some("Viewer %U"));
+        assert_eq!(
+            handlers[2].app_id().as_ref().map(AsRef::as_ref),
+            Some("org.example.Viewer")
+        );
+
+        let ids = entries
+            .for_content_type(&"image/jpeg".parse().unwrap())
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["org.example.Editor", "org.example.Viewer"]);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,19 +1,21 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
 use async_trait::async_trait;
 
 use crate::{
     backend::{
-        request::{Request, RequestImpl},
+        request::{Dispatcher, Request, RequestHandle, RequestImpl},
         MaybeAppID, MaybeWindowIdentifier, Result,
     },
     desktop::request::Response,
-    zvariant::{self, DeserializeDict, OwnedObjectPath},
+    helpers::Redacted,
+    zvariant::{self, OwnedObjectPath, OwnedValue},
     ActivationToken, AppID, WindowIdentifierType,
 };
 
-#[derive(DeserializeDict, zvariant::Type)]
+#[derive(zvariant::Type)]
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct Options {
     address: Option<String>,
     addresses: Option<Vec<String>>,
@@ -23,6 +25,34 @@ pub struct Options {
     body: Option<String>,
     attachments: Option<Vec<url::Url>>,
     activation_token: Option<ActivationToken>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(Options {
+    address => "address",
+    addresses => "addresses",
+    cc => "cc",
+    bcc => "bcc",
+    subject => "subject",
+    body => "body",
+    attachments => "attachments",
+    activation_token => "activation_token",
+});
+
+impl std::fmt::Debug for Options {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Options")
+            .field("address", &self.address.as_ref().map(Redacted))
+            .field("addresses", &self.addresses.as_ref().map(Redacted))
+            .field("cc", &self.cc.as_ref().map(Redacted))
+            .field("bcc", &self.bcc.as_ref().map(Redacted))
+            .field("subject", &self.subject.as_ref().map(Redacted))
+            .field("body", &self.body.as_ref().map(Redacted))
+            .field("attachments", &self.attachments.as_ref().map(Redacted))
+            .field("activation_token", &self.activation_token)
+            .field("extra", &self.extra)
+            .finish()
+    }
 }
 
 impl Options {
@@ -57,12 +87,31 @@ impl Options {
     pub fn activation_token(&self) -> Option<&ActivationToken> {
         self.activation_token.as_ref()
     }
+
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+
+    /// Drop the options introduced after `version` of the interface, which
+    /// the implementation didn't declare to support.
+    fn strip_newer(&mut self, version: u32) {
+        if version < 3 {
+            self.addresses = None;
+            self.cc = None;
+            self.bcc = None;
+        }
+        if version < 4 {
+            self.activation_token = None;
+        }
+    }
 }
 
 #[async_trait]
 pub trait EmailImpl: RequestImpl {
     async fn compose(
         &self,
+        handle: RequestHandle,
         app_id: Option<AppID>,
         window_identifier: Option<WindowIdentifierType>,
         options: Options,
@@ -72,22 +121,36 @@ pub trait EmailImpl: RequestImpl {
 pub struct EmailInterface {
     imp: Arc<dyn EmailImpl>,
     cnx: zbus::Connection,
+    dispatcher: Dispatcher,
+    version: u32,
 }
 
+impl_version!(EmailInterface, 4);
+
 impl EmailInterface {
     pub fn new(imp: impl EmailImpl + 'static, cnx: zbus::Connection) -> Self {
         Self {
             imp: Arc::new(imp),
             cnx,
+            dispatcher: Dispatcher::default(),
+            version: Self::VERSION,
         }
     }
+
+    /// Limit how many requests are handled at the same time, see
+    /// [`Dispatcher`].
+    #[must_use]
+    pub fn with_dispatcher(mut self, dispatcher: Dispatcher) -> Self {
+        self.dispatcher = dispatcher;
+        self
+    }
 }
 
 #[zbus::interface(name = "org.freedesktop.impl.portal.Email")]
 impl EmailInterface {
     #[dbus_interface(property(emits_changed_signal = "const"), name = "version")]
     fn version(&self) -> u32 {
-        4
+        self.version
     }
 
     #[dbus_interface(out_args("response", "results"))]
@@ -96,18 +159,25 @@ impl EmailInterface {
         handle: OwnedObjectPath,
         app_id: MaybeAppID,
         window_identifier: MaybeWindowIdentifier,
-        options: Options,
+        mut options: Options,
     ) -> Result<Response<()>> {
         let imp = Arc::clone(&self.imp);
+        options.strip_newer(self.version);
 
         Request::spawn(
             "Email::ComposeEmail",
             &self.cnx,
-            handle,
+            &self.dispatcher,
+            handle.clone(),
             Arc::clone(&self.imp),
             async move {
-                imp.compose(app_id.inner(), window_identifier.inner(), options)
-                    .await
+                imp.compose(
+                    handle.into(),
+                    app_id.inner(),
+                    window_identifier.inner(),
+                    options,
+                )
+                .await
             },
         )
         .await
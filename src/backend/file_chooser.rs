@@ -1,21 +1,25 @@
-use std::sync::Arc;
-
-use async_trait::async_trait;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use crate::{
     backend::{
-        request::{Request, RequestImpl},
+        request::{Dispatcher, Request, RequestHandle, RequestImpl},
         MaybeAppID, MaybeWindowIdentifier, Result,
     },
     desktop::{
         file_chooser::{Choice, FileFilter},
         request::Response,
     },
-    zvariant::{DeserializeDict, OwnedObjectPath, SerializeDict, Type},
-    AppID, FilePath, WindowIdentifierType,
+    helpers::Redacted,
+    zvariant::{OwnedObjectPath, OwnedValue, Type},
+    AppID, FilePath, PortalError, WindowIdentifierType,
 };
 
-#[derive(Debug, Type, SerializeDict, Default)]
+#[derive(Type, Default)]
 #[zvariant(signature = "dict")]
 pub struct SelectedFiles {
     uris: Vec<url::Url>,
@@ -24,6 +28,28 @@ pub struct SelectedFiles {
     current_filter: Option<FileFilter>,
     // Only relavant for OpenFile
     writable: Option<bool>,
+    current_folder: Option<FilePath>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(Serialize for SelectedFiles {
+    uris [required] => "uris",
+    choices => "choices",
+    current_filter => "current_filter",
+    writable => "writable",
+    current_folder => "current_folder",
+});
+
+impl std::fmt::Debug for SelectedFiles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SelectedFiles")
+            .field("uris", &Redacted(&self.uris))
+            .field("choices", &self.choices)
+            .field("current_filter", &self.current_filter)
+            .field("writable", &self.writable)
+            .field("current_folder", &Redacted(&self.current_folder))
+            .finish()
+    }
 }
 
 impl SelectedFiles {
@@ -48,13 +74,144 @@ impl SelectedFiles {
         self.writable = value.into();
         self
     }
+
+    /// Sets the folder the dialog was showing when it was closed, which
+    /// applications use to open the next dialog in it.
+    pub fn current_folder(mut self, value: impl Into<Option<FilePath>>) -> Self {
+        self.current_folder = value.into();
+        self
+    }
+
+    /// Sets the result `key`, unknown to this version of the crate, e.g. a
+    /// vendor extension like `x-gnome-...`. The results known to the crate
+    /// can't be overridden.
+    pub fn extra<'v>(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<zbus::zvariant::Value<'v>>,
+    ) -> Self {
+        crate::helpers::insert_extra(&mut self.extra, key, value);
+        self
+    }
+
+    /// Ensure the results of a `SaveFiles` call contain one URI per file that
+    /// was requested to be saved.
+    pub fn check_save_files(&self, options: &SaveFilesOptions) -> Result<()> {
+        self.check_uris_count(options.files().len())
+    }
+
+    /// Ensure the results of an `OpenFile` call are absolute `file://` URIs,
+    /// pointing to directories if the options requested so.
+    pub fn check_open_file(&self, options: &OpenFileOptions) -> Result<()> {
+        self.check_uris(options.directory().unwrap_or(false))
+    }
+
+    fn check_uris(&self, directory: bool) -> Result<()> {
+        for uri in &self.uris {
+            let path = match uri.scheme() {
+                "file" => uri.to_file_path().map_err(|_| {
+                    PortalError::InvalidArgument(format!("`{uri}` is not an absolute file URI"))
+                })?,
+                scheme => {
+                    return Err(PortalError::InvalidArgument(format!(
+                        "`{uri}` has the unsupported scheme `{scheme}`"
+                    )))
+                }
+            };
+            if directory && !path.is_dir() {
+                return Err(PortalError::InvalidArgument(format!(
+                    "`{uri}` is not a directory"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_uris_count(&self, expected: usize) -> Result<()> {
+        if self.uris.len() != expected {
+            return Err(PortalError::Failed(format!(
+                "SaveFiles returned {} URIs while {expected} files were requested",
+                self.uris.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// How [`dedup_filenames`] handles files that already exist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollisionStrategy {
+    /// Append a ` (n)` suffix before the extension until the name is free.
+    #[default]
+    Suffix,
+    /// Keep the names as is, the existing files get overwritten. Used once the
+    /// user confirmed it.
+    Overwrite,
+}
+
+// Extensions that are made of two parts, so that `a.tar.gz` becomes
+// `a (1).tar.gz` and not `a.tar (1).gz`.
+const COMPOUND_EXTENSIONS: &[&str] = &[
+    "tar.gz", "tar.bz2", "tar.xz", "tar.zst", "tar.lz", "tar.lzma", "tar.Z",
+];
+
+fn split_extension(name: &str) -> (&str, &str) {
+    let lowercase = name.to_ascii_lowercase();
+    for ext in COMPOUND_EXTENSIONS {
+        let suffix = format!(".{}", ext.to_ascii_lowercase());
+        if lowercase.len() > suffix.len() && lowercase.ends_with(&suffix) {
+            return name.split_at(name.len() - suffix.len());
+        }
+    }
+    // A leading dot marks a hidden file, not an extension
+    match name.rfind('.') {
+        Some(idx) if idx > 0 => name.split_at(idx),
+        _ => (name, ""),
+    }
+}
+
+/// Compute the paths the `files` should be saved to inside `base_dir`.
+///
+/// Only the file names of `files` are used. With
+/// [`CollisionStrategy::Suffix`], names colliding with an existing file or
+/// with another entry of `files` get a ` (n)` suffix, while keeping compound
+/// extensions like `.tar.gz` intact.
+pub fn dedup_filenames(
+    base_dir: impl AsRef<Path>,
+    files: &[impl AsRef<Path>],
+    strategy: CollisionStrategy,
+) -> Vec<PathBuf> {
+    let base_dir = base_dir.as_ref();
+    let mut taken = HashSet::new();
+    files
+        .iter()
+        .map(|file| {
+            let name = file
+                .as_ref()
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let mut path = base_dir.join(&name);
+            if strategy == CollisionStrategy::Suffix {
+                let (stem, ext) = split_extension(&name);
+                let mut n = 1;
+                while taken.contains(&path) || path.exists() {
+                    path = base_dir.join(format!("{stem} ({n}){ext}"));
+                    n += 1;
+                }
+            }
+            taken.insert(path.clone());
+            path
+        })
+        .collect()
 }
 // TODO: We should de-duplicate those types
 // but we will have to figure out how to handle handle_token
 // as if we set it to Option<T>, the Default would no longer
 // generate a random value, breaking some of the infrastructure we had
-#[derive(DeserializeDict, Type, Debug)]
+#[derive(Type)]
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct OpenFileOptions {
     accept_label: Option<String>,
     modal: Option<bool>,
@@ -64,6 +221,37 @@ pub struct OpenFileOptions {
     current_filter: Option<FileFilter>,
     choices: Option<Vec<Choice>>,
     current_folder: Option<FilePath>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(OpenFileOptions {
+    accept_label => "accept_label",
+    modal => "modal",
+    multiple => "multiple",
+    directory => "directory",
+    filters => "filters",
+    current_filter => "current_filter",
+    choices => "choices",
+    current_folder => "current_folder",
+});
+
+impl std::fmt::Debug for OpenFileOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenFileOptions")
+            .field("accept_label", &self.accept_label)
+            .field("modal", &self.modal)
+            .field("multiple", &self.multiple)
+            .field("directory", &self.directory)
+            .field("filters", &self.filters)
+            .field("current_filter", &self.current_filter)
+            .field("choices", &self.choices)
+            .field(
+                "current_folder",
+                &self.current_folder.as_ref().map(Redacted),
+            )
+            .field("extra", &self.extra)
+            .finish()
+    }
 }
 
 impl OpenFileOptions {
@@ -98,10 +286,27 @@ impl OpenFileOptions {
     pub fn current_folder(&self) -> Option<&FilePath> {
         self.current_folder.as_ref()
     }
+
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+
+    /// Drop the options introduced after `version` of the interface, which
+    /// the implementation didn't declare to support.
+    fn strip_newer(&mut self, version: u32) {
+        if version < 3 {
+            self.directory = None;
+        }
+        if version < 4 {
+            self.current_folder = None;
+        }
+    }
 }
 
-#[derive(DeserializeDict, Type, Debug)]
+#[derive(Type)]
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct SaveFileOptions {
     accept_label: Option<String>,
     modal: Option<bool>,
@@ -112,6 +317,39 @@ pub struct SaveFileOptions {
     current_name: Option<String>,
     current_folder: Option<FilePath>,
     current_file: Option<FilePath>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(SaveFileOptions {
+    accept_label => "accept_label",
+    modal => "modal",
+    multiple => "multiple",
+    filters => "filters",
+    current_filter => "current_filter",
+    choices => "choices",
+    current_name => "current_name",
+    current_folder => "current_folder",
+    current_file => "current_file",
+});
+
+impl std::fmt::Debug for SaveFileOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SaveFileOptions")
+            .field("accept_label", &self.accept_label)
+            .field("modal", &self.modal)
+            .field("multiple", &self.multiple)
+            .field("filters", &self.filters)
+            .field("current_filter", &self.current_filter)
+            .field("choices", &self.choices)
+            .field("current_name", &self.current_name.as_ref().map(Redacted))
+            .field(
+                "current_folder",
+                &self.current_folder.as_ref().map(Redacted),
+            )
+            .field("current_file", &self.current_file.as_ref().map(Redacted))
+            .field("extra", &self.extra)
+            .finish()
+    }
 }
 
 impl SaveFileOptions {
@@ -150,16 +388,47 @@ impl SaveFileOptions {
     pub fn current_name(&self) -> Option<&str> {
         self.current_name.as_deref()
     }
+
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
-#[derive(DeserializeDict, Type, Debug)]
+#[derive(Type)]
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct SaveFilesOptions {
     accept_label: Option<String>,
     modal: Option<bool>,
     choices: Option<Vec<Choice>>,
     current_folder: Option<FilePath>,
     files: Option<Vec<FilePath>>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(SaveFilesOptions {
+    accept_label => "accept_label",
+    modal => "modal",
+    choices => "choices",
+    current_folder => "current_folder",
+    files => "files",
+});
+
+impl std::fmt::Debug for SaveFilesOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SaveFilesOptions")
+            .field("accept_label", &self.accept_label)
+            .field("modal", &self.modal)
+            .field("choices", &self.choices)
+            .field(
+                "current_folder",
+                &self.current_folder.as_ref().map(Redacted),
+            )
+            .field("files", &self.files.as_ref().map(Redacted))
+            .field("extra", &self.extra)
+            .finish()
+    }
 }
 
 impl SaveFilesOptions {
@@ -182,54 +451,75 @@ impl SaveFilesOptions {
     pub fn files(&self) -> &[FilePath] {
         self.files.as_deref().unwrap_or_default()
     }
+
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
-#[async_trait]
 pub trait FileChooserImpl: RequestImpl {
-    async fn open_file(
+    fn open_file(
         &self,
+        handle: RequestHandle,
         app_id: Option<AppID>,
         window_identifier: Option<WindowIdentifierType>,
         title: &str,
         options: OpenFileOptions,
-    ) -> Result<SelectedFiles>;
+    ) -> impl Future<Output = Result<SelectedFiles>> + Send;
 
-    async fn save_file(
+    fn save_file(
         &self,
+        handle: RequestHandle,
         app_id: Option<AppID>,
         window_identifier: Option<WindowIdentifierType>,
         title: &str,
         options: SaveFileOptions,
-    ) -> Result<SelectedFiles>;
+    ) -> impl Future<Output = Result<SelectedFiles>> + Send;
 
-    async fn save_files(
+    fn save_files(
         &self,
+        handle: RequestHandle,
         app_id: Option<AppID>,
         window_identifier: Option<WindowIdentifierType>,
         title: &str,
         options: SaveFilesOptions,
-    ) -> Result<SelectedFiles>;
+    ) -> impl Future<Output = Result<SelectedFiles>> + Send;
 }
 
-pub struct FileChooserInterface {
-    imp: Arc<dyn FileChooserImpl>,
+pub struct FileChooserInterface<T> {
+    imp: Arc<T>,
     cnx: zbus::Connection,
+    dispatcher: Dispatcher,
+    version: u32,
 }
 
-impl FileChooserInterface {
-    pub fn new(imp: impl FileChooserImpl + 'static, cnx: zbus::Connection) -> Self {
+impl_version!(FileChooserInterface<T: FileChooserImpl>, 4);
+
+impl<T: FileChooserImpl + 'static> FileChooserInterface<T> {
+    pub fn new(imp: T, cnx: zbus::Connection) -> Self {
         Self {
             imp: Arc::new(imp),
             cnx,
+            dispatcher: Dispatcher::default(),
+            version: Self::VERSION,
         }
     }
+
+    /// Limit how many requests are handled at the same time, see
+    /// [`Dispatcher`].
+    #[must_use]
+    pub fn with_dispatcher(mut self, dispatcher: Dispatcher) -> Self {
+        self.dispatcher = dispatcher;
+        self
+    }
 }
 
 #[zbus::interface(name = "org.freedesktop.impl.portal.FileChooser")]
-impl FileChooserInterface {
+impl<T: FileChooserImpl + 'static> FileChooserInterface<T> {
     #[zbus(property(emits_changed_signal = "const"), name = "version")]
     fn version(&self) -> u32 {
-        4
+        self.version
     }
 
     #[dbus_interface(out_args("response", "results"))]
@@ -239,18 +529,30 @@ impl FileChooserInterface {
         app_id: MaybeAppID,
         window_identifier: MaybeWindowIdentifier,
         title: String,
-        options: OpenFileOptions,
+        mut options: OpenFileOptions,
     ) -> Result<Response<SelectedFiles>> {
         let imp = Arc::clone(&self.imp);
+        options.strip_newer(self.version);
 
         Request::spawn(
             "FileChooser::OpenFile",
             &self.cnx,
-            handle,
+            &self.dispatcher,
+            handle.clone(),
             Arc::clone(&self.imp),
             async move {
-                imp.open_file(app_id.inner(), window_identifier.inner(), &title, options)
-                    .await
+                let directory = options.directory().unwrap_or(false);
+                let selected = imp
+                    .open_file(
+                        handle.into(),
+                        app_id.inner(),
+                        window_identifier.inner(),
+                        &title,
+                        options,
+                    )
+                    .await?;
+                selected.check_uris(directory)?;
+                Ok(selected)
             },
         )
         .await
@@ -270,11 +572,21 @@ impl FileChooserInterface {
         Request::spawn(
             "FileChooser::SaveFile",
             &self.cnx,
-            handle,
+            &self.dispatcher,
+            handle.clone(),
             Arc::clone(&self.imp),
             async move {
-                imp.save_file(app_id.inner(), window_identifier.inner(), &title, options)
-                    .await
+                let selected = imp
+                    .save_file(
+                        handle.into(),
+                        app_id.inner(),
+                        window_identifier.inner(),
+                        &title,
+                        options,
+                    )
+                    .await?;
+                selected.check_uris(false)?;
+                Ok(selected)
             },
         )
         .await
@@ -294,13 +606,125 @@ impl FileChooserInterface {
         Request::spawn(
             "FileChooser::SaveFiles",
             &self.cnx,
-            handle,
+            &self.dispatcher,
+            handle.clone(),
             Arc::clone(&self.imp),
             async move {
-                imp.save_files(app_id.inner(), window_identifier.inner(), &title, options)
-                    .await
+                let n_files = options.files().len();
+                let selected = imp
+                    .save_files(
+                        handle.into(),
+                        app_id.inner(),
+                        window_identifier.inner(),
+                        &title,
+                        options,
+                    )
+                    .await?;
+                if n_files > 0 {
+                    selected.check_uris_count(n_files)?;
+                }
+                selected.check_uris(false)?;
+                Ok(selected)
             },
         )
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extensions() {
+        assert_eq!(split_extension("file.txt"), ("file", ".txt"));
+        assert_eq!(split_extension("a.tar.gz"), ("a", ".tar.gz"));
+        assert_eq!(split_extension("A.TAR.XZ"), ("A", ".TAR.XZ"));
+        assert_eq!(split_extension("my.report.pdf"), ("my.report", ".pdf"));
+        assert_eq!(split_extension(".bashrc"), (".bashrc", ""));
+        assert_eq!(split_extension(".tar.gz"), (".tar", ".gz"));
+        assert_eq!(split_extension("README"), ("README", ""));
+    }
+
+    #[test]
+    fn uris_validation() {
+        let dir = std::env::temp_dir();
+        let file = dir.join(format!("ashpd-uris-{}", std::process::id()));
+        std::fs::write(&file, b"").unwrap();
+
+        let selected = SelectedFiles::default().uri(url::Url::from_directory_path(&dir).unwrap());
+        assert!(selected.check_uris(true).is_ok());
+
+        let selected = SelectedFiles::default().uri(url::Url::from_file_path(&file).unwrap());
+        assert!(selected.check_uris(false).is_ok());
+        assert!(selected.check_uris(true).is_err());
+
+        let selected = SelectedFiles::default().uri("https://example.org/a".parse().unwrap());
+        assert!(selected.check_uris(false).is_err());
+
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn dedup() {
+        let dir = std::env::temp_dir().join(format!("ashpd-dedup-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.tar.gz"), b"").unwrap();
+        std::fs::write(dir.join("file.txt"), b"").unwrap();
+        std::fs::write(dir.join("file (1).txt"), b"").unwrap();
+
+        let files = [
+            "/some/where/a.tar.gz",
+            "file.txt",
+            "new.txt",
+            "other/new.txt",
+            ".hidden",
+        ];
+        let paths = dedup_filenames(&dir, &files, CollisionStrategy::Suffix);
+        let names = paths
+            .iter()
+            .map(|p| p.strip_prefix(&dir).unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "a (1).tar.gz",
+                "file (2).txt",
+                "new.txt",
+                "new (1).txt",
+                ".hidden"
+            ]
+        );
+
+        let paths = dedup_filenames(&dir, &files[..2], CollisionStrategy::Overwrite);
+        assert_eq!(paths, [dir.join("a.tar.gz"), dir.join("file.txt")]);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn strip_newer_options() {
+        use zbus::zvariant::{serialized::Context, to_bytes, Value, LE};
+
+        let dict = HashMap::from([
+            ("directory", Value::from(true)),
+            ("current_folder", Value::from(b"/tmp\0".as_slice())),
+        ]);
+        let data = to_bytes(Context::new_dbus(LE, 0), &dict).unwrap();
+        let mut options: OpenFileOptions = data.deserialize().unwrap().0;
+
+        options.strip_newer(4);
+        assert_eq!(options.directory(), Some(true));
+        assert_eq!(
+            options.current_folder().map(AsRef::as_ref),
+            Some(std::path::Path::new("/tmp"))
+        );
+
+        options.strip_newer(3);
+        assert_eq!(options.directory(), Some(true));
+        assert!(options.current_folder().is_none());
+
+        options.strip_newer(2);
+        assert_eq!(options.directory(), None);
+    }
+}
@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{
+    backend::Result,
+    desktop::game_mode::Status,
+    process::host_pid,
+    zvariant::{OwnedFd, Value},
+    PortalError,
+};
+
+/// The pids passed to a [`GameModeImpl`] are host pids.
+///
+/// The pids of the `*ByPid` methods arrive already translated by
+/// xdg-desktop-portal, the sender of the calls, and are forwarded as is. The
+/// pidfds are turned into pids, which doesn't depend on the namespace of the
+/// caller. The methods taking a single pid use it as the requester as well.
+#[async_trait]
+pub trait GameModeImpl: Send + Sync {
+    async fn query_status(&self, pid: u32, requester: u32) -> Result<Status>;
+
+    /// Returns the status replied by GameMode, `0` if it registered `pid`
+    /// and `-1` if it rejected it.
+    async fn register_game(&self, pid: u32, requester: u32) -> Result<i32>;
+
+    /// Returns the status replied by GameMode, `0` if it unregistered `pid`
+    /// and `-1` if it rejected it.
+    async fn unregister_game(&self, pid: u32, requester: u32) -> Result<i32>;
+}
+
+pub struct GameModeInterface {
+    imp: Arc<dyn GameModeImpl>,
+    version: u32,
+}
+
+impl_version!(GameModeInterface, 4);
+
+impl GameModeInterface {
+    pub fn new(imp: impl GameModeImpl + 'static) -> Self {
+        Self {
+            imp: Arc::new(imp),
+            version: Self::VERSION,
+        }
+    }
+}
+
+fn to_pid(pid: i32) -> Result<u32> {
+    u32::try_from(pid)
+        .ok()
+        .filter(|pid| *pid != 0)
+        .ok_or_else(|| PortalError::InvalidArgument(format!("Invalid pid {pid}")))
+}
+
+fn to_pids(pid: i32, requester: i32) -> Result<(u32, u32)> {
+    Ok((to_pid(pid)?, to_pid(requester)?))
+}
+
+fn pidfds_to_pids(pid: &OwnedFd, requester: &OwnedFd) -> Result<(u32, u32)> {
+    Ok((host_pid(pid)?, host_pid(requester)?))
+}
+
+#[zbus::interface(name = "org.freedesktop.impl.portal.GameMode")]
+impl GameModeInterface {
+    #[zbus(property(emits_changed_signal = "const"), name = "version")]
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    async fn query_status(&self, pid: i32) -> Result<i32> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("GameMode::QueryStatus");
+
+        let (pid, requester) = to_pids(pid, pid)?;
+        Ok(self.imp.query_status(pid, requester).await? as i32)
+    }
+
+    async fn query_status_by_pid(&self, target: i32, requester: i32) -> Result<i32> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("GameMode::QueryStatusByPid");
+
+        let (pid, requester) = to_pids(target, requester)?;
+        Ok(self.imp.query_status(pid, requester).await? as i32)
+    }
+
+    #[zbus(name = "QueryStatusByPIDFd")]
+    async fn query_status_by_pidfd(&self, target: OwnedFd, requester: OwnedFd) -> Result<i32> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("GameMode::QueryStatusByPIDFd");
+
+        let (pid, requester) = pidfds_to_pids(&target, &requester)?;
+        Ok(self.imp.query_status(pid, requester).await? as i32)
+    }
+
+    async fn register_game(&self, pid: i32) -> Result<i32> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("GameMode::RegisterGame");
+
+        let (pid, requester) = to_pids(pid, pid)?;
+        self.imp.register_game(pid, requester).await
+    }
+
+    async fn register_game_by_pid(&self, target: i32, requester: i32) -> Result<i32> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("GameMode::RegisterGameByPid");
+
+        let (pid, requester) = to_pids(target, requester)?;
+        self.imp.register_game(pid, requester).await
+    }
+
+    #[zbus(name = "RegisterGameByPIDFd")]
+    async fn register_game_by_pidfd(&self, target: OwnedFd, requester: OwnedFd) -> Result<i32> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("GameMode::RegisterGameByPIDFd");
+
+        let (pid, requester) = pidfds_to_pids(&target, &requester)?;
+        self.imp.register_game(pid, requester).await
+    }
+
+    async fn unregister_game(&self, pid: i32) -> Result<i32> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("GameMode::UnregisterGame");
+
+        let (pid, requester) = to_pids(pid, pid)?;
+        self.imp.unregister_game(pid, requester).await
+    }
+
+    async fn unregister_game_by_pid(&self, target: i32, requester: i32) -> Result<i32> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("GameMode::UnregisterGameByPid");
+
+        let (pid, requester) = to_pids(target, requester)?;
+        self.imp.unregister_game(pid, requester).await
+    }
+
+    #[zbus(name = "UnregisterGameByPIDFd")]
+    async fn unregister_game_by_pidfd(&self, target: OwnedFd, requester: OwnedFd) -> Result<i32> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("GameMode::UnregisterGameByPIDFd");
+
+        let (pid, requester) = pidfds_to_pids(&target, &requester)?;
+        self.imp.unregister_game(pid, requester).await
+    }
+}
+
+/// A [`GameModeImpl`] forwarding the requests to the
+/// [GameMode](https://github.com/FeralInteractive/gamemode) daemon running on
+/// the host, `com.feralinteractive.GameMode`.
+///
+/// ```rust,no_run
+/// use ashpd::backend::{
+///     game_mode::{GameModeInterface, GameModePassthrough},
+///     Backend,
+/// };
+///
+/// async fn run() -> ashpd::Result<()> {
+///     let backend = Backend::new("org.freedesktop.impl.portal.desktop.myportal").await?;
+///     let passthrough = GameModePassthrough::new(backend.connection()).await?;
+///     let iface = GameModeInterface::new(passthrough);
+///     backend.serve(iface).await?;
+///     Ok(())
+/// }
+/// ```
+pub struct GameModePassthrough {
+    proxy: zbus::Proxy<'static>,
+}
+
+impl GameModePassthrough {
+    /// Connect to the GameMode daemon reachable from `cnx`, typically the
+    /// session bus connection of the backend.
+    pub async fn new(cnx: &zbus::Connection) -> zbus::Result<Self> {
+        let proxy = zbus::Proxy::new(
+            cnx,
+            "com.feralinteractive.GameMode",
+            "/com/feralinteractive/GameMode",
+            "com.feralinteractive.GameMode",
+        )
+        .await?;
+        Ok(Self { proxy })
+    }
+
+    async fn call(&self, method: &str, pid: u32, requester: u32) -> Result<i32> {
+        self.proxy
+            .call(method, &(pid as i32, requester as i32))
+            .await
+            .map_err(|err| PortalError::Failed(err.to_string()))
+    }
+}
+
+#[async_trait]
+impl GameModeImpl for GameModePassthrough {
+    async fn query_status(&self, pid: u32, requester: u32) -> Result<Status> {
+        let status = self.call("QueryStatusByPID", pid, requester).await?;
+        Status::try_from(Value::from(status)).map_err(|err| PortalError::Failed(err.to_string()))
+    }
+
+    async fn register_game(&self, pid: u32, requester: u32) -> Result<i32> {
+        self.call("RegisterGameByPID", pid, requester).await
+    }
+
+    async fn unregister_game(&self, pid: u32, requester: u32) -> Result<i32> {
+        self.call("UnregisterGameByPID", pid, requester).await
+    }
+}
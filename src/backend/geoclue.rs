@@ -0,0 +1,486 @@
+//! An implementation of the Location portal backend reading the location
+//! from GeoClue.
+//!
+//! Each started session gets its own GeoClue client, configured with the
+//! thresholds and the accuracy of the session. The accuracy is lowered to the
+//! one stored for the application in the `location` table of the permission
+//! store, the table xdg-desktop-portal uses for the Location portal. The
+//! applications without a stored permission, or whose permission can't be
+//! read, are asked for through the [`AccessImpl`](crate::backend::access::AccessImpl)
+//! served by the backend, see [`Backend::access`](crate::backend::Backend::access),
+//! and denied if it isn't served.
+//!
+//! GeoClue only serves the desktop IDs allowed by its configuration, the
+//! clients are created with the `xdg-desktop-portal` one unless changed with
+//! [`GeoClue::desktop_id`].
+//!
+//! ```rust,no_run
+//! use ashpd::backend::{geoclue::GeoClue, location::LocationInterface, Backend};
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     let backend = Backend::new("org.freedesktop.impl.portal.desktop.myportal").await?;
+//!     let geoclue = GeoClue::new(backend.connection().clone()).await?;
+//!     backend
+//!         .serve(LocationInterface::new(
+//!             geoclue,
+//!             backend.connection().clone(),
+//!         ))
+//!         .await?;
+//!     Ok(())
+//! }
+//! ```
+
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use async_trait::async_trait;
+use futures_util::{
+    future::{abortable, AbortHandle},
+    StreamExt,
+};
+
+use crate::{
+    backend::{
+        access::{Access, AccessDialog},
+        location::{
+            CreateSessionOptions, LocationImpl, LocationInterface, LocationUpdate, StartOptions,
+        },
+        permission_store::{
+            location_accuracy, PERMISSION_STORE_DESTINATION, PERMISSION_STORE_PATH,
+        },
+        request::{RequestHandle, RequestImpl},
+        Result,
+    },
+    desktop::location::Accuracy,
+    proxy::DESKTOP_PATH,
+    zbus::proxy::CacheProperties,
+    zvariant::{OwnedObjectPath, OwnedValue},
+    AppID, PortalError, WindowIdentifierType,
+};
+
+const GEOCLUE_DESTINATION: &str = "org.freedesktop.GeoClue2";
+const GEOCLUE_MANAGER_PATH: &str = "/org/freedesktop/GeoClue2/Manager";
+
+/// The `GClueAccuracyLevel` matching `accuracy`.
+fn accuracy_level(accuracy: Accuracy) -> u32 {
+    match accuracy {
+        Accuracy::None => 0,
+        Accuracy::Country => 1,
+        Accuracy::City => 4,
+        Accuracy::Neighborhood => 5,
+        Accuracy::Street => 6,
+        Accuracy::Exact => 8,
+    }
+}
+
+#[derive(Debug)]
+struct Client {
+    path: OwnedObjectPath,
+    updates: AbortHandle,
+}
+
+#[derive(Debug)]
+struct SessionState {
+    distance_threshold: u32,
+    time_threshold: u32,
+    accuracy: Accuracy,
+    client: Option<Client>,
+}
+
+/// A [`LocationImpl`] reading the location from GeoClue on the system bus.
+#[derive(Debug)]
+pub struct GeoClue {
+    cnx: zbus::Connection,
+    system: zbus::Connection,
+    desktop_id: String,
+    sessions: Mutex<HashMap<RequestHandle, SessionState>>,
+}
+
+impl GeoClue {
+    /// Connect to the system bus GeoClue is on. `cnx` is the connection the
+    /// [`LocationInterface`] is served on.
+    pub async fn new(cnx: zbus::Connection) -> zbus::Result<Self> {
+        let system = zbus::Connection::system().await?;
+        Ok(Self::with_system_connection(cnx, system))
+    }
+
+    /// Like [`GeoClue::new`], but reaches GeoClue through `system` instead of
+    /// connecting to the system bus.
+    pub fn with_system_connection(cnx: zbus::Connection, system: zbus::Connection) -> Self {
+        Self {
+            cnx,
+            system,
+            desktop_id: "xdg-desktop-portal".to_owned(),
+            sessions: Default::default(),
+        }
+    }
+
+    /// Sets the desktop ID the GeoClue clients are created with.
+    #[must_use]
+    pub fn desktop_id(mut self, desktop_id: impl Into<String>) -> Self {
+        self.desktop_id = desktop_id.into();
+        self
+    }
+
+    /// The accuracy stored for `app_id` in the permission store, if any.
+    async fn stored_accuracy(&self, app_id: &AppID) -> Option<Accuracy> {
+        let reply = self
+            .cnx
+            .call_method(
+                Some(PERMISSION_STORE_DESTINATION),
+                PERMISSION_STORE_PATH,
+                Some(PERMISSION_STORE_DESTINATION),
+                "GetPermission",
+                &("location", "location", app_id.as_ref()),
+            )
+            .await
+            .map_err(|_err| {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("No stored location permission for {app_id}: {_err}");
+            })
+            .ok()?;
+        let permissions = reply.body().deserialize::<Vec<String>>().ok()?;
+        permissions
+            .first()
+            .and_then(|permission| location_accuracy(permission))
+    }
+
+    /// The accuracy `app_id` is allowed, at most `requested`.
+    ///
+    /// The user is asked with an access dialog when no permission is stored,
+    /// the location is denied if they can't be asked.
+    async fn granted_accuracy(
+        &self,
+        handle: RequestHandle,
+        app_id: Option<AppID>,
+        window_identifier: Option<WindowIdentifierType>,
+        requested: Accuracy,
+    ) -> Accuracy {
+        let stored = match &app_id {
+            Some(app_id) => self.stored_accuracy(app_id).await,
+            None => None,
+        };
+        if let Some(stored) = stored {
+            return requested.min(stored);
+        }
+        let dialog = AccessDialog::location(app_id.as_ref());
+        match Access::new(&self.cnx)
+            .request(handle, app_id, window_identifier, dialog)
+            .await
+        {
+            Ok(Some(_)) => requested,
+            Ok(None) => Accuracy::None,
+            Err(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::warn!("Denying the location, the user can't be asked: {_err}");
+                Accuracy::None
+            }
+        }
+    }
+
+    /// Create and start a GeoClue client forwarding its locations to
+    /// `session`.
+    async fn start_client(
+        &self,
+        session: &RequestHandle,
+        distance_threshold: u32,
+        time_threshold: u32,
+        accuracy: Accuracy,
+    ) -> Result<Client> {
+        let manager = zbus::Proxy::new(
+            &self.system,
+            GEOCLUE_DESTINATION,
+            GEOCLUE_MANAGER_PATH,
+            "org.freedesktop.GeoClue2.Manager",
+        )
+        .await?;
+        let path = manager
+            .call::<_, _, OwnedObjectPath>("CreateClient", &())
+            .await?;
+        let client: zbus::Proxy<'static> = zbus::proxy::Builder::new(&self.system)
+            .destination(GEOCLUE_DESTINATION)?
+            .path(path.clone())?
+            .interface("org.freedesktop.GeoClue2.Client")?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+        client
+            .set_property("DesktopId", self.desktop_id.as_str())
+            .await?;
+        client
+            .set_property("DistanceThreshold", distance_threshold)
+            .await?;
+        client.set_property("TimeThreshold", time_threshold).await?;
+        client
+            .set_property("RequestedAccuracyLevel", accuracy_level(accuracy))
+            .await?;
+        let mut updates = client.receive_signal("LocationUpdated").await?;
+        client.call_method("Start", &()).await?;
+
+        let cnx = self.cnx.clone();
+        let system = self.system.clone();
+        let session = session.clone();
+        let (forward, abort_handle) = abortable(async move {
+            while let Some(msg) = updates.next().await {
+                let Ok((_old, new)) = msg
+                    .body()
+                    .deserialize::<(OwnedObjectPath, OwnedObjectPath)>()
+                else {
+                    continue;
+                };
+                let location = match read_location(&system, new).await {
+                    Ok(location) => location,
+                    Err(_err) => {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!("Failed to read the GeoClue location: {_err}");
+                        continue;
+                    }
+                };
+                let Ok(iface_ref) = cnx
+                    .object_server()
+                    .interface::<_, LocationInterface>(DESKTOP_PATH)
+                    .await
+                else {
+                    break;
+                };
+                let iface = iface_ref.get().await;
+                if let Err(_err) = iface.location_updated(&session, location).await {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!("Failed to send the location: {_err}");
+                }
+            }
+        });
+        tokio::spawn(forward);
+        Ok(Client {
+            path,
+            updates: abort_handle,
+        })
+    }
+
+    async fn stop_client(&self, client: Client) -> zbus::Result<()> {
+        client.updates.abort();
+        self.system
+            .call_method(
+                Some(GEOCLUE_DESTINATION),
+                &client.path,
+                Some("org.freedesktop.GeoClue2.Client"),
+                "Stop",
+                &(),
+            )
+            .await?;
+        self.system
+            .call_method(
+                Some(GEOCLUE_DESTINATION),
+                GEOCLUE_MANAGER_PATH,
+                Some("org.freedesktop.GeoClue2.Manager"),
+                "DeleteClient",
+                &(&client.path,),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// Read the GeoClue location at `path`.
+async fn read_location(
+    system: &zbus::Connection,
+    path: OwnedObjectPath,
+) -> zbus::Result<LocationUpdate> {
+    let reply = system
+        .call_method(
+            Some(GEOCLUE_DESTINATION),
+            &path,
+            Some("org.freedesktop.DBus.Properties"),
+            "GetAll",
+            &("org.freedesktop.GeoClue2.Location",),
+        )
+        .await?;
+    let mut properties = reply.body().deserialize::<HashMap<String, OwnedValue>>()?;
+    let mut take_f64 = |name: &str| {
+        properties
+            .remove(name)
+            .and_then(|value| f64::try_from(value).ok())
+    };
+    let (Some(latitude), Some(longitude), Some(accuracy)) = (
+        take_f64("Latitude"),
+        take_f64("Longitude"),
+        take_f64("Accuracy"),
+    ) else {
+        return Err(zbus::Error::Failure(format!(
+            "Incomplete location at `{}`",
+            path.as_str()
+        )));
+    };
+    let altitude = take_f64("Altitude").filter(|altitude| *altitude != -f64::MAX);
+    let speed = take_f64("Speed").filter(|speed| *speed >= 0.0);
+    let heading = take_f64("Heading").filter(|heading| *heading >= 0.0);
+    let description = properties
+        .remove("Description")
+        .and_then(|value| String::try_from(value).ok())
+        .filter(|description| !description.is_empty());
+    let timestamp = properties
+        .remove("Timestamp")
+        .and_then(|value| <(u64, u64)>::try_from(value).ok());
+
+    let mut location = LocationUpdate::new(latitude, longitude, accuracy)
+        .altitude(altitude)
+        .speed(speed)
+        .heading(heading)
+        .description(description.as_deref());
+    if let Some((secs, micros)) = timestamp {
+        location = location.timestamp(Duration::from_secs(secs) + Duration::from_micros(micros));
+    }
+    Ok(location)
+}
+
+#[async_trait]
+impl RequestImpl for GeoClue {
+    async fn close(&self, _handle: RequestHandle) {}
+}
+
+#[async_trait]
+impl LocationImpl for GeoClue {
+    async fn create_session(
+        &self,
+        _handle: RequestHandle,
+        session: RequestHandle,
+        _app_id: Option<AppID>,
+        options: CreateSessionOptions,
+    ) -> Result<()> {
+        self.sessions.lock().unwrap().insert(
+            session,
+            SessionState {
+                distance_threshold: options.distance_threshold(),
+                time_threshold: options.time_threshold(),
+                accuracy: options.accuracy(),
+                client: None,
+            },
+        );
+        Ok(())
+    }
+
+    async fn start(
+        &self,
+        handle: RequestHandle,
+        session: RequestHandle,
+        app_id: Option<AppID>,
+        window_identifier: Option<WindowIdentifierType>,
+        _options: StartOptions,
+    ) -> Result<Accuracy> {
+        let state = self.sessions.lock().unwrap().get(&session).map(|state| {
+            (
+                state.distance_threshold,
+                state.time_threshold,
+                state.accuracy,
+                state.client.is_some(),
+            )
+        });
+        let Some((distance_threshold, time_threshold, mut accuracy, started)) = state else {
+            return Err(PortalError::NotFound(format!(
+                "No location session at `{}`",
+                session.path()
+            )));
+        };
+        if started {
+            return Err(PortalError::Failed("Session already started".to_owned()));
+        }
+        accuracy = self
+            .granted_accuracy(handle, app_id, window_identifier, accuracy)
+            .await;
+        if accuracy == Accuracy::None {
+            return Ok(accuracy);
+        }
+
+        let client = self
+            .start_client(&session, distance_threshold, time_threshold, accuracy)
+            .await?;
+        let closed = match self.sessions.lock().unwrap().get_mut(&session) {
+            Some(state) => {
+                state.client = Some(client);
+                None
+            }
+            // Closed while the client was being started.
+            None => Some(client),
+        };
+        if let Some(client) = closed {
+            let _ = self.stop_client(client).await;
+        }
+        Ok(accuracy)
+    }
+
+    async fn close_session(&self, session: RequestHandle) {
+        let state = self.sessions.lock().unwrap().remove(&session);
+        if let Some(client) = state.and_then(|state| state.client) {
+            if let Err(_err) = self.stop_client(client).await {
+                #[cfg(feature = "tracing")]
+                tracing::warn!("Failed to stop the GeoClue client: {_err}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::access::{AccessImpl, AccessInterface, AccessOptions, AccessResponse};
+
+    #[test]
+    fn accuracy_levels() {
+        assert!(accuracy_level(Accuracy::City) < accuracy_level(Accuracy::Neighborhood));
+        assert_eq!(accuracy_level(Accuracy::Exact), 8);
+    }
+
+    struct Grant;
+
+    #[async_trait]
+    impl RequestImpl for Grant {
+        async fn close(&self, _handle: RequestHandle) {}
+    }
+
+    #[async_trait]
+    impl AccessImpl for Grant {
+        async fn access_dialog(
+            &self,
+            _handle: RequestHandle,
+            _app_id: Option<AppID>,
+            _window_identifier: Option<WindowIdentifierType>,
+            _title: String,
+            _subtitle: String,
+            _body: String,
+            _options: AccessOptions,
+        ) -> Result<AccessResponse> {
+            Ok(AccessResponse::default())
+        }
+    }
+
+    #[tokio::test]
+    async fn unknown_applications_are_asked() {
+        let (server, client) = crate::helpers::p2p_pair().await;
+        // Replies to the permission store calls with an error, once its task
+        // started.
+        client.object_server();
+        tokio::task::yield_now().await;
+        let geoclue = GeoClue::with_system_connection(server.clone(), client);
+        let handle = RequestHandle::from(
+            OwnedObjectPath::try_from("/org/freedesktop/portal/desktop/request/1_1/t").unwrap(),
+        );
+        let app_id = "org.example.App".parse::<AppID>().ok();
+
+        // Without a permission store nor an access dialog, denied.
+        for app_id in [app_id.clone(), None] {
+            let accuracy = geoclue
+                .granted_accuracy(handle.clone(), app_id, None, Accuracy::Exact)
+                .await;
+            assert_eq!(accuracy, Accuracy::None);
+        }
+
+        server
+            .object_server()
+            .at(DESKTOP_PATH, AccessInterface::new(Grant, server.clone()))
+            .await
+            .unwrap();
+        let accuracy = geoclue
+            .granted_accuracy(handle, app_id, None, Accuracy::City)
+            .await;
+        assert_eq!(accuracy, Accuracy::City);
+    }
+}
@@ -0,0 +1,58 @@
+//! Run the GTK parts of a backend on the GLib main context.
+//!
+//! The backend interfaces are served from the async runtime threads while GTK
+//! widgets can only be used from the thread running the default
+//! [`glib::MainContext`]. Blocking on the main context from an interface
+//! method, or awaiting a future that needs the main context to make progress
+//! from the wrong thread, is a common source of deadlocks.
+//!
+//! [`spawn_on_main`] creates the future on the main context and hands back
+//! its output once it completes.
+//!
+//! ```rust,ignore
+//! use ashpd::backend::{glib_spawn::spawn_on_main, Result};
+//!
+//! async fn pick_file() -> Result<Option<String>> {
+//!     spawn_on_main(|| async move {
+//!         let dialog = gtk::FileDialog::new();
+//!         dialog
+//!             .open_future(None::<&gtk::Window>)
+//!             .await
+//!             .ok()
+//!             .and_then(|file| file.path())
+//!             .map(|path| path.display().to_string())
+//!     })
+//!     .await
+//! }
+//! ```
+
+use std::future::Future;
+
+use futures_channel::oneshot;
+
+use crate::PortalError;
+
+/// Run the future created by `func` on the default [`glib::MainContext`] and
+/// wait for its output.
+///
+/// `func` is called on the thread owning the main context so the future
+/// doesn't have to be [`Send`]; only its output crosses threads.
+///
+/// Fails with [`PortalError::Cancelled`] if the main context dropped the
+/// future before it completed, for example because the main loop exited.
+pub async fn spawn_on_main<F, Fut, T>(func: F) -> crate::backend::Result<T>
+where
+    F: FnOnce() -> Fut + Send + 'static,
+    Fut: Future<Output = T> + 'static,
+    T: Send + 'static,
+{
+    let (sender, receiver) = oneshot::channel();
+    glib::MainContext::default().invoke(move || {
+        glib::MainContext::default().spawn_local(async move {
+            let _ = sender.send(func().await);
+        });
+    });
+    receiver.await.map_err(|_| {
+        PortalError::Cancelled("The main context dropped the task before it completed".to_owned())
+    })
+}
@@ -0,0 +1,751 @@
+//! The Location portal backend, serving the location of the device to the
+//! sessions the applications create through the Location portal.
+//!
+//! Once a session is started, the backend sends its location updates with
+//! [`LocationInterface::location_updated`]. The interface clamps them to the
+//! accuracy the application requested and was granted, so the
+//! implementations can send the most accurate location they have to all the
+//! sessions.
+//!
+//! A default implementation reading the location from GeoClue is provided by
+//! `backend::geoclue` with the `geoclue` feature.
+//!
+//! ```rust,no_run
+//! use ashpd::{
+//!     backend::{
+//!         location::{CreateSessionOptions, LocationImpl, StartOptions},
+//!         request::{RequestHandle, RequestImpl},
+//!         Result,
+//!     },
+//!     desktop::location::Accuracy,
+//!     AppID, WindowIdentifierType,
+//! };
+//! use async_trait::async_trait;
+//!
+//! struct Location;
+//!
+//! #[async_trait]
+//! impl RequestImpl for Location {
+//!     async fn close(&self, _handle: RequestHandle) {}
+//! }
+//!
+//! #[async_trait]
+//! impl LocationImpl for Location {
+//!     async fn create_session(
+//!         &self,
+//!         _handle: RequestHandle,
+//!         _session: RequestHandle,
+//!         _app_id: Option<AppID>,
+//!         _options: CreateSessionOptions,
+//!     ) -> Result<()> {
+//!         Ok(())
+//!     }
+//!
+//!     async fn start(
+//!         &self,
+//!         _handle: RequestHandle,
+//!         _session: RequestHandle,
+//!         _app_id: Option<AppID>,
+//!         _window_identifier: Option<WindowIdentifierType>,
+//!         _options: StartOptions,
+//!     ) -> Result<Accuracy> {
+//!         // Only let the applications know the city they are in.
+//!         Ok(Accuracy::City)
+//!     }
+//!
+//!     async fn close_session(&self, _session: RequestHandle) {}
+//! }
+//! ```
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use async_trait::async_trait;
+
+use crate::{
+    backend::{
+        request::{Dispatcher, Request, RequestHandle, RequestImpl},
+        session::Session,
+        MaybeAppID, MaybeWindowIdentifier, Result,
+    },
+    desktop::{location::Accuracy, request::Response},
+    proxy::DESKTOP_PATH,
+    zbus::SignalContext,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, SerializeDict, Type},
+    AppID, PortalError, WindowIdentifierType,
+};
+
+/// The meters in a degree of latitude.
+const METERS_PER_DEGREE: f64 = 111_320.0;
+
+/// The radius, in meters, a location of `accuracy` is blurred to. `None` if
+/// no location is sent at all.
+fn blur_radius(accuracy: Accuracy) -> Option<f64> {
+    match accuracy {
+        Accuracy::None => None,
+        Accuracy::Country => Some(300_000.0),
+        Accuracy::City => Some(15_000.0),
+        Accuracy::Neighborhood => Some(1_000.0),
+        Accuracy::Street => Some(100.0),
+        Accuracy::Exact => Some(0.0),
+    }
+}
+
+#[derive(Debug, Type)]
+#[zvariant(signature = "dict")]
+#[non_exhaustive]
+pub struct CreateSessionOptions {
+    distance_threshold: Option<u32>,
+    time_threshold: Option<u32>,
+    accuracy: Option<Accuracy>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(CreateSessionOptions {
+    distance_threshold => "distance-threshold",
+    time_threshold => "time-threshold",
+    accuracy => "accuracy",
+});
+
+impl CreateSessionOptions {
+    /// The distance, in meters, the device has to move by before a new
+    /// location is sent. Defaults to `0`.
+    pub fn distance_threshold(&self) -> u32 {
+        self.distance_threshold.unwrap_or(0)
+    }
+
+    /// The time, in seconds, to wait for before sending a new location.
+    /// Defaults to `0`.
+    pub fn time_threshold(&self) -> u32 {
+        self.time_threshold.unwrap_or(0)
+    }
+
+    /// The accuracy requested by the application. Defaults to
+    /// [`Accuracy::Exact`].
+    pub fn accuracy(&self) -> Accuracy {
+        self.accuracy.unwrap_or(Accuracy::Exact)
+    }
+
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+}
+
+#[derive(Debug, Type)]
+#[zvariant(signature = "dict")]
+#[non_exhaustive]
+pub struct StartOptions {
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(StartOptions {});
+
+impl StartOptions {
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+}
+
+#[derive(Debug, SerializeDict, Type)]
+#[zvariant(signature = "dict")]
+struct StartResults {
+    accuracy: Accuracy,
+}
+
+/// A location sent to a session with
+/// [`LocationInterface::location_updated`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocationUpdate {
+    latitude: f64,
+    longitude: f64,
+    accuracy: f64,
+    altitude: Option<f64>,
+    speed: Option<f64>,
+    heading: Option<f64>,
+    description: Option<String>,
+    timestamp: Duration,
+}
+
+impl LocationUpdate {
+    /// A location at `latitude` and `longitude`, in degrees, within
+    /// `accuracy` meters, retrieved now.
+    pub fn new(latitude: f64, longitude: f64, accuracy: f64) -> Self {
+        Self {
+            latitude,
+            longitude,
+            accuracy,
+            altitude: None,
+            speed: None,
+            heading: None,
+            description: None,
+            timestamp: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Sets the altitude, in meters.
+    #[must_use]
+    pub fn altitude(mut self, altitude: impl Into<Option<f64>>) -> Self {
+        self.altitude = altitude.into();
+        self
+    }
+
+    /// Sets the speed, in meters per second.
+    #[must_use]
+    pub fn speed(mut self, speed: impl Into<Option<f64>>) -> Self {
+        self.speed = speed.into();
+        self
+    }
+
+    /// Sets the heading, in degrees, going clockwise. North 0, East 90, South
+    /// 180, West 270.
+    #[must_use]
+    pub fn heading(mut self, heading: impl Into<Option<f64>>) -> Self {
+        self.heading = heading.into();
+        self
+    }
+
+    /// Sets the description of the location.
+    #[must_use]
+    pub fn description<'a>(mut self, description: impl Into<Option<&'a str>>) -> Self {
+        self.description = description.into().map(ToOwned::to_owned);
+        self
+    }
+
+    /// Sets when the location was retrieved, since the Unix epoch.
+    #[must_use]
+    pub fn timestamp(mut self, timestamp: Duration) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// The latitude, in degrees.
+    pub fn latitude(&self) -> f64 {
+        self.latitude
+    }
+
+    /// The longitude, in degrees.
+    pub fn longitude(&self) -> f64 {
+        self.longitude
+    }
+
+    /// The accuracy, in meters.
+    pub fn accuracy(&self) -> f64 {
+        self.accuracy
+    }
+
+    /// Blur the location down to `accuracy`.
+    ///
+    /// The coordinates are snapped to a grid matching the accuracy. The
+    /// description, which would name the place, and the speed and heading,
+    /// which tell where the device is heading to, are only kept for
+    /// [`Accuracy::Exact`], like the altitude is only kept from
+    /// [`Accuracy::Street`]. Returns `None` for [`Accuracy::None`].
+    pub fn clamp(mut self, accuracy: Accuracy) -> Option<Self> {
+        let radius = blur_radius(accuracy)?;
+        if accuracy == Accuracy::Exact {
+            return Some(self);
+        }
+        let step = radius / METERS_PER_DEGREE;
+        self.latitude = ((self.latitude / step).round() * step).clamp(-90.0, 90.0);
+        // A degree of longitude shrinks towards the poles, widen the step to
+        // keep the same distance. It depends on the snapped latitude only, not
+        // to leak the position within the cell.
+        let step = (step / self.latitude.to_radians().cos()).min(360.0);
+        // Snapping can go past the antimeridian, wrap into [-180, 180).
+        self.longitude = ((self.longitude / step).round() * step + 180.0).rem_euclid(360.0) - 180.0;
+        self.accuracy = self.accuracy.max(radius);
+        self.speed = None;
+        self.heading = None;
+        self.description = None;
+        if accuracy < Accuracy::Street {
+            self.altitude = None;
+        }
+        Some(self)
+    }
+}
+
+/// The location as sent in `LocationUpdated`, the unknown values being
+/// replaced by the sentinels GeoClue uses.
+#[derive(Debug, SerializeDict, Type)]
+#[zvariant(signature = "dict")]
+struct LocationDict {
+    #[zvariant(rename = "Latitude")]
+    latitude: f64,
+    #[zvariant(rename = "Longitude")]
+    longitude: f64,
+    #[zvariant(rename = "Accuracy")]
+    accuracy: f64,
+    #[zvariant(rename = "Altitude")]
+    altitude: f64,
+    #[zvariant(rename = "Speed")]
+    speed: f64,
+    #[zvariant(rename = "Heading")]
+    heading: f64,
+    #[zvariant(rename = "Description")]
+    description: String,
+    #[zvariant(rename = "Timestamp")]
+    timestamp: (u64, u64),
+}
+
+impl From<LocationUpdate> for LocationDict {
+    fn from(location: LocationUpdate) -> Self {
+        Self {
+            latitude: location.latitude,
+            longitude: location.longitude,
+            accuracy: location.accuracy,
+            altitude: location.altitude.unwrap_or(-f64::MAX),
+            speed: location.speed.unwrap_or(-1.0),
+            heading: location.heading.unwrap_or(-1.0),
+            description: location.description.unwrap_or_default(),
+            timestamp: (
+                location.timestamp.as_secs(),
+                location.timestamp.subsec_micros().into(),
+            ),
+        }
+    }
+}
+
+#[async_trait]
+pub trait LocationImpl: RequestImpl {
+    /// Create the location session `session` for `app_id`.
+    async fn create_session(
+        &self,
+        handle: RequestHandle,
+        session: RequestHandle,
+        app_id: Option<AppID>,
+        options: CreateSessionOptions,
+    ) -> Result<()>;
+
+    /// Start sending the location updates of `session`, typically once the
+    /// user allowed it.
+    ///
+    /// Returns the accuracy granted to the application, the updates are
+    /// clamped to it and to the accuracy requested when creating the
+    /// session. [`Accuracy::None`] starts the session without sending any
+    /// location.
+    async fn start(
+        &self,
+        handle: RequestHandle,
+        session: RequestHandle,
+        app_id: Option<AppID>,
+        window_identifier: Option<WindowIdentifierType>,
+        options: StartOptions,
+    ) -> Result<Accuracy>;
+
+    /// Stop sending the location updates of `session`, closed either by the
+    /// application or with [`LocationInterface::close_session`].
+    async fn close_session(&self, session: RequestHandle);
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SessionState {
+    requested: Accuracy,
+    granted: Option<Accuracy>,
+}
+
+pub struct LocationInterface {
+    imp: Arc<dyn LocationImpl>,
+    cnx: zbus::Connection,
+    dispatcher: Dispatcher,
+    version: u32,
+    sessions: Arc<Mutex<HashMap<RequestHandle, SessionState>>>,
+}
+
+impl_version!(LocationInterface, 1);
+
+impl LocationInterface {
+    pub fn new(imp: impl LocationImpl + 'static, cnx: zbus::Connection) -> Self {
+        Self {
+            imp: Arc::new(imp),
+            cnx,
+            dispatcher: Dispatcher::default(),
+            version: Self::VERSION,
+            sessions: Default::default(),
+        }
+    }
+
+    /// Limit how many requests are handled at the same time, see
+    /// [`Dispatcher`].
+    #[must_use]
+    pub fn with_dispatcher(mut self, dispatcher: Dispatcher) -> Self {
+        self.dispatcher = dispatcher;
+        self
+    }
+
+    /// Send `location` to `session`, clamped to the accuracy the application
+    /// requested and was granted, see [`LocationUpdate::clamp`].
+    ///
+    /// Returns `false` if the location wasn't sent: the session is unknown,
+    /// not started yet, or was granted [`Accuracy::None`].
+    pub async fn location_updated(
+        &self,
+        session: &RequestHandle,
+        location: LocationUpdate,
+    ) -> zbus::Result<bool> {
+        let state = self.sessions.lock().unwrap().get(session).copied();
+        let Some(accuracy) = state.and_then(|state| state.granted) else {
+            return Ok(false);
+        };
+        let Some(location) = location.clamp(accuracy) else {
+            return Ok(false);
+        };
+        let signal_ctxt = SignalContext::new(&self.cnx, DESKTOP_PATH)?;
+        Self::emit_location_updated(&signal_ctxt, session.path(), location.into()).await?;
+        Ok(true)
+    }
+
+    /// Close `session`, e.g. when the location can't be retrieved anymore.
+    ///
+    /// Returns `false` if the session is unknown.
+    pub async fn close_session(&self, session: &RequestHandle) -> zbus::Result<bool> {
+        Session::close_at(&self.cnx, session.path()).await
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.impl.portal.Location")]
+impl LocationInterface {
+    #[zbus(property(emits_changed_signal = "const"), name = "version")]
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    #[zbus(name = "CreateSession")]
+    #[dbus_interface(out_args("response", "results"))]
+    async fn create_session(
+        &self,
+        handle: OwnedObjectPath,
+        session_handle: OwnedObjectPath,
+        app_id: MaybeAppID,
+        options: CreateSessionOptions,
+    ) -> Result<Response<HashMap<String, OwnedValue>>> {
+        let imp = Arc::clone(&self.imp);
+        let session = RequestHandle::from(session_handle.clone());
+        let requested = options.accuracy();
+
+        let response = Request::spawn(
+            "Location::CreateSession",
+            &self.cnx,
+            &self.dispatcher,
+            handle.clone(),
+            Arc::clone(&self.imp),
+            {
+                let session = session.clone();
+                async move {
+                    imp.create_session(handle.into(), session, app_id.inner(), options)
+                        .await
+                        .map(|()| HashMap::new())
+                }
+            },
+        )
+        .await?;
+        if response.is_ok() {
+            self.sessions.lock().unwrap().insert(
+                session.clone(),
+                SessionState {
+                    requested,
+                    granted: None,
+                },
+            );
+            let sessions = Arc::clone(&self.sessions);
+            let imp = Arc::clone(&self.imp);
+            Session::serve(&self.cnx, session_handle, move || {
+                sessions.lock().unwrap().remove(&session);
+                tokio::spawn(async move {
+                    imp.close_session(session).await;
+                });
+            })
+            .await?;
+        }
+        Ok(response)
+    }
+
+    #[zbus(name = "Start")]
+    #[dbus_interface(out_args("response", "results"))]
+    async fn start(
+        &self,
+        handle: OwnedObjectPath,
+        session_handle: OwnedObjectPath,
+        app_id: MaybeAppID,
+        window_identifier: MaybeWindowIdentifier,
+        options: StartOptions,
+    ) -> Result<Response<StartResults>> {
+        let imp = Arc::clone(&self.imp);
+        let session = RequestHandle::from(session_handle);
+        let state = self.sessions.lock().unwrap().get(&session).copied();
+        let Some(state) = state else {
+            return Err(PortalError::NotFound(format!(
+                "No location session at `{}`",
+                session.path()
+            )));
+        };
+
+        let response = Request::spawn(
+            "Location::Start",
+            &self.cnx,
+            &self.dispatcher,
+            handle.clone(),
+            Arc::clone(&self.imp),
+            {
+                let session = session.clone();
+                async move {
+                    imp.start(
+                        handle.into(),
+                        session,
+                        app_id.inner(),
+                        window_identifier.inner(),
+                        options,
+                    )
+                    .await
+                }
+            },
+        )
+        .await?
+        .map(|granted| granted.min(state.requested));
+        if let Response::Ok(granted) = &response {
+            if let Some(state) = self.sessions.lock().unwrap().get_mut(&session) {
+                state.granted = Some(*granted);
+            }
+        }
+        Ok(response.map(|accuracy| StartResults { accuracy }))
+    }
+
+    #[zbus(signal, name = "LocationUpdated")]
+    async fn emit_location_updated(
+        signal_ctxt: &SignalContext<'_>,
+        session_handle: ObjectPath<'_>,
+        location: LocationDict,
+    ) -> zbus::Result<()>;
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::zvariant::{serialized::Context, to_bytes, LE};
+
+    use super::*;
+    use crate::desktop::location::Location;
+    #[cfg(feature = "tokio")]
+    use crate::helpers::serve_p2p;
+
+    #[test]
+    fn clamp_location() {
+        let location = LocationUpdate::new(48.8584, 2.2945, 10.0)
+            .altitude(35.0)
+            .speed(1.5)
+            .heading(90.0)
+            .description("Champ de Mars")
+            .timestamp(Duration::from_millis(1_700_000_000_250));
+
+        assert_eq!(location.clone().clamp(Accuracy::None), None);
+        assert_eq!(
+            location.clone().clamp(Accuracy::Exact).as_ref(),
+            Some(&location)
+        );
+
+        let street = location.clone().clamp(Accuracy::Street).unwrap();
+        assert_eq!(street.accuracy(), 100.0);
+        assert!((street.latitude() - location.latitude()).abs() < 0.001);
+        assert_eq!((street.altitude, street.speed), (Some(35.0), None));
+
+        let city = location.clone().clamp(Accuracy::City).unwrap();
+        assert_eq!(city.accuracy(), 15_000.0);
+        assert_eq!(city.altitude, None);
+        // Nearby locations are blurred to the same one.
+        let nearby = LocationUpdate::new(48.86, 2.29, 10.0)
+            .clamp(Accuracy::City)
+            .unwrap();
+        assert_eq!(
+            (city.latitude(), city.longitude()),
+            (nearby.latitude(), nearby.longitude())
+        );
+
+        let path = ObjectPath::try_from("/org/freedesktop/portal/desktop/session/1_42/s").unwrap();
+        let data = to_bytes(Context::new_dbus(LE, 0), &(path, LocationDict::from(city))).unwrap();
+        let location: Location = data.deserialize().unwrap().0;
+        assert_eq!(location.accuracy(), 15_000.0);
+        assert_eq!(location.altitude(), None);
+        assert_eq!(location.speed(), None);
+        assert_eq!(location.description(), None);
+        assert_eq!(location.timestamp(), Duration::from_secs(1_700_000_000));
+
+        for longitude in [179.9, -179.9] {
+            let country = LocationUpdate::new(0.0, longitude, 10.0)
+                .clamp(Accuracy::Country)
+                .unwrap();
+            assert!((-180.0..=180.0).contains(&country.longitude()));
+        }
+    }
+
+    #[test]
+    fn clamp_high_latitude() {
+        let radius = 15_000.0;
+        let latitude = 70.0;
+        let meters_per_longitude = METERS_PER_DEGREE * f64::to_radians(latitude).cos();
+
+        // Walk east over a few cells, the blur reaching half of the radius
+        // but not more.
+        let mut farthest = 0.0_f64;
+        for meters in (0..3 * radius as u32).step_by(250) {
+            let longitude = 20.0 + f64::from(meters) / meters_per_longitude;
+            let city = LocationUpdate::new(latitude, longitude, 10.0)
+                .clamp(Accuracy::City)
+                .unwrap();
+            let meters_per_longitude = METERS_PER_DEGREE * city.latitude().to_radians().cos();
+            let blur = (city.longitude() - longitude).abs() * meters_per_longitude;
+            assert!(blur <= radius / 2.0 + 1.0, "blurred by {blur}m");
+            farthest = farthest.max(blur);
+        }
+        assert!(
+            farthest >= radius / 2.0 - 250.0,
+            "blurred by {farthest}m only"
+        );
+
+        // Close to the pole, the whole circle of latitude is a single cell.
+        let polar = LocationUpdate::new(89.99, 120.0, 10.0)
+            .clamp(Accuracy::Country)
+            .unwrap();
+        assert!((-180.0..=180.0).contains(&polar.longitude()));
+    }
+
+    /// Sends the handle of the closed sessions.
+    #[cfg(feature = "tokio")]
+    struct Locator(futures_channel::mpsc::UnboundedSender<RequestHandle>);
+
+    #[cfg(feature = "tokio")]
+    #[async_trait]
+    impl RequestImpl for Locator {
+        async fn close(&self, _handle: RequestHandle) {}
+    }
+
+    #[cfg(feature = "tokio")]
+    #[async_trait]
+    impl LocationImpl for Locator {
+        async fn create_session(
+            &self,
+            _handle: RequestHandle,
+            _session: RequestHandle,
+            _app_id: Option<AppID>,
+            _options: CreateSessionOptions,
+        ) -> Result<()> {
+            Ok(())
+        }
+
+        async fn start(
+            &self,
+            _handle: RequestHandle,
+            _session: RequestHandle,
+            _app_id: Option<AppID>,
+            _window_identifier: Option<WindowIdentifierType>,
+            _options: StartOptions,
+        ) -> Result<Accuracy> {
+            Ok(Accuracy::Exact)
+        }
+
+        async fn close_session(&self, session: RequestHandle) {
+            self.0.unbounded_send(session).unwrap();
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn location_session() {
+        use futures_util::StreamExt;
+
+        let (closed_sender, mut closed) = futures_channel::mpsc::unbounded();
+        let (backend, proxy) =
+            serve_p2p(|cnx| LocationInterface::new(Locator(closed_sender), cnx)).await;
+        let session = RequestHandle::from(
+            OwnedObjectPath::try_from("/org/freedesktop/portal/desktop/session/0/location")
+                .unwrap(),
+        );
+        let request = |token: &str| {
+            OwnedObjectPath::try_from(format!("/org/freedesktop/portal/desktop/request/0/{token}"))
+                .unwrap()
+        };
+        let options = std::collections::HashMap::from([(
+            "accuracy",
+            zbus::zvariant::Value::from(Accuracy::City),
+        )]);
+        let _: (u32, std::collections::HashMap<String, OwnedValue>) = proxy
+            .call(
+                "CreateSession",
+                &(
+                    request("create"),
+                    session.path(),
+                    "org.example.App",
+                    options,
+                ),
+            )
+            .await
+            .unwrap();
+
+        let iface_ref = backend
+            .connection()
+            .object_server()
+            .interface::<_, LocationInterface>(DESKTOP_PATH)
+            .await
+            .unwrap();
+        let location = LocationUpdate::new(48.8584, 2.2945, 10.0);
+        // Not started yet.
+        assert!(!iface_ref
+            .get()
+            .await
+            .location_updated(&session, location.clone())
+            .await
+            .unwrap());
+
+        let (response, results): (u32, std::collections::HashMap<String, OwnedValue>) = proxy
+            .call(
+                "Start",
+                &(
+                    request("start"),
+                    session.path(),
+                    "org.example.App",
+                    "",
+                    std::collections::HashMap::<&str, OwnedValue>::new(),
+                ),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response, 0);
+        // The granted accuracy is clamped to the requested one.
+        assert_eq!(
+            Accuracy::try_from(results["accuracy"].try_clone().unwrap()).unwrap(),
+            Accuracy::City
+        );
+
+        let mut updates = proxy.receive_signal("LocationUpdated").await.unwrap();
+        assert!(iface_ref
+            .get()
+            .await
+            .location_updated(&session, location)
+            .await
+            .unwrap());
+        let update: Location = updates.next().await.unwrap().body().deserialize().unwrap();
+        assert_eq!(update.session_handle(), session.path());
+        assert_eq!(update.accuracy(), 15_000.0);
+
+        proxy
+            .connection()
+            .call_method(
+                Some("org.example.Portal"),
+                session.path(),
+                Some("org.freedesktop.impl.portal.Session"),
+                "Close",
+                &(),
+            )
+            .await
+            .unwrap();
+        let closed = tokio::time::timeout(std::time::Duration::from_secs(5), closed.next())
+            .await
+            .expect("The session wasn't closed");
+        assert_eq!(closed.as_ref(), Some(&session));
+        assert!(!iface_ref.get().await.close_session(&session).await.unwrap());
+    }
+}
@@ -33,13 +33,17 @@ pub struct LockdownInterface {
     imp: Arc<dyn LockdownImpl>,
     #[allow(dead_code)]
     cnx: zbus::Connection,
+    version: u32,
 }
 
+impl_version!(LockdownInterface, 1);
+
 impl LockdownInterface {
     pub fn new(imp: impl LockdownImpl + 'static, cnx: zbus::Connection) -> Self {
         Self {
             imp: Arc::new(imp),
             cnx,
+            version: Self::VERSION,
         }
     }
 }
@@ -48,7 +52,7 @@ impl LockdownInterface {
 impl LockdownInterface {
     #[zbus(property(emits_changed_signal = "const"), name = "version")]
     fn version(&self) -> u32 {
-        1
+        self.version
     }
 
     #[zbus(property, name = "disable-printing")]
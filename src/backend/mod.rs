@@ -1,7 +1,12 @@
 use serde::{de::Deserializer, Deserialize};
-use zbus::zvariant::Type;
+use zbus::{
+    fdo::{RequestNameFlags, RequestNameReply},
+    names::{OwnedWellKnownName, WellKnownName},
+    object_server::Interface,
+    zvariant::Type,
+};
 
-use crate::{AppID, WindowIdentifierType};
+use crate::{proxy::DESKTOP_PATH, AppID, WindowIdentifierType};
 
 pub type Result<T> = std::result::Result<T, crate::error::PortalError>;
 
@@ -53,17 +58,402 @@ impl<'de> Deserialize<'de> for MaybeAppID {
     }
 }
 
+/// The error returned when creating a [`Backend`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum BackendError {
+    /// The well-known name is owned by another process, typically the
+    /// portal backend installed by the desktop, which can't or wasn't allowed
+    /// to be replaced.
+    NameTaken(OwnedWellKnownName),
+    /// A zbus error.
+    Zbus(zbus::Error),
+}
+
+impl std::fmt::Display for BackendError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NameTaken(name) => write!(f, "The name `{name}` is already owned"),
+            Self::Zbus(err) => write!(f, "ZBus Error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+impl From<zbus::Error> for BackendError {
+    fn from(err: zbus::Error) -> Self {
+        Self::Zbus(err)
+    }
+}
+
+impl From<BackendError> for crate::Error {
+    fn from(err: BackendError) -> Self {
+        match err {
+            BackendError::NameTaken(_) => Self::Zbus(zbus::Error::NameTaken),
+            BackendError::Zbus(err) => Self::Zbus(err),
+        }
+    }
+}
+
+/// The bus a [`Backend`] connects to.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum BusType {
+    /// The session bus of the user.
+    #[default]
+    Session,
+    /// The system bus, for system level portals.
+    System,
+    /// A bus reachable at a D-Bus address, e.g. the private bus of a greeter
+    /// like `unix:path=/run/greeter/bus`.
+    Address(String),
+}
+
+impl BusType {
+    async fn connect(&self) -> zbus::Result<zbus::Connection> {
+        match self {
+            Self::Session => zbus::Connection::session().await,
+            Self::System => zbus::Connection::system().await,
+            Self::Address(address) => {
+                zbus::connection::Builder::address(address.as_str())?
+                    .build()
+                    .await
+            }
+        }
+    }
+}
+
+/// What to do when the well-known name of a [`Backend`] is already owned.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum NameRequest {
+    /// Replace the current owner if it allows it, fail with
+    /// [`BackendError::NameTaken`] otherwise.
+    #[default]
+    Replace,
+    /// Fail with [`BackendError::NameTaken`].
+    DoNotReplace,
+    /// Wait in the queue for the name to be released, see
+    /// [`Backend::is_queued`].
+    Queue,
+}
+
+impl NameRequest {
+    fn flags(self) -> enumflags2::BitFlags<RequestNameFlags> {
+        match self {
+            Self::Replace => {
+                RequestNameFlags::ReplaceExisting
+                    | RequestNameFlags::AllowReplacement
+                    | RequestNameFlags::DoNotQueue
+            }
+            Self::DoNotReplace => RequestNameFlags::AllowReplacement | RequestNameFlags::DoNotQueue,
+            Self::Queue => RequestNameFlags::AllowReplacement.into(),
+        }
+    }
+}
+
+/// A portal backend serving one or more `org.freedesktop.impl.portal.*`
+/// interfaces on a D-Bus connection.
+///
+/// ```rust,no_run
+/// use ashpd::backend::Backend;
+///
+/// async fn run() -> ashpd::Result<()> {
+///     let backend = Backend::new("org.freedesktop.impl.portal.desktop.myportal").await?;
+///     // Serve the interfaces using `backend.serve(...)`
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Backend {
+    cnx: zbus::Connection,
+    queued: bool,
+}
+
+impl Backend {
+    /// Connect to the session bus and request the well-known `name`,
+    /// replacing its current owner if allowed.
+    pub async fn new<'w, W>(name: W) -> std::result::Result<Self, BackendError>
+    where
+        W: TryInto<WellKnownName<'w>>,
+        W::Error: Into<zbus::Error>,
+    {
+        Self::new_on_bus(BusType::Session, name).await
+    }
+
+    /// Like [`Backend::new`], but connects to `bus` instead of the session
+    /// bus.
+    pub async fn new_on_bus<'w, W>(bus: BusType, name: W) -> std::result::Result<Self, BackendError>
+    where
+        W: TryInto<WellKnownName<'w>>,
+        W::Error: Into<zbus::Error>,
+    {
+        let cnx = bus.connect().await?;
+        Self::new_with_connection(cnx, name).await
+    }
+
+    /// Use an existing connection instead of dialing the session bus.
+    ///
+    /// The well-known `name` is only requested if the connection is a bus
+    /// connection, peer-to-peer connections have no bus to request it from
+    /// which makes them convenient for tests.
+    pub async fn new_with_connection<'w, W>(
+        cnx: zbus::Connection,
+        name: W,
+    ) -> std::result::Result<Self, BackendError>
+    where
+        W: TryInto<WellKnownName<'w>>,
+        W::Error: Into<zbus::Error>,
+    {
+        Self::new_with_name_request(cnx, name, NameRequest::default()).await
+    }
+
+    /// Like [`Backend::new_with_connection`], with control over what happens
+    /// when the well-known `name` is already owned.
+    pub async fn new_with_name_request<'w, W>(
+        cnx: zbus::Connection,
+        name: W,
+        request: NameRequest,
+    ) -> std::result::Result<Self, BackendError>
+    where
+        W: TryInto<WellKnownName<'w>>,
+        W::Error: Into<zbus::Error>,
+    {
+        let name = name.try_into().map_err(Into::into)?;
+        let mut queued = false;
+        if cnx.unique_name().is_some() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("Requesting name `{name}` ({request:?})");
+            match cnx.request_name_with_flags(&name, request.flags()).await {
+                Ok(RequestNameReply::PrimaryOwner | RequestNameReply::AlreadyOwner) => (),
+                Ok(RequestNameReply::InQueue) => queued = true,
+                Ok(RequestNameReply::Exists) | Err(zbus::Error::NameTaken) => {
+                    return Err(BackendError::NameTaken(name.into()))
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(Self { cnx, queued })
+    }
+
+    /// Whether the backend was queued waiting for the name to be released,
+    /// when created with [`NameRequest::Queue`].
+    pub fn is_queued(&self) -> bool {
+        self.queued
+    }
+
+    /// The connection the backend is serving on.
+    pub fn connection(&self) -> &zbus::Connection {
+        &self.cnx
+    }
+
+    /// Show access dialogs with the [`AccessImpl`](access::AccessImpl)
+    /// served by this backend, e.g. before starting a location session.
+    ///
+    /// ```rust,no_run
+    /// use ashpd::{
+    ///     backend::{access::AccessDialog, request::RequestHandle, Backend},
+    ///     AppID,
+    /// };
+    ///
+    /// async fn start(backend: &Backend, handle: RequestHandle, app_id: Option<AppID>) -> bool {
+    ///     let dialog = AccessDialog::location(app_id.as_ref());
+    ///     matches!(
+    ///         backend.access().request(handle, app_id, None, dialog).await,
+    ///         Ok(Some(_))
+    ///     )
+    /// }
+    /// ```
+    pub fn access(&self) -> access::Access<'_> {
+        access::Access::new(&self.cnx)
+    }
+
+    /// Serve `iface` at `/org/freedesktop/portal/desktop`.
+    ///
+    /// Returns `false` if an interface of the same type was already being
+    /// served.
+    pub async fn serve<I: Interface>(&self, iface: I) -> crate::Result<bool> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Serving interface `{}`", I::name());
+        let added = self.cnx.object_server().at(DESKTOP_PATH, iface).await?;
+        Ok(added)
+    }
+}
+
+/// Implements `VERSION` and `with_version` for an interface advertising the
+/// version stored in its `version` field.
+macro_rules! impl_version {
+    ($interface:ident $(<$param:ident: $bound:path>)?, $version:literal) => {
+        impl$(<$param: $bound + 'static>)? $interface$(<$param>)? {
+            /// The latest version of the interface supported.
+            pub const VERSION: u32 = $version;
+
+            /// Advertise `version` of the interface instead of
+            /// [`Self::VERSION`], e.g. when the implementation doesn't support
+            /// the features of the latest versions. Frontends use it to detect
+            /// the supported features, and the options introduced after it are
+            /// dropped before reaching the implementation.
+            ///
+            /// Fails with [`PortalError::InvalidArgument`](crate::PortalError::InvalidArgument)
+            /// if `version` is 0 or greater than [`Self::VERSION`].
+            pub fn with_version(mut self, version: u32) -> crate::backend::Result<Self> {
+                if !(1..=Self::VERSION).contains(&version) {
+                    return Err(crate::PortalError::InvalidArgument(format!(
+                        "Version {version} of the interface is not supported, expected 1 to {}",
+                        Self::VERSION
+                    )));
+                }
+                self.version = version;
+                Ok(self)
+            }
+        }
+    };
+}
+
 pub mod access;
 pub mod account;
 pub mod app_chooser;
 pub mod background;
+pub mod clipboard;
+#[cfg(feature = "desktop_entries")]
+#[cfg_attr(docsrs, doc(cfg(feature = "desktop_entries")))]
+pub mod desktop_entries;
 pub mod email;
 pub mod file_chooser;
+pub mod game_mode;
+#[cfg(feature = "geoclue")]
+#[cfg_attr(docsrs, doc(cfg(feature = "geoclue")))]
+pub mod geoclue;
+#[cfg(feature = "glib")]
+#[cfg_attr(docsrs, doc(cfg(feature = "glib")))]
+pub mod glib_spawn;
+pub mod location;
 pub mod lockdown;
+pub mod notification;
 pub mod permission_store;
 pub mod print;
 pub mod request;
 pub mod screenshot;
 pub mod secret;
+#[cfg(feature = "secret_store")]
+#[cfg_attr(docsrs, doc(cfg(feature = "secret_store")))]
+pub mod secret_store;
+pub mod session;
 pub mod settings;
+pub mod usb;
 pub mod wallpaper;
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::{
+        backend::{
+            account::{AccountImpl, AccountInterface, UserInformationOptions},
+            request::{RequestHandle, RequestImpl},
+        },
+        desktop::account::UserInformation,
+        helpers::{p2p_pair, serve_p2p},
+        AppID, WindowIdentifierType,
+    };
+
+    struct Account;
+
+    #[async_trait]
+    impl RequestImpl for Account {
+        async fn close(&self, _handle: RequestHandle) {}
+    }
+
+    impl AccountImpl for Account {
+        async fn get_user_information(
+            &self,
+            _handle: RequestHandle,
+            _app_id: Option<AppID>,
+            _window_identifier: Option<WindowIdentifierType>,
+            _options: UserInformationOptions,
+        ) -> Result<UserInformation> {
+            unreachable!()
+        }
+    }
+
+    #[tokio::test]
+    async fn serve_on_p2p_connections() {
+        // Two independent backends, each on its own connection.
+        for _ in 0..2 {
+            let (_backend, proxy) = serve_p2p(|cnx| AccountInterface::new(Account, cnx)).await;
+            assert_eq!(proxy.get_property::<u32>("version").await.unwrap(), 1);
+        }
+    }
+
+    #[test]
+    fn name_request_flags() {
+        assert!(NameRequest::Replace
+            .flags()
+            .contains(RequestNameFlags::ReplaceExisting));
+        assert!(!NameRequest::DoNotReplace
+            .flags()
+            .contains(RequestNameFlags::ReplaceExisting));
+        assert!(!NameRequest::Queue
+            .flags()
+            .contains(RequestNameFlags::DoNotQueue));
+    }
+
+    #[tokio::test]
+    async fn serve_on_private_bus() {
+        use std::io::{BufRead, BufReader};
+
+        let socket = std::env::temp_dir().join(format!("ashpd-bus-{}", std::process::id()));
+        let Ok(mut daemon) = std::process::Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address=1"])
+            .arg(format!("--address=unix:path={}", socket.display()))
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+        else {
+            // No bus daemon to test with, which is only expected outside of
+            // the CI.
+            assert!(std::env::var_os("CI").is_none(), "dbus-daemon is missing");
+            eprintln!("Skipping serve_on_private_bus, dbus-daemon is missing");
+            return;
+        };
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        let bus = BusType::Address(address.trim().to_owned());
+
+        let backend = Backend::new_on_bus(bus.clone(), "org.example.Portal")
+            .await
+            .unwrap();
+        let iface = AccountInterface::new(Account, backend.connection().clone());
+        assert!(backend.serve(iface).await.unwrap());
+
+        let client = bus.connect().await.unwrap();
+        let proxy = zbus::Proxy::new(
+            &client,
+            "org.example.Portal",
+            DESKTOP_PATH,
+            "org.freedesktop.impl.portal.Account",
+        )
+        .await
+        .unwrap();
+        assert_eq!(proxy.get_property::<u32>("version").await.unwrap(), 1);
+
+        let taken =
+            Backend::new_with_name_request(client, "org.example.Portal", NameRequest::DoNotReplace)
+                .await;
+        assert!(matches!(taken, Err(BackendError::NameTaken(_))));
+
+        daemon.kill().unwrap();
+        daemon.wait().unwrap();
+        let _ = std::fs::remove_file(socket);
+    }
+
+    #[tokio::test]
+    async fn invalid_name() {
+        let (server, _client) = p2p_pair().await;
+        assert!(Backend::new_with_connection(server, "not a name")
+            .await
+            .is_err());
+    }
+}
@@ -0,0 +1,520 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+
+use crate::{
+    backend::{MaybeAppID, Result},
+    desktop::{notification::Priority, Icon},
+    proxy::DESKTOP_PATH,
+    zbus::SignalContext,
+    zvariant::{OwnedValue, Type, Value},
+    AppID, PortalError,
+};
+
+#[derive(Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+#[non_exhaustive]
+pub struct Notification {
+    title: Option<String>,
+    body: Option<String>,
+    icon: Option<Icon>,
+    priority: Option<Priority>,
+    default_action: Option<String>,
+    default_action_target: Option<OwnedValue>,
+    buttons: Option<Vec<Button>>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(Notification {
+    title => "title",
+    body => "body",
+    icon => "icon",
+    priority => "priority",
+    default_action => "default-action",
+    default_action_target => "default-action-target",
+    buttons => "buttons",
+});
+
+impl Notification {
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn body(&self) -> Option<&str> {
+        self.body.as_deref()
+    }
+
+    pub fn icon(&self) -> Option<&Icon> {
+        self.icon.as_ref()
+    }
+
+    pub fn priority(&self) -> Option<Priority> {
+        self.priority
+    }
+
+    pub fn default_action(&self) -> Option<&str> {
+        self.default_action.as_deref()
+    }
+
+    pub fn default_action_target(&self) -> Option<&OwnedValue> {
+        self.default_action_target.as_ref()
+    }
+
+    pub fn buttons(&self) -> &[Button] {
+        self.buttons.as_deref().unwrap_or_default()
+    }
+
+    /// The entries of the notification unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+}
+
+#[derive(Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+#[non_exhaustive]
+pub struct Button {
+    label: Option<String>,
+    action: Option<String>,
+    target: Option<OwnedValue>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(Button {
+    label => "label",
+    action => "action",
+    target => "target",
+});
+
+impl Button {
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    pub fn action(&self) -> Option<&str> {
+        self.action.as_deref()
+    }
+
+    pub fn target(&self) -> Option<&OwnedValue> {
+        self.target.as_ref()
+    }
+
+    /// The entries of the button unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+}
+
+#[async_trait]
+pub trait NotificationImpl: Send + Sync {
+    async fn add_notification(
+        &self,
+        app_id: Option<AppID>,
+        id: &str,
+        notification: Notification,
+    ) -> Result<()>;
+
+    async fn remove_notification(&self, app_id: Option<AppID>, id: &str) -> Result<()>;
+}
+
+pub struct NotificationInterface {
+    imp: Arc<dyn NotificationImpl>,
+    cnx: zbus::Connection,
+    version: u32,
+}
+
+impl_version!(NotificationInterface, 1);
+
+impl NotificationInterface {
+    pub fn new(imp: impl NotificationImpl + 'static, cnx: zbus::Connection) -> Self {
+        Self {
+            imp: Arc::new(imp),
+            cnx,
+            version: Self::VERSION,
+        }
+    }
+
+    /// Emit `ActionInvoked` for the notification `id` of `app_id`.
+    pub async fn invoke_action(
+        &self,
+        app_id: Option<&AppID>,
+        id: &str,
+        action: &str,
+        parameter: Vec<Value<'_>>,
+    ) -> zbus::Result<()> {
+        invoke_action(&self.cnx, app_id, id, action, parameter).await
+    }
+}
+
+async fn invoke_action(
+    cnx: &zbus::Connection,
+    app_id: Option<&AppID>,
+    id: &str,
+    action: &str,
+    parameter: Vec<Value<'_>>,
+) -> zbus::Result<()> {
+    let object_server = cnx.object_server();
+    let iface_ref = object_server
+        .interface::<_, NotificationInterface>(DESKTOP_PATH)
+        .await?;
+    let app_id = app_id.map(|app_id| app_id.as_ref()).unwrap_or_default();
+    NotificationInterface::action_invoked(iface_ref.signal_context(), app_id, id, action, parameter)
+        .await
+}
+
+#[zbus::interface(name = "org.freedesktop.impl.portal.Notification")]
+impl NotificationInterface {
+    #[zbus(property(emits_changed_signal = "const"), name = "version")]
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    async fn add_notification(
+        &self,
+        app_id: MaybeAppID,
+        id: &str,
+        notification: Notification,
+    ) -> Result<()> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Notification::AddNotification");
+
+        let response = self
+            .imp
+            .add_notification(app_id.inner(), id, notification)
+            .await;
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Notification::AddNotification returned {:#?}", response);
+        response
+    }
+
+    async fn remove_notification(&self, app_id: MaybeAppID, id: &str) -> Result<()> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Notification::RemoveNotification");
+
+        let response = self.imp.remove_notification(app_id.inner(), id).await;
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Notification::RemoveNotification returned {:#?}", response);
+        response
+    }
+
+    #[zbus(signal)]
+    async fn action_invoked(
+        signal_ctxt: &SignalContext<'_>,
+        app_id: &str,
+        id: &str,
+        action: &str,
+        parameter: Vec<Value<'_>>,
+    ) -> zbus::Result<()>;
+}
+
+/// The key of the default action in the `org.freedesktop.Notifications`
+/// specification.
+const DEFAULT_ACTION_KEY: &str = "default";
+
+/// A notification forwarded to the notification server.
+#[derive(Debug)]
+struct Forwarded {
+    app_id: Option<AppID>,
+    id: String,
+    /// The portal actions, indexed by the key passed to the server.
+    actions: HashMap<String, (String, Option<OwnedValue>)>,
+}
+
+/// The arguments of an `org.freedesktop.Notifications.Notify` call, except
+/// for `replaces_id` and `expire_timeout`.
+#[derive(Debug)]
+struct Translated {
+    app_icon: String,
+    summary: String,
+    body: String,
+    actions: Vec<String>,
+    hints: HashMap<&'static str, OwnedValue>,
+    forwarded: Forwarded,
+}
+
+impl Translated {
+    /// Translate a portal notification, dropping what the server doesn't
+    /// advertise in its `capabilities`.
+    fn new(
+        app_id: Option<AppID>,
+        id: &str,
+        notification: Notification,
+        capabilities: &[String],
+    ) -> Self {
+        let supports = |capability: &str| capabilities.iter().any(|c| c == capability);
+
+        let app_icon = match notification.icon {
+            Some(Icon::Names(names)) => names.into_iter().next().unwrap_or_default(),
+            Some(Icon::Uri(uri)) if uri.scheme() == "file" => uri.into(),
+            // The classic specification only takes raw pixels, not encoded
+            // images.
+            _ => String::new(),
+        };
+
+        let mut actions = Vec::new();
+        let mut keyed = HashMap::new();
+        if supports("actions") {
+            if let Some(action) = notification.default_action {
+                actions.extend([DEFAULT_ACTION_KEY.to_owned(), String::new()]);
+                keyed.insert(
+                    DEFAULT_ACTION_KEY.to_owned(),
+                    (action, notification.default_action_target),
+                );
+            }
+            for (index, button) in notification.buttons.into_iter().flatten().enumerate() {
+                let Some(action) = button.action else {
+                    continue;
+                };
+                let key = format!("button-{index}");
+                actions.extend([key.clone(), button.label.unwrap_or_default()]);
+                keyed.insert(key, (action, button.target));
+            }
+        }
+
+        let mut hints = HashMap::new();
+        let urgency: u8 = match notification.priority {
+            Some(Priority::Low) => 0,
+            Some(Priority::Urgent) => 2,
+            _ => 1,
+        };
+        hints.insert("urgency", OwnedValue::from(urgency));
+        if let Some(app_id) = &app_id {
+            hints.insert(
+                "desktop-entry",
+                OwnedValue::try_from(Value::from(app_id.as_ref())).unwrap(),
+            );
+        }
+
+        let body = if supports("body") {
+            notification.body.unwrap_or_default()
+        } else {
+            String::new()
+        };
+
+        Self {
+            app_icon,
+            summary: notification.title.unwrap_or_default(),
+            body,
+            actions,
+            hints,
+            forwarded: Forwarded {
+                app_id,
+                id: id.to_owned(),
+                actions: keyed,
+            },
+        }
+    }
+}
+
+/// A [`NotificationImpl`] forwarding the portal notifications to a
+/// notification server implementing the classic
+/// [`org.freedesktop.Notifications`](https://specifications.freedesktop.org/notification-spec/latest/)
+/// specification.
+///
+/// Actions are dropped if the server doesn't advertise the `actions`
+/// capability, and the actions invoked on the server are emitted as
+/// `ActionInvoked` on the [`NotificationInterface`], which has to be served
+/// on the same connection.
+///
+/// ```rust,no_run
+/// use ashpd::backend::{
+///     notification::{NotificationBridge, NotificationInterface},
+///     Backend,
+/// };
+///
+/// async fn run() -> ashpd::Result<()> {
+///     let backend = Backend::new("org.freedesktop.impl.portal.desktop.myportal").await?;
+///     let bridge = NotificationBridge::new(backend.connection()).await?;
+///     let iface = NotificationInterface::new(bridge, backend.connection().clone());
+///     backend.serve(iface).await?;
+///     Ok(())
+/// }
+/// ```
+pub struct NotificationBridge {
+    proxy: zbus::Proxy<'static>,
+    capabilities: Vec<String>,
+    notifications: Arc<Mutex<HashMap<u32, Forwarded>>>,
+}
+
+impl NotificationBridge {
+    /// Connect to the notification server reachable from `cnx`, typically
+    /// the session bus connection of the backend.
+    pub async fn new(cnx: &zbus::Connection) -> zbus::Result<Self> {
+        let proxy = zbus::Proxy::new(
+            cnx,
+            "org.freedesktop.Notifications",
+            "/org/freedesktop/Notifications",
+            "org.freedesktop.Notifications",
+        )
+        .await?;
+        let capabilities: Vec<String> = proxy.call("GetCapabilities", &()).await?;
+        let notifications = Arc::new(Mutex::new(HashMap::<u32, Forwarded>::new()));
+
+        let mut invoked = proxy.receive_signal("ActionInvoked").await?;
+        let mut closed = proxy.receive_signal("NotificationClosed").await?;
+        let forwarded = Arc::clone(&notifications);
+        let signal_cnx = cnx.clone();
+        cnx.executor()
+            .spawn(
+                async move {
+                    loop {
+                        futures_util::select! {
+                            msg = invoked.next() => {
+                                let Some(msg) = msg else { break };
+                                let Ok((server_id, key)) = msg.body().deserialize::<(u32, String)>()
+                                else {
+                                    continue;
+                                };
+                                let action = forwarded.lock().unwrap().get(&server_id).and_then(
+                                    |notification| {
+                                        let (action, target) = notification.actions.get(&key)?;
+                                        let target = target.as_ref().and_then(|t| t.try_clone().ok());
+                                        Some((
+                                            notification.app_id.clone(),
+                                            notification.id.clone(),
+                                            action.clone(),
+                                            target,
+                                        ))
+                                    },
+                                );
+                                if let Some((app_id, id, action, target)) = action {
+                                    let parameter = target.map(Value::from).into_iter().collect();
+                                    let _ = invoke_action(&signal_cnx, app_id.as_ref(), &id, &action, parameter)
+                                        .await;
+                                }
+                            }
+                            msg = closed.next() => {
+                                let Some(msg) = msg else { break };
+                                if let Ok((server_id, _reason)) = msg.body().deserialize::<(u32, u32)>() {
+                                    forwarded.lock().unwrap().remove(&server_id);
+                                }
+                            }
+                        }
+                    }
+                },
+                "forward notification actions",
+            )
+            .detach();
+
+        Ok(Self {
+            proxy,
+            capabilities,
+            notifications,
+        })
+    }
+
+    /// The capabilities advertised by the notification server.
+    pub fn capabilities(&self) -> &[String] {
+        &self.capabilities
+    }
+
+    fn server_id(&self, app_id: Option<&AppID>, id: &str) -> Option<u32> {
+        self.notifications
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|(_, n)| n.app_id.as_ref() == app_id && n.id == id)
+            .map(|(server_id, _)| *server_id)
+    }
+}
+
+#[async_trait]
+impl NotificationImpl for NotificationBridge {
+    async fn add_notification(
+        &self,
+        app_id: Option<AppID>,
+        id: &str,
+        notification: Notification,
+    ) -> Result<()> {
+        let replaces_id = self.server_id(app_id.as_ref(), id).unwrap_or(0);
+        let translated = Translated::new(app_id, id, notification, &self.capabilities);
+        let app_name = translated
+            .forwarded
+            .app_id
+            .as_ref()
+            .map(|app_id| app_id.to_string())
+            .unwrap_or_default();
+        let server_id: u32 = self
+            .proxy
+            .call(
+                "Notify",
+                &(
+                    app_name,
+                    replaces_id,
+                    &translated.app_icon,
+                    &translated.summary,
+                    &translated.body,
+                    &translated.actions,
+                    &translated.hints,
+                    -1i32,
+                ),
+            )
+            .await
+            .map_err(|err| PortalError::Failed(err.to_string()))?;
+        let mut notifications = self.notifications.lock().unwrap();
+        notifications.remove(&replaces_id);
+        notifications.insert(server_id, translated.forwarded);
+        Ok(())
+    }
+
+    async fn remove_notification(&self, app_id: Option<AppID>, id: &str) -> Result<()> {
+        let Some(server_id) = self.server_id(app_id.as_ref(), id) else {
+            return Ok(());
+        };
+        self.notifications.lock().unwrap().remove(&server_id);
+        self.proxy
+            .call::<_, _, ()>("CloseNotification", &(server_id,))
+            .await
+            .map_err(|err| PortalError::Failed(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notification() -> Notification {
+        Notification {
+            title: Some("title".to_owned()),
+            body: Some("body".to_owned()),
+            icon: Some(Icon::with_names(["dialog-information"])),
+            priority: Some(Priority::Urgent),
+            default_action: Some("app.open".to_owned()),
+            default_action_target: None,
+            buttons: Some(vec![Button {
+                label: Some("Reply".to_owned()),
+                action: Some("reply".to_owned()),
+                target: Some(OwnedValue::from(42u32)),
+                extra: HashMap::new(),
+            }]),
+            extra: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn translate() {
+        let app_id = AppID::try_from("org.example.App").ok();
+        let capabilities = ["actions".to_owned(), "body".to_owned()];
+        let translated = Translated::new(app_id, "id", notification(), &capabilities);
+        assert_eq!(translated.app_icon, "dialog-information");
+        assert_eq!(translated.summary, "title");
+        assert_eq!(translated.body, "body");
+        assert_eq!(
+            translated.actions,
+            ["default", "", "button-0", "Reply"].map(ToOwned::to_owned)
+        );
+        assert_eq!(translated.forwarded.actions["button-0"].0, "reply");
+        assert_eq!(u8::try_from(&translated.hints["urgency"]).unwrap(), 2);
+
+        let translated = Translated::new(None, "id", notification(), &[]);
+        assert!(translated.actions.is_empty());
+        assert!(translated.body.is_empty());
+        assert!(!translated.hints.contains_key("desktop-entry"));
+    }
+}
@@ -1,14 +1,264 @@
 use std::{collections::HashMap, sync::Arc};
 
 use async_trait::async_trait;
+use futures_util::{stream, Stream, StreamExt};
 
 use crate::{
+    desktop::location::Accuracy,
     documents::{DocumentID, Permission},
-    zbus::SignalContext,
+    proxy::Proxy,
+    zbus::{names::BusName, SignalContext},
     zvariant::{OwnedValue, Value},
-    AppID, PortalError,
+    AppID, Error, PortalError,
 };
 
+/// The well-known name of xdg-permission-store, also the name of its
+/// interface.
+pub(crate) const PERMISSION_STORE_DESTINATION: &str = "org.freedesktop.impl.portal.PermissionStore";
+pub(crate) const PERMISSION_STORE_PATH: &str = "/org/freedesktop/impl/portal/PermissionStore";
+
+/// The tables of xdg-desktop-portal whose permissions are a single `yes` or
+/// `no`.
+const ALLOWED_TABLES: &[&str] = &[
+    "background",
+    "devices",
+    "gamemode",
+    "notifications",
+    "realtime",
+    "screenshot",
+    "wallpaper",
+];
+
+/// The permissions of an application for an entry of a permission store
+/// table, decoded following the schema of the table used by
+/// xdg-desktop-portal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoredPermissions {
+    /// The permissions of the `documents` table.
+    Document(Vec<Permission>),
+    /// The `yes` or `no` permissions of e.g. the `devices`, `notifications`
+    /// or `background` tables.
+    Allowed(bool),
+    /// The permissions of the `location` table.
+    Location {
+        /// The maximum accuracy granted.
+        accuracy: Accuracy,
+        /// When the location was last used, in seconds since the epoch.
+        last_used: Option<u64>,
+    },
+    /// The permissions of the other tables, or not following the schema of
+    /// their table.
+    Other(Vec<String>),
+}
+
+impl StoredPermissions {
+    /// Decode the `permissions` stored in `table`.
+    pub fn decode(table: &str, permissions: Vec<String>) -> Self {
+        let decoded = match table {
+            "documents" => permissions
+                .iter()
+                .map(|permission| Permission::try_from(Value::from(permission.as_str())).ok())
+                .collect::<Option<Vec<_>>>()
+                .map(Self::Document),
+            "location" => match permissions.as_slice() {
+                [accuracy, rest @ ..] if rest.len() <= 1 => {
+                    location_accuracy(accuracy).map(|accuracy| Self::Location {
+                        accuracy,
+                        last_used: rest.first().and_then(|t| t.parse().ok()),
+                    })
+                }
+                _ => None,
+            },
+            table if ALLOWED_TABLES.contains(&table) => match permissions.as_slice() {
+                [allowed] if allowed == "yes" => Some(Self::Allowed(true)),
+                [allowed] if allowed == "no" => Some(Self::Allowed(false)),
+                _ => None,
+            },
+            _ => None,
+        };
+        decoded.unwrap_or(Self::Other(permissions))
+    }
+}
+
+/// The accuracy stored in the `location` table, e.g. `CITY`.
+pub(crate) fn location_accuracy(permission: &str) -> Option<Accuracy> {
+    match permission {
+        "NONE" => Some(Accuracy::None),
+        "COUNTRY" => Some(Accuracy::Country),
+        "CITY" => Some(Accuracy::City),
+        "NEIGHBORHOOD" => Some(Accuracy::Neighborhood),
+        "STREET" => Some(Accuracy::Street),
+        "EXACT" => Some(Accuracy::Exact),
+        _ => None,
+    }
+}
+
+/// A change of the permissions of an application, see
+/// [`PermissionStoreProxy::watch_table`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermissionChange {
+    id: String,
+    app_id: AppID,
+    deleted: bool,
+    permissions: StoredPermissions,
+}
+
+impl PermissionChange {
+    /// The entry of the table, e.g. `camera` in the `devices` table.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The application whose permissions changed.
+    pub fn app_id(&self) -> &AppID {
+        &self.app_id
+    }
+
+    /// Whether the entry was deleted, along with its permissions.
+    pub fn is_deleted(&self) -> bool {
+        self.deleted
+    }
+
+    /// The permissions of the application.
+    pub fn permissions(&self) -> &StoredPermissions {
+        &self.permissions
+    }
+}
+
+/// A client of the permission store, e.g. for a settings panel listing the
+/// permissions granted to the applications.
+///
+/// ```rust,no_run
+/// use ashpd::backend::permission_store::PermissionStoreProxy;
+/// use futures_util::StreamExt;
+///
+/// async fn run() -> ashpd::Result<()> {
+///     let store = PermissionStoreProxy::new().await?;
+///     let mut changes = store.watch_table("devices").await?.boxed();
+///     while let Some(change) = changes.next().await {
+///         println!(
+///             "{} of {}: {:?}",
+///             change.id(),
+///             change.app_id(),
+///             change.permissions()
+///         );
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct PermissionStoreProxy<'a>(Proxy<'a>);
+
+impl<'a> PermissionStoreProxy<'a> {
+    /// Create a new instance of [`PermissionStoreProxy`] for
+    /// xdg-permission-store on the session bus.
+    pub async fn new() -> Result<PermissionStoreProxy<'a>, Error> {
+        let proxy = Proxy::new(
+            PERMISSION_STORE_DESTINATION,
+            PERMISSION_STORE_PATH,
+            PERMISSION_STORE_DESTINATION,
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
+    /// Create a new instance of [`PermissionStoreProxy`] for the store
+    /// owning `destination`.
+    pub async fn new_with_connection<D>(
+        connection: zbus::Connection,
+        destination: D,
+    ) -> Result<PermissionStoreProxy<'a>, Error>
+    where
+        D: TryInto<BusName<'a>>,
+        D::Error: Into<zbus::Error>,
+    {
+        let proxy = Proxy::new_with_connection(
+            connection,
+            PERMISSION_STORE_DESTINATION,
+            PERMISSION_STORE_PATH,
+            destination,
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
+    /// The permissions of the applications for the entry `id` of `table`.
+    ///
+    /// The applications whose ID is not valid, e.g. the empty ID used for
+    /// the applications running on the host, are left out.
+    ///
+    /// # Specifications
+    ///
+    /// See also [`Lookup`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.impl.portal.PermissionStore.html#org-freedesktop-impl-portal-permissionstore-lookup).
+    #[doc(alias = "Lookup")]
+    pub async fn lookup(
+        &self,
+        table: &str,
+        id: &str,
+    ) -> Result<HashMap<AppID, StoredPermissions>, Error> {
+        let (permissions, _data) = self
+            .0
+            .call::<(HashMap<String, Vec<String>>, OwnedValue)>("Lookup", &(table, id))
+            .await?;
+        Ok(permissions
+            .into_iter()
+            .filter_map(|(app_id, permissions)| {
+                let app_id = app_id.parse::<AppID>().ok()?;
+                Some((app_id, StoredPermissions::decode(table, permissions)))
+            })
+            .collect())
+    }
+
+    /// Listen to the changes of the permissions stored in `table`, one item
+    /// per application of the changed entry.
+    ///
+    /// The applications whose ID is not valid are left out, like with
+    /// [`PermissionStoreProxy::lookup`].
+    ///
+    /// # Specifications
+    ///
+    /// See also [`Changed`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.impl.portal.PermissionStore.html#org-freedesktop-impl-portal-permissionstore-changed).
+    #[doc(alias = "Changed")]
+    pub async fn watch_table(
+        &self,
+        table: &str,
+    ) -> Result<impl Stream<Item = PermissionChange>, Error> {
+        let table = table.to_owned();
+        Ok(self
+            .0
+            .signal_with_args::<(
+                String,
+                String,
+                bool,
+                OwnedValue,
+                HashMap<String, Vec<String>>,
+            )>("Changed", &[(0, &table)])
+            .await?
+            .flat_map(move |(_, id, deleted, _data, permissions)| {
+                let changes = permissions
+                    .into_iter()
+                    .filter_map(|(app_id, permissions)| {
+                        Some(PermissionChange {
+                            id: id.clone(),
+                            app_id: app_id.parse().ok()?,
+                            deleted,
+                            permissions: StoredPermissions::decode(&table, permissions),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                stream::iter(changes)
+            }))
+    }
+}
+
+impl<'a> std::ops::Deref for PermissionStoreProxy<'a> {
+    type Target = zbus::Proxy<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 #[async_trait]
 pub trait PermissionStoreImpl: Send + Sync {
     async fn lookup(
@@ -66,13 +316,17 @@ pub struct PermissionStoreInterface {
     imp: Arc<dyn PermissionStoreImpl>,
     #[allow(dead_code)]
     cnx: zbus::Connection,
+    version: u32,
 }
 
+impl_version!(PermissionStoreInterface, 2);
+
 impl PermissionStoreInterface {
     pub fn new(imp: impl PermissionStoreImpl + 'static, cnx: zbus::Connection) -> Self {
         Self {
             imp: Arc::new(imp),
             cnx,
+            version: Self::VERSION,
         }
     }
 
@@ -104,7 +358,7 @@ impl PermissionStoreInterface {
 impl PermissionStoreInterface {
     #[zbus(property(emits_changed_signal = "const"), name = "version")]
     fn version(&self) -> u32 {
-        2
+        self.version
     }
 
     #[dbus_interface(out_args("permissions", "data"))]
@@ -245,3 +499,115 @@ impl PermissionStoreInterface {
         permissions: HashMap<AppID, Vec<Permission>>,
     ) -> zbus::Result<()>;
 }
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::helpers::p2p_pair;
+
+    #[test]
+    fn decode() {
+        let decode = |table, permissions: &[&str]| {
+            StoredPermissions::decode(table, permissions.iter().map(|p| p.to_string()).collect())
+        };
+        assert_eq!(
+            decode("devices", &["yes"]),
+            StoredPermissions::Allowed(true)
+        );
+        assert_eq!(
+            decode("notifications", &["no"]),
+            StoredPermissions::Allowed(false)
+        );
+        assert_eq!(
+            decode("devices", &["maybe"]),
+            StoredPermissions::Other(vec!["maybe".to_owned()])
+        );
+        assert_eq!(
+            decode("documents", &["read", "grant-permissions"]),
+            StoredPermissions::Document(vec![Permission::Read, Permission::GrantPermissions])
+        );
+        assert_eq!(
+            decode("location", &["CITY", "1700000000"]),
+            StoredPermissions::Location {
+                accuracy: Accuracy::City,
+                last_used: Some(1700000000),
+            }
+        );
+        assert_eq!(
+            decode("location", &["city"]),
+            StoredPermissions::Other(vec!["city".to_owned()])
+        );
+        assert_eq!(
+            decode("unknown", &["yes"]),
+            StoredPermissions::Other(vec!["yes".to_owned()])
+        );
+    }
+
+    #[test]
+    fn location_accuracies() {
+        assert_eq!(location_accuracy("CITY"), Some(Accuracy::City));
+        assert_eq!(location_accuracy("city"), None);
+        for accuracy in [Accuracy::Country, Accuracy::Street, Accuracy::Exact] {
+            let permission = format!("{accuracy:?}").to_uppercase();
+            assert_eq!(location_accuracy(&permission), Some(accuracy));
+        }
+    }
+
+    struct Store;
+
+    #[zbus::interface(name = "org.freedesktop.impl.portal.PermissionStore")]
+    impl Store {
+        #[zbus(property, name = "version")]
+        fn version(&self) -> u32 {
+            2
+        }
+    }
+
+    #[tokio::test]
+    async fn watch_table() {
+        let (server, client) = p2p_pair().await;
+        server
+            .object_server()
+            .at(PERMISSION_STORE_PATH, Store)
+            .await
+            .unwrap();
+        let store = PermissionStoreProxy::new_with_connection(client, PERMISSION_STORE_DESTINATION)
+            .await
+            .unwrap();
+        let mut changes = store.watch_table("devices").await.unwrap().boxed();
+
+        let emit = |table: &'static str, permissions: HashMap<&'static str, Vec<&'static str>>| {
+            let server = server.clone();
+            async move {
+                server
+                    .emit_signal(
+                        None::<()>,
+                        PERMISSION_STORE_PATH,
+                        PERMISSION_STORE_DESTINATION,
+                        "Changed",
+                        &(table, "camera", false, Value::from(0u8), permissions),
+                    )
+                    .await
+                    .unwrap();
+            }
+        };
+        emit(
+            "location",
+            HashMap::from([("org.example.App", vec!["EXACT"])]),
+        )
+        .await;
+        emit(
+            "devices",
+            HashMap::from([("org.example.App", vec!["no"]), ("", vec!["yes"])]),
+        )
+        .await;
+
+        let change = changes.next().await.unwrap();
+        assert_eq!(change.id(), "camera");
+        assert_eq!(change.app_id().as_ref(), "org.example.App");
+        assert!(!change.is_deleted());
+        assert_eq!(change.permissions(), &StoredPermissions::Allowed(false));
+    }
+}
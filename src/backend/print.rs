@@ -1,27 +1,42 @@
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, Seek},
+    os::{fd::OwnedFd, unix::fs::OpenOptionsExt},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use async_trait::async_trait;
 
 use crate::{
     backend::{
-        request::{Request, RequestImpl},
+        request::{Dispatcher, Request, RequestHandle, RequestImpl},
         MaybeAppID, MaybeWindowIdentifier, Result,
     },
     desktop::{
         print::{PageSetup, PreparePrint, Settings},
         request::Response,
+        HandleToken,
     },
-    zvariant::{self, DeserializeDict, OwnedObjectPath},
+    zvariant::{self, OwnedObjectPath, OwnedValue},
     AppID, WindowIdentifierType,
 };
 
-#[derive(DeserializeDict, zvariant::Type)]
+#[derive(zvariant::Type)]
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct PreparePrintOptions {
     modal: Option<bool>,
     accept_label: Option<String>,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(PreparePrintOptions {
+    modal => "modal",
+    accept_label => "accept_label",
+});
+
 impl PreparePrintOptions {
     pub fn is_modal(&self) -> Option<bool> {
         self.modal
@@ -30,15 +45,35 @@ impl PreparePrintOptions {
     pub fn accept_label(&self) -> Option<&str> {
         self.accept_label.as_deref()
     }
+
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+
+    /// Drop the options introduced after `version` of the interface, which
+    /// the implementation didn't declare to support.
+    fn strip_newer(&mut self, version: u32) {
+        if version < 2 {
+            self.accept_label = None;
+        }
+    }
 }
 
-#[derive(DeserializeDict, zvariant::Type)]
+#[derive(zvariant::Type)]
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct PrintOptions {
     modal: Option<bool>,
     token: Option<u32>,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(PrintOptions {
+    modal => "modal",
+    token => "token",
+});
+
 impl PrintOptions {
     pub fn is_modal(&self) -> Option<bool> {
         self.modal
@@ -47,12 +82,85 @@ impl PrintOptions {
     pub fn token(&self) -> Option<u32> {
         self.token
     }
+
+    /// Whether the print wasn't prepared by a `PreparePrint` call, whose token
+    /// would be passed. The backend then shows its print dialog, usually with
+    /// a preview of the document, see [`SpooledDocument`], before printing.
+    pub fn requires_dialog(&self) -> bool {
+        self.token.is_none()
+    }
+
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+}
+
+/// The document received by [`PrintImpl::print`], usually a PDF, spooled to
+/// a temporary file which is removed when dropped.
+///
+/// The print dialogs render a preview of the document before replying, and
+/// most print systems expect a path rather than a file descriptor.
+#[derive(Debug)]
+pub struct SpooledDocument {
+    path: PathBuf,
+    file: File,
+}
+
+impl SpooledDocument {
+    /// Copy the document read from `fd` to a file of the temporary
+    /// directory.
+    pub async fn spool(fd: impl Into<OwnedFd>) -> io::Result<Self> {
+        Self::spool_in(fd, std::env::temp_dir()).await
+    }
+
+    /// Like [`SpooledDocument::spool`], to a file of `dir`, e.g. a directory
+    /// shared with the print system.
+    pub async fn spool_in(fd: impl Into<OwnedFd>, dir: impl AsRef<Path>) -> io::Result<Self> {
+        let mut source = File::from(fd.into());
+        let path = dir
+            .as_ref()
+            .join(format!("ashpd-print-{}.pdf", HandleToken::default()));
+        tokio::task::spawn_blocking(move || {
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .mode(0o600)
+                .open(&path)?;
+            // Removes the file if the copy fails.
+            let mut document = Self { path, file };
+            io::copy(&mut source, &mut document.file)?;
+            document.file.rewind()?;
+            Ok(document)
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    /// The path of the spooled document.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The spooled document, open for reading from its start.
+    pub fn file(&self) -> &File {
+        &self.file
+    }
+}
+
+impl Drop for SpooledDocument {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
 }
 
 #[async_trait]
 pub trait PrintImpl: RequestImpl {
+    #[allow(clippy::too_many_arguments)]
     async fn prepare_print(
         &self,
+        handle: RequestHandle,
         app_id: Option<AppID>,
         parent_window: Option<WindowIdentifierType>,
         title: String,
@@ -61,8 +169,12 @@ pub trait PrintImpl: RequestImpl {
         options: PreparePrintOptions,
     ) -> Result<PreparePrint>;
 
+    /// Print the document read from `fd`, e.g. after spooling it with
+    /// [`SpooledDocument::spool`] to show a preview in the print dialog if
+    /// [`PrintOptions::requires_dialog`].
     async fn print(
         &self,
+        handle: RequestHandle,
         app_id: Option<AppID>,
         parent_window: Option<WindowIdentifierType>,
         title: String,
@@ -74,22 +186,36 @@ pub trait PrintImpl: RequestImpl {
 pub struct PrintInterface {
     imp: Arc<dyn PrintImpl>,
     cnx: zbus::Connection,
+    dispatcher: Dispatcher,
+    version: u32,
 }
 
+impl_version!(PrintInterface, 3);
+
 impl PrintInterface {
     pub fn new(imp: impl PrintImpl + 'static, cnx: zbus::Connection) -> Self {
         Self {
             imp: Arc::new(imp),
             cnx,
+            dispatcher: Dispatcher::default(),
+            version: Self::VERSION,
         }
     }
+
+    /// Limit how many requests are handled at the same time, see
+    /// [`Dispatcher`].
+    #[must_use]
+    pub fn with_dispatcher(mut self, dispatcher: Dispatcher) -> Self {
+        self.dispatcher = dispatcher;
+        self
+    }
 }
 
 #[zbus::interface(name = "org.freedesktop.impl.portal.Print")]
 impl PrintInterface {
     #[dbus_interface(property(emits_changed_signal = "const"), name = "version")]
     fn version(&self) -> u32 {
-        3
+        self.version
     }
 
     #[allow(clippy::too_many_arguments)]
@@ -102,17 +228,20 @@ impl PrintInterface {
         title: String,
         settings: Settings,
         page_setup: PageSetup,
-        options: PreparePrintOptions,
+        mut options: PreparePrintOptions,
     ) -> Result<Response<PreparePrint>> {
         let imp = Arc::clone(&self.imp);
+        options.strip_newer(self.version);
 
         Request::spawn(
             "Print::PreparePrint",
             &self.cnx,
-            handle,
+            &self.dispatcher,
+            handle.clone(),
             Arc::clone(&self.imp),
             async move {
                 imp.prepare_print(
+                    handle.into(),
                     app_id.inner(),
                     window_identifier.inner(),
                     title,
@@ -142,10 +271,12 @@ impl PrintInterface {
         Request::spawn(
             "Print::Print",
             &self.cnx,
-            handle,
+            &self.dispatcher,
+            handle.clone(),
             Arc::clone(&self.imp),
             async move {
                 imp.print(
+                    handle.into(),
                     app_id.inner(),
                     window_identifier.inner(),
                     title,
@@ -158,3 +289,34 @@ impl PrintInterface {
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Write};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn spooled_document() {
+        let dir = std::env::temp_dir().join(format!("ashpd-spool-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("source.pdf");
+        File::create(&source)
+            .unwrap()
+            .write_all(b"%PDF-1.7")
+            .unwrap();
+
+        let document = SpooledDocument::spool_in(File::open(&source).unwrap(), &dir)
+            .await
+            .unwrap();
+        let path = document.path().to_owned();
+        assert!(path.starts_with(&dir));
+        let mut content = String::new();
+        document.file().read_to_string(&mut content).unwrap();
+        assert_eq!(content, "%PDF-1.7");
+
+        drop(document);
+        assert!(!path.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
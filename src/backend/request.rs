@@ -1,15 +1,206 @@
-use std::{boxed::Box, future::Future, sync::Arc};
+use std::{
+    boxed::Box,
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
 
 use async_trait::async_trait;
 use futures_util::future::{abortable, AbortHandle};
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
 use zbus::zvariant::{ObjectPath, OwnedObjectPath};
 
 use crate::desktop::Response;
 
 #[async_trait]
 pub trait RequestImpl: Send + Sync {
-    async fn close(&self);
+    async fn close(&self, handle: RequestHandle);
+}
+
+const REQUEST_PREFIX: &str = "/org/freedesktop/portal/desktop/request/";
+const SESSION_PREFIX: &str = "/org/freedesktop/portal/desktop/session/";
+
+/// The object path of a request or a session, as created by the portal
+/// frontend on behalf of a client.
+///
+/// The path has the form
+/// `/org/freedesktop/portal/desktop/{request,session}/SENDER/TOKEN`, where
+/// `SENDER` is the unique name of the client and `TOKEN` the handle token it
+/// chose. Calls made by the same client share the same sender, which allows
+/// correlating them, e.g. a `SelectSources` followed by a `Start`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RequestHandle(OwnedObjectPath);
+
+impl RequestHandle {
+    pub fn path(&self) -> ObjectPath<'_> {
+        self.0.as_ref()
+    }
+
+    /// The escaped unique name of the client, e.g. `1_42` for `:1.42`.
+    ///
+    /// Returns `None` if the path doesn't follow the expected form.
+    pub fn sender(&self) -> Option<&str> {
+        self.parts().map(|(sender, _)| sender)
+    }
+
+    /// The unique name of the client, e.g. `:1.42`.
+    pub fn unique_name(&self) -> Option<String> {
+        self.sender()
+            .map(|sender| format!(":{}", sender.replace('_', ".")))
+    }
+
+    /// The handle token chosen by the client.
+    ///
+    /// Returns `None` if the path doesn't follow the expected form.
+    pub fn token(&self) -> Option<&str> {
+        self.parts().map(|(_, token)| token)
+    }
+
+    fn parts(&self) -> Option<(&str, &str)> {
+        let path = self.0.as_str();
+        let rest = path
+            .strip_prefix(REQUEST_PREFIX)
+            .or_else(|| path.strip_prefix(SESSION_PREFIX))?;
+        match rest.split_once('/')? {
+            (sender, token) if !sender.is_empty() && !token.is_empty() && !token.contains('/') => {
+                Some((sender, token))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<OwnedObjectPath> for RequestHandle {
+    fn from(path: OwnedObjectPath) -> Self {
+        Self(path)
+    }
+}
+
+impl From<RequestHandle> for OwnedObjectPath {
+    fn from(handle: RequestHandle) -> Self {
+        handle.0
+    }
+}
+
+/// What a [`Dispatcher`] does with a call once its capacity is reached.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Backpressure {
+    /// Wait for one of the requests being handled to complete, the client's
+    /// call stays pending meanwhile.
+    #[default]
+    Wait,
+    /// Fail the call right away with
+    /// `org.freedesktop.DBus.Error.LimitsExceeded`.
+    Reject,
+}
+
+/// Limits how many requests of an interface are handled at the same time.
+///
+/// A dispatcher is unbounded by default. Cloning it shares the capacity,
+/// which allows limiting several interfaces together.
+///
+/// ```rust,no_run
+/// use ashpd::backend::request::{Backpressure, Dispatcher};
+///
+/// let dispatcher = Dispatcher::new(4)
+///     .backpressure(Backpressure::Reject)
+///     .on_queue_depth(|depth| println!("{depth} pending requests"));
+/// ```
+#[derive(Clone, Default)]
+pub struct Dispatcher {
+    permits: Option<Arc<Semaphore>>,
+    backpressure: Backpressure,
+    depth: Arc<AtomicUsize>,
+    on_queue_depth: Option<Arc<dyn Fn(usize) + Send + Sync>>,
+}
+
+impl Dispatcher {
+    /// Handle at most `capacity` requests at the same time, at least one.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            permits: Some(Arc::new(Semaphore::new(capacity.max(1)))),
+            ..Default::default()
+        }
+    }
+
+    /// What to do with calls received once the capacity is reached.
+    ///
+    /// Defaults to [`Backpressure::Wait`].
+    #[must_use]
+    pub fn backpressure(mut self, backpressure: Backpressure) -> Self {
+        self.backpressure = backpressure;
+        self
+    }
+
+    /// Call `callback` with the new queue depth, see
+    /// [`Dispatcher::queue_depth`], whenever it changes.
+    #[must_use]
+    pub fn on_queue_depth(mut self, callback: impl Fn(usize) + Send + Sync + 'static) -> Self {
+        self.on_queue_depth = Some(Arc::new(callback));
+        self
+    }
+
+    /// The number of requests being handled or waiting to be.
+    pub fn queue_depth(&self) -> usize {
+        self.depth.load(Ordering::SeqCst)
+    }
+
+    async fn acquire(&self, _method: &'static str) -> crate::backend::Result<DispatchGuard> {
+        let mut guard = DispatchGuard {
+            dispatcher: self.clone(),
+            _permit: None,
+        };
+        self.update_depth(self.depth.fetch_add(1, Ordering::SeqCst) + 1);
+        let Some(permits) = &self.permits else {
+            return Ok(guard);
+        };
+        let permit = match self.backpressure {
+            Backpressure::Wait => permits.clone().acquire_owned().await.ok(),
+            Backpressure::Reject => permits.clone().try_acquire_owned().ok(),
+        };
+        if permit.is_none() {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("Rejecting {_method}, too many pending requests");
+            return Err(
+                zbus::fdo::Error::LimitsExceeded("Too many pending requests".to_owned()).into(),
+            );
+        }
+        guard._permit = permit;
+        Ok(guard)
+    }
+
+    fn update_depth(&self, depth: usize) {
+        if let Some(callback) = &self.on_queue_depth {
+            callback(depth);
+        }
+    }
+}
+
+impl std::fmt::Debug for Dispatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Dispatcher")
+            .field(
+                "available",
+                &self.permits.as_ref().map(|p| p.available_permits()),
+            )
+            .field("backpressure", &self.backpressure)
+            .field("queue_depth", &self.queue_depth())
+            .finish()
+    }
+}
+
+struct DispatchGuard {
+    dispatcher: Dispatcher,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl Drop for DispatchGuard {
+    fn drop(&mut self) {
+        let dispatcher = &self.dispatcher;
+        dispatcher.update_depth(dispatcher.depth.fetch_sub(1, Ordering::SeqCst) - 1);
+    }
 }
 
 pub struct Request {
@@ -28,6 +219,7 @@ impl Request {
     pub(crate) async fn spawn<T, R>(
         _method: &'static str,
         cnx: &zbus::Connection,
+        dispatcher: &Dispatcher,
         path: OwnedObjectPath,
         imp: Arc<R>,
         callback: impl Future<Output = crate::backend::Result<T>>,
@@ -38,10 +230,12 @@ impl Request {
     {
         #[cfg(feature = "tracing")]
         tracing::debug!("{_method}");
+        let _guard = dispatcher.acquire(_method).await?;
         let (fut, abort_handle) = abortable(callback);
+        let handle = RequestHandle::from(path.clone());
         let close_cb = || {
             tokio::spawn(async move {
-                RequestImpl::close(&*imp).await;
+                RequestImpl::close(&*imp, handle).await;
             });
         };
         let request = Request::new(close_cb, path.clone(), abort_handle, cnx.clone());
@@ -53,7 +247,24 @@ impl Request {
         );
         server.at(&path, request).await?;
 
-        let response = match fut.await {
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+        let result = fut.await;
+        #[cfg(feature = "metrics")]
+        if let Some((interface, member)) = _method.split_once("::") {
+            let outcome = match &result {
+                Ok(Ok(_)) => crate::metrics::Outcome::Success,
+                Ok(Err(_)) => crate::metrics::Outcome::Failed,
+                Err(_) => crate::metrics::Outcome::Cancelled,
+            };
+            crate::metrics::record(
+                &format!("org.freedesktop.impl.portal.{interface}"),
+                member,
+                start.elapsed(),
+                outcome,
+            );
+        }
+        let response = match result {
             Err(_) => Response::cancelled(),
             Ok(response) => Response::ok(response?),
         };
@@ -105,3 +316,73 @@ impl Request {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex as StdMutex;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn dispatcher_backpressure() {
+        let depths = Arc::new(StdMutex::new(Vec::new()));
+        let dispatcher = Dispatcher::new(1)
+            .backpressure(Backpressure::Reject)
+            .on_queue_depth({
+                let depths = depths.clone();
+                move |depth| depths.lock().unwrap().push(depth)
+            });
+        let first = dispatcher.acquire("first").await.unwrap();
+        assert_eq!(dispatcher.queue_depth(), 1);
+        assert!(matches!(
+            dispatcher.acquire("second").await,
+            Err(crate::PortalError::ZBus(zbus::Error::FDO(err)))
+                if matches!(*err, zbus::fdo::Error::LimitsExceeded(_))
+        ));
+        drop(first);
+        assert_eq!(dispatcher.queue_depth(), 0);
+        assert_eq!(*depths.lock().unwrap(), [1, 2, 1, 0]);
+
+        let dispatcher = dispatcher.backpressure(Backpressure::Wait);
+        let first = dispatcher.acquire("first").await.unwrap();
+        let second = tokio::spawn({
+            let dispatcher = dispatcher.clone();
+            async move { dispatcher.acquire("second").await.map(|_| ()) }
+        });
+        tokio::task::yield_now().await;
+        assert_eq!(dispatcher.queue_depth(), 2);
+        drop(first);
+        second.await.unwrap().unwrap();
+        assert_eq!(dispatcher.queue_depth(), 0);
+
+        let unbounded = Dispatcher::default();
+        let _guards =
+            futures_util::future::try_join_all((0..16).map(|_| unbounded.acquire("call")))
+                .await
+                .unwrap();
+        assert_eq!(unbounded.queue_depth(), 16);
+    }
+
+    #[test]
+    fn request_handle() {
+        let handle = RequestHandle::from(
+            OwnedObjectPath::try_from("/org/freedesktop/portal/desktop/request/1_42/ashpd_abc")
+                .unwrap(),
+        );
+        assert_eq!(handle.sender(), Some("1_42"));
+        assert_eq!(handle.unique_name().as_deref(), Some(":1.42"));
+        assert_eq!(handle.token(), Some("ashpd_abc"));
+
+        let handle = RequestHandle::from(
+            OwnedObjectPath::try_from("/org/freedesktop/portal/desktop/session/1_42/ashpd_abc")
+                .unwrap(),
+        );
+        assert_eq!(handle.sender(), Some("1_42"));
+
+        let handle = RequestHandle::from(
+            OwnedObjectPath::try_from("/org/freedesktop/portal/desktop/request/1_42").unwrap(),
+        );
+        assert_eq!(handle.sender(), None);
+        assert_eq!(handle.token(), None);
+    }
+}
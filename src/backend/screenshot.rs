@@ -1,25 +1,39 @@
-use std::sync::Arc;
+use std::{
+    collections::{HashMap, VecDeque},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use async_trait::async_trait;
+use tokio::io::AsyncWriteExt;
 
 use crate::{
     backend::{
-        request::{Request, RequestImpl},
+        request::{Dispatcher, Request, RequestHandle, RequestImpl},
         MaybeAppID, MaybeWindowIdentifier, Result,
     },
     desktop::{request::Response, screenshot::Screenshot as ScreenshotResponse, Color},
-    zvariant::{DeserializeDict, OwnedObjectPath, Type},
-    AppID, WindowIdentifierType,
+    zvariant::{OwnedObjectPath, OwnedValue, Type},
+    AppID, PortalError, WindowIdentifierType,
 };
 
-#[derive(DeserializeDict, Type, Debug)]
+#[derive(Type, Debug)]
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct ScreenshotOptions {
     modal: Option<bool>,
     interactive: Option<bool>,
     permission_store_checked: Option<bool>,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(ScreenshotOptions {
+    modal => "modal",
+    interactive => "interactive",
+    permission_store_checked => "permission_store_checked",
+});
+
 impl ScreenshotOptions {
     pub fn modal(&self) -> Option<bool> {
         self.modal
@@ -32,16 +46,42 @@ impl ScreenshotOptions {
     pub fn permission_store_checked(&self) -> Option<bool> {
         self.permission_store_checked
     }
+
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+
+    /// Drop the options introduced after `version` of the interface, which
+    /// the implementation didn't declare to support.
+    fn strip_newer(&mut self, version: u32) {
+        if version < 2 {
+            self.permission_store_checked = None;
+        }
+    }
 }
 
-#[derive(DeserializeDict, Type, Debug)]
+#[derive(Type, Debug)]
 #[zvariant(signature = "dict")]
-pub struct ColorOptions;
+#[non_exhaustive]
+pub struct ColorOptions {
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(ColorOptions {});
+
+impl ColorOptions {
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+}
 
 #[async_trait]
 pub trait ScreenshotImpl: RequestImpl {
     async fn screenshot(
         &self,
+        handle: RequestHandle,
         app_id: Option<AppID>,
         window_identifier: Option<WindowIdentifierType>,
         options: ScreenshotOptions,
@@ -49,31 +89,407 @@ pub trait ScreenshotImpl: RequestImpl {
 
     async fn pick_color(
         &self,
+        handle: RequestHandle,
         app_id: Option<AppID>,
         window_identifier: Option<WindowIdentifierType>,
         options: ColorOptions,
     ) -> Result<Color>;
 }
 
+/// The directory screenshots are saved into by [`save_screenshot`].
+///
+/// That is `XDG_PICTURES_DIR` as configured in `user-dirs.dirs`, or
+/// `$HOME/Pictures` if it is not set.
+pub async fn pictures_dir() -> Option<PathBuf> {
+    let home = PathBuf::from(std::env::var_os("HOME")?);
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home.join(".config"));
+    tokio::fs::read_to_string(config_dir.join("user-dirs.dirs"))
+        .await
+        .ok()
+        .and_then(|content| pictures_dir_from_user_dirs(&content, &home))
+        .or_else(|| Some(home.join("Pictures")))
+}
+
+fn pictures_dir_from_user_dirs(content: &str, home: &Path) -> Option<PathBuf> {
+    content.lines().find_map(|line| {
+        let value = line.trim().strip_prefix("XDG_PICTURES_DIR=")?;
+        let value = value.trim_matches('"');
+        match value.strip_prefix("$HOME") {
+            Some(relative) => Some(home.join(relative.trim_start_matches('/'))),
+            None if value.starts_with('/') => Some(PathBuf::from(value)),
+            None => None,
+        }
+    })
+}
+
+/// Write the PNG encoded `data` into `directory` and create the response
+/// the frontend expects, a `file://` URI pointing to the saved file.
+///
+/// If `directory` is `None`, [`pictures_dir`] is used. The frontend takes care
+/// of exporting the file through the documents portal for sandboxed apps.
+///
+/// See [`ScreenshotPolicy`] to configure the file names, clean up the old
+/// screenshots or limit how often applications can take them.
+pub async fn save_screenshot(directory: Option<&Path>, data: &[u8]) -> Result<ScreenshotResponse> {
+    let policy = ScreenshotPolicy {
+        directory: directory.map(ToOwned::to_owned),
+        ..Default::default()
+    };
+    policy.save(None, data).await
+}
+
+/// Where and how screenshots are saved, so that backends don't accumulate
+/// them forever in the user's home.
+///
+/// Cloning a policy shares the rate limiting state.
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+///
+/// use ashpd::{
+///     backend::{screenshot::ScreenshotPolicy, Result},
+///     desktop::screenshot::Screenshot,
+///     AppID,
+/// };
+///
+/// async fn screenshot(
+///     policy: &ScreenshotPolicy,
+///     app_id: Option<&AppID>,
+///     take: impl std::future::Future<Output = Vec<u8>>,
+/// ) -> Result<Screenshot> {
+///     policy.check_rate_limit(app_id)?;
+///     let data = take.await;
+///     policy.save(app_id, &data).await
+/// }
+///
+/// let policy = ScreenshotPolicy::new()
+///     .filename_template("Screenshot-{app_id}-{timestamp}")
+///     .max_age(Duration::from_secs(30 * 24 * 60 * 60))
+///     .rate_limit(5, Duration::from_secs(60));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ScreenshotPolicy {
+    directory: Option<PathBuf>,
+    filename_template: String,
+    max_age: Option<Duration>,
+    rate_limit: Option<(usize, Duration)>,
+    requests: Arc<Mutex<HashMap<String, VecDeque<Instant>>>>,
+}
+
+impl Default for ScreenshotPolicy {
+    fn default() -> Self {
+        Self {
+            directory: None,
+            filename_template: "Screenshot-{timestamp}".to_owned(),
+            max_age: None,
+            rate_limit: None,
+            requests: Default::default(),
+        }
+    }
+}
+
+impl ScreenshotPolicy {
+    /// Save into [`pictures_dir`] as `Screenshot-{timestamp}.png`, without
+    /// removing the old screenshots nor limiting the applications.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the directory screenshots are saved into, [`pictures_dir`] by
+    /// default.
+    #[must_use]
+    pub fn directory(mut self, directory: impl Into<PathBuf>) -> Self {
+        self.directory = Some(directory.into());
+        self
+    }
+
+    /// Sets the name of the files, without the `.png` extension.
+    ///
+    /// `{timestamp}` is replaced by the milliseconds since the Unix epoch and
+    /// `{app_id}` by the ID of the application taking the screenshot, empty
+    /// for the host ones.
+    #[must_use]
+    pub fn filename_template(mut self, template: impl Into<String>) -> Self {
+        self.filename_template = template.into();
+        self
+    }
+
+    /// Remove the screenshots older than `max_age` whenever one is saved.
+    ///
+    /// Only the PNG files whose name matches the whole
+    /// [template][`Self::filename_template`] are removed, `{timestamp}`
+    /// standing for digits and `{app_id}` for the characters of an
+    /// application ID. Nothing is removed if the template starts with a
+    /// placeholder.
+    #[must_use]
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// Allow each application to take at most `count` screenshots per
+    /// `period`, see [`Self::check_rate_limit`].
+    #[must_use]
+    pub fn rate_limit(mut self, count: usize, period: Duration) -> Self {
+        self.rate_limit = Some((count, period));
+        self
+    }
+
+    /// Count a screenshot request of `app_id`, failing with
+    /// [`PortalError::NotAllowed`] if it made too many of them recently.
+    ///
+    /// Call it before taking the screenshot, so that the user isn't
+    /// interrupted by the rejected requests.
+    pub fn check_rate_limit(&self, app_id: Option<&AppID>) -> Result<()> {
+        let Some((count, period)) = self.rate_limit else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        let key = app_id.map(ToString::to_string).unwrap_or_default();
+        let mut requests = self.requests.lock().unwrap();
+        requests.retain(|_, times| {
+            while times
+                .front()
+                .is_some_and(|time| now.duration_since(*time) >= period)
+            {
+                times.pop_front();
+            }
+            !times.is_empty()
+        });
+        let times = requests.entry(key).or_default();
+        if times.len() >= count {
+            #[cfg(feature = "tracing")]
+            tracing::debug!("Rejecting screenshot of {app_id:?}, rate limit reached");
+            return Err(PortalError::NotAllowed(
+                "Too many screenshots, try again later".to_owned(),
+            ));
+        }
+        times.push_back(now);
+        Ok(())
+    }
+
+    /// Write the PNG encoded `data` taken for `app_id` and create the
+    /// response the frontend expects, like [`save_screenshot`].
+    pub async fn save(&self, app_id: Option<&AppID>, data: &[u8]) -> Result<ScreenshotResponse> {
+        let directory = self.resolve_directory().await?;
+        let io_error =
+            |e: std::io::Error| PortalError::Failed(format!("Failed to save screenshot: {e}"));
+        tokio::fs::create_dir_all(&directory)
+            .await
+            .map_err(io_error)?;
+        if let Err(_err) = self.clean_up_in(&directory).await {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("Failed to remove the old screenshots: {_err}");
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or_default();
+        let name = self
+            .filename_template
+            .replace("{timestamp}", &timestamp.to_string())
+            .replace("{app_id}", app_id.map(AppID::as_ref).unwrap_or_default());
+        if name.is_empty() || name.contains('/') {
+            return Err(PortalError::InvalidArgument(format!(
+                "Invalid screenshot file name `{name}`"
+            )));
+        }
+        let mut path = directory.join(format!("{name}.png"));
+        let mut n = 1;
+        let mut file = loop {
+            match tokio::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .await
+            {
+                Ok(file) => break file,
+                Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                    path = directory.join(format!("{name} ({n}).png"));
+                    n += 1;
+                }
+                Err(err) => return Err(io_error(err)),
+            }
+        };
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Saving screenshot to {}", path.display());
+        if let Err(err) = async {
+            file.write_all(data).await?;
+            file.flush().await
+        }
+        .await
+        {
+            let _ = tokio::fs::remove_file(&path).await;
+            return Err(io_error(err));
+        }
+
+        let uri = url::Url::from_file_path(&path)
+            .map_err(|_| PortalError::Failed(format!("Invalid path {}", path.display())))?;
+        Ok(ScreenshotResponse::new(uri))
+    }
+
+    /// Remove the screenshots older than the [maximum
+    /// age][`Self::max_age`], returning how many were removed.
+    pub async fn clean_up(&self) -> Result<usize> {
+        let directory = self.resolve_directory().await?;
+        self.clean_up_in(&directory)
+            .await
+            .map_err(|e| PortalError::Failed(format!("Failed to remove the old screenshots: {e}")))
+    }
+
+    async fn resolve_directory(&self) -> Result<PathBuf> {
+        match &self.directory {
+            Some(directory) => Ok(directory.clone()),
+            None => pictures_dir().await.ok_or_else(|| {
+                PortalError::Failed("Failed to find the pictures directory".to_owned())
+            }),
+        }
+    }
+
+    async fn clean_up_in(&self, directory: &Path) -> std::io::Result<usize> {
+        let Some(max_age) = self.max_age else {
+            return Ok(0);
+        };
+        let template = TemplatePart::parse(&self.filename_template);
+        if !matches!(template.first(), Some(TemplatePart::Literal(_))) {
+            return Ok(0);
+        }
+        let mut entries = match tokio::fs::read_dir(directory).await {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err),
+        };
+        let mut removed = 0;
+        while let Some(entry) = entries.next_entry().await? {
+            let name = entry.file_name();
+            let Some(name) = name.to_str() else {
+                continue;
+            };
+            if !TemplatePart::matches_file_name(&template, name) {
+                continue;
+            }
+            let metadata = entry.metadata().await?;
+            let expired = metadata.is_file()
+                && metadata
+                    .modified()?
+                    .elapsed()
+                    .is_ok_and(|age| age > max_age);
+            if expired {
+                tokio::fs::remove_file(entry.path()).await?;
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// A piece of the file name template of a [`ScreenshotPolicy`].
+enum TemplatePart<'a> {
+    Literal(&'a str),
+    Timestamp,
+    AppID,
+}
+
+impl<'a> TemplatePart<'a> {
+    fn parse(mut template: &'a str) -> Vec<Self> {
+        let mut parts = Vec::new();
+        loop {
+            let next = [("{timestamp}", Self::Timestamp), ("{app_id}", Self::AppID)]
+                .into_iter()
+                .filter_map(|(placeholder, part)| {
+                    template
+                        .find(placeholder)
+                        .map(|start| (start, placeholder.len(), part))
+                })
+                .min_by_key(|(start, _, _)| *start);
+            let Some((start, len, part)) = next else {
+                break;
+            };
+            if start > 0 {
+                parts.push(Self::Literal(&template[..start]));
+            }
+            parts.push(part);
+            template = &template[start + len..];
+        }
+        if !template.is_empty() {
+            parts.push(Self::Literal(template));
+        }
+        parts
+    }
+
+    /// Whether `name` is the name of a file saved with `template`, the ` (n)`
+    /// suffix added to the names already taken included.
+    fn matches_file_name(template: &[Self], name: &str) -> bool {
+        let Some(stem) = name.strip_suffix(".png") else {
+            return false;
+        };
+        let deduplicated = stem
+            .strip_suffix(')')
+            .and_then(|stem| stem.rsplit_once(" ("))
+            .filter(|(_, n)| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+            .map(|(stem, _)| stem);
+        Self::matches(template, stem)
+            || deduplicated.is_some_and(|stem| Self::matches(template, stem))
+    }
+
+    fn matches(template: &[Self], name: &str) -> bool {
+        let Some((part, rest)) = template.split_first() else {
+            return name.is_empty();
+        };
+        let (min, max) = match part {
+            Self::Literal(literal) => {
+                return name
+                    .strip_prefix(literal)
+                    .is_some_and(|name| Self::matches(rest, name));
+            }
+            Self::Timestamp => (1, name.bytes().take_while(u8::is_ascii_digit).count()),
+            Self::AppID => (
+                0,
+                name.bytes()
+                    .take_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-' | b'.'))
+                    .count(),
+            ),
+        };
+        (min..=max).any(|len| Self::matches(rest, &name[len..]))
+    }
+}
+
 pub struct ScreenshotInterface {
     imp: Arc<dyn ScreenshotImpl>,
     cnx: zbus::Connection,
+    dispatcher: Dispatcher,
+    version: u32,
 }
 
+impl_version!(ScreenshotInterface, 2);
+
 impl ScreenshotInterface {
     pub fn new(imp: impl ScreenshotImpl + 'static, cnx: zbus::Connection) -> Self {
         Self {
             imp: Arc::new(imp),
             cnx,
+            dispatcher: Dispatcher::default(),
+            version: Self::VERSION,
         }
     }
+
+    /// Limit how many requests are handled at the same time, see
+    /// [`Dispatcher`].
+    #[must_use]
+    pub fn with_dispatcher(mut self, dispatcher: Dispatcher) -> Self {
+        self.dispatcher = dispatcher;
+        self
+    }
 }
 
 #[zbus::interface(name = "org.freedesktop.impl.portal.Screenshot")]
 impl ScreenshotInterface {
     #[zbus(property(emits_changed_signal = "const"), name = "version")]
     fn version(&self) -> u32 {
-        2
+        self.version
     }
 
     #[zbus(name = "Screenshot")]
@@ -83,18 +499,25 @@ impl ScreenshotInterface {
         handle: OwnedObjectPath,
         app_id: MaybeAppID,
         window_identifier: MaybeWindowIdentifier,
-        options: ScreenshotOptions,
+        mut options: ScreenshotOptions,
     ) -> Result<Response<ScreenshotResponse>> {
         let imp = Arc::clone(&self.imp);
+        options.strip_newer(self.version);
 
         Request::spawn(
             "Screenshot::Screenshot",
             &self.cnx,
-            handle,
+            &self.dispatcher,
+            handle.clone(),
             Arc::clone(&self.imp),
             async move {
-                imp.screenshot(app_id.inner(), window_identifier.inner(), options)
-                    .await
+                imp.screenshot(
+                    handle.into(),
+                    app_id.inner(),
+                    window_identifier.inner(),
+                    options,
+                )
+                .await
             },
         )
         .await
@@ -114,13 +537,137 @@ impl ScreenshotInterface {
         Request::spawn(
             "Screenshot::PickColor",
             &self.cnx,
-            handle,
+            &self.dispatcher,
+            handle.clone(),
             Arc::clone(&self.imp),
             async move {
-                imp.pick_color(app_id.inner(), window_identifier.inner(), options)
-                    .await
+                imp.pick_color(
+                    handle.into(),
+                    app_id.inner(),
+                    window_identifier.inner(),
+                    options,
+                )
+                .await
             },
         )
         .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_dirs() {
+        let home = Path::new("/home/user");
+        let content = "# This file is written by xdg-user-dirs-update
+XDG_DESKTOP_DIR=\"$HOME/Desktop\"
+XDG_PICTURES_DIR=\"$HOME/Images\"
+";
+        assert_eq!(
+            pictures_dir_from_user_dirs(content, home),
+            Some(PathBuf::from("/home/user/Images"))
+        );
+        assert_eq!(
+            pictures_dir_from_user_dirs("XDG_PICTURES_DIR=\"/data/pics\"", home),
+            Some(PathBuf::from("/data/pics"))
+        );
+        assert_eq!(
+            pictures_dir_from_user_dirs("XDG_DESKTOP_DIR=\"$HOME/Desktop\"", home),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn save_into_directory() {
+        let dir = std::env::temp_dir().join(format!("ashpd-screenshot-{}", std::process::id()));
+        let first = save_screenshot(Some(&dir), b"png").await.unwrap();
+        let second = save_screenshot(Some(&dir), b"png").await.unwrap();
+        assert_eq!(first.uri().scheme(), "file");
+        assert_ne!(first.uri(), second.uri());
+        let path = PathBuf::from(first.file().unwrap());
+        assert_eq!(std::fs::read(path).unwrap(), b"png");
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn policy_file_names_and_clean_up() {
+        let dir =
+            std::env::temp_dir().join(format!("ashpd-screenshot-policy-{}", std::process::id()));
+        let app_id = AppID::try_from("org.example.App").unwrap();
+        let policy = ScreenshotPolicy::new()
+            .directory(&dir)
+            .filename_template("Shot-{app_id}-{timestamp}");
+        let saved = policy.save(Some(&app_id), b"png").await.unwrap();
+        let name = saved.file().unwrap().path().file_name().unwrap().to_owned();
+        assert!(name.to_str().unwrap().starts_with("Shot-org.example.App-"));
+
+        std::fs::write(dir.join("Holidays.png"), b"png").unwrap();
+        let policy = policy.max_age(Duration::ZERO);
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(policy.clean_up().await.unwrap(), 1);
+        assert!(dir.join("Holidays.png").exists());
+
+        std::fs::write(dir.join("Shot-org.example.App-notes.png"), b"png").unwrap();
+        std::fs::write(dir.join("Shot-org.example.App-1 (2).png"), b"png").unwrap();
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(policy.clean_up().await.unwrap(), 1);
+        assert!(dir.join("Shot-org.example.App-notes.png").exists());
+
+        let policy = policy.filename_template("{timestamp}");
+        assert_eq!(policy.clean_up().await.unwrap(), 0);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn template_file_names() {
+        let template = TemplatePart::parse("Shot-{app_id}-{timestamp}");
+        assert!(TemplatePart::matches_file_name(
+            &template,
+            "Shot-org.example.App-1700000000000.png"
+        ));
+        assert!(TemplatePart::matches_file_name(
+            &template,
+            "Shot--1700000000000 (3).png"
+        ));
+        assert!(!TemplatePart::matches_file_name(
+            &template,
+            "Shot-org.example.App-1700000000000.jpg"
+        ));
+        assert!(!TemplatePart::matches_file_name(
+            &template,
+            "Shot-org.example.App-holidays.png"
+        ));
+        assert!(!TemplatePart::matches_file_name(
+            &template,
+            "Shot-org.example.App-1700000000000-edited.png"
+        ));
+        let template = TemplatePart::parse("Screenshot");
+        assert!(TemplatePart::matches_file_name(
+            &template,
+            "Screenshot (1).png"
+        ));
+        assert!(!TemplatePart::matches_file_name(
+            &template,
+            "Screenshot ().png"
+        ));
+    }
+
+    #[test]
+    fn rate_limit() {
+        let policy = ScreenshotPolicy::new().rate_limit(2, Duration::from_secs(60));
+        let app_id = AppID::try_from("org.example.App").unwrap();
+        assert!(policy.check_rate_limit(Some(&app_id)).is_ok());
+        assert!(policy.clone().check_rate_limit(Some(&app_id)).is_ok());
+        assert!(matches!(
+            policy.check_rate_limit(Some(&app_id)),
+            Err(PortalError::NotAllowed(_))
+        ));
+        assert!(policy.check_rate_limit(None).is_ok());
+
+        let policy = ScreenshotPolicy::new().rate_limit(1, Duration::ZERO);
+        assert!(policy.check_rate_limit(None).is_ok());
+        assert!(policy.check_rate_limit(None).is_ok());
+    }
+}
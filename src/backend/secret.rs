@@ -5,7 +5,7 @@ use zbus::zvariant::{self, OwnedValue};
 
 use crate::{
     backend::{
-        request::{Request, RequestImpl},
+        request::{Dispatcher, Request, RequestHandle, RequestImpl},
         Result,
     },
     desktop::Response,
@@ -16,6 +16,7 @@ use crate::{
 pub trait SecretImpl: RequestImpl {
     async fn retrieve(
         &self,
+        handle: RequestHandle,
         app_id: AppID,
         fd: std::os::fd::OwnedFd,
     ) -> Result<HashMap<String, OwnedValue>>;
@@ -24,22 +25,36 @@ pub trait SecretImpl: RequestImpl {
 pub struct SecretInterface {
     imp: Arc<dyn SecretImpl>,
     cnx: zbus::Connection,
+    dispatcher: Dispatcher,
+    version: u32,
 }
 
+impl_version!(SecretInterface, 1);
+
 impl SecretInterface {
     pub fn new(imp: impl SecretImpl + 'static, cnx: zbus::Connection) -> Self {
         Self {
             imp: Arc::new(imp),
             cnx,
+            dispatcher: Dispatcher::default(),
+            version: Self::VERSION,
         }
     }
+
+    /// Limit how many requests are handled at the same time, see
+    /// [`Dispatcher`].
+    #[must_use]
+    pub fn with_dispatcher(mut self, dispatcher: Dispatcher) -> Self {
+        self.dispatcher = dispatcher;
+        self
+    }
 }
 
 #[zbus::interface(name = "org.freedesktop.impl.portal.Secret")]
 impl SecretInterface {
     #[dbus_interface(property(emits_changed_signal = "const"), name = "version")]
     fn version(&self) -> u32 {
-        1
+        self.version
     }
 
     #[dbus_interface(out_args("response", "results"))]
@@ -55,9 +70,13 @@ impl SecretInterface {
         Request::spawn(
             "Secret::RetrieveSecret",
             &self.cnx,
-            handle,
+            &self.dispatcher,
+            handle.clone(),
             Arc::clone(&self.imp),
-            async move { imp.retrieve(app_id, std::os::fd::OwnedFd::from(fd)).await },
+            async move {
+                imp.retrieve(handle.into(), app_id, std::os::fd::OwnedFd::from(fd))
+                    .await
+            },
         )
         .await
     }
@@ -0,0 +1,573 @@
+//! A [`SecretImpl`] deriving the master secret of each application from a key
+//! stored in the host keyring.
+//!
+//! Sandboxed applications using libsecret, or any other client of the Secret
+//! portal, derive the encryption key of their local keyring from the secret
+//! written by the portal backend. The secret must therefore stay the same
+//! across sessions, and not be readable by other applications.
+//!
+//! A single random key is stored in the default collection of the keyring,
+//! through the [Secret Service](https://specifications.freedesktop.org/secret-service-spec/latest/)
+//! API, and the secret of each application is derived from it with
+//! HMAC-SHA512, keyed by the key and computed over the application ID. No
+//! secret is stored outside of the keyring.
+//!
+//! ```rust,no_run
+//! use ashpd::backend::{secret::SecretInterface, secret_store::KeyringSecretStore, Backend};
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     let backend = Backend::new("org.freedesktop.impl.portal.desktop.myportal").await?;
+//!     let store = KeyringSecretStore::new(backend.connection().clone());
+//!     backend
+//!         .serve(SecretInterface::new(store, backend.connection().clone()))
+//!         .await?;
+//!     Ok(())
+//! }
+//! ```
+
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha512;
+use tokio::{io::AsyncWriteExt, sync::Mutex};
+
+use crate::{
+    backend::{
+        request::{RequestHandle, RequestImpl},
+        secret::SecretImpl,
+        Result,
+    },
+    zbus::proxy::CacheProperties,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Type, Value},
+    AppID, PortalError,
+};
+
+/// The length of the key and of the derived secrets, the same as the one of
+/// the secrets of gnome-keyring so that the keyrings of libsecret can be moved
+/// between both.
+const SECRET_LEN: usize = 64;
+
+const SECRET_SERVICE_DESTINATION: &str = "org.freedesktop.secrets";
+const SECRET_SERVICE_PATH: &str = "/org/freedesktop/secrets";
+const SECRET_SERVICE_INTERFACE: &str = "org.freedesktop.Secret.Service";
+
+/// The schema of the keyring item holding the key.
+const SCHEMA: &str = "org.freedesktop.ashpd.SecretPortal";
+
+/// A secret of the Secret Service API, sent in plain text over the session
+/// bus.
+#[derive(Serialize, Deserialize, Type)]
+struct ServiceSecret {
+    session: OwnedObjectPath,
+    parameters: Vec<u8>,
+    value: Vec<u8>,
+    content_type: String,
+}
+
+impl Drop for ServiceSecret {
+    fn drop(&mut self) {
+        crate::helpers::zeroize(&mut self.value);
+    }
+}
+
+/// Derives the secret of each application from a key stored in the default
+/// collection of the keyring, created the first time a secret is requested.
+///
+/// If the keyring holds several keys, e.g. created by another instance of the
+/// backend, the oldest one is used and the others are deleted.
+#[derive(Debug, Clone)]
+pub struct KeyringSecretStore {
+    cnx: zbus::Connection,
+    label: String,
+    // Serializes the lookup and creation of the key, so that concurrent
+    // requests don't each create one.
+    lock: Arc<Mutex<()>>,
+}
+
+impl KeyringSecretStore {
+    /// Reach the keyring through `cnx`, a connection to the session bus.
+    pub fn new(cnx: zbus::Connection) -> Self {
+        Self {
+            cnx,
+            label: "Secret portal key".to_owned(),
+            lock: Default::default(),
+        }
+    }
+
+    /// Sets the label of the keyring item holding the key, shown by the
+    /// keyring managers like Seahorse.
+    #[must_use]
+    pub fn label(mut self, label: impl Into<String>) -> Self {
+        self.label = label.into();
+        self
+    }
+
+    /// The secret of `app_id`, creating the key in the keyring if it doesn't
+    /// exist yet.
+    pub async fn secret(&self, app_id: &AppID) -> crate::Result<Vec<u8>> {
+        let mut key = self.key().await?;
+        let secret = derive_secret(&key, app_id);
+        crate::helpers::zeroize(&mut key);
+        Ok(secret)
+    }
+
+    async fn service(&self) -> zbus::Result<zbus::Proxy<'static>> {
+        self.proxy(SECRET_SERVICE_PATH, SECRET_SERVICE_INTERFACE)
+            .await
+    }
+
+    async fn proxy(
+        &self,
+        path: impl TryInto<ObjectPath<'static>, Error = impl Into<zbus::Error>>,
+        interface: &'static str,
+    ) -> zbus::Result<zbus::Proxy<'static>> {
+        zbus::proxy::Builder::new(&self.cnx)
+            .destination(SECRET_SERVICE_DESTINATION)?
+            .path(path)?
+            .interface(interface)?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await
+    }
+
+    /// The key, read from the keyring or created.
+    async fn key(&self) -> crate::Result<Vec<u8>> {
+        let _guard = self.lock.lock().await;
+        let service = self.service().await?;
+        let (_output, session) = service
+            .call::<_, _, (OwnedValue, OwnedObjectPath)>("OpenSession", &("plain", Value::from("")))
+            .await?;
+        let key = self.read_or_create_key(&service, &session).await;
+        let _ = self
+            .cnx
+            .call_method(
+                Some(SECRET_SERVICE_DESTINATION),
+                &session,
+                Some("org.freedesktop.Secret.Session"),
+                "Close",
+                &(),
+            )
+            .await;
+        key
+    }
+
+    async fn read_or_create_key(
+        &self,
+        service: &zbus::Proxy<'static>,
+        session: &OwnedObjectPath,
+    ) -> crate::Result<Vec<u8>> {
+        let attributes = HashMap::from([("xdg:schema", SCHEMA)]);
+        let (mut items, locked) = service
+            .call::<_, _, (Vec<OwnedObjectPath>, Vec<OwnedObjectPath>)>(
+                "SearchItems",
+                &(&attributes,),
+            )
+            .await?;
+        if items.is_empty() && !locked.is_empty() {
+            let (unlocked, prompt) = service
+                .call::<_, _, (Vec<OwnedObjectPath>, OwnedObjectPath)>("Unlock", &(&locked,))
+                .await?;
+            items = match self.prompt(prompt).await? {
+                Some(value) => {
+                    Vec::<OwnedObjectPath>::try_from(value).map_err(zbus::Error::Variant)?
+                }
+                None => unlocked,
+            };
+        }
+
+        if items.len() > 1 {
+            items = self.oldest_first(items).await?;
+        }
+        if let Some((item, extra)) = items.split_first() {
+            let mut secrets = service
+                .call::<_, _, HashMap<OwnedObjectPath, ServiceSecret>>(
+                    "GetSecrets",
+                    &(std::slice::from_ref(item), session),
+                )
+                .await?;
+            let secret = secrets.remove(item).ok_or_else(|| {
+                PortalError::NotFound(format!("No secret for the keyring item `{item}`"))
+            })?;
+            // A truncated key would derive weak secrets.
+            if secret.value.len() != SECRET_LEN {
+                return Err(PortalError::Failed(format!(
+                    "The key stored in the keyring item `{item}` is {} bytes long instead of {SECRET_LEN}",
+                    secret.value.len()
+                ))
+                .into());
+            }
+            for item in extra {
+                self.delete(item).await;
+            }
+            return Ok(secret.value.clone());
+        }
+
+        let collection = service
+            .call::<_, _, OwnedObjectPath>("ReadAlias", &("default",))
+            .await?;
+        if collection.as_str() == "/" {
+            return Err(
+                PortalError::NotFound("The keyring has no default collection".to_owned()).into(),
+            );
+        }
+        let mut key = vec![0; SECRET_LEN];
+        fill_random(&mut key)?;
+        let secret = ServiceSecret {
+            session: session.clone(),
+            parameters: Vec::new(),
+            value: key.clone(),
+            content_type: "application/octet-stream".to_owned(),
+        };
+        let properties = HashMap::from([
+            (
+                "org.freedesktop.Secret.Item.Label",
+                Value::from(self.label.as_str()),
+            ),
+            (
+                "org.freedesktop.Secret.Item.Attributes",
+                Value::from(attributes),
+            ),
+        ]);
+        let collection = self
+            .proxy(collection, "org.freedesktop.Secret.Collection")
+            .await?;
+        let (_item, prompt) = collection
+            .call::<_, _, (OwnedObjectPath, OwnedObjectPath)>(
+                "CreateItem",
+                &(properties, secret, false),
+            )
+            .await?;
+        self.prompt(prompt).await?;
+        Ok(key)
+    }
+
+    /// Sort `items` by creation time, the path breaking ties.
+    async fn oldest_first(
+        &self,
+        items: Vec<OwnedObjectPath>,
+    ) -> crate::Result<Vec<OwnedObjectPath>> {
+        let mut sorted = Vec::with_capacity(items.len());
+        for item in items {
+            let created = self
+                .proxy(item.clone(), "org.freedesktop.Secret.Item")
+                .await?
+                .get_property::<u64>("Created")
+                .await?;
+            sorted.push((created, item));
+        }
+        sorted.sort_by(|(a, a_item), (b, b_item)| {
+            a.cmp(b).then_with(|| a_item.as_str().cmp(b_item.as_str()))
+        });
+        Ok(sorted.into_iter().map(|(_created, item)| item).collect())
+    }
+
+    /// Delete the duplicated key `item`, leaving it in place on failure.
+    async fn delete(&self, item: &OwnedObjectPath) {
+        let deleted = async {
+            let prompt = self
+                .proxy(item.clone(), "org.freedesktop.Secret.Item")
+                .await?
+                .call::<_, _, OwnedObjectPath>("Delete", &())
+                .await?;
+            self.prompt(prompt).await
+        }
+        .await;
+        #[cfg(feature = "tracing")]
+        if let Err(err) = deleted {
+            tracing::warn!("Failed to delete the duplicated key `{item}`: {err}");
+        }
+        #[cfg(not(feature = "tracing"))]
+        let _ = deleted;
+    }
+
+    /// Show the prompt at `path` if any, returning its result.
+    async fn prompt(&self, path: OwnedObjectPath) -> crate::Result<Option<OwnedValue>> {
+        if path.as_str() == "/" {
+            return Ok(None);
+        }
+        let prompt = self.proxy(path, "org.freedesktop.Secret.Prompt").await?;
+        let mut completed = prompt.receive_signal("Completed").await?;
+        prompt.call::<_, _, ()>("Prompt", &("",)).await?;
+        let message = completed.next().await.ok_or(crate::Error::NoResponse)?;
+        let (dismissed, result) = message.body().deserialize::<(bool, OwnedValue)>()?;
+        if dismissed {
+            return Err(
+                PortalError::Cancelled("The keyring prompt was dismissed".to_owned()).into(),
+            );
+        }
+        Ok(Some(result))
+    }
+}
+
+/// Fill `bytes` from the random source of the kernel.
+fn fill_random(bytes: &mut [u8]) -> std::io::Result<()> {
+    let mut filled = 0;
+    while filled < bytes.len() {
+        let remaining = &mut bytes[filled..];
+        // SAFETY: `remaining` is valid for writes of its length.
+        let read = unsafe { libc::getrandom(remaining.as_mut_ptr().cast(), remaining.len(), 0) };
+        if read < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() != std::io::ErrorKind::Interrupted {
+                return Err(err);
+            }
+        } else {
+            filled += read as usize;
+        }
+    }
+    Ok(())
+}
+
+/// The secret of `app_id`, derived from `key`.
+fn derive_secret(key: &[u8], app_id: &AppID) -> Vec<u8> {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(app_id.as_ref().as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[async_trait]
+impl RequestImpl for KeyringSecretStore {
+    async fn close(&self, _handle: RequestHandle) {}
+}
+
+#[async_trait]
+impl SecretImpl for KeyringSecretStore {
+    async fn retrieve(
+        &self,
+        _handle: RequestHandle,
+        app_id: AppID,
+        fd: std::os::fd::OwnedFd,
+    ) -> Result<HashMap<String, OwnedValue>> {
+        let mut secret = self.secret(&app_id).await.map_err(|err| {
+            PortalError::Failed(format!("Failed to retrieve the secret of {app_id}: {err}"))
+        })?;
+        let mut file = tokio::fs::File::from_std(std::fs::File::from(fd));
+        let written = async {
+            file.write_all(&secret).await?;
+            file.flush().await
+        }
+        .await;
+        crate::helpers::zeroize(&mut secret);
+        written.map_err(|err| PortalError::Failed(format!("Failed to write the secret: {err}")))?;
+        Ok(HashMap::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read, os::unix::net::UnixStream, sync::Mutex};
+
+    use zbus::zvariant::OwnedObjectPath;
+
+    use super::*;
+
+    const COLLECTION_PATH: &str = "/org/freedesktop/secrets/collection/login";
+    const SESSION_PATH: &str = "/org/freedesktop/secrets/session/1";
+    const ITEMS: usize = 4;
+
+    fn item_path(index: usize) -> OwnedObjectPath {
+        OwnedObjectPath::try_from(format!("{COLLECTION_PATH}/{index}")).unwrap()
+    }
+
+    fn item_index(path: &OwnedObjectPath) -> usize {
+        path.rsplit('/').next().unwrap().parse().unwrap()
+    }
+
+    /// The creation time and key of an item, `None` once deleted.
+    type StoredItem = Option<(u64, Vec<u8>)>;
+
+    #[derive(Default)]
+    struct Keyring {
+        items: Mutex<Vec<StoredItem>>,
+    }
+
+    impl Keyring {
+        fn keys(&self) -> Vec<Vec<u8>> {
+            let items = self.items.lock().unwrap();
+            items.iter().flatten().map(|(_, key)| key.clone()).collect()
+        }
+    }
+
+    struct Service(Arc<Keyring>);
+
+    #[zbus::interface(name = "org.freedesktop.Secret.Service")]
+    impl Service {
+        fn open_session(
+            &self,
+            _algorithm: &str,
+            _input: Value<'_>,
+        ) -> (OwnedValue, OwnedObjectPath) {
+            (
+                OwnedValue::try_from(Value::from("")).unwrap(),
+                OwnedObjectPath::try_from(SESSION_PATH).unwrap(),
+            )
+        }
+
+        async fn search_items(
+            &self,
+            attributes: HashMap<String, String>,
+        ) -> (Vec<OwnedObjectPath>, Vec<OwnedObjectPath>) {
+            assert_eq!(attributes["xdg:schema"], SCHEMA);
+            // Let concurrent requests interleave.
+            tokio::task::yield_now().await;
+            let items = self.0.items.lock().unwrap();
+            // Newest first, the keyring doesn't guarantee any order.
+            let items = (0..items.len())
+                .rev()
+                .filter(|index| items[*index].is_some())
+                .map(item_path)
+                .collect();
+            (items, Vec::new())
+        }
+
+        fn get_secrets(
+            &self,
+            items: Vec<OwnedObjectPath>,
+            session: OwnedObjectPath,
+        ) -> HashMap<OwnedObjectPath, ServiceSecret> {
+            let keys = self.0.items.lock().unwrap();
+            items
+                .into_iter()
+                .map(|item| {
+                    let (_, key) = keys[item_index(&item)].clone().unwrap();
+                    let secret = ServiceSecret {
+                        session: session.clone(),
+                        parameters: Vec::new(),
+                        value: key,
+                        content_type: "application/octet-stream".to_owned(),
+                    };
+                    (item, secret)
+                })
+                .collect()
+        }
+
+        fn read_alias(&self, name: &str) -> OwnedObjectPath {
+            assert_eq!(name, "default");
+            OwnedObjectPath::try_from(COLLECTION_PATH).unwrap()
+        }
+    }
+
+    struct Collection(Arc<Keyring>);
+
+    #[zbus::interface(name = "org.freedesktop.Secret.Collection")]
+    impl Collection {
+        fn create_item(
+            &self,
+            _properties: HashMap<String, OwnedValue>,
+            secret: ServiceSecret,
+            _replace: bool,
+        ) -> (OwnedObjectPath, OwnedObjectPath) {
+            let mut items = self.0.items.lock().unwrap();
+            let created = items.len() as u64;
+            items.push(Some((created, secret.value.clone())));
+            (
+                item_path(items.len() - 1),
+                OwnedObjectPath::try_from("/").unwrap(),
+            )
+        }
+    }
+
+    struct Item(Arc<Keyring>, usize);
+
+    #[zbus::interface(name = "org.freedesktop.Secret.Item")]
+    impl Item {
+        fn delete(&self) -> OwnedObjectPath {
+            self.0.items.lock().unwrap()[self.1] = None;
+            OwnedObjectPath::try_from("/").unwrap()
+        }
+
+        #[zbus(property)]
+        fn created(&self) -> u64 {
+            self.0.items.lock().unwrap()[self.1].as_ref().unwrap().0
+        }
+    }
+
+    /// The keyring, the store using it, and the server connection serving it,
+    /// which has to be kept alive.
+    async fn keyring_store() -> (Arc<Keyring>, KeyringSecretStore, zbus::Connection) {
+        let keyring = Arc::new(Keyring::default());
+        let (server, client) = crate::helpers::p2p_pair().await;
+        server
+            .object_server()
+            .at(SECRET_SERVICE_PATH, Service(keyring.clone()))
+            .await
+            .unwrap();
+        server
+            .object_server()
+            .at(COLLECTION_PATH, Collection(keyring.clone()))
+            .await
+            .unwrap();
+        for index in 0..ITEMS {
+            server
+                .object_server()
+                .at(item_path(index), Item(keyring.clone(), index))
+                .await
+                .unwrap();
+        }
+        (keyring, KeyringSecretStore::new(client), server)
+    }
+
+    #[tokio::test]
+    async fn retrieve() {
+        let (keyring, store, _server) = keyring_store().await;
+        let handle = RequestHandle::from(
+            OwnedObjectPath::try_from("/org/freedesktop/portal/desktop/request/1_1/t").unwrap(),
+        );
+        let mut secrets = Vec::new();
+        for app_id in ["org.example.App", "org.example.App", "org.example.Other"] {
+            let (mut reader, writer) = UnixStream::pair().unwrap();
+            store
+                .retrieve(handle.clone(), app_id.parse().unwrap(), writer.into())
+                .await
+                .unwrap();
+            let mut secret = Vec::new();
+            reader.read_to_end(&mut secret).unwrap();
+            assert_eq!(secret.len(), SECRET_LEN);
+            secrets.push(secret);
+        }
+        assert_eq!(secrets[0], secrets[1]);
+        assert_ne!(secrets[0], secrets[2]);
+        let key = keyring.keys().remove(0);
+        assert_eq!(
+            secrets[0],
+            derive_secret(&key, &"org.example.App".parse().unwrap())
+        );
+
+        // A truncated key is rejected.
+        keyring.items.lock().unwrap()[0] = Some((0, key[..SECRET_LEN / 2].to_vec()));
+        assert!(store
+            .secret(&"org.example.App".parse().unwrap())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn concurrent_first_use() {
+        let (keyring, store, _server) = keyring_store().await;
+        let app_id = "org.example.App".parse().unwrap();
+        let (first, second) = tokio::join!(store.secret(&app_id), store.secret(&app_id));
+        assert_eq!(first.unwrap(), second.unwrap());
+        assert_eq!(keyring.keys().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn duplicated_keys() {
+        let (keyring, store, _server) = keyring_store().await;
+        let oldest = vec![1; SECRET_LEN];
+        *keyring.items.lock().unwrap() = vec![
+            Some((20, vec![2; SECRET_LEN])),
+            Some((10, oldest.clone())),
+            Some((30, vec![3; SECRET_LEN])),
+        ];
+
+        let app_id = "org.example.App".parse().unwrap();
+        assert_eq!(
+            store.secret(&app_id).await.unwrap(),
+            derive_secret(&oldest, &app_id)
+        );
+        assert_eq!(keyring.keys(), vec![oldest]);
+    }
+}
@@ -0,0 +1,90 @@
+use std::boxed::Box;
+
+use tokio::sync::Mutex;
+use zbus::{
+    zvariant::{ObjectPath, OwnedObjectPath},
+    SignalContext,
+};
+
+/// A session served by a backend on behalf of the portal frontend, at the
+/// `session_handle` it passed when creating the session.
+///
+/// The frontend closes it when the client closes its own session or goes
+/// away. The backend can also close it, e.g. when the device it is using
+/// disappears, which is notified to the frontend with the `Closed` signal.
+pub struct Session {
+    close_cb: Mutex<Option<Box<dyn FnOnce() + Send + Sync>>>,
+    path: OwnedObjectPath,
+}
+
+impl Session {
+    pub fn path(&self) -> ObjectPath<'_> {
+        self.path.as_ref()
+    }
+
+    /// Serve a session at `path`, calling `close_cb` once it is closed
+    /// either by the frontend or with [`Session::close_at`].
+    pub(crate) async fn serve(
+        cnx: &zbus::Connection,
+        path: OwnedObjectPath,
+        close_cb: impl FnOnce() + Send + Sync + 'static,
+    ) -> zbus::Result<bool> {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            "Serving `org.freedesktop.impl.portal.Session` at {:?}",
+            path.as_str()
+        );
+        let session = Self {
+            close_cb: Mutex::new(Some(Box::new(close_cb))),
+            path: path.clone(),
+        };
+        cnx.object_server().at(&path, session).await
+    }
+
+    /// Close the session at `path` from the backend side, notifying the
+    /// frontend with the `Closed` signal.
+    ///
+    /// Returns `false` if there is no session at `path`.
+    pub(crate) async fn close_at(
+        cnx: &zbus::Connection,
+        path: ObjectPath<'_>,
+    ) -> zbus::Result<bool> {
+        let server = cnx.object_server();
+        let Ok(iface_ref) = server.interface::<_, Self>(&path).await else {
+            return Ok(false);
+        };
+        let close_cb = iface_ref.get().await.close_cb.lock().await.take();
+        if let Some(close_cb) = close_cb {
+            close_cb();
+        }
+        Self::closed(iface_ref.signal_context()).await?;
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Releasing session {:?}", path.as_str());
+        server.remove::<Self, _>(&path).await
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.impl.portal.Session")]
+impl Session {
+    #[zbus(property(emits_changed_signal = "const"), name = "version")]
+    fn version(&self) -> u32 {
+        1
+    }
+
+    async fn close(
+        &self,
+        #[zbus(object_server)] server: &zbus::ObjectServer,
+    ) -> zbus::fdo::Result<()> {
+        if let Some(close_cb) = self.close_cb.lock().await.take() {
+            close_cb();
+        }
+
+        #[cfg(feature = "tracing")]
+        tracing::debug!("Releasing session {:?}", self.path.as_str());
+        server.remove::<Self, _>(&self.path).await?;
+        Ok(())
+    }
+
+    #[zbus(signal)]
+    async fn closed(signal_ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+}
@@ -1,12 +1,11 @@
-use std::{collections::HashMap, sync::Arc};
-
-use async_trait::async_trait;
+use std::{collections::HashMap, future::Future, sync::Arc};
 
 use crate::{
     desktop::{
         settings::{
-            ColorScheme, Contrast, Namespace, ACCENT_COLOR_SCHEME_KEY, APPEARANCE_NAMESPACE,
-            COLOR_SCHEME_KEY, CONTRAST_KEY,
+            namespace_matches, ColorScheme, Contrast, Namespace, ReducedMotion,
+            ACCENT_COLOR_SCHEME_KEY, APPEARANCE_NAMESPACE, COLOR_SCHEME_KEY, CONTRAST_KEY,
+            REDUCED_MOTION_KEY,
         },
         Color,
     },
@@ -15,26 +14,80 @@ use crate::{
     PortalError,
 };
 
-#[async_trait]
 pub trait SettingsImpl: Send + Sync {
-    async fn read_all(
+    /// The settings of the requested `namespaces`.
+    ///
+    /// Return whatever subset of them the implementation knows about: the
+    /// result is filtered with [`filter_namespaces`] before being sent, and
+    /// [`PortalError::NotFound`] is replied as an empty map.
+    fn read_all(
         &self,
         namespaces: Vec<String>,
-    ) -> Result<HashMap<String, Namespace>, PortalError>;
+    ) -> impl Future<Output = Result<HashMap<String, Namespace>, PortalError>> + Send;
+
+    /// The value of a single setting, or [`PortalError::NotFound`] if the
+    /// implementation doesn't provide it.
+    ///
+    /// Looks the setting up in [`SettingsImpl::read_all`] by default.
+    fn read(
+        &self,
+        namespace: &str,
+        key: &str,
+    ) -> impl Future<Output = Result<OwnedValue, PortalError>> + Send {
+        async move {
+            self.read_all(vec![namespace.to_owned()])
+                .await?
+                .remove(namespace)
+                .and_then(|mut settings| settings.remove(key))
+                .ok_or_else(|| {
+                    PortalError::NotFound(format!("Requested setting {namespace}.{key} not found"))
+                })
+        }
+    }
+}
 
-    async fn read(&self, namespace: &str, key: &str) -> Result<OwnedValue, PortalError>;
+/// Keep the namespaces of `settings` requested by a `ReadAll` call, matched
+/// with [`namespace_matches`].
+pub fn filter_namespaces(
+    namespaces: &[impl AsRef<str>],
+    settings: HashMap<String, Namespace>,
+) -> HashMap<String, Namespace> {
+    settings
+        .into_iter()
+        .filter(|(namespace, _)| namespace_matches(namespaces, namespace))
+        .collect()
 }
 
-pub struct SettingsInterface {
-    imp: Arc<dyn SettingsImpl>,
+/// Serves `org.freedesktop.impl.portal.Settings`.
+///
+/// Unlike the request based portals, `Read` and `ReadAll` calls are dispatched
+/// to the [`SettingsImpl`] directly from the method handler, without creating
+/// a request object or going through a task.
+pub struct SettingsInterface<T> {
+    imp: Arc<T>,
     cnx: zbus::Connection,
+    version: u32,
 }
 
-impl SettingsInterface {
-    pub fn new(imp: impl SettingsImpl + 'static, cnx: zbus::Connection) -> Self {
+impl_version!(SettingsInterface<T: SettingsImpl>, 2);
+
+impl<T: SettingsImpl + 'static> SettingsInterface<T> {
+    pub fn new(imp: T, cnx: zbus::Connection) -> Self {
         Self {
             imp: Arc::new(imp),
             cnx,
+            version: Self::VERSION,
+        }
+    }
+
+    /// Like [`SettingsInterface::new`], but shares an implementation the
+    /// caller keeps a handle to, so it can update the settings it serves
+    /// in-place.
+    pub fn new_shared(imp: Arc<T>, cnx: zbus::Connection) -> Self {
+        Self {
+            imp,
+            cnx,
+            version: Self::VERSION,
         }
     }
 
@@ -55,6 +108,15 @@ impl SettingsInterface {
         .await
     }
 
+    pub async fn reduced_motion_changed(&self, reduced_motion: ReducedMotion) -> zbus::Result<()> {
+        self.changed(
+            APPEARANCE_NAMESPACE,
+            REDUCED_MOTION_KEY,
+            OwnedValue::from(reduced_motion).into(),
+        )
+        .await
+    }
+
     pub async fn accent_color_changed(&self, color: Color) -> zbus::Result<()> {
         self.changed(
             APPEARANCE_NAMESPACE,
@@ -75,10 +137,10 @@ impl SettingsInterface {
 }
 
 #[zbus::interface(name = "org.freedesktop.impl.portal.Settings")]
-impl SettingsInterface {
+impl<T: SettingsImpl + 'static> SettingsInterface<T> {
     #[zbus(property(emits_changed_signal = "const"), name = "version")]
     fn version(&self) -> u32 {
-        2
+        self.version
     }
 
     #[dbus_interface(out_args("value"))]
@@ -89,7 +151,11 @@ impl SettingsInterface {
         #[cfg(feature = "tracing")]
         tracing::debug!("Settings::ReadAll");
 
-        let response = self.imp.read_all(namespaces).await;
+        let response = match self.imp.read_all(namespaces.clone()).await {
+            Ok(settings) => Ok(filter_namespaces(&namespaces, settings)),
+            Err(PortalError::NotFound(_)) => Ok(HashMap::new()),
+            Err(err) => Err(err),
+        };
 
         #[cfg(feature = "tracing")]
         tracing::debug!("Settings::ReadAll returned {:#?}", response);
@@ -116,3 +182,103 @@ impl SettingsInterface {
         value: Value<'_>,
     ) -> zbus::Result<()>;
 }
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+    use crate::{
+        backend::Result,
+        helpers::{p2p_pair, serve_p2p},
+    };
+
+    struct Settings(AtomicU32);
+
+    impl SettingsImpl for Settings {
+        async fn read_all(
+            &self,
+            _namespaces: Vec<String>,
+        ) -> Result<std::collections::HashMap<String, Namespace>> {
+            unreachable!()
+        }
+
+        async fn read(&self, _namespace: &str, _key: &str) -> Result<OwnedValue> {
+            Ok(self.0.load(Ordering::SeqCst).into())
+        }
+    }
+
+    struct PartialSettings;
+
+    impl SettingsImpl for PartialSettings {
+        async fn read_all(
+            &self,
+            _namespaces: Vec<String>,
+        ) -> Result<std::collections::HashMap<String, Namespace>> {
+            Ok([
+                (
+                    "org.example".to_owned(),
+                    [("key".to_owned(), 1u32.into())].into(),
+                ),
+                ("org.other".to_owned(), Namespace::new()),
+            ]
+            .into())
+        }
+    }
+
+    #[tokio::test]
+    async fn advertise_older_version() {
+        let (_backend, proxy) = serve_p2p(|cnx| {
+            SettingsInterface::new(PartialSettings, cnx)
+                .with_version(1)
+                .unwrap()
+        })
+        .await;
+        assert_eq!(proxy.get_property::<u32>("version").await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn advertise_unsupported_version() {
+        let (server, _client) = p2p_pair().await;
+        for version in [0, 3] {
+            let iface = SettingsInterface::new(PartialSettings, server.clone());
+            assert!(matches!(
+                iface.with_version(version),
+                Err(PortalError::InvalidArgument(_))
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn shared_settings() {
+        let settings = Arc::new(Settings(0.into()));
+        let (_backend, proxy) =
+            serve_p2p(|cnx| SettingsInterface::new_shared(settings.clone(), cnx)).await;
+        for value in [0u32, 1] {
+            settings.0.store(value, Ordering::SeqCst);
+            let read: OwnedValue = proxy.call("Read", &("ns", "key")).await.unwrap();
+            assert_eq!(u32::try_from(read).unwrap(), value);
+        }
+    }
+
+    #[tokio::test]
+    async fn settings_not_found() {
+        let (_backend, proxy) = serve_p2p(|cnx| SettingsInterface::new(PartialSettings, cnx)).await;
+        let read: OwnedValue = proxy.call("Read", &("org.example", "key")).await.unwrap();
+        assert_eq!(u32::try_from(read).unwrap(), 1);
+        let err = proxy
+            .call::<_, _, OwnedValue>("Read", &("org.example", "missing"))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            zbus::Error::MethodError(name, _, _) if name == "org.freedesktop.portal.Error.NotFound"
+        ));
+
+        let all: std::collections::HashMap<String, Namespace> = proxy
+            .call("ReadAll", &(vec!["org.example"],))
+            .await
+            .unwrap();
+        assert_eq!(all.keys().collect::<Vec<_>>(), ["org.example"]);
+    }
+}
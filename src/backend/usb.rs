@@ -0,0 +1,385 @@
+//! The USB portal backend, asked by the portal frontend whether an
+//! application may access the USB devices it requested.
+//!
+//! The frontend enumerates the devices and emits the device events of the
+//! sessions by itself, the backend is only responsible for the approval of the
+//! devices, typically by showing a dialog listing them.
+//!
+//! ```rust,no_run
+//! use ashpd::{
+//!     backend::{
+//!         request::{RequestHandle, RequestImpl},
+//!         usb::{AcquireDevicesOptions, AcquiredDevices, UsbDeviceRequest, UsbImpl},
+//!         Result,
+//!     },
+//!     AppID, WindowIdentifierType,
+//! };
+//! use async_trait::async_trait;
+//!
+//! struct Usb;
+//!
+//! #[async_trait]
+//! impl RequestImpl for Usb {
+//!     async fn close(&self, _handle: RequestHandle) {}
+//! }
+//!
+//! #[async_trait]
+//! impl UsbImpl for Usb {
+//!     async fn acquire_devices(
+//!         &self,
+//!         _handle: RequestHandle,
+//!         _window_identifier: Option<WindowIdentifierType>,
+//!         _app_id: Option<AppID>,
+//!         devices: Vec<UsbDeviceRequest>,
+//!         _options: AcquireDevicesOptions,
+//!     ) -> Result<AcquiredDevices> {
+//!         // Only grant read access to the devices.
+//!         let mut acquired = AcquiredDevices::default();
+//!         for request in &devices {
+//!             println!("Granting access to {}", request.device().description());
+//!             acquired = acquired.approve(request, false);
+//!         }
+//!         Ok(acquired)
+//!     }
+//! }
+//! ```
+
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::{
+    backend::{
+        request::{Dispatcher, Request, RequestHandle, RequestImpl},
+        MaybeAppID, MaybeWindowIdentifier, Result,
+    },
+    desktop::request::Response,
+    zvariant::{OwnedObjectPath, OwnedValue, SerializeDict, Type},
+    AppID, WindowIdentifierType,
+};
+
+/// A USB device, as described by the portal frontend from its udev
+/// properties.
+#[derive(Debug, Type)]
+#[zvariant(signature = "dict")]
+#[non_exhaustive]
+pub struct UsbDevice {
+    parent: Option<String>,
+    readable: Option<bool>,
+    writable: Option<bool>,
+    device_file: Option<String>,
+    properties: Option<HashMap<String, OwnedValue>>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(UsbDevice {
+    parent => "parent",
+    readable => "readable",
+    writable => "writable",
+    device_file => "device-file",
+    properties => "properties",
+});
+
+impl UsbDevice {
+    /// The identifier of the parent device, if any.
+    pub fn parent(&self) -> Option<&str> {
+        self.parent.as_deref()
+    }
+
+    /// Whether the device can be opened for reading.
+    pub fn is_readable(&self) -> bool {
+        self.readable.unwrap_or(false)
+    }
+
+    /// Whether the device can be opened for writing.
+    pub fn is_writable(&self) -> bool {
+        self.writable.unwrap_or(false)
+    }
+
+    /// The path of the device node, e.g. `/dev/bus/usb/001/002`.
+    pub fn device_file(&self) -> Option<&str> {
+        self.device_file.as_deref()
+    }
+
+    /// A udev property of the device, e.g. `ID_VENDOR_ID`.
+    pub fn property(&self, name: &str) -> Option<&str> {
+        self.properties
+            .as_ref()?
+            .get(name)
+            .and_then(|value| value.downcast_ref::<&str>().ok())
+    }
+
+    /// The hexadecimal vendor ID, from `ID_VENDOR_ID`.
+    pub fn vendor_id(&self) -> Option<&str> {
+        self.property("ID_VENDOR_ID")
+    }
+
+    /// The hexadecimal product ID, from `ID_MODEL_ID`.
+    pub fn product_id(&self) -> Option<&str> {
+        self.property("ID_MODEL_ID")
+    }
+
+    /// The vendor name, preferably from the hardware database.
+    pub fn vendor(&self) -> Option<&str> {
+        self.property("ID_VENDOR_FROM_DATABASE")
+            .or_else(|| self.property("ID_VENDOR"))
+    }
+
+    /// The product name, preferably from the hardware database.
+    pub fn model(&self) -> Option<&str> {
+        self.property("ID_MODEL_FROM_DATABASE")
+            .or_else(|| self.property("ID_MODEL"))
+    }
+
+    /// The serial number, from `ID_SERIAL_SHORT`.
+    pub fn serial(&self) -> Option<&str> {
+        self.property("ID_SERIAL_SHORT")
+    }
+
+    /// A human readable name for the device, e.g. to be listed in an access
+    /// dialog.
+    ///
+    /// Falls back to the vendor and product IDs, then to the device file,
+    /// when the names are unknown.
+    pub fn description(&self) -> String {
+        match (self.vendor(), self.model()) {
+            (Some(vendor), Some(model)) => format!("{vendor} {model}"),
+            (None, Some(name)) | (Some(name), None) => name.to_owned(),
+            (None, None) => match (self.vendor_id(), self.product_id()) {
+                (Some(vendor_id), Some(product_id)) => format!("{vendor_id}:{product_id}"),
+                _ => self.device_file().unwrap_or("Unknown device").to_owned(),
+            },
+        }
+    }
+
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+}
+
+/// How a device is, or is requested to be, accessed.
+#[derive(Debug, Default, Type)]
+#[zvariant(signature = "dict")]
+#[non_exhaustive]
+pub struct UsbAccess {
+    writable: Option<bool>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(UsbAccess with Serialize {
+    writable => "writable",
+});
+
+impl UsbAccess {
+    /// Whether the device is opened for writing.
+    pub fn writable(&self) -> bool {
+        self.writable.unwrap_or(false)
+    }
+
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+}
+
+/// A device an application requested access to.
+#[derive(Debug, Deserialize, Type)]
+pub struct UsbDeviceRequest(String, UsbDevice, UsbAccess);
+
+impl UsbDeviceRequest {
+    /// The identifier of the device.
+    pub fn id(&self) -> &str {
+        &self.0
+    }
+
+    /// The description of the device.
+    pub fn device(&self) -> &UsbDevice {
+        &self.1
+    }
+
+    /// The access requested by the application.
+    pub fn access(&self) -> &UsbAccess {
+        &self.2
+    }
+}
+
+#[derive(Debug, Type)]
+#[zvariant(signature = "dict")]
+#[non_exhaustive]
+pub struct AcquireDevicesOptions {
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(AcquireDevicesOptions {});
+
+impl AcquireDevicesOptions {
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+}
+
+/// The devices the application was allowed to access.
+#[derive(Debug, Default, SerializeDict, Type)]
+#[zvariant(signature = "dict")]
+pub struct AcquiredDevices {
+    devices: Vec<(String, UsbAccess)>,
+}
+
+impl AcquiredDevices {
+    /// Allow the access to the device of `request`, for writing only if
+    /// `writable` and both the application requested it and the device
+    /// supports it.
+    #[must_use]
+    pub fn approve(mut self, request: &UsbDeviceRequest, writable: bool) -> Self {
+        let writable = writable && request.access().writable() && request.device().is_writable();
+        self.devices.push((
+            request.id().to_owned(),
+            UsbAccess {
+                writable: Some(writable),
+                extra: HashMap::new(),
+            },
+        ));
+        self
+    }
+
+    /// The identifiers of the approved devices.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.devices.iter().map(|(id, _)| id.as_str())
+    }
+}
+
+#[async_trait]
+pub trait UsbImpl: RequestImpl {
+    /// Ask the user whether the application may access `devices`.
+    ///
+    /// The devices left out of the returned [`AcquiredDevices`] are denied.
+    async fn acquire_devices(
+        &self,
+        handle: RequestHandle,
+        window_identifier: Option<WindowIdentifierType>,
+        app_id: Option<AppID>,
+        devices: Vec<UsbDeviceRequest>,
+        options: AcquireDevicesOptions,
+    ) -> Result<AcquiredDevices>;
+}
+
+pub struct UsbInterface {
+    imp: Arc<dyn UsbImpl>,
+    cnx: zbus::Connection,
+    dispatcher: Dispatcher,
+    version: u32,
+}
+
+impl_version!(UsbInterface, 1);
+
+impl UsbInterface {
+    pub fn new(imp: impl UsbImpl + 'static, cnx: zbus::Connection) -> Self {
+        Self {
+            imp: Arc::new(imp),
+            cnx,
+            dispatcher: Dispatcher::default(),
+            version: Self::VERSION,
+        }
+    }
+
+    /// Limit how many requests are handled at the same time, see
+    /// [`Dispatcher`].
+    #[must_use]
+    pub fn with_dispatcher(mut self, dispatcher: Dispatcher) -> Self {
+        self.dispatcher = dispatcher;
+        self
+    }
+}
+
+#[zbus::interface(name = "org.freedesktop.impl.portal.Usb")]
+impl UsbInterface {
+    #[zbus(property(emits_changed_signal = "const"), name = "version")]
+    fn version(&self) -> u32 {
+        self.version
+    }
+
+    #[zbus(name = "AcquireDevices")]
+    #[dbus_interface(out_args("response", "results"))]
+    async fn acquire_devices(
+        &self,
+        handle: OwnedObjectPath,
+        window_identifier: MaybeWindowIdentifier,
+        app_id: MaybeAppID,
+        devices: Vec<UsbDeviceRequest>,
+        options: AcquireDevicesOptions,
+    ) -> Result<Response<AcquiredDevices>> {
+        let imp = Arc::clone(&self.imp);
+
+        Request::spawn(
+            "Usb::AcquireDevices",
+            &self.cnx,
+            &self.dispatcher,
+            handle.clone(),
+            Arc::clone(&self.imp),
+            async move {
+                imp.acquire_devices(
+                    handle.into(),
+                    window_identifier.inner(),
+                    app_id.inner(),
+                    devices,
+                    options,
+                )
+                .await
+            },
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::zvariant::{serialized::Context, to_bytes, Value, LE};
+
+    use super::*;
+
+    #[test]
+    fn device_request() {
+        let device = || {
+            let properties = HashMap::from([
+                ("ID_VENDOR_ID", Value::from("046d")),
+                ("ID_MODEL_ID", Value::from("c52b")),
+                ("ID_VENDOR_FROM_DATABASE", Value::from("Logitech, Inc.")),
+                ("ID_MODEL", Value::from("USB_Receiver")),
+            ]);
+            HashMap::from([
+                ("readable", Value::from(true)),
+                ("writable", Value::from(true)),
+                ("device-file", Value::from("/dev/bus/usb/001/002")),
+                ("properties", Value::from(properties)),
+            ])
+        };
+        let access = HashMap::from([("writable", Value::from(true))]);
+        let requests = vec![
+            ("dev1", device(), access),
+            ("dev2", device(), HashMap::new()),
+        ];
+        let data = to_bytes(Context::new_dbus(LE, 0), &requests).unwrap();
+        let requests: Vec<UsbDeviceRequest> = data.deserialize().unwrap().0;
+
+        let device = requests[0].device();
+        assert_eq!(device.vendor_id(), Some("046d"));
+        assert_eq!(device.product_id(), Some("c52b"));
+        assert_eq!(device.description(), "Logitech, Inc. USB_Receiver");
+        assert_eq!(device.device_file(), Some("/dev/bus/usb/001/002"));
+        assert!(device.is_readable());
+        assert!(requests[0].access().writable());
+        assert!(!requests[1].access().writable());
+
+        let acquired = AcquiredDevices::default()
+            .approve(&requests[0], true)
+            .approve(&requests[1], true);
+        assert_eq!(acquired.ids().collect::<Vec<_>>(), ["dev1", "dev2"]);
+        // Write access is only granted if requested.
+        assert_eq!(acquired.devices[0].1.writable, Some(true));
+        assert_eq!(acquired.devices[1].1.writable, Some(false));
+        to_bytes(Context::new_dbus(LE, 0), &acquired).unwrap();
+    }
+}
@@ -1,26 +1,33 @@
-use std::sync::Arc;
-
-use async_trait::async_trait;
+use std::{collections::HashMap, future::Future, sync::Arc, time::Duration};
 
 use crate::{
     backend::{
-        request::{Request, RequestImpl},
+        request::{Dispatcher, Request, RequestHandle, RequestImpl},
         MaybeAppID, MaybeWindowIdentifier, Result,
     },
-    desktop::{request::ResponseType, wallpaper::SetOn},
-    zvariant::{DeserializeDict, OwnedObjectPath, Type},
+    desktop::{
+        request::{Response, ResponseType},
+        wallpaper::{FillMode, SetOn, FILL_COLOR_KEY, FILL_MODE_KEY, SLIDESHOW_INTERVAL_KEY},
+        Color,
+    },
+    zvariant::{OwnedObjectPath, OwnedValue, Type},
     AppID, WindowIdentifierType,
 };
 
-#[derive(DeserializeDict, Type, Debug)]
+#[derive(Type, Debug)]
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct WallpaperOptions {
-    #[zvariant(rename = "show-preview")]
     show_preview: Option<bool>,
-    #[zvariant(rename = "set-on")]
     set_on: Option<SetOn>,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(WallpaperOptions {
+    show_preview => "show-preview",
+    set_on => "set-on",
+});
+
 impl WallpaperOptions {
     pub fn show_preview(&self) -> Option<bool> {
         self.show_preview
@@ -29,38 +36,106 @@ impl WallpaperOptions {
     pub fn set_on(&self) -> Option<SetOn> {
         self.set_on
     }
+
+    /// How the picture fills the screen, a vendor extension, see
+    /// [`FILL_MODE_KEY`].
+    pub fn fill_mode(&self) -> Option<FillMode> {
+        let value = self.extra.get(FILL_MODE_KEY)?.try_clone().ok()?;
+        FillMode::try_from(value).ok()
+    }
+
+    /// The color of the areas the picture doesn't cover, a vendor extension,
+    /// see [`FILL_COLOR_KEY`].
+    pub fn fill_color(&self) -> Option<Color> {
+        let value = self.extra.get(FILL_COLOR_KEY)?.try_clone().ok()?;
+        <(f64, f64, f64)>::try_from(value).ok().map(Color::from)
+    }
+
+    /// The interval between the pictures of a folder, a vendor extension, see
+    /// [`SLIDESHOW_INTERVAL_KEY`].
+    pub fn slideshow_interval(&self) -> Option<Duration> {
+        let seconds = u32::try_from(self.extra.get(SLIDESHOW_INTERVAL_KEY)?).ok()?;
+        Some(Duration::from_secs(seconds.into()))
+    }
+
+    /// The options unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+}
+
+/// The answer of the user to the preview of a wallpaper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PreviewDecision {
+    /// Set the wallpaper.
+    Accept,
+    /// Don't set the wallpaper, the request is reported as cancelled.
+    Decline,
 }
 
-#[async_trait]
 pub trait WallpaperImpl: RequestImpl {
-    async fn with_uri(
+    /// Show a preview of the wallpaper at `uri` and wait for the user to
+    /// accept or decline it.
+    ///
+    /// Only called when the application asked for a preview, before
+    /// [`WallpaperImpl::with_uri`] which is skipped if the preview is
+    /// declined. Accepts right away by default, for backends without a
+    /// preview dialog.
+    fn preview(
         &self,
+        _handle: RequestHandle,
+        _app_id: Option<AppID>,
+        _window_identifier: Option<WindowIdentifierType>,
+        _uri: &url::Url,
+        _options: &WallpaperOptions,
+    ) -> impl Future<Output = Result<PreviewDecision>> + Send {
+        async { Ok(PreviewDecision::Accept) }
+    }
+
+    /// Set the wallpaper at `uri`.
+    fn with_uri(
+        &self,
+        handle: RequestHandle,
         app_id: Option<AppID>,
         window_identifier: Option<WindowIdentifierType>,
         uri: url::Url,
         options: WallpaperOptions,
-    ) -> Result<()>;
+    ) -> impl Future<Output = Result<()>> + Send;
 }
 
-pub struct WallpaperInterface {
-    imp: Arc<dyn WallpaperImpl>,
+pub struct WallpaperInterface<T> {
+    imp: Arc<T>,
     cnx: zbus::Connection,
+    dispatcher: Dispatcher,
+    version: u32,
 }
 
-impl WallpaperInterface {
-    pub fn new(imp: impl WallpaperImpl + 'static, cnx: zbus::Connection) -> Self {
+impl_version!(WallpaperInterface<T: WallpaperImpl>, 1);
+
+impl<T: WallpaperImpl + 'static> WallpaperInterface<T> {
+    pub fn new(imp: T, cnx: zbus::Connection) -> Self {
         Self {
             imp: Arc::new(imp),
             cnx,
+            dispatcher: Dispatcher::default(),
+            version: Self::VERSION,
         }
     }
+
+    /// Limit how many requests are handled at the same time, see
+    /// [`Dispatcher`].
+    #[must_use]
+    pub fn with_dispatcher(mut self, dispatcher: Dispatcher) -> Self {
+        self.dispatcher = dispatcher;
+        self
+    }
 }
 
 #[zbus::interface(name = "org.freedesktop.impl.portal.Wallpaper")]
-impl WallpaperInterface {
+impl<T: WallpaperImpl + 'static> WallpaperInterface<T> {
     #[zbus(property(emits_changed_signal = "const"), name = "version")]
     fn version(&self) -> u32 {
-        1
+        self.version
     }
 
     #[zbus(name = "SetWallpaperURI")]
@@ -78,14 +153,123 @@ impl WallpaperInterface {
         Request::spawn(
             "Wallpaper::SetWallpaperURI",
             &self.cnx,
-            handle,
+            &self.dispatcher,
+            handle.clone(),
             Arc::clone(&self.imp),
             async move {
-                imp.with_uri(app_id.inner(), window_identifier.inner(), uri, options)
-                    .await
+                let handle = RequestHandle::from(handle);
+                let (app_id, window_identifier) = (app_id.inner(), window_identifier.inner());
+                if options.show_preview() == Some(true) {
+                    let decision = imp
+                        .preview(
+                            handle.clone(),
+                            app_id.clone(),
+                            window_identifier.clone(),
+                            &uri,
+                            &options,
+                        )
+                        .await?;
+                    if decision == PreviewDecision::Decline {
+                        return Ok(decision);
+                    }
+                }
+                imp.with_uri(handle, app_id, window_identifier, uri, options)
+                    .await?;
+                Ok(PreviewDecision::Accept)
             },
         )
         .await
-        .map(|r| r.response_type())
+        .map(|response| match response {
+            Response::Ok(PreviewDecision::Decline) => ResponseType::Cancelled,
+            response => response.response_type(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::helpers::serve_p2p;
+
+    struct Wallpaper {
+        decision: PreviewDecision,
+        set: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl RequestImpl for Wallpaper {
+        async fn close(&self, _handle: RequestHandle) {}
+    }
+
+    impl WallpaperImpl for Wallpaper {
+        async fn preview(
+            &self,
+            _handle: RequestHandle,
+            _app_id: Option<AppID>,
+            _window_identifier: Option<WindowIdentifierType>,
+            _uri: &url::Url,
+            _options: &WallpaperOptions,
+        ) -> Result<PreviewDecision> {
+            Ok(self.decision)
+        }
+
+        async fn with_uri(
+            &self,
+            _handle: RequestHandle,
+            _app_id: Option<AppID>,
+            _window_identifier: Option<WindowIdentifierType>,
+            _uri: url::Url,
+            _options: WallpaperOptions,
+        ) -> Result<()> {
+            self.set.store(true, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn preview() {
+        let cases = [
+            (PreviewDecision::Accept, true, ResponseType::Success, true),
+            (
+                PreviewDecision::Decline,
+                true,
+                ResponseType::Cancelled,
+                false,
+            ),
+            (PreviewDecision::Decline, false, ResponseType::Success, true),
+        ];
+        for (decision, show_preview, expected, set) in cases {
+            let wallpaper = Wallpaper {
+                decision,
+                set: Arc::default(),
+            };
+            let was_set = wallpaper.set.clone();
+            let (_backend, proxy) = serve_p2p(|cnx| WallpaperInterface::new(wallpaper, cnx)).await;
+            let options =
+                HashMap::from([("show-preview", zbus::zvariant::Value::from(show_preview))]);
+            let response: ResponseType = proxy
+                .call(
+                    "SetWallpaperURI",
+                    &(
+                        OwnedObjectPath::try_from("/org/freedesktop/portal/desktop/request/1_1/t")
+                            .unwrap(),
+                        "",
+                        "",
+                        "file:///wallpaper.png",
+                        options,
+                    ),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response, expected);
+            assert_eq!(was_set.load(Ordering::SeqCst), set);
+        }
     }
 }
@@ -21,26 +21,58 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! With the `fallback` feature enabled,
+//! [`UserInformationRequest::send_with_fallback`] returns the information of
+//! the local user from the user database, through NSS, when the portal is not
+//! available.
+
+use std::collections::HashMap;
 
-use zbus::zvariant::{DeserializeDict, SerializeDict, Type};
+use zbus::zvariant::{OwnedValue, Type};
 
 use super::HandleToken;
-use crate::{desktop::request::Request, proxy::Proxy, Error, WindowIdentifier};
+use crate::{desktop::request::Request, helpers::Redacted, proxy::Proxy, Error, WindowIdentifier};
 
-#[derive(SerializeDict, Type, Debug, Default)]
+#[derive(Type, Debug, Default)]
 #[zvariant(signature = "dict")]
 struct UserInformationOptions {
     handle_token: HandleToken,
     reason: Option<String>,
+    extra: HashMap<String, OwnedValue>,
 }
 
-#[derive(Debug, DeserializeDict, SerializeDict, Type)]
+crate::helpers::impl_dict_with_extra!(Serialize for UserInformationOptions {
+    handle_token [required] => "handle_token",
+    reason => "reason",
+});
+
+#[derive(Type)]
 /// The response of a [`UserInformationRequest`] request.
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct UserInformation {
     id: String,
     name: String,
     image: url::Url,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(UserInformation with Serialize {
+    id [required] => "id",
+    name [required] => "name",
+    image [required] => "image",
+});
+
+impl std::fmt::Debug for UserInformation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserInformation")
+            .field("id", &Redacted(&self.id))
+            .field("name", &Redacted(&self.name))
+            .field("image", &Redacted(&self.image))
+            .field("extra", &self.extra)
+            .finish()
+    }
 }
 
 impl UserInformation {
@@ -52,6 +84,7 @@ impl UserInformation {
             id: id.to_owned(),
             name: name.to_owned(),
             image,
+            extra: HashMap::new(),
         }
     }
 
@@ -77,13 +110,20 @@ impl UserInformation {
     pub fn request() -> UserInformationRequest {
         UserInformationRequest::default()
     }
+
+    /// The entries of the response unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
 struct AccountProxy<'a>(Proxy<'a>);
 
 impl<'a> AccountProxy<'a> {
-    pub async fn new() -> Result<AccountProxy<'a>, Error> {
-        let proxy = Proxy::new_desktop("org.freedesktop.portal.Account").await?;
+    pub async fn new(connection: Option<zbus::Connection>) -> Result<AccountProxy<'a>, Error> {
+        let proxy =
+            Proxy::new_desktop_with_connection(connection, "org.freedesktop.portal.Account")
+                .await?;
         Ok(Self(proxy))
     }
 
@@ -119,9 +159,28 @@ impl<'a> std::ops::Deref for AccountProxy<'a> {
 pub struct UserInformationRequest {
     options: UserInformationOptions,
     identifier: WindowIdentifier,
+    connection: Option<zbus::Connection>,
+    close_on_drop: Option<bool>,
 }
 
 impl UserInformationRequest {
+    /// Sets the connection to send the request on, instead of the session
+    /// bus connection.
+    #[must_use]
+    pub fn connection(mut self, connection: impl Into<Option<zbus::Connection>>) -> Self {
+        self.connection = connection.into();
+        self
+    }
+
+    /// Whether to close the request, dismissing its dialog, if the future
+    /// sending it is dropped before the response is received. Overrides
+    /// [`set_close_on_drop`](crate::set_close_on_drop) for this request.
+    #[must_use]
+    pub fn close_on_drop(mut self, close: bool) -> Self {
+        self.close_on_drop = Some(close);
+        self
+    }
+
     #[must_use]
     /// Sets a user-visible reason for the request.
     pub fn reason<'a>(mut self, reason: impl Into<Option<&'a str>>) -> Self {
@@ -136,9 +195,194 @@ impl UserInformationRequest {
         self
     }
 
+    /// Sets the option `key`, unknown to this version of the crate, e.g. a
+    /// vendor extension like `x-gnome-...` to experiment with the features of
+    /// a backend. The options known to the crate can't be overridden.
+    #[must_use]
+    pub fn extra<'v>(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<zbus::zvariant::Value<'v>>,
+    ) -> Self {
+        crate::helpers::insert_extra(&mut self.options.extra, key, value);
+        self
+    }
+
     /// Build the [`UserInformation`].
     pub async fn send(self) -> Result<Request<UserInformation>, Error> {
-        let proxy = AccountProxy::new().await?;
+        let mut proxy = AccountProxy::new(self.connection).await?;
+        proxy.0.close_requests_on_drop(self.close_on_drop);
         proxy.user_information(&self.identifier, self.options).await
     }
+
+    #[cfg(feature = "fallback")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "fallback")))]
+    /// Send the request and wait for its response, falling back to the local
+    /// user information if the Account portal is not available.
+    ///
+    /// Use [`FallbackUserInformation::is_fallback`] to figure out where the
+    /// information came from.
+    pub async fn send_with_fallback(self) -> Result<FallbackUserInformation, Error> {
+        match self.send().await.and_then(|request| request.response()) {
+            Ok(inner) => Ok(FallbackUserInformation {
+                inner,
+                is_fallback: false,
+            }),
+            Err(err) if crate::helpers::is_portal_missing(&err) => {
+                #[cfg(feature = "tracing")]
+                tracing::info!("Account portal not available, using the local user information");
+                let inner = local_user_information().await?;
+                Ok(FallbackUserInformation {
+                    inner,
+                    is_fallback: true,
+                })
+            }
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(feature = "fallback")]
+#[cfg_attr(docsrs, doc(cfg(feature = "fallback")))]
+#[derive(Debug)]
+/// The response of [`UserInformationRequest::send_with_fallback`].
+pub struct FallbackUserInformation {
+    inner: UserInformation,
+    is_fallback: bool,
+}
+
+#[cfg(feature = "fallback")]
+impl FallbackUserInformation {
+    /// Whether the information was retrieved locally instead of through the
+    /// portal.
+    pub fn is_fallback(&self) -> bool {
+        self.is_fallback
+    }
+
+    /// The user information.
+    pub fn into_inner(self) -> UserInformation {
+        self.inner
+    }
+}
+
+#[cfg(feature = "fallback")]
+impl std::ops::Deref for FallbackUserInformation {
+    type Target = UserInformation;
+
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
+
+#[cfg(feature = "fallback")]
+async fn local_user_information() -> Result<UserInformation, Error> {
+    // The lookup goes through NSS, which may block on the network, e.g. LDAP.
+    crate::helpers::unblock(|| {
+        // SAFETY: getuid always succeeds.
+        let uid = unsafe { libc::getuid() };
+        let not_found = || {
+            Error::IO(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("No passwd entry for the user {uid}"),
+            ))
+        };
+        let (login, gecos, home) = passwd_entry(uid)?.ok_or_else(not_found)?;
+        user_information(&login, &gecos, &home).ok_or_else(not_found)
+    })
+    .await
+}
+
+/// The login, GECOS and home directory of the `passwd(5)` entry of `uid`.
+#[cfg(feature = "fallback")]
+fn passwd_entry(uid: libc::uid_t) -> std::io::Result<Option<(String, String, String)>> {
+    use std::ffi::CStr;
+
+    let mut buffer = vec![0_u8; 1024];
+    loop {
+        let mut passwd = std::mem::MaybeUninit::<libc::passwd>::uninit();
+        let mut entry = std::ptr::null_mut();
+        // SAFETY: the pointers are valid for the duration of the call, and
+        // `buffer` for writes of its length.
+        let ret = unsafe {
+            libc::getpwuid_r(
+                uid,
+                passwd.as_mut_ptr(),
+                buffer.as_mut_ptr().cast(),
+                buffer.len(),
+                &mut entry,
+            )
+        };
+        match ret {
+            0 if entry.is_null() => return Ok(None),
+            0 => {}
+            libc::ERANGE => {
+                buffer.resize(buffer.len() * 2, 0);
+                continue;
+            }
+            err => return Err(std::io::Error::from_raw_os_error(err)),
+        }
+        // SAFETY: the entry was found, its strings point into `buffer`.
+        let field = |ptr: *const libc::c_char| {
+            if ptr.is_null() {
+                String::new()
+            } else {
+                unsafe { CStr::from_ptr(ptr) }
+                    .to_string_lossy()
+                    .into_owned()
+            }
+        };
+        let passwd = unsafe { passwd.assume_init() };
+        return Ok(Some((
+            field(passwd.pw_name),
+            field(passwd.pw_gecos),
+            field(passwd.pw_dir),
+        )));
+    }
+}
+
+/// The information of the user `login`, from its GECOS and home directory.
+///
+/// The avatar points to `~/.face`, which may not exist.
+#[cfg(feature = "fallback")]
+fn user_information(login: &str, gecos: &str, home: &str) -> Option<UserInformation> {
+    // The first field of GECOS is the full name
+    let name = gecos
+        .split(',')
+        .next()
+        .filter(|n| !n.is_empty())
+        .unwrap_or(login);
+    let image = url::Url::from_file_path(std::path::Path::new(home).join(".face")).ok()?;
+    Some(UserInformation {
+        id: login.to_owned(),
+        name: name.to_owned(),
+        image,
+        extra: HashMap::new(),
+    })
+}
+
+#[cfg(all(test, feature = "fallback"))]
+mod tests {
+    use super::{passwd_entry, user_information};
+
+    #[test]
+    fn gecos() {
+        let user = user_information("bilal", "Bilal Elmoussaoui,,,", "/home/bilal").unwrap();
+        assert_eq!(user.id(), "bilal");
+        assert_eq!(user.name(), "Bilal Elmoussaoui");
+        assert_eq!(user.image().as_str(), "file:///home/bilal/.face");
+
+        let user = user_information("nobody", "", "/nonexistent").unwrap();
+        assert_eq!(user.name(), "nobody");
+
+        assert!(user_information("nobody", "", "relative").is_none());
+    }
+
+    #[test]
+    fn current_user() {
+        // SAFETY: getuid always succeeds.
+        let uid = unsafe { libc::getuid() };
+        let (login, _gecos, home) = passwd_entry(uid).unwrap().unwrap();
+        assert!(!login.is_empty());
+        assert!(!home.is_empty());
+    }
 }
@@ -30,32 +30,48 @@
 //! If no `command` is provided, the [`Exec`](https://specifications.freedesktop.org/desktop-entry-spec/desktop-entry-spec-latest.html#exec-variables) line from the [desktop
 //! file](https://specifications.freedesktop.org/desktop-entry-spec/desktop-entry-spec-latest.html#introduction) will be used.
 
+use std::collections::HashMap;
+
 use serde::Serialize;
-use zbus::zvariant::{DeserializeDict, SerializeDict, Type};
+use zbus::zvariant::{OwnedValue, SerializeDict, Type};
 
 use super::{HandleToken, Request};
 use crate::{proxy::Proxy, Error, WindowIdentifier};
 
-#[derive(SerializeDict, Type, Debug, Default)]
+#[derive(Type, Debug, Default)]
 #[zvariant(signature = "dict")]
 struct BackgroundOptions {
     handle_token: HandleToken,
     reason: Option<String>,
     autostart: Option<bool>,
-    #[zvariant(rename = "dbus-activatable")]
     dbus_activatable: Option<bool>,
-    #[zvariant(rename = "commandline")]
     command: Option<Vec<String>>,
+    extra: HashMap<String, OwnedValue>,
 }
 
-#[derive(DeserializeDict, Type, Debug)]
+crate::helpers::impl_dict_with_extra!(Serialize for BackgroundOptions {
+    handle_token [required] => "handle_token",
+    reason => "reason",
+    autostart => "autostart",
+    dbus_activatable => "dbus-activatable",
+    command => "commandline",
+});
+
+#[derive(Type, Debug)]
 /// The response of a [`BackgroundRequest`] request.
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct Background {
     background: bool,
     autostart: bool,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(Background {
+    background [required] => "background",
+    autostart [required] => "autostart",
+});
+
 impl Background {
     /// Creates a new builder-pattern struct instance to construct
     /// [`Background`].
@@ -74,6 +90,11 @@ impl Background {
     pub fn auto_start(&self) -> bool {
         self.autostart
     }
+
+    /// The entries of the response unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
 #[derive(SerializeDict, Type, Debug, Default)]
@@ -97,6 +118,20 @@ impl<'a> BackgroundProxy<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`BackgroundProxy`] using `connection` instead
+    /// of the session bus connection, e.g. a peer-to-peer connection to the
+    /// portal.
+    pub async fn new_with_connection(
+        connection: zbus::Connection,
+    ) -> Result<BackgroundProxy<'a>, Error> {
+        let proxy = Proxy::new_desktop_with_connection(
+            Some(connection),
+            "org.freedesktop.portal.Background",
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
     ///  Sets the status of the application running in background.
     ///
     /// # Arguments
@@ -112,7 +147,6 @@ impl<'a> BackgroundProxy<'a> {
     /// # Specifications
     ///
     /// See also [`SetStatus`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.Background.html#org-freedesktop-portal-background-setstatus).
-
     pub async fn set_status(&self, message: &str) -> Result<(), Error> {
         self.0
             .call_versioned(
@@ -156,9 +190,19 @@ impl<'a> std::ops::Deref for BackgroundProxy<'a> {
 pub struct BackgroundRequest {
     identifier: WindowIdentifier,
     options: BackgroundOptions,
+    close_on_drop: Option<bool>,
 }
 
 impl BackgroundRequest {
+    /// Whether to close the request, dismissing its dialog, if the future
+    /// sending it is dropped before the response is received. Overrides
+    /// [`set_close_on_drop`](crate::set_close_on_drop) for this request.
+    #[must_use]
+    pub fn close_on_drop(mut self, close: bool) -> Self {
+        self.close_on_drop = Some(close);
+        self
+    }
+
     #[must_use]
     /// Sets a window identifier.
     pub fn identifier(mut self, identifier: impl Into<Option<WindowIdentifier>>) -> Self {
@@ -201,9 +245,23 @@ impl BackgroundRequest {
         self
     }
 
+    /// Sets the option `key`, unknown to this version of the crate, e.g. a
+    /// vendor extension like `x-gnome-...` to experiment with the features of
+    /// a backend. The options known to the crate can't be overridden.
+    #[must_use]
+    pub fn extra<'v>(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<zbus::zvariant::Value<'v>>,
+    ) -> Self {
+        crate::helpers::insert_extra(&mut self.options.extra, key, value);
+        self
+    }
+
     /// Build the [`Background`].
     pub async fn send(self) -> Result<Request<Background>, Error> {
-        let proxy = BackgroundProxy::new().await?;
+        let mut proxy = BackgroundProxy::new().await?;
+        proxy.0.close_requests_on_drop(self.close_on_drop);
         proxy
             .request_background(&self.identifier, self.options)
             .await
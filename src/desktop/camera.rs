@@ -19,6 +19,8 @@
 
 use std::{collections::HashMap, os::fd::OwnedFd};
 
+use futures_util::Stream;
+
 #[cfg(feature = "pipewire")]
 use pipewire::{context::Context, main_loop::MainLoop};
 use zbus::zvariant::{self, SerializeDict, Type, Value};
@@ -47,6 +49,15 @@ impl<'a> Camera<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`Camera`] using `connection` instead of the
+    /// session bus connection, e.g. a peer-to-peer connection to the portal.
+    pub async fn new_with_connection(connection: zbus::Connection) -> Result<Camera<'a>, Error> {
+        let proxy =
+            Proxy::new_desktop_with_connection(Some(connection), "org.freedesktop.portal.Camera")
+                .await?;
+        Ok(Self(proxy))
+    }
+
     /// Requests an access to the camera.
     ///
     /// # Specifications
@@ -94,6 +105,17 @@ impl<'a> Camera<'a> {
     pub async fn is_present(&self) -> Result<bool, Error> {
         self.0.property("IsCameraPresent").await
     }
+
+    /// Listen to the changes of [`is_present`](Self::is_present), e.g. when a
+    /// camera is plugged or unplugged.
+    ///
+    /// # Specifications
+    ///
+    /// See also [`IsCameraPresent`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.Camera.html#org-freedesktop-portal-camera-iscamerapresent).
+    #[doc(alias = "IsCameraPresent")]
+    pub async fn receive_is_present_changed(&self) -> Result<impl Stream<Item = bool> + 'a, Error> {
+        self.0.property_changed("IsCameraPresent").await
+    }
 }
 
 impl<'a> std::ops::Deref for Camera<'a> {
@@ -143,7 +165,7 @@ fn pipewire_streams_inner<F: Fn(Stream) + Clone + 'static, G: FnOnce() + Clone +
         .add_listener_local()
         .global(move |global| {
             if let Some(props) = &global.props {
-                if props.get("media.role") == Some("Camera") {
+                if super::pipewire_node::is_camera(props) {
                     #[cfg(feature = "tracing")]
                     tracing::info!("found camera: {:#?}", props);
 
@@ -6,7 +6,7 @@
 use std::collections::HashMap;
 
 use futures_util::{Stream, StreamExt};
-use zbus::zvariant::{DeserializeDict, OwnedFd, OwnedObjectPath, SerializeDict, Type, Value};
+use zbus::zvariant::{OwnedFd, OwnedObjectPath, OwnedValue, SerializeDict, Type, Value};
 
 use super::{remote_desktop::RemoteDesktop, Session};
 use crate::{proxy::Proxy, Result};
@@ -17,14 +17,21 @@ struct SetSelectionOptions<'a> {
     mime_types: &'a [&'a str],
 }
 
-#[derive(Debug, Type, DeserializeDict)]
+#[derive(Debug, Type)]
 #[zvariant(signature = "dict")]
 /// The details of a new clipboard selection.
+#[non_exhaustive]
 pub struct SelectionOwnerChanged {
     mime_types: Option<Vec<String>>,
     session_is_owner: Option<bool>,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(SelectionOwnerChanged {
+    mime_types => "mime_types",
+    session_is_owner => "session_is_owner",
+});
+
 impl SelectionOwnerChanged {
     /// Whether the session is the owner of the clipboard selection or not.
     pub fn session_is_owner(&self) -> Option<bool> {
@@ -32,9 +39,17 @@ impl SelectionOwnerChanged {
     }
 
     /// A list of mime types the new clipboard has content for.
+    ///
+    /// They are not necessarily valid mime types, X11 clients also offer
+    /// targets like `UTF8_STRING`.
     pub fn mime_types(&self) -> Vec<String> {
         self.mime_types.clone().unwrap_or_default()
     }
+
+    /// The entries of the signal unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
 #[doc(alias = "org.freedesktop.portal.Clipboard")]
@@ -49,6 +64,17 @@ impl<'a> Clipboard<'a> {
         ))
     }
 
+    /// Create a new instance of [`Clipboard`] using `connection` instead of the
+    /// session bus connection, e.g. a peer-to-peer connection to the portal.
+    pub async fn new_with_connection(connection: zbus::Connection) -> Result<Clipboard<'a>> {
+        let proxy = Proxy::new_desktop_with_connection(
+            Some(connection),
+            "org.freedesktop.portal.Clipboard",
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
     /// # Specifications
     ///
     /// See also [`RequestClipboard`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.Clipboard.html#org-freedesktop-portal-clipboard-requestclipboard).
@@ -71,7 +97,9 @@ impl<'a> Clipboard<'a> {
         mime_types: &[&str],
     ) -> Result<()> {
         let options = SetSelectionOptions { mime_types };
-        self.0.call("SetSelection", &(session, options)).await?;
+        self.0
+            .call::<()>("SetSelection", &(session, options))
+            .await?;
 
         Ok(())
     }
@@ -130,12 +158,14 @@ impl<'a> Clipboard<'a> {
     #[doc(alias = "SelectionOwnerChanged")]
     pub async fn receive_selection_owner_changed(
         &self,
-    ) -> Result<impl Stream<Item = (Session<RemoteDesktop>, SelectionOwnerChanged)>> {
+    ) -> Result<impl Stream<Item = (Session<'_, RemoteDesktop<'_>>, SelectionOwnerChanged)>> {
         Ok(self
             .0
             .signal::<(OwnedObjectPath, SelectionOwnerChanged)>("SelectionOwnerChanged")
             .await?
-            .filter_map(|(p, o)| async move { Session::new(p).await.map(|s| (s, o)).ok() }))
+            .filter_map(move |(p, o)| async move {
+                Session::new(&self.0, p).await.map(|s| (s, o)).ok()
+            }))
     }
 
     /// # Specifications
@@ -144,13 +174,13 @@ impl<'a> Clipboard<'a> {
     #[doc(alias = "SelectionTransfer")]
     pub async fn receive_selection_transfer(
         &self,
-    ) -> Result<impl Stream<Item = (Session<RemoteDesktop>, String, u32)>> {
+    ) -> Result<impl Stream<Item = (Session<'_, RemoteDesktop<'_>>, String, u32)>> {
         Ok(self
             .0
             .signal::<(OwnedObjectPath, String, u32)>("SelectionTransfer")
             .await?
-            .filter_map(|(p, mime_type, serial)| async move {
-                Session::new(p)
+            .filter_map(move |(p, mime_type, serial)| async move {
+                Session::new(&self.0, p)
                     .await
                     .map(|session| (session, mime_type, serial))
                     .ok()
@@ -59,6 +59,13 @@ impl From<Color> for gtk4::gdk::RGBA {
     }
 }
 
+#[cfg(feature = "gtk4")]
+impl From<gtk4::gdk::RGBA> for Color {
+    fn from(rgba: gtk4::gdk::RGBA) -> Self {
+        Self::new(rgba.red() as f64, rgba.green() as f64, rgba.blue() as f64)
+    }
+}
+
 impl std::fmt::Debug for Color {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("Color")
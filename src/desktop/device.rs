@@ -47,6 +47,12 @@ pub enum Device {
     Camera,
 }
 
+crate::helpers::impl_value_conversions!(Device {
+    Microphone => "microphone",
+    Speakers => "speakers",
+    Camera => "camera",
+});
+
 impl fmt::Display for Device {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -107,6 +113,18 @@ impl<'a> DeviceProxy<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`DeviceProxy`] using `connection` instead of
+    /// the session bus connection, e.g. a peer-to-peer connection to the
+    /// portal.
+    pub async fn new_with_connection(
+        connection: zbus::Connection,
+    ) -> Result<DeviceProxy<'a>, Error> {
+        let proxy =
+            Proxy::new_desktop_with_connection(Some(connection), "org.freedesktop.portal.Device")
+                .await?;
+        Ok(Self(proxy))
+    }
+
     /// Asks for access to a device.
     ///
     /// # Arguments
@@ -49,16 +49,45 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! ## Launching and uninstalling
+//!
+//! The launchers installed by the application can be launched, or uninstalled
+//! after asking the user for confirmation. Both fail with
+//! [`Error::LauncherNotFound`] if the launcher doesn't exist.
+//!
+//! ```rust,no_run
+//! use ashpd::{
+//!     desktop::dynamic_launcher::{LaunchRequest, UninstallRequest},
+//!     Error,
+//! };
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     LaunchRequest::default()
+//!         .send("org.example.App.WebApp.desktop")
+//!         .await?;
+//!
+//!     match UninstallRequest::default()
+//!         .ask(true)
+//!         .send("org.example.App.WebApp.desktop")
+//!         .await
+//!     {
+//!         Err(Error::LauncherNotFound(id)) => println!("{id} was already removed"),
+//!         result => result?,
+//!     }
+//!     Ok(())
+//! }
+//! ```
 
 use std::collections::HashMap;
 
 use enumflags2::{bitflags, BitFlags};
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
-use zbus::zvariant::{self, DeserializeDict, OwnedValue, SerializeDict, Type, Value};
+use zbus::zvariant::{self, OwnedValue, SerializeDict, Type, Value};
 
 use super::{HandleToken, Icon, Request};
-use crate::{proxy::Proxy, ActivationToken, Error, WindowIdentifier};
+use crate::{helpers::SecretString, proxy::Proxy, ActivationToken, Error, WindowIdentifier};
 
 #[bitflags]
 #[derive(Default, Serialize_repr, Deserialize_repr, PartialEq, Eq, Debug, Copy, Clone, Type)]
@@ -75,6 +104,8 @@ pub enum LauncherType {
     WebApplication,
 }
 
+crate::helpers::impl_value_conversions!(LauncherType as u32 { Application, WebApplication });
+
 #[cfg_attr(feature = "glib", derive(glib::Enum))]
 #[cfg_attr(feature = "glib", enum_type(name = "AshpdIconType"))]
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Type)]
@@ -90,6 +121,12 @@ pub enum IconType {
     Svg,
 }
 
+crate::helpers::impl_value_conversions!(IconType {
+    Png => "png",
+    Jpeg => "jpeg",
+    Svg => "svg",
+});
+
 #[derive(Debug, Deserialize, Type)]
 #[zvariant(signature = "(vsu)")]
 /// The icon of the launcher.
@@ -157,15 +194,23 @@ impl PrepareInstallOptions {
     }
 }
 
-#[derive(DeserializeDict, Type)]
+#[derive(Type)]
 #[zvariant(signature = "dict")]
 /// A response of [`DynamicLauncherProxy::prepare_install`]
+#[non_exhaustive]
 pub struct PrepareInstallResponse {
     name: String,
     icon: OwnedValue,
-    token: String,
+    token: SecretString,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(PrepareInstallResponse {
+    name [required] => "name",
+    icon [required] => "icon",
+    token [required] => "token",
+});
+
 impl PrepareInstallResponse {
     /// The user defined name or a predefined one
     pub fn name(&self) -> &str {
@@ -174,7 +219,7 @@ impl PrepareInstallResponse {
 
     /// A token to pass to [`DynamicLauncherProxy::install`]
     pub fn token(&self) -> &str {
-        &self.token
+        self.token.as_str()
     }
 
     /// The user selected icon or a predefined one
@@ -182,6 +227,11 @@ impl PrepareInstallResponse {
         let inner = self.icon.downcast_ref::<Value>().unwrap();
         Icon::try_from(inner).unwrap()
     }
+
+    /// The entries of the response unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
 impl std::fmt::Debug for PrepareInstallResponse {
@@ -189,7 +239,7 @@ impl std::fmt::Debug for PrepareInstallResponse {
         f.debug_struct("PrepareInstallResponse")
             .field("name", &self.name())
             .field("icon", &self.icon())
-            .field("token", &self.token())
+            .field("token", &self.token)
             .finish()
     }
 }
@@ -213,6 +263,126 @@ impl LaunchOptions {
     }
 }
 
+#[derive(SerializeDict, Type, Debug, Default)]
+#[zvariant(signature = "dict")]
+/// Specified options for a [`UninstallRequest`] request.
+struct UninstallOptions {
+    ask: Option<bool>,
+}
+
+#[derive(Debug, Default)]
+#[doc(alias = "xdp_portal_dynamic_launcher_launch")]
+/// A [builder-pattern] type to launch an installed launcher.
+///
+/// [builder-pattern]: https://doc.rust-lang.org/1.0.0/style/ownership/builders.html
+pub struct LaunchRequest {
+    options: LaunchOptions,
+    connection: Option<zbus::Connection>,
+}
+
+impl LaunchRequest {
+    /// Sets the connection to send the request on, instead of the session
+    /// bus connection.
+    #[must_use]
+    pub fn connection(mut self, connection: impl Into<Option<zbus::Connection>>) -> Self {
+        self.connection = connection.into();
+        self
+    }
+
+    /// Sets the token that can be used to activate the launched application.
+    #[must_use]
+    pub fn activation_token(
+        mut self,
+        activation_token: impl Into<Option<ActivationToken>>,
+    ) -> Self {
+        self.options = self.options.activation_token(activation_token);
+        self
+    }
+
+    /// Launch the launcher `desktop_file_id`.
+    ///
+    /// Fails with [`Error::LauncherNotFound`] if it isn't installed.
+    pub async fn send(self, desktop_file_id: &str) -> Result<(), Error> {
+        let proxy = DynamicLauncherProxy::with_connection(self.connection).await?;
+        proxy.launch(desktop_file_id, self.options).await
+    }
+}
+
+#[derive(Debug, Default)]
+#[doc(alias = "xdp_portal_dynamic_launcher_uninstall")]
+/// A [builder-pattern] type to uninstall a launcher.
+///
+/// [builder-pattern]: https://doc.rust-lang.org/1.0.0/style/ownership/builders.html
+pub struct UninstallRequest {
+    options: UninstallOptions,
+    connection: Option<zbus::Connection>,
+}
+
+impl UninstallRequest {
+    /// Sets the connection to send the request on, instead of the session
+    /// bus connection.
+    #[must_use]
+    pub fn connection(mut self, connection: impl Into<Option<zbus::Connection>>) -> Self {
+        self.connection = connection.into();
+        self
+    }
+
+    /// Sets whether the portal should ask the user to confirm the removal.
+    ///
+    /// **Note** the `ask` option isn't part of the portal specification, the
+    /// portals not supporting it ignore it and uninstall the launcher right
+    /// away.
+    #[must_use]
+    pub fn ask(mut self, ask: impl Into<Option<bool>>) -> Self {
+        self.options.ask = ask.into();
+        self
+    }
+
+    /// Uninstall the launcher `desktop_file_id`.
+    ///
+    /// Fails with [`Error::LauncherNotFound`] if it isn't installed, or
+    /// with [`Error::NotAllowed`] if the user refused the removal.
+    pub async fn send(self, desktop_file_id: &str) -> Result<(), Error> {
+        let proxy = DynamicLauncherProxy::with_connection(self.connection).await?;
+        proxy
+            .uninstall_with_options(desktop_file_id, &self.options)
+            .await
+    }
+}
+
+/// Whether `err` means that the launcher the method was called with doesn't
+/// exist.
+///
+/// The frontend forwards the errors of GIO as is, e.g.
+/// `G_IO_ERROR_NOT_FOUND` or `G_FILE_ERROR_NOENT`.
+fn is_launcher_not_found(err: &Error) -> bool {
+    match err {
+        Error::Portal(crate::PortalError::NotFound(_)) => true,
+        Error::Portal(crate::PortalError::ZBus(err)) | Error::Zbus(err) => match err {
+            zbus::Error::FDO(err) => matches!(**err, zbus::fdo::Error::FileNotFound(_)),
+            zbus::Error::MethodError(name, _, _) => matches!(
+                name.as_str(),
+                "org.gtk.GDBus.UnmappedGError.Quark._g_2dio_2derror_2dquark.Code1"
+                    | "org.gtk.GDBus.UnmappedGError.Quark._g_2dfile_2derror_2dquark.Code4"
+            ),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Replace the errors meaning that `desktop_file_id` doesn't exist by
+/// [`Error::LauncherNotFound`].
+fn launcher_not_found<T>(result: Result<T, Error>, desktop_file_id: &str) -> Result<T, Error> {
+    result.map_err(|err| {
+        if is_launcher_not_found(&err) {
+            Error::LauncherNotFound(desktop_file_id.to_owned())
+        } else {
+            err
+        }
+    })
+}
+
 #[derive(Debug)]
 /// Wrong type of [`crate::desktop::Icon`] was used.
 pub struct UnexpectedIconError;
@@ -224,6 +394,235 @@ impl std::fmt::Display for UnexpectedIconError {
     }
 }
 
+/// The largest width and height of a PNG or JPEG launcher icon.
+pub const MAX_ICON_SIZE: u32 = 512;
+
+/// The largest width and height of an SVG launcher icon.
+pub const MAX_SVG_ICON_SIZE: u32 = 4096;
+
+/// The largest launcher icon, in bytes.
+pub const MAX_ICON_BYTES: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+/// A launcher icon rejected by [`validate_icon`].
+pub enum InvalidIconError {
+    /// The icon is larger than [`MAX_ICON_BYTES`].
+    TooLarge(usize),
+    /// The icon is neither a PNG, a JPEG nor an SVG.
+    UnknownFormat,
+    /// The icon data is truncated or malformed.
+    Malformed(IconType),
+    /// The width and height of the icon differ.
+    NotSquare(u32, u32),
+    /// The icon exceeds [`MAX_ICON_SIZE`], or [`MAX_SVG_ICON_SIZE`] for SVGs.
+    TooBig(u32, u32),
+}
+
+impl std::error::Error for InvalidIconError {}
+impl std::fmt::Display for InvalidIconError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooLarge(len) => write!(
+                f,
+                "Icon of {len} bytes exceeds the limit of {MAX_ICON_BYTES} bytes"
+            ),
+            Self::UnknownFormat => f.write_str("Icon is neither a PNG, a JPEG nor an SVG"),
+            Self::Malformed(type_) => write!(f, "Malformed {type_:?} icon"),
+            Self::NotSquare(width, height) => {
+                write!(f, "Expected a square icon, got {width}x{height}")
+            }
+            Self::TooBig(width, height) => write!(f, "Icon of {width}x{height} is too big"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The format and size of a launcher icon, as returned by [`validate_icon`].
+pub struct IconInfo {
+    type_: IconType,
+    size: Option<u32>,
+}
+
+impl IconInfo {
+    /// The icon format.
+    pub fn type_(&self) -> IconType {
+        self.type_
+    }
+
+    /// The width and height of the icon. Unknown for SVGs without explicit
+    /// dimensions.
+    pub fn size(&self) -> Option<u32> {
+        self.size
+    }
+}
+
+/// Check that `bytes` is a valid launcher icon.
+///
+/// Like xdg-desktop-portal, only square PNG and JPEG icons up to
+/// [`MAX_ICON_SIZE`] and SVG icons up to [`MAX_SVG_ICON_SIZE`] are accepted.
+/// [`DynamicLauncherProxy::prepare_install`] validates the icon before
+/// sending it, which backends can do as well with the icons they receive.
+pub fn validate_icon(bytes: &[u8]) -> Result<IconInfo, InvalidIconError> {
+    if bytes.len() > MAX_ICON_BYTES {
+        return Err(InvalidIconError::TooLarge(bytes.len()));
+    }
+    let (type_, size) = if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        (IconType::Png, Some(png_size(bytes)))
+    } else if bytes.starts_with(&[0xff, 0xd8, 0xff]) {
+        (IconType::Jpeg, Some(jpeg_size(bytes)))
+    } else if is_svg(bytes) {
+        (IconType::Svg, svg_size(bytes))
+    } else {
+        return Err(InvalidIconError::UnknownFormat);
+    };
+    let size = match size {
+        Some(Some(size)) => Some(size),
+        Some(None) => return Err(InvalidIconError::Malformed(type_)),
+        None => None,
+    };
+    let max = match type_ {
+        IconType::Svg => MAX_SVG_ICON_SIZE,
+        IconType::Png | IconType::Jpeg => MAX_ICON_SIZE,
+    };
+    let size = match size {
+        Some((width, height)) if width != height => {
+            return Err(InvalidIconError::NotSquare(width, height))
+        }
+        Some((width, height)) if width > max || width == 0 => {
+            return Err(InvalidIconError::TooBig(width, height))
+        }
+        size => size.map(|(width, _)| width),
+    };
+    Ok(IconInfo { type_, size })
+}
+
+/// Scale a PNG or JPEG icon rejected by [`validate_icon`] for its size down to
+/// [`MAX_ICON_SIZE`], centered on a transparent square if it isn't one, and
+/// re-encode it as a PNG.
+///
+/// The valid icons are returned as is, and the other errors, e.g. for SVG
+/// icons, are forwarded.
+#[cfg(feature = "image")]
+#[cfg_attr(docsrs, doc(cfg(feature = "image")))]
+pub fn downscale_icon(bytes: &[u8]) -> Result<std::borrow::Cow<'_, [u8]>, InvalidIconError> {
+    use image::{imageops, DynamicImage, ImageFormat, RgbaImage};
+
+    match validate_icon(bytes) {
+        Ok(_) => return Ok(std::borrow::Cow::Borrowed(bytes)),
+        Err(
+            InvalidIconError::TooLarge(_)
+            | InvalidIconError::NotSquare(..)
+            | InvalidIconError::TooBig(..),
+        ) if !is_svg(bytes) => (),
+        Err(err) => return Err(err),
+    }
+    let mut icon = image::load_from_memory(bytes).map_err(|_| InvalidIconError::UnknownFormat)?;
+    if icon.width() > MAX_ICON_SIZE || icon.height() > MAX_ICON_SIZE {
+        icon = icon.resize(MAX_ICON_SIZE, MAX_ICON_SIZE, imageops::FilterType::Lanczos3);
+    }
+    let size = icon.width().max(icon.height());
+    let mut square = RgbaImage::new(size, size);
+    imageops::overlay(
+        &mut square,
+        &icon.to_rgba8(),
+        i64::from((size - icon.width()) / 2),
+        i64::from((size - icon.height()) / 2),
+    );
+    let mut png = Vec::new();
+    DynamicImage::ImageRgba8(square)
+        .write_to(&mut std::io::Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|_| InvalidIconError::Malformed(IconType::Png))?;
+    Ok(std::borrow::Cow::Owned(png))
+}
+
+fn be_u32(bytes: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(bytes.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be_u16(bytes: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(bytes.get(at..at + 2)?.try_into().ok()?))
+}
+
+/// The size in the `IHDR` chunk, which comes first.
+fn png_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    if bytes.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    Some((be_u32(bytes, 16)?, be_u32(bytes, 20)?))
+}
+
+/// The size in the first start of frame segment.
+fn jpeg_size(bytes: &[u8]) -> Option<(u32, u32)> {
+    let mut at = 2;
+    loop {
+        if *bytes.get(at)? != 0xff {
+            return None;
+        }
+        let marker = *bytes.get(at + 1)?;
+        match marker {
+            // Padding.
+            0xff => at += 1,
+            // Markers without a payload.
+            0x01 | 0xd0..=0xd7 => at += 2,
+            0xc0..=0xcf if !matches!(marker, 0xc4 | 0xc8 | 0xcc) => {
+                let height = be_u16(bytes, at + 5)?;
+                let width = be_u16(bytes, at + 7)?;
+                return Some((width.into(), height.into()));
+            }
+            _ => at += 2 + usize::from(be_u16(bytes, at + 2)?),
+        }
+    }
+}
+
+/// The `<svg` root element, skipping the XML declaration, comments and
+/// doctype.
+fn svg_root(bytes: &[u8]) -> Option<&str> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    let start = text.find("<svg")?;
+    let end = start + text[start..].find('>')?;
+    Some(&text[start..end])
+}
+
+fn is_svg(bytes: &[u8]) -> bool {
+    let text = bytes.strip_prefix(b"\xef\xbb\xbf").unwrap_or(bytes);
+    text.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'<') && svg_root(bytes).is_some()
+}
+
+/// The size set by the `width` and `height` attributes, or by `viewBox`.
+fn svg_size(bytes: &[u8]) -> Option<Option<(u32, u32)>> {
+    let root = svg_root(bytes)?;
+    let attribute = |name: &str| {
+        let mut rest = root;
+        while let Some(at) = rest.find(name) {
+            let preceded_by_space = rest[..at].ends_with(char::is_whitespace);
+            rest = &rest[at + name.len()..];
+            if let (true, Some(value)) = (preceded_by_space, rest.strip_prefix('=')) {
+                let quote = value.chars().next()?;
+                let value = &value[1..];
+                return value.find(quote).map(|end| &value[..end]);
+            }
+        }
+        None
+    };
+    let length = |value: &str| {
+        let value = value.trim().trim_end_matches("px");
+        value.parse::<f64>().ok().map(|value| value.ceil() as u32)
+    };
+    if let (Some(width), Some(height)) = (attribute("width"), attribute("height")) {
+        if let (Some(width), Some(height)) = (length(width), length(height)) {
+            return Some(Some((width, height)));
+        }
+    }
+    let view_box = attribute("viewBox")?;
+    let mut values = view_box
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|value| !value.is_empty())
+        .skip(2)
+        .map(length);
+    Some(Some((values.next()??, values.next()??)))
+}
+
 /// The interface lets sandboxed applications install launchers like Web
 /// Application from your browser or Steam.
 ///
@@ -239,6 +638,31 @@ impl<'a> DynamicLauncherProxy<'a> {
         Ok(Self(proxy))
     }
 
+    async fn with_connection(
+        connection: Option<zbus::Connection>,
+    ) -> Result<DynamicLauncherProxy<'a>, Error> {
+        let proxy = Proxy::new_desktop_with_connection(
+            connection,
+            "org.freedesktop.portal.DynamicLauncher",
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
+    /// Create a new instance of [`DynamicLauncherProxy`] using `connection`
+    /// instead of the session bus connection, e.g. a peer-to-peer
+    /// connection to the portal.
+    pub async fn new_with_connection(
+        connection: zbus::Connection,
+    ) -> Result<DynamicLauncherProxy<'a>, Error> {
+        let proxy = Proxy::new_desktop_with_connection(
+            Some(connection),
+            "org.freedesktop.portal.DynamicLauncher",
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
     /// *Note* Only `Icon::Bytes` is accepted.
     ///
     ///  # Specifications
@@ -254,7 +678,9 @@ impl<'a> DynamicLauncherProxy<'a> {
         icon: Icon,
         options: PrepareInstallOptions,
     ) -> Result<Request<PrepareInstallResponse>, Error> {
-        if !icon.is_bytes() {
+        if let Icon::Bytes(bytes) = &icon {
+            validate_icon(bytes)?;
+        } else {
             return Err(UnexpectedIconError {}.into());
         }
 
@@ -275,7 +701,9 @@ impl<'a> DynamicLauncherProxy<'a> {
     #[doc(alias = "RequestInstallToken")]
     #[doc(alias = "xdp_portal_dynamic_launcher_request_install_token")]
     pub async fn request_install_token(&self, name: &str, icon: Icon) -> Result<String, Error> {
-        if !icon.is_bytes() {
+        if let Icon::Bytes(bytes) = &icon {
+            validate_icon(bytes)?;
+        } else {
             return Err(UnexpectedIconError {}.into());
         }
 
@@ -304,44 +732,69 @@ impl<'a> DynamicLauncherProxy<'a> {
             .await
     }
 
+    /// Fails with [`Error::LauncherNotFound`] if the launcher isn't
+    /// installed, see [`UninstallRequest`] to ask the user for confirmation.
+    ///
     /// # Specifications
     ///
     /// See also [`Uninstall`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.DynamicLauncher.html#org-freedesktop-portal-dynamiclauncher-uninstall).
     #[doc(alias = "Uninstall")]
     #[doc(alias = "xdp_portal_dynamic_launcher_uninstall")]
     pub async fn uninstall(&self, desktop_file_id: &str) -> Result<(), Error> {
-        // No supported options for now
-        let options: HashMap<&str, zvariant::Value<'_>> = HashMap::new();
-        self.0
+        self.uninstall_with_options(desktop_file_id, &UninstallOptions::default())
+            .await
+    }
+
+    async fn uninstall_with_options(
+        &self,
+        desktop_file_id: &str,
+        options: &UninstallOptions,
+    ) -> Result<(), Error> {
+        let result = self
+            .0
             .call::<()>("Uninstall", &(desktop_file_id, options))
             .await
+            .map_err(Error::into_not_allowed);
+        launcher_not_found(result, desktop_file_id)
     }
 
+    /// Fails with [`Error::LauncherNotFound`] if the launcher isn't
+    /// installed.
+    ///
     /// # Specifications
     ///
     /// See also [`GetDesktopEntry`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.DynamicLauncher.html#org-freedesktop-portal-dynamiclauncher-getdesktopentry).
     #[doc(alias = "GetDesktopEntry")]
     #[doc(alias = "xdp_portal_dynamic_launcher_get_desktop_entry")]
     pub async fn desktop_entry(&self, desktop_file_id: &str) -> Result<String, Error> {
-        self.0.call("GetDesktopEntry", &(desktop_file_id)).await
+        let result = self.0.call("GetDesktopEntry", &(desktop_file_id)).await;
+        launcher_not_found(result, desktop_file_id)
     }
 
+    /// Fails with [`Error::LauncherNotFound`] if the launcher isn't
+    /// installed.
+    ///
     /// # Specifications
     ///
     /// See also [`GetIcon`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.DynamicLauncher.html#org-freedesktop-portal-dynamiclauncher-geticon).
     #[doc(alias = "GetIcon")]
     #[doc(alias = "xdp_portal_dynamic_launcher_get_icon")]
     pub async fn icon(&self, desktop_file_id: &str) -> Result<LauncherIcon, Error> {
-        self.0.call("GetIcon", &(desktop_file_id)).await
+        let result = self.0.call("GetIcon", &(desktop_file_id)).await;
+        launcher_not_found(result, desktop_file_id)
     }
 
+    /// Fails with [`Error::LauncherNotFound`] if the launcher isn't
+    /// installed.
+    ///
     /// # Specifications
     ///
     /// See also [`Launch`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.DynamicLauncher.html#org-freedesktop-portal-dynamiclauncher-launch).
     #[doc(alias = "Launch")]
     #[doc(alias = "xdp_portal_dynamic_launcher_launch")]
     pub async fn launch(&self, desktop_file_id: &str, options: LaunchOptions) -> Result<(), Error> {
-        self.0.call("Launch", &(desktop_file_id, &options)).await
+        let result = self.0.call("Launch", &(desktop_file_id, &options)).await;
+        launcher_not_found(result, desktop_file_id)
     }
 
     /// # Specifications
@@ -365,7 +818,91 @@ impl<'a> std::ops::Deref for DynamicLauncherProxy<'a> {
 
 #[cfg(test)]
 mod test {
+    #[cfg(feature = "tokio")]
+    use std::sync::{Arc, Mutex};
+
     use super::*;
+    #[cfg(feature = "tokio")]
+    use crate::{helpers::p2p_pair, PortalError};
+
+    #[cfg(feature = "tokio")]
+    #[derive(Default)]
+    struct Portal {
+        asked: Arc<Mutex<Option<bool>>>,
+    }
+
+    #[cfg(feature = "tokio")]
+    #[zbus::interface(name = "org.freedesktop.portal.DynamicLauncher")]
+    impl Portal {
+        #[zbus(property, name = "version")]
+        fn version(&self) -> u32 {
+            1
+        }
+
+        fn launch(
+            &self,
+            desktop_file_id: &str,
+            _options: HashMap<String, zvariant::Value<'_>>,
+        ) -> Result<(), PortalError> {
+            Err(PortalError::NotFound(format!("No `{desktop_file_id}`")))
+        }
+
+        fn uninstall(
+            &self,
+            _desktop_file_id: &str,
+            options: HashMap<String, zvariant::Value<'_>>,
+        ) -> zbus::fdo::Result<()> {
+            *self.asked.lock().unwrap() =
+                options.get("ask").and_then(|ask| bool::try_from(ask).ok());
+            Ok(())
+        }
+
+        fn get_desktop_entry(&self, _desktop_file_id: &str) -> zbus::fdo::Result<String> {
+            Err(zbus::fdo::Error::Failed("Broken".to_owned()))
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn launch_and_uninstall() {
+        let portal = Portal::default();
+        let asked = Arc::clone(&portal.asked);
+        let (server, client) = p2p_pair().await;
+        server
+            .object_server()
+            .at(crate::proxy::DESKTOP_PATH, portal)
+            .await
+            .unwrap();
+
+        let launched = LaunchRequest::default()
+            .connection(client.clone())
+            .send("org.example.App.Missing.desktop")
+            .await;
+        assert!(
+            matches!(launched, Err(Error::LauncherNotFound(id)) if id == "org.example.App.Missing.desktop")
+        );
+
+        UninstallRequest::default()
+            .connection(client.clone())
+            .ask(true)
+            .send("org.example.App.WebApp.desktop")
+            .await
+            .unwrap();
+        assert_eq!(*asked.lock().unwrap(), Some(true));
+
+        let proxy = DynamicLauncherProxy::new_with_connection(client)
+            .await
+            .unwrap();
+        proxy
+            .uninstall("org.example.App.WebApp.desktop")
+            .await
+            .unwrap();
+        assert_eq!(*asked.lock().unwrap(), None);
+        assert!(matches!(
+            proxy.desktop_entry("org.example.App.WebApp.desktop").await,
+            Err(Error::Zbus(_) | Error::Portal(_))
+        ));
+    }
 
     #[test]
     fn test_icon_signature() {
@@ -375,4 +912,75 @@ mod test {
         let icon = vec![IconType::Png];
         assert_eq!(serde_json::to_string(&icon).unwrap(), "[\"png\"]");
     }
+
+    #[test]
+    fn icon_validation() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend(64u32.to_be_bytes());
+        png.extend(64u32.to_be_bytes());
+        let info = validate_icon(&png).unwrap();
+        assert_eq!((info.type_(), info.size()), (IconType::Png, Some(64)));
+        png[19] = 32;
+        assert_eq!(
+            validate_icon(&png),
+            Err(InvalidIconError::NotSquare(32, 64))
+        );
+        assert_eq!(
+            validate_icon(&png[..20]),
+            Err(InvalidIconError::Malformed(IconType::Png))
+        );
+
+        // SOI, an APP0 segment then a baseline SOF0 segment of 1024x1024.
+        let jpeg = [
+            0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x00, 0x00, 0xff, 0xc0, 0x00, 0x11, 0x08, 0x04,
+            0x00, 0x04, 0x00,
+        ];
+        assert_eq!(
+            validate_icon(&jpeg),
+            Err(InvalidIconError::TooBig(1024, 1024))
+        );
+
+        let svg = br#"<?xml version="1.0"?>
+            <svg xmlns="http://www.w3.org/2000/svg" stroke-width="2" width="128px" height="128">"#;
+        let info = validate_icon(svg).unwrap();
+        assert_eq!((info.type_(), info.size()), (IconType::Svg, Some(128)));
+        let svg = br#"<svg viewBox="0 0 16 32"></svg>"#;
+        assert_eq!(validate_icon(svg), Err(InvalidIconError::NotSquare(16, 32)));
+        let svg = br#"<svg xmlns="http://www.w3.org/2000/svg"></svg>"#;
+        assert_eq!(validate_icon(svg).unwrap().size(), None);
+
+        assert_eq!(
+            validate_icon(b"GIF89a"),
+            Err(InvalidIconError::UnknownFormat)
+        );
+        assert_eq!(
+            validate_icon(&vec![0; MAX_ICON_BYTES + 1]),
+            Err(InvalidIconError::TooLarge(MAX_ICON_BYTES + 1))
+        );
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn icon_downscaling() {
+        let mut png = Vec::new();
+        image::DynamicImage::new_rgb8(1024, 256)
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let icon = downscale_icon(&png).unwrap();
+        let info = validate_icon(&icon).unwrap();
+        assert_eq!(
+            (info.type_(), info.size()),
+            (IconType::Png, Some(MAX_ICON_SIZE))
+        );
+        assert!(matches!(
+            downscale_icon(&icon),
+            Ok(std::borrow::Cow::Borrowed(_))
+        ));
+
+        let svg = br#"<svg viewBox="0 0 16 32"></svg>"#;
+        assert_eq!(
+            downscale_icon(svg),
+            Err(InvalidIconError::NotSquare(16, 32))
+        );
+    }
 }
@@ -24,15 +24,21 @@
 //! }
 //! ```
 
-use std::os::fd::OwnedFd;
+use std::{
+    collections::HashMap,
+    os::fd::OwnedFd,
+    path::{Path, PathBuf},
+};
 
-use serde::Serialize;
-use zbus::zvariant::{self, SerializeDict, Type};
+use zbus::zvariant::{self, OwnedValue, Type};
 
 use super::{HandleToken, Request};
-use crate::{proxy::Proxy, ActivationToken, Error, WindowIdentifier};
+use crate::{
+    helpers::Redacted, proxy::Proxy, ActivationToken, Error, ExportedPath, PathPolicy,
+    WindowIdentifier,
+};
 
-#[derive(SerializeDict, Type, Debug, Default)]
+#[derive(Type, Default)]
 #[zvariant(signature = "dict")]
 struct EmailOptions {
     handle_token: HandleToken,
@@ -44,6 +50,133 @@ struct EmailOptions {
     body: Option<String>,
     attachment_fds: Option<Vec<zvariant::OwnedFd>>,
     activation_token: Option<ActivationToken>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(Serialize for EmailOptions {
+    handle_token [required] => "handle_token",
+    address => "address",
+    addresses => "addresses",
+    cc => "cc",
+    bcc => "bcc",
+    subject => "subject",
+    body => "body",
+    attachment_fds => "attachment_fds",
+    activation_token => "activation_token",
+});
+
+impl EmailOptions {
+    fn validate_addresses(&self) -> Result<(), InvalidAddressesError> {
+        let invalid = self
+            .address
+            .iter()
+            .chain(self.addresses.iter().flatten())
+            .chain(self.cc.iter().flatten())
+            .chain(self.bcc.iter().flatten())
+            .filter(|address| !is_valid_address(address))
+            .cloned()
+            .collect::<Vec<_>>();
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(InvalidAddressesError(invalid))
+        }
+    }
+}
+
+impl std::fmt::Debug for EmailOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EmailOptions")
+            .field("handle_token", &self.handle_token)
+            .field("address", &self.address.as_ref().map(Redacted))
+            .field("addresses", &self.addresses.as_ref().map(Redacted))
+            .field("cc", &self.cc.as_ref().map(Redacted))
+            .field("bcc", &self.bcc.as_ref().map(Redacted))
+            .field("subject", &self.subject.as_ref().map(Redacted))
+            .field("body", &self.body.as_ref().map(Redacted))
+            .field("attachment_fds", &self.attachment_fds)
+            .field("activation_token", &self.activation_token)
+            .finish()
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+/// The email addresses rejected by [`EmailRequest::send`].
+///
+/// Its `Display` output only counts them, unless the `debug_full` feature is
+/// enabled, see [`InvalidAddressesError::addresses`] to retrieve them.
+pub struct InvalidAddressesError(Vec<String>);
+
+impl InvalidAddressesError {
+    /// The invalid addresses, in the order they were set.
+    pub fn addresses(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for InvalidAddressesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("InvalidAddressesError")
+            .field(&Redacted(&self.0))
+            .finish()
+    }
+}
+
+impl std::error::Error for InvalidAddressesError {}
+impl std::fmt::Display for InvalidAddressesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if cfg!(feature = "debug_full") {
+            write!(f, "Invalid email addresses: {}", self.0.join(", "))
+        } else {
+            write!(f, "Invalid email addresses: {} rejected", self.0.len())
+        }
+    }
+}
+
+/// Split the entries of `addresses` on commas and semicolons, dropping the
+/// empty and duplicated addresses.
+fn split_addresses(addresses: impl IntoIterator<Item = impl AsRef<str>>) -> Vec<String> {
+    let mut split: Vec<String> = Vec::new();
+    for entry in addresses {
+        for address in entry.as_ref().split([',', ';']).map(str::trim) {
+            if !address.is_empty() && !split.iter().any(|a| a.eq_ignore_ascii_case(address)) {
+                split.push(address.to_owned());
+            }
+        }
+    }
+    split
+}
+
+/// Whether `address` is a plain `local@domain` address, a light version of
+/// the `addr-spec` of RFC 5322 without comments and quoted local parts.
+fn is_valid_address(address: &str) -> bool {
+    let Some((local, domain)) = address.rsplit_once('@') else {
+        return false;
+    };
+    let is_atext = |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-/=?^_`{|}~".contains(c);
+    let is_dot_atom = |s: &str, atext: &dyn Fn(char) -> bool| {
+        !s.is_empty()
+            && s.split('.')
+                .all(|atom| !atom.is_empty() && atom.chars().all(atext))
+    };
+    let is_label = |label: &str| {
+        label.len() <= 63
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+    };
+    let is_domain =
+        if let Some(literal) = domain.strip_prefix('[').and_then(|d| d.strip_suffix(']')) {
+            literal.parse::<std::net::Ipv4Addr>().is_ok()
+                || literal
+                    .strip_prefix("IPv6:")
+                    .is_some_and(|ip| ip.parse::<std::net::Ipv6Addr>().is_ok())
+        } else {
+            domain.len() <= 253
+                && is_dot_atom(domain, &|c| c.is_ascii_alphanumeric() || c == '-')
+                && domain.split('.').all(is_label)
+        };
+    local.len() <= 64 && is_dot_atom(local, &is_atext) && is_domain
 }
 
 #[derive(Debug)]
@@ -51,9 +184,11 @@ struct EmailOptions {
 struct EmailProxy<'a>(Proxy<'a>);
 
 impl<'a> EmailProxy<'a> {
-    /// Create a new instance of [`EmailProxy`].
-    pub async fn new() -> Result<EmailProxy<'a>, Error> {
-        let proxy = Proxy::new_desktop("org.freedesktop.portal.Email").await?;
+    /// Create a new instance of [`EmailProxy`], on `connection` or the session
+    /// bus connection.
+    pub async fn new(connection: Option<zbus::Connection>) -> Result<EmailProxy<'a>, Error> {
+        let proxy =
+            Proxy::new_desktop_with_connection(connection, "org.freedesktop.portal.Email").await?;
         Ok(Self(proxy))
     }
 
@@ -102,9 +237,29 @@ impl<'a> std::ops::Deref for EmailProxy<'a> {
 pub struct EmailRequest {
     identifier: WindowIdentifier,
     options: EmailOptions,
+    attachment_paths: Vec<(PathBuf, PathPolicy)>,
+    connection: Option<zbus::Connection>,
+    close_on_drop: Option<bool>,
 }
 
 impl EmailRequest {
+    /// Sets the connection to send the request on, instead of the session
+    /// bus connection.
+    #[must_use]
+    pub fn connection(mut self, connection: impl Into<Option<zbus::Connection>>) -> Self {
+        self.connection = connection.into();
+        self
+    }
+
+    /// Whether to close the request, dismissing its dialog, if the future
+    /// sending it is dropped before the response is received. Overrides
+    /// [`set_close_on_drop`](crate::set_close_on_drop) for this request.
+    #[must_use]
+    pub fn close_on_drop(mut self, close: bool) -> Self {
+        self.close_on_drop = Some(close);
+        self
+    }
+
     /// Sets a window identifier.
     #[must_use]
     pub fn identifier(mut self, identifier: impl Into<Option<WindowIdentifier>>) -> Self {
@@ -113,6 +268,9 @@ impl EmailRequest {
     }
 
     /// Sets the email address to send the email to.
+    ///
+    /// An invalid address makes [`send`](Self::send) fail with
+    /// [`Error::InvalidAddresses`].
     #[must_use]
     pub fn address<'a>(mut self, address: impl Into<Option<&'a str>>) -> Self {
         self.options.address = address.into().map(ToOwned::to_owned);
@@ -120,38 +278,44 @@ impl EmailRequest {
     }
 
     /// Sets a list of email addresses to send the email to.
+    ///
+    /// Each entry can itself be a list of addresses separated by commas or
+    /// semicolons. The duplicated addresses are dropped, and the invalid ones
+    /// make [`send`](Self::send) fail with [`Error::InvalidAddresses`].
     #[must_use]
-    pub fn addresses<P: IntoIterator<Item = I>, I: AsRef<str> + Type + Serialize>(
+    pub fn addresses<P: IntoIterator<Item = I>, I: AsRef<str>>(
         mut self,
         addresses: impl Into<Option<P>>,
     ) -> Self {
-        self.options.addresses = addresses
-            .into()
-            .map(|a| a.into_iter().map(|s| s.as_ref().to_owned()).collect());
+        self.options.addresses = addresses.into().map(split_addresses);
         self
     }
 
     /// Sets a list of email addresses to BCC.
+    ///
+    /// Each entry can itself be a list of addresses separated by commas or
+    /// semicolons. The duplicated addresses are dropped, and the invalid ones
+    /// make [`send`](Self::send) fail with [`Error::InvalidAddresses`].
     #[must_use]
-    pub fn bcc<P: IntoIterator<Item = I>, I: AsRef<str> + Type + Serialize>(
+    pub fn bcc<P: IntoIterator<Item = I>, I: AsRef<str>>(
         mut self,
         bcc: impl Into<Option<P>>,
     ) -> Self {
-        self.options.bcc = bcc
-            .into()
-            .map(|a| a.into_iter().map(|s| s.as_ref().to_owned()).collect());
+        self.options.bcc = bcc.into().map(split_addresses);
         self
     }
 
     /// Sets a list of email addresses to CC.
+    ///
+    /// Each entry can itself be a list of addresses separated by commas or
+    /// semicolons. The duplicated addresses are dropped, and the invalid ones
+    /// make [`send`](Self::send) fail with [`Error::InvalidAddresses`].
     #[must_use]
-    pub fn cc<P: IntoIterator<Item = I>, I: AsRef<str> + Type + Serialize>(
+    pub fn cc<P: IntoIterator<Item = I>, I: AsRef<str>>(
         mut self,
         cc: impl Into<Option<P>>,
     ) -> Self {
-        self.options.cc = cc
-            .into()
-            .map(|a| a.into_iter().map(|s| s.as_ref().to_owned()).collect());
+        self.options.cc = cc.into().map(split_addresses);
         self
     }
 
@@ -176,6 +340,15 @@ impl EmailRequest {
         self
     }
 
+    /// Attaches a file to the email from its path, handed over to the portal
+    /// following `policy` once the request is sent.
+    #[must_use]
+    pub fn attach_path(mut self, path: impl AsRef<Path>, policy: PathPolicy) -> Self {
+        self.attachment_paths
+            .push((path.as_ref().to_owned(), policy));
+        self
+    }
+
     // TODO Added in version 4 of the interface.
     /// Sets the token that can be used to activate the chosen application.
     #[must_use]
@@ -198,9 +371,92 @@ impl EmailRequest {
         };
     }
 
+    /// Sets the option `key`, unknown to this version of the crate, e.g. a
+    /// vendor extension like `x-gnome-...` to experiment with the features of
+    /// a backend. The options known to the crate can't be overridden.
+    #[must_use]
+    pub fn extra<'v>(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<zbus::zvariant::Value<'v>>,
+    ) -> Self {
+        crate::helpers::insert_extra(&mut self.options.extra, key, value);
+        self
+    }
+
     /// Send the request.
-    pub async fn send(self) -> Result<Request<()>, Error> {
-        let proxy = EmailProxy::new().await?;
+    ///
+    /// Fails with [`Error::InvalidAddresses`], before reaching the portal, if
+    /// any of the addresses is invalid: the backends would otherwise drop
+    /// them silently.
+    pub async fn send(mut self) -> Result<Request<()>, Error> {
+        self.options.validate_addresses()?;
+        for (path, policy) in std::mem::take(&mut self.attachment_paths) {
+            let fd = ExportedPath::new(path, policy).await?.into_fd()?;
+            self.add_attachment(fd);
+        }
+        let mut proxy = EmailProxy::new(self.connection).await?;
+        proxy.0.close_requests_on_drop(self.close_on_drop);
         proxy.compose(&self.identifier, self.options).await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn address_validation() {
+        for address in [
+            "test@gmail.com",
+            "first.last+tag@example.co.uk",
+            "o'hara@xn--bcher-kva.example",
+            "admin@[192.168.0.1]",
+            "admin@[IPv6:::1]",
+            "user@localhost",
+        ] {
+            assert!(is_valid_address(address), "{address}");
+        }
+        for address in [
+            "",
+            "test",
+            "@example.com",
+            "test@",
+            "a b@example.com",
+            ".test@example.com",
+            "te..st@example.com",
+            "test@example..com",
+            "test@-example.com",
+            "test@example.com.",
+            "Name <test@example.com>",
+            "test@[300.0.0.1]",
+        ] {
+            assert!(!is_valid_address(address), "{address}");
+        }
+    }
+
+    #[test]
+    fn address_lists() {
+        let addresses = split_addresses([
+            "a@example.com, b@example.com",
+            "A@example.com;c@example.com ;",
+            "",
+        ]);
+        assert_eq!(
+            addresses,
+            ["a@example.com", "b@example.com", "c@example.com"]
+        );
+
+        let request = EmailRequest::default()
+            .address("test@gmail.com")
+            .cc(["valid@example.com, invalid"])
+            .bcc(["other@"]);
+        let err = request.options.validate_addresses().unwrap_err();
+        assert_eq!(err.addresses(), ["invalid", "other@"]);
+        if cfg!(feature = "debug_full") {
+            assert_eq!(err.to_string(), "Invalid email addresses: invalid, other@");
+        } else {
+            assert_eq!(err.to_string(), "Invalid email addresses: 2 rejected");
+        }
+    }
+}
@@ -78,15 +78,42 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! #### Selecting folders
+//!
+//! ```rust,no_run
+//! use ashpd::desktop::file_chooser::SelectedFolders;
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     let folders = SelectedFolders::open_folder()
+//!         .title("pick a folder to import")
+//!         .multiple(true)
+//!         .send()
+//!         .await?
+//!         .response()?;
+//!
+//!     for path in folders.paths() {
+//!         println!("{}", path.display());
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
 
-use std::path::Path;
+use std::{
+    collections::HashMap,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
-use zbus::zvariant::{DeserializeDict, SerializeDict, Type};
+use zbus::zvariant::{OwnedValue, Type};
 
 use super::{HandleToken, Request};
-use crate::{proxy::Proxy, Error, FilePath, WindowIdentifier};
+use crate::{
+    helpers::Redacted, proxy::Proxy, Error, FilePath, FileUri, MimeType, WindowIdentifier,
+};
 
 #[derive(Clone, Serialize, Deserialize, Type, Debug, PartialEq)]
 /// A file filter, to limit the available file choices to a mimetype or a glob
@@ -123,12 +150,21 @@ impl FileFilter {
     }
 
     /// Adds a mime type to the file filter.
+    ///
+    /// See [`FileFilter::mime_type`] to validate it first.
     #[must_use]
     pub fn mimetype(mut self, mimetype: &str) -> Self {
         self.1.push((FilterType::MimeType, mimetype.to_owned()));
         self
     }
 
+    /// Adds a validated mime type to the file filter.
+    #[must_use]
+    pub fn mime_type(mut self, mime_type: &MimeType) -> Self {
+        self.1.push((FilterType::MimeType, mime_type.to_string()));
+        self
+    }
+
     /// Adds a glob pattern to the file filter.
     #[must_use]
     pub fn glob(mut self, pattern: &str) -> Self {
@@ -158,6 +194,120 @@ impl FileFilter {
             .filter_map(|(type_, string)| type_.is_pattern().then_some(string.as_str()))
             .collect()
     }
+
+    /// Whether the file name of `path` matches one of the glob patterns.
+    ///
+    /// Patterns are case-sensitive as required by the portal specification,
+    /// `*.[iI][cC][oO]` can be used to match different capitalizations.
+    /// Supported are `*`, `?` and bracket expressions like `[a-z]` or `[!0-9]`.
+    pub fn matches_path(&self, path: impl AsRef<Path>) -> bool {
+        self.matches_path_inner(path.as_ref(), false)
+    }
+
+    /// Same as [`FileFilter::matches_path`] but ignores the case of both the
+    /// patterns and the file name.
+    pub fn matches_path_ignore_case(&self, path: impl AsRef<Path>) -> bool {
+        self.matches_path_inner(path.as_ref(), true)
+    }
+
+    fn matches_path_inner(&self, path: &Path, ignore_case: bool) -> bool {
+        let Some(name) = path.file_name() else {
+            return false;
+        };
+        let name = name.to_string_lossy();
+        let normalize = |s: &str| -> Vec<char> {
+            if ignore_case {
+                s.chars().flat_map(char::to_lowercase).collect()
+            } else {
+                s.chars().collect()
+            }
+        };
+        let name = normalize(&name);
+        self.pattern_filters()
+            .into_iter()
+            .any(|pattern| glob_match(&normalize(pattern), &name))
+    }
+
+    /// Whether `mime_type` matches one of the mime type filters.
+    ///
+    /// The comparison is case-insensitive and filters like `image/*` match
+    /// any subtype.
+    pub fn matches_mime_type(&self, mime_type: &str) -> bool {
+        let Ok(mime_type) = mime_type.parse::<MimeType>() else {
+            return false;
+        };
+        self.mimetype_filters()
+            .into_iter()
+            .filter_map(|filter| filter.parse::<MimeType>().ok())
+            .any(|filter| filter.matches(&mime_type))
+    }
+
+    /// Whether the file matches the filter, either by its file name or by its
+    /// mime type if known.
+    pub fn matches(&self, path: impl AsRef<Path>, mime_type: Option<&str>) -> bool {
+        self.matches_path(path) || mime_type.is_some_and(|m| self.matches_mime_type(m))
+    }
+}
+
+// Match a shell glob pattern against the whole `text`.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Position to backtrack to if the pattern after the last `*` fails to match
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        let advanced = match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(p + 1),
+            Some('[') => match_bracket(&pattern[p..], text[t]).map(|len| p + len),
+            Some(c) => (*c == text[t]).then_some(p + 1),
+            None => None,
+        };
+        match (advanced, backtrack) {
+            (Some(next), _) => {
+                p = next;
+                t += 1;
+            }
+            (None, Some((star, start))) => {
+                p = star + 1;
+                t = start + 1;
+                backtrack = Some((star, start + 1));
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+// Match a bracket expression at the start of `pattern`, returning its length
+// if `c` is part of it.
+fn match_bracket(pattern: &[char], c: char) -> Option<usize> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some('!') | Some('^'));
+    if negated {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let start = *pattern.get(i)?;
+        if start == ']' && !first {
+            break;
+        }
+        first = false;
+        if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|e| *e != ']') {
+            let end = pattern[i + 2];
+            matched |= (start..=end).contains(&c);
+            i += 3;
+        } else {
+            matched |= start == c;
+            i += 1;
+        }
+    }
+    (matched != negated).then_some(i + 1)
 }
 
 #[derive(Clone, Serialize, Deserialize, Type, Debug)]
@@ -223,7 +373,7 @@ impl Choice {
     }
 }
 
-#[derive(SerializeDict, Type, Debug, Default)]
+#[derive(Type, Default)]
 #[zvariant(signature = "dict")]
 struct OpenFileOptions {
     handle_token: HandleToken,
@@ -235,9 +385,42 @@ struct OpenFileOptions {
     current_filter: Option<FileFilter>,
     choices: Option<Vec<Choice>>,
     current_folder: Option<FilePath>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(Serialize for OpenFileOptions {
+    handle_token [required] => "handle_token",
+    accept_label => "accept_label",
+    modal => "modal",
+    multiple => "multiple",
+    directory => "directory",
+    filters [required] => "filters",
+    current_filter => "current_filter",
+    choices => "choices",
+    current_folder => "current_folder",
+});
+
+impl std::fmt::Debug for OpenFileOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OpenFileOptions")
+            .field("handle_token", &self.handle_token)
+            .field("accept_label", &self.accept_label)
+            .field("modal", &self.modal)
+            .field("multiple", &self.multiple)
+            .field("directory", &self.directory)
+            .field("filters", &self.filters)
+            .field("current_filter", &self.current_filter)
+            .field("choices", &self.choices)
+            .field(
+                "current_folder",
+                &self.current_folder.as_ref().map(Redacted),
+            )
+            .field("extra", &self.extra)
+            .finish()
+    }
 }
 
-#[derive(SerializeDict, Type, Debug, Default)]
+#[derive(Type, Default)]
 #[zvariant(signature = "dict")]
 struct SaveFileOptions {
     handle_token: HandleToken,
@@ -249,9 +432,42 @@ struct SaveFileOptions {
     filters: Vec<FileFilter>,
     current_filter: Option<FileFilter>,
     choices: Option<Vec<Choice>>,
+    extra: HashMap<String, OwnedValue>,
 }
 
-#[derive(SerializeDict, Type, Debug, Default)]
+crate::helpers::impl_dict_with_extra!(Serialize for SaveFileOptions {
+    handle_token [required] => "handle_token",
+    accept_label => "accept_label",
+    modal => "modal",
+    current_name => "current_name",
+    current_folder => "current_folder",
+    current_file => "current_file",
+    filters [required] => "filters",
+    current_filter => "current_filter",
+    choices => "choices",
+});
+
+impl std::fmt::Debug for SaveFileOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SaveFileOptions")
+            .field("handle_token", &self.handle_token)
+            .field("accept_label", &self.accept_label)
+            .field("modal", &self.modal)
+            .field("current_name", &self.current_name.as_ref().map(Redacted))
+            .field(
+                "current_folder",
+                &self.current_folder.as_ref().map(Redacted),
+            )
+            .field("current_file", &self.current_file.as_ref().map(Redacted))
+            .field("filters", &self.filters)
+            .field("current_filter", &self.current_filter)
+            .field("choices", &self.choices)
+            .field("extra", &self.extra)
+            .finish()
+    }
+}
+
+#[derive(Type, Default)]
 #[zvariant(signature = "dict")]
 struct SaveFilesOptions {
     handle_token: HandleToken,
@@ -260,15 +476,65 @@ struct SaveFilesOptions {
     choices: Option<Vec<Choice>>,
     current_folder: Option<FilePath>,
     files: Option<Vec<FilePath>>,
+    extra: HashMap<String, OwnedValue>,
 }
 
-#[derive(Debug, Type, DeserializeDict)]
+crate::helpers::impl_dict_with_extra!(Serialize for SaveFilesOptions {
+    handle_token [required] => "handle_token",
+    accept_label => "accept_label",
+    modal => "modal",
+    choices => "choices",
+    current_folder => "current_folder",
+    files => "files",
+});
+
+impl std::fmt::Debug for SaveFilesOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SaveFilesOptions")
+            .field("handle_token", &self.handle_token)
+            .field("accept_label", &self.accept_label)
+            .field("modal", &self.modal)
+            .field("choices", &self.choices)
+            .field(
+                "current_folder",
+                &self.current_folder.as_ref().map(Redacted),
+            )
+            .field("files", &self.files.as_ref().map(Redacted))
+            .field("extra", &self.extra)
+            .finish()
+    }
+}
+
+#[derive(Type)]
 /// A response of [`OpenFileRequest`], [`SaveFileRequest`] or
 /// [`SaveFilesRequest`].
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct SelectedFiles {
     uris: Vec<url::Url>,
     choices: Option<Vec<(String, String)>>,
+    current_filter: Option<FileFilter>,
+    current_folder: Option<FilePath>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(SelectedFiles {
+    uris [required] => "uris",
+    choices => "choices",
+    current_filter => "current_filter",
+    current_folder => "current_folder",
+});
+
+impl std::fmt::Debug for SelectedFiles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SelectedFiles")
+            .field("uris", &Redacted(&self.uris))
+            .field("choices", &self.choices)
+            .field("current_filter", &self.current_filter)
+            .field("current_folder", &Redacted(&self.current_folder))
+            .field("extra", &self.extra)
+            .finish()
+    }
 }
 
 impl SelectedFiles {
@@ -292,28 +558,135 @@ impl SelectedFiles {
         self.uris.as_slice()
     }
 
+    /// The selected files, skipping the uris that aren't local `file://`
+    /// ones.
+    pub fn files(&self) -> impl Iterator<Item = FileUri> + '_ {
+        self.uris
+            .iter()
+            .filter_map(|uri| FileUri::try_from(uri).ok())
+    }
+
     /// The selected value of each choice as a tuple of (key, value)
     pub fn choices(&self) -> &[(String, String)] {
         self.choices.as_deref().unwrap_or_default()
     }
+
+    /// The filter that was selected, if the portal implementation reported
+    /// it.
+    ///
+    /// Pass it to [`OpenFileRequest::current_filter`] or
+    /// [`SaveFileRequest::current_filter`] to select it again the next time,
+    /// see also [`FileChooserHistory`](crate::file_chooser_history::FileChooserHistory).
+    pub fn current_filter(&self) -> Option<&FileFilter> {
+        self.current_filter.as_ref()
+    }
+
+    /// The folder the dialog was showing when it was closed, if the portal
+    /// implementation reported it.
+    ///
+    /// Unlike the parent folder of the selected files, it is known even when
+    /// the user selected nothing or a non local file.
+    pub fn current_folder(&self) -> Option<&Path> {
+        self.current_folder.as_ref().map(AsRef::as_ref)
+    }
+
+    /// The entries of the response unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+}
+
+#[derive(Debug, Type, Deserialize)]
+#[serde(transparent)]
+/// A response of [`OpenFileRequest::directories`], the selected directories.
+///
+/// Unlike [`SelectedFiles`], it has no accessors meant for files, e.g. the
+/// selected filter.
+pub struct SelectedFolders(SelectedFiles);
+
+impl SelectedFolders {
+    /// Start an open file request selecting directories.
+    pub fn open_folder() -> OpenFileRequest<SelectedFolders> {
+        SelectedFiles::open_file().directories()
+    }
+
+    /// The selected directories uris.
+    pub fn uris(&self) -> &[url::Url] {
+        self.0.uris()
+    }
+
+    /// The paths of the selected directories, skipping the uris that aren't
+    /// local `file://` ones.
+    pub fn paths(&self) -> impl Iterator<Item = PathBuf> + '_ {
+        self.0.files().map(PathBuf::from)
+    }
+
+    /// Like [`SelectedFolders::paths`], consuming the response.
+    pub fn into_paths(self) -> Vec<PathBuf> {
+        self.paths().collect()
+    }
+
+    /// The selected value of each choice as a tuple of (key, value)
+    pub fn choices(&self) -> &[(String, String)] {
+        self.0.choices()
+    }
+
+    /// The folder the dialog was showing when it was closed, if the portal
+    /// implementation reported it.
+    pub fn current_folder(&self) -> Option<&Path> {
+        self.0.current_folder()
+    }
+
+    /// The entries of the response unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        self.0.extra()
+    }
+}
+
+impl From<SelectedFiles> for SelectedFolders {
+    /// The response of an [`OpenFileRequest`] with
+    /// [`OpenFileRequest::directory`] set.
+    fn from(files: SelectedFiles) -> Self {
+        Self(files)
+    }
+}
+
+impl From<SelectedFolders> for SelectedFiles {
+    fn from(folders: SelectedFolders) -> Self {
+        folders.0
+    }
 }
 
 #[doc(alias = "org.freedesktop.portal.FileChooser")]
 struct FileChooserProxy<'a>(Proxy<'a>);
 
 impl<'a> FileChooserProxy<'a> {
-    /// Create a new instance of [`FileChooserProxy`].
-    pub async fn new() -> Result<FileChooserProxy<'a>, Error> {
-        let proxy = Proxy::new_desktop("org.freedesktop.portal.FileChooser").await?;
+    /// Create a new instance of [`FileChooserProxy`], on `connection` or the
+    /// session bus connection.
+    pub async fn new(connection: Option<zbus::Connection>) -> Result<FileChooserProxy<'a>, Error> {
+        let proxy =
+            Proxy::new_desktop_with_connection(connection, "org.freedesktop.portal.FileChooser")
+                .await?;
         Ok(Self(proxy))
     }
 
-    pub async fn open_file(
+    pub async fn open_file<T>(
         &self,
         identifier: &WindowIdentifier,
         title: &str,
         options: OpenFileOptions,
-    ) -> Result<Request<SelectedFiles>, Error> {
+    ) -> Result<Request<T>, Error>
+    where
+        T: for<'de> Deserialize<'de> + Type + std::fmt::Debug,
+    {
+        self.0
+            .check_option("OpenFile", "directory", 3, options.directory.is_some())?;
+        self.0.check_option(
+            "OpenFile",
+            "current_folder",
+            4,
+            options.current_folder.is_some(),
+        )?;
         self.0
             .request(
                 &options.handle_token,
@@ -362,18 +735,83 @@ impl<'a> std::ops::Deref for FileChooserProxy<'a> {
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 #[doc(alias = "xdp_portal_open_file")]
 /// A [builder-pattern] type to open a file.
 ///
+/// `T` is the response, [`SelectedFiles`], or [`SelectedFolders`] once
+/// [`OpenFileRequest::directories`] is used.
+///
 /// [builder-pattern]: https://doc.rust-lang.org/1.0.0/style/ownership/builders.html
-pub struct OpenFileRequest {
+pub struct OpenFileRequest<T = SelectedFiles> {
     identifier: WindowIdentifier,
     title: String,
     options: OpenFileOptions,
+    connection: Option<zbus::Connection>,
+    close_on_drop: Option<bool>,
+    response: PhantomData<fn() -> T>,
+}
+
+impl<T> Default for OpenFileRequest<T> {
+    fn default() -> Self {
+        Self {
+            identifier: Default::default(),
+            title: Default::default(),
+            options: Default::default(),
+            connection: None,
+            close_on_drop: None,
+            response: PhantomData,
+        }
+    }
 }
 
 impl OpenFileRequest {
+    /// Sets whether to select directories or not.
+    ///
+    /// The response is still a [`SelectedFiles`], prefer
+    /// [`OpenFileRequest::directories`] to select directories.
+    #[must_use]
+    pub fn directory(mut self, directory: impl Into<Option<bool>>) -> Self {
+        self.options.directory = directory.into();
+        self
+    }
+
+    /// Select directories instead of files, the response becoming a
+    /// [`SelectedFolders`].
+    #[must_use]
+    pub fn directories(self) -> OpenFileRequest<SelectedFolders> {
+        OpenFileRequest {
+            identifier: self.identifier,
+            title: self.title,
+            options: OpenFileOptions {
+                directory: Some(true),
+                ..self.options
+            },
+            connection: self.connection,
+            close_on_drop: self.close_on_drop,
+            response: PhantomData,
+        }
+    }
+}
+
+impl<T> OpenFileRequest<T> {
+    /// Sets the connection to send the request on, instead of the session
+    /// bus connection.
+    #[must_use]
+    pub fn connection(mut self, connection: impl Into<Option<zbus::Connection>>) -> Self {
+        self.connection = connection.into();
+        self
+    }
+
+    /// Whether to close the request, dismissing its dialog, if the future
+    /// sending it is dropped before the response is received. Overrides
+    /// [`set_close_on_drop`](crate::set_close_on_drop) for this request.
+    #[must_use]
+    pub fn close_on_drop(mut self, close: bool) -> Self {
+        self.close_on_drop = Some(close);
+        self
+    }
+
     #[must_use]
     /// Sets a window identifier.
     pub fn identifier(mut self, identifier: impl Into<Option<WindowIdentifier>>) -> Self {
@@ -409,13 +847,6 @@ impl OpenFileRequest {
         self
     }
 
-    /// Sets whether to select directories or not.
-    #[must_use]
-    pub fn directory(mut self, directory: impl Into<Option<bool>>) -> Self {
-        self.options.directory = directory.into();
-        self
-    }
-
     /// Adds a files filter.
     #[must_use]
     pub fn filter(mut self, filter: FileFilter) -> Self {
@@ -466,9 +897,26 @@ impl OpenFileRequest {
         Ok(self)
     }
 
+    /// Sets the option `key`, unknown to this version of the crate, e.g. a
+    /// vendor extension like `x-gnome-...` to experiment with the features of
+    /// a backend. The options known to the crate can't be overridden.
+    #[must_use]
+    pub fn extra<'v>(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<zbus::zvariant::Value<'v>>,
+    ) -> Self {
+        crate::helpers::insert_extra(&mut self.options.extra, key, value);
+        self
+    }
+
     /// Send the request.
-    pub async fn send(self) -> Result<Request<SelectedFiles>, Error> {
-        let proxy = FileChooserProxy::new().await?;
+    pub async fn send(self) -> Result<Request<T>, Error>
+    where
+        T: for<'de> Deserialize<'de> + Type + std::fmt::Debug,
+    {
+        let mut proxy = FileChooserProxy::new(self.connection).await?;
+        proxy.0.close_requests_on_drop(self.close_on_drop);
         proxy
             .open_file(&self.identifier, &self.title, self.options)
             .await
@@ -484,9 +932,28 @@ pub struct SaveFilesRequest {
     identifier: WindowIdentifier,
     title: String,
     options: SaveFilesOptions,
+    connection: Option<zbus::Connection>,
+    close_on_drop: Option<bool>,
 }
 
 impl SaveFilesRequest {
+    /// Sets the connection to send the request on, instead of the session
+    /// bus connection.
+    #[must_use]
+    pub fn connection(mut self, connection: impl Into<Option<zbus::Connection>>) -> Self {
+        self.connection = connection.into();
+        self
+    }
+
+    /// Whether to close the request, dismissing its dialog, if the future
+    /// sending it is dropped before the response is received. Overrides
+    /// [`set_close_on_drop`](crate::set_close_on_drop) for this request.
+    #[must_use]
+    pub fn close_on_drop(mut self, close: bool) -> Self {
+        self.close_on_drop = Some(close);
+        self
+    }
+
     #[must_use]
     /// Sets a window identifier.
     pub fn identifier(mut self, identifier: impl Into<Option<WindowIdentifier>>) -> Self {
@@ -556,9 +1023,23 @@ impl SaveFilesRequest {
         Ok(self)
     }
 
+    /// Sets the option `key`, unknown to this version of the crate, e.g. a
+    /// vendor extension like `x-gnome-...` to experiment with the features of
+    /// a backend. The options known to the crate can't be overridden.
+    #[must_use]
+    pub fn extra<'v>(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<zbus::zvariant::Value<'v>>,
+    ) -> Self {
+        crate::helpers::insert_extra(&mut self.options.extra, key, value);
+        self
+    }
+
     /// Send the request.
     pub async fn send(self) -> Result<Request<SelectedFiles>, Error> {
-        let proxy = FileChooserProxy::new().await?;
+        let mut proxy = FileChooserProxy::new(self.connection).await?;
+        proxy.0.close_requests_on_drop(self.close_on_drop);
         proxy
             .save_files(&self.identifier, &self.title, self.options)
             .await
@@ -574,9 +1055,28 @@ pub struct SaveFileRequest {
     identifier: WindowIdentifier,
     title: String,
     options: SaveFileOptions,
+    connection: Option<zbus::Connection>,
+    close_on_drop: Option<bool>,
 }
 
 impl SaveFileRequest {
+    /// Sets the connection to send the request on, instead of the session
+    /// bus connection.
+    #[must_use]
+    pub fn connection(mut self, connection: impl Into<Option<zbus::Connection>>) -> Self {
+        self.connection = connection.into();
+        self
+    }
+
+    /// Whether to close the request, dismissing its dialog, if the future
+    /// sending it is dropped before the response is received. Overrides
+    /// [`set_close_on_drop`](crate::set_close_on_drop) for this request.
+    #[must_use]
+    pub fn close_on_drop(mut self, close: bool) -> Self {
+        self.close_on_drop = Some(close);
+        self
+    }
+
     #[must_use]
     /// Sets a window identifier.
     pub fn identifier(mut self, identifier: impl Into<Option<WindowIdentifier>>) -> Self {
@@ -671,11 +1171,115 @@ impl SaveFileRequest {
         self
     }
 
+    /// Sets the option `key`, unknown to this version of the crate, e.g. a
+    /// vendor extension like `x-gnome-...` to experiment with the features of
+    /// a backend. The options known to the crate can't be overridden.
+    #[must_use]
+    pub fn extra<'v>(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<zbus::zvariant::Value<'v>>,
+    ) -> Self {
+        crate::helpers::insert_extra(&mut self.options.extra, key, value);
+        self
+    }
+
     /// Send the request.
     pub async fn send(self) -> Result<Request<SelectedFiles>, Error> {
-        let proxy = FileChooserProxy::new().await?;
+        let mut proxy = FileChooserProxy::new(self.connection).await?;
+        proxy.0.close_requests_on_drop(self.close_on_drop);
         proxy
             .save_file(&self.identifier, &self.title, self.options)
             .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacted_options() {
+        let options = SaveFileOptions {
+            current_name: Some("report.pdf".to_owned()),
+            ..Default::default()
+        };
+        let debug = format!("{options:?}");
+        assert_eq!(debug.contains("report.pdf"), cfg!(feature = "debug_full"));
+    }
+
+    #[test]
+    fn glob_patterns() {
+        let filter = FileFilter::new("Images")
+            .glob("*.png")
+            .glob("*.[jJ][pP][gG]")
+            .glob("photo-??.raw")
+            .glob("scan[!0-9]*");
+        assert!(filter.matches_path("/home/user/a.png"));
+        assert!(filter.matches_path("a.b.png"));
+        assert!(!filter.matches_path("a.PNG"));
+        assert!(filter.matches_path_ignore_case("a.PNG"));
+        assert!(filter.matches_path("b.JpG"));
+        assert!(filter.matches_path("photo-01.raw"));
+        assert!(!filter.matches_path("photo-1.raw"));
+        assert!(filter.matches_path("scan_a.tiff"));
+        assert!(!filter.matches_path("scan1.tiff"));
+        assert!(!filter.matches_path("png"));
+        assert!(!filter.matches_path("/"));
+
+        let filter = FileFilter::new("Any").glob("*");
+        assert!(filter.matches_path(".hidden"));
+        let filter = FileFilter::new("Closing bracket").glob("[]a]*");
+        assert!(filter.matches_path("]x"));
+        assert!(!filter.matches_path("bx"));
+        let filter = FileFilter::new("Unicode").glob("ÉTÉ*");
+        assert!(filter.matches_path_ignore_case("été.txt"));
+    }
+
+    #[test]
+    fn mime_types() {
+        let filter = FileFilter::new("Media")
+            .mimetype("image/*")
+            .mimetype("text/plain");
+        assert!(filter.matches_mime_type("image/png"));
+        assert!(filter.matches_mime_type("Text/Plain"));
+        assert!(!filter.matches_mime_type("text/html"));
+        assert!(!filter.matches_mime_type("invalid"));
+        assert!(filter.matches("file.unknown", Some("image/jpeg")));
+        assert!(!filter.matches("file.png", None));
+    }
+
+    #[test]
+    fn selected_folders() {
+        use zbus::zvariant::{serialized::Context, to_bytes, Value, LE};
+
+        let response = HashMap::from([
+            (
+                "uris",
+                Value::from(vec![
+                    "file:///home/user/My%20Music",
+                    "sftp://example.org/backup",
+                ]),
+            ),
+            ("current_folder", Value::from(b"/home/user\0".as_slice())),
+        ]);
+        let encoded = to_bytes(Context::new_dbus(LE, 0), &response).unwrap();
+        let folders: SelectedFolders = encoded.deserialize().unwrap().0;
+        assert_eq!(folders.uris().len(), 2);
+        assert_eq!(
+            folders.paths().collect::<Vec<_>>(),
+            [PathBuf::from("/home/user/My Music")]
+        );
+        assert_eq!(folders.current_folder(), Some(Path::new("/home/user")));
+
+        let files = SelectedFiles::from(folders);
+        assert_eq!(files.files().count(), 1);
+        assert_eq!(
+            SelectedFolders::from(files).into_paths(),
+            [PathBuf::from("/home/user/My Music")]
+        );
+
+        let request = SelectedFolders::open_folder();
+        assert_eq!(request.options.directory, Some(true));
+    }
+}
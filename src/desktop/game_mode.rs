@@ -38,6 +38,8 @@ pub enum Status {
     Rejected = -1,
 }
 
+crate::helpers::impl_value_conversions!(Status as i32 { Inactive, Active, Registered, Rejected });
+
 #[derive(Deserialize_repr, PartialEq, Eq, Debug, Type)]
 #[repr(i32)]
 /// The status of a (un-)register game mode request.
@@ -83,6 +85,15 @@ impl<'a> GameMode<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`GameMode`] using `connection` instead of the
+    /// session bus connection, e.g. a peer-to-peer connection to the portal.
+    pub async fn new_with_connection(connection: zbus::Connection) -> Result<GameMode<'a>, Error> {
+        let proxy =
+            Proxy::new_desktop_with_connection(Some(connection), "org.freedesktop.portal.GameMode")
+                .await?;
+        Ok(Self(proxy))
+    }
+
     /// Query the GameMode status for a process.
     /// If the caller is running inside a sandbox with pid namespace isolation,
     /// the pid will be translated to the respective host pid.
@@ -0,0 +1,307 @@
+use std::ops::{Add, Sub};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::zvariant::Type;
+
+/// A position, e.g. of a stream or a zone in the compositor coordinate space.
+///
+/// The compositor coordinate space is a logical one, a position in pixels
+/// of a scaled output is obtained with [`Position::scale`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+pub struct Position {
+    x: i32,
+    y: i32,
+}
+
+impl Position {
+    /// Create a new position.
+    pub const fn new(x: i32, y: i32) -> Self {
+        Self { x, y }
+    }
+
+    /// The horizontal coordinate.
+    pub fn x(self) -> i32 {
+        self.x
+    }
+
+    /// The vertical coordinate.
+    pub fn y(self) -> i32 {
+        self.y
+    }
+
+    /// The position multiplied by `factor`, e.g. the scale of an output to
+    /// convert a logical position to a physical one, rounded to the nearest
+    /// integer.
+    #[must_use]
+    pub fn scale(self, factor: f64) -> Self {
+        Self::new(scale(self.x, factor), scale(self.y, factor))
+    }
+}
+
+impl Add for Position {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.x.saturating_add(rhs.x), self.y.saturating_add(rhs.y))
+    }
+}
+
+impl Sub for Position {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.x.saturating_sub(rhs.x), self.y.saturating_sub(rhs.y))
+    }
+}
+
+impl From<(i32, i32)> for Position {
+    fn from((x, y): (i32, i32)) -> Self {
+        Self::new(x, y)
+    }
+}
+
+impl From<Position> for (i32, i32) {
+    fn from(position: Position) -> Self {
+        (position.x, position.y)
+    }
+}
+
+/// A size, e.g. of a stream or a zone in the compositor coordinate space.
+///
+/// See [`Position`] for the coordinate space.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize, Type)]
+pub struct Size {
+    width: u32,
+    height: u32,
+}
+
+impl Size {
+    /// Create a new size.
+    pub const fn new(width: u32, height: u32) -> Self {
+        Self { width, height }
+    }
+
+    /// The width.
+    pub fn width(self) -> u32 {
+        self.width
+    }
+
+    /// The height.
+    pub fn height(self) -> u32 {
+        self.height
+    }
+
+    /// Whether the size has no area.
+    pub fn is_empty(self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// The size multiplied by `factor`, e.g. the scale of an output to
+    /// convert a logical size to a physical one, rounded to the nearest
+    /// integer.
+    #[must_use]
+    pub fn scale(self, factor: f64) -> Self {
+        let length = |value: u32| {
+            (f64::from(value) * factor)
+                .round()
+                .clamp(0., u32::MAX.into()) as u32
+        };
+        Self::new(length(self.width), length(self.height))
+    }
+}
+
+impl From<(u32, u32)> for Size {
+    fn from((width, height): (u32, u32)) -> Self {
+        Self::new(width, height)
+    }
+}
+
+impl From<Size> for (u32, u32) {
+    fn from(size: Size) -> Self {
+        (size.width, size.height)
+    }
+}
+
+/// A rectangle, e.g. a zone of
+/// [`InputCapture`](crate::desktop::input_capture::InputCapture) or the area
+/// a stream covers in the compositor coordinate space.
+///
+/// See [`Position`] for the coordinate space.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Type)]
+#[zvariant(signature = "(uuii)")]
+pub struct Region {
+    position: Position,
+    size: Size,
+}
+
+impl Region {
+    /// Create a new region of `size` with its top-left corner at `position`.
+    pub const fn new(position: Position, size: Size) -> Self {
+        Self { position, size }
+    }
+
+    /// The position of the top-left corner.
+    pub fn position(self) -> Position {
+        self.position
+    }
+
+    /// The size.
+    pub fn size(self) -> Size {
+        self.size
+    }
+
+    /// The width.
+    pub fn width(self) -> u32 {
+        self.size.width
+    }
+
+    /// The height.
+    pub fn height(self) -> u32 {
+        self.size.height
+    }
+
+    /// The x offset.
+    pub fn x_offset(self) -> i32 {
+        self.position.x
+    }
+
+    /// The y offset.
+    pub fn y_offset(self) -> i32 {
+        self.position.y
+    }
+
+    /// Whether `position` is inside the region.
+    pub fn contains(self, position: Position) -> bool {
+        let (x, y) = (i64::from(position.x), i64::from(position.y));
+        x >= self.left() && x < self.right() && y >= self.top() && y < self.bottom()
+    }
+
+    /// The region covered by both regions, if any.
+    pub fn intersection(self, other: Self) -> Option<Self> {
+        let left = self.left().max(other.left());
+        let top = self.top().max(other.top());
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+        (left < right && top < bottom).then(|| Self::from_edges(left, top, right, bottom))
+    }
+
+    /// The smallest region containing both regions.
+    #[must_use]
+    pub fn union(self, other: Self) -> Self {
+        Self::from_edges(
+            self.left().min(other.left()),
+            self.top().min(other.top()),
+            self.right().max(other.right()),
+            self.bottom().max(other.bottom()),
+        )
+    }
+
+    /// The region moved by `offset`.
+    #[must_use]
+    pub fn translate(self, offset: Position) -> Self {
+        Self::new(self.position + offset, self.size)
+    }
+
+    /// The region multiplied by `factor`, see [`Position::scale`].
+    #[must_use]
+    pub fn scale(self, factor: f64) -> Self {
+        Self::new(self.position.scale(factor), self.size.scale(factor))
+    }
+
+    fn left(self) -> i64 {
+        self.position.x.into()
+    }
+
+    fn top(self) -> i64 {
+        self.position.y.into()
+    }
+
+    fn right(self) -> i64 {
+        self.left() + i64::from(self.size.width)
+    }
+
+    fn bottom(self) -> i64 {
+        self.top() + i64::from(self.size.height)
+    }
+
+    fn from_edges(left: i64, top: i64, right: i64, bottom: i64) -> Self {
+        let clamp = |value: i64| value.clamp(i32::MIN.into(), i32::MAX.into()) as i32;
+        let length = |value: i64| value.clamp(0, u32::MAX.into()) as u32;
+        Self::new(
+            Position::new(clamp(left), clamp(top)),
+            Size::new(length(right - left), length(bottom - top)),
+        )
+    }
+}
+
+impl Serialize for Region {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (
+            self.size.width,
+            self.size.height,
+            self.position.x,
+            self.position.y,
+        )
+            .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Region {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (width, height, x, y) = <(u32, u32, i32, i32)>::deserialize(deserializer)?;
+        Ok(Self::new(Position::new(x, y), Size::new(width, height)))
+    }
+}
+
+fn scale(value: i32, factor: f64) -> i32 {
+    (f64::from(value) * factor)
+        .round()
+        .clamp(i32::MIN.into(), i32::MAX.into()) as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::zvariant::{serialized::Context, to_bytes, LE};
+
+    use super::*;
+
+    #[test]
+    fn region() {
+        let region = Region::new(Position::new(10, 20), Size::new(100, 50));
+        assert!(region.contains(Position::new(10, 20)));
+        assert!(region.contains(Position::new(109, 69)));
+        assert!(!region.contains(Position::new(110, 20)));
+
+        let other = Region::new(Position::new(60, -10), Size::new(100, 40));
+        assert_eq!(
+            region.intersection(other),
+            Some(Region::new(Position::new(60, 20), Size::new(50, 10)))
+        );
+        assert_eq!(
+            region.union(other),
+            Region::new(Position::new(10, -10), Size::new(150, 80))
+        );
+        let far = region.translate(Position::new(1000, 0));
+        assert_eq!(far.position(), Position::new(1010, 20));
+        assert_eq!(region.intersection(far), None);
+        assert_eq!(
+            region.scale(1.5),
+            Region::new(Position::new(15, 30), Size::new(150, 75))
+        );
+    }
+
+    #[test]
+    fn wire_format() {
+        assert_eq!(Region::signature(), "(uuii)");
+        assert_eq!(Position::signature(), "(ii)");
+        assert_eq!(Size::signature(), "(uu)");
+
+        let region = Region::new(Position::new(-5, 7), Size::new(1920, 1080));
+        let ctxt = Context::new_dbus(LE, 0);
+        let data = to_bytes(ctxt, &region).unwrap();
+        let (width, height, x, y): (u32, u32, i32, i32) = data.deserialize().unwrap().0;
+        assert_eq!((width, height, x, y), (1920, 1080, -5, 7));
+        assert_eq!(data.deserialize::<Region>().unwrap().0, region);
+    }
+}
@@ -9,7 +9,10 @@ use zbus::zvariant::{
 };
 
 use super::{session::SessionPortal, HandleToken, Request, Session};
-use crate::{desktop::session::CreateSessionResponse, proxy::Proxy, Error, WindowIdentifier};
+use crate::{
+    desktop::session::CreateSessionResponse, proxy::Proxy, shortcuts::Trigger, Error,
+    WindowIdentifier,
+};
 
 #[derive(Clone, SerializeDict, Type, Debug, Default)]
 #[zvariant(signature = "dict")]
@@ -45,6 +48,16 @@ impl NewShortcut {
         self.1.preferred_trigger = preferred_trigger.into().map(ToOwned::to_owned);
         self
     }
+
+    /// Sets the preferred shortcut trigger from a parsed [`Trigger`].
+    #[must_use]
+    pub fn preferred_trigger_typed(
+        mut self,
+        preferred_trigger: impl Into<Option<Trigger>>,
+    ) -> Self {
+        self.1.preferred_trigger = preferred_trigger.into().map(|t| t.to_string());
+        self
+    }
 }
 
 #[derive(Clone, DeserializeDict, Type, Debug, Default)]
@@ -101,17 +114,28 @@ struct BindShortcutsOptions {
 }
 
 /// A response to a [`GlobalShortcuts::bind_shortcuts`] request.
-#[derive(DeserializeDict, Type, Debug)]
+#[derive(Type, Debug)]
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct BindShortcuts {
     shortcuts: Vec<Shortcut>,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(BindShortcuts {
+    shortcuts [required] => "shortcuts",
+});
+
 impl BindShortcuts {
     /// A list of shortcuts.
     pub fn shortcuts(&self) -> &[Shortcut] {
         &self.shortcuts
     }
+
+    /// The entries of the response unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
 /// Specified options for a [`GlobalShortcuts::list_shortcuts`] request.
@@ -123,18 +147,29 @@ struct ListShortcutsOptions {
 }
 
 /// A response to a [`GlobalShortcuts::list_shortcuts`] request.
-#[derive(DeserializeDict, Type, Debug)]
+#[derive(Type, Debug)]
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct ListShortcuts {
     /// A list of shortcuts.
     shortcuts: Vec<Shortcut>,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(ListShortcuts {
+    shortcuts [required] => "shortcuts",
+});
+
 impl ListShortcuts {
     /// A list of shortcuts.
     pub fn shortcuts(&self) -> &[Shortcut] {
         &self.shortcuts
     }
+
+    /// The entries of the response unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
 /// Notifies about a shortcut becoming active.
@@ -218,6 +253,20 @@ impl<'a> GlobalShortcuts<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`GlobalShortcuts`] using `connection` instead
+    /// of the session bus connection, e.g. a peer-to-peer connection to the
+    /// portal.
+    pub async fn new_with_connection(
+        connection: zbus::Connection,
+    ) -> Result<GlobalShortcuts<'a>, Error> {
+        let proxy = Proxy::new_desktop_with_connection(
+            Some(connection),
+            "org.freedesktop.portal.GlobalShortcuts",
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
     /// Create a global shortcuts session.
     ///
     /// # Specifications
@@ -225,14 +274,18 @@ impl<'a> GlobalShortcuts<'a> {
     /// See also [`CreateSession`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.GlobalShortcuts.html#org-freedesktop-portal-globalshortcuts-createsession).
     #[doc(alias = "CreateSession")]
     pub async fn create_session(&self) -> Result<Session<'a, Self>, Error> {
-        let options = CreateSessionOptions::default();
+        let session_handle_token = HandleToken::default();
+        let options = CreateSessionOptions {
+            handle_token: session_handle_token.derive(0),
+            session_handle_token,
+        };
         let (request, proxy) = futures_util::try_join!(
             self.0
                 .request::<CreateSessionResponse>(&options.handle_token, "CreateSession", &options)
                 .into_future(),
-            Session::from_unique_name(&options.session_handle_token).into_future(),
+            Session::from_unique_name(&self.0, &options.session_handle_token).into_future(),
         )?;
-        assert_eq!(proxy.path(), &request.response()?.session_handle.as_ref());
+        proxy.check_created(&request.response()?.session_handle)?;
         Ok(proxy)
     }
 
@@ -248,7 +301,9 @@ impl<'a> GlobalShortcuts<'a> {
         shortcuts: &[NewShortcut],
         parent_window: &WindowIdentifier,
     ) -> Result<Request<BindShortcuts>, Error> {
-        let options = BindShortcutsOptions::default();
+        let options = BindShortcutsOptions {
+            handle_token: session.next_handle_token(),
+        };
         self.0
             .request(
                 &options.handle_token,
@@ -268,7 +323,9 @@ impl<'a> GlobalShortcuts<'a> {
         &self,
         session: &Session<'_, Self>,
     ) -> Result<Request<ListShortcuts>, Error> {
-        let options = ListShortcutsOptions::default();
+        let options = ListShortcutsOptions {
+            handle_token: session.next_handle_token(),
+        };
         self.0
             .request(&options.handle_token, "ListShortcuts", &(session, &options))
             .await
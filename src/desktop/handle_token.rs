@@ -5,7 +5,13 @@ use std::{
 
 use rand::{distributions::Alphanumeric, thread_rng, Rng};
 use serde::{Deserialize, Serialize};
-use zbus::{names::OwnedMemberName, zvariant::Type};
+use zbus::{
+    names::{OwnedMemberName, UniqueName},
+    zvariant::{OwnedObjectPath, Type},
+};
+
+const REQUEST_PREFIX: &str = "/org/freedesktop/portal/desktop/request";
+const SESSION_PREFIX: &str = "/org/freedesktop/portal/desktop/session";
 
 /// A handle token is a DBus Object Path element, specified in the
 /// [`Request`](crate::desktop::Request)  or
@@ -15,9 +21,63 @@ use zbus::{names::OwnedMemberName, zvariant::Type};
 ///
 /// A valid object path element must only contain the ASCII characters
 /// `[A-Z][a-z][0-9]_`
-#[derive(Serialize, Type)]
+///
+/// The default token is random, so tokens created concurrently, e.g. from
+/// different threads, don't collide.
+#[derive(Clone, Serialize, Type)]
 pub struct HandleToken(OwnedMemberName);
 
+crate::helpers::impl_value_conversions!(HandleToken as str);
+
+impl HandleToken {
+    /// The path of the `org.freedesktop.portal.Request` object the portal
+    /// creates for a call made by `unique_name` with this token.
+    ///
+    /// Subscribing to the `Response` signal of that path before making the
+    /// call avoids missing it.
+    ///
+    /// ```
+    /// use ashpd::desktop::HandleToken;
+    /// use zbus::names::UniqueName;
+    ///
+    /// let token = HandleToken::try_from("ashpd_token").unwrap();
+    /// let sender = UniqueName::try_from(":1.42").unwrap();
+    /// assert_eq!(
+    ///     token.request_path(&sender).as_str(),
+    ///     "/org/freedesktop/portal/desktop/request/1_42/ashpd_token"
+    /// );
+    /// ```
+    pub fn request_path(&self, unique_name: &UniqueName<'_>) -> OwnedObjectPath {
+        self.path(REQUEST_PREFIX, unique_name)
+    }
+
+    /// The path of the `org.freedesktop.portal.Session` object the portal
+    /// creates for a session requested by `unique_name` with this token.
+    pub fn session_path(&self, unique_name: &UniqueName<'_>) -> OwnedObjectPath {
+        self.path(SESSION_PREFIX, unique_name)
+    }
+
+    /// The token `{self}_{n}`, e.g. for the `n`th request made on the session
+    /// created with this token.
+    #[cfg(any(
+        feature = "global_shortcuts",
+        feature = "inhibit",
+        feature = "input_capture",
+        feature = "location",
+        feature = "screencast",
+    ))]
+    pub(crate) fn derive(&self, n: u32) -> Self {
+        // Appending `_` and digits to a valid token keeps it valid.
+        format!("{self}_{n}").parse().unwrap()
+    }
+
+    fn path(&self, prefix: &str, unique_name: &UniqueName<'_>) -> OwnedObjectPath {
+        let sender = unique_name.trim_start_matches(':').replace('.', "_");
+        // Both the sender and the token are valid path elements.
+        OwnedObjectPath::try_from(format!("{prefix}/{sender}/{self}")).unwrap()
+    }
+}
+
 impl Display for HandleToken {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.write_str(&self.0)
@@ -45,6 +105,8 @@ impl Default for HandleToken {
 }
 
 #[derive(Debug)]
+/// The error returned when parsing a [`HandleToken`] containing a character
+/// that is not allowed in an object path element.
 pub struct HandleInvalidCharacter(char);
 
 impl std::fmt::Display for HandleInvalidCharacter {
@@ -116,4 +178,21 @@ mod test {
 
         HandleToken::default(); // ensure we don't panic
     }
+
+    #[test]
+    #[cfg(any(
+        feature = "global_shortcuts",
+        feature = "inhibit",
+        feature = "input_capture",
+        feature = "location",
+        feature = "screencast",
+    ))]
+    fn derive() {
+        let token = HandleToken::from_str("ashpd_abc").unwrap();
+        assert_eq!(token.derive(0).to_string(), "ashpd_abc_0");
+        assert_eq!(token.derive(12).to_string(), "ashpd_abc_12");
+
+        let token = HandleToken::default();
+        assert!(HandleToken::from_str(&token.derive(u32::MAX).to_string()).is_ok());
+    }
 }
@@ -30,11 +30,7 @@ impl Icon {
         Self::Names(names.into_iter().map(|name| name.to_string()).collect())
     }
 
-    pub(crate) fn is_bytes(&self) -> bool {
-        matches!(self, Self::Bytes(_))
-    }
-
-    pub(crate) fn inner_bytes(&self) -> Value {
+    pub(crate) fn inner_bytes(&self) -> Value<'_> {
         match self {
             Self::Bytes(bytes) => {
                 let mut array = zvariant::Array::new(u8::signature());
@@ -48,7 +44,8 @@ impl Icon {
         }
     }
 
-    pub(crate) fn as_value(&self) -> Value {
+    #[cfg(feature = "dynamic_launcher")]
+    pub(crate) fn as_value(&self) -> Value<'_> {
         let tuple = match self {
             Self::Uri(uri) => ("file", Value::from(uri.as_str())),
             Self::Names(names) => {
@@ -88,6 +88,8 @@ pub enum InhibitFlags {
     Idle,
 }
 
+crate::helpers::impl_value_conversions!(InhibitFlags as u32 { Logout, UserSwitch, Suspend, Idle });
+
 #[derive(Debug, DeserializeDict, Type)]
 #[zvariant(signature = "dict")]
 struct State {
@@ -136,6 +138,8 @@ pub enum SessionState {
     Ending = 3,
 }
 
+crate::helpers::impl_value_conversions!(SessionState as u32 { Running, QueryEnd, Ending });
+
 /// The interface lets sandboxed applications inhibit the user session from
 /// ending, suspending, idling or getting switched away.
 ///
@@ -151,6 +155,18 @@ impl<'a> InhibitProxy<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`InhibitProxy`] using `connection` instead of
+    /// the session bus connection, e.g. a peer-to-peer connection to the
+    /// portal.
+    pub async fn new_with_connection(
+        connection: zbus::Connection,
+    ) -> Result<InhibitProxy<'a>, Error> {
+        let proxy =
+            Proxy::new_desktop_with_connection(Some(connection), "org.freedesktop.portal.Inhibit")
+                .await?;
+        Ok(Self(proxy))
+    }
+
     /// Creates a monitoring session.
     /// While this session is active, the caller will receive `state_changed`
     /// signals with updates on the session state.
@@ -168,15 +184,19 @@ impl<'a> InhibitProxy<'a> {
         &self,
         identifier: &WindowIdentifier,
     ) -> Result<Session<'a, Self>, Error> {
-        let options = CreateMonitorOptions::default();
+        let session_handle_token = HandleToken::default();
+        let options = CreateMonitorOptions {
+            handle_token: session_handle_token.derive(0),
+            session_handle_token,
+        };
         let body = &(&identifier, &options);
         let (monitor, proxy) = futures_util::try_join!(
             self.0
                 .request::<CreateSessionResponse>(&options.handle_token, "CreateMonitor", body)
                 .into_future(),
-            Session::from_unique_name(&options.session_handle_token).into_future(),
+            Session::from_unique_name(&self.0, &options.session_handle_token).into_future(),
         )?;
-        assert_eq!(proxy.path(), &monitor.response()?.session_handle.as_ref());
+        proxy.check_created(&monitor.response()?.session_handle)?;
         Ok(proxy)
     }
 
@@ -252,3 +272,119 @@ impl<'a> std::ops::Deref for InhibitProxy<'a> {
 }
 
 impl SessionPortal for InhibitProxy<'_> {}
+
+/// Prevents the system from suspending until the returned guard is dropped or
+/// [released][`SleepInhibitor::release`].
+///
+/// The Inhibit portal is used when available. With the `logind` feature, the
+/// function falls back to `org.freedesktop.login1.Manager.Inhibit` on the
+/// system bus when the portal is missing, which covers applications running
+/// outside of a sandbox on systems without the portal.
+///
+/// # Arguments
+///
+/// * `reason` - User-visible reason for the inhibition.
+#[doc(alias = "xdp_portal_session_inhibit")]
+pub async fn prevent_sleep(reason: &str) -> Result<SleepInhibitor, Error> {
+    let portal = async {
+        let proxy = InhibitProxy::new().await?;
+        proxy
+            .inhibit(
+                &WindowIdentifier::default(),
+                InhibitFlags::Suspend.into(),
+                reason,
+            )
+            .await
+    };
+    match portal.await {
+        Ok(request) => Ok(SleepInhibitor(Some(Inhibitor::Portal(Box::new(request))))),
+        #[cfg(feature = "logind")]
+        Err(err) if crate::helpers::is_portal_missing(&err) => logind_inhibit_sleep(reason).await,
+        Err(err) => Err(err),
+    }
+}
+
+#[cfg(feature = "logind")]
+async fn logind_inhibit_sleep(reason: &str) -> Result<SleepInhibitor, Error> {
+    let who = std::env::args()
+        .next()
+        .and_then(|arg| {
+            std::path::Path::new(&arg)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "ashpd".to_owned());
+    let cnx = zbus::Connection::system().await?;
+    let reply = cnx
+        .call_method(
+            Some("org.freedesktop.login1"),
+            "/org/freedesktop/login1",
+            Some("org.freedesktop.login1.Manager"),
+            "Inhibit",
+            &("sleep", who.as_str(), reason, "block"),
+        )
+        .await?;
+    let fd = reply.body().deserialize::<zbus::zvariant::OwnedFd>()?;
+    Ok(SleepInhibitor(Some(Inhibitor::Logind(fd.into()))))
+}
+
+#[derive(Debug)]
+enum Inhibitor {
+    Portal(Box<Request<()>>),
+    #[cfg(feature = "logind")]
+    Logind(std::os::fd::OwnedFd),
+}
+
+/// A guard returned by [`prevent_sleep`], the system is prevented from
+/// suspending as long as it is alive.
+///
+/// Dropping the guard releases the inhibition in the background, use
+/// [`SleepInhibitor::release`] to wait for it instead.
+#[derive(Debug)]
+pub struct SleepInhibitor(Option<Inhibitor>);
+
+impl SleepInhibitor {
+    /// Whether the inhibition goes through the Inhibit portal, as opposed to
+    /// logind directly.
+    pub fn is_portal(&self) -> bool {
+        matches!(self.0, Some(Inhibitor::Portal(_)))
+    }
+
+    /// Release the inhibition.
+    pub async fn release(mut self) -> Result<(), Error> {
+        match self.0.take() {
+            Some(Inhibitor::Portal(request)) => request.close().await,
+            #[cfg(feature = "logind")]
+            Some(Inhibitor::Logind(fd)) => {
+                drop(fd);
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for SleepInhibitor {
+    fn drop(&mut self) {
+        if let Some(Inhibitor::Portal(request)) = self.0.take() {
+            #[cfg(feature = "tokio")]
+            if tokio::runtime::Handle::try_current().is_err() {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    "Not releasing the sleep inhibitor {}, dropped outside of a tokio runtime",
+                    request.path()
+                );
+                return;
+            }
+            let executor = request.connection().executor().clone();
+            executor
+                .spawn(
+                    async move {
+                        let _ = request.close().await;
+                    },
+                    "release sleep inhibitor",
+                )
+                .detach();
+        }
+    }
+}
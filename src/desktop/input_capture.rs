@@ -300,6 +300,7 @@ use zbus::zvariant::{
     self, DeserializeDict, ObjectPath, OwnedObjectPath, OwnedValue, SerializeDict, Type, Value,
 };
 
+pub use super::Region;
 use super::{session::SessionPortal, HandleToken, Request, Session};
 use crate::{proxy::Proxy, Error, WindowIdentifier};
 
@@ -316,6 +317,8 @@ pub enum Capabilities {
     Touchscreen,
 }
 
+crate::helpers::impl_value_conversions!(Capabilities as u32 { Keyboard, Pointer, Touchscreen });
+
 #[derive(Debug, SerializeDict, Type)]
 #[zvariant(signature = "dict")]
 struct CreateSessionOptions {
@@ -457,41 +460,21 @@ impl ZonesChanged {
     }
 }
 
-/// A region of a [`Zones`].
-#[derive(Debug, Clone, Copy, Deserialize, Type)]
-#[zvariant(signature = "(uuii)")]
-pub struct Region(u32, u32, i32, i32);
-
-impl Region {
-    /// The width.
-    pub fn width(self) -> u32 {
-        self.0
-    }
-
-    /// The height
-    pub fn height(self) -> u32 {
-        self.1
-    }
-
-    /// The x offset.
-    pub fn x_offset(self) -> i32 {
-        self.2
-    }
-
-    /// The y offset.
-    pub fn y_offset(self) -> i32 {
-        self.3
-    }
-}
-
 /// A response of [`InputCapture::zones`].
-#[derive(Debug, Type, DeserializeDict)]
+#[derive(Debug, Type)]
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct Zones {
     zones: Vec<Region>,
     zone_set: u32,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(Zones {
+    zones [required] => "zones",
+    zone_set [required] => "zone_set",
+});
+
 impl Zones {
     /// A list of regions.
     pub fn regions(&self) -> &[Region] {
@@ -502,6 +485,11 @@ impl Zones {
     pub fn zone_set(&self) -> u32 {
         self.zone_set
     }
+
+    /// The entries of the response unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
 /// A barrier ID.
@@ -526,17 +514,28 @@ impl Barrier {
 }
 
 /// A response to [`InputCapture::set_pointer_barriers`]
-#[derive(Debug, DeserializeDict, Type)]
+#[derive(Debug, Type)]
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct SetPointerBarriersResponse {
     failed_barriers: Vec<BarrierID>,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(SetPointerBarriersResponse {
+    failed_barriers [required] => "failed_barriers",
+});
+
 impl SetPointerBarriersResponse {
     /// List of pointer barriers that have been denied
     pub fn failed_barriers(&self) -> &[BarrierID] {
         &self.failed_barriers
     }
+
+    /// The entries of the response unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
 /// Wrapper of the DBus interface: [`org.freedesktop.portal.InputCapture`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.InputCapture.html).
@@ -550,6 +549,20 @@ impl<'a> InputCapture<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`InputCapture`] using `connection` instead of
+    /// the session bus connection, e.g. a peer-to-peer connection to the
+    /// portal.
+    pub async fn new_with_connection(
+        connection: zbus::Connection,
+    ) -> Result<InputCapture<'a>, Error> {
+        let proxy = Proxy::new_desktop_with_connection(
+            Some(connection),
+            "org.freedesktop.portal.InputCapture",
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
     /// Create an input capture session.
     ///
     /// # Specifications
@@ -560,9 +573,10 @@ impl<'a> InputCapture<'a> {
         parent_window: &WindowIdentifier,
         capabilities: BitFlags<Capabilities>,
     ) -> Result<(Session<'_, Self>, BitFlags<Capabilities>), Error> {
+        let session_handle_token = HandleToken::default();
         let options = CreateSessionOptions {
-            handle_token: Default::default(),
-            session_handle_token: Default::default(),
+            handle_token: session_handle_token.derive(0),
+            session_handle_token,
             capabilities,
         };
 
@@ -574,10 +588,10 @@ impl<'a> InputCapture<'a> {
                     (parent_window, &options)
                 )
                 .into_future(),
-            Session::from_unique_name(&options.session_handle_token).into_future(),
+            Session::from_unique_name(&self.0, &options.session_handle_token).into_future(),
         )?;
         let response = request.response()?;
-        assert_eq!(proxy.path(), &response.session_handle.as_ref());
+        proxy.check_created(&response.session_handle)?;
         Ok((proxy, response.capabilities))
     }
 
@@ -588,7 +602,9 @@ impl<'a> InputCapture<'a> {
     /// See also [`GetZones`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.InputCapture.html#org-freedesktop-portal-inputcapture-getzones).
     #[doc(alias = "GetZones")]
     pub async fn zones(&self, session: &Session<'_, Self>) -> Result<Request<Zones>, Error> {
-        let options = GetZonesOptions::default();
+        let options = GetZonesOptions {
+            handle_token: session.next_handle_token(),
+        };
         self.0
             .request(&options.handle_token, "GetZones", (session, &options))
             .await
@@ -606,7 +622,9 @@ impl<'a> InputCapture<'a> {
         barriers: &[Barrier],
         zone_set: u32,
     ) -> Result<Request<SetPointerBarriersResponse>, Error> {
-        let options = SetPointerBarriersOptions::default();
+        let options = SetPointerBarriersOptions {
+            handle_token: session.next_handle_token(),
+        };
         self.0
             .request(
                 &options.handle_token,
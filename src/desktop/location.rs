@@ -32,7 +32,7 @@ use std::fmt::Debug;
 
 use futures_util::{Stream, TryFutureExt};
 use serde::Deserialize;
-use serde_repr::Serialize_repr;
+use serde_repr::{Deserialize_repr, Serialize_repr};
 use zbus::zvariant::{DeserializeDict, ObjectPath, OwnedObjectPath, SerializeDict, Type};
 
 use super::{session::SessionPortal, HandleToken, Request, Session};
@@ -40,10 +40,12 @@ use crate::{proxy::Proxy, Error, WindowIdentifier};
 
 #[cfg_attr(feature = "glib", derive(glib::Enum))]
 #[cfg_attr(feature = "glib", enum_type(name = "AshpdLocationAccuracy"))]
-#[derive(Serialize_repr, PartialEq, Eq, Clone, Copy, Debug, Type)]
+#[derive(
+    Serialize_repr, Deserialize_repr, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Type,
+)]
 #[doc(alias = "XdpLocationAccuracy")]
 #[repr(u32)]
-/// The accuracy of the location.
+/// The accuracy of the location, ordered from the least to the most accurate.
 pub enum Accuracy {
     #[doc(alias = "XDP_LOCATION_ACCURACY_NONE")]
     /// None.
@@ -65,6 +67,8 @@ pub enum Accuracy {
     Exact = 5,
 }
 
+crate::helpers::impl_value_conversions!(Accuracy as u32 { None, Country, City, Neighborhood, Street, Exact });
+
 #[derive(SerializeDict, Type, Debug, Default)]
 /// Specified options for a [`LocationProxy::create_session`] request.
 #[zvariant(signature = "dict")]
@@ -208,6 +212,18 @@ impl<'a> LocationProxy<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`LocationProxy`] using `connection` instead of
+    /// the session bus connection, e.g. a peer-to-peer connection to the
+    /// portal.
+    pub async fn new_with_connection(
+        connection: zbus::Connection,
+    ) -> Result<LocationProxy<'a>, Error> {
+        let proxy =
+            Proxy::new_desktop_with_connection(Some(connection), "org.freedesktop.portal.Location")
+                .await?;
+        Ok(Self(proxy))
+    }
+
     /// Signal emitted when the user location is updated.
     ///
     /// # Specifications
@@ -249,9 +265,9 @@ impl<'a> LocationProxy<'a> {
             self.0
                 .call::<OwnedObjectPath>("CreateSession", &(options))
                 .into_future(),
-            Session::from_unique_name(&options.session_handle_token).into_future(),
+            Session::from_unique_name(&self.0, &options.session_handle_token).into_future(),
         )?;
-        assert_eq!(proxy.path(), &path.into_inner());
+        proxy.check_created(&path)?;
         Ok(proxy)
     }
 
@@ -274,7 +290,9 @@ impl<'a> LocationProxy<'a> {
         session: &Session<'_, Self>,
         identifier: &WindowIdentifier,
     ) -> Result<Request<()>, Error> {
-        let options = SessionStartOptions::default();
+        let options = SessionStartOptions {
+            handle_token: session.next_handle_token(),
+        };
         self.0
             .empty_request(
                 &options.handle_token,
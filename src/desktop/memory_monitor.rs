@@ -39,6 +39,20 @@ impl<'a> MemoryMonitor<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`MemoryMonitor`] using `connection` instead of
+    /// the session bus connection, e.g. a peer-to-peer connection to the
+    /// portal.
+    pub async fn new_with_connection(
+        connection: zbus::Connection,
+    ) -> Result<MemoryMonitor<'a>, Error> {
+        let proxy = Proxy::new_desktop_with_connection(
+            Some(connection),
+            "org.freedesktop.portal.MemoryMonitor",
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
     /// Signal emitted when a particular low memory situation happens
     /// with 0 being the lowest level of memory availability warning, and 255
     /// being the highest.
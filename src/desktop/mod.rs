@@ -1,60 +1,122 @@
 mod handle_token;
 pub(crate) mod request;
-mod session;
-pub(crate) use self::handle_token::HandleToken;
+pub(crate) mod session;
 pub use self::{
+    handle_token::{HandleInvalidCharacter, HandleToken},
     request::{Request, Response, ResponseError, ResponseType},
     session::Session,
 };
 mod color;
 pub use color::Color;
+mod geometry;
+pub use geometry::{Position, Region, Size};
 mod icon;
 pub use icon::Icon;
 
+#[cfg(feature = "account")]
+#[cfg_attr(docsrs, doc(cfg(feature = "account")))]
 pub mod account;
+#[cfg(feature = "background")]
+#[cfg_attr(docsrs, doc(cfg(feature = "background")))]
 pub mod background;
+#[cfg(feature = "camera")]
+#[cfg_attr(docsrs, doc(cfg(feature = "camera")))]
 pub mod camera;
+#[cfg(feature = "clipboard")]
+#[cfg_attr(docsrs, doc(cfg(feature = "clipboard")))]
 pub mod clipboard;
 #[deprecated = "The portal does not serve any purpose as nothing really can make use of it as is."]
+#[cfg(feature = "device")]
+#[cfg_attr(docsrs, doc(cfg(feature = "device")))]
 pub mod device;
+#[cfg(feature = "dynamic_launcher")]
+#[cfg_attr(docsrs, doc(cfg(feature = "dynamic_launcher")))]
 pub mod dynamic_launcher;
+#[cfg(feature = "email")]
+#[cfg_attr(docsrs, doc(cfg(feature = "email")))]
 pub mod email;
 /// Open/save file(s) chooser.
+#[cfg(feature = "file_chooser")]
+#[cfg_attr(docsrs, doc(cfg(feature = "file_chooser")))]
 pub mod file_chooser;
 /// Enable/disable/query the status of Game Mode.
+#[cfg(feature = "game_mode")]
+#[cfg_attr(docsrs, doc(cfg(feature = "game_mode")))]
 pub mod game_mode;
 /// Register global shortcuts
+#[cfg(feature = "global_shortcuts")]
+#[cfg_attr(docsrs, doc(cfg(feature = "global_shortcuts")))]
 pub mod global_shortcuts;
 /// Inhibit the session from being restarted or the user from logging out.
+#[cfg(feature = "inhibit")]
+#[cfg_attr(docsrs, doc(cfg(feature = "inhibit")))]
 pub mod inhibit;
 /// Capture input events from physical or logical devices.
+#[cfg(feature = "input_capture")]
+#[cfg_attr(docsrs, doc(cfg(feature = "input_capture")))]
 pub mod input_capture;
 /// Query the user's GPS location.
+#[cfg(feature = "location")]
+#[cfg_attr(docsrs, doc(cfg(feature = "location")))]
 pub mod location;
 /// Monitor memory level.
+#[cfg(feature = "memory_monitor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "memory_monitor")))]
 pub mod memory_monitor;
 /// Check the status of the network on a user's machine.
+#[cfg(feature = "network_monitor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "network_monitor")))]
 pub mod network_monitor;
 /// Send/withdraw notifications.
+#[cfg(feature = "notification")]
+#[cfg_attr(docsrs, doc(cfg(feature = "notification")))]
 pub mod notification;
+#[cfg(feature = "open_uri")]
+#[cfg_attr(docsrs, doc(cfg(feature = "open_uri")))]
 pub mod open_uri;
+/// PipeWire node properties.
+#[cfg(any(feature = "camera", feature = "screencast"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "camera", feature = "screencast"))))]
+pub mod pipewire_node;
 /// Power profile monitoring.
+#[cfg(feature = "power_profile_monitor")]
+#[cfg_attr(docsrs, doc(cfg(feature = "power_profile_monitor")))]
 pub mod power_profile_monitor;
 /// Print a document.
+#[cfg(feature = "print")]
+#[cfg_attr(docsrs, doc(cfg(feature = "print")))]
 pub mod print;
 /// Proxy information.
+#[cfg(feature = "proxy_resolver")]
+#[cfg_attr(docsrs, doc(cfg(feature = "proxy_resolver")))]
 pub mod proxy_resolver;
+#[cfg(feature = "realtime")]
+#[cfg_attr(docsrs, doc(cfg(feature = "realtime")))]
 pub mod realtime;
 /// Start a remote desktop session and interact with it.
+#[cfg(feature = "remote_desktop")]
+#[cfg_attr(docsrs, doc(cfg(feature = "remote_desktop")))]
 pub mod remote_desktop;
+#[cfg(feature = "screencast")]
+#[cfg_attr(docsrs, doc(cfg(feature = "screencast")))]
 pub mod screencast;
+#[cfg(feature = "screenshot")]
+#[cfg_attr(docsrs, doc(cfg(feature = "screenshot")))]
 pub mod screenshot;
 /// Retrieve a per-application secret used to encrypt confidential data inside
 /// the sandbox.
+#[cfg(feature = "secret")]
+#[cfg_attr(docsrs, doc(cfg(feature = "secret")))]
 pub mod secret;
 /// Read & listen to system settings changes.
+#[cfg(feature = "settings")]
+#[cfg_attr(docsrs, doc(cfg(feature = "settings")))]
 pub mod settings;
+#[cfg(feature = "trash")]
+#[cfg_attr(docsrs, doc(cfg(feature = "trash")))]
 pub mod trash;
+#[cfg(feature = "wallpaper")]
+#[cfg_attr(docsrs, doc(cfg(feature = "wallpaper")))]
 pub mod wallpaper;
 
 #[cfg_attr(feature = "glib", derive(glib::Enum))]
@@ -77,3 +139,5 @@ pub enum PersistMode {
     /// Persist until explicitly revoked.
     ExplicitlyRevoked = 2,
 }
+
+crate::helpers::impl_value_conversions!(PersistMode as u32 { DoNot, Application, ExplicitlyRevoked });
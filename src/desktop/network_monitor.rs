@@ -16,18 +16,52 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! `CanReach` can take a long time on flaky networks, it can be bounded with
+//! a timeout and cancelled.
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//!
+//! use ashpd::desktop::network_monitor::{NetworkMonitor, Reachability};
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     let proxy = NetworkMonitor::new().await?;
+//!     let (cancel, cancelled) = futures_channel::oneshot::channel::<()>();
+//!     // Call `cancel.send(())` to stop waiting, e.g. when the user leaves the
+//!     // page that needs the host.
+//!     # drop(cancel);
+//!
+//!     match proxy
+//!         .reachability_with_cancel("www.google.com", 80, Duration::from_secs(5), async {
+//!             let _ = cancelled.await;
+//!         })
+//!         .await?
+//!     {
+//!         Reachability::Reachable => println!("Reachable"),
+//!         Reachability::TimedOut => println!("Still unknown after 5 seconds"),
+//!         reachability => println!("{reachability:?}"),
+//!     }
+//!
+//!     Ok(())
+//! }
+//! ```
 
-use std::fmt;
+use std::{collections::HashMap, fmt, future::Future, time::Duration};
 
-use futures_util::Stream;
+use futures_util::{
+    future::{self, Either},
+    Stream,
+};
 use serde_repr::Deserialize_repr;
-use zbus::zvariant::{DeserializeDict, Type};
+use zbus::zvariant::{OwnedValue, Type};
 
 use crate::{proxy::Proxy, Error};
 
-#[derive(DeserializeDict, Type, Debug)]
+#[derive(Type, Debug)]
 /// The network status, composed of the availability, metered & connectivity
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct NetworkStatus {
     /// Whether the network is considered available.
     available: bool,
@@ -35,8 +69,15 @@ pub struct NetworkStatus {
     metered: bool,
     /// More detailed information about the host's network connectivity
     connectivity: Connectivity,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(NetworkStatus {
+    available [required] => "available",
+    metered [required] => "metered",
+    connectivity [required] => "connectivity",
+});
+
 impl NetworkStatus {
     /// Returns whether the network is considered available.
     pub fn is_available(&self) -> bool {
@@ -52,6 +93,11 @@ impl NetworkStatus {
     pub fn connectivity(&self) -> Connectivity {
         self.connectivity
     }
+
+    /// The entries of the status unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
 #[cfg_attr(feature = "glib", derive(glib::Enum))]
@@ -70,6 +116,8 @@ pub enum Connectivity {
     FullNetwork = 4,
 }
 
+crate::helpers::impl_value_conversions!(Connectivity as u32 { Local, Limited, CaptivePortal, FullNetwork });
+
 impl fmt::Display for Connectivity {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let connectivity = match self {
@@ -82,6 +130,31 @@ impl fmt::Display for Connectivity {
     }
 }
 
+#[cfg_attr(feature = "glib", derive(glib::Enum))]
+#[cfg_attr(feature = "glib", enum_type(name = "AshpdReachability"))]
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+/// The outcome of [`NetworkMonitor::reachability`].
+pub enum Reachability {
+    /// The host is believed to be reachable.
+    Reachable,
+    /// The host is believed to be unreachable.
+    Unreachable,
+    /// The portal didn't answer before the timeout.
+    TimedOut,
+    /// The check was cancelled before the portal answered.
+    Cancelled,
+}
+
+impl From<bool> for Reachability {
+    fn from(reachable: bool) -> Self {
+        if reachable {
+            Self::Reachable
+        } else {
+            Self::Unreachable
+        }
+    }
+}
+
 /// The interface provides network status information to sandboxed applications.
 ///
 /// It is not a portal in the strict sense, since it does not involve user
@@ -100,6 +173,20 @@ impl<'a> NetworkMonitor<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`NetworkMonitor`] using `connection` instead
+    /// of the session bus connection, e.g. a peer-to-peer connection to the
+    /// portal.
+    pub async fn new_with_connection(
+        connection: zbus::Connection,
+    ) -> Result<NetworkMonitor<'a>, Error> {
+        let proxy = Proxy::new_desktop_with_connection(
+            Some(connection),
+            "org.freedesktop.portal.NetworkMonitor",
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
     /// Returns whether the given hostname is believed to be reachable.
     ///
     /// # Arguments
@@ -122,6 +209,49 @@ impl<'a> NetworkMonitor<'a> {
             .await
     }
 
+    /// Like [`NetworkMonitor::can_reach`], but stops waiting for the portal
+    /// after `timeout`.
+    ///
+    /// The call is also cancelled when the returned future is dropped.
+    ///
+    /// # Required version
+    ///
+    /// The method requires the 3nd version implementation of the portal and
+    /// would fail with [`Error::RequiresVersion`] otherwise.
+    pub async fn reachability(
+        &self,
+        hostname: &str,
+        port: u32,
+        timeout: Duration,
+    ) -> Result<Reachability, Error> {
+        self.reachability_with_cancel(hostname, port, timeout, future::pending())
+            .await
+    }
+
+    /// Like [`NetworkMonitor::reachability`], but also stops waiting for the
+    /// portal once `cancel` completes, returning
+    /// [`Reachability::Cancelled`].
+    pub async fn reachability_with_cancel(
+        &self,
+        hostname: &str,
+        port: u32,
+        timeout: Duration,
+        cancel: impl Future<Output = ()>,
+    ) -> Result<Reachability, Error> {
+        let can_reach = std::pin::pin!(self.can_reach(hostname, port));
+        let timeout = std::pin::pin!(crate::helpers::sleep(timeout));
+        let cancel = std::pin::pin!(cancel);
+        match future::select(can_reach, future::select(cancel, timeout)).await {
+            Either::Left((reachable, _)) => reachable.map(Reachability::from),
+            Either::Right((Either::Left(_), _)) => Ok(Reachability::Cancelled),
+            Either::Right((Either::Right(_), _)) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("Timed out checking whether {hostname}:{port} is reachable");
+                Ok(Reachability::TimedOut)
+            }
+        }
+    }
+
     /// Returns whether the network is considered available.
     /// That is, whether the system as a default route for at least one of IPv4
     /// or IPv6.
@@ -207,3 +337,69 @@ impl<'a> std::ops::Deref for NetworkMonitor<'a> {
         &self.0
     }
 }
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use super::*;
+    use crate::helpers::p2p_pair;
+
+    struct Portal;
+
+    #[zbus::interface(name = "org.freedesktop.portal.NetworkMonitor")]
+    impl Portal {
+        #[zbus(property, name = "version")]
+        fn version(&self) -> u32 {
+            3
+        }
+
+        async fn can_reach(&self, hostname: &str, _port: u32) -> bool {
+            if hostname == "slow.example.org" {
+                future::pending::<()>().await;
+            }
+            hostname == "example.org"
+        }
+    }
+
+    #[tokio::test]
+    async fn reachability() {
+        let (server, client) = p2p_pair().await;
+        server
+            .object_server()
+            .at(crate::proxy::DESKTOP_PATH, Portal)
+            .await
+            .unwrap();
+        let proxy = NetworkMonitor::new_with_connection(client).await.unwrap();
+        let timeout = Duration::from_millis(50);
+
+        assert_eq!(
+            proxy
+                .reachability("example.org", 80, timeout)
+                .await
+                .unwrap(),
+            Reachability::Reachable
+        );
+        assert_eq!(
+            proxy.reachability("other.org", 80, timeout).await.unwrap(),
+            Reachability::Unreachable
+        );
+        assert_eq!(
+            proxy
+                .reachability("slow.example.org", 80, timeout)
+                .await
+                .unwrap(),
+            Reachability::TimedOut
+        );
+        assert_eq!(
+            proxy
+                .reachability_with_cancel(
+                    "slow.example.org",
+                    80,
+                    Duration::from_secs(60),
+                    future::ready(())
+                )
+                .await
+                .unwrap(),
+            Reachability::Cancelled
+        );
+    }
+}
@@ -40,19 +40,20 @@
 //!         _ => (),
 //!     };
 //!     println!("{:#?}", action.id());
-//!     println!(
-//!         "{:#?}",
-//!         action.parameter().get(0).unwrap().downcast_ref::<u32>()
-//!     );
+//!     println!("{:#?}", action.target_i64());
 //!
 //!     proxy.remove_notification(notification_id).await?;
 //!     Ok(())
 //! }
 //! ```
 
-use std::{fmt, str::FromStr};
+use std::{collections::HashMap, fmt, future::Future, str::FromStr};
 
-use futures_util::Stream;
+use futures_util::{
+    future::BoxFuture,
+    stream::{BoxStream, StreamExt},
+    FutureExt, Stream,
+};
 use serde::{self, Deserialize, Serialize};
 use zbus::zvariant::{OwnedValue, SerializeDict, Type, Value};
 
@@ -61,7 +62,7 @@ use crate::{proxy::Proxy, Error};
 
 #[cfg_attr(feature = "glib", derive(glib::Enum))]
 #[cfg_attr(feature = "glib", enum_type(name = "AshpdPriority"))]
-#[derive(Debug, Copy, Clone, Serialize, PartialEq, Eq, Type)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, Type)]
 #[zvariant(signature = "s")]
 #[serde(rename_all = "lowercase")]
 /// The notification priority
@@ -76,6 +77,13 @@ pub enum Priority {
     Urgent,
 }
 
+crate::helpers::impl_value_conversions!(Priority {
+    Low => "low",
+    Normal => "normal",
+    High => "high",
+    Urgent => "urgent",
+});
+
 impl fmt::Display for Priority {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -193,6 +201,9 @@ impl Notification {
     }
 
     /// Sets a value to be sent in the `action_invoked` signal.
+    ///
+    /// Strings and integers can be read back with [`Action::target_str`] and
+    /// [`Action::target_i64`].
     #[must_use]
     pub fn default_action_target<'a, T: Into<Value<'a>>>(
         mut self,
@@ -247,6 +258,9 @@ impl Button {
     }
 
     /// The value to send with the action name when the button is clicked.
+    ///
+    /// Strings and integers can be read back with [`Action::target_str`] and
+    /// [`Action::target_i64`].
     #[must_use]
     pub fn target<'a, T: Into<Value<'a>>>(mut self, target: impl Into<Option<T>>) -> Self {
         self.target = target.into().map(|t| t.into().try_to_owned().unwrap());
@@ -273,6 +287,38 @@ impl Action {
     pub fn parameter(&self) -> &Vec<OwnedValue> {
         &self.2
     }
+
+    /// The target of the action, set with
+    /// [`Notification::default_action_target`] or [`Button::target`].
+    pub fn target(&self) -> Option<&OwnedValue> {
+        self.2.first()
+    }
+
+    /// The target of the action if it is a string.
+    pub fn target_str(&self) -> Option<&str> {
+        match &**self.target()? {
+            Value::Str(target) => Some(target.as_str()),
+            _ => None,
+        }
+    }
+
+    /// The target of the action if it is an integer, whatever its D-Bus
+    /// type.
+    ///
+    /// Note that a Rust integer literal, as in `.target(32)`, is sent as an
+    /// `i32`, which only matches `downcast_ref::<i32>()`.
+    pub fn target_i64(&self) -> Option<i64> {
+        match **self.target()? {
+            Value::U8(target) => Some(target.into()),
+            Value::I16(target) => Some(target.into()),
+            Value::U16(target) => Some(target.into()),
+            Value::I32(target) => Some(target.into()),
+            Value::U32(target) => Some(target.into()),
+            Value::I64(target) => Some(target),
+            Value::U64(target) => target.try_into().ok(),
+            _ => None,
+        }
+    }
 }
 
 /// The interface lets sandboxed applications send and withdraw notifications.
@@ -293,7 +339,7 @@ impl Action {
 /// application.
 ///
 /// Wrapper of the DBus interface: [`org.freedesktop.portal.Notification`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.Notification.html).
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[doc(alias = "org.freedesktop.portal.Notification")]
 pub struct NotificationProxy<'a>(Proxy<'a>);
 
@@ -304,6 +350,20 @@ impl<'a> NotificationProxy<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`NotificationProxy`] using `connection`
+    /// instead of the session bus connection, e.g. a peer-to-peer
+    /// connection to the portal.
+    pub async fn new_with_connection(
+        connection: zbus::Connection,
+    ) -> Result<NotificationProxy<'a>, Error> {
+        let proxy = Proxy::new_desktop_with_connection(
+            Some(connection),
+            "org.freedesktop.portal.Notification",
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
     /// Signal emitted when a particular action is invoked.
     ///
     /// # Specifications
@@ -339,6 +399,28 @@ impl<'a> NotificationProxy<'a> {
         self.0.call("AddNotification", &(id, notification)).await
     }
 
+    /// Like [`NotificationProxy::add_notification`], but returns a
+    /// [`NotificationHandle`] to dispatch the actions invoked on the
+    /// notification to callbacks, and withdraw it once dropped.
+    ///
+    /// The actions are listened to before the notification is sent, so none
+    /// is missed.
+    pub async fn add_notification_with_handle(
+        &self,
+        id: &str,
+        notification: Notification,
+    ) -> Result<NotificationHandle<'a>, Error> {
+        let actions = self.receive_action_invoked().await?.boxed();
+        self.add_notification(id, notification).await?;
+        Ok(NotificationHandle {
+            proxy: self.clone(),
+            id: id.to_owned(),
+            actions,
+            callbacks: HashMap::new(),
+            withdraw_on_drop: true,
+        })
+    }
+
     /// Withdraws a notification.
     ///
     /// # Arguments
@@ -355,6 +437,142 @@ impl<'a> NotificationProxy<'a> {
     }
 }
 
+type ActionCallback = Box<dyn Fn(Action) -> BoxFuture<'static, ()> + Send + Sync>;
+
+/// A notification sent with
+/// [`NotificationProxy::add_notification_with_handle`].
+///
+/// The notification is withdrawn in the background when the handle is
+/// dropped, unless [`NotificationHandle::keep_on_drop`] was called.
+///
+/// ```rust,no_run
+/// use ashpd::desktop::notification::{Button, Notification, NotificationProxy};
+///
+/// async fn run() -> ashpd::Result<()> {
+///     let proxy = NotificationProxy::new().await?;
+///     let mut handle = proxy
+///         .add_notification_with_handle(
+///             "download",
+///             Notification::new("Download finished").button(Button::new("Open", "open")),
+///         )
+///         .await?;
+///     handle.on_action("open", |_action| async {
+///         // Open the downloaded file
+///     });
+///     handle.dispatch().await;
+///     Ok(())
+/// }
+/// ```
+pub struct NotificationHandle<'a> {
+    proxy: NotificationProxy<'a>,
+    id: String,
+    actions: BoxStream<'a, Action>,
+    callbacks: HashMap<String, ActionCallback>,
+    withdraw_on_drop: bool,
+}
+
+impl<'a> NotificationHandle<'a> {
+    /// The ID of the notification.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Call `callback` when the action named `name` is invoked on the
+    /// notification, replacing the previous callback of the action.
+    pub fn on_action<F, Fut>(&mut self, name: &str, callback: F) -> &mut Self
+    where
+        F: Fn(Action) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.callbacks.insert(
+            name.to_owned(),
+            Box::new(move |action| callback(action).boxed()),
+        );
+        self
+    }
+
+    /// Keep the notification around once the handle is dropped, e.g. for
+    /// the ones meant to outlast the application.
+    pub fn keep_on_drop(&mut self) -> &mut Self {
+        self.withdraw_on_drop = false;
+        self
+    }
+
+    /// Wait for the next action invoked on the notification and run its
+    /// callback, if any.
+    ///
+    /// Returns the name of the invoked action, or `None` if the portal went
+    /// away.
+    pub async fn dispatch(&mut self) -> Option<String> {
+        while let Some(action) = self.actions.next().await {
+            if action.id() != self.id {
+                continue;
+            }
+            let name = action.name().to_owned();
+            if let Some(callback) = self.callbacks.get(&name) {
+                callback(action).await;
+            }
+            return Some(name);
+        }
+        None
+    }
+
+    /// Withdraw the notification.
+    pub async fn withdraw(mut self) -> Result<(), Error> {
+        self.withdraw_on_drop = false;
+        self.proxy.remove_notification(&self.id).await
+    }
+}
+
+impl fmt::Debug for NotificationHandle<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NotificationHandle")
+            .field("id", &self.id)
+            .field("actions", &self.callbacks.keys().collect::<Vec<_>>())
+            .field("withdraw_on_drop", &self.withdraw_on_drop)
+            .finish()
+    }
+}
+
+impl Drop for NotificationHandle<'_> {
+    fn drop(&mut self) {
+        if !self.withdraw_on_drop {
+            return;
+        }
+        #[cfg(feature = "tokio")]
+        if tokio::runtime::Handle::try_current().is_err() {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "Not withdrawing the notification {}, dropped outside of a tokio runtime",
+                self.id
+            );
+            return;
+        }
+        let cnx = self.proxy.connection().clone();
+        let destination = self.proxy.destination().to_owned();
+        let path = self.proxy.path().to_owned();
+        let interface = self.proxy.interface().to_owned();
+        let id = std::mem::take(&mut self.id);
+        cnx.executor()
+            .clone()
+            .spawn(
+                async move {
+                    let _ = cnx
+                        .call_method(
+                            Some(destination),
+                            path,
+                            Some(interface),
+                            "RemoveNotification",
+                            &(id),
+                        )
+                        .await;
+                },
+                "withdraw notification",
+            )
+            .detach();
+    }
+}
+
 impl<'a> std::ops::Deref for NotificationProxy<'a> {
     type Target = zbus::Proxy<'a>;
 
@@ -362,3 +580,119 @@ impl<'a> std::ops::Deref for NotificationProxy<'a> {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[cfg(feature = "tokio")]
+    use std::sync::{Arc, Mutex};
+
+    use zbus::zvariant::{serialized::Context, to_bytes, LE};
+
+    use super::*;
+    #[cfg(feature = "tokio")]
+    use crate::helpers::p2p_pair;
+
+    fn invoked(target: Option<Value<'_>>) -> Action {
+        let parameter = target.into_iter().collect::<Vec<_>>();
+        let ctxt = Context::new_dbus(LE, 0);
+        let data = to_bytes(ctxt, &("id", "action", parameter)).unwrap();
+        data.deserialize().unwrap().0
+    }
+
+    #[cfg(feature = "tokio")]
+    struct Portal(Arc<Mutex<Vec<String>>>);
+
+    #[cfg(feature = "tokio")]
+    #[zbus::interface(name = "org.freedesktop.portal.Notification")]
+    impl Portal {
+        #[zbus(property, name = "version")]
+        fn version(&self) -> u32 {
+            1
+        }
+
+        async fn add_notification(
+            &self,
+            #[zbus(signal_context)] ctxt: zbus::SignalContext<'_>,
+            id: &str,
+            _notification: HashMap<String, Value<'_>>,
+        ) -> zbus::fdo::Result<()> {
+            for (id, action) in [("other", "open"), (id, "open")] {
+                ctxt.connection()
+                    .emit_signal(
+                        None::<()>,
+                        crate::proxy::DESKTOP_PATH,
+                        "org.freedesktop.portal.Notification",
+                        "ActionInvoked",
+                        &(id, action, vec![Value::from(42)]),
+                    )
+                    .await?;
+            }
+            Ok(())
+        }
+
+        fn remove_notification(&self, id: &str) {
+            self.0.lock().unwrap().push(id.to_owned());
+        }
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn handle() {
+        let removed = Arc::new(Mutex::new(Vec::new()));
+        let (server, client) = p2p_pair().await;
+        server
+            .object_server()
+            .at(crate::proxy::DESKTOP_PATH, Portal(removed.clone()))
+            .await
+            .unwrap();
+        let proxy = NotificationProxy::new_with_connection(client)
+            .await
+            .unwrap();
+
+        let mut handle = proxy
+            .add_notification_with_handle("download", Notification::new("Downloaded"))
+            .await
+            .unwrap();
+        let (sender, receiver) = futures_channel::oneshot::channel();
+        let sender = Mutex::new(Some(sender));
+        handle.on_action("open", move |action| {
+            let sender = sender.lock().unwrap().take();
+            async move {
+                sender.unwrap().send(action.target_i64()).unwrap();
+            }
+        });
+        assert_eq!(handle.dispatch().await.as_deref(), Some("open"));
+        assert_eq!(receiver.await.unwrap(), Some(42));
+
+        handle.withdraw().await.unwrap();
+        assert_eq!(*removed.lock().unwrap(), ["download"]);
+
+        let handle = proxy
+            .add_notification_with_handle("upload", Notification::new("Uploaded"))
+            .await
+            .unwrap();
+        drop(handle);
+        for _ in 0..100 {
+            if removed.lock().unwrap().len() == 2 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(*removed.lock().unwrap(), ["download", "upload"]);
+    }
+
+    #[test]
+    fn action_targets() {
+        let action = invoked(Some(Value::from("open")));
+        assert_eq!(action.target_str(), Some("open"));
+        assert_eq!(action.target_i64(), None);
+
+        assert_eq!(invoked(Some(Value::from(32))).target_i64(), Some(32));
+        assert_eq!(invoked(Some(Value::from(7u8))).target_i64(), Some(7));
+        assert_eq!(invoked(Some(Value::from(u64::MAX))).target_i64(), None);
+
+        let action = invoked(None);
+        assert!(action.target().is_none());
+        assert_eq!(action.target_str(), None);
+    }
+}
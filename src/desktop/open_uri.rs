@@ -28,9 +28,10 @@
 //! use ashpd::desktop::open_uri::OpenFileRequest;
 //!
 //! async fn run() -> ashpd::Result<()> {
-//!     let uri =
-//!         url::Url::parse("file:///home/bilelmoussaoui/Downloads/adwaita-night.jpg").unwrap();
-//!     OpenFileRequest::default().ask(true).send_uri(&uri).await?;
+//!     OpenFileRequest::default()
+//!         .ask(true)
+//!         .send_uri("file:///home/bilelmoussaoui/Downloads/adwaita-night.jpg")
+//!         .await?;
 //!     Ok(())
 //! }
 //! ```
@@ -51,36 +52,90 @@
 //! }
 //! ```
 
-use std::os::fd::BorrowedFd;
+use std::{
+    collections::HashMap,
+    os::fd::{AsFd, BorrowedFd},
+    path::Path,
+};
 
-use url::Url;
-use zbus::zvariant::{Fd, SerializeDict, Type};
+use zbus::zvariant::{Fd, OwnedValue, Type};
 
 use super::{HandleToken, Request};
-use crate::{proxy::Proxy, ActivationToken, Error, WindowIdentifier};
+use crate::{
+    proxy::Proxy, ActivationToken, Error, ExportedPath, IntoUrl, PathPolicy, WindowIdentifier,
+};
 
-#[derive(SerializeDict, Type, Debug, Default)]
+#[derive(Type, Debug, Default)]
 #[zvariant(signature = "dict")]
 struct OpenDirOptions {
     handle_token: HandleToken,
     activation_token: Option<ActivationToken>,
+    extra: HashMap<String, OwnedValue>,
 }
 
-#[derive(SerializeDict, Type, Debug, Default)]
+crate::helpers::impl_dict_with_extra!(Serialize for OpenDirOptions {
+    handle_token [required] => "handle_token",
+    activation_token => "activation_token",
+});
+
+#[derive(Type, Debug, Default)]
 #[zvariant(signature = "dict")]
 struct OpenFileOptions {
     handle_token: HandleToken,
     writeable: Option<bool>,
     ask: Option<bool>,
     activation_token: Option<ActivationToken>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(Serialize for OpenFileOptions {
+    handle_token [required] => "handle_token",
+    writeable => "writeable",
+    ask => "ask",
+    activation_token => "activation_token",
+});
+
+/// A response of [`OpenFileRequest`] or [`OpenDirectoryRequest`].
+///
+/// The specification doesn't define any result for these requests and
+/// xdg-desktop-portal doesn't forward which application was launched yet. The
+/// `choice` result of the `org.freedesktop.impl.portal.AppChooser` backend is
+/// read for the portal implementations forwarding it. Whether the choice was
+/// remembered isn't reported by any of them.
+#[derive(Debug, Default, Type)]
+#[zvariant(signature = "dict")]
+#[non_exhaustive]
+pub struct OpenUriResponse {
+    choice: Option<String>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(OpenUriResponse {
+    choice => "choice",
+});
+
+impl OpenUriResponse {
+    /// The ID of the desktop file of the application that handled the URI,
+    /// without the `.desktop` suffix, e.g. `firefox` or
+    /// `org.mozilla.firefox`, to show "Opened in Firefox".
+    pub fn choice(&self) -> Option<&str> {
+        self.choice.as_deref()
+    }
+
+    /// The entries of the response unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
 #[derive(Debug)]
 struct OpenURIProxy<'a>(Proxy<'a>);
 
 impl<'a> OpenURIProxy<'a> {
-    pub async fn new() -> Result<OpenURIProxy<'a>, Error> {
-        let proxy = Proxy::new_desktop("org.freedesktop.portal.OpenURI").await?;
+    pub async fn new(connection: Option<zbus::Connection>) -> Result<OpenURIProxy<'a>, Error> {
+        let proxy =
+            Proxy::new_desktop_with_connection(connection, "org.freedesktop.portal.OpenURI")
+                .await?;
         Ok(Self(proxy))
     }
 
@@ -89,9 +144,9 @@ impl<'a> OpenURIProxy<'a> {
         identifier: &WindowIdentifier,
         directory: &BorrowedFd<'_>,
         options: OpenDirOptions,
-    ) -> Result<Request<()>, Error> {
+    ) -> Result<Request<OpenUriResponse>, Error> {
         self.0
-            .empty_request(
+            .request(
                 &options.handle_token,
                 "OpenDirectory",
                 &(&identifier, Fd::from(directory), &options),
@@ -104,9 +159,9 @@ impl<'a> OpenURIProxy<'a> {
         identifier: &WindowIdentifier,
         file: &BorrowedFd<'_>,
         options: OpenFileOptions,
-    ) -> Result<Request<()>, Error> {
+    ) -> Result<Request<OpenUriResponse>, Error> {
         self.0
-            .empty_request(
+            .request(
                 &options.handle_token,
                 "OpenFile",
                 &(&identifier, Fd::from(file), &options),
@@ -119,9 +174,9 @@ impl<'a> OpenURIProxy<'a> {
         identifier: &WindowIdentifier,
         uri: &url::Url,
         options: OpenFileOptions,
-    ) -> Result<Request<()>, Error> {
+    ) -> Result<Request<OpenUriResponse>, Error> {
         self.0
-            .empty_request(
+            .request(
                 &options.handle_token,
                 "OpenURI",
                 &(&identifier, uri, &options),
@@ -147,9 +202,28 @@ impl<'a> std::ops::Deref for OpenURIProxy<'a> {
 pub struct OpenFileRequest {
     identifier: WindowIdentifier,
     options: OpenFileOptions,
+    connection: Option<zbus::Connection>,
+    close_on_drop: Option<bool>,
 }
 
 impl OpenFileRequest {
+    /// Sets the connection to send the request on, instead of the session
+    /// bus connection.
+    #[must_use]
+    pub fn connection(mut self, connection: impl Into<Option<zbus::Connection>>) -> Self {
+        self.connection = connection.into();
+        self
+    }
+
+    /// Whether to close the request, dismissing its dialog, if the future
+    /// sending it is dropped before the response is received. Overrides
+    /// [`set_close_on_drop`](crate::set_close_on_drop) for this request.
+    #[must_use]
+    pub fn close_on_drop(mut self, close: bool) -> Self {
+        self.close_on_drop = Some(close);
+        self
+    }
+
     #[must_use]
     /// Sets a window identifier.
     pub fn identifier(mut self, identifier: impl Into<Option<WindowIdentifier>>) -> Self {
@@ -181,16 +255,52 @@ impl OpenFileRequest {
         self
     }
 
+    /// Sets the option `key`, unknown to this version of the crate, e.g. a
+    /// vendor extension like `x-gnome-...` to experiment with the features of
+    /// a backend. The options known to the crate can't be overridden.
+    #[must_use]
+    pub fn extra<'v>(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<zbus::zvariant::Value<'v>>,
+    ) -> Self {
+        crate::helpers::insert_extra(&mut self.options.extra, key, value);
+        self
+    }
+
     /// Send the request for a file.
-    pub async fn send_file(self, file: &BorrowedFd<'_>) -> Result<Request<()>, Error> {
-        let proxy = OpenURIProxy::new().await?;
+    pub async fn send_file(self, file: &BorrowedFd<'_>) -> Result<Request<OpenUriResponse>, Error> {
+        let mut proxy = OpenURIProxy::new(self.connection).await?;
+        proxy.0.close_requests_on_drop(self.close_on_drop);
         proxy.open_file(&self.identifier, file, self.options).await
     }
 
+    /// Send the request for a path, handed over to the portal following
+    /// `policy`.
+    pub async fn send_path(
+        self,
+        path: impl AsRef<Path>,
+        policy: PathPolicy,
+    ) -> Result<Request<OpenUriResponse>, Error> {
+        match ExportedPath::new(path, policy).await? {
+            ExportedPath::Fd(fd) => self.send_file(&fd.as_fd()).await,
+            exported => {
+                let uri = exported
+                    .uri()
+                    .ok_or(Error::ParseError("Failed to convert path to a URI"))?;
+                self.send_uri(uri).await
+            }
+        }
+    }
+
     /// Send the request for a URI.
-    pub async fn send_uri(self, uri: &Url) -> Result<Request<()>, Error> {
-        let proxy = OpenURIProxy::new().await?;
-        proxy.open_uri(&self.identifier, uri, self.options).await
+    ///
+    /// Paths are converted to `file://` URIs, see [`IntoUrl`].
+    pub async fn send_uri(self, uri: impl IntoUrl) -> Result<Request<OpenUriResponse>, Error> {
+        let uri = uri.into_url()?;
+        let mut proxy = OpenURIProxy::new(self.connection).await?;
+        proxy.0.close_requests_on_drop(self.close_on_drop);
+        proxy.open_uri(&self.identifier, &uri, self.options).await
     }
 }
 
@@ -203,9 +313,28 @@ impl OpenFileRequest {
 pub struct OpenDirectoryRequest {
     identifier: WindowIdentifier,
     options: OpenDirOptions,
+    connection: Option<zbus::Connection>,
+    close_on_drop: Option<bool>,
 }
 
 impl OpenDirectoryRequest {
+    /// Sets the connection to send the request on, instead of the session
+    /// bus connection.
+    #[must_use]
+    pub fn connection(mut self, connection: impl Into<Option<zbus::Connection>>) -> Self {
+        self.connection = connection.into();
+        self
+    }
+
+    /// Whether to close the request, dismissing its dialog, if the future
+    /// sending it is dropped before the response is received. Overrides
+    /// [`set_close_on_drop`](crate::set_close_on_drop) for this request.
+    #[must_use]
+    pub fn close_on_drop(mut self, close: bool) -> Self {
+        self.close_on_drop = Some(close);
+        self
+    }
+
     #[must_use]
     /// Sets a window identifier.
     pub fn identifier(mut self, identifier: impl Into<Option<WindowIdentifier>>) -> Self {
@@ -223,11 +352,48 @@ impl OpenDirectoryRequest {
         self
     }
 
+    /// Sets the option `key`, unknown to this version of the crate, e.g. a
+    /// vendor extension like `x-gnome-...` to experiment with the features of
+    /// a backend. The options known to the crate can't be overridden.
+    #[must_use]
+    pub fn extra<'v>(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<zbus::zvariant::Value<'v>>,
+    ) -> Self {
+        crate::helpers::insert_extra(&mut self.options.extra, key, value);
+        self
+    }
+
     /// Send the request.
-    pub async fn send(self, directory: &BorrowedFd<'_>) -> Result<Request<()>, Error> {
-        let proxy = OpenURIProxy::new().await?;
+    pub async fn send(self, directory: &BorrowedFd<'_>) -> Result<Request<OpenUriResponse>, Error> {
+        let mut proxy = OpenURIProxy::new(self.connection).await?;
+        proxy.0.close_requests_on_drop(self.close_on_drop);
         proxy
             .open_directory(&self.identifier, directory, self.options)
             .await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use zbus::zvariant::{serialized::Context, to_bytes, Value, LE};
+
+    use super::*;
+
+    #[test]
+    fn response() {
+        let ctxt = Context::new_dbus(LE, 0);
+        let empty = HashMap::<&str, Value<'_>>::new();
+        let response: OpenUriResponse = to_bytes(ctxt, &empty).unwrap().deserialize().unwrap().0;
+        assert_eq!(response.choice(), None);
+
+        let results = HashMap::from([
+            ("choice", Value::from("firefox")),
+            ("remember_choice", Value::from(true)),
+        ]);
+        let response: OpenUriResponse = to_bytes(ctxt, &results).unwrap().deserialize().unwrap().0;
+        assert_eq!(response.choice(), Some("firefox"));
+        assert!(response.extra().contains_key("remember_choice"));
+    }
+}
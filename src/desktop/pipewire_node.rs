@@ -0,0 +1,129 @@
+//! Once a remote is opened with
+//! [`Camera::open_pipe_wire_remote`](crate::desktop::camera::Camera::open_pipe_wire_remote)
+//! or
+//! [`Screencast::open_pipe_wire_remote`](crate::desktop::screencast::Screencast::open_pipe_wire_remote),
+//! the application lists the nodes it can see and picks the ones to connect
+//! to. These are the properties to filter them by.
+//!
+//! ```rust
+//! use std::collections::HashMap;
+//!
+//! use ashpd::desktop::pipewire_node::{self, MEDIA_ROLE, MEDIA_ROLE_CAMERA, NODE_NAME};
+//!
+//! let properties = HashMap::from([
+//!     (MEDIA_ROLE.to_owned(), MEDIA_ROLE_CAMERA.to_owned()),
+//!     (NODE_NAME.to_owned(), "libcamera_input.front".to_owned()),
+//! ]);
+//! assert!(pipewire_node::is_camera(&properties));
+//! assert!(pipewire_node::node_name_matches(
+//!     &properties,
+//!     "libcamera_input.*"
+//! ));
+//! ```
+
+use std::{collections::HashMap, hash::BuildHasher};
+
+/// The role of the media of the node, [`MEDIA_ROLE_CAMERA`] for the cameras.
+pub const MEDIA_ROLE: &str = "media.role";
+/// The class of the media of the node, e.g. [`MEDIA_CLASS_VIDEO_SOURCE`].
+pub const MEDIA_CLASS: &str = "media.class";
+/// The name of the node.
+pub const NODE_NAME: &str = "node.name";
+/// The human readable description of the node.
+pub const NODE_DESCRIPTION: &str = "node.description";
+/// A short human readable name of the node.
+pub const NODE_NICK: &str = "node.nick";
+/// The serial number of the object, unlike its id it is never reused.
+pub const OBJECT_SERIAL: &str = "object.serial";
+/// The name or serial of the node a stream connects to.
+pub const TARGET_OBJECT: &str = "target.object";
+/// Where a camera handled by libcamera is located, e.g. `front` or `back`.
+pub const CAMERA_LOCATION: &str = "api.libcamera.location";
+
+/// The [`MEDIA_ROLE`] of the cameras, the only nodes the camera portal gives
+/// access to.
+pub const MEDIA_ROLE_CAMERA: &str = "Camera";
+/// The [`MEDIA_CLASS`] of the nodes producing video, like cameras and screen
+/// cast streams.
+pub const MEDIA_CLASS_VIDEO_SOURCE: &str = "Video/Source";
+
+/// Set to `true` on the clients whose access is managed by the portal.
+pub const PORTAL_IS_PORTAL: &str = "pipewire.access.portal.is_portal";
+/// The app ID of a client whose access is managed by the portal.
+pub const PORTAL_APP_ID: &str = "pipewire.access.portal.app_id";
+/// The comma separated media roles a client managed by the portal has access
+/// to, e.g. [`MEDIA_ROLE_CAMERA`].
+pub const PORTAL_MEDIA_ROLES: &str = "pipewire.access.portal.media_roles";
+
+/// The properties of a PipeWire object.
+pub trait NodeProperties {
+    /// The value of the property `key`, if any.
+    fn property(&self, key: &str) -> Option<&str>;
+}
+
+impl<T: NodeProperties + ?Sized> NodeProperties for &T {
+    fn property(&self, key: &str) -> Option<&str> {
+        (**self).property(key)
+    }
+}
+
+impl<S: BuildHasher> NodeProperties for HashMap<String, String, S> {
+    fn property(&self, key: &str) -> Option<&str> {
+        self.get(key).map(String::as_str)
+    }
+}
+
+#[cfg(feature = "pipewire")]
+impl NodeProperties for pipewire::spa::utils::dict::DictRef {
+    fn property(&self, key: &str) -> Option<&str> {
+        self.get(key)
+    }
+}
+
+/// Whether the node is a camera.
+pub fn is_camera(properties: &impl NodeProperties) -> bool {
+    properties.property(MEDIA_ROLE) == Some(MEDIA_ROLE_CAMERA)
+}
+
+/// Whether the node produces video.
+pub fn is_video_source(properties: &impl NodeProperties) -> bool {
+    properties.property(MEDIA_CLASS) == Some(MEDIA_CLASS_VIDEO_SOURCE)
+}
+
+/// Whether the [`NODE_NAME`] of the node matches `pattern`.
+///
+/// A trailing `*` in `pattern` matches any name starting with the rest of the
+/// pattern.
+pub fn node_name_matches(properties: &impl NodeProperties, pattern: &str) -> bool {
+    let Some(name) = properties.property(NODE_NAME) else {
+        return false;
+    };
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matchers() {
+        let camera = HashMap::from([
+            (MEDIA_ROLE.to_owned(), MEDIA_ROLE_CAMERA.to_owned()),
+            (MEDIA_CLASS.to_owned(), MEDIA_CLASS_VIDEO_SOURCE.to_owned()),
+            (NODE_NAME.to_owned(), "v4l2_input.platform".to_owned()),
+        ]);
+        assert!(is_camera(&camera));
+        assert!(is_video_source(&camera));
+        assert!(node_name_matches(&camera, "v4l2_input.*"));
+        assert!(node_name_matches(&camera, "v4l2_input.platform"));
+        assert!(!node_name_matches(&camera, "v4l2_input"));
+
+        let empty = HashMap::<String, String>::new();
+        assert!(!is_camera(&empty));
+        assert!(!is_video_source(&empty));
+        assert!(!node_name_matches(&empty, "*"));
+    }
+}
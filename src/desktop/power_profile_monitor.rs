@@ -21,6 +21,20 @@ impl<'a> PowerProfileMonitor<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`PowerProfileMonitor`] using `connection`
+    /// instead of the session bus connection, e.g. a peer-to-peer
+    /// connection to the portal.
+    pub async fn new_with_connection(
+        connection: zbus::Connection,
+    ) -> Result<PowerProfileMonitor<'a>, Error> {
+        let proxy = Proxy::new_desktop_with_connection(
+            Some(connection),
+            "org.freedesktop.portal.PowerProfileMonitor",
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
     /// Whether the power saver is enabled.
     ///
     /// # Specifications
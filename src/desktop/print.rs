@@ -37,14 +37,39 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! Use the paper size of the user's locale
+//!
+//! ```rust,no_run
+//! use ashpd::desktop::print::{PageSetup, PaperSize, PrintRequest};
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     println!("Printing on {}", PaperSize::locale_default().display_name());
+//!     PrintRequest::default()
+//!         .title("Invoice")
+//!         .page_setup(PageSetup::locale_default())
+//!         .print_bytes(b"%PDF-1.7")
+//!         .await?;
+//!     Ok(())
+//! }
+//! ```
 
-use std::{fmt, os::fd::BorrowedFd, str::FromStr};
-
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::OpenOptions,
+    io::{Seek, Write},
+    os::fd::{AsFd, BorrowedFd, OwnedFd},
+    path::Path,
+    str::FromStr,
+};
+
+use futures_util::{future::BoxFuture, stream::BoxStream};
 use serde::{Deserialize, Serialize};
-use zbus::zvariant::{DeserializeDict, Fd, SerializeDict, Type};
+use zbus::zvariant::{Fd, OwnedValue, SerializeDict, Type};
 
-use super::{HandleToken, Request};
-use crate::{proxy::Proxy, Error, WindowIdentifier};
+use super::{request::ResponseError, HandleToken, Request};
+use crate::{proxy::Proxy, Error, ExportedPath, PathPolicy, WindowIdentifier};
 
 #[cfg_attr(feature = "glib", derive(glib::Enum))]
 #[cfg_attr(feature = "glib", enum_type(name = "AshpdOrientation"))]
@@ -63,6 +88,13 @@ pub enum Orientation {
     ReversePortrait,
 }
 
+crate::helpers::impl_value_conversions!(Orientation {
+    Landscape => "landscape",
+    Portrait => "portrait",
+    ReverseLandscape => "reverse_landscape",
+    ReversePortrait => "reverse_portrait",
+});
+
 impl fmt::Display for Orientation {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -103,8 +135,12 @@ impl FromStr for Orientation {
         match s {
             "Landscape" | "landscape" => Ok(Orientation::Landscape),
             "Portrait" | "portrait" => Ok(Orientation::Portrait),
-            "ReverseLandscape" | "reverse_landscape" => Ok(Orientation::ReverseLandscape),
-            "ReversePortrait" | "reverse_portrait" => Ok(Orientation::ReversePortrait),
+            "ReverseLandscape" | "Reverse Landscape" | "reverse_landscape" => {
+                Ok(Orientation::ReverseLandscape)
+            }
+            "ReversePortrait" | "Reverse Portrait" | "reverse_portrait" => {
+                Ok(Orientation::ReversePortrait)
+            }
             _ => Err(Error::ParseError(
                 "Failed to parse orientation, invalid value",
             )),
@@ -129,6 +165,13 @@ pub enum Quality {
     High,
 }
 
+crate::helpers::impl_value_conversions!(Quality {
+    Draft => "draft",
+    Low => "low",
+    Normal => "normal",
+    High => "high",
+});
+
 impl fmt::Display for Quality {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -176,33 +219,187 @@ impl FromStr for Quality {
     }
 }
 
-#[derive(SerializeDict, DeserializeDict, Type, Debug, Default)]
+#[cfg_attr(feature = "glib", derive(glib::Enum))]
+#[cfg_attr(feature = "glib", enum_type(name = "AshpdPaperSize"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The common paper sizes.
+pub enum PaperSize {
+    /// ISO A3, 297 × 420 mm.
+    A3,
+    /// ISO A4, 210 × 297 mm.
+    A4,
+    /// ISO A5, 148 × 210 mm.
+    A5,
+    /// ISO B5, 176 × 250 mm.
+    B5,
+    /// US Letter, 8.5 × 11 in.
+    Letter,
+    /// US Legal, 8.5 × 14 in.
+    Legal,
+    /// US Executive, 7.25 × 10.5 in.
+    Executive,
+    /// US Tabloid, also known as Ledger, 11 × 17 in.
+    Tabloid,
+}
+
+/// The territories of the locales using [`PaperSize::Letter`] by default.
+const LETTER_TERRITORIES: &[&str] = &[
+    "US", "CA", "MX", "PR", "PH", "CL", "CO", "CR", "DO", "GT", "NI", "PA", "SV", "VE",
+];
+
+impl PaperSize {
+    /// All the known paper sizes.
+    pub const ALL: [PaperSize; 8] = [
+        Self::A3,
+        Self::A4,
+        Self::A5,
+        Self::B5,
+        Self::Letter,
+        Self::Legal,
+        Self::Executive,
+        Self::Tabloid,
+    ];
+
+    /// The name of the paper, as used by GTK and the print settings, e.g.
+    /// `iso_a4`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::A3 => "iso_a3",
+            Self::A4 => "iso_a4",
+            Self::A5 => "iso_a5",
+            Self::B5 => "iso_b5",
+            Self::Letter => "na_letter",
+            Self::Legal => "na_legal",
+            Self::Executive => "na_executive",
+            Self::Tabloid => "na_ledger",
+        }
+    }
+
+    /// The name of the paper in PPD files, e.g. `A4`.
+    pub fn ppd_name(self) -> &'static str {
+        match self {
+            Self::A3 => "A3",
+            Self::A4 => "A4",
+            Self::A5 => "A5",
+            Self::B5 => "ISOB5",
+            Self::Letter => "Letter",
+            Self::Legal => "Legal",
+            Self::Executive => "Executive",
+            Self::Tabloid => "Tabloid",
+        }
+    }
+
+    /// The user-visible name of the paper, in English.
+    pub fn display_name(self) -> &'static str {
+        match self {
+            Self::A3 => "A3",
+            Self::A4 => "A4",
+            Self::A5 => "A5",
+            Self::B5 => "B5",
+            Self::Letter => "US Letter",
+            Self::Legal => "US Legal",
+            Self::Executive => "Executive",
+            Self::Tabloid => "Tabloid",
+        }
+    }
+
+    /// The width of the paper in portrait, in millimeters.
+    pub fn width(self) -> f64 {
+        self.size().0
+    }
+
+    /// The height of the paper in portrait, in millimeters.
+    pub fn height(self) -> f64 {
+        self.size().1
+    }
+
+    fn size(self) -> (f64, f64) {
+        match self {
+            Self::A3 => (297.0, 420.0),
+            Self::A4 => (210.0, 297.0),
+            Self::A5 => (148.0, 210.0),
+            Self::B5 => (176.0, 250.0),
+            Self::Letter => (215.9, 279.4),
+            Self::Legal => (215.9, 355.6),
+            Self::Executive => (184.15, 266.7),
+            Self::Tabloid => (279.4, 431.8),
+        }
+    }
+
+    /// The paper size named `name`, either its [name][`Self::name`] or its
+    /// [PPD name][`Self::ppd_name`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|paper| paper.name() == name || paper.ppd_name().eq_ignore_ascii_case(name))
+    }
+
+    /// The default paper size of `locale`, e.g. `en_US.UTF-8`.
+    ///
+    /// That is [`PaperSize::Letter`] in North America and in the parts of
+    /// Latin America using it, [`PaperSize::A4`] everywhere else.
+    pub fn for_locale(locale: &str) -> Self {
+        let territory = locale
+            .split(['.', '@'])
+            .next()
+            .and_then(|locale| locale.split_once('_'))
+            .map(|(_language, territory)| territory);
+        match territory {
+            Some(territory) if LETTER_TERRITORIES.contains(&territory) => Self::Letter,
+            _ => Self::A4,
+        }
+    }
+
+    /// The default paper size of the locale of the process, looked up in the
+    /// `LC_ALL`, `LC_PAPER` and `LANG` environment variables, see
+    /// [`PaperSize::for_locale`].
+    pub fn locale_default() -> Self {
+        let locale = ["LC_ALL", "LC_PAPER", "LANG"]
+            .into_iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|locale| !locale.is_empty());
+        locale.as_deref().map(Self::for_locale).unwrap_or(Self::A4)
+    }
+}
+
+impl fmt::Display for PaperSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.display_name())
+    }
+}
+
+impl FromStr for PaperSize {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::from_name(s).ok_or(Error::ParseError(
+            "Failed to parse paper size, invalid value",
+        ))
+    }
+}
+
+#[derive(Type, Debug, Default)]
 /// Print settings to set in the print dialog.
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct Settings {
     /// One of landscape, portrait, reverse_landscape or reverse_portrait.
     pub orientation: Option<Orientation>,
     /// A paper name according to [PWG 5101.1-2002](ftp://ftp.pwg.org/pub/pwg/candidates/cs-pwgmsn10-20020226-5101.1.pdf)
-    #[zvariant(rename = "paper-format")]
     pub paper_format: Option<String>,
     /// Paper width, in millimeters.
-    #[zvariant(rename = "paper-width")]
     pub paper_width: Option<String>,
     /// Paper height, in millimeters.
-    #[zvariant(rename = "paper-height")]
     pub paper_height: Option<String>,
     /// The number of copies to print.
-    #[zvariant(rename = "n-copies")]
     pub n_copies: Option<String>,
     /// The default paper source.
-    #[zvariant(rename = "default-source")]
     pub default_source: Option<String>,
     /// Print quality.
     pub quality: Option<Quality>,
     /// The resolution, sets both resolution-x & resolution-y
     pub resolution: Option<String>,
     /// Whether to use color.
-    #[zvariant(rename = "use-color")]
     pub use_color: Option<bool>,
     /// Duplex printing mode, one of simplex, horizontal or vertical.
     pub duplex: Option<String>,
@@ -211,7 +408,6 @@ pub struct Settings {
     /// Whether to reverse the order of printed pages.
     pub reverse: Option<String>,
     /// A media type according to [PWG 5101.1-2002](ftp://ftp.pwg.org/pub/pwg/candidates/cs-pwgmsn10-20020226-5101.1.pdf)
-    #[zvariant(rename = "media-type")]
     pub media_type: Option<String>,
     /// The dithering to use, one of fine, none, coarse, lineart, grayscale or
     /// error-diffusion.
@@ -219,45 +415,65 @@ pub struct Settings {
     /// The scale in percent
     pub scale: Option<String>,
     /// What pages to print, one of all, selection, current or ranges.
-    #[zvariant(rename = "print-pages")]
     pub print_pages: Option<String>,
     /// A list of page ranges, formatted like this: 0-2,4,9-11.
-    #[zvariant(rename = "page-ranges")]
     pub page_ranges: Option<String>,
     /// What pages to print, one of all, even or odd.
-    #[zvariant(rename = "page-set")]
     pub page_set: Option<String>,
     /// The finishings.
     pub finishings: Option<String>,
     /// The number of pages per sheet.
-    #[zvariant(rename = "number-up")]
     pub number_up: Option<String>,
     /// One of lrtb, lrbt, rltb, rlbt, tblr, tbrl, btlr, btrl.
-    #[zvariant(rename = "number-up-layout")]
     pub number_up_layout: Option<String>,
-    #[zvariant(rename = "output-bin")]
     /// The output bin.
     pub output_bin: Option<String>,
     /// The horizontal resolution in dpi.
-    #[zvariant(rename = "resolution-x")]
     pub resolution_x: Option<String>,
     /// The vertical resolution in dpi.
-    #[zvariant(rename = "resolution-y")]
     pub resolution_y: Option<String>,
     /// The resolution in lpi (lines per inch).
-    #[zvariant(rename = "printer-lpi")]
     pub print_lpi: Option<String>,
     /// Basename to use for print-to-file.
-    #[zvariant(rename = "output-basename")]
     pub output_basename: Option<String>,
     /// Format to use for print-to-file, one of PDF, PS, SVG
-    #[zvariant(rename = "output-file-format")]
     pub output_file_format: Option<String>,
     /// The uri used for print-to file.
-    #[zvariant(rename = "output-uri")]
     pub output_uri: Option<url::Url>,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(Settings with Serialize {
+    orientation => "orientation",
+    paper_format => "paper-format",
+    paper_width => "paper-width",
+    paper_height => "paper-height",
+    n_copies => "n-copies",
+    default_source => "default-source",
+    quality => "quality",
+    resolution => "resolution",
+    use_color => "use-color",
+    duplex => "duplex",
+    collate => "collate",
+    reverse => "reverse",
+    media_type => "media-type",
+    dither => "dither",
+    scale => "scale",
+    print_pages => "print-pages",
+    page_ranges => "page-ranges",
+    page_set => "page-set",
+    finishings => "finishings",
+    number_up => "number-up",
+    number_up_layout => "number-up-layout",
+    output_bin => "output-bin",
+    resolution_x => "resolution-x",
+    resolution_y => "resolution-y",
+    print_lpi => "printer-lpi",
+    output_basename => "output-basename",
+    output_file_format => "output-file-format",
+    output_uri => "output-uri",
+});
+
 impl Settings {
     /// Sets the orientation.
     #[must_use]
@@ -266,6 +482,15 @@ impl Settings {
         self
     }
 
+    /// Sets the paper name and size to the ones of `paper_size`.
+    #[must_use]
+    pub fn paper_size(mut self, paper_size: PaperSize) -> Self {
+        self.paper_format = Some(paper_size.name().to_owned());
+        self.paper_width = Some(paper_size.width().to_string());
+        self.paper_height = Some(paper_size.height().to_string());
+        self
+    }
+
     /// Sets the paper name.
     #[must_use]
     pub fn paper_format<'a>(mut self, paper_format: impl Into<Option<&'a str>>) -> Self {
@@ -458,14 +683,19 @@ impl Settings {
         self.output_uri = output_uri.into().map(ToOwned::to_owned);
         self
     }
+
+    /// The settings unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
-#[derive(SerializeDict, DeserializeDict, Type, Debug, Default)]
+#[derive(Type, Debug, Default)]
 /// Setup the printed pages.
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct PageSetup {
     /// the PPD name. It's the name to select a given driver.
-    #[zvariant(rename = "PPDName")]
     pub ppdname: Option<String>,
     /// The name of the page setup.
     pub name: Option<String>,
@@ -485,9 +715,40 @@ pub struct PageSetup {
     pub margin_left: Option<f64>,
     /// The page orientation.
     pub orientation: Option<Orientation>,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(PageSetup with Serialize {
+    ppdname => "PPDName",
+    name => "name",
+    display_name => "display_name",
+    width => "width",
+    height => "height",
+    margin_top => "margin_top",
+    margin_bottom => "margin_bottom",
+    margin_right => "margin_right",
+    margin_left => "margin_left",
+    orientation => "orientation",
+});
+
 impl PageSetup {
+    /// A page setup using the paper size of the user's locale, see
+    /// [`PaperSize::locale_default`].
+    pub fn locale_default() -> Self {
+        Self::default().paper_size(PaperSize::locale_default())
+    }
+
+    /// Sets the names and the size of the page to the ones of
+    /// `paper_size`.
+    #[must_use]
+    pub fn paper_size(self, paper_size: PaperSize) -> Self {
+        self.ppdname(paper_size.ppd_name())
+            .name(paper_size.name())
+            .display_name(paper_size.display_name())
+            .width(paper_size.width())
+            .height(paper_size.height())
+    }
+
     /// Sets the ppdname.
     #[must_use]
     pub fn ppdname<'a>(mut self, ppdname: impl Into<Option<&'a str>>) -> Self {
@@ -557,6 +818,11 @@ impl PageSetup {
         self.margin_left = margin_left.into();
         self
     }
+
+    /// The page setup entries unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
 #[derive(SerializeDict, Type, Debug, Default)]
@@ -614,17 +880,31 @@ impl PrintOptions {
     }
 }
 
-#[derive(DeserializeDict, SerializeDict, Type, Debug)]
+#[derive(Type, Debug)]
 /// A response to a [`PrintProxy::prepare_print`] request.
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct PreparePrint {
     /// The printing settings.
     pub settings: Settings,
-    #[zvariant(rename = "page-setup")]
     /// The printed pages setup.
     pub page_setup: PageSetup,
     /// A token to pass to the print request.
     pub token: u32,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(PreparePrint with Serialize {
+    settings [required] => "settings",
+    page_setup [required] => "page-setup",
+    token [required] => "token",
+});
+
+impl PreparePrint {
+    /// The entries of the response unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
 /// The interface lets sandboxed applications print.
@@ -641,6 +921,18 @@ impl<'a> PrintProxy<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`PrintProxy`] using `connection` instead of
+    /// the session bus connection, e.g. a peer-to-peer connection to the
+    /// portal.
+    pub async fn new_with_connection(
+        connection: zbus::Connection,
+    ) -> Result<PrintProxy<'a>, Error> {
+        let proxy =
+            Proxy::new_desktop_with_connection(Some(connection), "org.freedesktop.portal.Print")
+                .await?;
+        Ok(Self(proxy))
+    }
+
     // TODO accept_label: Added in version 2 of the interface.
     /// Presents a print dialog to the user and returns print settings and page
     /// setup.
@@ -719,6 +1011,76 @@ impl<'a> PrintProxy<'a> {
             )
             .await
     }
+
+    /// Like [`print()`][`PrintProxy::print`], but takes the path of the file
+    /// to print, handed over to the portal following `policy`.
+    ///
+    /// The portal only accepts file descriptors, the file is opened for
+    /// reading once exported.
+    ///
+    /// See [`print()`][`PrintProxy::print`] for the other arguments.
+    pub async fn print_path(
+        &self,
+        identifier: &WindowIdentifier,
+        title: &str,
+        path: impl AsRef<Path>,
+        policy: PathPolicy,
+        token: Option<u32>,
+        modal: bool,
+    ) -> Result<Request<()>, Error> {
+        let fd = ExportedPath::new(path, policy).await?.into_fd()?;
+        self.print(identifier, title, &fd.as_fd(), token, modal)
+            .await
+    }
+
+    /// Like [`print()`][`PrintProxy::print`], but also listens to a signal
+    /// emitted by the backend on the request object while the print job is
+    /// in progress.
+    ///
+    /// Such signals are not part of the specification, some backends use them
+    /// to report the progress of the job. The returned stream ends once the
+    /// returned future resolves to the [`Request`].
+    ///
+    /// # Arguments
+    ///
+    /// * `signal_interface` - The interface of the signal.
+    /// * `signal_name` - The name of the signal, its body is deserialized to
+    ///   `I`.
+    ///
+    /// See [`print()`][`PrintProxy::print`] for the other arguments.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn print_with_progress<I>(
+        &self,
+        identifier: &WindowIdentifier,
+        title: &str,
+        fd: &BorrowedFd<'_>,
+        token: Option<u32>,
+        modal: bool,
+        signal_interface: &'static str,
+        signal_name: &'static str,
+    ) -> Result<
+        (
+            BoxStream<'static, I>,
+            BoxFuture<'static, Result<Request<()>, Error>>,
+        ),
+        Error,
+    >
+    where
+        I: for<'de> Deserialize<'de> + zbus::zvariant::Type + fmt::Debug + Send + 'static,
+    {
+        let options = PrintOptions::default()
+            .token(token.unwrap_or(0))
+            .modal(modal);
+        self.0
+            .request_with_signal(
+                &options.handle_token,
+                "Print",
+                &(&identifier, title, Fd::from(fd), &options),
+                signal_interface,
+                signal_name,
+            )
+            .await
+    }
 }
 
 impl<'a> std::ops::Deref for PrintProxy<'a> {
@@ -728,3 +1090,195 @@ impl<'a> std::ops::Deref for PrintProxy<'a> {
         &self.0
     }
 }
+
+/// The outcome of a [`PrintRequest`].
+#[derive(Debug)]
+pub enum PrintOutcome {
+    /// The document was sent to the printer, using the settings picked by the
+    /// user.
+    Printed(Box<PreparePrint>),
+    /// The user cancelled the print dialog.
+    Cancelled,
+}
+
+#[derive(Debug, Default)]
+#[doc(alias = "xdp_portal_print_file")]
+/// A [builder-pattern] type to print a document.
+///
+/// It presents the print dialog through
+/// [`prepare_print()`][`PrintProxy::prepare_print`] then passes the returned
+/// token along with the document to [`print()`][`PrintProxy::print`].
+///
+/// ```rust,no_run
+/// use ashpd::desktop::print::{PrintOutcome, PrintRequest};
+///
+/// async fn run() -> ashpd::Result<()> {
+///     let outcome = PrintRequest::default()
+///         .title("Print the report")
+///         .modal(true)
+///         .print_path("report.pdf", Default::default())
+///         .await?;
+///     if let PrintOutcome::Printed(prepared) = outcome {
+///         println!("{:#?}", prepared.settings);
+///     }
+///     Ok(())
+/// }
+/// ```
+///
+/// [builder-pattern]: https://doc.rust-lang.org/1.0.0/style/ownership/builders.html
+pub struct PrintRequest {
+    identifier: WindowIdentifier,
+    title: String,
+    settings: Settings,
+    page_setup: PageSetup,
+    accept_label: Option<String>,
+    modal: bool,
+    close_on_drop: Option<bool>,
+}
+
+impl PrintRequest {
+    /// Sets a window identifier.
+    #[must_use]
+    pub fn identifier(mut self, identifier: impl Into<Option<WindowIdentifier>>) -> Self {
+        self.identifier = identifier.into().unwrap_or_default();
+        self
+    }
+
+    /// Sets a title for the print dialog.
+    #[must_use]
+    pub fn title<'a>(mut self, title: impl Into<Option<&'a str>>) -> Self {
+        self.title = title.into().map(ToOwned::to_owned).unwrap_or_default();
+        self
+    }
+
+    /// Sets the initial print settings.
+    #[must_use]
+    pub fn settings(mut self, settings: Settings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    /// Sets the initial page setup.
+    #[must_use]
+    pub fn page_setup(mut self, page_setup: PageSetup) -> Self {
+        self.page_setup = page_setup;
+        self
+    }
+
+    /// Label for the accept button. Mnemonic underlines are allowed.
+    #[must_use]
+    pub fn accept_label<'a>(mut self, accept_label: impl Into<Option<&'a str>>) -> Self {
+        self.accept_label = accept_label.into().map(ToOwned::to_owned);
+        self
+    }
+
+    /// Sets whether the dialogs should be modal.
+    #[must_use]
+    pub fn modal(mut self, modal: bool) -> Self {
+        self.modal = modal;
+        self
+    }
+
+    /// Whether to close the request, dismissing its dialog, if the future
+    /// sending it is dropped before the response is received. Overrides
+    /// [`set_close_on_drop`](crate::set_close_on_drop) for this request.
+    #[must_use]
+    pub fn close_on_drop(mut self, close: bool) -> Self {
+        self.close_on_drop = Some(close);
+        self
+    }
+
+    /// Print `data`.
+    ///
+    /// The data is written to an unlinked temporary file whose file
+    /// descriptor is passed to the portal.
+    pub async fn print_bytes(self, data: &[u8]) -> Result<PrintOutcome, Error> {
+        let path = std::env::temp_dir().join(format!("ashpd-print-{}", HandleToken::default()));
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        std::fs::remove_file(&path)?;
+        file.write_all(data)?;
+        file.rewind()?;
+        self.print_fd(file.into()).await
+    }
+
+    /// Print the file at `path`, handed over to the portal following
+    /// `policy`.
+    pub async fn print_path(
+        self,
+        path: impl AsRef<Path>,
+        policy: PathPolicy,
+    ) -> Result<PrintOutcome, Error> {
+        let fd = ExportedPath::new(path, policy).await?.into_fd()?;
+        self.print_fd(fd).await
+    }
+
+    async fn print_fd(self, fd: OwnedFd) -> Result<PrintOutcome, Error> {
+        let mut proxy = PrintProxy::new().await?;
+        proxy.0.close_requests_on_drop(self.close_on_drop);
+        let prepared = proxy
+            .prepare_print(
+                &self.identifier,
+                &self.title,
+                self.settings,
+                self.page_setup,
+                self.accept_label.as_deref(),
+                self.modal,
+            )
+            .await?
+            .response();
+        let prepared = match prepared {
+            Ok(prepared) => prepared,
+            Err(Error::Response(ResponseError::Cancelled)) => return Ok(PrintOutcome::Cancelled),
+            Err(err) => return Err(err),
+        };
+        let printed = proxy
+            .print(
+                &self.identifier,
+                &self.title,
+                &fd.as_fd(),
+                Some(prepared.token),
+                self.modal,
+            )
+            .await?
+            .response();
+        match printed {
+            Ok(()) => Ok(PrintOutcome::Printed(Box::new(prepared))),
+            Err(Error::Response(ResponseError::Cancelled)) => Ok(PrintOutcome::Cancelled),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paper_sizes() {
+        assert_eq!(PaperSize::for_locale("en_US.UTF-8"), PaperSize::Letter);
+        assert_eq!(PaperSize::for_locale("fr_CA"), PaperSize::Letter);
+        assert_eq!(PaperSize::for_locale("en_GB.UTF-8"), PaperSize::A4);
+        assert_eq!(PaperSize::for_locale("de_DE@euro"), PaperSize::A4);
+        assert_eq!(PaperSize::for_locale("C"), PaperSize::A4);
+
+        assert_eq!(PaperSize::from_name("na_letter"), Some(PaperSize::Letter));
+        assert_eq!("a4".parse::<PaperSize>().unwrap(), PaperSize::A4);
+        assert!("a0".parse::<PaperSize>().is_err());
+        for paper in PaperSize::ALL {
+            assert_eq!(PaperSize::from_name(paper.name()), Some(paper));
+            assert!(paper.width() < paper.height());
+        }
+
+        let page_setup = PageSetup::default().paper_size(PaperSize::Letter);
+        assert_eq!(page_setup.name.as_deref(), Some("na_letter"));
+        assert_eq!(page_setup.ppdname.as_deref(), Some("Letter"));
+        assert_eq!(page_setup.width, Some(215.9));
+        let settings = Settings::default().paper_size(PaperSize::A4);
+        assert_eq!(settings.paper_format.as_deref(), Some("iso_a4"));
+        assert_eq!(settings.paper_height.as_deref(), Some("297"));
+    }
+}
@@ -34,6 +34,20 @@ impl<'a> ProxyResolver<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`ProxyResolver`] using `connection` instead of
+    /// the session bus connection, e.g. a peer-to-peer connection to the
+    /// portal.
+    pub async fn new_with_connection(
+        connection: zbus::Connection,
+    ) -> Result<ProxyResolver<'a>, Error> {
+        let proxy = Proxy::new_desktop_with_connection(
+            Some(connection),
+            "org.freedesktop.portal.ProxyResolver",
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
     /// Looks up which proxy to use to connect to `uri`.
     ///
     /// # Returns
@@ -18,6 +18,15 @@ impl<'a> Realtime<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`Realtime`] using `connection` instead of the
+    /// session bus connection, e.g. a peer-to-peer connection to the portal.
+    pub async fn new_with_connection(connection: zbus::Connection) -> Result<Realtime<'a>, Error> {
+        let proxy =
+            Proxy::new_desktop_with_connection(Some(connection), "org.freedesktop.portal.Realtime")
+                .await?;
+        Ok(Self(proxy))
+    }
+
     #[doc(alias = "MakeThreadRealtimeWithPID")]
     #[allow(missing_docs)]
     pub async fn max_thread_realtime_with_pid(
@@ -92,6 +92,35 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! The same can be achieved with [`RemoteSession`] which takes care of calling
+//! the various methods in the right order.
+//!
+//! ```rust,no_run
+//! use ashpd::desktop::{
+//!     remote_desktop::{DeviceType, KeyState, RemoteSession},
+//!     screencast::{CursorMode, SourceType},
+//! };
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     let remote = RemoteSession::builder()
+//!         .devices(DeviceType::Keyboard | DeviceType::Pointer)
+//!         .sources(SourceType::Monitor.into())
+//!         .cursor_mode(CursorMode::Metadata)
+//!         .start()
+//!         .await?;
+//!     println!("{:#?}", remote.devices());
+//!     println!("{:#?}", remote.streams());
+//!     println!("{:#?}", remote.cursor_mode());
+//!
+//!     let _fd = remote.open_pipe_wire_remote().await?;
+//!     remote
+//!         .remote_desktop()
+//!         .notify_keyboard_keycode(remote.session(), 13, KeyState::Pressed)
+//!         .await?;
+//!     Ok(())
+//! }
+//! ```
 //! [select_sources]: crate::desktop::screencast::Screencast::select_sources
 //! [create_session]: crate::desktop::remote_desktop::RemoteDesktop::create_session
 
@@ -100,12 +129,17 @@ use std::{collections::HashMap, os::fd::OwnedFd};
 use enumflags2::{bitflags, BitFlags};
 use futures_util::TryFutureExt;
 use serde_repr::{Deserialize_repr, Serialize_repr};
-use zbus::zvariant::{self, DeserializeDict, SerializeDict, Type, Value};
+use zbus::zvariant::{self, OwnedValue, SerializeDict, Type, Value};
 
 use super::{
-    screencast::Stream, session::SessionPortal, HandleToken, PersistMode, Request, Session,
+    screencast::{CursorMode, Screencast, SourceType, Stream},
+    session::SessionPortal,
+    HandleToken, PersistMode, Request, Session,
+};
+use crate::{
+    desktop::session::CreateSessionResponse, helpers::SecretString, proxy::Proxy, Error,
+    WindowIdentifier,
 };
-use crate::{desktop::session::CreateSessionResponse, proxy::Proxy, Error, WindowIdentifier};
 
 #[cfg_attr(feature = "glib", derive(glib::Enum))]
 #[cfg_attr(feature = "glib", enum_type(name = "AshpdKeyState"))]
@@ -122,6 +156,8 @@ pub enum KeyState {
     Released = 0,
 }
 
+crate::helpers::impl_value_conversions!(KeyState as u32 { Pressed, Released });
+
 #[bitflags]
 #[derive(Serialize_repr, Deserialize_repr, PartialEq, Eq, Debug, Clone, Copy, Type)]
 #[repr(u32)]
@@ -139,6 +175,8 @@ pub enum DeviceType {
     Touchscreen,
 }
 
+crate::helpers::impl_value_conversions!(DeviceType as u32 { Keyboard, Pointer, Touchscreen });
+
 #[cfg_attr(feature = "glib", derive(glib::Enum))]
 #[cfg_attr(feature = "glib", enum_type(name = "AshpdAxis"))]
 #[derive(Serialize_repr, Deserialize_repr, PartialEq, Eq, Debug, Clone, Copy, Type)]
@@ -154,6 +192,8 @@ pub enum Axis {
     Horizontal = 1,
 }
 
+crate::helpers::impl_value_conversions!(Axis as u32 { Vertical, Horizontal });
+
 #[derive(SerializeDict, Type, Debug, Default)]
 /// Specified options for a [`RemoteDesktop::create_session`] request.
 #[zvariant(signature = "dict")]
@@ -172,7 +212,7 @@ struct SelectDevicesOptions {
     handle_token: HandleToken,
     /// The device types to request remote controlling of. Default is all.
     types: Option<BitFlags<DeviceType>>,
-    restore_token: Option<String>,
+    restore_token: Option<SecretString>,
     persist_mode: Option<PersistMode>,
 }
 
@@ -189,7 +229,7 @@ impl SelectDevicesOptions {
     }
 
     pub fn restore_token<'a>(mut self, token: impl Into<Option<&'a str>>) -> Self {
-        self.restore_token = token.into().map(ToOwned::to_owned);
+        self.restore_token = token.into().map(SecretString::from);
         self
     }
 }
@@ -202,15 +242,23 @@ struct StartRemoteOptions {
     handle_token: HandleToken,
 }
 
-#[derive(DeserializeDict, Type, Debug, Default)]
+#[derive(Type, Debug, Default)]
 /// A response to a [`RemoteDesktop::select_devices`] request.
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct SelectedDevices {
     devices: BitFlags<DeviceType>,
     streams: Option<Vec<Stream>>,
-    restore_token: Option<String>,
+    restore_token: Option<SecretString>,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(SelectedDevices {
+    devices [required] => "devices",
+    streams => "streams",
+    restore_token => "restore_token",
+});
+
 impl SelectedDevices {
     /// The selected devices.
     pub fn devices(&self) -> BitFlags<DeviceType> {
@@ -224,7 +272,12 @@ impl SelectedDevices {
 
     /// The session restore token.
     pub fn restore_token(&self) -> Option<&str> {
-        self.restore_token.as_deref()
+        self.restore_token.as_ref().map(SecretString::as_str)
+    }
+
+    /// The entries of the response unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
     }
 }
 
@@ -242,6 +295,20 @@ impl<'a> RemoteDesktop<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`RemoteDesktop`] using `connection` instead of
+    /// the session bus connection, e.g. a peer-to-peer connection to the
+    /// portal.
+    pub async fn new_with_connection(
+        connection: zbus::Connection,
+    ) -> Result<RemoteDesktop<'a>, Error> {
+        let proxy = Proxy::new_desktop_with_connection(
+            Some(connection),
+            "org.freedesktop.portal.RemoteDesktop",
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
     /// Create a remote desktop session.
     /// A remote desktop session is used to allow remote controlling a desktop
     /// session. It can also be used together with a screen cast session.
@@ -252,14 +319,18 @@ impl<'a> RemoteDesktop<'a> {
     #[doc(alias = "CreateSession")]
     #[doc(alias = "xdp_portal_create_remote_desktop_session")]
     pub async fn create_session(&self) -> Result<Session<'a, Self>, Error> {
-        let options = CreateRemoteOptions::default();
+        let session_handle_token = HandleToken::default();
+        let options = CreateRemoteOptions {
+            handle_token: session_handle_token.derive(0),
+            session_handle_token,
+        };
         let (request, proxy) = futures_util::try_join!(
             self.0
                 .request::<CreateSessionResponse>(&options.handle_token, "CreateSession", &options)
                 .into_future(),
-            Session::from_unique_name(&options.session_handle_token).into_future()
+            Session::from_unique_name(&self.0, &options.session_handle_token).into_future()
         )?;
-        assert_eq!(proxy.path(), &request.response()?.session_handle.as_ref());
+        proxy.check_created(&request.response()?.session_handle)?;
         Ok(proxy)
     }
 
@@ -286,6 +357,22 @@ impl<'a> RemoteDesktop<'a> {
             .types(types)
             .persist_mode(persist_mode)
             .restore_token(restore_token);
+        let options = SelectDevicesOptions {
+            handle_token: session.next_handle_token(),
+            ..options
+        };
+        self.0.check_option(
+            "SelectDevices",
+            "restore_token",
+            2,
+            options.restore_token.is_some(),
+        )?;
+        self.0.check_option(
+            "SelectDevices",
+            "persist_mode",
+            2,
+            persist_mode != PersistMode::DoNot,
+        )?;
         self.0
             .empty_request(&options.handle_token, "SelectDevices", &(session, &options))
             .await
@@ -312,7 +399,9 @@ impl<'a> RemoteDesktop<'a> {
         session: &Session<'_, Self>,
         identifier: &WindowIdentifier,
     ) -> Result<Request<SelectedDevices>, Error> {
-        let options = StartRemoteOptions::default();
+        let options = StartRemoteOptions {
+            handle_token: session.next_handle_token(),
+        };
         self.0
             .request(
                 &options.handle_token,
@@ -696,3 +785,257 @@ impl<'a> std::ops::Deref for RemoteDesktop<'a> {
 }
 
 impl SessionPortal for RemoteDesktop<'_> {}
+
+/// A remote desktop session also used for screen casting.
+///
+/// Combining the two portals requires creating the session with
+/// [`RemoteDesktop::create_session`], selecting the devices, then the sources
+/// with [`Screencast::select_sources`] and finally starting it with
+/// [`RemoteDesktop::start`]. The restore token and persist mode only apply to
+/// the devices selection in that case.
+///
+/// Use [`RemoteSession::builder`] to create one.
+#[derive(Debug)]
+pub struct RemoteSession<'a> {
+    remote_desktop: RemoteDesktop<'a>,
+    screencast: Screencast<'a>,
+    session: Session<'a, RemoteDesktop<'a>>,
+    cursor_mode: Option<CursorMode>,
+    selected: SelectedDevices,
+}
+
+impl RemoteSession<'static> {
+    /// Creates a new builder-pattern struct instance to construct
+    /// [`RemoteSession`].
+    pub fn builder() -> RemoteSessionBuilder {
+        RemoteSessionBuilder::default()
+    }
+}
+
+impl<'a> RemoteSession<'a> {
+    /// The session, to be passed to the various [`RemoteDesktop`] methods.
+    pub fn session(&self) -> &Session<'a, RemoteDesktop<'a>> {
+        &self.session
+    }
+
+    /// The remote desktop proxy the session was created with.
+    pub fn remote_desktop(&self) -> &RemoteDesktop<'a> {
+        &self.remote_desktop
+    }
+
+    /// The screen cast proxy the sources were selected with.
+    pub fn screencast(&self) -> &Screencast<'a> {
+        &self.screencast
+    }
+
+    /// The selected devices.
+    pub fn devices(&self) -> BitFlags<DeviceType> {
+        self.selected.devices()
+    }
+
+    /// The selected streams, empty if no sources were requested.
+    pub fn streams(&self) -> &[Stream] {
+        self.selected.streams().unwrap_or_default()
+    }
+
+    /// The cursor mode used for the streams, if any sources were requested.
+    ///
+    /// It might differ from the requested one if the portal doesn't support
+    /// it.
+    pub fn cursor_mode(&self) -> Option<CursorMode> {
+        self.cursor_mode
+    }
+
+    /// The session restore token.
+    pub fn restore_token(&self) -> Option<&str> {
+        self.selected.restore_token()
+    }
+
+    /// Open a file descriptor to the PipeWire remote where the streams are
+    /// available.
+    ///
+    /// See also [`Screencast::open_pipe_wire_remote`].
+    pub async fn open_pipe_wire_remote(&self) -> Result<OwnedFd, Error> {
+        self.screencast.open_pipe_wire_remote(&self.session).await
+    }
+
+    /// Connect to EIS.
+    ///
+    /// See also [`RemoteDesktop::connect_to_eis`].
+    pub async fn connect_to_eis(&self) -> Result<OwnedFd, Error> {
+        self.remote_desktop.connect_to_eis(&self.session).await
+    }
+
+    /// Close the session.
+    pub async fn close(&self) -> Result<(), Error> {
+        self.session.close().await
+    }
+}
+
+/// A [builder-pattern] type to construct a [`RemoteSession`].
+///
+/// [builder-pattern]: https://doc.rust-lang.org/1.0.0/style/ownership/builders.html
+#[derive(Debug, Default)]
+pub struct RemoteSessionBuilder {
+    identifier: WindowIdentifier,
+    devices: BitFlags<DeviceType>,
+    sources: BitFlags<SourceType>,
+    cursor_mode: Option<CursorMode>,
+    multiple: bool,
+    restore_token: Option<SecretString>,
+    persist_mode: PersistMode,
+}
+
+impl RemoteSessionBuilder {
+    #[must_use]
+    /// Sets a window identifier.
+    pub fn identifier(mut self, identifier: impl Into<Option<WindowIdentifier>>) -> Self {
+        self.identifier = identifier.into().unwrap_or_default();
+        self
+    }
+
+    #[must_use]
+    /// Sets the device types to request remote controlling of.
+    pub fn devices(mut self, devices: BitFlags<DeviceType>) -> Self {
+        self.devices = devices;
+        self
+    }
+
+    #[must_use]
+    /// Sets the types of content to record. No screen cast sources are
+    /// selected if empty.
+    pub fn sources(mut self, sources: BitFlags<SourceType>) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    #[must_use]
+    /// Sets how the cursor should be drawn in the streams.
+    ///
+    /// If the portal doesn't support the requested mode, the first supported
+    /// one of [`CursorMode::Embedded`] and [`CursorMode::Hidden`] is used
+    /// instead.
+    pub fn cursor_mode(mut self, cursor_mode: impl Into<Option<CursorMode>>) -> Self {
+        self.cursor_mode = cursor_mode.into();
+        self
+    }
+
+    #[must_use]
+    /// Sets whether to allow selecting multiple sources.
+    pub fn multiple(mut self, multiple: bool) -> Self {
+        self.multiple = multiple;
+        self
+    }
+
+    #[must_use]
+    /// Sets the token of a previous session to restore.
+    pub fn restore_token<'a>(mut self, token: impl Into<Option<&'a str>>) -> Self {
+        self.restore_token = token.into().map(SecretString::from);
+        self
+    }
+
+    #[must_use]
+    /// Sets how the session should be persisted.
+    pub fn persist_mode(mut self, persist_mode: PersistMode) -> Self {
+        self.persist_mode = persist_mode;
+        self
+    }
+
+    /// Create the session, select the devices and sources then start it.
+    pub async fn start(self) -> Result<RemoteSession<'static>, Error> {
+        let remote_desktop = RemoteDesktop::new().await?;
+        let screencast = Screencast::new().await?;
+        let session = remote_desktop.create_session().await?;
+
+        remote_desktop
+            .select_devices(
+                &session,
+                self.devices,
+                self.restore_token.as_ref().map(SecretString::as_str),
+                self.persist_mode,
+            )
+            .await?
+            .response()?;
+
+        let cursor_mode = if self.sources.is_empty() {
+            None
+        } else {
+            let cursor_mode = match self.cursor_mode {
+                Some(requested) => match screencast.available_cursor_modes().await {
+                    Ok(available) => negotiate_cursor_mode(requested, available),
+                    // The property requires version 2, let the portal decide
+                    Err(Error::RequiresVersion(_, _)) => Some(requested),
+                    Err(err) => return Err(err),
+                },
+                None => None,
+            };
+            #[cfg(feature = "tracing")]
+            if cursor_mode != self.cursor_mode {
+                tracing::warn!(
+                    "Cursor mode {:?} is not available, using {:?} instead",
+                    self.cursor_mode,
+                    cursor_mode
+                );
+            }
+            let options = crate::desktop::screencast::SelectSourcesOptions::default()
+                .cursor_mode(cursor_mode)
+                .types(self.sources)
+                .multiple(self.multiple);
+            screencast
+                .select_sources_with_options(&session, options)
+                .await?
+                .response()?;
+            cursor_mode
+        };
+
+        let selected = remote_desktop
+            .start(&session, &self.identifier)
+            .await?
+            .response()?;
+
+        Ok(RemoteSession {
+            remote_desktop,
+            screencast,
+            session,
+            cursor_mode,
+            selected,
+        })
+    }
+}
+
+fn negotiate_cursor_mode(
+    requested: CursorMode,
+    available: BitFlags<CursorMode>,
+) -> Option<CursorMode> {
+    [requested, CursorMode::Embedded, CursorMode::Hidden]
+        .into_iter()
+        .find(|mode| available.contains(*mode))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_mode_negotiation() {
+        assert_eq!(
+            negotiate_cursor_mode(CursorMode::Metadata, BitFlags::all()),
+            Some(CursorMode::Metadata)
+        );
+        assert_eq!(
+            negotiate_cursor_mode(
+                CursorMode::Metadata,
+                CursorMode::Hidden | CursorMode::Embedded
+            ),
+            Some(CursorMode::Embedded)
+        );
+        assert_eq!(
+            negotiate_cursor_mode(CursorMode::Metadata, CursorMode::Hidden.into()),
+            Some(CursorMode::Hidden)
+        );
+        assert_eq!(
+            negotiate_cursor_mode(CursorMode::Embedded, BitFlags::empty()),
+            None
+        );
+    }
+}
@@ -5,18 +5,30 @@ use std::{
     sync::Mutex,
 };
 
-use futures_util::StreamExt;
 use serde::{
     de::{self, Error as SeError, Visitor},
     ser::SerializeTuple,
     Deserialize, Deserializer, Serialize,
 };
-use zbus::{
-    proxy::SignalStream,
-    zvariant::{ObjectPath, Type, Value},
-};
+use zbus::zvariant::{ObjectPath, Type, Value};
+
+use crate::{proxy::Proxy, Error};
+
+cfg_request! {
+    use std::pin::pin;
 
-use crate::{desktop::HandleToken, proxy::Proxy, Error};
+    use futures_util::{
+        future::{self, Either},
+        StreamExt,
+    };
+    use zbus::{
+        fdo::{DBusProxy, NameOwnerChangedStream},
+        proxy::{CacheProperties, SignalStream},
+        Message,
+    };
+
+    use crate::desktop::HandleToken;
+}
 
 /// A typical response returned by the [`Request::response`].
 /// of a [`Request`].
@@ -29,6 +41,61 @@ pub enum Response<T> {
     Err(ResponseError),
 }
 
+impl<T> Response<T> {
+    /// Whether the request was carried out.
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Self::Ok(_))
+    }
+
+    /// Whether the user cancelled the request.
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self, Self::Err(ResponseError::Cancelled))
+    }
+
+    /// Maps the result of a successful response with `f`.
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> Response<U> {
+        match self {
+            Self::Ok(inner) => Response::Ok(f(inner)),
+            Self::Err(err) => Response::Err(err),
+        }
+    }
+
+    /// Converts the response to a [`Result`].
+    pub fn into_result(self) -> Result<T, ResponseError> {
+        self.into()
+    }
+
+    /// Converts the response to a [`Result`] with `None` if the user
+    /// cancelled the request, which usually isn't an error worth reporting.
+    ///
+    /// See also [`Request::response_or_cancelled`].
+    pub fn ok_or_cancelled(self) -> Result<Option<T>, ResponseError> {
+        match self {
+            Self::Ok(inner) => Ok(Some(inner)),
+            Self::Err(ResponseError::Cancelled) => Ok(None),
+            Self::Err(err) => Err(err),
+        }
+    }
+}
+
+impl<T> From<Response<T>> for Result<T, ResponseError> {
+    fn from(response: Response<T>) -> Self {
+        match response {
+            Response::Ok(inner) => Ok(inner),
+            Response::Err(err) => Err(err),
+        }
+    }
+}
+
+impl<T> From<Result<T, ResponseError>> for Response<T> {
+    fn from(result: Result<T, ResponseError>) -> Self {
+        match result {
+            Ok(inner) => Self::Ok(inner),
+            Err(err) => Self::Err(err),
+        }
+    }
+}
+
 #[cfg(feature = "backend")]
 #[cfg_attr(docsrs, doc(cfg(feature = "backend")))]
 impl<T> Response<T> {
@@ -199,9 +266,31 @@ impl From<ResponseError> for ResponseType {
 #[doc(alias = "org.freedesktop.portal.Request")]
 pub struct Request<T>(
     Proxy<'static>,
-    SignalStream<'static>,
     Mutex<Option<Result<T, Error>>>,
     PhantomData<T>,
+    #[cfg(any(
+        feature = "account",
+        feature = "background",
+        feature = "camera",
+        feature = "device",
+        feature = "dynamic_launcher",
+        feature = "email",
+        feature = "file_chooser",
+        feature = "global_shortcuts",
+        feature = "inhibit",
+        feature = "input_capture",
+        feature = "location",
+        feature = "open_uri",
+        feature = "print",
+        feature = "screencast",
+        feature = "screenshot",
+        feature = "secret",
+        feature = "wallpaper",
+    ))]
+    Option<(
+        SignalStream<'static>,
+        Option<NameOwnerChangedStream<'static>>,
+    )>,
 )
 where
     T: for<'de> Deserialize<'de> + Type + Debug;
@@ -210,40 +299,6 @@ impl<T> Request<T>
 where
     T: for<'de> Deserialize<'de> + Type + Debug,
 {
-    pub(crate) async fn new<P>(path: P) -> Result<Request<T>, Error>
-    where
-        P: TryInto<ObjectPath<'static>>,
-        P::Error: Into<zbus::Error>,
-    {
-        let proxy = Proxy::new_desktop_with_path("org.freedesktop.portal.Request", path).await?;
-        // Start listening for a response signal the moment request is created
-        let stream = proxy.receive_signal("Response").await?;
-        Ok(Self(proxy, stream, Default::default(), PhantomData))
-    }
-
-    pub(crate) async fn from_unique_name(handle_token: &HandleToken) -> Result<Request<T>, Error> {
-        let path =
-            Proxy::unique_name("/org/freedesktop/portal/desktop/request", handle_token).await?;
-        #[cfg(feature = "tracing")]
-        tracing::info!("Creating a org.freedesktop.portal.Request {}", path);
-        Self::new(path).await
-    }
-
-    pub(crate) async fn prepare_response(&mut self) -> Result<(), Error> {
-        let message = self.1.next().await.ok_or(Error::NoResponse)?;
-        #[cfg(feature = "tracing")]
-        tracing::info!("Received signal 'Response' on '{}'", self.0.interface());
-        let response = match message.body().deserialize::<Response<T>>()? {
-            Response::Err(e) => Err(e.into()),
-            Response::Ok(r) => Ok(r),
-        };
-        #[cfg(feature = "tracing")]
-        tracing::debug!("Received response {:#?}", response);
-        let r = response as Result<T, Error>;
-        *self.2.get_mut().unwrap() = Some(r);
-        Ok(())
-    }
-
     /// The corresponding response if the request was successful.
     ///
     /// # Specifications
@@ -252,13 +307,37 @@ where
     pub fn response(&self) -> Result<T, Error> {
         // It should be safe to unwrap here as we are sure we have received a response
         // by the time the user calls response
-        self.2.lock().unwrap().take().unwrap()
+        self.1.lock().unwrap().take().unwrap()
+    }
+
+    /// Like [`Request::response`], with `None` if the user cancelled the
+    /// request.
+    ///
+    /// ```rust,no_run
+    /// use ashpd::desktop::screenshot::Screenshot;
+    ///
+    /// async fn run() -> ashpd::Result<()> {
+    ///     let request = Screenshot::request().interactive(true).send().await?;
+    ///     if let Some(screenshot) = request.response_or_cancelled()? {
+    ///         println!("URI: {}", screenshot.uri());
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn response_or_cancelled(&self) -> Result<Option<T>, Error> {
+        match self.response() {
+            Ok(inner) => Ok(Some(inner)),
+            Err(Error::Response(ResponseError::Cancelled)) => Ok(None),
+            Err(err) => Err(err),
+        }
     }
 
     /// Closes the portal request to which this object refers and ends all
     /// related user interaction (dialogs, etc). A Response signal will not
     /// be emitted in this case.
     ///
+    /// This is how a pending request is cancelled, for every portal.
+    ///
     /// # Specifications
     ///
     /// See also [`Close`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.Request.html#org-freedesktop-portal-request-close).
@@ -267,11 +346,164 @@ where
         self.0.call("Close", &()).await
     }
 
-    pub(crate) fn path(&self) -> &ObjectPath<'_> {
+    /// The object path of the request.
+    pub fn path(&self) -> &ObjectPath<'_> {
         self.0.path()
     }
 }
 
+cfg_request! {
+    impl<T> Request<T>
+    where
+        T: for<'de> Deserialize<'de> + Type + Debug,
+    {
+        /// The request at `path`, made through `portal`.
+        pub(crate) async fn new<P>(portal: &Proxy<'_>, path: P) -> Result<Request<T>, Error>
+        where
+            P: TryInto<ObjectPath<'static>>,
+            P::Error: Into<zbus::Error>,
+        {
+            let proxy = portal
+                .new_sibling("org.freedesktop.portal.Request", path)
+                .await?;
+            // Start listening for a response signal the moment request is created
+            let stream = proxy.receive_signal("Response").await?;
+            // Used to notice the portal going away while waiting for the response,
+            // the peer of a peer-to-peer connection going away closes it instead.
+            let owner_changed = if proxy.connection().is_bus() {
+                let owner_changed = DBusProxy::builder(proxy.connection())
+                    .cache_properties(CacheProperties::No)
+                    .build()
+                    .await?
+                    .receive_name_owner_changed_with_args(&[(0, proxy.destination().as_str())])
+                    .await?;
+                Some(owner_changed)
+            } else {
+                None
+            };
+            Ok(Self(
+                proxy,
+                Default::default(),
+                PhantomData,
+                Some((stream, owner_changed)),
+            ))
+        }
+
+        pub(crate) async fn from_unique_name(
+            portal: &Proxy<'_>,
+            handle_token: &HandleToken,
+        ) -> Result<Request<T>, Error> {
+            let path = portal.request_path(handle_token);
+            #[cfg(feature = "tracing")]
+            tracing::info!("Creating a org.freedesktop.portal.Request {}", path);
+            Self::new(portal, path).await
+        }
+
+        /// Wait for the response, returning the `Response` signal.
+        ///
+        /// The response is stored for [`Request::response`], its error, if any,
+        /// is reported to the error observer as the one of `member` of
+        /// `interface`.
+        pub(crate) async fn prepare_response(
+            &mut self,
+            interface: &str,
+            member: &str,
+        ) -> Result<Message, Error> {
+            // The streams are dropped once the response is received, which removes
+            // their match rules right away instead of when the request is dropped.
+            let (mut stream, owner_changed) = self.3.take().ok_or(Error::NoResponse)?;
+            let closed = async move {
+                if let Some(mut owner_changed) = owner_changed {
+                    while let Some(signal) = owner_changed.next().await {
+                        if signal.args().is_ok_and(|args| args.new_owner().is_none()) {
+                            return;
+                        }
+                    }
+                }
+                future::pending::<()>().await
+            };
+            let message = match future::select(stream.next(), pin!(closed)).await {
+                Either::Left((message, _)) => message.ok_or(Error::NoResponse)?,
+                Either::Right(_) => return Err(Error::Closed),
+            };
+            #[cfg(feature = "tracing")]
+            tracing::info!("Received signal 'Response' on '{}'", self.0.interface());
+            let response = match message.body().deserialize::<Response<T>>()? {
+                Response::Err(e) => Err(e.into()),
+                Response::Ok(r) => Ok(r),
+            };
+            #[cfg(feature = "tracing")]
+            tracing::debug!("Received response {:#?}", response);
+            let r = response as Result<T, Error>;
+            if let Err(err) = &r {
+                crate::error::observe_error(interface, member, err);
+            }
+            *self.1.get_mut().unwrap() = Some(r);
+            Ok(message)
+        }
+
+        /// A guard closing the request if dropped before being disarmed, when
+        /// `close` is set.
+        pub(crate) fn close_on_drop(&self, close: bool) -> CloseOnDrop {
+            CloseOnDrop(close.then(|| self.0.clone()))
+        }
+
+        /// How the request ended, from the response received.
+        #[cfg(feature = "metrics")]
+        pub(crate) fn outcome(&self) -> crate::metrics::Outcome {
+            match self.1.lock().unwrap().as_ref() {
+                Some(response) => crate::metrics::Outcome::of(response),
+                None => crate::metrics::Outcome::Failed,
+            }
+        }
+
+        #[cfg(feature = "inhibit")]
+        pub(crate) fn connection(&self) -> &zbus::Connection {
+            self.0.connection()
+        }
+    }
+
+    /// Closes a request whose future is dropped before its response is received,
+    /// see [`set_close_on_drop`](crate::set_close_on_drop).
+    pub(crate) struct CloseOnDrop(Option<Proxy<'static>>);
+
+    impl CloseOnDrop {
+        /// The response was received, or the request failed, there is nothing to
+        /// close anymore.
+        pub(crate) fn disarm(mut self) {
+            self.0 = None;
+        }
+    }
+
+    impl Drop for CloseOnDrop {
+        fn drop(&mut self) {
+            let Some(proxy) = self.0.take() else {
+                return;
+            };
+            #[cfg(feature = "tokio")]
+            if tokio::runtime::Handle::try_current().is_err() {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(
+                    "Not closing the dropped request {}, dropped outside of a tokio runtime",
+                    proxy.path()
+                );
+                return;
+            }
+            #[cfg(feature = "tracing")]
+            tracing::info!("Closing the dropped request {}", proxy.path());
+            let executor = proxy.connection().executor().clone();
+            executor
+                .spawn(
+                    async move {
+                        let _ = proxy.call::<()>("Close", &()).await;
+                    },
+                    "close dropped request",
+                )
+                .detach();
+        }
+    }
+}
+
 impl<T> Debug for Request<T>
 where
     T: for<'de> Deserialize<'de> + Type + Debug,
@@ -285,11 +517,10 @@ where
 
 #[cfg(test)]
 mod tests {
-    use zbus::zvariant::Value;
-
     use super::*;
 
     #[test]
+    #[cfg(feature = "account")]
     fn response_signature() {
         use crate::desktop::account::UserInformation;
         assert_eq!(
@@ -302,4 +533,26 @@ mod tests {
         );
         assert_eq!(Response::<()>::signature(), "(ua{sv})");
     }
+
+    #[test]
+    fn response_combinators() {
+        let response = Response::Ok(1).map(|value| value + 1);
+        assert!(response.is_ok());
+        assert_eq!(response.into_result(), Ok(2));
+
+        let cancelled = Response::<u32>::Err(ResponseError::Cancelled);
+        assert!(cancelled.is_cancelled());
+        assert_eq!(cancelled.ok_or_cancelled(), Ok(None));
+        assert_eq!(Response::Ok(1).ok_or_cancelled(), Ok(Some(1)));
+        assert_eq!(
+            Response::<u32>::Err(ResponseError::Other).ok_or_cancelled(),
+            Err(ResponseError::Other)
+        );
+
+        let result: Result<u32, ResponseError> = Response::Err(ResponseError::Other).into();
+        assert_eq!(
+            Response::from(result).into_result(),
+            Err(ResponseError::Other)
+        );
+    }
 }
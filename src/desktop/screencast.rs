@@ -40,6 +40,12 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! The streams are PipeWire nodes. A sandboxed application reaches them
+//! through the remote opened by [`Screencast::open_pipe_wire_remote`], while
+//! an application already connected to PipeWire outside of a sandbox can
+//! use its own connection, see [`Screencast::pipe_wire_remote`] and
+//! [`is_pipe_wire_available`].
 
 use std::{collections::HashMap, fmt::Debug, os::fd::OwnedFd};
 
@@ -47,13 +53,15 @@ use enumflags2::{bitflags, BitFlags};
 use futures_util::TryFutureExt;
 use serde::Deserialize;
 use serde_repr::{Deserialize_repr, Serialize_repr};
-use zbus::zvariant::{self, DeserializeDict, SerializeDict, Type, Value};
+use zbus::zvariant::{self, OwnedValue, SerializeDict, Type};
 
 use super::{
-    remote_desktop::RemoteDesktop, session::SessionPortal, HandleToken, PersistMode, Request,
-    Session,
+    session::SessionPortal, HandleToken, PersistMode, Position, Region, Request, Session, Size,
+};
+use crate::{
+    desktop::session::CreateSessionResponse, helpers::SecretString, proxy::Proxy, Error,
+    WindowIdentifier,
 };
-use crate::{desktop::session::CreateSessionResponse, proxy::Proxy, Error, WindowIdentifier};
 
 #[bitflags]
 #[derive(Serialize_repr, Deserialize_repr, PartialEq, Eq, Copy, Clone, Debug, Type)]
@@ -72,6 +80,8 @@ pub enum SourceType {
     Virtual,
 }
 
+crate::helpers::impl_value_conversions!(SourceType as u32 { Monitor, Window, Virtual });
+
 #[bitflags]
 #[derive(Serialize_repr, Deserialize_repr, PartialEq, Eq, Debug, Copy, Clone, Type)]
 #[repr(u32)]
@@ -90,6 +100,8 @@ pub enum CursorMode {
     Metadata,
 }
 
+crate::helpers::impl_value_conversions!(CursorMode as u32 { Hidden, Embedded, Metadata });
+
 #[derive(SerializeDict, Type, Debug, Default)]
 /// Specified options for a [`Screencast::create_session`] request.
 #[zvariant(signature = "dict")]
@@ -103,7 +115,7 @@ struct CreateSessionOptions {
 #[derive(SerializeDict, Type, Debug, Default)]
 /// Specified options for a [`Screencast::select_sources`] request.
 #[zvariant(signature = "dict")]
-struct SelectSourcesOptions {
+pub(crate) struct SelectSourcesOptions {
     /// A string that will be used as the last element of the handle.
     handle_token: HandleToken,
     /// What types of content to record.
@@ -112,7 +124,7 @@ struct SelectSourcesOptions {
     multiple: Option<bool>,
     /// Determines how the cursor will be drawn in the screen cast stream.
     cursor_mode: Option<CursorMode>,
-    restore_token: Option<String>,
+    restore_token: Option<SecretString>,
     persist_mode: Option<PersistMode>,
 }
 
@@ -146,7 +158,7 @@ impl SelectSourcesOptions {
 
     #[must_use]
     pub fn restore_token<'a>(mut self, token: impl Into<Option<&'a str>>) -> Self {
-        self.restore_token = token.into().map(ToOwned::to_owned);
+        self.restore_token = token.into().map(SecretString::from);
         self
     }
 }
@@ -159,24 +171,130 @@ struct StartCastOptions {
     handle_token: HandleToken,
 }
 
-#[derive(DeserializeDict, Type)]
+#[derive(SerializeDict, Type, Debug, Default)]
+/// Specified options for a [`Screencast::open_pipe_wire_remote_with_options`]
+/// request.
+///
+/// The specification doesn't define any option yet.
+#[zvariant(signature = "dict")]
+pub struct OpenPipeWireRemoteOptions {}
+
+/// How to reach the PipeWire nodes of the streams of a session, as returned
+/// by [`Screencast::pipe_wire_remote`].
+#[derive(Debug)]
+pub enum PipeWireRemote {
+    /// A PipeWire remote opened by the portal, only giving access to the
+    /// streams of the session.
+    Fd(OwnedFd),
+    /// The PipeWire daemon of the user is directly reachable, the streams
+    /// can be found by their [node id][Stream::pipe_wire_node_id] over the
+    /// connection the application already has.
+    Default,
+}
+
+impl PipeWireRemote {
+    /// The file descriptor of the remote opened by the portal, if any.
+    pub fn into_fd(self) -> Option<OwnedFd> {
+        match self {
+            Self::Fd(fd) => Some(fd),
+            Self::Default => None,
+        }
+    }
+}
+
+/// Whether the socket of the PipeWire daemon of the user is reachable.
+///
+/// The socket is looked up like the PipeWire client library does, using the
+/// `PIPEWIRE_REMOTE`, `PIPEWIRE_RUNTIME_DIR` and `XDG_RUNTIME_DIR` environment
+/// variables. It is usually not visible from a sandbox, where the remote has
+/// to be opened through the portal.
+pub fn is_pipe_wire_available() -> bool {
+    use std::os::unix::fs::FileTypeExt;
+
+    let runtime_dir =
+        std::env::var_os("PIPEWIRE_RUNTIME_DIR").or_else(|| std::env::var_os("XDG_RUNTIME_DIR"));
+    pipe_wire_socket(std::env::var_os("PIPEWIRE_REMOTE"), runtime_dir)
+        .and_then(|path| std::fs::metadata(path).ok())
+        .is_some_and(|metadata| metadata.file_type().is_socket())
+}
+
+fn pipe_wire_socket(
+    remote: Option<std::ffi::OsString>,
+    runtime_dir: Option<std::ffi::OsString>,
+) -> Option<std::path::PathBuf> {
+    let remote = std::path::PathBuf::from(
+        remote
+            .filter(|remote| !remote.is_empty())
+            .unwrap_or_else(|| "pipewire-0".into()),
+    );
+    if remote.is_absolute() {
+        Some(remote)
+    } else {
+        let runtime_dir = runtime_dir.filter(|dir| !dir.is_empty())?;
+        Some(std::path::Path::new(&runtime_dir).join(remote))
+    }
+}
+
+#[derive(Type)]
 /// A response to a [`Screencast::start`] request.
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct Streams {
     streams: Vec<Stream>,
-    restore_token: Option<String>,
+    restore_token: Option<SecretString>,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(Streams {
+    streams [required] => "streams",
+    restore_token => "restore_token",
+});
+
 impl Streams {
     /// The session restore token.
     pub fn restore_token(&self) -> Option<&str> {
-        self.restore_token.as_deref()
+        self.restore_token.as_ref().map(SecretString::as_str)
     }
 
     /// The list of streams.
     pub fn streams(&self) -> &[Stream] {
         &self.streams
     }
+
+    /// An iterator over the streams.
+    pub fn iter(&self) -> std::slice::Iter<'_, Stream> {
+        self.streams.iter()
+    }
+
+    /// The stream of the monitor named `output_name`, see
+    /// [`Stream::output_name`].
+    pub fn find_output(&self, output_name: &str) -> Option<&Stream> {
+        self.iter()
+            .find(|stream| stream.output_name() == Some(output_name))
+    }
+
+    /// The entries of the response unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
+}
+
+impl<'a> IntoIterator for &'a Streams {
+    type Item = &'a Stream;
+    type IntoIter = std::slice::Iter<'a, Stream>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for Streams {
+    type Item = Stream;
+    type IntoIter = std::vec::IntoIter<Stream>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.streams.into_iter()
+    }
 }
 
 impl Debug for Streams {
@@ -198,23 +316,29 @@ impl Stream {
         self.0
     }
 
-    /// A tuple consisting of the position (x, y) in the compositor coordinate
-    /// space.
+    /// The position of the stream in the compositor coordinate space.
     ///
     /// **Note** the position may not be equivalent to a position in a pixel
     /// coordinate space. Only available for monitor streams.
-    pub fn position(&self) -> Option<(i32, i32)> {
-        self.1.position
+    pub fn position(&self) -> Option<Position> {
+        self.1.position.map(Position::from)
     }
 
-    /// A tuple consisting of (width, height).
-    /// The size represents the size of the stream as it is displayed in the
-    /// compositor coordinate space.
+    /// The size of the stream as it is displayed in the compositor coordinate
+    /// space.
     ///
     /// **Note** the size may not be equivalent to a size in a pixel coordinate
     /// space. The size may differ from the size of the stream.
-    pub fn size(&self) -> Option<(i32, i32)> {
-        self.1.size
+    pub fn size(&self) -> Option<Size> {
+        let (width, height) = self.1.size?;
+        Some(Size::new(width.try_into().ok()?, height.try_into().ok()?))
+    }
+
+    /// The area the stream covers in the compositor coordinate space, see
+    /// [`Stream::position`] and [`Stream::size`]. Only available for monitor
+    /// streams.
+    pub fn region(&self) -> Option<Region> {
+        Some(Region::new(self.position()?, self.size()?))
     }
 
     /// The source type of the stream.
@@ -232,6 +356,21 @@ impl Stream {
     pub fn mapping_id(&self) -> Option<&str> {
         self.1.mapping_id.as_deref()
     }
+
+    /// The name of the output of a monitor stream, e.g. `DP-1`, to label the
+    /// stream without resorting to the Wayland protocols.
+    ///
+    /// It is not part of the specification, and is only available when the
+    /// portal backend adds it to the stream properties as `output_name`.
+    pub fn output_name(&self) -> Option<&str> {
+        let value = self.1.extra.get("output_name")?;
+        <&str>::try_from(value).ok().filter(|name| !name.is_empty())
+    }
+
+    /// The properties of the stream unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.1.extra
+    }
 }
 
 impl Debug for Stream {
@@ -242,10 +381,12 @@ impl Debug for Stream {
             .field("size", &self.size())
             .field("source_type", &self.source_type())
             .field("id", &self.id())
+            .field("mapping_id", &self.mapping_id())
+            .field("output_name", &self.output_name())
             .finish()
     }
 }
-#[derive(Clone, DeserializeDict, Type, Debug)]
+#[derive(Type, Debug)]
 /// The stream properties.
 #[zvariant(signature = "dict")]
 struct StreamProperties {
@@ -254,8 +395,36 @@ struct StreamProperties {
     size: Option<(i32, i32)>,
     source_type: Option<SourceType>,
     mapping_id: Option<String>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+impl Clone for StreamProperties {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id.clone(),
+            position: self.position,
+            size: self.size,
+            source_type: self.source_type,
+            mapping_id: self.mapping_id.clone(),
+            // Only the file descriptors can't be cloned, which the stream
+            // properties don't hold.
+            extra: self
+                .extra
+                .iter()
+                .filter_map(|(key, value)| Some((key.clone(), value.try_clone().ok()?)))
+                .collect(),
+        }
+    }
 }
 
+crate::helpers::impl_dict_with_extra!(StreamProperties {
+    id => "id",
+    position => "position",
+    size => "size",
+    source_type => "source_type",
+    mapping_id => "mapping_id",
+});
+
 /// The interface lets sandboxed applications create screen cast sessions.
 ///
 /// Wrapper of the DBus interface: [`org.freedesktop.portal.ScreenCast`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.ScreenCast.html).
@@ -270,6 +439,20 @@ impl<'a> Screencast<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`Screencast`] using `connection` instead of
+    /// the session bus connection, e.g. a peer-to-peer connection to the
+    /// portal.
+    pub async fn new_with_connection(
+        connection: zbus::Connection,
+    ) -> Result<Screencast<'a>, Error> {
+        let proxy = Proxy::new_desktop_with_connection(
+            Some(connection),
+            "org.freedesktop.portal.ScreenCast",
+        )
+        .await?;
+        Ok(Self(proxy))
+    }
+
     /// Create a screen cast session.
     ///
     /// # Specifications
@@ -278,14 +461,18 @@ impl<'a> Screencast<'a> {
     #[doc(alias = "CreateSession")]
     #[doc(alias = "xdp_portal_create_screencast_session")]
     pub async fn create_session(&self) -> Result<Session<'a, Self>, Error> {
-        let options = CreateSessionOptions::default();
+        let session_handle_token = HandleToken::default();
+        let options = CreateSessionOptions {
+            handle_token: session_handle_token.derive(0),
+            session_handle_token,
+        };
         let (request, proxy) = futures_util::try_join!(
             self.0
                 .request::<CreateSessionResponse>(&options.handle_token, "CreateSession", &options)
                 .into_future(),
-            Session::from_unique_name(&options.session_handle_token).into_future(),
+            Session::from_unique_name(&self.0, &options.session_handle_token).into_future(),
         )?;
-        assert_eq!(proxy.path(), &request.response()?.session_handle.as_ref());
+        proxy.check_created(&request.response()?.session_handle)?;
         Ok(proxy)
     }
 
@@ -309,9 +496,22 @@ impl<'a> Screencast<'a> {
         &self,
         session: &Session<'_, impl HasScreencastSession>,
     ) -> Result<OwnedFd, Error> {
-        // `options` parameter doesn't seems to be used yet
-        // see https://github.com/flatpak/xdg-desktop-portal/blob/master/src/screen-cast.c#L812
-        let options: HashMap<&str, Value<'_>> = HashMap::new();
+        self.open_pipe_wire_remote_with_options(session, OpenPipeWireRemoteOptions::default())
+            .await
+    }
+
+    /// Like [`open_pipe_wire_remote()`][`Screencast::open_pipe_wire_remote`],
+    /// with the given `options`.
+    ///
+    /// # Specifications
+    ///
+    /// See also [`OpenPipeWireRemote`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.ScreenCast.html#org-freedesktop-portal-screencast-openpipewireremote).
+    #[doc(alias = "OpenPipeWireRemote")]
+    pub async fn open_pipe_wire_remote_with_options(
+        &self,
+        session: &Session<'_, impl HasScreencastSession>,
+        options: OpenPipeWireRemoteOptions,
+    ) -> Result<OwnedFd, Error> {
         let fd = self
             .0
             .call::<zvariant::OwnedFd>("OpenPipeWireRemote", &(session, options))
@@ -319,6 +519,32 @@ impl<'a> Screencast<'a> {
         Ok(fd.into())
     }
 
+    /// Find out how to reach the streams of a started session.
+    ///
+    /// Applications embedding their own media stack and running outside of a
+    /// sandbox can use their existing PipeWire connection and the node ids of
+    /// the [`Stream`]s, in which case the remote isn't opened. Otherwise,
+    /// this is the same as
+    /// [`open_pipe_wire_remote_with_options()`][`Screencast::open_pipe_wire_remote_with_options`].
+    ///
+    /// # Arguments
+    ///
+    /// * `session` - A [`Session`], created with
+    ///   [`create_session()`][`Screencast::create_session`].
+    /// * `options` - The options of the remote, if it is opened.
+    pub async fn pipe_wire_remote(
+        &self,
+        session: &Session<'_, impl HasScreencastSession>,
+        options: OpenPipeWireRemoteOptions,
+    ) -> Result<PipeWireRemote, Error> {
+        if !crate::is_sandboxed().await && is_pipe_wire_available() {
+            return Ok(PipeWireRemote::Default);
+        }
+        self.open_pipe_wire_remote_with_options(session, options)
+            .await
+            .map(PipeWireRemote::Fd)
+    }
+
     /// Configure what the screen cast session should record.
     /// This method must be called before starting the session.
     ///
@@ -354,6 +580,31 @@ impl<'a> Screencast<'a> {
             .types(types)
             .persist_mode(persist_mode)
             .restore_token(restore_token);
+        self.select_sources_with_options(session, options).await
+    }
+
+    pub(crate) async fn select_sources_with_options(
+        &self,
+        session: &Session<'_, impl HasScreencastSession>,
+        options: SelectSourcesOptions,
+    ) -> Result<Request<()>, Error> {
+        let options = SelectSourcesOptions {
+            handle_token: session.next_handle_token(),
+            ..options
+        };
+        let method = "SelectSources";
+        self.0
+            .check_option(method, "cursor_mode", 2, options.cursor_mode.is_some())?;
+        self.0
+            .check_option(method, "restore_token", 4, options.restore_token.is_some())?;
+        self.0.check_option(
+            method,
+            "persist_mode",
+            4,
+            options
+                .persist_mode
+                .is_some_and(|mode| mode != PersistMode::DoNot),
+        )?;
         self.0
             .empty_request(&options.handle_token, "SelectSources", &(session, &options))
             .await
@@ -385,7 +636,9 @@ impl<'a> Screencast<'a> {
         session: &Session<'_, impl HasScreencastSession>,
         identifier: &WindowIdentifier,
     ) -> Result<Request<Streams>, Error> {
-        let options = StartCastOptions::default();
+        let options = StartCastOptions {
+            handle_token: session.next_handle_token(),
+        };
         self.0
             .request(
                 &options.handle_token,
@@ -429,4 +682,266 @@ impl SessionPortal for Screencast<'_> {}
 /// Defines which portals session can be used in a screen-cast.
 pub trait HasScreencastSession: SessionPortal {}
 impl HasScreencastSession for Screencast<'_> {}
-impl HasScreencastSession for RemoteDesktop<'_> {}
+#[cfg(feature = "remote_desktop")]
+impl HasScreencastSession for super::remote_desktop::RemoteDesktop<'_> {}
+
+/// The size of `struct spa_meta_cursor`.
+#[cfg(feature = "pipewire")]
+const SPA_META_CURSOR_SIZE: usize = 28;
+/// The size of `struct spa_meta_bitmap`.
+#[cfg(feature = "pipewire")]
+const SPA_META_BITMAP_SIZE: usize = 20;
+
+/// The size of the cursor metadata to request from the PipeWire stream, with
+/// the `SPA_PARAM_Meta` param, for cursor bitmaps of up to `width` by
+/// `height` pixels in a 4 bytes per pixel format.
+#[cfg(feature = "pipewire")]
+#[cfg_attr(docsrs, doc(cfg(feature = "pipewire")))]
+pub const fn cursor_meta_size(width: usize, height: usize) -> usize {
+    SPA_META_CURSOR_SIZE + SPA_META_BITMAP_SIZE + width * height * 4
+}
+
+/// The cursor of a stream, sent as metadata of the PipeWire buffers when the
+/// sources were selected with [`CursorMode::Metadata`].
+#[cfg(feature = "pipewire")]
+#[cfg_attr(docsrs, doc(cfg(feature = "pipewire")))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorMetadata {
+    id: u32,
+    position: Position,
+    hotspot: Position,
+    bitmap: Option<CursorBitmap>,
+}
+
+#[cfg(feature = "pipewire")]
+impl CursorMetadata {
+    /// Decode the cursor metadata of a buffer.
+    ///
+    /// Returns `None` if the buffer has no valid cursor metadata, e.g. when
+    /// the cursor is not over the stream.
+    ///
+    /// # Safety
+    ///
+    /// `buffer` must be null or a buffer dequeued from a stream, typically
+    /// with `StreamRef::dequeue_raw_buffer`, and not queued back yet.
+    pub unsafe fn from_raw_buffer(buffer: *const pipewire::sys::pw_buffer) -> Option<Self> {
+        let buffer = buffer.as_ref()?.buffer.as_ref()?;
+        if buffer.metas.is_null() {
+            return None;
+        }
+        let metas = std::slice::from_raw_parts(buffer.metas, buffer.n_metas as usize);
+        let meta = metas
+            .iter()
+            .find(|meta| meta.type_ == pipewire::spa::sys::SPA_META_Cursor)?;
+        if meta.data.is_null() {
+            return None;
+        }
+        let data = std::slice::from_raw_parts(meta.data as *const u8, meta.size as usize);
+        Self::from_bytes(data)
+    }
+
+    /// Decode the cursor metadata from the bytes of a `spa_meta_cursor`,
+    /// followed by its bitmap if any.
+    ///
+    /// Returns `None` if the metadata is invalid or truncated.
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        let id = read_u32(data, 0)?;
+        if id == 0 {
+            return None;
+        }
+        let bitmap_offset = read_u32(data, 24)? as usize;
+        let bitmap = if bitmap_offset >= SPA_META_CURSOR_SIZE {
+            CursorBitmap::from_bytes(data.get(bitmap_offset..)?)
+        } else {
+            None
+        };
+        Some(Self {
+            id,
+            position: Position::new(read_i32(data, 8)?, read_i32(data, 12)?),
+            hotspot: Position::new(read_i32(data, 16)?, read_i32(data, 20)?),
+            bitmap,
+        })
+    }
+
+    /// The id of the cursor, which changes along with its bitmap.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// The position of the cursor in the stream, in pixels.
+    pub fn position(&self) -> Position {
+        self.position
+    }
+
+    /// The hotspot of the cursor, relative to its bitmap.
+    pub fn hotspot(&self) -> Position {
+        self.hotspot
+    }
+
+    /// The bitmap of the cursor, only sent when it changed.
+    pub fn bitmap(&self) -> Option<&CursorBitmap> {
+        self.bitmap.as_ref()
+    }
+}
+
+/// The image of a cursor, see [`CursorMetadata::bitmap`].
+#[cfg(feature = "pipewire")]
+#[cfg_attr(docsrs, doc(cfg(feature = "pipewire")))]
+#[derive(Clone, PartialEq, Eq)]
+pub struct CursorBitmap {
+    format: u32,
+    size: Size,
+    stride: u32,
+    pixels: Vec<u8>,
+}
+
+#[cfg(feature = "pipewire")]
+impl CursorBitmap {
+    fn from_bytes(data: &[u8]) -> Option<Self> {
+        let format = read_u32(data, 0)?;
+        if format == pipewire::spa::sys::SPA_VIDEO_FORMAT_UNKNOWN {
+            return None;
+        }
+        let size = Size::new(read_u32(data, 4)?, read_u32(data, 8)?);
+        let stride = u32::try_from(read_i32(data, 12)?).ok()?;
+        let offset = read_u32(data, 16)? as usize;
+        let len = (stride as usize).checked_mul(size.height() as usize)?;
+        let pixels = data.get(offset..offset.checked_add(len)?)?.to_vec();
+        Some(Self {
+            format,
+            size,
+            stride,
+            pixels,
+        })
+    }
+
+    /// The format of the pixels.
+    pub fn format(&self) -> pipewire::spa::param::video::VideoFormat {
+        pipewire::spa::param::video::VideoFormat::from_raw(self.format)
+    }
+
+    /// The width and height of the bitmap, in pixels.
+    pub fn size(&self) -> Size {
+        self.size
+    }
+
+    /// The number of bytes per row of pixels.
+    pub fn stride(&self) -> u32 {
+        self.stride
+    }
+
+    /// The pixels, row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+}
+
+#[cfg(feature = "pipewire")]
+impl Debug for CursorBitmap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CursorBitmap")
+            .field("format", &self.format())
+            .field("size", &self.size)
+            .field("stride", &self.stride)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "pipewire")]
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    let bytes = data.get(offset..offset + 4)?;
+    Some(u32::from_ne_bytes(bytes.try_into().ok()?))
+}
+
+#[cfg(feature = "pipewire")]
+fn read_i32(data: &[u8], offset: usize) -> Option<i32> {
+    read_u32(data, offset).map(|value| value as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn pipe_wire_socket_lookup() {
+        assert_eq!(
+            pipe_wire_socket(None, Some("/run/user/1000".into())).as_deref(),
+            Some(Path::new("/run/user/1000/pipewire-0"))
+        );
+        assert_eq!(
+            pipe_wire_socket(Some("pipewire-1".into()), Some("/run/user/1000".into())).as_deref(),
+            Some(Path::new("/run/user/1000/pipewire-1"))
+        );
+        assert_eq!(
+            pipe_wire_socket(Some("/tmp/pipewire".into()), None).as_deref(),
+            Some(Path::new("/tmp/pipewire"))
+        );
+        assert_eq!(pipe_wire_socket(None, None), None);
+    }
+
+    #[test]
+    fn streams() {
+        use zbus::zvariant::{serialized::Context, to_bytes, Value, LE};
+
+        let monitor = HashMap::from([
+            ("position", Value::from((0i32, 0i32))),
+            ("size", Value::from((1920i32, 1080i32))),
+            ("source_type", Value::from(1u32)),
+            ("output_name", Value::from("DP-1")),
+        ]);
+        let window = HashMap::from([("source_type", Value::from(2u32))]);
+        let results = HashMap::from([(
+            "streams",
+            Value::from(vec![(42u32, monitor), (43u32, window)]),
+        )]);
+        let data = to_bytes(Context::new_dbus(LE, 0), &results).unwrap();
+        let (streams, _) = data.deserialize::<Streams>().unwrap();
+
+        let monitor = streams.find_output("DP-1").unwrap();
+        assert_eq!(monitor.pipe_wire_node_id(), 42);
+        assert_eq!(monitor.source_type(), Some(SourceType::Monitor));
+        assert_eq!(monitor.size(), Some(Size::new(1920, 1080)));
+        assert!(monitor.extra().contains_key("output_name"));
+        assert!(streams.find_output("HDMI-1").is_none());
+
+        let windows = streams
+            .iter()
+            .filter(|stream| stream.source_type() == Some(SourceType::Window))
+            .map(Stream::pipe_wire_node_id)
+            .collect::<Vec<_>>();
+        assert_eq!(windows, [43]);
+        assert_eq!(streams.into_iter().count(), 2);
+    }
+
+    #[cfg(feature = "pipewire")]
+    #[test]
+    fn cursor_metadata() {
+        let mut data = Vec::new();
+        for value in [7u32, 0, 10, 20, 1, 2, SPA_META_CURSOR_SIZE as u32] {
+            data.extend(value.to_ne_bytes());
+        }
+        let format = pipewire::spa::param::video::VideoFormat::BGRA.as_raw();
+        for value in [format, 2, 2, 8, SPA_META_BITMAP_SIZE as u32] {
+            data.extend(value.to_ne_bytes());
+        }
+        data.extend([0xff; 16]);
+
+        let cursor = CursorMetadata::from_bytes(&data).unwrap();
+        assert_eq!(cursor.id(), 7);
+        assert_eq!(cursor.position(), Position::new(10, 20));
+        assert_eq!(cursor.hotspot(), Position::new(1, 2));
+        let bitmap = cursor.bitmap().unwrap();
+        assert_eq!(bitmap.size(), Size::new(2, 2));
+        assert_eq!(bitmap.pixels().len(), 16);
+
+        // Truncated bitmap.
+        let cursor = CursorMetadata::from_bytes(&data[..data.len() - 1]).unwrap();
+        assert!(cursor.bitmap().is_none());
+        // Invalid cursor.
+        data[..4].copy_from_slice(&0u32.to_ne_bytes());
+        assert!(CursorMetadata::from_bytes(&data).is_none());
+        assert!(CursorMetadata::from_bytes(&[]).is_none());
+    }
+}
@@ -21,6 +21,20 @@
 //! }
 //! ```
 //!
+//! ## Saving a screenshot to a given location
+//!
+//! ```rust,no_run
+//! use ashpd::desktop::screenshot::Screenshot;
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     Screenshot::request()
+//!         .interactive(false)
+//!         .save_to("/tmp/screenshot.png")
+//!         .await?;
+//!     Ok(())
+//! }
+//! ```
+//!
 //! ## Picking a color
 //!
 //! ```rust,no_run
@@ -33,34 +47,56 @@
 //!     Ok(())
 //! }
 //! ```
-use std::fmt::Debug;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    fs::File,
+    os::fd::BorrowedFd,
+    path::{Path, PathBuf},
+};
 
-use zbus::zvariant::{DeserializeDict, SerializeDict, Type};
+use zbus::zvariant::{OwnedValue, Type};
 
 use super::{HandleToken, Request};
-use crate::{desktop::Color, proxy::Proxy, Error, WindowIdentifier};
+use crate::{desktop::Color, proxy::Proxy, Error, FileUri, WindowIdentifier};
 
-#[derive(SerializeDict, Type, Debug, Default)]
+#[derive(Type, Debug, Default)]
 #[zvariant(signature = "dict")]
 struct ScreenshotOptions {
     handle_token: HandleToken,
     modal: Option<bool>,
     interactive: Option<bool>,
+    extra: HashMap<String, OwnedValue>,
 }
 
-#[derive(SerializeDict, DeserializeDict, Type)]
+crate::helpers::impl_dict_with_extra!(Serialize for ScreenshotOptions {
+    handle_token [required] => "handle_token",
+    modal => "modal",
+    interactive => "interactive",
+});
+
+#[derive(Type)]
 #[zvariant(signature = "dict")]
 /// The response of a [`ScreenshotRequest`] request.
+#[non_exhaustive]
 pub struct Screenshot {
     uri: url::Url,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(Screenshot with Serialize {
+    uri [required] => "uri",
+});
+
 impl Screenshot {
     #[cfg(feature = "backend")]
     #[cfg_attr(docsrs, doc(cfg(feature = "backend")))]
     /// Create a new instance of the screenshot.
     pub fn new(uri: url::Url) -> Self {
-        Self { uri }
+        Self {
+            uri,
+            extra: HashMap::new(),
+        }
     }
 
     /// Creates a new builder-pattern struct instance to construct
@@ -75,6 +111,16 @@ impl Screenshot {
     pub fn uri(&self) -> &url::Url {
         &self.uri
     }
+
+    /// The screenshot file, if the URI is a local `file://` one.
+    pub fn file(&self) -> Option<FileUri> {
+        FileUri::try_from(&self.uri).ok()
+    }
+
+    /// The entries of the response unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
 impl Debug for Screenshot {
@@ -83,20 +129,28 @@ impl Debug for Screenshot {
     }
 }
 
-#[derive(SerializeDict, Type, Debug, Default)]
+#[derive(Type, Debug, Default)]
 #[zvariant(signature = "dict")]
 struct ColorOptions {
     handle_token: HandleToken,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(Serialize for ColorOptions {
+    handle_token [required] => "handle_token",
+});
+
 #[derive(Debug)]
 #[doc(alias = "org.freedesktop.portal.Screenshot")]
 struct ScreenshotProxy<'a>(Proxy<'a>);
 
 impl<'a> ScreenshotProxy<'a> {
-    /// Create a new instance of [`ScreenshotProxy`].
-    pub async fn new() -> Result<ScreenshotProxy<'a>, Error> {
-        let proxy = Proxy::new_desktop("org.freedesktop.portal.Screenshot").await?;
+    /// Create a new instance of [`ScreenshotProxy`], on `connection` or the
+    /// session bus connection.
+    pub async fn new(connection: Option<zbus::Connection>) -> Result<ScreenshotProxy<'a>, Error> {
+        let proxy =
+            Proxy::new_desktop_with_connection(connection, "org.freedesktop.portal.Screenshot")
+                .await?;
         Ok(Self(proxy))
     }
 
@@ -106,6 +160,11 @@ impl<'a> ScreenshotProxy<'a> {
     ///
     /// * `identifier` - Identifier for the application window.
     ///
+    /// # Required version
+    ///
+    /// The method requires the 2nd version implementation of the portal and
+    /// would fail with [`Error::RequiresVersion`] otherwise.
+    ///
     /// # Specifications
     ///
     /// See also [`PickColor`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.Screenshot.html#org-freedesktop-portal-screenshot-pickcolor).
@@ -116,6 +175,10 @@ impl<'a> ScreenshotProxy<'a> {
         identifier: &WindowIdentifier,
         options: ColorOptions,
     ) -> Result<Request<Color>, Error> {
+        let version = self.0.version();
+        if version < 2 {
+            return Err(Error::RequiresVersion(2, version));
+        }
         self.0
             .request(&options.handle_token, "PickColor", &(&identifier, &options))
             .await
@@ -144,6 +207,12 @@ impl<'a> ScreenshotProxy<'a> {
         identifier: &WindowIdentifier,
         options: ScreenshotOptions,
     ) -> Result<Request<Screenshot>, Error> {
+        self.0.check_option(
+            "Screenshot",
+            "interactive",
+            2,
+            options.interactive.is_some(),
+        )?;
         self.0
             .request(
                 &options.handle_token,
@@ -170,9 +239,30 @@ impl<'a> std::ops::Deref for ScreenshotProxy<'a> {
 pub struct ColorRequest {
     identifier: WindowIdentifier,
     options: ColorOptions,
+    #[cfg(feature = "gtk4")]
+    allow_fallback: bool,
+    connection: Option<zbus::Connection>,
+    close_on_drop: Option<bool>,
 }
 
 impl ColorRequest {
+    /// Sets the connection to send the request on, instead of the session
+    /// bus connection.
+    #[must_use]
+    pub fn connection(mut self, connection: impl Into<Option<zbus::Connection>>) -> Self {
+        self.connection = connection.into();
+        self
+    }
+
+    /// Whether to close the request, dismissing its dialog, if the future
+    /// sending it is dropped before the response is received. Overrides
+    /// [`set_close_on_drop`](crate::set_close_on_drop) for this request.
+    #[must_use]
+    pub fn close_on_drop(mut self, close: bool) -> Self {
+        self.close_on_drop = Some(close);
+        self
+    }
+
     #[must_use]
     /// Sets a window identifier.
     pub fn identifier(mut self, identifier: WindowIdentifier) -> Self {
@@ -180,11 +270,70 @@ impl ColorRequest {
         self
     }
 
+    /// Whether to fall back to a GTK color chooser dialog when the portal
+    /// doesn't support picking a color. Defaults to `false`.
+    ///
+    /// Only used by [`ColorRequest::color`], the dialog must be shown from the
+    /// thread running the GTK main loop.
+    #[cfg(feature = "gtk4")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "gtk4")))]
+    #[must_use]
+    pub fn allow_fallback(mut self, allow_fallback: bool) -> Self {
+        self.allow_fallback = allow_fallback;
+        self
+    }
+
+    /// Sets the option `key`, unknown to this version of the crate, e.g. a
+    /// vendor extension like `x-gnome-...` to experiment with the features of
+    /// a backend. The options known to the crate can't be overridden.
+    #[must_use]
+    pub fn extra<'v>(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<zbus::zvariant::Value<'v>>,
+    ) -> Self {
+        crate::helpers::insert_extra(&mut self.options.extra, key, value);
+        self
+    }
+
     /// Build the [`Color`].
     pub async fn send(self) -> Result<Request<Color>, Error> {
-        let proxy = ScreenshotProxy::new().await?;
+        let mut proxy = ScreenshotProxy::new(self.connection).await?;
+        proxy.0.close_requests_on_drop(self.close_on_drop);
         proxy.pick_color(&self.identifier, self.options).await
     }
+
+    /// Send the request and wait for the picked [`Color`].
+    ///
+    /// Fails with [`Error::RequiresVersion`] if the portal doesn't support
+    /// picking a color, unless a fallback is allowed with
+    /// `ColorRequest::allow_fallback`.
+    pub async fn color(self) -> Result<Color, Error> {
+        #[cfg(feature = "gtk4")]
+        let allow_fallback = self.allow_fallback;
+        match self.send().await {
+            Ok(request) => request.response(),
+            #[cfg(feature = "gtk4")]
+            Err(Error::RequiresVersion(_, _)) if allow_fallback => pick_color_fallback().await,
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[cfg(feature = "gtk4")]
+async fn pick_color_fallback() -> Result<Color, Error> {
+    use gtk4::prelude::*;
+
+    let dialog = gtk4::ColorChooserDialog::new(None, None::<&gtk4::Window>);
+    dialog.set_use_alpha(false);
+    dialog.set_modal(true);
+    let response = dialog.run_future().await;
+    let rgba = dialog.rgba();
+    dialog.destroy();
+    match response {
+        gtk4::ResponseType::Ok => Ok(Color::from(rgba)),
+        _ => Err(Error::Response(super::ResponseError::Cancelled)),
+    }
 }
 
 impl Color {
@@ -205,9 +354,28 @@ impl Color {
 pub struct ScreenshotRequest {
     options: ScreenshotOptions,
     identifier: WindowIdentifier,
+    connection: Option<zbus::Connection>,
+    close_on_drop: Option<bool>,
 }
 
 impl ScreenshotRequest {
+    /// Sets the connection to send the request on, instead of the session
+    /// bus connection.
+    #[must_use]
+    pub fn connection(mut self, connection: impl Into<Option<zbus::Connection>>) -> Self {
+        self.connection = connection.into();
+        self
+    }
+
+    /// Whether to close the request, dismissing its dialog, if the future
+    /// sending it is dropped before the response is received. Overrides
+    /// [`set_close_on_drop`](crate::set_close_on_drop) for this request.
+    #[must_use]
+    pub fn close_on_drop(mut self, close: bool) -> Self {
+        self.close_on_drop = Some(close);
+        self
+    }
+
     #[must_use]
     /// Sets a window identifier.
     pub fn identifier(mut self, identifier: impl Into<Option<WindowIdentifier>>) -> Self {
@@ -230,9 +398,76 @@ impl ScreenshotRequest {
         self
     }
 
+    /// Sets the option `key`, unknown to this version of the crate, e.g. a
+    /// vendor extension like `x-gnome-...` to experiment with the features of
+    /// a backend. The options known to the crate can't be overridden.
+    #[must_use]
+    pub fn extra<'v>(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<zbus::zvariant::Value<'v>>,
+    ) -> Self {
+        crate::helpers::insert_extra(&mut self.options.extra, key, value);
+        self
+    }
+
     /// Build the [`Screenshot`].
     pub async fn send(self) -> Result<Request<Screenshot>, Error> {
-        let proxy = ScreenshotProxy::new().await?;
+        let mut proxy = ScreenshotProxy::new(self.connection).await?;
+        proxy.0.close_requests_on_drop(self.close_on_drop);
         proxy.screenshot(&self.identifier, self.options).await
     }
+
+    /// Take the screenshot and copy it to `path`.
+    ///
+    /// The portal has no way to write the screenshot to a location chosen by
+    /// the application, backends save it to a file, usually in the user's
+    /// pictures directory. That file is copied to `path` and left in place,
+    /// it belongs to the user.
+    pub async fn save_to(self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let source = self.take().await?;
+        crate::helpers::copy_file(&source, path).await?;
+        Ok(())
+    }
+
+    /// Take the screenshot and write it to `fd`, which must be open for
+    /// writing.
+    ///
+    /// The file saved by the backend is left in place, see
+    /// [`ScreenshotRequest::save_to`].
+    pub async fn write_to(self, fd: &BorrowedFd<'_>) -> Result<(), Error> {
+        let source = self.take().await?;
+        crate::helpers::copy_into(&source, File::from(fd.try_clone_to_owned()?)).await?;
+        Ok(())
+    }
+
+    async fn take(self) -> Result<PathBuf, Error> {
+        let screenshot = self.send().await?.response()?;
+        screenshot
+            .file()
+            .map(PathBuf::from)
+            .ok_or(Error::ParseError(
+                "Failed to parse the screenshot URI, not a file URI",
+            ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use zbus::zvariant::{serialized::Context, to_bytes, LE};
+
+    use super::*;
+
+    #[test]
+    fn extra_options() {
+        let request = Screenshot::request()
+            .interactive(true)
+            .extra("x-gnome-delay", 5u32)
+            .extra("interactive", false);
+        let encoded = to_bytes(Context::new_dbus(LE, 0), &request.options).unwrap();
+        let options: HashMap<String, OwnedValue> = encoded.deserialize().unwrap().0;
+        assert_eq!(options.len(), 3);
+        assert_eq!(options["x-gnome-delay"], OwnedValue::from(5u32));
+        assert_eq!(options["interactive"], OwnedValue::from(true));
+    }
 }
@@ -29,7 +29,7 @@ use tokio::{io::AsyncReadExt, net::UnixStream};
 use zbus::zvariant::{Fd, SerializeDict, Type};
 
 use super::{HandleToken, Request};
-use crate::{proxy::Proxy, Error};
+use crate::{helpers::SecretString, proxy::Proxy, Error};
 
 #[derive(SerializeDict, Type, Debug, Default)]
 /// Specified options for a [`Secret::retrieve`] request.
@@ -38,7 +38,7 @@ struct RetrieveOptions {
     handle_token: HandleToken,
     /// A string returned by a previous call to `retrieve`.
     /// TODO: seems to not be used by the portal...
-    token: Option<String>,
+    token: Option<SecretString>,
 }
 
 /// The interface lets sandboxed applications retrieve a per-application secret.
@@ -58,6 +58,15 @@ impl<'a> Secret<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`Secret`] using `connection` instead of the
+    /// session bus connection, e.g. a peer-to-peer connection to the portal.
+    pub async fn new_with_connection(connection: zbus::Connection) -> Result<Secret<'a>, Error> {
+        let proxy =
+            Proxy::new_desktop_with_connection(Some(connection), "org.freedesktop.portal.Secret")
+                .await?;
+        Ok(Self(proxy))
+    }
+
     /// Retrieves a master secret for a sandboxed application.
     ///
     /// # Arguments
@@ -88,17 +97,68 @@ impl<'a> std::ops::Deref for Secret<'a> {
     }
 }
 
+/// The secret of an application, retrieved with [`retrieve`].
+///
+/// It is left out of the `Debug` output and zeroed when dropped. With the
+/// `secrecy` feature, it can be converted to a [`secrecy::SecretVec`] without
+/// copying it.
+pub struct MasterSecret(Vec<u8>);
+
+impl MasterSecret {
+    /// The bytes of the secret.
+    pub fn expose_secret(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl std::fmt::Debug for MasterSecret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("MasterSecret(<redacted>)")
+    }
+}
+
+impl Drop for MasterSecret {
+    fn drop(&mut self) {
+        crate::helpers::zeroize(&mut self.0);
+    }
+}
+
+#[cfg(feature = "secrecy")]
+impl From<MasterSecret> for secrecy::SecretVec<u8> {
+    fn from(mut secret: MasterSecret) -> Self {
+        secrecy::SecretVec::new(std::mem::take(&mut secret.0))
+    }
+}
+
+/// The size of the secrets sent by the portal backends, so that the buffer
+/// they are read into doesn't have to grow.
+const SECRET_CAPACITY: usize = 64;
+
 /// A handy wrapper around [`Secret::retrieve`].
 ///
 /// It crates a UnixStream internally for receiving the secret.
-pub async fn retrieve() -> Result<Vec<u8>, Error> {
+pub async fn retrieve() -> Result<MasterSecret, Error> {
     let proxy = Secret::new().await?;
 
     let (mut x1, x2) = UnixStream::pair()?;
     proxy.retrieve(&x2.as_fd()).await?;
     drop(x2);
-    let mut buf = Vec::new();
-    x1.read_to_end(&mut buf).await?;
+    // The buffer is grown by hand, zeroing the previous one, as reallocating
+    // would leave copies of the secret behind.
+    let mut secret = MasterSecret(vec![0; SECRET_CAPACITY]);
+    let mut len = 0;
+    loop {
+        if len == secret.0.len() {
+            let mut grown = vec![0; secret.0.len() * 2];
+            grown[..len].copy_from_slice(&secret.0);
+            secret = MasterSecret(grown);
+        }
+        match x1.read(&mut secret.0[len..]).await? {
+            0 => break,
+            read => len += read,
+        }
+    }
+    secret.0.truncate(len);
 
-    Ok(buf)
+    Ok(secret)
 }
@@ -1,8 +1,13 @@
-use std::{collections::HashMap, fmt::Debug, marker::PhantomData};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    marker::PhantomData,
+    sync::atomic::{AtomicBool, Ordering},
+};
 
 use futures_util::Stream;
-use serde::{Deserialize, Serialize, Serializer};
-use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Type};
+use serde::{Serialize, Serializer};
+use zbus::zvariant::{ObjectPath, OwnedValue, Type};
 
 use crate::{desktop::HandleToken, proxy::Proxy, Error};
 
@@ -19,38 +24,47 @@ pub type SessionDetails = HashMap<String, OwnedValue>;
 /// and a signal [`Session::receive_closed`]. Whether it is allowed to
 /// directly call [`Session::close`] depends on the interface.
 ///
+/// Dropping a [`Session`] doesn't close it, as that requires a D-Bus call. A
+/// session created by the application stays alive on the portal side until
+/// [`Session::close`] is called or the application exits, which is reported
+/// as a warning with the `tracing` feature.
+///
+/// The handle tokens of the requests made on a session created by the
+/// application, e.g. `SelectSources` then `Start`, are derived from the token
+/// of the session: `ashpd_abc_1`, `ashpd_abc_2`, ... for the session
+/// `ashpd_abc`, which makes the calls of a session easy to follow in the
+/// D-Bus logs. The `CreateSession` request uses `ashpd_abc_0`.
+///
 /// Wrapper of the DBus interface: [`org.freedesktop.portal.Session`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.Session.html).
 #[derive(Type)]
 #[doc(alias = "org.freedesktop.portal.Session")]
 #[zvariant(signature = "o")]
-pub struct Session<'a, T>(Proxy<'a>, PhantomData<T>)
+pub struct Session<'a, T>
 where
-    T: SessionPortal;
+    T: SessionPortal,
+{
+    proxy: Proxy<'a>,
+    /// Whether the session was created by the application and is still open.
+    owned: AtomicBool,
+    handle_token: Option<HandleToken>,
+    #[cfg(any(
+        feature = "global_shortcuts",
+        feature = "input_capture",
+        feature = "location",
+        feature = "screencast",
+    ))]
+    requests: std::sync::atomic::AtomicU32,
+    marker: PhantomData<T>,
+}
 
 impl<'a, T> Session<'a, T>
 where
     T: SessionPortal,
 {
-    /// Create a new instance of [`Session`].
-    ///
-    /// **Note** A [`Session`] is not supposed to be created manually.
-    pub(crate) async fn new<P>(path: P) -> Result<Session<'a, T>, Error>
-    where
-        P: TryInto<ObjectPath<'a>>,
-        P::Error: Into<zbus::Error>,
-    {
-        let proxy = Proxy::new_desktop_with_path("org.freedesktop.portal.Session", path).await?;
-        Ok(Self(proxy, PhantomData))
-    }
-
-    pub(crate) async fn from_unique_name(
-        handle_token: &HandleToken,
-    ) -> Result<Session<'a, T>, crate::Error> {
-        let path =
-            Proxy::unique_name("/org/freedesktop/portal/desktop/session", handle_token).await?;
-        #[cfg(feature = "tracing")]
-        tracing::info!("Creating a org.freedesktop.portal.Session {}", path);
-        Self::new(path).await
+    /// The token the session was created with, `None` for the sessions not
+    /// created by the application.
+    pub fn handle_token(&self) -> Option<&HandleToken> {
+        self.handle_token.as_ref()
     }
 
     /// Emitted when a session is closed.
@@ -60,7 +74,7 @@ where
     /// See also [`Closed`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.Session.html#org-freedesktop-portal-session-closed).
     #[doc(alias = "Closed")]
     pub async fn receive_closed(&self) -> Result<impl Stream<Item = SessionDetails>, Error> {
-        self.0.signal("Closed").await
+        self.proxy.signal("Closed").await
     }
 
     /// Closes the portal session to which this object refers and ends all
@@ -71,11 +85,102 @@ where
     /// See also [`Close`](https://flatpak.github.io/xdg-desktop-portal/docs/doc-org.freedesktop.portal.Session.html#org-freedesktop-portal-session-close).
     #[doc(alias = "Close")]
     pub async fn close(&self) -> Result<(), Error> {
-        self.0.call("Close", &()).await
+        self.proxy.call::<()>("Close", &()).await?;
+        self.owned.store(false, Ordering::Relaxed);
+        Ok(())
     }
 
     pub(crate) fn path(&self) -> &ObjectPath<'_> {
-        self.0.path()
+        self.proxy.path()
+    }
+
+    /// The name of the portal the session belongs to.
+    pub(crate) fn destination(&self) -> zbus::names::BusName<'static> {
+        self.proxy.destination().to_owned()
+    }
+}
+
+#[cfg(any(
+    feature = "global_shortcuts",
+    feature = "inhibit",
+    feature = "input_capture",
+    feature = "location",
+    feature = "screencast",
+))]
+impl<'a, T> Session<'a, T>
+where
+    T: SessionPortal,
+{
+    /// Create a new instance of [`Session`].
+    ///
+    /// **Note** A [`Session`] is not supposed to be created manually.
+    pub(crate) async fn new<P>(portal: &Proxy<'_>, path: P) -> Result<Session<'a, T>, Error>
+    where
+        P: TryInto<ObjectPath<'static>>,
+        P::Error: Into<zbus::Error>,
+    {
+        let proxy = portal
+            .new_sibling("org.freedesktop.portal.Session", path)
+            .await?;
+        Ok(Self {
+            proxy,
+            owned: AtomicBool::new(false),
+            handle_token: None,
+            #[cfg(any(
+                feature = "global_shortcuts",
+                feature = "input_capture",
+                feature = "location",
+                feature = "screencast",
+            ))]
+            requests: std::sync::atomic::AtomicU32::new(1),
+            marker: PhantomData,
+        })
+    }
+
+    pub(crate) async fn from_unique_name(
+        portal: &Proxy<'_>,
+        handle_token: &HandleToken,
+    ) -> Result<Session<'a, T>, crate::Error> {
+        let path = portal.session_path(handle_token);
+        #[cfg(feature = "tracing")]
+        tracing::info!("Creating a org.freedesktop.portal.Session {}", path);
+        let mut session = Self::new(portal, path).await?;
+        // Only the sessions created by the application have to be closed.
+        session.owned.store(true, Ordering::Relaxed);
+        session.handle_token = Some(handle_token.clone());
+        Ok(session)
+    }
+
+    /// The handle token of the next request made on the session, derived
+    /// from [`Session::handle_token`].
+    #[cfg(any(
+        feature = "global_shortcuts",
+        feature = "input_capture",
+        feature = "location",
+        feature = "screencast",
+    ))]
+    pub(crate) fn next_handle_token(&self) -> HandleToken {
+        match &self.handle_token {
+            Some(handle_token) => {
+                handle_token.derive(self.requests.fetch_add(1, Ordering::Relaxed))
+            }
+            None => HandleToken::default(),
+        }
+    }
+
+    /// Check that the portal created the session at the path expected from
+    /// its handle token, instead of leaving the application with a session
+    /// it can't use.
+    pub(crate) fn check_created(&self, session_handle: &ObjectPath<'_>) -> Result<(), Error> {
+        if self.path() == session_handle {
+            Ok(())
+        } else {
+            Err(crate::PortalError::Failed(format!(
+                "The portal created the session `{session_handle}` instead of `{}`",
+                self.path()
+            ))
+            .into())
+        }
     }
 }
 
@@ -102,14 +207,34 @@ where
     }
 }
 
+impl<'a, T> Drop for Session<'a, T>
+where
+    T: SessionPortal,
+{
+    fn drop(&mut self) {
+        if self.owned.load(Ordering::Relaxed) {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "Session {} dropped without being closed, it stays alive until the application exits",
+                self.path()
+            );
+        }
+    }
+}
+
 /// Portals that have a long-lived interaction
 pub trait SessionPortal {}
 
 /// A response to a `create_session` request.
+#[cfg(any(
+    feature = "global_shortcuts",
+    feature = "inhibit",
+    feature = "screencast"
+))]
 #[derive(Type, Debug)]
 #[zvariant(signature = "dict")]
 pub(crate) struct CreateSessionResponse {
-    pub(crate) session_handle: OwnedObjectPath,
+    pub(crate) session_handle: zbus::zvariant::OwnedObjectPath,
 }
 
 // Context: Various portal were expected to actually return an OwnedObjectPath
@@ -122,12 +247,17 @@ pub(crate) struct CreateSessionResponse {
 // So in order to be future proof, we try to deserialize the `session_handle`
 // key as a string and fallback to an object path in case the situation gets
 // resolved in the future.
-impl<'de> Deserialize<'de> for CreateSessionResponse {
+#[cfg(any(
+    feature = "global_shortcuts",
+    feature = "inhibit",
+    feature = "screencast"
+))]
+impl<'de> serde::Deserialize<'de> for CreateSessionResponse {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: serde::Deserializer<'de>,
     {
-        let map: HashMap<String, OwnedValue> = HashMap::deserialize(deserializer)?;
+        let map: HashMap<String, OwnedValue> = serde::Deserialize::deserialize(deserializer)?;
         let session_handle = map.get("session_handle").ok_or_else(|| {
             serde::de::Error::custom(
                 "CreateSessionResponse failed to deserialize. Couldn't find a session_handle",
@@ -29,11 +29,14 @@
 
 use std::{collections::HashMap, convert::TryFrom, fmt::Debug, future::ready};
 
-use futures_util::{Stream, StreamExt};
+use futures_util::{
+    stream::{self, BoxStream},
+    Stream, StreamExt,
+};
 use serde::{Deserialize, Serialize};
 use zbus::zvariant::{OwnedValue, Type, Value};
 
-use crate::{desktop::Color, proxy::Proxy, Error};
+use crate::{desktop::Color, proxy::Proxy, Error, PortalError};
 
 /// A HashMap of the <key, value> settings found on a specific namespace.
 pub type Namespace = HashMap<String, OwnedValue>;
@@ -155,6 +158,48 @@ impl TryFrom<Value<'_>> for Contrast {
     }
 }
 
+/// The system's preference for reduced motion, e.g. for users sensitive to
+/// animations.
+#[cfg_attr(feature = "glib", derive(glib::Enum))]
+#[cfg_attr(feature = "glib", enum_type(name = "AshpdReducedMotion"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum ReducedMotion {
+    /// No preference
+    #[default]
+    NoPreference,
+    /// Reduce the non-essential animations
+    Reduce,
+}
+
+impl From<ReducedMotion> for OwnedValue {
+    fn from(value: ReducedMotion) -> Self {
+        match value {
+            ReducedMotion::Reduce => 1,
+            _ => 0,
+        }
+        .into()
+    }
+}
+
+impl TryFrom<OwnedValue> for ReducedMotion {
+    type Error = Error;
+
+    fn try_from(value: OwnedValue) -> Result<Self, Self::Error> {
+        TryFrom::<Value>::try_from(value.into())
+    }
+}
+
+impl TryFrom<Value<'_>> for ReducedMotion {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        Ok(match u32::try_from(value)? {
+            1 => Self::Reduce,
+            _ => Self::NoPreference,
+        })
+    }
+}
+
 /// Appearance namespace
 pub const APPEARANCE_NAMESPACE: &str = "org.freedesktop.appearance";
 /// Color scheme key
@@ -163,6 +208,166 @@ pub const COLOR_SCHEME_KEY: &str = "color-scheme";
 pub const ACCENT_COLOR_SCHEME_KEY: &str = "accent-color";
 /// Contrast key
 pub const CONTRAST_KEY: &str = "contrast";
+/// Reduced motion key
+pub const REDUCED_MOTION_KEY: &str = "reduced-motion";
+
+// The GNOME settings the accessibility helpers fall back to, on the desktops
+// not implementing the standardized keys yet.
+const GNOME_INTERFACE_NAMESPACE: &str = "org.gnome.desktop.interface";
+const GNOME_ENABLE_ANIMATIONS_KEY: &str = "enable-animations";
+const GNOME_A11Y_INTERFACE_NAMESPACE: &str = "org.gnome.desktop.a11y.interface";
+const GNOME_HIGH_CONTRAST_KEY: &str = "high-contrast";
+
+/// The appearance settings most applications care about, read at once.
+///
+/// ```rust,no_run
+/// use ashpd::desktop::settings::{Appearance, Settings};
+/// use futures_util::StreamExt;
+///
+/// async fn run() -> ashpd::Result<()> {
+///     let settings = Settings::new().await?;
+///     let appearance = Appearance::load(&settings).await?;
+///     println!("{:#?}", appearance);
+///
+///     let mut changes = appearance.receive_changed(&settings).await?.boxed();
+///     while let Some(appearance) = changes.next().await {
+///         println!("{:#?}", appearance);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Appearance {
+    color_scheme: ColorScheme,
+    accent_color: Option<Color>,
+    contrast: Contrast,
+    reduced_motion: ReducedMotion,
+}
+
+impl Appearance {
+    /// Read the appearance settings with a single `ReadAll` call.
+    ///
+    /// Missing or invalid values are replaced with their defaults.
+    pub async fn load(settings: &Settings<'_>) -> Result<Self, Error> {
+        let mut namespaces = settings.read_all(&[APPEARANCE_NAMESPACE]).await?;
+        let mut appearance = Self::default();
+        if let Some(namespace) = namespaces.remove(APPEARANCE_NAMESPACE) {
+            for (key, value) in namespace {
+                appearance.update(&key, value);
+            }
+        }
+        Ok(appearance)
+    }
+
+    /// Listen to changes of any of the appearance settings.
+    ///
+    /// Each item is a snapshot of the appearance, starting from `self`, with
+    /// the change applied.
+    pub async fn receive_changed(
+        self,
+        settings: &Settings<'_>,
+    ) -> Result<impl Stream<Item = Appearance>, Error> {
+        Ok(settings
+            .0
+            .signal_with_args::<Setting>("SettingChanged", &[(0, APPEARANCE_NAMESPACE)])
+            .await?
+            .scan(self, |appearance, setting| {
+                let Setting(_, key, value) = setting;
+                let changed = appearance.update(&key, value);
+                ready(Some(changed.then_some(*appearance)))
+            })
+            .filter_map(ready))
+    }
+
+    /// The system's preferred color scheme.
+    pub fn color_scheme(&self) -> ColorScheme {
+        self.color_scheme
+    }
+
+    /// The system's preferred accent color, if any.
+    pub fn accent_color(&self) -> Option<Color> {
+        self.accent_color
+    }
+
+    /// The system's preferred contrast level.
+    pub fn contrast(&self) -> Contrast {
+        self.contrast
+    }
+
+    /// The system's preference for reduced motion.
+    pub fn reduced_motion(&self) -> ReducedMotion {
+        self.reduced_motion
+    }
+
+    /// Apply the value of an appearance `key`, returns whether anything
+    /// changed.
+    fn update(&mut self, key: &str, value: OwnedValue) -> bool {
+        let previous = *self;
+        match key {
+            COLOR_SCHEME_KEY => self.color_scheme = value.try_into().unwrap_or_default(),
+            CONTRAST_KEY => self.contrast = value.try_into().unwrap_or_default(),
+            REDUCED_MOTION_KEY => self.reduced_motion = value.try_into().unwrap_or_default(),
+            ACCENT_COLOR_SCHEME_KEY => {
+                self.accent_color = <(f64, f64, f64)>::try_from(value).ok().map(Color::from)
+            }
+            _ => (),
+        }
+        previous != *self
+    }
+}
+
+/// Whether `namespace` is matched by one of the `patterns`, following the
+/// rules of [`Settings::read_all`].
+///
+/// An empty list or an empty pattern matches all the namespaces, and a
+/// trailing `*` matches any namespace starting with the rest of the pattern.
+pub fn namespace_matches(patterns: &[impl AsRef<str>], namespace: &str) -> bool {
+    patterns.is_empty()
+        || patterns.iter().any(|pattern| {
+            let pattern = pattern.as_ref();
+            match pattern.strip_suffix('*') {
+                _ if pattern.is_empty() => true,
+                Some(prefix) => namespace.starts_with(prefix),
+                None => namespace == pattern,
+            }
+        })
+}
+
+/// Decodes the settings of a namespace, as returned by
+/// [`Settings::read_all`], into `T`.
+///
+/// `T` is usually a struct deriving [`zbus::zvariant::DeserializeDict`] with
+/// an optional field per key of interest; the other keys are ignored.
+///
+/// ```rust
+/// use std::collections::HashMap;
+///
+/// use ashpd::desktop::settings::decode_namespace;
+/// use zbus::zvariant::{DeserializeDict, OwnedValue, Type};
+///
+/// #[derive(DeserializeDict, Type, Debug)]
+/// #[zvariant(signature = "dict")]
+/// struct Interface {
+///     #[zvariant(rename = "clock-format")]
+///     clock_format: Option<String>,
+/// }
+///
+/// let namespace = HashMap::from([(
+///     "clock-format".to_owned(),
+///     OwnedValue::try_from(zbus::zvariant::Value::from("24h")).unwrap(),
+/// )]);
+/// let interface = decode_namespace::<Interface>(&namespace).unwrap();
+/// assert_eq!(interface.clock_format.as_deref(), Some("24h"));
+/// ```
+pub fn decode_namespace<T>(namespace: &Namespace) -> Result<T, Error>
+where
+    T: Type + for<'de> Deserialize<'de>,
+{
+    use zbus::zvariant::{serialized::Context, to_bytes, LE};
+
+    let data = to_bytes(Context::new_dbus(LE, 0), namespace)?;
+    Ok(data.deserialize::<T>()?.0)
+}
 
 /// The interface provides read-only access to a small number of host settings
 /// required for toolkits similar to XSettings. It is not for general purpose
@@ -180,6 +385,15 @@ impl<'a> Settings<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`Settings`] using `connection` instead of the
+    /// session bus connection, e.g. a peer-to-peer connection to the portal.
+    pub async fn new_with_connection(connection: zbus::Connection) -> Result<Settings<'a>, Error> {
+        let proxy =
+            Proxy::new_desktop_with_connection(Some(connection), "org.freedesktop.portal.Settings")
+                .await?;
+        Ok(Self(proxy))
+    }
+
     /// Reads a single value. Returns an error on any unknown namespace or key.
     ///
     /// # Arguments
@@ -205,6 +419,26 @@ impl<'a> Settings<'a> {
         self.0.call("ReadAll", &(namespaces)).await
     }
 
+    /// Like [`Settings::read_all`], but decodes the settings of each
+    /// namespace into `T` with [`decode_namespace`].
+    ///
+    /// The namespaces are read in a single call. To decode them into
+    /// different types, call [`decode_namespace`] on the result of
+    /// [`Settings::read_all`] instead.
+    pub async fn read_all_typed<T>(
+        &self,
+        namespaces: &[impl AsRef<str> + Type + Serialize + Debug],
+    ) -> Result<HashMap<String, T>, Error>
+    where
+        T: Type + for<'de> Deserialize<'de>,
+    {
+        self.read_all(namespaces)
+            .await?
+            .into_iter()
+            .map(|(name, namespace)| Ok((name, decode_namespace(&namespace)?)))
+            .collect()
+    }
+
     /// Reads a single value. Returns an error on any unknown namespace or key.
     ///
     /// # Arguments
@@ -253,6 +487,37 @@ impl<'a> Settings<'a> {
             .await
     }
 
+    /// Retrieves the system's preference for reduced motion.
+    ///
+    /// Falls back to the `enable-animations` GNOME setting if the desktop
+    /// doesn't provide the standardized key.
+    pub async fn reduced_motion(&self) -> Result<ReducedMotion, Error> {
+        match self
+            .read::<ReducedMotion>(APPEARANCE_NAMESPACE, REDUCED_MOTION_KEY)
+            .await
+        {
+            Err(Error::Portal(PortalError::NotFound(_))) => self
+                .read::<bool>(GNOME_INTERFACE_NAMESPACE, GNOME_ENABLE_ANIMATIONS_KEY)
+                .await
+                .map(reduced_motion_from_animations),
+            result => result,
+        }
+    }
+
+    /// Retrieves whether the system prefers a high contrast.
+    ///
+    /// Falls back to the `high-contrast` GNOME accessibility setting if the
+    /// desktop doesn't provide the standardized [`CONTRAST_KEY`].
+    pub async fn high_contrast(&self) -> Result<bool, Error> {
+        match self.contrast().await {
+            Err(Error::Portal(PortalError::NotFound(_))) => {
+                self.read::<bool>(GNOME_A11Y_INTERFACE_NAMESPACE, GNOME_HIGH_CONTRAST_KEY)
+                    .await
+            }
+            result => result.map(|contrast| contrast == Contrast::High),
+        }
+    }
+
     /// Listen to changes of the system's preferred color scheme
     pub async fn receive_color_scheme_changed(
         &self,
@@ -282,6 +547,45 @@ impl<'a> Settings<'a> {
             .filter_map(|t| ready(t.ok())))
     }
 
+    /// Listen to changes of the system's preference for reduced motion,
+    /// including the changes of the GNOME setting
+    /// [`Settings::reduced_motion`] falls back to.
+    pub async fn receive_reduced_motion_changed(
+        &self,
+    ) -> Result<impl Stream<Item = ReducedMotion>, Error> {
+        let standard = self
+            .receive_setting_changed_with_args::<ReducedMotion>(
+                APPEARANCE_NAMESPACE,
+                REDUCED_MOTION_KEY,
+            )
+            .await?;
+        let gnome = self
+            .receive_setting_changed_with_args::<bool>(
+                GNOME_INTERFACE_NAMESPACE,
+                GNOME_ENABLE_ANIMATIONS_KEY,
+            )
+            .await?
+            .map(|t| t.map(reduced_motion_from_animations));
+        Ok(stream::select(standard, gnome).filter_map(|t| ready(t.ok())))
+    }
+
+    /// Listen to changes of whether the system prefers a high contrast,
+    /// including the changes of the GNOME setting
+    /// [`Settings::high_contrast`] falls back to.
+    pub async fn receive_high_contrast_changed(&self) -> Result<impl Stream<Item = bool>, Error> {
+        let standard = self
+            .receive_setting_changed_with_args::<Contrast>(APPEARANCE_NAMESPACE, CONTRAST_KEY)
+            .await?
+            .map(|t| t.map(|contrast| contrast == Contrast::High));
+        let gnome = self
+            .receive_setting_changed_with_args::<bool>(
+                GNOME_A11Y_INTERFACE_NAMESPACE,
+                GNOME_HIGH_CONTRAST_KEY,
+            )
+            .await?;
+        Ok(stream::select(standard, gnome).filter_map(|t| ready(t.ok())))
+    }
+
     /// Signal emitted when a setting changes.
     ///
     /// # Specifications
@@ -292,6 +596,56 @@ impl<'a> Settings<'a> {
         self.0.signal("SettingChanged").await
     }
 
+    /// Similar to [Self::receive_setting_changed] but only for the settings
+    /// of the `namespaces`, matched like in [`Settings::read_all`].
+    ///
+    /// Exact namespaces are filtered by the bus through match rules, so the
+    /// application is not woken up by the changes of the other namespaces.
+    /// Patterns with a trailing `*` are filtered on the client side.
+    ///
+    /// # Example
+    /// ```rust,no_run
+    /// use ashpd::desktop::settings::Settings;
+    /// use futures_util::StreamExt;
+    ///
+    /// # async fn run() -> ashpd::Result<()> {
+    /// let settings = Settings::new().await?;
+    /// let mut changes = settings
+    ///     .watch(&["org.gnome.desktop.interface", "org.freedesktop.appearance"])
+    ///     .await?;
+    /// while let Some(setting) = changes.next().await {
+    ///     println!("{}: {:#?}", setting.key(), setting.value());
+    /// }
+    /// #    Ok(())
+    /// # }
+    /// ```
+    pub async fn watch(
+        &self,
+        namespaces: &[&str],
+    ) -> Result<impl Stream<Item = Setting> + '_, Error> {
+        let exact = namespaces
+            .iter()
+            .all(|n| !n.is_empty() && !n.ends_with('*'));
+        if namespaces.is_empty() || !exact {
+            let patterns = namespaces.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+            let stream = self
+                .receive_setting_changed()
+                .await?
+                .filter(move |setting| ready(namespace_matches(&patterns, setting.namespace())));
+            return Ok(stream.boxed());
+        }
+        let mut streams = Vec::with_capacity(namespaces.len());
+        for namespace in namespaces {
+            let stream: BoxStream<'_, Setting> = self
+                .0
+                .signal_with_args::<Setting>("SettingChanged", &[(0, namespace)])
+                .await?
+                .boxed();
+            streams.push(stream);
+        }
+        Ok(stream::select_all(streams).boxed())
+    }
+
     /// Similar to [Self::receive_setting_changed]
     /// but allows you to filter specific settings.
     ///
@@ -333,6 +687,14 @@ impl<'a> Settings<'a> {
     }
 }
 
+fn reduced_motion_from_animations(enable_animations: bool) -> ReducedMotion {
+    if enable_animations {
+        ReducedMotion::NoPreference
+    } else {
+        ReducedMotion::Reduce
+    }
+}
+
 impl<'a> std::ops::Deref for Settings<'a> {
     type Target = zbus::Proxy<'a>;
 
@@ -340,3 +702,43 @@ impl<'a> std::ops::Deref for Settings<'a> {
         &self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn namespaces() {
+        let empty: [&str; 0] = [];
+        assert!(namespace_matches(&empty, "org.example"));
+        assert!(namespace_matches(&[""], "org.example"));
+        assert!(namespace_matches(&["org.example.*"], "org.example.foo"));
+        assert!(!namespace_matches(&["org.example.*"], "org.other"));
+        assert!(namespace_matches(
+            &["org.other", "org.example"],
+            "org.example"
+        ));
+        assert!(!namespace_matches(&["org.example"], "org.example.foo"));
+    }
+
+    #[test]
+    fn appearance_update() {
+        let mut appearance = Appearance::default();
+        assert!(appearance.update(COLOR_SCHEME_KEY, OwnedValue::from(1u32)));
+        assert_eq!(appearance.color_scheme(), ColorScheme::PreferDark);
+        assert!(!appearance.update(COLOR_SCHEME_KEY, OwnedValue::from(1u32)));
+        assert!(!appearance.update("unknown-key", OwnedValue::from(1u32)));
+
+        assert!(appearance.update(CONTRAST_KEY, OwnedValue::from(1u32)));
+        assert_eq!(appearance.contrast(), Contrast::High);
+
+        assert!(appearance.update(REDUCED_MOTION_KEY, OwnedValue::from(1u32)));
+        assert_eq!(appearance.reduced_motion(), ReducedMotion::Reduce);
+        assert!(appearance.update(REDUCED_MOTION_KEY, OwnedValue::from(7u32)));
+        assert_eq!(appearance.reduced_motion(), ReducedMotion::NoPreference);
+
+        let color = OwnedValue::try_from(Value::from((1.0, 0.5, 0.0))).unwrap();
+        assert!(appearance.update(ACCENT_COLOR_SCHEME_KEY, color));
+        assert_eq!(appearance.accent_color(), Some(Color::new(1.0, 0.5, 0.0)));
+    }
+}
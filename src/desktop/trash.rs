@@ -58,6 +58,18 @@ impl<'a> TrashProxy<'a> {
         Ok(Self(proxy))
     }
 
+    /// Create a new instance of [`TrashProxy`] using `connection` instead of
+    /// the session bus connection, e.g. a peer-to-peer connection to the
+    /// portal.
+    pub async fn new_with_connection(
+        connection: zbus::Connection,
+    ) -> Result<TrashProxy<'a>, Error> {
+        let proxy =
+            Proxy::new_desktop_with_connection(Some(connection), "org.freedesktop.portal.Trash")
+                .await?;
+        Ok(Self(proxy))
+    }
+
     /// Sends a file to the trashcan.
     /// Applications are allowed to trash a file if they can open it in
     /// read/write mode.
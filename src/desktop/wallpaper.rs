@@ -16,7 +16,7 @@
 //!     WallpaperRequest::default()
 //!         .set_on(SetOn::Both)
 //!         .show_preview(true)
-//!         .build_file(&file.as_fd())
+//!         .send_file(&file.as_fd())
 //!         .await?;
 //!     Ok(())
 //! }
@@ -28,24 +28,135 @@
 //! use ashpd::desktop::wallpaper::{SetOn, WallpaperRequest};
 //!
 //! async fn run() -> ashpd::Result<()> {
-//!     let uri =
-//!         url::Url::parse("file:///home/bilelmoussaoui/Downloads/adwaita-night.jpg").unwrap();
 //!     WallpaperRequest::default()
 //!         .set_on(SetOn::Both)
 //!         .show_preview(true)
-//!         .build_uri(&uri)
+//!         .send_uri("file:///home/bilelmoussaoui/Downloads/adwaita-night.jpg")
 //!         .await?;
 //!     Ok(())
 //! }
 //! ```
+//!
+//! ## Using the vendor extensions
+//!
+//! The backends implementing them can be asked for more than the options of
+//! the portal, the others ignore them. The `x-gnome-*` and `x-kde-*` keys are
+//! defined by ASHPD after the settings of these desktops, neither
+//! xdg-desktop-portal-gnome nor xdg-desktop-portal-kde support them.
+//!
+//! ```rust,no_run
+//! use ashpd::desktop::{
+//!     wallpaper::{FillMode, WallpaperRequest},
+//!     Color,
+//! };
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     WallpaperRequest::default()
+//!         .fill_mode(FillMode::Centered)
+//!         .fill_color(Color::new(0.1, 0.1, 0.1))
+//!         .send_uri("file:///home/bilelmoussaoui/Pictures/logo.png")
+//!         .await?;
+//!     Ok(())
+//! }
+//! ```
+//!
+//! ## Handling the outcome of the request
+//!
+//! ```rust,no_run
+//! use ashpd::{
+//!     desktop::wallpaper::{WallpaperOutcome, WallpaperRequest},
+//!     Error,
+//! };
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     let uri =
+//!         url::Url::parse("file:///home/bilelmoussaoui/Downloads/adwaita-night.jpg").unwrap();
+//!     let request = match WallpaperRequest::default()
+//!         .show_preview(true)
+//!         .send_uri(&uri)
+//!         .await
+//!     {
+//!         Err(Error::NotAllowed(reason)) => {
+//!             println!("Changing the wallpaper is not allowed: {reason}");
+//!             return Ok(());
+//!         }
+//!         request => request?,
+//!     };
+//!     match WallpaperOutcome::from_response(request.response())? {
+//!         WallpaperOutcome::Set => println!("Wallpaper changed"),
+//!         WallpaperOutcome::Cancelled => println!("Preview dismissed"),
+//!         WallpaperOutcome::Failed => println!("Failed to set the wallpaper"),
+//!     }
+//!     Ok(())
+//! }
+//! ```
 
-use std::{fmt, os::fd::BorrowedFd, str::FromStr};
+use std::{
+    collections::HashMap,
+    fmt,
+    os::fd::{AsFd, BorrowedFd},
+    path::Path,
+    str::FromStr,
+    time::Duration,
+};
 
 use serde::{self, Deserialize, Serialize};
-use zbus::zvariant::{Fd, SerializeDict, Type};
+use zbus::zvariant::{Fd, OwnedValue, Type};
+
+use super::{Request, ResponseError};
+use crate::{
+    desktop::{Color, HandleToken},
+    proxy::Proxy,
+    Error, ExportedPath, IntoUrl, PathPolicy, WindowIdentifier,
+};
+
+/// The vendor extension setting how the picture fills the screen, a
+/// [`FillMode`], following the `picture-options` GNOME setting.
+///
+/// **Note** the key is defined by ASHPD, xdg-desktop-portal-gnome doesn't
+/// support it.
+pub const FILL_MODE_KEY: &str = "x-gnome-picture-options";
+/// The vendor extension setting the color of the areas the picture doesn't
+/// cover, a `(ddd)` RGB tuple, following the `primary-color` GNOME setting.
+///
+/// **Note** the key is defined by ASHPD, xdg-desktop-portal-gnome doesn't
+/// support it.
+pub const FILL_COLOR_KEY: &str = "x-gnome-primary-color";
+/// The vendor extension setting the interval between the pictures, in
+/// seconds, when the URI is a folder shown as a slideshow like KDE does.
+///
+/// **Note** the key is defined by ASHPD, xdg-desktop-portal-kde doesn't
+/// support it.
+pub const SLIDESHOW_INTERVAL_KEY: &str = "x-kde-slideshow-interval";
+
+#[cfg_attr(feature = "glib", derive(glib::Enum))]
+#[cfg_attr(feature = "glib", enum_type(name = "AshpdFillMode"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// How the picture fills the screen, when its size differs, see
+/// [`WallpaperRequest::fill_mode`].
+pub enum FillMode {
+    /// Scale the picture to cover the screen, cropping it.
+    Zoom,
+    /// Show the picture at its size, centered.
+    Centered,
+    /// Scale the picture to fit the screen, keeping its aspect ratio.
+    Scaled,
+    /// Stretch the picture to the size of the screen.
+    Stretched,
+    /// Span the picture across all the screens.
+    Spanned,
+    /// Repeat the picture to cover the screen.
+    Tiled,
+}
 
-use super::Request;
-use crate::{desktop::HandleToken, proxy::Proxy, Error, WindowIdentifier};
+crate::helpers::impl_value_conversions!(FillMode {
+    Zoom => "zoom",
+    Centered => "centered",
+    Scaled => "scaled",
+    Stretched => "stretched",
+    Spanned => "spanned",
+    Tiled => "wallpaper",
+});
 
 #[cfg_attr(feature = "glib", derive(glib::Enum))]
 #[cfg_attr(feature = "glib", enum_type(name = "AshpdSetOn"))]
@@ -62,6 +173,12 @@ pub enum SetOn {
     Both,
 }
 
+crate::helpers::impl_value_conversions!(SetOn {
+    Lockscreen => "lockscreen",
+    Background => "background",
+    Both => "both",
+});
+
 impl fmt::Display for SetOn {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -97,29 +214,62 @@ impl FromStr for SetOn {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "Lockscreen" => Ok(SetOn::Lockscreen),
-            "Background" => Ok(SetOn::Background),
-            "Both" => Ok(SetOn::Both),
+            "Lockscreen" | "lockscreen" => Ok(SetOn::Lockscreen),
+            "Background" | "background" => Ok(SetOn::Background),
+            "Both" | "both" => Ok(SetOn::Both),
             _ => Err(Error::ParseError("Failed to parse SetOn, invalid value")),
         }
     }
 }
 
-#[derive(SerializeDict, Type, Debug, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+/// The outcome of a [`WallpaperRequest`].
+pub enum WallpaperOutcome {
+    /// The wallpaper was set.
+    Set,
+    /// The user dismissed the preview.
+    Cancelled,
+    /// The backend failed to set the wallpaper.
+    Failed,
+}
+
+impl WallpaperOutcome {
+    /// Convert the response of a [`WallpaperRequest`] to its outcome.
+    ///
+    /// Errors that are not part of the portal response, like
+    /// [`Error::NotAllowed`], are forwarded.
+    pub fn from_response(response: Result<(), Error>) -> Result<Self, Error> {
+        match response {
+            Ok(()) => Ok(Self::Set),
+            Err(Error::Response(ResponseError::Cancelled)) => Ok(Self::Cancelled),
+            Err(Error::Response(ResponseError::Other)) => Ok(Self::Failed),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+#[derive(Type, Debug, Default)]
 #[zvariant(signature = "dict")]
 struct WallpaperOptions {
     handle_token: HandleToken,
-    #[zvariant(rename = "show-preview")]
     show_preview: Option<bool>,
-    #[zvariant(rename = "set-on")]
     set_on: Option<SetOn>,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(Serialize for WallpaperOptions {
+    handle_token [required] => "handle_token",
+    show_preview => "show-preview",
+    set_on => "set-on",
+});
+
 struct WallpaperProxy<'a>(Proxy<'a>);
 
 impl<'a> WallpaperProxy<'a> {
-    pub async fn new() -> Result<WallpaperProxy<'a>, Error> {
-        let proxy = Proxy::new_desktop("org.freedesktop.portal.Wallpaper").await?;
+    pub async fn new(connection: Option<zbus::Connection>) -> Result<WallpaperProxy<'a>, Error> {
+        let proxy =
+            Proxy::new_desktop_with_connection(connection, "org.freedesktop.portal.Wallpaper")
+                .await?;
         Ok(Self(proxy))
     }
 
@@ -136,6 +286,7 @@ impl<'a> WallpaperProxy<'a> {
                 &(&identifier, Fd::from(file), &options),
             )
             .await
+            .map_err(Error::into_not_allowed)
     }
 
     pub async fn set_wallpaper_uri(
@@ -151,6 +302,7 @@ impl<'a> WallpaperProxy<'a> {
                 &(&identifier, uri, &options),
             )
             .await
+            .map_err(Error::into_not_allowed)
     }
 }
 
@@ -171,9 +323,28 @@ impl<'a> std::ops::Deref for WallpaperProxy<'a> {
 pub struct WallpaperRequest {
     identifier: WindowIdentifier,
     options: WallpaperOptions,
+    connection: Option<zbus::Connection>,
+    close_on_drop: Option<bool>,
 }
 
 impl WallpaperRequest {
+    /// Sets the connection to send the request on, instead of the session
+    /// bus connection.
+    #[must_use]
+    pub fn connection(mut self, connection: impl Into<Option<zbus::Connection>>) -> Self {
+        self.connection = connection.into();
+        self
+    }
+
+    /// Whether to close the request, dismissing its dialog, if the future
+    /// sending it is dropped before the response is received. Overrides
+    /// [`set_close_on_drop`](crate::set_close_on_drop) for this request.
+    #[must_use]
+    pub fn close_on_drop(mut self, close: bool) -> Self {
+        self.close_on_drop = Some(close);
+        self
+    }
+
     #[must_use]
     /// Sets a window identifier.
     pub fn identifier(mut self, identifier: impl Into<Option<WindowIdentifier>>) -> Self {
@@ -197,25 +368,103 @@ impl WallpaperRequest {
         self
     }
 
-    /// Build using a URI.
-    pub async fn build_uri(self, uri: &url::Url) -> Result<Request<()>, Error> {
-        let proxy = WallpaperProxy::new().await?;
+    /// Sets the option `key`, unknown to this version of the crate, e.g. a
+    /// vendor extension like `x-gnome-...` to experiment with the features of
+    /// a backend. The options known to the crate can't be overridden.
+    #[must_use]
+    pub fn extra<'v>(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<zbus::zvariant::Value<'v>>,
+    ) -> Self {
+        crate::helpers::insert_extra(&mut self.options.extra, key, value);
+        self
+    }
+
+    /// Sets how the picture fills the screen, see [`FILL_MODE_KEY`].
+    ///
+    /// This is a vendor extension defined by ASHPD, ignored by the backends
+    /// not implementing it, including the GNOME and KDE ones.
+    #[must_use]
+    pub fn fill_mode(self, fill_mode: FillMode) -> Self {
+        self.extra(FILL_MODE_KEY, fill_mode)
+    }
+
+    /// Sets the color of the areas the picture doesn't cover, see
+    /// [`FILL_COLOR_KEY`].
+    ///
+    /// This is a vendor extension defined by ASHPD, ignored by the backends
+    /// not implementing it, including the GNOME and KDE ones.
+    #[must_use]
+    pub fn fill_color(self, color: Color) -> Self {
+        self.extra(FILL_COLOR_KEY, (color.red(), color.green(), color.blue()))
+    }
+
+    /// Sets the interval between the pictures when the URI is a folder, see
+    /// [`SLIDESHOW_INTERVAL_KEY`].
+    ///
+    /// This is a vendor extension defined by ASHPD, ignored by the backends
+    /// not implementing it, including the GNOME and KDE ones.
+    #[must_use]
+    pub fn slideshow_interval(self, interval: Duration) -> Self {
+        let seconds = u32::try_from(interval.as_secs()).unwrap_or(u32::MAX);
+        self.extra(SLIDESHOW_INTERVAL_KEY, seconds)
+    }
+
+    /// Send the request for a URI.
+    ///
+    /// Paths are converted to `file://` URIs, see [`IntoUrl`].
+    pub async fn send_uri(self, uri: impl IntoUrl) -> Result<Request<()>, Error> {
+        let uri = uri.into_url()?;
+        let mut proxy = WallpaperProxy::new(self.connection).await?;
+        proxy.0.close_requests_on_drop(self.close_on_drop);
         proxy
-            .set_wallpaper_uri(&self.identifier, uri, self.options)
+            .set_wallpaper_uri(&self.identifier, &uri, self.options)
             .await
     }
 
-    /// Build using a file.
-    pub async fn build_file(self, file: &BorrowedFd<'_>) -> Result<Request<()>, Error> {
-        let proxy = WallpaperProxy::new().await?;
+    /// Send the request for a file.
+    pub async fn send_file(self, file: &BorrowedFd<'_>) -> Result<Request<()>, Error> {
+        let mut proxy = WallpaperProxy::new(self.connection).await?;
+        proxy.0.close_requests_on_drop(self.close_on_drop);
         proxy
             .set_wallpaper_file(&self.identifier, file, self.options)
             .await
     }
+
+    /// Send the request for a path, handed over to the portal following
+    /// `policy`.
+    pub async fn send_path(
+        self,
+        path: impl AsRef<Path>,
+        policy: PathPolicy,
+    ) -> Result<Request<()>, Error> {
+        match ExportedPath::new(path, policy).await? {
+            ExportedPath::Fd(fd) => self.send_file(&fd.as_fd()).await,
+            exported => {
+                let uri = exported
+                    .uri()
+                    .ok_or(Error::ParseError("Failed to convert path to a URI"))?;
+                self.send_uri(uri).await
+            }
+        }
+    }
+
+    /// Build using a URI.
+    #[deprecated = "Use `WallpaperRequest::send_uri` instead"]
+    pub async fn build_uri(self, uri: &url::Url) -> Result<Request<()>, Error> {
+        self.send_uri(uri).await
+    }
+
+    /// Build using a file.
+    #[deprecated = "Use `WallpaperRequest::send_file` instead"]
+    pub async fn build_file(self, file: &BorrowedFd<'_>) -> Result<Request<()>, Error> {
+        self.send_file(file).await
+    }
 }
 #[cfg(test)]
 mod tests {
-    use super::SetOn;
+    use super::*;
 
     #[test]
     fn serialize_deserialize() {
@@ -226,4 +475,42 @@ mod tests {
         let decoded = serde_json::from_str(&string).unwrap();
         assert_eq!(set_on, decoded);
     }
+
+    #[test]
+    fn vendor_extensions() {
+        let request = WallpaperRequest::default()
+            .fill_mode(FillMode::Tiled)
+            .fill_color(Color::new(1.0, 0.5, 0.0))
+            .slideshow_interval(Duration::from_secs(600));
+        let extra = &request.options.extra;
+        assert_eq!(
+            FillMode::try_from(extra[FILL_MODE_KEY].try_clone().unwrap()).unwrap(),
+            FillMode::Tiled
+        );
+        assert_eq!(
+            <(f64, f64, f64)>::try_from(extra[FILL_COLOR_KEY].try_clone().unwrap()).unwrap(),
+            (1.0, 0.5, 0.0)
+        );
+        assert_eq!(u32::try_from(&extra[SLIDESHOW_INTERVAL_KEY]).unwrap(), 600);
+    }
+
+    #[test]
+    fn outcome() {
+        assert_eq!(
+            WallpaperOutcome::from_response(Ok(())).unwrap(),
+            WallpaperOutcome::Set
+        );
+        assert_eq!(
+            WallpaperOutcome::from_response(Err(ResponseError::Cancelled.into())).unwrap(),
+            WallpaperOutcome::Cancelled
+        );
+        assert_eq!(
+            WallpaperOutcome::from_response(Err(ResponseError::Other.into())).unwrap(),
+            WallpaperOutcome::Failed
+        );
+        assert!(matches!(
+            WallpaperOutcome::from_response(Err(Error::NotAllowed("disable-wallpaper".to_owned()))),
+            Err(Error::NotAllowed(_))
+        ));
+    }
 }
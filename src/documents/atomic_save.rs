@@ -0,0 +1,302 @@
+use std::{
+    ffi::CString,
+    fs::{File, OpenOptions},
+    io::{ErrorKind, Write},
+    os::{
+        fd::AsRawFd,
+        unix::fs::{OpenOptionsExt, PermissionsExt},
+    },
+    path::{Path, PathBuf},
+};
+
+use crate::{desktop::HandleToken, helpers::paths};
+
+/// How [`atomic_save`] wrote the file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveStrategy {
+    /// Written to an unnamed `O_TMPFILE` file, linked then renamed over the
+    /// destination.
+    UnnamedTemporary,
+    /// Written to a temporary file next to the destination then renamed over
+    /// it.
+    Temporary,
+    /// Written in place, the file system refused the rename. The file is
+    /// left truncated if the write fails.
+    InPlace,
+}
+
+/// Whether `path` is in the FUSE file system of the document portal, e.g. a
+/// file chosen with the file chooser portal by a sandboxed application.
+///
+/// The path is not resolved, only its prefix is checked against the mount
+/// points of the document portal.
+pub fn is_document_path(path: impl AsRef<Path>) -> bool {
+    let path = path.as_ref();
+    let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(format!("/run/user/{}", unsafe { libc::getuid() })));
+    [runtime_dir.join("doc"), PathBuf::from("/run/flatpak/doc")]
+        .iter()
+        .any(|mount_point| path.starts_with(mount_point))
+}
+
+/// Replace the content of the file at `path` with `data`, such that readers
+/// see either the old or the new content, but never a partially written
+/// file.
+///
+/// The data is written to a temporary file, flushed to the disk, then renamed
+/// over `path`, keeping the permissions of the existing file. The FUSE file
+/// system of the document portal, see [`is_document_path`], doesn't support
+/// `O_TMPFILE`, so a temporary file named after `path` is used there instead.
+/// The document portal accepts it and the rename over the document. The file
+/// is written in place as a last resort, when the rename is refused.
+///
+/// A symbolic link at `path` is followed, the file it points to is replaced
+/// and the link kept. A new file is created with the permissions of
+/// [`File::create`], filtered by the umask.
+pub fn atomic_save(path: impl AsRef<Path>, data: &[u8]) -> std::io::Result<SaveStrategy> {
+    save(path.as_ref(), data, 0o666)
+}
+
+/// Like [`atomic_save`], a new file being only readable by the current user.
+#[cfg(any(test, feature = "file_chooser", feature = "restore_tokens"))]
+pub(crate) fn atomic_save_private(path: &Path, data: &[u8]) -> std::io::Result<SaveStrategy> {
+    save(path, data, 0o600)
+}
+
+/// Save `data` at `path`, a new file being created with `mode`, filtered by
+/// the umask.
+fn save(path: &Path, data: &[u8], mode: u32) -> std::io::Result<SaveStrategy> {
+    let path = resolve_links(path)?;
+    let path = path.as_path();
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("`{}` is not a file path", path.display()),
+        ));
+    };
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let permissions = match std::fs::metadata(path) {
+        Ok(metadata) => Permissions::Existing(metadata.permissions()),
+        Err(err) if err.kind() == ErrorKind::NotFound => Permissions::New(mode),
+        Err(err) => return Err(err),
+    };
+    let temporary = dir.join(format!(
+        ".{}.{}~",
+        name.to_string_lossy(),
+        HandleToken::default()
+    ));
+
+    if !is_document_path(path) {
+        match save_unnamed(dir, &temporary, data, &permissions) {
+            Ok(()) => {
+                return replace(&temporary, path, data, SaveStrategy::UnnamedTemporary);
+            }
+            Err(_err) => {
+                #[cfg(feature = "tracing")]
+                tracing::debug!("O_TMPFILE unavailable in {}: {_err}", dir.display());
+            }
+        }
+    }
+    save_named(&temporary, data, &permissions)?;
+    replace(&temporary, path, data, SaveStrategy::Temporary)
+}
+
+/// The path of the file `path` points to, for the rename to replace it
+/// instead of the link.
+fn resolve_links(path: &Path) -> std::io::Result<PathBuf> {
+    match std::fs::canonicalize(path) {
+        Ok(path) => Ok(path),
+        // A new file, or a dangling link to one.
+        Err(err) if err.kind() == ErrorKind::NotFound => match std::fs::read_link(path) {
+            Ok(target) => Ok(path.parent().unwrap_or(Path::new("")).join(target)),
+            Err(_) => Ok(path.to_owned()),
+        },
+        Err(err) => Err(err),
+    }
+}
+
+/// The permissions the saved file ends up with.
+enum Permissions {
+    /// The ones of the replaced file.
+    Existing(std::fs::Permissions),
+    /// The mode of a new file.
+    New(u32),
+}
+
+impl Permissions {
+    /// The mode of the temporary files, before the permissions of the
+    /// replaced file, if any, are applied.
+    fn creation_mode(&self) -> u32 {
+        match self {
+            Self::Existing(_) => 0o600,
+            Self::New(mode) => *mode,
+        }
+    }
+}
+
+/// Write `data` to an `O_TMPFILE` file in `dir`, then link it at `temporary`.
+fn save_unnamed(
+    dir: &Path,
+    temporary: &Path,
+    data: &[u8],
+    permissions: &Permissions,
+) -> std::io::Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .mode(permissions.creation_mode())
+        .custom_flags(libc::O_TMPFILE)
+        .open(dir)?;
+    write_all_synced(&mut file, data, permissions)?;
+    let source = CString::new(format!("/proc/self/fd/{}", file.as_raw_fd()))?;
+    let target = paths::null_terminated(temporary)
+        .map_err(|err| std::io::Error::new(ErrorKind::InvalidInput, err))?;
+    // SAFETY: both paths are valid nul-terminated strings.
+    let linked = unsafe {
+        libc::linkat(
+            libc::AT_FDCWD,
+            source.as_ptr(),
+            libc::AT_FDCWD,
+            target.as_ptr().cast(),
+            libc::AT_SYMLINK_FOLLOW,
+        )
+    };
+    if linked == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// Write `data` to the new file `temporary`.
+fn save_named(temporary: &Path, data: &[u8], permissions: &Permissions) -> std::io::Result<()> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(permissions.creation_mode())
+        .open(temporary)?;
+    if let Err(err) = write_all_synced(&mut file, data, permissions) {
+        let _ = std::fs::remove_file(temporary);
+        return Err(err);
+    }
+    Ok(())
+}
+
+fn write_all_synced(
+    file: &mut File,
+    data: &[u8],
+    permissions: &Permissions,
+) -> std::io::Result<()> {
+    file.write_all(data)?;
+    if let Permissions::Existing(permissions) = permissions {
+        // Not supported by every file system, e.g. the document portal.
+        let _ = file.set_permissions(std::fs::Permissions::from_mode(permissions.mode()));
+    }
+    file.sync_all()
+}
+
+/// Rename `temporary` over `path`, or write `data` in place if the file
+/// system refuses it.
+fn replace(
+    temporary: &Path,
+    path: &Path,
+    data: &[u8],
+    strategy: SaveStrategy,
+) -> std::io::Result<SaveStrategy> {
+    let err = match std::fs::rename(temporary, path) {
+        Ok(()) => return Ok(strategy),
+        Err(err) => err,
+    };
+    let _ = std::fs::remove_file(temporary);
+    let refused = matches!(
+        err.raw_os_error(),
+        Some(libc::EXDEV | libc::EPERM | libc::EACCES)
+    ) || err.kind() == ErrorKind::Unsupported;
+    if !refused {
+        return Err(err);
+    }
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        "Failed to rename over {}, writing it in place: {err}",
+        path.display()
+    );
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    write_all_synced(&mut file, data, &Permissions::New(0o666))?;
+    Ok(SaveStrategy::InPlace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn document_paths() {
+        let runtime_dir = std::env::var_os("XDG_RUNTIME_DIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(format!("/run/user/{}", unsafe { libc::getuid() })));
+        assert!(is_document_path(runtime_dir.join("doc/f2ee988d/notes.txt")));
+        assert!(is_document_path("/run/flatpak/doc/f2ee988d/notes.txt"));
+        assert!(!is_document_path(runtime_dir.join("documents/notes.txt")));
+        assert!(!is_document_path("/home/user/notes.txt"));
+    }
+
+    #[test]
+    fn save() {
+        let dir = std::env::temp_dir().join(format!("ashpd-atomic-save-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.txt");
+        std::fs::write(&path, b"old").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o640)).unwrap();
+
+        let strategy = atomic_save(&path, b"new").unwrap();
+        assert_ne!(strategy, SaveStrategy::InPlace);
+        assert_eq!(std::fs::read(&path).unwrap(), b"new");
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+
+        let temporary = dir.join(".notes.txt.tmp~");
+        let permissions = Permissions::New(0o666);
+        save_named(&temporary, b"named", &permissions).unwrap();
+        assert!(save_named(&temporary, b"named", &permissions).is_err());
+        assert_eq!(
+            replace(&temporary, &path, b"named", SaveStrategy::Temporary).unwrap(),
+            SaveStrategy::Temporary
+        );
+        assert_eq!(std::fs::read(&path).unwrap(), b"named");
+
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        let link = dir.join("link.txt");
+        std::os::unix::fs::symlink("notes.txt", &link).unwrap();
+        atomic_save(&link, b"linked").unwrap();
+        assert!(std::fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink());
+        assert_eq!(std::fs::read(&path).unwrap(), b"linked");
+
+        atomic_save(dir.join("new.txt"), b"").unwrap();
+        std::fs::write(dir.join("reference.txt"), b"").unwrap();
+        let mode = |name| {
+            std::fs::metadata(dir.join(name))
+                .unwrap()
+                .permissions()
+                .mode()
+        };
+        assert_eq!(mode("new.txt"), mode("reference.txt"));
+
+        atomic_save_private(&dir.join("private.txt"), b"").unwrap();
+        assert_eq!(mode("private.txt") & 0o077, 0);
+
+        assert!(atomic_save(dir.join("missing/notes.txt"), b"").is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
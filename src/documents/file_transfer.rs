@@ -159,6 +159,17 @@ impl<'a> FileTransfer<'a> {
         self.0.call("StartTransfer", &(options)).await
     }
 
+    /// Like [`start_transfer()`][`FileTransfer::start_transfer`], but returns
+    /// a [`Transfer`] to explicitly [`close()`][`Transfer::close`] once done.
+    pub async fn start(&self, writeable: bool, auto_stop: bool) -> Result<Transfer<'_, 'a>, Error> {
+        let key = self.start_transfer(writeable, auto_stop).await?;
+        Ok(Transfer {
+            proxy: self,
+            key,
+            needs_stop: !auto_stop,
+        })
+    }
+
     /// Ends the transfer.
     /// Further calls to [`add_files()`][`FileTransfer::add_files`] or
     /// [`retrieve_files()`][`FileTransfer::retrieve_files`] for this key
@@ -193,6 +204,54 @@ impl<'a> FileTransfer<'a> {
     }
 }
 
+/// A transfer started with [`FileTransfer::start`].
+///
+/// Dropping a [`Transfer`] doesn't stop it, as that requires a D-Bus call.
+/// Unless it was started with `auto_stop`, it stays alive on the portal side
+/// until [`Transfer::close`] is called or the application exits, which is
+/// reported as a warning with the `tracing` feature.
+#[derive(Debug)]
+pub struct Transfer<'p, 'a> {
+    proxy: &'p FileTransfer<'a>,
+    key: String,
+    needs_stop: bool,
+}
+
+impl<'p, 'a> Transfer<'p, 'a> {
+    /// The key of the transfer, to pass to
+    /// [`retrieve_files()`][`FileTransfer::retrieve_files`].
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Adds files to the transfer.
+    ///
+    /// See also [`add_files()`][`FileTransfer::add_files`].
+    pub async fn add_files(&self, fds: &[&BorrowedFd<'_>]) -> Result<(), Error> {
+        self.proxy.add_files(&self.key, fds).await
+    }
+
+    /// Ends the transfer.
+    ///
+    /// See also [`stop_transfer()`][`FileTransfer::stop_transfer`].
+    pub async fn close(mut self) -> Result<(), Error> {
+        self.needs_stop = false;
+        self.proxy.stop_transfer(&self.key).await
+    }
+}
+
+impl Drop for Transfer<'_, '_> {
+    fn drop(&mut self) {
+        if self.needs_stop {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "Transfer {} dropped without being closed, it stays alive until the application exits",
+                self.key
+            );
+        }
+    }
+}
+
 impl<'a> std::ops::Deref for FileTransfer<'a> {
     type Target = zbus::Proxy<'a>;
 
@@ -33,12 +33,25 @@
 //! }
 //! ```
 
-use std::{collections::HashMap, fmt, os::fd::BorrowedFd, path::Path, str::FromStr};
+use std::{
+    collections::HashMap,
+    fmt,
+    ops::ControlFlow,
+    os::{
+        fd::{AsFd, BorrowedFd, OwnedFd},
+        unix::fs::OpenOptionsExt,
+    },
+    path::Path,
+    str::FromStr,
+};
 
 use enumflags2::{bitflags, BitFlags};
-use serde::{Deserialize, Serialize};
+use serde::{
+    de::{DeserializeSeed, MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
 use serde_repr::{Deserialize_repr, Serialize_repr};
-use zbus::zvariant::{Fd, OwnedValue, Type};
+use zbus::zvariant::{Fd, OwnedValue, Signature, Type};
 
 pub use crate::app_id::DocumentID;
 use crate::{proxy::Proxy, AppID, Error, FilePath};
@@ -58,6 +71,25 @@ pub enum DocumentFlags {
     ExportDirectory,
 }
 
+crate::helpers::impl_value_conversions!(DocumentFlags as u32 { ReuseExisting, Persistent, AsNeededByApp, ExportDirectory });
+
+/// Open `path` with `O_PATH`, as expected by the methods of [`Documents`]
+/// taking a file descriptor.
+///
+/// The descriptor is opened with `O_CLOEXEC`. If `follow_symlinks` is `false`
+/// and `path` is a symbolic link, the link itself is opened.
+pub fn open_path_fd(path: impl AsRef<Path>, follow_symlinks: bool) -> std::io::Result<OwnedFd> {
+    let mut flags = libc::O_PATH;
+    if !follow_symlinks {
+        flags |= libc::O_NOFOLLOW;
+    }
+    std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(flags)
+        .open(path)
+        .map(OwnedFd::from)
+}
+
 /// A [`HashMap`] mapping application IDs to the permissions for that
 /// application
 pub type Permissions = HashMap<AppID, Vec<Permission>>;
@@ -80,6 +112,13 @@ pub enum Permission {
     Delete,
 }
 
+crate::helpers::impl_value_conversions!(Permission {
+    Read => "read",
+    Write => "write",
+    GrantPermissions => "grant-permissions",
+    Delete => "delete",
+});
+
 impl fmt::Display for Permission {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -120,13 +159,39 @@ impl FromStr for Permission {
         match s {
             "Read" | "read" => Ok(Permission::Read),
             "Write" | "write" => Ok(Permission::Write),
-            "GrantPermissions" | "grant-permissions" => Ok(Permission::GrantPermissions),
+            "GrantPermissions" | "Grant Permissions" | "grant-permissions" => {
+                Ok(Permission::GrantPermissions)
+            }
             "Delete" | "delete" => Ok(Permission::Delete),
-            _ => Err(Error::ParseError("Failed to parse priority, invalid value")),
+            _ => Err(Error::ParseError(
+                "Failed to parse permission, invalid value",
+            )),
         }
     }
 }
 
+/// Parses the permissions sent by the document store, skipping the ones
+/// unknown to this version of the crate instead of failing the whole reply.
+fn permissions_from_wire(permissions: HashMap<AppID, Vec<String>>) -> Permissions {
+    permissions
+        .into_iter()
+        .map(|(app_id, permissions)| {
+            let permissions = permissions
+                .iter()
+                .filter_map(|permission| {
+                    let parsed = permission.parse().ok();
+                    #[cfg(feature = "tracing")]
+                    if parsed.is_none() {
+                        tracing::warn!("Ignoring the unknown permission `{permission}`");
+                    }
+                    parsed
+                })
+                .collect();
+            (app_id, permissions)
+        })
+        .collect()
+}
+
 /// The interface lets sandboxed applications make files from the outside world
 /// available to sandboxed applications in a controlled way.
 ///
@@ -309,6 +374,41 @@ impl<'a> Documents<'a> {
             .await
     }
 
+    /// Adds the file at `path` to the document store.
+    ///
+    /// Like [`Documents::add`], or [`Documents::add_full`] if `flags` contains
+    /// more than [`DocumentFlags::ReuseExisting`] and
+    /// [`DocumentFlags::Persistent`], but opens the file descriptor with
+    /// [`open_path_fd`] and closes it once the file is added.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file to add, symbolic links are followed.
+    /// * `flags` - A [`DocumentFlags`].
+    ///
+    /// # Returns
+    ///
+    /// The ID of the file in the document store.
+    pub async fn add_path(
+        &self,
+        path: impl AsRef<Path>,
+        flags: BitFlags<DocumentFlags>,
+    ) -> Result<DocumentID, Error> {
+        let fd = open_path_fd(path, true)?;
+        let add_flags = DocumentFlags::ReuseExisting | DocumentFlags::Persistent;
+        if add_flags.contains(flags) {
+            return self
+                .add(
+                    &fd.as_fd(),
+                    flags.contains(DocumentFlags::ReuseExisting),
+                    flags.contains(DocumentFlags::Persistent),
+                )
+                .await;
+        }
+        let (doc_ids, _) = self.add_full(&[&fd.as_fd()], flags, None, &[]).await?;
+        doc_ids.into_iter().next().ok_or(Error::NoResponse)
+    }
+
     /// Removes an entry from the document store. The file itself is not
     /// deleted.
     ///
@@ -388,7 +488,11 @@ impl<'a> Documents<'a> {
         &self,
         doc_id: impl Into<DocumentID>,
     ) -> Result<(FilePath, Permissions), Error> {
-        self.0.call("Info", &(doc_id.into())).await
+        let (path, permissions) = self
+            .0
+            .call::<(FilePath, HashMap<AppID, Vec<String>>)>("Info", &(doc_id.into()))
+            .await?;
+        Ok((path, permissions_from_wire(permissions)))
     }
 
     /// Lists documents in the document store for an application (or for all
@@ -413,15 +517,68 @@ impl<'a> Documents<'a> {
         &self,
         app_id: Option<&AppID>,
     ) -> Result<HashMap<DocumentID, FilePath>, Error> {
-        let app_id = app_id.map(|id| id.as_ref()).unwrap_or("");
-        let response: HashMap<String, FilePath> = self.0.call("List", &(app_id)).await?;
+        self.list_filtered(app_id, |_, _| true).await
+    }
 
-        let mut new_response: HashMap<DocumentID, FilePath> = HashMap::new();
-        for (key, file_name) in response {
-            new_response.insert(DocumentID::from(key), file_name);
-        }
+    /// Like [`Documents::list`], keeping only the documents for which
+    /// `predicate` returns `true`.
+    ///
+    /// The documents are filtered as the reply is decoded, the other ones are
+    /// never collected.
+    #[doc(alias = "List")]
+    pub async fn list_filtered(
+        &self,
+        app_id: Option<&AppID>,
+        mut predicate: impl FnMut(&DocumentID, &Path) -> bool + Send,
+    ) -> Result<HashMap<DocumentID, FilePath>, Error> {
+        let mut documents = HashMap::new();
+        self.list_with(app_id, |id, path| {
+            if predicate(&id, path.as_ref()) {
+                documents.insert(id, path);
+            }
+            ControlFlow::Continue(())
+        })
+        .await?;
+        Ok(documents)
+    }
 
-        Ok(new_response)
+    /// Like [`Documents::list`], calling `f` with each document as the
+    /// reply is decoded instead of collecting them, which keeps the memory
+    /// usage low with thousands of documents.
+    ///
+    /// The remaining documents are skipped once `f` returns
+    /// [`ControlFlow::Break`].
+    ///
+    /// ```rust,no_run
+    /// use std::ops::ControlFlow;
+    ///
+    /// use ashpd::documents::Documents;
+    ///
+    /// async fn run() -> ashpd::Result<()> {
+    ///     let proxy = Documents::new().await?;
+    ///     let mut count = 0;
+    ///     proxy
+    ///         .list_with(None, |id, path| {
+    ///             println!(
+    ///                 "{id}: {}",
+    ///                 AsRef::<std::path::Path>::as_ref(&path).display()
+    ///             );
+    ///             count += 1;
+    ///             ControlFlow::Continue(())
+    ///         })
+    ///         .await?;
+    ///     println!("{count} documents");
+    ///     Ok(())
+    /// }
+    /// ```
+    #[doc(alias = "List")]
+    pub async fn list_with(
+        &self,
+        app_id: Option<&AppID>,
+        f: impl FnMut(DocumentID, FilePath) -> ControlFlow<()> + Send,
+    ) -> Result<(), Error> {
+        let app_id = app_id.map(|id| id.as_ref()).unwrap_or("");
+        self.0.call_with_seed("List", &(app_id), ListSeed(f)).await
     }
 
     /// Looks up the document ID for a file.
@@ -451,6 +608,23 @@ impl<'a> Documents<'a> {
         }
     }
 
+    /// Like [`Documents::lookup`], but resolves the symbolic links and
+    /// relative components of `path` first, like the document store does for
+    /// the files added to it.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - A path in the host filesystem.
+    ///
+    /// # Returns
+    ///
+    /// The ID of the file in the document store, or [`None`] if the file is not
+    /// in the document store.
+    pub async fn lookup_path(&self, path: impl AsRef<Path>) -> Result<Option<DocumentID>, Error> {
+        let path = std::fs::canonicalize(path)?;
+        self.lookup(path).await
+    }
+
     /// Revokes access permissions for a file in the document store from an
     /// application.
     ///
@@ -512,16 +686,94 @@ impl<'a> std::ops::Deref for Documents<'a> {
 /// Interact with `org.freedesktop.portal.FileTransfer` interface.
 mod file_transfer;
 
-pub use file_transfer::FileTransfer;
+pub use file_transfer::{FileTransfer, Transfer};
+
+/// Save files atomically on the document portal mount.
+mod atomic_save;
+
+#[cfg(any(feature = "file_chooser", feature = "restore_tokens"))]
+pub(crate) use atomic_save::atomic_save_private;
+pub use atomic_save::{atomic_save, is_document_path, SaveStrategy};
+
+/// Calls its function with each entry of the reply of `List` while it is
+/// decoded.
+struct ListSeed<F>(F);
+
+impl<F> Type for ListSeed<F> {
+    fn signature() -> Signature<'static> {
+        <HashMap<DocumentID, FilePath>>::signature()
+    }
+}
+
+impl<'de, F> DeserializeSeed<'de> for ListSeed<F>
+where
+    F: FnMut(DocumentID, FilePath) -> ControlFlow<()>,
+{
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, F> Visitor<'de> for ListSeed<F>
+where
+    F: FnMut(DocumentID, FilePath) -> ControlFlow<()>,
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a map of document IDs to paths")
+    }
+
+    fn visit_map<M: MapAccess<'de>>(mut self, mut map: M) -> Result<(), M::Error> {
+        let mut done = false;
+        while let Some(id) = map.next_key::<DocumentID>()? {
+            if done {
+                map.next_value::<serde::de::IgnoredAny>()?;
+            } else {
+                let path = map.next_value::<FilePath>()?;
+                done = (self.0)(id, path).is_break();
+            }
+        }
+        Ok(())
+    }
+}
 
 #[cfg(test)]
 mod tests {
-    use std::collections::HashMap;
+    use std::{
+        collections::HashMap,
+        os::fd::{AsRawFd, OwnedFd},
+    };
 
     use zbus::zvariant::Type;
 
+    use super::open_path_fd;
     use crate::{app_id::DocumentID, documents::Permission, FilePath};
 
+    #[test]
+    fn path_fd() {
+        let dir = std::env::temp_dir().join(format!("ashpd-documents-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("file");
+        let link = dir.join("link");
+        std::fs::write(&file, b"").unwrap();
+        let _ = std::fs::remove_file(&link);
+        std::os::unix::fs::symlink(&file, &link).unwrap();
+
+        let fd_path = |fd: OwnedFd| std::fs::read_link(format!("/proc/self/fd/{}", fd.as_raw_fd()));
+        assert_eq!(fd_path(open_path_fd(&link, true).unwrap()).unwrap(), file);
+        assert_eq!(fd_path(open_path_fd(&link, false).unwrap()).unwrap(), link);
+        assert!(open_path_fd(&dir, true).is_ok());
+        assert_eq!(
+            open_path_fd(dir.join("missing"), true).unwrap_err().kind(),
+            std::io::ErrorKind::NotFound
+        );
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
     #[test]
     fn serialize_deserialize() {
         let permission = Permission::GrantPermissions;
@@ -533,4 +785,62 @@ mod tests {
 
         assert_eq!(HashMap::<DocumentID, FilePath>::signature(), "a{say}");
     }
+
+    #[test]
+    fn unknown_permissions() {
+        use super::permissions_from_wire;
+        use crate::AppID;
+
+        let app_id = AppID::try_from("org.example.App").unwrap();
+        let wire = HashMap::from([(
+            app_id.clone(),
+            vec!["read".to_owned(), "execute".to_owned(), "delete".to_owned()],
+        )]);
+        assert_eq!(
+            permissions_from_wire(wire)[&app_id],
+            [Permission::Read, Permission::Delete]
+        );
+    }
+
+    #[test]
+    fn list_seed() {
+        use std::ops::ControlFlow;
+
+        use zbus::zvariant::{serialized::Context, to_bytes, LE};
+
+        use super::ListSeed;
+
+        let documents = (0..1000)
+            .map(|i| {
+                (
+                    DocumentID::from(format!("{i:08x}")),
+                    FilePath::new(format!("/home/user/{i}.txt")).unwrap(),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        let data = to_bytes(Context::new_dbus(LE, 0), &documents).unwrap();
+
+        let mut decoded = HashMap::new();
+        data.deserialize_with_seed(ListSeed(|id, path| {
+            decoded.insert(id, path);
+            ControlFlow::Continue(())
+        }))
+        .unwrap();
+        assert_eq!(decoded, documents);
+
+        // The remaining entries are skipped, and the reply still decodes.
+        let mut count = 0;
+        let (_, read) = data
+            .deserialize_with_seed(ListSeed(|_, _| {
+                count += 1;
+                if count == 10 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }))
+            .unwrap();
+        assert_eq!(count, 10);
+        assert_eq!(read, data.len());
+    }
 }
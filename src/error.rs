@@ -1,9 +1,20 @@
+use std::sync::RwLock;
+
 use zbus::DBusError;
 
-use crate::desktop::{dynamic_launcher::UnexpectedIconError, request::ResponseError};
+#[cfg(feature = "dynamic_launcher")]
+use crate::desktop::dynamic_launcher::{InvalidIconError, UnexpectedIconError};
+#[cfg(feature = "email")]
+use crate::desktop::email::InvalidAddressesError;
+use crate::desktop::request::ResponseError;
 
 /// An error type that describes the various DBus errors.
 ///
+/// Backend implementations return it from their trait methods; it is replied
+/// with the matching `org.freedesktop.portal.Error.*` name. The conversions
+/// from [`std::io::Error`] and [`Error`] pick the closest variant, so `?` can
+/// be used on them directly.
+///
 /// See <https://github.com/flatpak/xdg-desktop-portal/blob/master/src/xdp-utils.h#L119-L127>.
 #[allow(missing_docs)]
 #[derive(DBusError, Debug)]
@@ -40,6 +51,9 @@ pub enum Error {
     Zbus(zbus::Error),
     /// A signal returned no response.
     NoResponse,
+    /// The portal went away before responding to the request, for example
+    /// because the portal service exited.
+    Closed,
     /// Failed to parse a string into an enum variant
     ParseError(&'static str),
     /// Input/Output
@@ -58,19 +72,50 @@ pub enum Error {
     /// The inner fields are the required version and the version advertised by
     /// the interface.
     RequiresVersion(u32, u32),
+    /// The portal refused to carry out the request, for example because it
+    /// was disabled by a lockdown setting or the permission was denied.
+    NotAllowed(String),
     /// Returned when the portal wasn't found. Either the user has no portals
     /// frontend installed or the frontend doesn't support the used portal.
     PortalNotFound(zbus::names::OwnedInterfaceName),
     /// An error indicating that a Icon::Bytes was expected but wrong type was
     /// passed
     UnexpectedIcon,
-    #[cfg(feature = "backend")]
-    /// Failed to parse a URL.
+    /// The icon is not a valid launcher icon.
+    #[cfg(feature = "dynamic_launcher")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dynamic_launcher")))]
+    InvalidIcon(InvalidIconError),
+    /// The launcher with the given desktop file ID isn't installed.
+    #[cfg(feature = "dynamic_launcher")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "dynamic_launcher")))]
+    LauncherNotFound(String),
+    /// Some email addresses are invalid.
+    #[cfg(feature = "email")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "email")))]
+    InvalidAddresses(InvalidAddressesError),
+    /// Failed to parse a URL, or to convert a path into a `file://` URL.
     Url(url::ParseError),
 }
 
 impl std::error::Error for Error {}
 
+impl Error {
+    /// Replace the `org.freedesktop.portal.Error.NotAllowed` error replied by
+    /// the portal by [`Error::NotAllowed`].
+    #[cfg(any(feature = "dynamic_launcher", feature = "wallpaper"))]
+    pub(crate) fn into_not_allowed(self) -> Self {
+        match self {
+            Self::Zbus(zbus::Error::MethodError(name, message, _))
+                if name.as_str() == "org.freedesktop.portal.Error.NotAllowed" =>
+            {
+                Self::NotAllowed(message.unwrap_or_default())
+            }
+            Self::Portal(PortalError::NotAllowed(message)) => Self::NotAllowed(message),
+            err => err,
+        }
+    }
+}
+
 impl std::fmt::Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -78,6 +123,7 @@ impl std::fmt::Display for Error {
             Self::Zbus(e) => f.write_str(&format!("ZBus Error: {e}")),
             Self::Portal(e) => f.write_str(&format!("Portal request failed: {e}")),
             Self::NoResponse => f.write_str("Portal error: no response"),
+            Self::Closed => f.write_str("Portal error: the request was closed by the portal"),
             Self::IO(e) => f.write_str(&format!("IO: {e}")),
             #[cfg(feature = "pipewire")]
             Self::Pipewire(e) => f.write_str(&format!("Pipewire: {e}")),
@@ -88,6 +134,7 @@ impl std::fmt::Display for Error {
                 f,
                 "This interface requires version {required}, but {current} is available"
             ),
+            Self::NotAllowed(e) => write!(f, "Not allowed: {e}"),
             Self::PortalNotFound(portal) => {
                 write!(f, "A portal frontend implementing `{portal}` was not found")
             }
@@ -95,7 +142,12 @@ impl std::fmt::Display for Error {
                 f,
                 "Expected icon of type Icon::Bytes but a different type was used."
             ),
-            #[cfg(feature = "backend")]
+            #[cfg(feature = "dynamic_launcher")]
+            Self::InvalidIcon(e) => write!(f, "Invalid icon: {e}"),
+            #[cfg(feature = "dynamic_launcher")]
+            Self::LauncherNotFound(id) => write!(f, "Launcher `{id}` not found"),
+            #[cfg(feature = "email")]
+            Self::InvalidAddresses(e) => write!(f, "{e}"),
             Self::Url(e) => f.write_str(&format!("Parse error: {e}")),
         }
     }
@@ -144,14 +196,199 @@ impl From<std::io::Error> for Error {
     }
 }
 
+#[cfg(feature = "dynamic_launcher")]
 impl From<UnexpectedIconError> for Error {
     fn from(_: UnexpectedIconError) -> Self {
         Self::UnexpectedIcon
     }
 }
-#[cfg(feature = "backend")]
+#[cfg(feature = "dynamic_launcher")]
+impl From<InvalidIconError> for Error {
+    fn from(e: InvalidIconError) -> Self {
+        Self::InvalidIcon(e)
+    }
+}
+
+#[cfg(feature = "email")]
+impl From<InvalidAddressesError> for Error {
+    fn from(e: InvalidAddressesError) -> Self {
+        Self::InvalidAddresses(e)
+    }
+}
+
+#[cfg(feature = "dynamic_launcher")]
+impl From<InvalidIconError> for PortalError {
+    fn from(e: InvalidIconError) -> Self {
+        Self::InvalidArgument(e.to_string())
+    }
+}
+
+impl From<std::io::Error> for PortalError {
+    fn from(e: std::io::Error) -> Self {
+        use std::io::ErrorKind;
+
+        match e.kind() {
+            ErrorKind::NotFound => Self::NotFound(e.to_string()),
+            ErrorKind::AlreadyExists => Self::Exist(e.to_string()),
+            ErrorKind::PermissionDenied => Self::NotAllowed(e.to_string()),
+            ErrorKind::InvalidInput | ErrorKind::InvalidData => {
+                Self::InvalidArgument(e.to_string())
+            }
+            _ => Self::Failed(e.to_string()),
+        }
+    }
+}
+
+impl From<zbus::fdo::Error> for PortalError {
+    fn from(e: zbus::fdo::Error) -> Self {
+        Self::ZBus(zbus::Error::FDO(Box::new(e)))
+    }
+}
+
+impl From<Error> for PortalError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Portal(e) => e,
+            Error::NotAllowed(e) => Self::NotAllowed(e),
+            Error::Zbus(e) => Self::ZBus(e),
+            Error::IO(e) => e.into(),
+            Error::Response(ResponseError::Cancelled) => Self::Cancelled(e.to_string()),
+            Error::InvalidAppID | Error::NulTerminated(_) | Error::Url(_) => {
+                Self::InvalidArgument(e.to_string())
+            }
+            #[cfg(feature = "email")]
+            Error::InvalidAddresses(_) => Self::InvalidArgument(e.to_string()),
+            #[cfg(feature = "dynamic_launcher")]
+            Error::LauncherNotFound(_) => Self::NotFound(e.to_string()),
+            e => Self::Failed(e.to_string()),
+        }
+    }
+}
+
+/// For the methods that have to return [`zbus::fdo::Error`], e.g. property
+/// setters.
+impl From<PortalError> for zbus::fdo::Error {
+    fn from(e: PortalError) -> Self {
+        match e {
+            PortalError::ZBus(zbus::Error::FDO(e)) => *e,
+            PortalError::ZBus(e) => Self::ZBus(e),
+            PortalError::Failed(e)
+            | PortalError::Cancelled(e)
+            | PortalError::WindowDestroyed(e) => Self::Failed(e),
+            PortalError::InvalidArgument(e) => Self::InvalidArgs(e),
+            PortalError::NotFound(e) => Self::FileNotFound(e),
+            PortalError::Exist(e) => Self::FileExists(e),
+            PortalError::NotAllowed(e) => Self::AccessDenied(e),
+        }
+    }
+}
+
 impl From<url::ParseError> for Error {
     fn from(e: url::ParseError) -> Self {
         Self::Url(e)
     }
 }
+
+/// Where an [`Error`] reported to the observer set with [`set_error_observer`]
+/// happened.
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorContext<'a> {
+    interface: &'a str,
+    member: &'a str,
+}
+
+impl<'a> ErrorContext<'a> {
+    /// The D-Bus interface of the portal, e.g.
+    /// `org.freedesktop.portal.Screenshot`.
+    pub fn interface(&self) -> &'a str {
+        self.interface
+    }
+
+    /// The method, or the property, that failed, e.g. `Screenshot`.
+    pub fn member(&self) -> &'a str {
+        self.member
+    }
+}
+
+/// A function called with every error returned by a portal call.
+pub type ErrorObserver = fn(&Error, ErrorContext<'_>);
+
+static ERROR_OBSERVER: RwLock<Option<ErrorObserver>> = RwLock::new(None);
+
+/// Call `observer` with every error returned by a portal call, including the
+/// requests the user cancelled, replacing the previous observer. `None`
+/// removes it.
+///
+/// This allows counting the portal errors, e.g. in the telemetry of an
+/// application, without wrapping every call. The errors are still returned to
+/// the callers.
+///
+/// ```rust
+/// use ashpd::{Error, ErrorContext};
+///
+/// fn observer(err: &Error, context: ErrorContext<'_>) {
+///     eprintln!("{}.{} failed: {err}", context.interface(), context.member());
+/// }
+///
+/// ashpd::set_error_observer(observer as ashpd::ErrorObserver);
+/// ```
+pub fn set_error_observer(observer: impl Into<Option<ErrorObserver>>) {
+    *ERROR_OBSERVER
+        .write()
+        .unwrap_or_else(|err| err.into_inner()) = observer.into();
+}
+
+pub(crate) fn observe<T>(
+    interface: &str,
+    member: &str,
+    result: Result<T, Error>,
+) -> Result<T, Error> {
+    if let Err(err) = &result {
+        observe_error(interface, member, err);
+    }
+    result
+}
+
+pub(crate) fn observe_error(interface: &str, member: &str, err: &Error) {
+    let observer = *ERROR_OBSERVER.read().unwrap_or_else(|err| err.into_inner());
+    if let Some(observer) = observer {
+        observer(err, ErrorContext { interface, member });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn portal_error_conversions() {
+        let err = std::io::Error::new(std::io::ErrorKind::NotFound, "gone");
+        assert!(matches!(PortalError::from(err), PortalError::NotFound(_)));
+        assert!(matches!(
+            PortalError::from(Error::Response(ResponseError::Cancelled)),
+            PortalError::Cancelled(_)
+        ));
+        assert!(matches!(
+            PortalError::from(Error::InvalidAppID),
+            PortalError::InvalidArgument(_)
+        ));
+        assert!(matches!(
+            zbus::fdo::Error::from(PortalError::NotAllowed("no".to_owned())),
+            zbus::fdo::Error::AccessDenied(_)
+        ));
+        let err = PortalError::from(zbus::fdo::Error::LimitsExceeded("busy".to_owned()));
+        assert!(matches!(
+            zbus::fdo::Error::from(err),
+            zbus::fdo::Error::LimitsExceeded(_)
+        ));
+        assert!(matches!(
+            Error::from(PortalError::NotAllowed("no".to_owned())),
+            Error::Portal(PortalError::NotAllowed(_))
+        ));
+        #[cfg(any(feature = "dynamic_launcher", feature = "wallpaper"))]
+        assert!(matches!(
+            Error::from(PortalError::NotAllowed("no".to_owned())).into_not_allowed(),
+            Error::NotAllowed(_)
+        ));
+    }
+}
@@ -0,0 +1,250 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use futures_util::{
+    stream::{BoxStream, SelectAll},
+    FutureExt, Stream, StreamExt,
+};
+use zbus::{proxy::CacheProperties, zvariant::OwnedObjectPath};
+
+#[cfg(feature = "global_shortcuts")]
+use crate::desktop::global_shortcuts::{Activated, Deactivated, GlobalShortcuts};
+#[cfg(feature = "notification")]
+use crate::desktop::notification::{Action, NotificationProxy};
+#[cfg(feature = "settings")]
+use crate::desktop::settings::{Setting, Settings};
+use crate::{
+    desktop::{session::SessionPortal, Session},
+    Error,
+};
+
+/// An event received by an [`EventLoop`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Event {
+    /// A setting changed, see [`Settings::receive_setting_changed`].
+    #[cfg(feature = "settings")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "settings")))]
+    SettingChanged(Setting),
+    /// An action of a notification was invoked, see
+    /// [`NotificationProxy::receive_action_invoked`].
+    #[cfg(feature = "notification")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "notification")))]
+    NotificationAction(Action),
+    /// A global shortcut was activated, see
+    /// [`GlobalShortcuts::receive_activated`].
+    #[cfg(feature = "global_shortcuts")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "global_shortcuts")))]
+    ShortcutActivated(Activated),
+    /// A global shortcut was deactivated, see
+    /// [`GlobalShortcuts::receive_deactivated`].
+    #[cfg(feature = "global_shortcuts")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "global_shortcuts")))]
+    ShortcutDeactivated(Deactivated),
+    /// The portal closed the session at the given path, see
+    /// [`Session::receive_closed`].
+    SessionClosed(OwnedObjectPath),
+}
+
+/// Gathers the signals of several portals into a single stream of
+/// [`Event`]s, for applications whose main loop isn't async.
+///
+/// The event loop owns the connection the portals are watched on. It is a
+/// [`Stream`] of events, ending once nothing is watched anymore, which can
+/// also be polled without blocking with [`EventLoop::try_next`], e.g. from the
+/// frame callback of the GUI toolkit.
+///
+/// ```rust,no_run
+/// use ashpd::{Event, EventLoop};
+///
+/// async fn run() -> ashpd::Result<()> {
+///     let mut events = EventLoop::new().await?;
+///     events.watch_settings().await?;
+///     events.watch_notifications().await?;
+///
+///     // In the frame callback of the toolkit.
+///     while let Some(event) = events.try_next() {
+///         match event {
+///             Event::SettingChanged(setting) => println!("{setting:?}"),
+///             Event::NotificationAction(action) => println!("{}", action.name()),
+///             _ => {}
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct EventLoop {
+    connection: zbus::Connection,
+    streams: SelectAll<BoxStream<'static, Event>>,
+}
+
+impl std::fmt::Debug for EventLoop {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EventLoop")
+            .field("connection", &self.connection)
+            .field("streams", &self.streams.len())
+            .finish()
+    }
+}
+
+impl EventLoop {
+    /// Create an event loop on a new connection to the session bus.
+    pub async fn new() -> Result<Self, Error> {
+        let connection = zbus::Connection::session().await?;
+        Ok(Self::with_connection(connection))
+    }
+
+    /// Create an event loop on `connection`, e.g. a peer-to-peer connection
+    /// to the portal.
+    pub fn with_connection(connection: zbus::Connection) -> Self {
+        Self {
+            connection,
+            streams: SelectAll::new(),
+        }
+    }
+
+    /// The connection the portals are watched on.
+    pub fn connection(&self) -> &zbus::Connection {
+        &self.connection
+    }
+
+    /// Whether nothing is watched.
+    pub fn is_empty(&self) -> bool {
+        self.streams.is_empty()
+    }
+
+    /// Receive [`Event::SettingChanged`].
+    #[cfg(feature = "settings")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "settings")))]
+    pub async fn watch_settings(&mut self) -> Result<(), Error> {
+        let proxy = Settings::new_with_connection(self.connection.clone()).await?;
+        let changed = proxy.receive_setting_changed().await?;
+        self.watch(changed.map(Event::SettingChanged));
+        Ok(())
+    }
+
+    /// Receive [`Event::NotificationAction`].
+    #[cfg(feature = "notification")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "notification")))]
+    pub async fn watch_notifications(&mut self) -> Result<(), Error> {
+        let proxy = NotificationProxy::new_with_connection(self.connection.clone()).await?;
+        let invoked = proxy.receive_action_invoked().await?;
+        self.watch(invoked.map(Event::NotificationAction));
+        Ok(())
+    }
+
+    /// Receive [`Event::ShortcutActivated`] and
+    /// [`Event::ShortcutDeactivated`].
+    #[cfg(feature = "global_shortcuts")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "global_shortcuts")))]
+    pub async fn watch_global_shortcuts(&mut self) -> Result<(), Error> {
+        let proxy = GlobalShortcuts::new_with_connection(self.connection.clone()).await?;
+        let activated = proxy.receive_activated().await?;
+        let deactivated = proxy.receive_deactivated().await?;
+        self.watch(activated.map(Event::ShortcutActivated));
+        self.watch(deactivated.map(Event::ShortcutDeactivated));
+        Ok(())
+    }
+
+    /// Receive [`Event::SessionClosed`] once the portal closes `session`.
+    pub async fn watch_session<T: SessionPortal>(
+        &mut self,
+        session: &Session<'_, T>,
+    ) -> Result<(), Error> {
+        let path = OwnedObjectPath::from(session.path().to_owned());
+        // A proxy of its own, the stream outlives the borrow of `session`.
+        let proxy: zbus::Proxy<'static> = zbus::proxy::Builder::new(&self.connection)
+            .destination(session.destination())?
+            .path(path.clone())?
+            .interface("org.freedesktop.portal.Session")?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+        let closed = proxy.receive_signal("Closed").await?;
+        self.watch(
+            closed
+                .take(1)
+                .map(move |_msg| Event::SessionClosed(path.clone())),
+        );
+        Ok(())
+    }
+
+    /// Receive the events of `stream`, e.g. to merge the signals of a portal
+    /// the event loop doesn't know about.
+    pub fn watch(&mut self, stream: impl Stream<Item = Event> + Send + 'static) {
+        self.streams.push(stream.boxed());
+    }
+
+    /// The next event if one was received, without waiting.
+    pub fn try_next(&mut self) -> Option<Event> {
+        self.streams.next().now_or_never().flatten()
+    }
+}
+
+impl Stream for EventLoop {
+    type Item = Event;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.streams.poll_next_unpin(cx)
+    }
+}
+
+#[cfg(all(test, feature = "settings", feature = "tokio"))]
+mod tests {
+    use zbus::zvariant::Value;
+
+    use super::*;
+    use crate::{helpers::p2p_pair, proxy::DESKTOP_PATH};
+
+    struct Portal;
+
+    #[zbus::interface(name = "org.freedesktop.portal.Settings")]
+    impl Portal {
+        #[zbus(property, name = "version")]
+        fn version(&self) -> u32 {
+            2
+        }
+    }
+
+    #[tokio::test]
+    async fn events() {
+        let (server, client) = p2p_pair().await;
+        server
+            .object_server()
+            .at(DESKTOP_PATH, Portal)
+            .await
+            .unwrap();
+
+        let mut events = EventLoop::with_connection(client);
+        assert!(events.is_empty());
+        assert!(events.try_next().is_none());
+        events.watch_settings().await.unwrap();
+        events.watch(futures_util::stream::iter([Event::SessionClosed(
+            OwnedObjectPath::try_from("/org/freedesktop/portal/desktop/session/1/a").unwrap(),
+        )]));
+
+        server
+            .emit_signal(
+                None::<()>,
+                DESKTOP_PATH,
+                "org.freedesktop.portal.Settings",
+                "SettingChanged",
+                &("org.example", "key", Value::from(1u32)),
+            )
+            .await
+            .unwrap();
+        let mut received = Vec::new();
+        for _ in 0..2 {
+            received.push(events.next().await.unwrap());
+        }
+        assert!(received.iter().any(
+            |event| matches!(event, Event::SessionClosed(path) if path.as_str().ends_with("/a"))
+        ));
+        assert!(received.iter().any(
+            |event| matches!(event, Event::SettingChanged(setting) if setting.key() == "key")
+        ));
+        assert!(events.try_next().is_none());
+    }
+}
@@ -0,0 +1,255 @@
+//! # Examples
+//!
+//! Open the dialog in the folder and with the filter used the last time
+//!
+//! ```rust,no_run
+//! use ashpd::{
+//!     desktop::file_chooser::FileFilter, file_chooser_history::FileChooserHistory, AppID,
+//! };
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     let app_id = AppID::try_from("org.example.Viewer").unwrap();
+//!     let history = FileChooserHistory::for_app(&app_id)?;
+//!     let files = history
+//!         .open_file("import")
+//!         .await?
+//!         .title("Import an image")
+//!         .filter(FileFilter::new("PNG").mimetype("image/png"))
+//!         .filter(FileFilter::new("JPEG").mimetype("image/jpeg"))
+//!         .send()
+//!         .await?
+//!         .response()?;
+//!     history.remember("import", &files).await?;
+//!     Ok(())
+//! }
+//! ```
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use zbus::zvariant::{
+    serialized::{Context, Data},
+    to_bytes, OwnedValue, Type, LE,
+};
+
+use crate::{
+    app_state,
+    desktop::file_chooser::{
+        FileFilter, OpenFileRequest, SaveFileRequest, SaveFilesRequest, SelectedFiles,
+    },
+    AppID, Error, FilePath,
+};
+
+/// The folder and filter a file chooser dialog was last used with.
+#[derive(Debug, Default, Type)]
+#[zvariant(signature = "dict")]
+pub struct DialogState {
+    folder: Option<FilePath>,
+    filter: Option<FileFilter>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(DialogState with Serialize {
+    folder => "folder",
+    filter => "filter",
+});
+
+impl DialogState {
+    /// The folder the selected files were in.
+    pub fn folder(&self) -> Option<&Path> {
+        self.folder.as_ref().map(AsRef::as_ref)
+    }
+
+    /// The selected filter.
+    pub fn filter(&self) -> Option<&FileFilter> {
+        self.filter.as_ref()
+    }
+
+    /// The folder, if it still exists.
+    async fn existing_folder(&self) -> Option<&Path> {
+        let folder = self.folder()?;
+        app_state::is_dir(folder).await.then_some(folder)
+    }
+}
+
+/// A store of the last folder and filter of file chooser dialogs, keyed by
+/// an application defined purpose, e.g. `"import"` or `"export"`.
+///
+/// The state is stored under the XDG state directory, which is private to
+/// the application when it is sandboxed.
+#[derive(Debug, Clone)]
+pub struct FileChooserHistory {
+    dir: PathBuf,
+}
+
+impl FileChooserHistory {
+    /// Store the state of the dialogs of the application `app_id` in
+    /// `$XDG_STATE_HOME/<app_id>/file-chooser`.
+    pub fn for_app(app_id: &AppID) -> Result<Self, Error> {
+        Ok(Self::with_dir(app_state::app_dir(app_id, "file-chooser")?))
+    }
+
+    /// Store the state of the dialogs in `dir`.
+    pub fn with_dir(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_owned(),
+        }
+    }
+
+    /// The directory the state is stored in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The stored state of `purpose`, if any.
+    ///
+    /// A state that can't be decoded, e.g. because the file was corrupted, is
+    /// treated as missing.
+    pub async fn get(&self, purpose: &str) -> Result<Option<DialogState>, Error> {
+        let Some(bytes) = app_state::read(self.path(purpose)?).await? else {
+            return Ok(None);
+        };
+        let data = Data::new(bytes, Context::new_dbus(LE, 0));
+        Ok(data.deserialize().ok().map(|(state, _)| state))
+    }
+
+    /// Store the `state` of `purpose`, or forget it if `None`.
+    pub async fn set(&self, purpose: &str, state: Option<&DialogState>) -> Result<(), Error> {
+        let path = self.path(purpose)?;
+        match state {
+            Some(state) => {
+                let data = to_bytes(Context::new_dbus(LE, 0), state)?;
+                app_state::write(path, data.bytes().to_vec()).await
+            }
+            None => app_state::remove(path).await,
+        }
+    }
+
+    /// Remember the folder of `files` and their selected filter for
+    /// `purpose`.
+    ///
+    /// The folder is the one the dialog was showing if the portal
+    /// implementation reported it, the folder of the first selected file
+    /// otherwise.
+    ///
+    /// What the response doesn't tell, e.g. the filter if the portal
+    /// implementation doesn't report it, is kept from the stored state.
+    pub async fn remember(&self, purpose: &str, files: &SelectedFiles) -> Result<(), Error> {
+        let mut state = self.get(purpose).await?.unwrap_or_default();
+        let folder = match files.current_folder() {
+            Some(folder) => Some(FilePath::new(folder)?),
+            None => files
+                .files()
+                .next()
+                .and_then(|file| file.path().parent().map(FilePath::new))
+                .transpose()?,
+        };
+        if folder.is_some() {
+            state.folder = folder;
+        }
+        if let Some(filter) = files.current_filter() {
+            state.filter = Some(filter.clone());
+        }
+        self.set(purpose, Some(&state)).await
+    }
+
+    /// An open file request starting in the stored folder of `purpose`,
+    /// with its stored filter selected.
+    ///
+    /// The stored filter should be one of the filters added to the request.
+    pub async fn open_file(&self, purpose: &str) -> Result<OpenFileRequest, Error> {
+        let state = self.get(purpose).await?.unwrap_or_default();
+        SelectedFiles::open_file()
+            .current_filter(state.filter.clone())
+            .current_folder::<&Path>(state.existing_folder().await)
+    }
+
+    /// A save file request starting in the stored folder of `purpose`, with
+    /// its stored filter selected.
+    ///
+    /// The stored filter should be one of the filters added to the request.
+    pub async fn save_file(&self, purpose: &str) -> Result<SaveFileRequest, Error> {
+        let state = self.get(purpose).await?.unwrap_or_default();
+        SelectedFiles::save_file()
+            .current_filter(state.filter.clone())
+            .current_folder::<&Path>(state.existing_folder().await)
+    }
+
+    /// A save files request starting in the stored folder of `purpose`.
+    pub async fn save_files(&self, purpose: &str) -> Result<SaveFilesRequest, Error> {
+        let state = self.get(purpose).await?.unwrap_or_default();
+        SelectedFiles::save_files().current_folder::<&Path>(state.existing_folder().await)
+    }
+
+    fn path(&self, purpose: &str) -> Result<PathBuf, Error> {
+        if purpose.is_empty() || purpose.starts_with('.') || purpose.contains('/') {
+            return Err(Error::ParseError("Invalid file chooser purpose"));
+        }
+        Ok(self.dir.join(purpose))
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use std::os::unix::ffi::OsStrExt;
+
+    use zbus::zvariant::Value;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn store() {
+        let dir = std::env::temp_dir().join(format!("ashpd-file-chooser-{}", std::process::id()));
+        let history = FileChooserHistory::with_dir(&dir);
+        assert!(history.get("import").await.unwrap().is_none());
+
+        let filter = FileFilter::new("Images").mimetype("image/png");
+        let folder = std::env::temp_dir();
+        let uri = url::Url::from_file_path(folder.join("image.png")).unwrap();
+        let response = HashMap::from([
+            ("uris", Value::from(vec![uri.as_str()])),
+            (
+                "current_filter",
+                Value::from(("Images", vec![(1u32, "image/png")])),
+            ),
+        ]);
+        let data = to_bytes(Context::new_dbus(LE, 0), &response).unwrap();
+        let files: SelectedFiles = data.deserialize().unwrap().0;
+        assert_eq!(files.current_filter(), Some(&filter));
+
+        history.remember("import", &files).await.unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+        let state = history.get("import").await.unwrap().unwrap();
+        assert_eq!(state.folder(), Some(folder.as_path()));
+        assert_eq!(state.filter(), Some(&filter));
+        history.open_file("import").await.unwrap();
+
+        // The folder reported by the portal wins over the one of the files.
+        let current_folder = folder.join("current");
+        let mut folder_bytes = current_folder.as_os_str().as_bytes().to_vec();
+        folder_bytes.push(0);
+        let response = HashMap::from([
+            ("uris", Value::from(vec![uri.as_str()])),
+            ("current_folder", Value::from(folder_bytes)),
+        ]);
+        let data = to_bytes(Context::new_dbus(LE, 0), &response).unwrap();
+        let files: SelectedFiles = data.deserialize().unwrap().0;
+        assert_eq!(files.current_folder(), Some(current_folder.as_path()));
+        history.remember("import", &files).await.unwrap();
+        let state = history.get("import").await.unwrap().unwrap();
+        assert_eq!(state.folder(), Some(current_folder.as_path()));
+        assert_eq!(state.filter(), Some(&filter));
+
+        // A corrupted state is ignored.
+        std::fs::write(dir.join("export"), b"garbage").unwrap();
+        assert!(history.get("export").await.unwrap().is_none());
+
+        history.set("import", None).await.unwrap();
+        assert!(history.get("import").await.unwrap().is_none());
+        assert!(history.get("../escape").await.is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
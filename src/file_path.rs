@@ -7,6 +7,8 @@ use std::{
 use serde::{Deserialize, Serialize};
 use zbus::zvariant::Type;
 
+use crate::helpers::paths;
+
 /// A file name represented as a nul-terminated byte array.
 #[derive(Type, Debug, Default, PartialEq)]
 #[zvariant(signature = "ay")]
@@ -19,11 +21,19 @@ impl AsRef<Path> for FilePath {
 }
 
 impl FilePath {
-    pub(crate) fn new<T: AsRef<Path>>(s: T) -> Result<Self, crate::Error> {
-        let c_string = CString::new(s.as_ref().as_os_str().as_bytes())
-            .map_err(|err| crate::Error::NulTerminated(err.nul_position()))?;
+    /// The file name of `path`.
+    ///
+    /// Fails with [`Error::NulTerminated`](crate::Error::NulTerminated) if
+    /// `path` contains a nul byte.
+    pub fn new<T: AsRef<Path>>(path: T) -> Result<Self, crate::Error> {
+        Self::from_null_terminated(paths::null_terminated(path)?)
+    }
 
-        Ok(Self(c_string))
+    fn from_null_terminated(bytes: Vec<u8>) -> Result<Self, crate::Error> {
+        paths::path_from_null_terminated(&bytes)?;
+        CString::from_vec_with_nul(bytes)
+            .map(Self)
+            .map_err(|_| crate::Error::ParseError("Path bytes are not nul-terminated"))
     }
 }
 
@@ -42,10 +52,7 @@ impl<'de> Deserialize<'de> for FilePath {
         D: serde::Deserializer<'de>,
     {
         let bytes = <Vec<u8>>::deserialize(deserializer)?;
-        let c_string = CString::from_vec_with_nul(bytes)
-            .map_err(|_| serde::de::Error::custom("Bytes are not nul-terminated"))?;
-
-        Ok(Self(c_string))
+        Self::from_null_terminated(bytes).map_err(serde::de::Error::custom)
     }
 }
 
@@ -84,5 +91,10 @@ mod tests {
         let decoded: FilePath = Data::new(encoded_bytes, ctxt).deserialize().unwrap().0;
         assert_eq!(decoded, file_path);
         assert_eq!(decoded, file_path_2);
+
+        let encoded_bytes = to_bytes(ctxt, &b"a\0b\0".to_vec()).unwrap().to_vec();
+        assert!(Data::new(encoded_bytes, ctxt)
+            .deserialize::<FilePath>()
+            .is_err());
     }
 }
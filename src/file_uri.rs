@@ -0,0 +1,240 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Serialize, Serializer};
+use url::Url;
+use zbus::zvariant::Type;
+
+use crate::{Error, IntoUrl};
+
+/// A `file://` URI, like the ones of the files selected through the
+/// [`FileChooser`](crate::desktop::file_chooser) portal.
+///
+/// Unlike a [`Url`], it is guaranteed to point to a local path, so that
+/// converting it into a [`PathBuf`] can't fail.
+///
+/// ```rust
+/// use std::path::Path;
+///
+/// use ashpd::FileUri;
+///
+/// let uri: FileUri = "file:///home/user/My%20Pictures/a.png".parse().unwrap();
+/// assert_eq!(uri.path(), Path::new("/home/user/My Pictures/a.png"));
+///
+/// let uri = FileUri::from_path("/tmp/a b.txt").unwrap();
+/// assert_eq!(uri.as_str(), "file:///tmp/a%20b.txt");
+///
+/// assert!("https://example.org/a.png".parse::<FileUri>().is_err());
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, Type)]
+#[zvariant(signature = "s")]
+pub struct FileUri {
+    url: Url,
+    /// The path of `url`, decoded once it is known to be local.
+    path: PathBuf,
+}
+
+crate::helpers::impl_value_conversions!(FileUri as str);
+
+impl FileUri {
+    /// The URI of `path`, relative paths being resolved against the current
+    /// directory.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        Self::try_from(path.as_ref().into_url()?)
+    }
+
+    /// The percent-decoded path the URI points to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The URI as a string.
+    pub fn as_str(&self) -> &str {
+        self.url.as_str()
+    }
+
+    /// The URI as a [`Url`].
+    pub fn as_url(&self) -> &Url {
+        &self.url
+    }
+}
+
+/// The path of a `file://` URI, if it is local.
+///
+/// The host of the URI must either be empty, `localhost`, or the name of the
+/// current host, as some applications write the hostname in the URIs they
+/// hand over, e.g. when dragging files.
+fn local_path(url: &Url) -> Option<PathBuf> {
+    if url.scheme() != "file" {
+        return None;
+    }
+    match url.host_str() {
+        None | Some("") | Some("localhost") => url.to_file_path().ok(),
+        Some(host) if hostname().is_some_and(|name| name.eq_ignore_ascii_case(host)) => {
+            Url::parse(&format!("file://{}", url.path()))
+                .ok()?
+                .to_file_path()
+                .ok()
+        }
+        Some(_) => None,
+    }
+}
+
+fn hostname() -> Option<String> {
+    let mut buffer = [0u8; 256];
+    // SAFETY: The buffer is valid for writes of its length.
+    let ret = unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) };
+    if ret != 0 {
+        return None;
+    }
+    let len = buffer.iter().position(|&b| b == 0)?;
+    String::from_utf8(buffer[..len].to_vec()).ok()
+}
+
+impl TryFrom<Url> for FileUri {
+    type Error = Error;
+
+    fn try_from(url: Url) -> Result<Self, Self::Error> {
+        if url.scheme() != "file" {
+            return Err(Error::ParseError("Not a file:// URI"));
+        }
+        let path = local_path(&url).ok_or(Error::ParseError(
+            "The file:// URI doesn't point to a local path",
+        ))?;
+        Ok(Self { url, path })
+    }
+}
+
+impl TryFrom<&Url> for FileUri {
+    type Error = Error;
+
+    fn try_from(url: &Url) -> Result<Self, Self::Error> {
+        Self::try_from(url.clone())
+    }
+}
+
+impl FromStr for FileUri {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(Url::parse(s)?)
+    }
+}
+
+impl TryFrom<&str> for FileUri {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl TryFrom<&Path> for FileUri {
+    type Error = Error;
+
+    fn try_from(path: &Path) -> Result<Self, Self::Error> {
+        Self::from_path(path)
+    }
+}
+
+impl TryFrom<PathBuf> for FileUri {
+    type Error = Error;
+
+    fn try_from(path: PathBuf) -> Result<Self, Self::Error> {
+        Self::from_path(path)
+    }
+}
+
+impl From<FileUri> for PathBuf {
+    fn from(uri: FileUri) -> Self {
+        uri.path
+    }
+}
+
+impl From<FileUri> for Url {
+    fn from(uri: FileUri) -> Self {
+        uri.url
+    }
+}
+
+impl AsRef<Url> for FileUri {
+    fn as_ref(&self) -> &Url {
+        &self.url
+    }
+}
+
+impl IntoUrl for FileUri {
+    fn into_url(self) -> Result<Url, Error> {
+        Ok(self.url)
+    }
+}
+
+impl IntoUrl for &FileUri {
+    fn into_url(self) -> Result<Url, Error> {
+        Ok(self.url.clone())
+    }
+}
+
+impl fmt::Display for FileUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for FileUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("FileUri").field(&self.as_str()).finish()
+    }
+}
+
+impl Serialize for FileUri {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.url.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for FileUri {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let url = Url::deserialize(deserializer)?;
+        Self::try_from(url).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_uri() {
+        let uri = FileUri::from_str("file:///tmp/%C3%A9t%C3%A9%23.txt").unwrap();
+        assert_eq!(uri.path(), Path::new("/tmp/été#.txt"));
+        assert_eq!(FileUri::from_path(uri.path()).unwrap(), uri);
+
+        let uri = FileUri::from_str("file://localhost/tmp/a").unwrap();
+        assert_eq!(PathBuf::from(uri), Path::new("/tmp/a"));
+
+        if let Some(hostname) = hostname() {
+            let uri = FileUri::from_str(&format!("file://{hostname}/tmp/a")).unwrap();
+            assert_eq!(uri.path(), Path::new("/tmp/a"));
+        }
+
+        assert!(FileUri::from_str("file://some-other-host.invalid/tmp/a").is_err());
+        assert!(FileUri::from_str("https://example.org/a").is_err());
+        assert!(FileUri::from_str("/tmp/a").is_err());
+
+        let relative = FileUri::from_path("a.txt").unwrap();
+        assert_eq!(
+            relative.path(),
+            std::env::current_dir().unwrap().join("a.txt")
+        );
+    }
+}
@@ -1,12 +1,52 @@
 //! The Development interface lets any client, possibly in a sandbox if it has
 //! access to the session helper, spawn a process on the host, outside any
 //! sandbox.
+//!
+//! # Examples
+//!
+//! Run a command on the host and print its output, like `flatpak-spawn --host`
+//! would
+//!
+//! ```rust,no_run
+//! use ashpd::flatpak::host_command;
+//! use futures_util::StreamExt;
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     let output = host_command(["ls", "-l"])
+//!         .cwd("/usr")
+//!         .stdout_stream()
+//!         .await?;
+//!     let (mut lines, exit_status) = output.into_parts();
+//!     while let Some(line) = lines.next().await {
+//!         println!("{}", line?);
+//!     }
+//!     println!("Exited with {}", exit_status.await?);
+//!     Ok(())
+//! }
+//! ```
 
-use std::{collections::HashMap, os::fd::BorrowedFd, path::Path};
+use std::{
+    collections::HashMap,
+    os::{
+        fd::{AsFd, BorrowedFd},
+        unix::process::ExitStatusExt,
+    },
+    path::{Path, PathBuf},
+    process::ExitStatus,
+};
 
+#[cfg(feature = "async-std")]
+use async_net::unix::UnixStream;
 use enumflags2::{bitflags, BitFlags};
-use futures_util::Stream;
+use futures_util::{future::BoxFuture, stream::BoxStream, FutureExt, Stream, StreamExt};
+#[cfg(feature = "async-std")]
+use futures_util::{io::BufReader, AsyncBufReadExt};
 use serde_repr::{Deserialize_repr, Serialize_repr};
+#[cfg(feature = "tokio")]
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    net::UnixStream,
+};
 use zbus::zvariant::{Fd, Type};
 
 use crate::{proxy::Proxy, Error, FilePath};
@@ -24,6 +64,8 @@ pub enum HostCommandFlags {
     WatchBus,
 }
 
+crate::helpers::impl_value_conversions!(HostCommandFlags as u32 { ClearEnv, WatchBus });
+
 /// The Development interface lets any client, possibly in a sandbox if it has
 /// access to the session helper, spawn a process on the host, outside any
 /// sandbox.
@@ -123,3 +165,148 @@ impl<'a> std::ops::Deref for Development<'a> {
         &self.0
     }
 }
+
+/// A command to run on the host with [`Development::host_command`], created
+/// with [`host_command`].
+#[derive(Debug)]
+pub struct HostCommand {
+    argv: Vec<PathBuf>,
+    cwd: Option<PathBuf>,
+    envs: HashMap<String, String>,
+    flags: BitFlags<HostCommandFlags>,
+}
+
+/// Run `argv` on the host, starting with the executable to launch.
+///
+/// The command inherits the standard input and error of the current process,
+/// and runs in its working directory unless [`HostCommand::cwd`] is set.
+pub fn host_command(argv: impl IntoIterator<Item = impl AsRef<Path>>) -> HostCommand {
+    HostCommand {
+        argv: argv
+            .into_iter()
+            .map(|arg| arg.as_ref().to_owned())
+            .collect(),
+        cwd: None,
+        envs: HashMap::new(),
+        flags: BitFlags::empty(),
+    }
+}
+
+impl HostCommand {
+    /// Sets the working directory of the command.
+    #[must_use]
+    pub fn cwd(mut self, cwd: impl AsRef<Path>) -> Self {
+        self.cwd = Some(cwd.as_ref().to_owned());
+        self
+    }
+
+    /// Sets an environment variable of the command.
+    #[must_use]
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.envs.insert(key.to_owned(), value.to_owned());
+        self
+    }
+
+    /// Sets the flags affecting the running of the command.
+    #[must_use]
+    pub fn flags(mut self, flags: impl Into<BitFlags<HostCommandFlags>>) -> Self {
+        self.flags = flags.into();
+        self
+    }
+
+    /// Spawn the command with its standard output piped, to be read line by
+    /// line.
+    pub async fn stdout_stream(self) -> Result<HostCommandOutput, Error> {
+        let cwd = match self.cwd {
+            Some(cwd) => cwd,
+            None => std::env::current_dir()?,
+        };
+        let proxy = Development::new().await?;
+        // Subscribe before spawning, to not miss the exit of a short lived
+        // command.
+        let exited = proxy.receive_spawn_exited().await?;
+
+        let (reader, writer) = UnixStream::pair()?;
+        let stdin = std::io::stdin();
+        let stderr = std::io::stderr();
+        let fds = HashMap::from([(0, stdin.as_fd()), (1, writer.as_fd()), (2, stderr.as_fd())]);
+        let envs = self
+            .envs
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        let pid = proxy
+            .host_command(cwd, &self.argv, fds, envs, self.flags)
+            .await?;
+        // Only the command keeps the write end open, for the lines to end
+        // when it exits.
+        drop(writer);
+
+        #[cfg(feature = "tokio")]
+        let lines =
+            futures_util::stream::unfold(BufReader::new(reader).lines(), |mut lines| async move {
+                lines
+                    .next_line()
+                    .await
+                    .transpose()
+                    .map(|line| (line, lines))
+            })
+            .boxed();
+        #[cfg(feature = "async-std")]
+        let lines = BufReader::new(reader).lines().boxed();
+
+        let exit_status = async move {
+            // Keep the proxy alive while waiting.
+            let _proxy = proxy;
+            let mut exited = std::pin::pin!(exited);
+            while let Some((exited_pid, status)) = exited.next().await {
+                if exited_pid == pid {
+                    return Ok(ExitStatus::from_raw(status as i32));
+                }
+            }
+            Err(Error::NoResponse)
+        }
+        .boxed();
+
+        Ok(HostCommandOutput {
+            pid,
+            lines,
+            exit_status,
+        })
+    }
+}
+
+/// The output of a [`HostCommand`] spawned with
+/// [`HostCommand::stdout_stream`].
+pub struct HostCommandOutput {
+    pid: u32,
+    lines: BoxStream<'static, std::io::Result<String>>,
+    exit_status: BoxFuture<'static, Result<ExitStatus, Error>>,
+}
+
+impl HostCommandOutput {
+    /// The PID of the command, to be used with
+    /// [`Development::host_command_signal`].
+    pub fn pid(&self) -> u32 {
+        self.pid
+    }
+
+    /// The lines of the standard output of the command, and a future
+    /// resolving to its exit status, to be polled concurrently.
+    pub fn into_parts(
+        self,
+    ) -> (
+        BoxStream<'static, std::io::Result<String>>,
+        BoxFuture<'static, Result<ExitStatus, Error>>,
+    ) {
+        (self.lines, self.exit_status)
+    }
+}
+
+impl std::fmt::Debug for HostCommandOutput {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HostCommandOutput")
+            .field("pid", &self.pid)
+            .finish_non_exhaustive()
+    }
+}
@@ -24,6 +24,26 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! The same with [`SpawnRequest`], which validates the request against the
+//! version of the portal.
+//!
+//! ```rust,no_run
+//! use ashpd::flatpak::{Flatpak, SpawnRequest};
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     let proxy = Flatpak::new().await?;
+//!
+//!     SpawnRequest::new(["contrast"])
+//!         .cwd("/")
+//!         .clear_env(true)
+//!         .no_network(true)
+//!         .send(&proxy)
+//!         .await?;
+//!
+//!     Ok(())
+//! }
+//! ```
 
 use std::{
     collections::HashMap,
@@ -57,6 +77,8 @@ pub enum SandboxFlags {
     AccessibilityBusAccess,
 }
 
+crate::helpers::impl_value_conversions!(SandboxFlags as u32 { DisplayAccess, SoundAccess, GpuAccess, SessionBusAccess, AccessibilityBusAccess });
+
 #[bitflags]
 #[derive(Serialize_repr, Deserialize_repr, PartialEq, Eq, Copy, Clone, Debug, Type)]
 #[repr(u32)]
@@ -92,6 +114,8 @@ pub enum SpawnFlags {
     EmptyApp,
 }
 
+crate::helpers::impl_value_conversions!(SpawnFlags as u32 { ClearEnv, LatestVersion, Sandbox, NoNetwork, WatchBus, ExposePids, NotifyStart, SharePids, EmptyApp });
+
 #[bitflags]
 #[derive(Serialize_repr, Deserialize_repr, PartialEq, Eq, Copy, Clone, Debug, Type)]
 #[repr(u32)]
@@ -101,6 +125,8 @@ pub enum SupportsFlags {
     ExposePids,
 }
 
+crate::helpers::impl_value_conversions!(SupportsFlags as u32 { ExposePids });
+
 #[derive(SerializeDict, Type, Debug, Default)]
 /// Specified options for a [`Flatpak::spawn`] request.
 #[zvariant(signature = "dict")]
@@ -390,6 +416,12 @@ impl<'a> std::ops::Deref for Flatpak<'a> {
 mod update_monitor;
 pub use update_monitor::{UpdateInfo, UpdateMonitor, UpdateProgress, UpdateStatus};
 
+/// Build the requests of [`Flatpak::spawn`].
+mod spawn;
+pub use spawn::SpawnRequest;
+
 /// Provide for a way to execute processes outside of the sandbox
 mod development;
-pub use development::{Development, HostCommandFlags};
+pub use development::{
+    host_command, Development, HostCommand, HostCommandFlags, HostCommandOutput,
+};
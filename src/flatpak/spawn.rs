@@ -0,0 +1,385 @@
+use std::{
+    collections::HashMap,
+    os::fd::{BorrowedFd, OwnedFd},
+    path::{Path, PathBuf},
+};
+
+use enumflags2::BitFlags;
+
+use super::{Flatpak, SandboxFlags, SpawnFlags, SpawnOptions};
+use crate::{Error, PortalError};
+
+/// The version of the Flatpak portal introducing `flag`.
+fn flag_version(flag: SpawnFlags) -> u32 {
+    match flag {
+        SpawnFlags::ClearEnv
+        | SpawnFlags::LatestVersion
+        | SpawnFlags::Sandbox
+        | SpawnFlags::NoNetwork
+        | SpawnFlags::WatchBus => 1,
+        SpawnFlags::ExposePids => 3,
+        SpawnFlags::NotifyStart => 4,
+        SpawnFlags::SharePids => 5,
+        SpawnFlags::EmptyApp => 6,
+    }
+}
+
+impl SpawnOptions {
+    /// The version of the Flatpak portal required by the options that are
+    /// set.
+    fn required_version(&self) -> u32 {
+        [
+            (self.sandbox_expose_fd.is_some(), 3),
+            (self.sandbox_expose_fd_ro.is_some(), 3),
+            (self.unset_env.is_some(), 5),
+            (self.usr_fd.is_some(), 6),
+            (self.app_fd.is_some(), 6),
+        ]
+        .into_iter()
+        .filter_map(|(is_set, version)| is_set.then_some(version))
+        .max()
+        .unwrap_or(1)
+    }
+}
+
+/// A [builder-pattern] type to start a new instance of the application with
+/// [`Flatpak::spawn`], optionally in a tighter sandbox.
+///
+/// Unlike the raw method, the request is validated before being sent: the
+/// names of the exposed files and of the environment variables are checked,
+/// and the flags or options the portal doesn't implement make it fail with
+/// [`Error::RequiresVersion`] instead of being ignored or rejected by it.
+///
+/// ```rust,no_run
+/// use ashpd::flatpak::{Flatpak, SandboxFlags, SpawnRequest};
+///
+/// async fn run() -> ashpd::Result<()> {
+///     let proxy = Flatpak::new().await?;
+///     let pid = SpawnRequest::new(["contrast", "--verbose"])
+///         .cwd("/var/data")
+///         .clear_env(true)
+///         .env("LANG", "C.UTF-8")
+///         .unset_env("G_MESSAGES_DEBUG")
+///         .sandbox(true)
+///         .no_network(true)
+///         .sandbox_flags(SandboxFlags::DisplayAccess | SandboxFlags::GpuAccess)
+///         .expose_ro("shared-data")
+///         .send(&proxy)
+///         .await?;
+///     println!("Started {pid}");
+///     Ok(())
+/// }
+/// ```
+///
+/// [builder-pattern]: https://doc.rust-lang.org/1.0.0/style/ownership/builders.html
+#[derive(Debug)]
+#[doc(alias = "xdp_portal_spawn")]
+pub struct SpawnRequest<'a> {
+    argv: Vec<PathBuf>,
+    cwd: Option<PathBuf>,
+    fds: HashMap<u32, BorrowedFd<'a>>,
+    envs: HashMap<String, String>,
+    flags: BitFlags<SpawnFlags>,
+    options: SpawnOptions,
+}
+
+impl<'a> SpawnRequest<'a> {
+    /// Run `argv`, starting with the executable to launch.
+    pub fn new(argv: impl IntoIterator<Item = impl AsRef<Path>>) -> Self {
+        Self {
+            argv: argv
+                .into_iter()
+                .map(|arg| arg.as_ref().to_owned())
+                .collect(),
+            cwd: None,
+            fds: HashMap::new(),
+            envs: HashMap::new(),
+            flags: BitFlags::empty(),
+            options: SpawnOptions::default(),
+        }
+    }
+
+    /// Sets the working directory of the new process, the current one by
+    /// default.
+    #[must_use]
+    pub fn cwd(mut self, cwd: impl AsRef<Path>) -> Self {
+        self.cwd = Some(cwd.as_ref().to_owned());
+        self
+    }
+
+    /// Sets the environment variable `key` to `value` in the new process.
+    #[must_use]
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.envs.insert(key.into(), value.into());
+        self
+    }
+
+    /// Sets the environment variables of `envs` in the new process.
+    #[must_use]
+    pub fn envs<K: Into<String>, V: Into<String>>(
+        mut self,
+        envs: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        self.envs.extend(
+            envs.into_iter()
+                .map(|(key, value)| (key.into(), value.into())),
+        );
+        self
+    }
+
+    /// Removes the environment variable `key` from the new process.
+    ///
+    /// Requires version 5 of the portal.
+    #[must_use]
+    pub fn unset_env(mut self, key: impl Into<String>) -> Self {
+        self.options
+            .unset_env
+            .get_or_insert_with(Vec::new)
+            .push(key.into());
+        self
+    }
+
+    /// Sets whether to start from an empty environment, only containing the
+    /// variables set with [`Self::env`].
+    #[must_use]
+    pub fn clear_env(self, clear_env: bool) -> Self {
+        self.flag(SpawnFlags::ClearEnv, clear_env)
+    }
+
+    /// Passes `fd` to the new process as the file descriptor `target`.
+    #[must_use]
+    pub fn fd(mut self, target: u32, fd: BorrowedFd<'a>) -> Self {
+        self.fds.insert(target, fd);
+        self
+    }
+
+    /// Adds `flags` to the flags of the request.
+    #[must_use]
+    pub fn flags(mut self, flags: impl Into<BitFlags<SpawnFlags>>) -> Self {
+        self.flags |= flags.into();
+        self
+    }
+
+    /// Sets whether to spawn the latest installed version of the application.
+    #[must_use]
+    pub fn latest_version(self, latest_version: bool) -> Self {
+        self.flag(SpawnFlags::LatestVersion, latest_version)
+    }
+
+    /// Sets whether to spawn in a new, tighter, sandbox.
+    #[must_use]
+    pub fn sandbox(self, sandbox: bool) -> Self {
+        self.flag(SpawnFlags::Sandbox, sandbox)
+    }
+
+    /// Sets whether to spawn without network access.
+    #[must_use]
+    pub fn no_network(self, no_network: bool) -> Self {
+        self.flag(SpawnFlags::NoNetwork, no_network)
+    }
+
+    /// Sets whether to kill the new process when the caller disappears from
+    /// the session bus.
+    #[must_use]
+    pub fn watch_bus(self, watch_bus: bool) -> Self {
+        self.flag(SpawnFlags::WatchBus, watch_bus)
+    }
+
+    /// Sets whether the process IDs of the new sandbox are visible in the
+    /// caller's one.
+    ///
+    /// Requires version 3 of the portal and a Flatpak not using setuid, see
+    /// [`Flatpak::supports`].
+    #[must_use]
+    pub fn expose_pids(self, expose_pids: bool) -> Self {
+        self.flag(SpawnFlags::ExposePids, expose_pids)
+    }
+
+    /// Sets whether the caller and the new sandbox see the process IDs of
+    /// each other.
+    ///
+    /// Requires version 5 of the portal.
+    #[must_use]
+    pub fn share_pids(self, share_pids: bool) -> Self {
+        self.flag(SpawnFlags::SharePids, share_pids)
+    }
+
+    /// Sets the permissions of the new sandbox, see [`Self::sandbox`].
+    #[must_use]
+    pub fn sandbox_flags(mut self, sandbox_flags: impl Into<BitFlags<SandboxFlags>>) -> Self {
+        self.options.sandbox_flags = Some(sandbox_flags.into());
+        self
+    }
+
+    /// Exposes the file `name` of the application's sandbox to the new
+    /// sandbox, for reading and writing.
+    ///
+    /// The file is looked up in the application's data directory, `name` must
+    /// not be a path.
+    #[must_use]
+    pub fn expose(mut self, name: impl Into<String>) -> Self {
+        self.options
+            .sandbox_expose
+            .get_or_insert_with(Vec::new)
+            .push(name.into());
+        self
+    }
+
+    /// Exposes the file `name` of the application's sandbox to the new
+    /// sandbox, read-only, see [`Self::expose`].
+    #[must_use]
+    pub fn expose_ro(mut self, name: impl Into<String>) -> Self {
+        self.options
+            .sandbox_expose_ro
+            .get_or_insert_with(Vec::new)
+            .push(name.into());
+        self
+    }
+
+    /// Exposes the file opened as `fd` to the new sandbox, for reading and
+    /// writing.
+    ///
+    /// Requires version 3 of the portal.
+    #[must_use]
+    pub fn expose_fd(mut self, fd: OwnedFd) -> Self {
+        self.options
+            .sandbox_expose_fd
+            .get_or_insert_with(Vec::new)
+            .push(fd.into());
+        self
+    }
+
+    /// Exposes the file opened as `fd` to the new sandbox, read-only.
+    ///
+    /// Requires version 3 of the portal.
+    #[must_use]
+    pub fn expose_fd_ro(mut self, fd: OwnedFd) -> Self {
+        self.options
+            .sandbox_expose_fd_ro
+            .get_or_insert_with(Vec::new)
+            .push(fd.into());
+        self
+    }
+
+    /// Uses the directory opened as `fd` as `/usr` in the new sandbox.
+    ///
+    /// Requires version 6 of the portal.
+    #[must_use]
+    pub fn usr_fd(mut self, fd: OwnedFd) -> Self {
+        self.options.usr_fd = Some(fd.into());
+        self
+    }
+
+    /// Uses the directory opened as `fd` as `/app` in the new sandbox.
+    ///
+    /// Requires version 6 of the portal.
+    #[must_use]
+    pub fn app_fd(mut self, fd: OwnedFd) -> Self {
+        self.options.app_fd = Some(fd.into());
+        self
+    }
+
+    fn flag(mut self, flag: SpawnFlags, enabled: bool) -> Self {
+        self.flags.set(flag, enabled);
+        self
+    }
+
+    /// Check the request can be sent to `version` of the portal.
+    fn validate(&self, version: u32) -> Result<(), Error> {
+        if self.argv.is_empty() {
+            return Err(PortalError::InvalidArgument("No command to spawn".to_owned()).into());
+        }
+        let invalid_name = self
+            .options
+            .sandbox_expose
+            .iter()
+            .chain(&self.options.sandbox_expose_ro)
+            .flatten()
+            .find(|name| name.is_empty() || name.contains('/') || *name == "." || *name == "..");
+        if let Some(name) = invalid_name {
+            return Err(PortalError::InvalidArgument(format!(
+                "Invalid exposed file name `{name}`"
+            ))
+            .into());
+        }
+        let invalid_env = self
+            .envs
+            .keys()
+            .chain(self.options.unset_env.iter().flatten())
+            .find(|key| key.is_empty() || key.contains(['=', '\0']));
+        if let Some(key) = invalid_env {
+            return Err(PortalError::InvalidArgument(format!(
+                "Invalid environment variable `{key}`"
+            ))
+            .into());
+        }
+
+        let flags_version = self.flags.iter().map(flag_version).max().unwrap_or(1);
+        let required = flags_version.max(self.options.required_version());
+        if required > version {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "Spawn requires version {required} of the Flatpak portal, {version} is available"
+            );
+            return Err(Error::RequiresVersion(required, version));
+        }
+        Ok(())
+    }
+
+    /// Send the request with `proxy`, returning the PID of the new process.
+    pub async fn send(self, proxy: &Flatpak<'_>) -> Result<u32, Error> {
+        self.validate(proxy.0.version())?;
+        let cwd = match self.cwd {
+            Some(cwd) => cwd,
+            None => std::env::current_dir()?,
+        };
+        let envs = self
+            .envs
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+            .collect();
+        proxy
+            .spawn(cwd, &self.argv, self.fds, envs, self.flags, self.options)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation() {
+        let request = SpawnRequest::new(["app"]).env("LANG", "C").expose("data");
+        assert!(request.validate(1).is_ok());
+
+        let request = SpawnRequest::new(["app"]).share_pids(true);
+        assert!(matches!(
+            request.validate(4),
+            Err(Error::RequiresVersion(5, 4))
+        ));
+        assert!(request.validate(5).is_ok());
+
+        let request = SpawnRequest::new(["app"])
+            .expose_pids(true)
+            .unset_env("HOME");
+        assert!(matches!(
+            request.validate(3),
+            Err(Error::RequiresVersion(5, 3))
+        ));
+        let request = request.expose_pids(false).share_pids(false);
+        assert!(request.validate(5).is_ok());
+
+        for request in [
+            SpawnRequest::new(Vec::<&str>::new()),
+            SpawnRequest::new(["app"]).expose_ro("../secrets"),
+            SpawnRequest::new(["app"]).env("A=B", "C"),
+            SpawnRequest::new(["app"]).unset_env(""),
+        ] {
+            assert!(matches!(
+                request.validate(6),
+                Err(Error::Portal(PortalError::InvalidArgument(_)))
+            ));
+        }
+    }
+}
@@ -26,9 +26,11 @@
 //! }
 //! ```
 
+use std::collections::HashMap;
+
 use futures_util::Stream;
 use serde_repr::{Deserialize_repr, Serialize_repr};
-use zbus::zvariant::{DeserializeDict, ObjectPath, SerializeDict, Type};
+use zbus::zvariant::{ObjectPath, OwnedValue, SerializeDict, Type};
 
 use crate::{proxy::Proxy, Error, WindowIdentifier};
 
@@ -39,18 +41,23 @@ use crate::{proxy::Proxy, Error, WindowIdentifier};
 #[zvariant(signature = "dict")]
 struct UpdateOptions {}
 
-#[derive(DeserializeDict, Type, Debug)]
+#[derive(Type, Debug)]
 /// A response containing the update information when an update is available.
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct UpdateInfo {
-    #[zvariant(rename = "running-commit")]
     running_commit: String,
-    #[zvariant(rename = "local-commit")]
     local_commit: String,
-    #[zvariant(rename = "remote-commit")]
     remote_commit: String,
+    extra: HashMap<String, OwnedValue>,
 }
 
+crate::helpers::impl_dict_with_extra!(UpdateInfo {
+    running_commit [required] => "running-commit",
+    local_commit [required] => "local-commit",
+    remote_commit [required] => "remote-commit",
+});
+
 impl UpdateInfo {
     /// The currently running OSTree commit.
     pub fn running_commit(&self) -> &str {
@@ -66,6 +73,12 @@ impl UpdateInfo {
     pub fn remote_commit(&self) -> &str {
         &self.remote_commit
     }
+
+    /// The entries of the update information unknown to this version of the
+    /// crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
 #[cfg_attr(feature = "glib", derive(glib::Enum))]
@@ -88,9 +101,12 @@ pub enum UpdateStatus {
     Failed = 3,
 }
 
-#[derive(DeserializeDict, Type, Debug)]
+crate::helpers::impl_value_conversions!(UpdateStatus as u32 { Running, Empty, Done, Failed });
+
+#[derive(Type, Debug)]
 /// A response of the update progress signal.
 #[zvariant(signature = "dict")]
+#[non_exhaustive]
 pub struct UpdateProgress {
     /// The number of operations that the update consists of.
     pub n_ops: Option<u32>,
@@ -105,6 +121,23 @@ pub struct UpdateProgress {
     pub error: Option<String>,
     /// The error message, sent when status is `UpdateStatus::Failed`.
     pub error_message: Option<String>,
+    extra: HashMap<String, OwnedValue>,
+}
+
+crate::helpers::impl_dict_with_extra!(UpdateProgress {
+    n_ops => "n_ops",
+    op => "op",
+    progress => "progress",
+    status => "status",
+    error => "error",
+    error_message => "error_message",
+});
+
+impl UpdateProgress {
+    /// The entries of the progress unknown to this version of the crate.
+    pub fn extra(&self) -> &HashMap<String, OwnedValue> {
+        &self.extra
+    }
 }
 
 /// The interface exposes some interactions with Flatpak on the host to the
@@ -1,3 +1,5 @@
+pub mod paths;
+
 #[cfg(feature = "async-std")]
 use async_fs::File;
 #[cfg(feature = "async-std")]
@@ -19,15 +21,104 @@ pub(crate) async fn is_flatpak() -> bool {
 pub(crate) async fn is_snap() -> bool {
     let pid = std::process::id();
     let path = format!("/proc/{pid}/cgroup");
-    let mut file = match File::open(path).await {
-        Ok(file) => file,
-        Err(_) => return false,
-    };
+    match read_to_string(path).await {
+        Ok(buffer) => cgroup_v2_is_snap(&buffer),
+        Err(_) => false,
+    }
+}
 
+/// Sleeps for `duration`, with the timer of `async-io` as the one of tokio
+/// panics if the runtime of the application was built without `enable_time`.
+#[cfg(feature = "network_monitor")]
+pub(crate) async fn sleep(duration: std::time::Duration) {
+    async_io::Timer::after(duration).await;
+}
+
+pub(crate) async fn read_to_string(path: impl AsRef<std::path::Path>) -> std::io::Result<String> {
+    let mut file = File::open(path.as_ref()).await?;
     let mut buffer = String::new();
-    match file.read_to_string(&mut buffer).await {
-        Ok(_) => cgroup_v2_is_snap(&buffer),
-        Err(_) => false,
+    file.read_to_string(&mut buffer).await?;
+    Ok(buffer)
+}
+
+/// Run the blocking `f` off the executor.
+#[cfg(any(
+    feature = "fallback",
+    feature = "file_chooser",
+    feature = "restore_tokens"
+))]
+pub(crate) async fn unblock<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    #[cfg(feature = "async-std")]
+    {
+        blocking::unblock(f).await
+    }
+    #[cfg(feature = "tokio")]
+    {
+        tokio::task::spawn_blocking(f)
+            .await
+            .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))
+    }
+}
+
+#[cfg(feature = "screenshot")]
+pub(crate) async fn copy_file(
+    source: impl AsRef<std::path::Path>,
+    destination: impl AsRef<std::path::Path>,
+) -> std::io::Result<u64> {
+    #[cfg(feature = "async-std")]
+    {
+        async_fs::copy(source, destination).await
+    }
+    #[cfg(feature = "tokio")]
+    {
+        tokio::fs::copy(source, destination).await
+    }
+}
+
+/// Copy the file at `source` into `destination`, an open file.
+#[cfg(feature = "screenshot")]
+pub(crate) async fn copy_into(
+    source: impl AsRef<std::path::Path>,
+    destination: std::fs::File,
+) -> std::io::Result<u64> {
+    let mut source = File::open(source.as_ref()).await?;
+    #[cfg(feature = "async-std")]
+    {
+        use futures_util::AsyncWriteExt;
+
+        let mut destination = File::from(destination);
+        let copied = futures_util::io::copy(&mut source, &mut destination).await?;
+        destination.flush().await?;
+        Ok(copied)
+    }
+    #[cfg(feature = "tokio")]
+    {
+        use tokio::io::AsyncWriteExt;
+
+        let mut destination = File::from_std(destination);
+        let copied = tokio::io::copy(&mut source, &mut destination).await?;
+        destination.flush().await?;
+        Ok(copied)
+    }
+}
+
+#[cfg(any(feature = "fallback", feature = "logind"))]
+pub(crate) fn is_portal_missing(err: &crate::Error) -> bool {
+    match err {
+        crate::Error::PortalNotFound(_) => true,
+        crate::Error::Zbus(zbus::Error::MethodError(name, _, _)) => matches!(
+            name.as_str(),
+            "org.freedesktop.DBus.Error.ServiceUnknown"
+                | "org.freedesktop.DBus.Error.UnknownInterface"
+                | "org.freedesktop.DBus.Error.UnknownMethod"
+        ),
+        crate::Error::Zbus(zbus::Error::FDO(err)) => matches!(
+            **err,
+            zbus::fdo::Error::ServiceUnknown(_)
+                | zbus::fdo::Error::UnknownInterface(_)
+                | zbus::fdo::Error::UnknownMethod(_)
+        ),
+        _ => false,
     }
 }
 
@@ -51,10 +142,529 @@ fn cgroup_v2_is_snap(cgroups: &str) -> bool {
         .any(|x| x.unwrap_or(false))
 }
 
+/// Implements the [`zvariant::Value`] and [`zvariant::OwnedValue`]
+/// conversions for an enum, following its D-Bus wire format.
+///
+/// Integer backed enums list their variants along with the `repr` type, the
+/// discriminant being the value sent over the wire. String backed enums map
+/// each variant to its string representation.
+///
+/// String newtypes, e.g. [`AppID`](crate::AppID), are converted with their
+/// `Display` and `TryFrom<&str>` implementations.
+macro_rules! impl_value_conversions {
+    ($ty:ident as str) => {
+        impl From<$ty> for ::zbus::zvariant::Value<'_> {
+            fn from(value: $ty) -> Self {
+                value.to_string().into()
+            }
+        }
+
+        impl From<$ty> for ::zbus::zvariant::OwnedValue {
+            fn from(value: $ty) -> Self {
+                ::zbus::zvariant::Str::from(value.to_string()).into()
+            }
+        }
+
+        impl TryFrom<::zbus::zvariant::Value<'_>> for $ty {
+            type Error = $crate::Error;
+
+            fn try_from(value: ::zbus::zvariant::Value<'_>) -> Result<Self, Self::Error> {
+                <$ty>::try_from(<&str>::try_from(&value)?).map_err(|_| {
+                    $crate::Error::ParseError(concat!(
+                        "Failed to parse ",
+                        stringify!($ty),
+                        ", invalid value"
+                    ))
+                })
+            }
+        }
+
+        impl TryFrom<::zbus::zvariant::OwnedValue> for $ty {
+            type Error = $crate::Error;
+
+            fn try_from(value: ::zbus::zvariant::OwnedValue) -> Result<Self, Self::Error> {
+                Self::try_from(::zbus::zvariant::Value::from(value))
+            }
+        }
+    };
+    ($ty:ident as $repr:ty { $($variant:ident),+ $(,)? }) => {
+        $crate::helpers::impl_value_conversions!(@table $ty as $repr, $repr, ::std::convert::identity {
+            $($variant => $ty::$variant as $repr),+
+        });
+    };
+    ($ty:ident { $($variant:ident => $wire:literal),+ $(,)? }) => {
+        $crate::helpers::impl_value_conversions!(@table $ty as &'static str, &str, ::zbus::zvariant::Str::from_static {
+            $($variant => $wire),+
+        });
+    };
+    // Both directions are generated from the same table, the exhaustive match
+    // of `to_wire` making sure no variant is left out.
+    (@table $ty:ident as $wire_ty:ty, $read_ty:ty, $owned:path { $($variant:ident => $wire:expr),+ }) => {
+        const _: () = {
+            fn to_wire(value: $ty) -> $wire_ty {
+                match value {
+                    $($ty::$variant => $wire,)+
+                }
+            }
+
+            fn from_wire(wire: $read_ty) -> Option<$ty> {
+                $(
+                    if wire == $wire {
+                        return Some($ty::$variant);
+                    }
+                )+
+                None
+            }
+
+            impl From<$ty> for ::zbus::zvariant::Value<'_> {
+                fn from(value: $ty) -> Self {
+                    to_wire(value).into()
+                }
+            }
+
+            impl From<$ty> for ::zbus::zvariant::OwnedValue {
+                fn from(value: $ty) -> Self {
+                    $owned(to_wire(value)).into()
+                }
+            }
+
+            impl TryFrom<::zbus::zvariant::Value<'_>> for $ty {
+                type Error = $crate::Error;
+
+                fn try_from(value: ::zbus::zvariant::Value<'_>) -> Result<Self, Self::Error> {
+                    from_wire(<$read_ty>::try_from(&value)?).ok_or($crate::Error::ParseError(
+                        concat!("Failed to parse ", stringify!($ty), ", invalid value"),
+                    ))
+                }
+            }
+
+            impl TryFrom<::zbus::zvariant::OwnedValue> for $ty {
+                type Error = $crate::Error;
+
+                fn try_from(value: ::zbus::zvariant::OwnedValue) -> Result<Self, Self::Error> {
+                    Self::try_from(::zbus::zvariant::Value::from(value))
+                }
+            }
+        };
+    };
+}
+
+pub(crate) use impl_value_conversions;
+
+/// Decodes the value of a known key of a dictionary.
+///
+/// The value is read as a variant first so that one of an unexpected type is
+/// skipped without leaving the rest of the dictionary undecodable, as the
+/// specification requires unknown or invalid options to be ignored.
+pub(crate) fn dict_value<T>(_key: &str, value: &zbus::zvariant::OwnedValue) -> Option<T>
+where
+    T: zbus::zvariant::Type + for<'de> serde::Deserialize<'de>,
+{
+    use zbus::zvariant::{serialized::Context, to_bytes, DeserializeValue, LE};
+
+    let decoded = to_bytes(Context::new_dbus(LE, 0), value).and_then(|data| {
+        data.deserialize::<DeserializeValue<T>>()
+            .map(|(value, _)| value.0)
+    });
+    decoded
+        .map_err(|_err| {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(
+                "Ignoring `{_key}`, expected a value of type `{}`, got `{}`: {_err}",
+                T::signature(),
+                value.value_signature()
+            );
+        })
+        .ok()
+}
+
+/// Implements `Deserialize`, and `Serialize` if asked to, for a struct sent
+/// over the wire as an `a{sv}` dictionary.
+///
+/// Unlike the [`zvariant::DeserializeDict`] derive, the entries with an unknown
+/// key are kept in the `extra: HashMap<String, OwnedValue>` field of the
+/// struct rather than dropped, so the keys added to a portal are not lost
+/// on older versions. The values of an unexpected type are skipped, see
+/// [`dict_value`]. Fields are optional unless marked `[required]`.
+///
+/// `impl_dict_with_extra!(Serialize for T { .. })` only implements
+/// `Serialize`, for the options only sent to the portals.
+macro_rules! impl_dict_with_extra {
+    ($ty:ident { $($field:ident $([$required:ident])? => $key:literal),* $(,)? }) => {
+        $crate::helpers::impl_dict_with_extra!(@deserialize $ty { $($field $([$required])? => $key),* });
+    };
+    ($ty:ident with Serialize { $($field:ident $([$required:ident])? => $key:literal),* $(,)? }) => {
+        $crate::helpers::impl_dict_with_extra!(@deserialize $ty { $($field $([$required])? => $key),* });
+        $crate::helpers::impl_dict_with_extra!(@serialize $ty { $($field $([$required])? => $key),* });
+    };
+    (Serialize for $ty:ident { $($field:ident $([$required:ident])? => $key:literal),* $(,)? }) => {
+        $crate::helpers::impl_dict_with_extra!(@serialize $ty { $($field $([$required])? => $key),* });
+    };
+    (@serialize $ty:ident { $($field:ident $([$required:ident])? => $key:literal),* }) => {
+        impl ::serde::Serialize for $ty {
+            fn serialize<S>(&self, serializer: S) -> ::std::result::Result<S::Ok, S::Error>
+            where
+                S: ::serde::Serializer,
+            {
+                use ::serde::ser::SerializeMap;
+
+                let mut map = serializer.serialize_map(None)?;
+                $($crate::helpers::impl_dict_with_extra!(@entry map $key, &self.$field $(, $required)?);)*
+                for (key, value) in &self.extra {
+                    // The known entries can't be overridden.
+                    if ![$($key),*].contains(&key.as_str()) {
+                        map.serialize_entry(key, value)?;
+                    }
+                }
+                map.end()
+            }
+        }
+    };
+    (@deserialize $ty:ident { $($field:ident $([$required:ident])? => $key:literal),* }) => {
+        impl<'de> ::serde::Deserialize<'de> for $ty {
+            fn deserialize<D>(deserializer: D) -> ::std::result::Result<Self, D::Error>
+            where
+                D: ::serde::Deserializer<'de>,
+            {
+                struct Visitor;
+
+                impl<'de> ::serde::de::Visitor<'de> for Visitor {
+                    type Value = $ty;
+
+                    fn expecting(&self, formatter: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                        formatter.write_str("a dictionary")
+                    }
+
+                    fn visit_map<M>(self, mut access: M) -> ::std::result::Result<$ty, M::Error>
+                    where
+                        M: ::serde::de::MapAccess<'de>,
+                    {
+                        $(let mut $field = None;)*
+                        let mut extra = ::std::collections::HashMap::new();
+                        while let Some(key) = access.next_key::<String>()? {
+                            match key.as_str() {
+                                $($key => {
+                                    let value = access.next_value::<::zbus::zvariant::OwnedValue>()?;
+                                    $field = $crate::helpers::dict_value(&key, &value);
+                                })*
+                                _ => {
+                                    let value = access.next_value::<::zbus::zvariant::OwnedValue>()?;
+                                    extra.insert(key, value);
+                                }
+                            }
+                        }
+                        Ok($ty {
+                            $($field: $crate::helpers::impl_dict_with_extra!(@field M, $field, $key $(, $required)?),)*
+                            extra,
+                        })
+                    }
+                }
+
+                deserializer.deserialize_map(Visitor)
+            }
+        }
+    };
+    (@field $m:ident, $field:ident, $key:literal) => {
+        $field
+    };
+    (@field $m:ident, $field:ident, $key:literal, required) => {
+        $field.ok_or_else(|| <$m::Error as ::serde::de::Error>::missing_field($key))?
+    };
+    (@entry $map:ident $key:literal, $value:expr) => {
+        if let Some(value) = $value {
+            $map.serialize_entry($key, &::zbus::zvariant::SerializeValue(value))?;
+        }
+    };
+    (@entry $map:ident $key:literal, $value:expr, required) => {
+        $map.serialize_entry($key, &::zbus::zvariant::SerializeValue($value))?;
+    };
+}
+
+pub(crate) use impl_dict_with_extra;
+
+/// Inserts the entry `key`, unknown to this version of the crate, in the
+/// `extra` entries of a dictionary, e.g. a vendor extension of the options of a
+/// request.
+#[cfg(any(
+    feature = "account",
+    feature = "background",
+    feature = "email",
+    feature = "file_chooser",
+    feature = "open_uri",
+    feature = "screenshot",
+    feature = "wallpaper",
+))]
+pub(crate) fn insert_extra<'v>(
+    extra: &mut std::collections::HashMap<String, zbus::zvariant::OwnedValue>,
+    key: impl Into<String>,
+    value: impl Into<zbus::zvariant::Value<'v>>,
+) {
+    let key = key.into();
+    // Only fails to duplicate a file descriptor.
+    match zbus::zvariant::OwnedValue::try_from(value.into()) {
+        Ok(value) => {
+            extra.insert(key, value);
+        }
+        Err(_err) => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("Ignoring the extra entry `{key}`: {_err}");
+        }
+    }
+}
+
+/// Overwrites `bytes` with zeroes, in a way the compiler can't optimize out
+/// even if they are never read again.
+#[cfg(any(
+    feature = "dynamic_launcher",
+    feature = "screencast",
+    feature = "secret",
+    feature = "secret_store",
+))]
+pub(crate) fn zeroize(bytes: &mut [u8]) {
+    for byte in bytes.iter_mut() {
+        // SAFETY: `byte` is a valid and aligned reference.
+        unsafe { std::ptr::write_volatile(byte, 0) };
+    }
+    std::sync::atomic::compiler_fence(std::sync::atomic::Ordering::SeqCst);
+}
+
+/// Formats the wrapped user data, e.g. a path or an email address, as
+/// `<redacted>` unless the `debug_full` feature is enabled, for the options and
+/// responses logged with `{:?}` to not leak personal data.
+#[cfg(any(
+    feature = "account",
+    feature = "email",
+    feature = "file_chooser",
+    feature = "recorder",
+))]
+pub(crate) struct Redacted<'a, T: ?Sized>(pub &'a T);
+
+#[cfg(any(
+    feature = "account",
+    feature = "email",
+    feature = "file_chooser",
+    feature = "recorder",
+))]
+impl<T: std::fmt::Debug + ?Sized> std::fmt::Debug for Redacted<'_, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if cfg!(feature = "debug_full") {
+            self.0.fmt(f)
+        } else {
+            f.write_str("<redacted>")
+        }
+    }
+}
+
+/// A string granting access to something, e.g. a restore token, that is left
+/// out of the `Debug` output and zeroed when dropped.
+#[cfg(any(
+    feature = "dynamic_launcher",
+    feature = "screencast",
+    feature = "secret"
+))]
+#[derive(PartialEq, Eq, serde::Serialize, serde::Deserialize, zbus::zvariant::Type)]
+#[serde(transparent)]
+pub(crate) struct SecretString(String);
+
+#[cfg(any(
+    feature = "dynamic_launcher",
+    feature = "screencast",
+    feature = "secret"
+))]
+impl SecretString {
+    #[cfg(any(feature = "dynamic_launcher", feature = "screencast"))]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(any(
+    feature = "dynamic_launcher",
+    feature = "screencast",
+    feature = "secret"
+))]
+impl From<&str> for SecretString {
+    fn from(value: &str) -> Self {
+        Self(value.to_owned())
+    }
+}
+
+#[cfg(any(
+    feature = "dynamic_launcher",
+    feature = "screencast",
+    feature = "secret"
+))]
+impl std::fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("<redacted>")
+    }
+}
+
+#[cfg(any(
+    feature = "dynamic_launcher",
+    feature = "screencast",
+    feature = "secret"
+))]
+impl Drop for SecretString {
+    fn drop(&mut self) {
+        // SAFETY: Zeroes are valid UTF-8.
+        zeroize(unsafe { self.0.as_bytes_mut() });
+    }
+}
+
+/// A pair of peer-to-peer connections, the first one acting as the server, to
+/// serve fake portals to the tests without a bus.
+#[cfg(all(test, feature = "tokio"))]
+pub(crate) async fn p2p_pair() -> (zbus::Connection, zbus::Connection) {
+    let guid = zbus::Guid::generate();
+    let (server, client) = tokio::net::UnixStream::pair().unwrap();
+    futures_util::try_join!(
+        zbus::connection::Builder::unix_stream(server)
+            .server(guid)
+            .unwrap()
+            .p2p()
+            .build(),
+        zbus::connection::Builder::unix_stream(client).p2p().build(),
+    )
+    .unwrap()
+}
+
+/// A backend named `org.example.Portal` on a peer-to-peer connection, along
+/// with the connection of its client.
+#[cfg(all(test, feature = "tokio", feature = "backend"))]
+pub(crate) async fn p2p_backend() -> (crate::backend::Backend, zbus::Connection) {
+    let (server, client) = p2p_pair().await;
+    let backend = crate::backend::Backend::new_with_connection(server, "org.example.Portal")
+        .await
+        .unwrap();
+    (backend, client)
+}
+
+/// Serve the interface built by `iface` from the connection of a
+/// [`p2p_backend`], along with a proxy of the interface on the client side.
+#[cfg(all(test, feature = "tokio", feature = "backend"))]
+pub(crate) async fn serve_p2p<I: zbus::object_server::Interface>(
+    iface: impl FnOnce(zbus::Connection) -> I,
+) -> (crate::backend::Backend, zbus::Proxy<'static>) {
+    let (backend, client) = p2p_backend().await;
+    assert!(backend
+        .serve(iface(backend.connection().clone()))
+        .await
+        .unwrap());
+    let proxy = zbus::Proxy::new(
+        &client,
+        "org.example.Portal",
+        crate::proxy::DESKTOP_PATH,
+        I::name(),
+    )
+    .await
+    .unwrap();
+    (backend, proxy)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Locks the wire strings of the string backed enums, and checks that
+    /// both them and the `Display` output parse back.
+    macro_rules! assert_wire_strings {
+        ($ty:ty { $($variant:expr => $wire:literal),+ $(,)? }) => {
+            $(
+                assert_eq!(zbus::zvariant::Value::from($variant), zbus::zvariant::Value::from($wire));
+                assert_eq!(serde_json::to_string(&$variant).unwrap(), concat!("\"", $wire, "\""));
+                assert_eq!(serde_json::from_str::<$ty>(concat!("\"", $wire, "\"")).unwrap(), $variant);
+                assert_eq!($wire.parse::<$ty>().unwrap(), $variant);
+                assert_eq!($variant.to_string().parse::<$ty>().unwrap(), $variant);
+                assert_eq!($variant.as_ref(), $variant.to_string());
+            )+
+            assert!("unknown".parse::<$ty>().is_err());
+        };
+    }
+
+    #[test]
+    fn wire_strings() {
+        use crate::documents::Permission;
+
+        assert_wire_strings!(Permission {
+            Permission::Read => "read",
+            Permission::Write => "write",
+            Permission::GrantPermissions => "grant-permissions",
+            Permission::Delete => "delete",
+        });
+
+        #[cfg(feature = "wallpaper")]
+        {
+            use crate::desktop::wallpaper::SetOn;
+
+            assert_wire_strings!(SetOn {
+                SetOn::Lockscreen => "lockscreen",
+                SetOn::Background => "background",
+                SetOn::Both => "both",
+            });
+        }
+
+        #[cfg(feature = "print")]
+        {
+            use crate::desktop::print::{Orientation, Quality};
+
+            assert_wire_strings!(Orientation {
+                Orientation::Landscape => "landscape",
+                Orientation::Portrait => "portrait",
+                Orientation::ReverseLandscape => "reverse_landscape",
+                Orientation::ReversePortrait => "reverse_portrait",
+            });
+            assert_wire_strings!(Quality {
+                Quality::Draft => "draft",
+                Quality::Low => "low",
+                Quality::Normal => "normal",
+                Quality::High => "high",
+            });
+        }
+
+        #[cfg(feature = "notification")]
+        {
+            use crate::desktop::notification::Priority;
+
+            assert_wire_strings!(Priority {
+                Priority::Low => "low",
+                Priority::Normal => "normal",
+                Priority::High => "high",
+                Priority::Urgent => "urgent",
+            });
+        }
+    }
+
+    #[test]
+    #[cfg(any(
+        feature = "account",
+        feature = "email",
+        feature = "file_chooser",
+        feature = "recorder",
+    ))]
+    fn redacted() {
+        let address = Some("someone@example.org");
+        let debug = format!("{:?}", address.as_ref().map(Redacted));
+        if cfg!(feature = "debug_full") {
+            assert_eq!(debug, "Some(\"someone@example.org\")");
+        } else {
+            assert_eq!(debug, "Some(<redacted>)");
+        }
+    }
+
+    #[test]
+    #[cfg(any(feature = "dynamic_launcher", feature = "screencast"))]
+    fn secret_string() {
+        let token = SecretString::from("a1b2c3");
+        assert_eq!(token.as_str(), "a1b2c3");
+        assert_eq!(format!("{:?}", Some(&token)), "Some(<redacted>)");
+        assert_eq!(<SecretString as zbus::zvariant::Type>::signature(), "s");
+
+        let mut bytes = *b"secret";
+        zeroize(&mut bytes);
+        assert_eq!(bytes, [0; 6]);
+    }
+
     #[test]
     fn test_cgroup_v2_is_snap() {
         let data =
@@ -79,4 +689,103 @@ mod tests {
 0::/user.slice/user-1000.slice/user@1000.service/apps.slice/apps-org.gnome.Terminal.slice/vte-spawn-228ae109-a869-4533-8988-65ea4c10b492.scope\n";
         assert!(cgroup_v2_is_snap(data));
     }
+
+    #[test]
+    #[cfg(all(
+        feature = "network_monitor",
+        feature = "screencast",
+        feature = "wallpaper"
+    ))]
+    fn test_value_conversions() {
+        use zbus::zvariant::{OwnedValue, Value};
+
+        use crate::desktop::{
+            network_monitor::Connectivity, screencast::SourceType, wallpaper::SetOn,
+        };
+
+        let value = Value::from(SetOn::Lockscreen);
+        assert_eq!(value, Value::from("lockscreen"));
+        assert_eq!(SetOn::try_from(value).unwrap(), SetOn::Lockscreen);
+        assert_eq!(
+            SetOn::try_from(OwnedValue::from(SetOn::Both)).unwrap(),
+            SetOn::Both
+        );
+        assert!(SetOn::try_from(Value::from("Both")).is_err());
+        assert!(SetOn::try_from(Value::from(1u32)).is_err());
+
+        assert_eq!(Value::from(Connectivity::FullNetwork), Value::from(4u32));
+        assert_eq!(
+            Connectivity::try_from(OwnedValue::from(3u32)).unwrap(),
+            Connectivity::CaptivePortal
+        );
+        assert!(Connectivity::try_from(Value::from(0u32)).is_err());
+
+        assert_eq!(Value::from(SourceType::Virtual), Value::from(4u32));
+        assert_eq!(
+            SourceType::try_from(Value::from(2u32)).unwrap(),
+            SourceType::Window
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "account")]
+    fn test_dict_with_extra() {
+        use std::collections::HashMap;
+
+        use zbus::zvariant::{serialized::Context, to_bytes, Endian, OwnedValue, Value};
+
+        use crate::desktop::account::UserInformation;
+
+        let ctxt = Context::new_dbus(Endian::Little, 0);
+        let mut dict = HashMap::from([
+            ("id", Value::from("user")),
+            ("name", Value::from("User")),
+            ("image", Value::from("file:///avatar.png")),
+            ("org.example.pronouns", Value::from("they/them")),
+        ]);
+
+        let encoded = to_bytes(ctxt, &dict).unwrap();
+        let info: UserInformation = encoded.deserialize().unwrap().0;
+        assert_eq!(info.name(), "User");
+        assert_eq!(
+            info.extra().get("org.example.pronouns"),
+            Some(&OwnedValue::try_from(Value::from("they/them")).unwrap())
+        );
+
+        let encoded = to_bytes(ctxt, &info).unwrap();
+        let decoded: HashMap<String, OwnedValue> = encoded.deserialize().unwrap().0;
+        assert_eq!(decoded.len(), 4);
+        assert!(decoded.contains_key("org.example.pronouns"));
+
+        dict.remove("id");
+        let encoded = to_bytes(ctxt, &dict).unwrap();
+        assert!(encoded.deserialize::<UserInformation>().is_err());
+    }
+
+    #[cfg(feature = "backend")]
+    #[test]
+    fn test_dict_with_invalid_values() {
+        use std::collections::HashMap;
+
+        use zbus::zvariant::{serialized::Context, to_bytes, Endian, Value};
+
+        use crate::backend::screenshot::ScreenshotOptions;
+
+        let ctxt = Context::new_dbus(Endian::Little, 0);
+        // A value of an unexpected type must not prevent decoding the others,
+        // whatever their order.
+        for invalid in ["modal", "permission_store_checked"] {
+            let dict = HashMap::from([
+                (invalid, Value::from("yes")),
+                ("interactive", Value::from(true)),
+                ("org.example.option", Value::from(1u32)),
+            ]);
+            let encoded = to_bytes(ctxt, &dict).unwrap();
+            let options: ScreenshotOptions = encoded.deserialize().unwrap().0;
+            assert_eq!(options.modal(), None);
+            assert_eq!(options.permission_store_checked(), None);
+            assert_eq!(options.interactive(), Some(true));
+            assert_eq!(options.extra().len(), 1);
+        }
+    }
 }
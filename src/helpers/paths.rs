@@ -0,0 +1,107 @@
+//! Conversions between paths and the nul-terminated byte arrays, `ay`, the
+//! portals use to send them over D-Bus.
+//!
+//! Paths aren't necessarily valid UTF-8, unlike D-Bus strings, which is why
+//! they are sent as bytes. The trailing nul byte is part of the wire format.
+//!
+//! ```rust
+//! use std::path::{Path, PathBuf};
+//!
+//! use ashpd::helpers::paths;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Entry {
+//!     #[serde(with = "paths")]
+//!     path: PathBuf,
+//! }
+//!
+//! let bytes = paths::null_terminated("/home/user/file.txt").unwrap();
+//! assert_eq!(bytes.last(), Some(&0));
+//! assert_eq!(
+//!     paths::path_from_null_terminated(&bytes).unwrap(),
+//!     Path::new("/home/user/file.txt")
+//! );
+//! assert!(paths::null_terminated("/home/user/\0file.txt").is_err());
+//! assert!(paths::path_from_null_terminated(b"/home/user/file.txt").is_err());
+//! ```
+
+use std::{
+    ffi::OsStr,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Deserializer, Serializer};
+
+use crate::Error;
+
+/// The path of the nul-terminated `bytes`.
+///
+/// Fails with [`Error::NulTerminated`] if `bytes` contains a nul byte before
+/// the last one, or [`Error::ParseError`] if it isn't nul-terminated.
+pub fn path_from_null_terminated(bytes: &[u8]) -> Result<&Path, Error> {
+    let Some((0, bytes)) = bytes.split_last() else {
+        return Err(Error::ParseError("Path bytes are not nul-terminated"));
+    };
+    if let Some(position) = bytes.iter().position(|byte| *byte == 0) {
+        return Err(Error::NulTerminated(position));
+    }
+    Ok(Path::new(OsStr::from_bytes(bytes)))
+}
+
+/// The bytes of `path` followed by a nul byte.
+///
+/// Fails with [`Error::NulTerminated`] if `path` contains a nul byte.
+pub fn null_terminated(path: impl AsRef<Path>) -> Result<Vec<u8>, Error> {
+    let bytes = path.as_ref().as_os_str().as_bytes();
+    if let Some(position) = bytes.iter().position(|byte| *byte == 0) {
+        return Err(Error::NulTerminated(position));
+    }
+    let mut bytes = bytes.to_vec();
+    bytes.push(0);
+    Ok(bytes)
+}
+
+/// Serialize `path` as a nul-terminated byte array, to be used with
+/// `#[serde(serialize_with = "ashpd::helpers::paths::serialize")]`.
+///
+/// The field must be of signature `ay`.
+pub fn serialize<S: Serializer>(path: impl AsRef<Path>, serializer: S) -> Result<S::Ok, S::Error> {
+    let bytes = null_terminated(path).map_err(serde::ser::Error::custom)?;
+    serializer.serialize_bytes(&bytes)
+}
+
+/// Deserialize a path from a nul-terminated byte array, to be used with
+/// `#[serde(deserialize_with = "ashpd::helpers::paths::deserialize")]`.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<PathBuf, D::Error> {
+    let bytes = <Vec<u8>>::deserialize(deserializer)?;
+    path_from_null_terminated(&bytes)
+        .map(ToOwned::to_owned)
+        .map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions() {
+        assert_eq!(null_terminated("").unwrap(), [0]);
+        assert_eq!(path_from_null_terminated(&[0]).unwrap(), Path::new(""));
+        assert!(matches!(
+            path_from_null_terminated(b"a\0b\0"),
+            Err(Error::NulTerminated(1))
+        ));
+        assert!(matches!(
+            null_terminated("a\0b"),
+            Err(Error::NulTerminated(1))
+        ));
+        assert!(path_from_null_terminated(&[]).is_err());
+
+        // Non UTF-8 paths are kept as is.
+        let bytes = [b'/', 0xff, 0];
+        let path = path_from_null_terminated(&bytes).unwrap();
+        assert_eq!(null_terminated(path).unwrap(), bytes);
+    }
+}
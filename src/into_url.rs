@@ -0,0 +1,91 @@
+use std::path::{Path, PathBuf};
+
+use url::Url;
+
+use crate::Error;
+
+/// A value that can be converted into a [`Url`].
+///
+/// Strings are parsed as URIs and paths are converted to percent-encoded
+/// `file://` URIs, relative paths being resolved against the current
+/// directory. Conversion failures are reported as [`Error::Url`].
+pub trait IntoUrl {
+    /// Convert into a [`Url`].
+    fn into_url(self) -> Result<Url, Error>;
+}
+
+impl IntoUrl for Url {
+    fn into_url(self) -> Result<Url, Error> {
+        Ok(self)
+    }
+}
+
+impl IntoUrl for &Url {
+    fn into_url(self) -> Result<Url, Error> {
+        Ok(self.clone())
+    }
+}
+
+impl IntoUrl for &str {
+    fn into_url(self) -> Result<Url, Error> {
+        Ok(Url::parse(self)?)
+    }
+}
+
+impl IntoUrl for String {
+    fn into_url(self) -> Result<Url, Error> {
+        self.as_str().into_url()
+    }
+}
+
+impl IntoUrl for &String {
+    fn into_url(self) -> Result<Url, Error> {
+        self.as_str().into_url()
+    }
+}
+
+impl IntoUrl for &Path {
+    fn into_url(self) -> Result<Url, Error> {
+        let path = if self.is_absolute() {
+            self.to_owned()
+        } else {
+            std::env::current_dir()?.join(self)
+        };
+        Url::from_file_path(path).map_err(|_| Error::Url(url::ParseError::RelativeUrlWithoutBase))
+    }
+}
+
+impl IntoUrl for PathBuf {
+    fn into_url(self) -> Result<Url, Error> {
+        self.as_path().into_url()
+    }
+}
+
+impl IntoUrl for &PathBuf {
+    fn into_url(self) -> Result<Url, Error> {
+        self.as_path().into_url()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn into_url() {
+        assert_eq!(
+            Path::new("/tmp/a b#c.png").into_url().unwrap().as_str(),
+            "file:///tmp/a%20b%23c.png"
+        );
+        assert_eq!(
+            "https://example.org".into_url().unwrap().as_str(),
+            "https://example.org/"
+        );
+        assert!(matches!("not a uri".into_url(), Err(Error::Url(_))));
+        assert!(Path::new("relative")
+            .into_url()
+            .unwrap()
+            .path()
+            .ends_with("/relative"));
+    }
+}
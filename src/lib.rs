@@ -9,18 +9,87 @@
 #[cfg(all(all(feature = "tokio", feature = "async-std"), not(doc)))]
 compile_error!("You can't enable both async-std & tokio features at once");
 
+/// Compiles the items only when one of the portals of the `desktop` module is
+/// enabled. The `clipboard` and `remote_desktop` portals enable `screencast`.
+macro_rules! cfg_desktop {
+    ($($item:item)*) => {
+        $(
+            #[cfg(any(
+                feature = "account",
+                feature = "background",
+                feature = "camera",
+                feature = "device",
+                feature = "dynamic_launcher",
+                feature = "email",
+                feature = "file_chooser",
+                feature = "game_mode",
+                feature = "global_shortcuts",
+                feature = "inhibit",
+                feature = "input_capture",
+                feature = "location",
+                feature = "memory_monitor",
+                feature = "network_monitor",
+                feature = "notification",
+                feature = "open_uri",
+                feature = "power_profile_monitor",
+                feature = "print",
+                feature = "proxy_resolver",
+                feature = "realtime",
+                feature = "screencast",
+                feature = "screenshot",
+                feature = "secret",
+                feature = "settings",
+                feature = "trash",
+                feature = "wallpaper",
+            ))]
+            $item
+        )*
+    };
+}
+
+/// Like `cfg_desktop!`, for the portals answering through a `Request`.
+macro_rules! cfg_request {
+    ($($item:item)*) => {
+        $(
+            #[cfg(any(
+                feature = "account",
+                feature = "background",
+                feature = "camera",
+                feature = "device",
+                feature = "dynamic_launcher",
+                feature = "email",
+                feature = "file_chooser",
+                feature = "global_shortcuts",
+                feature = "inhibit",
+                feature = "input_capture",
+                feature = "location",
+                feature = "open_uri",
+                feature = "print",
+                feature = "screencast",
+                feature = "screenshot",
+                feature = "secret",
+                feature = "wallpaper",
+            ))]
+            $item
+        )*
+    };
+}
+
 /// Alias for a [`Result`] with the error type `ashpd::Error`.
 pub type Result<T> = std::result::Result<T, Error>;
 
 static IS_SANDBOXED: OnceLock<bool> = OnceLock::new();
 
 mod activation_token;
+#[cfg(any(feature = "file_chooser", feature = "restore_tokens"))]
+mod app_state;
 /// Interact with the user's desktop such as taking a screenshot, setting a
 /// background or querying the user's location.
 pub mod desktop;
 /// Interact with the documents store or transfer files across apps.
 pub mod documents;
 mod error;
+mod event_loop;
 mod window_identifier;
 
 pub use self::{activation_token::ActivationToken, window_identifier::WindowIdentifier};
@@ -28,9 +97,37 @@ mod app_id;
 pub use self::app_id::AppID;
 mod file_path;
 pub use self::file_path::FilePath;
+mod file_uri;
+pub use self::file_uri::FileUri;
+/// Remember the last folder and filter of the file chooser dialogs.
+#[cfg(feature = "file_chooser")]
+#[cfg_attr(docsrs, doc(cfg(feature = "file_chooser")))]
+pub mod file_chooser_history;
 
+mod into_url;
+pub use self::into_url::IntoUrl;
+#[cfg(feature = "metrics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+pub mod metrics;
+mod mime_type;
+pub use self::mime_type::MimeType;
+/// The most used types, to be imported with `use ashpd::prelude::*`.
+pub mod prelude;
+mod process;
+pub use self::process::{register_pidfd, PidFd};
 mod proxy;
-
+#[cfg(feature = "recorder")]
+#[cfg_attr(docsrs, doc(cfg(feature = "recorder")))]
+pub mod recorder;
+#[cfg(feature = "restore_tokens")]
+#[cfg_attr(docsrs, doc(cfg(feature = "restore_tokens")))]
+pub mod restore_tokens;
+mod sandbox_path;
+/// Parse and format the triggers of the global shortcuts.
+pub mod shortcuts;
+pub use self::sandbox_path::{ExportedPath, PathPolicy};
+mod single_instance;
+pub use self::single_instance::{Activation, Instance, PrimaryInstance};
 #[cfg(feature = "backend")]
 #[cfg_attr(docsrs, doc(cfg(feature = "backend")))]
 pub use self::window_identifier::WindowIdentifierType;
@@ -42,7 +139,8 @@ pub mod backend;
 /// Spawn commands outside the sandbox or monitor if the running application has
 /// received an update & install it.
 pub mod flatpak;
-mod helpers;
+/// Helpers for implementing or talking to portals by hand.
+pub mod helpers;
 use std::sync::OnceLock;
 
 #[cfg(feature = "backend")]
@@ -50,6 +148,8 @@ use std::sync::OnceLock;
 pub use async_trait;
 pub use enumflags2;
 pub use url;
+/// The `zbus` crate ASHPD is built against, bumping its major version is a
+/// breaking change of ASHPD.
 pub use zbus::{self, zvariant};
 
 /// Check whether the application is running inside a sandbox.
@@ -71,4 +171,11 @@ pub async fn is_sandboxed() -> bool {
     new_value
 }
 
-pub use self::error::{Error, PortalError};
+#[cfg(feature = "inhibit")]
+#[cfg_attr(docsrs, doc(cfg(feature = "inhibit")))]
+pub use self::desktop::inhibit::{prevent_sleep, SleepInhibitor};
+pub use self::{
+    error::{set_error_observer, Error, ErrorContext, ErrorObserver, PortalError},
+    event_loop::{Event, EventLoop},
+    proxy::{clear_proxy_cache, set_close_on_drop, set_strict_options},
+};
@@ -0,0 +1,144 @@
+//! Count the calls made to the portals and measure their latency.
+//!
+//! Every method call and request made through the portals, and every request
+//! handled by the [`backend`](crate::backend) interfaces, is recorded through
+//! the [`metrics`](https://docs.rs/metrics) facade, by interface and method.
+//! A request is measured until its response, the time the user spent in the
+//! dialog included.
+//!
+//! The metrics are only kept once the application installs a recorder, e.g.
+//! the Prometheus exporter of `metrics-exporter-prometheus`:
+//!
+//! * [`CALLS`], a counter labelled with `interface`, `member` and `outcome`,
+//!   one of `success`, `cancelled` or `failed`.
+//! * [`LATENCY`], a histogram of the latencies in seconds, labelled with
+//!   `interface` and `member`.
+//!
+//! ```rust,ignore
+//! metrics_exporter_prometheus::PrometheusBuilder::new().install()?;
+//! let response = ashpd::desktop::screenshot::Screenshot::request()
+//!     .send()
+//!     .await?
+//!     .response()?;
+//! ```
+
+use std::time::Duration;
+
+use crate::{desktop::ResponseError, Error};
+
+/// The name of the counter of the calls.
+pub const CALLS: &str = "ashpd_portal_calls_total";
+
+/// The name of the histogram of the latencies of the calls, in seconds.
+pub const LATENCY: &str = "ashpd_portal_call_duration_seconds";
+
+/// How a call ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    /// The call succeeded.
+    Success,
+    /// The user cancelled the request.
+    Cancelled,
+    /// The call failed.
+    Failed,
+}
+
+impl Outcome {
+    pub(crate) fn of<T>(result: &Result<T, Error>) -> Self {
+        match result {
+            Ok(_) => Self::Success,
+            Err(Error::Response(ResponseError::Cancelled)) => Self::Cancelled,
+            Err(_) => Self::Failed,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Cancelled => "cancelled",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+pub(crate) fn record(interface: &str, member: &str, latency: Duration, outcome: Outcome) {
+    ::metrics::counter!(
+        CALLS,
+        "interface" => interface.to_owned(),
+        "member" => member.to_owned(),
+        "outcome" => outcome.as_str(),
+    )
+    .increment(1);
+    ::metrics::histogram!(
+        LATENCY,
+        "interface" => interface.to_owned(),
+        "member" => member.to_owned(),
+    )
+    .record(latency);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use ::metrics::{
+        Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Keys(Mutex<Vec<Key>>);
+
+    impl Recorder for Keys {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            self.0.lock().unwrap().push(key.clone());
+            Counter::noop()
+        }
+
+        fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+            self.0.lock().unwrap().push(key.clone());
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            self.0.lock().unwrap().push(key.clone());
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn record_calls() {
+        let keys = Keys::default();
+        ::metrics::with_local_recorder(&keys, || {
+            record(
+                "org.example.Metrics",
+                "Call",
+                Duration::from_millis(3),
+                Outcome::of::<()>(&Err(Error::Response(ResponseError::Cancelled))),
+            );
+        });
+
+        let keys = keys.0.into_inner().unwrap();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[0].name(), CALLS);
+        let labels = keys[0]
+            .labels()
+            .map(|label| (label.key(), label.value()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            labels,
+            [
+                ("interface", "org.example.Metrics"),
+                ("member", "Call"),
+                ("outcome", "cancelled"),
+            ]
+        );
+        assert_eq!(keys[1].name(), LATENCY);
+        assert_eq!(keys[1].labels().count(), 2);
+    }
+}
@@ -0,0 +1,177 @@
+use std::{ops::Deref, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+use zbus::zvariant::Type;
+
+/// A mime type, e.g. `image/png` or `text/plain;charset=utf-8`.
+///
+/// The type and subtype are normalized to lowercase. A `*` subtype, as in
+/// `image/*`, is accepted to designate any subtype.
+///
+/// ```
+/// use ashpd::MimeType;
+///
+/// let mime_type = "Image/PNG".parse::<MimeType>().unwrap();
+/// assert_eq!(mime_type.as_ref(), "image/png");
+/// assert!("image/*".parse::<MimeType>().unwrap().matches(&mime_type));
+/// assert!("image png".parse::<MimeType>().is_err());
+/// ```
+#[derive(Debug, Serialize, Type, PartialEq, Eq, Hash, Clone)]
+pub struct MimeType(String);
+
+crate::helpers::impl_value_conversions!(MimeType as str);
+
+impl MimeType {
+    /// The type, e.g. `image` for `image/png`.
+    pub fn type_(&self) -> &str {
+        self.essence().split_once('/').unwrap().0
+    }
+
+    /// The subtype, e.g. `png` for `image/png`.
+    pub fn subtype(&self) -> &str {
+        self.essence().split_once('/').unwrap().1
+    }
+
+    /// The mime type without its parameters, e.g. `text/plain` for
+    /// `text/plain;charset=utf-8`.
+    pub fn essence(&self) -> &str {
+        self.0
+            .split_once(';')
+            .map_or(&self.0, |(essence, _)| essence)
+    }
+
+    /// Whether `other` is designated by this mime type, taking wildcards into
+    /// account and ignoring the parameters.
+    pub fn matches(&self, other: &MimeType) -> bool {
+        (self.type_() == "*" || self.type_() == other.type_())
+            && (self.subtype() == "*" || self.subtype() == other.subtype())
+    }
+}
+
+fn is_valid_token(token: &str) -> bool {
+    !token.is_empty()
+        && token.chars().all(|c| {
+            c.is_ascii_alphanumeric()
+                || matches!(c, '!' | '#' | '$' | '&' | '-' | '^' | '_' | '.' | '+')
+        })
+}
+
+impl FromStr for MimeType {
+    type Err = crate::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (essence, parameters) = match value.split_once(';') {
+            Some((essence, parameters)) => (essence, Some(parameters)),
+            None => (value, None),
+        };
+        let (type_, subtype) = essence.trim().split_once('/').ok_or(Self::Err::ParseError(
+            "Failed to parse mime type, missing `/`",
+        ))?;
+        let is_valid = match (type_, subtype) {
+            ("*", "*") => true,
+            ("*", _) => false,
+            (type_, "*") => is_valid_token(type_),
+            (type_, subtype) => is_valid_token(type_) && is_valid_token(subtype),
+        };
+        if !is_valid {
+            return Err(Self::Err::ParseError(
+                "Failed to parse mime type, invalid value",
+            ));
+        }
+        let mut mime_type = format!("{type_}/{subtype}").to_ascii_lowercase();
+        for parameter in parameters.into_iter().flat_map(|p| p.split(';')) {
+            let parameter = parameter.trim();
+            if !parameter.contains('=') {
+                return Err(Self::Err::ParseError(
+                    "Failed to parse mime type, invalid parameter",
+                ));
+            }
+            mime_type.push(';');
+            mime_type.push_str(parameter);
+        }
+        Ok(Self(mime_type))
+    }
+}
+
+impl TryFrom<String> for MimeType {
+    type Error = crate::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse::<Self>()
+    }
+}
+
+impl TryFrom<&str> for MimeType {
+    type Error = crate::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        value.parse::<Self>()
+    }
+}
+
+impl From<MimeType> for String {
+    fn from(value: MimeType) -> String {
+        value.0
+    }
+}
+
+impl AsRef<str> for MimeType {
+    fn as_ref(&self) -> &str {
+        self.0.as_ref()
+    }
+}
+
+impl Deref for MimeType {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for MimeType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_ref())
+    }
+}
+
+impl<'de> Deserialize<'de> for MimeType {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let mime_type = String::deserialize(deserializer)?;
+        mime_type
+            .parse::<Self>()
+            .map_err(|err| serde::de::Error::custom(err.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MimeType;
+
+    #[test]
+    fn parse() {
+        let mime_type = "Text/Plain; Charset=UTF-8".parse::<MimeType>().unwrap();
+        assert_eq!(mime_type.as_ref(), "text/plain;Charset=UTF-8");
+        assert_eq!(mime_type.essence(), "text/plain");
+        assert_eq!(mime_type.type_(), "text");
+        assert_eq!(mime_type.subtype(), "plain");
+
+        assert!("*/*".parse::<MimeType>().is_ok());
+        assert!("image/svg+xml".parse::<MimeType>().is_ok());
+        assert!("*/png".parse::<MimeType>().is_err());
+        assert!("image/".parse::<MimeType>().is_err());
+        assert!("image".parse::<MimeType>().is_err());
+        assert!("text/plain;charset".parse::<MimeType>().is_err());
+    }
+
+    #[test]
+    fn matches() {
+        let png = "image/png".parse::<MimeType>().unwrap();
+        assert!("*/*".parse::<MimeType>().unwrap().matches(&png));
+        assert!("IMAGE/*".parse::<MimeType>().unwrap().matches(&png));
+        assert!(!"text/*".parse::<MimeType>().unwrap().matches(&png));
+    }
+}
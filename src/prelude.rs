@@ -0,0 +1,71 @@
+//! The prelude only grows, the types re-exported here stay available under
+//! the same name. Builders sharing a name across portals, like the
+//! `OpenFileRequest` of both [`file_chooser`](crate::desktop::file_chooser)
+//! and [`open_uri`](crate::desktop::open_uri), are left out and are reachable
+//! from their response type or module instead. [`Error`](crate::Error) and
+//! [`Result`](crate::Result) are left out too, not to shadow the ones of the
+//! standard library.
+//!
+//! ```rust,no_run
+//! use ashpd::prelude::*;
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     let proxy = Screencast::new().await?;
+//!     let session = proxy.create_session().await?;
+//!     proxy
+//!         .select_sources(
+//!             &session,
+//!             CursorMode::Metadata,
+//!             SourceType::Monitor | SourceType::Window,
+//!             true,
+//!             None,
+//!             PersistMode::DoNot,
+//!         )
+//!         .await?;
+//!
+//!     let response = proxy
+//!         .start(&session, &WindowIdentifier::default())
+//!         .await?
+//!         .response()?;
+//!     for stream in response.streams() {
+//!         println!("node id: {}", stream.pipe_wire_node_id());
+//!     }
+//!
+//!     let files = SelectedFiles::open_file()
+//!         .filter(FileFilter::new("Images").mimetype("image/*"))
+//!         .send()
+//!         .await?
+//!         .response_or_cancelled()?;
+//!     println!("{files:#?}");
+//!     Ok(())
+//! }
+//! ```
+
+pub use enumflags2::BitFlags;
+
+#[cfg(feature = "account")]
+pub use crate::desktop::account::UserInformation;
+#[cfg(feature = "camera")]
+pub use crate::desktop::camera::Camera;
+#[cfg(feature = "email")]
+pub use crate::desktop::email::EmailRequest;
+#[cfg(feature = "file_chooser")]
+pub use crate::desktop::file_chooser::{FileFilter, SelectedFiles};
+#[cfg(feature = "notification")]
+pub use crate::desktop::notification::{Button, Notification, NotificationProxy, Priority};
+#[cfg(feature = "remote_desktop")]
+pub use crate::desktop::remote_desktop::{DeviceType, RemoteDesktop};
+#[cfg(feature = "screencast")]
+pub use crate::desktop::screencast::{CursorMode, Screencast, SourceType, Streams};
+#[cfg(feature = "screenshot")]
+pub use crate::desktop::screenshot::Screenshot;
+#[cfg(feature = "settings")]
+pub use crate::desktop::settings::{ColorScheme, Settings};
+#[cfg(feature = "wallpaper")]
+pub use crate::desktop::wallpaper::{SetOn, WallpaperRequest};
+pub use crate::{
+    desktop::{
+        Color, Icon, PersistMode, Position, Region, Request, Response, ResponseError, Session, Size,
+    },
+    ActivationToken, AppID, FileUri, PortalError, WindowIdentifier,
+};
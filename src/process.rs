@@ -0,0 +1,166 @@
+use std::{
+    fs,
+    io::{self, ErrorKind},
+    os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+};
+
+use crate::Error;
+
+/// A pid file descriptor, referring to a process.
+///
+/// Processes inside a sandbox with its own pid namespace, like Flatpak
+/// applications, don't share their pids with the host. The portals taking
+/// pids, like [`GameMode`](crate::desktop::game_mode::GameMode), translate
+/// them, but the ones taking pidfds expect a pid file descriptor, which refers
+/// to a process regardless of the namespaces.
+///
+/// Register the current process with GameMode
+///
+/// ```rust,no_run
+/// use std::os::fd::AsFd;
+///
+/// use ashpd::{desktop::game_mode::GameMode, PidFd};
+///
+/// async fn run() -> ashpd::Result<()> {
+///     let proxy = GameMode::new().await?;
+///     let pidfd = PidFd::current()?;
+///     proxy
+///         .register_by_pidfd(&pidfd.as_fd(), &pidfd.as_fd())
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct PidFd(OwnedFd);
+
+impl PidFd {
+    /// Open a pid file descriptor of the process `pid`.
+    #[doc(alias = "pidfd_open")]
+    pub fn open(pid: u32) -> Result<Self, Error> {
+        let pid = libc::pid_t::try_from(pid)
+            .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "Invalid pid"))?;
+        // SAFETY: pidfd_open takes no pointer and returns a new file
+        // descriptor on success.
+        let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error().into());
+        }
+        // SAFETY: the file descriptor was just opened and is owned by nobody
+        // else.
+        Ok(Self(unsafe { OwnedFd::from_raw_fd(fd as i32) }))
+    }
+
+    /// Open a pid file descriptor of the current process.
+    pub fn current() -> Result<Self, Error> {
+        Self::open(std::process::id())
+    }
+
+    /// The pid of the process, in the pid namespace of the current process.
+    ///
+    /// Fails if the process exited.
+    pub fn pid(&self) -> Result<u32, Error> {
+        host_pid(self)
+    }
+}
+
+impl TryFrom<OwnedFd> for PidFd {
+    type Error = Error;
+
+    /// Fails if `fd` is not a pid file descriptor, see [`register_pidfd`].
+    fn try_from(fd: OwnedFd) -> Result<Self, Self::Error> {
+        register_pidfd(fd)
+    }
+}
+
+impl From<PidFd> for OwnedFd {
+    fn from(pidfd: PidFd) -> Self {
+        pidfd.0
+    }
+}
+
+impl AsFd for PidFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+/// Check that `fd` is a pid file descriptor, as expected by the portals taking
+/// pidfds, before handing it to them.
+///
+/// Fails with an [`ErrorKind::InvalidInput`] I/O error if `fd` is not a pid
+/// file descriptor, for example a file descriptor of a `/proc/<pid>`
+/// directory, and [`ErrorKind::NotFound`] if the process exited.
+pub fn register_pidfd(fd: impl Into<OwnedFd>) -> Result<PidFd, Error> {
+    let fd = fd.into();
+    host_pid(&fd)?;
+    Ok(PidFd(fd))
+}
+
+/// The pid, in the pid namespace of the current process, of the process
+/// `pidfd` refers to.
+///
+/// On the host, this is the pid to pass to the services not aware of
+/// sandboxes, like the GameMode daemon. Fails with an
+/// [`ErrorKind::InvalidInput`] I/O error if `pidfd` is not a pid file
+/// descriptor, and [`ErrorKind::NotFound`] if the process exited or lives in
+/// a pid namespace not visible from the current one.
+pub(crate) fn host_pid(pidfd: &impl AsFd) -> Result<u32, Error> {
+    let fdinfo = fs::read_to_string(format!("/proc/self/fdinfo/{}", pidfd.as_fd().as_raw_fd()))?;
+    Ok(pid_from_fdinfo(&fdinfo)?)
+}
+
+fn pid_from_fdinfo(fdinfo: &str) -> io::Result<u32> {
+    let pid = proc_field(fdinfo, "Pid")
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "Not a pidfd"))?
+        .parse::<i32>()
+        .map_err(|err| io::Error::new(ErrorKind::InvalidData, err))?;
+    // -1 means the process exited, 0 that it lives in a pid namespace not
+    // visible from ours.
+    u32::try_from(pid)
+        .ok()
+        .filter(|pid| *pid != 0)
+        .ok_or_else(|| io::Error::new(ErrorKind::NotFound, "The process is not reachable"))
+}
+
+/// The value of a `Name:<tab>value` line of a `/proc` file.
+fn proc_field<'a>(contents: &'a str, name: &str) -> Option<&'a str> {
+    contents
+        .lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+        .map(str::trim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_proc_files() {
+        let fdinfo =
+            "pos:\t0\nflags:\t02000002\nmnt_id:\t15\nino:\t1057\nPid:\t4242\nNSpid:\t4242\t12\n";
+        assert_eq!(pid_from_fdinfo(fdinfo).unwrap(), 4242);
+        assert_eq!(
+            pid_from_fdinfo("Pid:\t-1\n").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            pid_from_fdinfo("pos:\t0\n").unwrap_err().kind(),
+            ErrorKind::InvalidInput
+        );
+    }
+
+    #[test]
+    fn current_process() {
+        let pid = std::process::id();
+
+        // pidfd_open is not available before Linux 5.3.
+        let Ok(pidfd) = PidFd::current() else {
+            return;
+        };
+        assert_eq!(pidfd.pid().unwrap(), pid);
+        assert_eq!(host_pid(&pidfd).unwrap(), pid);
+
+        let file = fs::File::open("/proc/self/status").unwrap();
+        assert!(register_pidfd(file).is_err());
+    }
+}
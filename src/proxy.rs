@@ -1,18 +1,39 @@
-use std::{fmt::Debug, future::ready, ops::Deref, sync::OnceLock};
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    future::ready,
+    ops::Deref,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, OnceLock,
+    },
+};
 
+#[cfg(feature = "print")]
+use futures_util::{future::BoxFuture, stream::BoxStream, FutureExt};
 use futures_util::{Stream, StreamExt};
-use serde::{Deserialize, Serialize};
-use zbus::zvariant::{ObjectPath, OwnedValue, Type};
+use serde::{de::DeserializeSeed, Deserialize, Serialize};
+#[cfg(feature = "print")]
+use zbus::proxy::CacheProperties;
 #[cfg(feature = "tracing")]
 use zbus::Message;
-
-use crate::{
-    desktop::{HandleToken, Request},
-    Error, PortalError,
+use zbus::{
+    names::{BusName, InterfaceName},
+    zvariant::{ObjectPath, OwnedValue, Type},
 };
 
-pub(crate) const DESKTOP_DESTINATION: &str = "org.freedesktop.portal.Desktop";
-pub(crate) const DESKTOP_PATH: &str = "/org/freedesktop/portal/desktop";
+use crate::{Error, PortalError};
+
+cfg_request! {
+    use zbus::names::UniqueName;
+
+    use crate::desktop::{HandleToken, Request};
+}
+
+cfg_desktop! {
+    pub(crate) const DESKTOP_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+    pub(crate) const DESKTOP_PATH: &str = "/org/freedesktop/portal/desktop";
+}
 
 pub(crate) const DOCUMENTS_DESTINATION: &str = "org.freedesktop.portal.Documents";
 pub(crate) const DOCUMENTS_PATH: &str = "/org/freedesktop/portal/documents";
@@ -23,12 +44,96 @@ pub(crate) const FLATPAK_PATH: &str = "/org/freedesktop/portal/Flatpak";
 pub(crate) const FLATPAK_DEVELOPMENT_DESTINATION: &str = "org.freedesktop.Flatpak";
 pub(crate) const FLATPAK_DEVELOPMENT_PATH: &str = "/org/freedesktop/Flatpak/Development";
 
+cfg_request! {
+    /// The unique name standing for the sender in the requests and sessions
+    /// paths of peer-to-peer connections, which have none.
+    static P2P_UNIQUE_NAME: UniqueName<'static> = UniqueName::from_static_str_unchecked(":0");
+}
+
 static SESSION: OnceLock<zbus::Connection> = OnceLock::new();
 
-#[derive(Debug)]
+/// The interface, path and destination of a cached proxy.
+type ProxyKey = (String, String, String);
+
+/// The proxies created on the shared session bus connection.
+static PROXIES: OnceLock<Mutex<HashMap<ProxyKey, Proxy<'static>>>> = OnceLock::new();
+
+fn proxies() -> std::sync::MutexGuard<'static, HashMap<ProxyKey, Proxy<'static>>> {
+    PROXIES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|err| err.into_inner())
+}
+
+/// Forget the portal proxies created on the shared session bus connection.
+///
+/// The proxies, and the interface version they looked up, are reused by the
+/// following calls on that connection to skip a round-trip to the portal.
+/// Clear them after the portal was restarted, e.g. when it was updated to a
+/// version supporting newer interfaces.
+///
+/// The proxies created with a connection passed explicitly, e.g. through
+/// `new_with_connection`, are never cached.
+pub fn clear_proxy_cache() {
+    proxies().clear();
+}
+
+static STRICT_OPTIONS: AtomicBool = AtomicBool::new(false);
+
+/// Fail the requests setting an option the portal doesn't support, instead
+/// of only warning about it.
+///
+/// The options added in a newer version of an interface than the one
+/// implemented by the portal are ignored by it. A warning is logged for them
+/// when the `tracing` feature is enabled; in strict mode the request also
+/// fails with [`Error::RequiresVersion`] before being sent. Meant to catch
+/// such silent no-ops during development.
+pub fn set_strict_options(strict: bool) {
+    STRICT_OPTIONS.store(strict, Ordering::Relaxed);
+}
+
+static CLOSE_ON_DROP: AtomicBool = AtomicBool::new(true);
+
+/// Close the requests whose future is dropped before their response is
+/// received, e.g. when the application gives up waiting for the user, which
+/// dismisses the dialog of the portal instead of leaving it open. Enabled by
+/// default.
+///
+/// The request builders can override it with their `close_on_drop` method.
+pub fn set_close_on_drop(close: bool) {
+    CLOSE_ON_DROP.store(close, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone)]
 pub struct Proxy<'a> {
     inner: zbus::Proxy<'a>,
     version: u32,
+    #[cfg(any(
+        feature = "account",
+        feature = "background",
+        feature = "camera",
+        feature = "device",
+        feature = "dynamic_launcher",
+        feature = "email",
+        feature = "file_chooser",
+        feature = "global_shortcuts",
+        feature = "inhibit",
+        feature = "input_capture",
+        feature = "location",
+        feature = "open_uri",
+        feature = "print",
+        feature = "screencast",
+        feature = "screenshot",
+        feature = "secret",
+        feature = "wallpaper",
+    ))]
+    close_on_drop: Option<bool>,
+    #[cfg(any(
+        feature = "file_chooser",
+        feature = "screencast",
+        feature = "screenshot"
+    ))]
+    strict_options: Option<bool>,
 }
 
 impl<'a> Proxy<'a> {
@@ -42,15 +147,17 @@ impl<'a> Proxy<'a> {
         }
     }
 
-    pub async fn unique_name(
-        prefix: &str,
-        handle_token: &HandleToken,
-    ) -> Result<ObjectPath<'static>, Error> {
-        let connection = Self::connection().await?;
-        let unique_name = connection.unique_name().unwrap();
-        let unique_identifier = unique_name.trim_start_matches(':').replace('.', "_");
-        ObjectPath::try_from(format!("{prefix}/{unique_identifier}/{handle_token}"))
-            .map_err(From::from)
+    /// The object path of the session the portal creates for a call made
+    /// with `handle_token` on the connection of this proxy.
+    #[cfg(any(
+        feature = "global_shortcuts",
+        feature = "inhibit",
+        feature = "input_capture",
+        feature = "location",
+        feature = "screencast",
+    ))]
+    pub(crate) fn session_path(&self, handle_token: &HandleToken) -> ObjectPath<'static> {
+        handle_token.session_path(self.unique_name()).into_inner()
     }
 
     pub async fn new<P>(
@@ -63,8 +170,56 @@ impl<'a> Proxy<'a> {
         P::Error: Into<zbus::Error>,
     {
         let connection = Self::connection().await?;
+        let path = path.try_into().map_err(Into::into)?;
+        let key = (
+            interface.to_owned(),
+            path.to_string(),
+            destination.to_owned(),
+        );
+        if let Some(proxy) = proxies().get(&key) {
+            return Ok(proxy.clone());
+        }
+
+        let proxy = Proxy::new_with_connection(
+            connection,
+            InterfaceName::try_from(interface)
+                .map_err(zbus::Error::from)?
+                .to_owned(),
+            path.into_owned(),
+            BusName::try_from(destination)
+                .map_err(zbus::Error::from)?
+                .to_owned(),
+        )
+        .await?;
+        proxies().insert(key, proxy.clone());
+        Ok(proxy)
+    }
+
+    /// Like [`Proxy::new`], but uses `connection` instead of the shared
+    /// session bus connection.
+    ///
+    /// On a peer-to-peer connection, the peer is the portal whatever the
+    /// `destination`, which is only used to fill the header of the messages.
+    /// Such a connection has no unique name, the requests and sessions are
+    /// then expected at `/org/freedesktop/portal/desktop/request/0/<token>`
+    /// and `/org/freedesktop/portal/desktop/session/0/<token>`.
+    pub async fn new_with_connection<I, P, D>(
+        connection: zbus::Connection,
+        interface: I,
+        path: P,
+        destination: D,
+    ) -> Result<Proxy<'a>, Error>
+    where
+        I: TryInto<InterfaceName<'a>>,
+        I::Error: Into<zbus::Error>,
+        P: TryInto<ObjectPath<'a>>,
+        P::Error: Into<zbus::Error>,
+        D: TryInto<BusName<'a>>,
+        D::Error: Into<zbus::Error>,
+    {
+        let interface = interface.try_into().map_err(Into::into)?;
         let inner: zbus::Proxy = zbus::ProxyBuilder::new(&connection)
-            .interface(interface)?
+            .interface(interface.clone())?
             .path(path)?
             .destination(destination)?
             .build()
@@ -75,33 +230,50 @@ impl<'a> Proxy<'a> {
             .await
             .map_err(zbus::fdo::Error::from)
         {
-            Ok(v) => Ok(v),
+            Ok(v) => {
+                #[cfg(feature = "recorder")]
+                crate::recorder::record_property(&interface, "version", &v.into());
+                Ok(v)
+            }
             Err(zbus::fdo::Error::InvalidArgs(details)) => {
-                if details.contains(interface) {
-                    Err(crate::Error::PortalNotFound(
-                        // We are sure it is a valid interface name, should fix the type system
-                        // here
-                        zbus::names::OwnedInterfaceName::try_from(interface).unwrap(),
-                    ))
+                if details.contains(interface.as_str()) {
+                    Err(crate::Error::PortalNotFound(interface.into()))
                 } else {
                     Ok(1)
                 }
             }
             _ => Ok(1),
         }?;
-        Ok(Self { inner, version })
-    }
-
-    pub async fn new_desktop_with_path<P>(interface: &'a str, path: P) -> Result<Proxy<'a>, Error>
-    where
-        P: TryInto<ObjectPath<'a>>,
-        P::Error: Into<zbus::Error>,
-    {
-        Self::new(interface, path, DESKTOP_DESTINATION).await
-    }
-
-    pub async fn new_desktop(interface: &'a str) -> Result<Proxy<'a>, Error> {
-        Self::new(interface, DESKTOP_PATH, DESKTOP_DESTINATION).await
+        Ok(Self {
+            inner,
+            version,
+            #[cfg(any(
+                feature = "account",
+                feature = "background",
+                feature = "camera",
+                feature = "device",
+                feature = "dynamic_launcher",
+                feature = "email",
+                feature = "file_chooser",
+                feature = "global_shortcuts",
+                feature = "inhibit",
+                feature = "input_capture",
+                feature = "location",
+                feature = "open_uri",
+                feature = "print",
+                feature = "screencast",
+                feature = "screenshot",
+                feature = "secret",
+                feature = "wallpaper",
+            ))]
+            close_on_drop: None,
+            #[cfg(any(
+                feature = "file_chooser",
+                feature = "screencast",
+                feature = "screenshot"
+            ))]
+            strict_options: None,
+        })
     }
 
     pub async fn new_documents(interface: &'a str) -> Result<Proxy<'a>, Error> {
@@ -129,24 +301,100 @@ impl<'a> Proxy<'a> {
         .await
     }
 
-    pub async fn request<T>(
+    /// Like [`Proxy::request`], but also subscribes to `signal_name` of
+    /// `signal_interface` emitted on the request object path before calling
+    /// `method_name`.
+    ///
+    /// The returned stream ends once the future resolving to the [`Request`]
+    /// completes, or is dropped.
+    #[cfg(feature = "print")]
+    pub(crate) async fn request_with_signal<T, I>(
         &self,
         handle_token: &HandleToken,
         method_name: &'static str,
         body: impl Serialize + Type + Debug,
-    ) -> Result<Request<T>, Error>
+        signal_interface: &'static str,
+        signal_name: &'static str,
+    ) -> Result<
+        (
+            BoxStream<'static, I>,
+            BoxFuture<'static, Result<Request<T>, Error>>,
+        ),
+        Error,
+    >
     where
-        T: for<'de> Deserialize<'de> + Type + Debug,
+        T: for<'de> Deserialize<'de> + Type + Debug + Send + 'static,
+        I: for<'de> Deserialize<'de> + Type + Debug + Send + 'static,
     {
-        let mut request = Request::from_unique_name(handle_token).await?;
-        futures_util::try_join!(request.prepare_response(), async {
-            self.call_method(method_name, &body)
-                .await
-                .map_err(From::from)
-        })?;
-        Ok(request)
+        let interface = self.interface().to_string();
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+        let result = async {
+            let mut request = Request::<T>::from_unique_name(self, handle_token).await?;
+            let close_guard = request.close_on_drop(self.close_on_drop());
+            let signal_proxy: zbus::Proxy<'static> =
+                zbus::ProxyBuilder::new(self.inner.connection())
+                    .interface(signal_interface)?
+                    .path(request.path().to_owned())?
+                    .destination(self.inner.destination().to_owned())?
+                    .cache_properties(CacheProperties::No)
+                    .build()
+                    .await?;
+            let signals = signal_proxy.receive_signal(signal_name).await?;
+
+            #[cfg(feature = "recorder")]
+            crate::recorder::record_call(&interface, method_name, &body);
+            self.call_method(method_name, &body).await?;
+
+            let (done_sender, done_receiver) = futures_channel::oneshot::channel::<()>();
+            let stream = signals
+                .take_until(done_receiver)
+                .filter_map(move |msg| ready(msg.body().deserialize::<I>().ok()));
+            let response = async move {
+                let result = request.prepare_response(&interface, method_name).await;
+                close_guard.disarm();
+                let _ = done_sender.send(());
+                #[cfg(feature = "recorder")]
+                if let Ok(response) = &result {
+                    crate::recorder::record_message(
+                        crate::recorder::EntryKind::Response,
+                        &interface,
+                        method_name,
+                        response,
+                    );
+                }
+                #[cfg(feature = "metrics")]
+                crate::metrics::record(
+                    &interface,
+                    method_name,
+                    start.elapsed(),
+                    result
+                        .as_ref()
+                        .map_or(crate::metrics::Outcome::Failed, |_| request.outcome()),
+                );
+                crate::error::observe(&interface, method_name, result.map(|_| request))
+            };
+            Ok((stream.boxed(), response.boxed()))
+        }
+        .await;
+        #[cfg(feature = "metrics")]
+        if result.is_err() {
+            self.record_metrics(method_name, start, &result);
+        }
+        self.observe(method_name, result)
     }
 
+    #[cfg(any(
+        feature = "camera",
+        feature = "device",
+        feature = "email",
+        feature = "inhibit",
+        feature = "location",
+        feature = "print",
+        feature = "screencast",
+        feature = "secret",
+        feature = "wallpaper",
+    ))]
     pub(crate) async fn empty_request(
         &self,
         handle_token: &HandleToken,
@@ -161,6 +409,106 @@ impl<'a> Proxy<'a> {
         self.version
     }
 
+    /// Overrides [`set_close_on_drop`] for the requests made with this proxy.
+    #[cfg(any(
+        feature = "account",
+        feature = "background",
+        feature = "email",
+        feature = "file_chooser",
+        feature = "open_uri",
+        feature = "print",
+        feature = "screenshot",
+        feature = "wallpaper",
+    ))]
+    pub(crate) fn close_requests_on_drop(&mut self, close: Option<bool>) {
+        self.close_on_drop = close;
+    }
+
+    /// Overrides [`set_strict_options`] for the options checked with this
+    /// proxy, letting the tests not depend on the global setting.
+    #[cfg(all(
+        test,
+        feature = "account",
+        feature = "tokio",
+        any(
+            feature = "file_chooser",
+            feature = "screencast",
+            feature = "screenshot"
+        )
+    ))]
+    pub(crate) fn check_options_strictly(&mut self, strict: Option<bool>) {
+        self.strict_options = strict;
+    }
+
+    #[cfg(any(
+        feature = "file_chooser",
+        feature = "screencast",
+        feature = "screenshot"
+    ))]
+    fn strict_options(&self) -> bool {
+        self.strict_options
+            .unwrap_or_else(|| STRICT_OPTIONS.load(Ordering::Relaxed))
+    }
+
+    /// Warns about `option` of `method_name` being set while the portal
+    /// doesn't implement `req_version` of the interface, or fails in strict
+    /// mode, see [`set_strict_options`].
+    #[cfg(any(
+        feature = "file_chooser",
+        feature = "screencast",
+        feature = "screenshot"
+    ))]
+    pub(crate) fn check_option(
+        &self,
+        method_name: &'static str,
+        _option: &'static str,
+        req_version: u32,
+        is_set: bool,
+    ) -> Result<(), Error> {
+        let version = self.version();
+        if !is_set || version >= req_version {
+            return Ok(());
+        }
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            interface = self.interface().as_str(),
+            method = method_name,
+            option = _option,
+            required_version = req_version,
+            version,
+            "`{_option}` requires version {req_version} of {}, the portal implements version {version} and ignores it",
+            self.interface()
+        );
+        if self.strict_options() {
+            self.observe(
+                method_name,
+                Err(Error::RequiresVersion(req_version, version)),
+            )
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "metrics")]
+    fn record_metrics<T>(
+        &self,
+        member: &str,
+        start: std::time::Instant,
+        result: &Result<T, Error>,
+    ) {
+        crate::metrics::record(
+            self.interface(),
+            member,
+            start.elapsed(),
+            crate::metrics::Outcome::of(result),
+        );
+    }
+
+    /// Report the error of `result`, if any, to the error observer.
+    fn observe<T>(&self, member: &str, result: Result<T, Error>) -> Result<T, Error> {
+        crate::error::observe(self.interface(), member, result)
+    }
+
     pub(crate) async fn call<R>(
         &self,
         method_name: &'static str,
@@ -174,13 +522,74 @@ impl<'a> Proxy<'a> {
             tracing::info!("Calling method {}:{}", self.interface(), method_name);
             tracing::debug!("With body {:#?}", body);
         }
-        let msg = self
-            .call_method(method_name, &body)
-            .await
-            .map_err::<PortalError, _>(From::from)?;
-        let reply = msg.body().deserialize::<R>()?;
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+        let result = async {
+            #[cfg(feature = "recorder")]
+            crate::recorder::record_call(self.interface(), method_name, &body);
+            let msg = self
+                .call_method(method_name, &body)
+                .await
+                .map_err::<PortalError, _>(From::from)?;
+            #[cfg(feature = "recorder")]
+            crate::recorder::record_message(
+                crate::recorder::EntryKind::Reply,
+                self.interface(),
+                method_name,
+                &msg,
+            );
+            Ok(msg.body().deserialize::<R>()?)
+        }
+        .await;
+        #[cfg(feature = "metrics")]
+        self.record_metrics(method_name, start, &result);
+        self.observe(method_name, result)
+    }
 
-        Ok(reply)
+    /// Like [`Proxy::call`], decoding the reply with `seed`, e.g. to process
+    /// the entries of a large array as they are decoded instead of collecting
+    /// them first.
+    pub(crate) async fn call_with_seed<S, V>(
+        &self,
+        method_name: &'static str,
+        body: impl Serialize + Type + Debug,
+        seed: S,
+    ) -> Result<V, Error>
+    where
+        S: for<'de> DeserializeSeed<'de, Value = V> + Type,
+    {
+        #[cfg(feature = "tracing")]
+        {
+            tracing::info!("Calling method {}:{}", self.interface(), method_name);
+            tracing::debug!("With body {:#?}", body);
+        }
+        #[cfg(feature = "metrics")]
+        let start = std::time::Instant::now();
+        let result = async {
+            #[cfg(feature = "recorder")]
+            crate::recorder::record_call(self.interface(), method_name, &body);
+            let msg = self
+                .call_method(method_name, &body)
+                .await
+                .map_err::<PortalError, _>(From::from)?;
+            #[cfg(feature = "recorder")]
+            crate::recorder::record_message(
+                crate::recorder::EntryKind::Reply,
+                self.interface(),
+                method_name,
+                &msg,
+            );
+            let body = msg.body();
+            if body.signature().as_ref() != Some(&S::signature()) {
+                return Err(zbus::Error::InvalidReply.into());
+            }
+            let (reply, _) = body.data().deserialize_with_seed(seed)?;
+            Ok(reply)
+        }
+        .await;
+        #[cfg(feature = "metrics")]
+        self.record_metrics(method_name, start, &result);
+        self.observe(method_name, result)
     }
 
     pub(crate) async fn call_versioned<R>(
@@ -196,7 +605,10 @@ impl<'a> Proxy<'a> {
         if version >= req_version {
             self.call::<R>(method_name, body).await
         } else {
-            Err(Error::RequiresVersion(req_version, version))
+            self.observe(
+                method_name,
+                Err(Error::RequiresVersion(req_version, version)),
+            )
         }
     }
 
@@ -205,10 +617,17 @@ impl<'a> Proxy<'a> {
         T: TryFrom<OwnedValue>,
         zbus::Error: From<<T as TryFrom<OwnedValue>>::Error>,
     {
-        self.inner
-            .get_property::<T>(property_name)
+        let result = self
+            .inner
+            .get_property::<OwnedValue>(property_name)
             .await
-            .map_err(From::from)
+            .and_then(|value| {
+                #[cfg(feature = "recorder")]
+                crate::recorder::record_property(self.interface(), property_name, &value);
+                T::try_from(value).map_err(From::from)
+            })
+            .map_err(From::from);
+        self.observe(property_name, result)
     }
 
     pub(crate) async fn property_versioned<T>(
@@ -224,10 +643,39 @@ impl<'a> Proxy<'a> {
         if version >= req_version {
             self.property::<T>(property_name).await
         } else {
-            Err(Error::RequiresVersion(req_version, version))
+            self.observe(
+                property_name,
+                Err(Error::RequiresVersion(req_version, version)),
+            )
         }
     }
 
+    /// The values of `property_name`, each time the portal changes it.
+    #[cfg(feature = "camera")]
+    pub(crate) async fn property_changed<T>(
+        &self,
+        property_name: &'static str,
+    ) -> Result<impl Stream<Item = T> + 'a, Error>
+    where
+        T: TryFrom<OwnedValue> + Unpin + 'a,
+        T::Error: Into<zbus::Error>,
+    {
+        let interface = self.interface().to_owned();
+        Ok(self
+            .inner
+            .receive_property_changed::<T>(property_name)
+            .await
+            .then(move |changed| {
+                let interface = interface.clone();
+                async move {
+                    let result = changed.get().await.map_err(Error::from);
+                    crate::error::observe(&interface, property_name, result).ok()
+                }
+            })
+            .filter_map(ready))
+    }
+
+    #[cfg(feature = "settings")]
     pub(crate) async fn signal_with_args<I>(
         &self,
         name: &'static str,
@@ -271,6 +719,131 @@ impl<'a> Proxy<'a> {
     }
 }
 
+cfg_desktop! {
+    impl<'a> Proxy<'a> {
+        pub async fn new_desktop(interface: &'a str) -> Result<Proxy<'a>, Error> {
+            Self::new(interface, DESKTOP_PATH, DESKTOP_DESTINATION).await
+        }
+
+        /// Like [`Proxy::new_desktop`], on `connection` if any.
+        pub async fn new_desktop_with_connection(
+            connection: Option<zbus::Connection>,
+            interface: &'a str,
+        ) -> Result<Proxy<'a>, Error> {
+            match connection {
+                Some(connection) => {
+                    Self::new_with_connection(
+                        connection,
+                        interface,
+                        DESKTOP_PATH,
+                        DESKTOP_DESTINATION,
+                    )
+                    .await
+                }
+                None => Self::new_desktop(interface).await,
+            }
+        }
+    }
+}
+
+cfg_request! {
+    impl<'a> Proxy<'a> {
+        /// The object path of the request the portal creates for a call made
+        /// with `handle_token` on the connection of this proxy.
+        pub(crate) fn request_path(&self, handle_token: &HandleToken) -> ObjectPath<'static> {
+            handle_token.request_path(self.unique_name()).into_inner()
+        }
+
+        /// The unique name of the connection, or [`P2P_UNIQUE_NAME`] for a
+        /// peer-to-peer connection without one.
+        fn unique_name(&self) -> &UniqueName<'static> {
+            self.inner
+                .connection()
+                .unique_name()
+                .map_or(&P2P_UNIQUE_NAME, |name| name.inner())
+        }
+
+        /// A proxy of `interface` at `path`, on the same connection and
+        /// destination as this one.
+        pub(crate) async fn new_sibling<P>(
+            &self,
+            interface: &'static str,
+            path: P,
+        ) -> Result<Proxy<'static>, Error>
+        where
+            P: TryInto<ObjectPath<'static>>,
+            P::Error: Into<zbus::Error>,
+        {
+            Proxy::new_with_connection(
+                self.inner.connection().clone(),
+                interface,
+                path,
+                self.inner.destination().to_owned(),
+            )
+            .await
+        }
+
+        pub async fn request<T>(
+            &self,
+            handle_token: &HandleToken,
+            method_name: &'static str,
+            body: impl Serialize + Type + Debug,
+        ) -> Result<Request<T>, Error>
+        where
+            T: for<'de> Deserialize<'de> + Type + Debug,
+        {
+            #[cfg(feature = "metrics")]
+            let start = std::time::Instant::now();
+            let result = async {
+                let mut request = Request::from_unique_name(self, handle_token).await?;
+                let close_guard = request.close_on_drop(self.close_on_drop());
+                #[cfg(feature = "recorder")]
+                crate::recorder::record_call(self.interface(), method_name, &body);
+                // `body` borrows the window identifier of the request, if any, so an
+                // exported window handle stays exported until the response is
+                // received, and is unexported once the builder sending the request
+                // drops it, right after.
+                let result = futures_util::try_join!(
+                    request.prepare_response(self.interface(), method_name),
+                    async {
+                        self.call_method(method_name, &body)
+                            .await
+                            .map_err(From::from)
+                    }
+                );
+                close_guard.disarm();
+                let (_response, _) = result?;
+                #[cfg(feature = "recorder")]
+                crate::recorder::record_message(
+                    crate::recorder::EntryKind::Response,
+                    self.interface(),
+                    method_name,
+                    &_response,
+                );
+                Ok(request)
+            }
+            .await;
+            // The request succeeds once the response is received, whether the user
+            // cancelled it or not.
+            #[cfg(feature = "metrics")]
+            crate::metrics::record(
+                self.interface(),
+                method_name,
+                start.elapsed(),
+                result
+                    .as_ref()
+                    .map_or(crate::metrics::Outcome::Failed, Request::outcome),
+            );
+            self.observe(method_name, result)
+        }
+
+        fn close_on_drop(&self) -> bool {
+            self.close_on_drop
+                .unwrap_or_else(|| CLOSE_ON_DROP.load(Ordering::Relaxed))
+        }
+    }
+}
+
 #[cfg(feature = "tracing")]
 fn trace_body<I>(name: &'static str, ifc: &str, msg: Message) -> Option<I>
 where
@@ -296,3 +869,219 @@ impl<'a> Deref for Proxy<'a> {
         &self.inner
     }
 }
+
+#[cfg(all(test, feature = "account", feature = "tokio"))]
+mod tests {
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
+
+    use futures_channel::oneshot;
+    use futures_util::future::{self, Either};
+    use zbus::{
+        zvariant::{OwnedObjectPath, Value},
+        SignalContext,
+    };
+
+    use crate::{desktop::account::UserInformation, helpers::p2p_pair};
+
+    /// Replies to the requests with the response code it wraps.
+    struct Portal(u32);
+
+    #[zbus::interface(name = "org.freedesktop.portal.Account")]
+    impl Portal {
+        #[zbus(property, name = "version")]
+        fn version(&self) -> u32 {
+            1
+        }
+
+        async fn get_user_information(
+            &self,
+            #[zbus(signal_context)] ctxt: SignalContext<'_>,
+            _window: &str,
+            options: HashMap<String, Value<'_>>,
+        ) -> zbus::fdo::Result<OwnedObjectPath> {
+            let token = String::try_from(options["handle_token"].try_clone().unwrap()).unwrap();
+            let path = format!("/org/freedesktop/portal/desktop/request/0/{token}");
+            let results = HashMap::from([
+                ("id", Value::from("user")),
+                ("name", Value::from("User")),
+                ("image", Value::from("file:///user.png")),
+            ]);
+            ctxt.connection()
+                .emit_signal(
+                    None::<()>,
+                    path.as_str(),
+                    "org.freedesktop.portal.Request",
+                    "Response",
+                    &(self.0, results),
+                )
+                .await?;
+            Ok(OwnedObjectPath::try_from(path).unwrap())
+        }
+    }
+
+    #[tokio::test]
+    async fn p2p_connection() {
+        let (server, client) = p2p_pair().await;
+        server
+            .object_server()
+            .at(super::DESKTOP_PATH, Portal(0))
+            .await
+            .unwrap();
+
+        let user = UserInformation::request()
+            .connection(client)
+            .send()
+            .await
+            .unwrap()
+            .response()
+            .unwrap();
+        assert_eq!(user.id(), "user");
+        assert_eq!(user.name(), "User");
+    }
+
+    #[cfg(any(
+        feature = "file_chooser",
+        feature = "screencast",
+        feature = "screenshot"
+    ))]
+    #[tokio::test]
+    async fn strict_options() {
+        let (server, client) = p2p_pair().await;
+        server
+            .object_server()
+            .at(super::DESKTOP_PATH, Portal(0))
+            .await
+            .unwrap();
+        let mut proxy = super::Proxy::new_desktop_with_connection(
+            Some(client),
+            "org.freedesktop.portal.Account",
+        )
+        .await
+        .unwrap();
+
+        proxy.check_options_strictly(Some(false));
+        assert!(proxy.check_option("Method", "option", 2, true).is_ok());
+        proxy.check_options_strictly(Some(true));
+        assert!(matches!(
+            proxy.check_option("Method", "option", 2, true),
+            Err(crate::Error::RequiresVersion(2, 1))
+        ));
+        assert!(proxy.check_option("Method", "option", 2, false).is_ok());
+        assert!(proxy.check_option("Method", "option", 1, true).is_ok());
+    }
+
+    static OBSERVED: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    fn observer(err: &crate::Error, context: crate::ErrorContext<'_>) {
+        if matches!(
+            context.member(),
+            "Fail" | "Unobserved" | "GetUserInformation"
+        ) {
+            OBSERVED
+                .lock()
+                .unwrap()
+                .push(format!("{}: {err}", context.member()));
+        }
+    }
+
+    #[tokio::test]
+    async fn error_observer() {
+        let (server, client) = p2p_pair().await;
+        server
+            .object_server()
+            .at(super::DESKTOP_PATH, Portal(1))
+            .await
+            .unwrap();
+
+        crate::set_error_observer(observer as crate::ErrorObserver);
+        let interface = "org.example.Test";
+        assert!(crate::error::observe(interface, "Ok", Ok(())).is_ok());
+        let failed = crate::error::observe::<()>(interface, "Fail", Err(crate::Error::NoResponse));
+        let request = UserInformation::request()
+            .connection(client)
+            .send()
+            .await
+            .unwrap();
+        crate::set_error_observer(None);
+        let unobserved =
+            crate::error::observe::<()>(interface, "Unobserved", Err(crate::Error::NoResponse));
+
+        assert!(failed.is_err() && unobserved.is_err());
+        let cancelled = request.response().unwrap_err();
+        assert_eq!(
+            *OBSERVED.lock().unwrap(),
+            [
+                "Fail: Portal error: no response".to_owned(),
+                format!("GetUserInformation: {cancelled}"),
+            ]
+        );
+    }
+
+    struct SilentPortal {
+        called: Mutex<Option<oneshot::Sender<()>>>,
+        closed: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+    }
+
+    #[zbus::interface(name = "org.freedesktop.portal.Account")]
+    impl SilentPortal {
+        #[zbus(property, name = "version")]
+        fn version(&self) -> u32 {
+            1
+        }
+
+        async fn get_user_information(
+            &self,
+            #[zbus(object_server)] server: &zbus::ObjectServer,
+            _window: &str,
+            options: HashMap<String, Value<'_>>,
+        ) -> zbus::fdo::Result<OwnedObjectPath> {
+            let token = String::try_from(options["handle_token"].try_clone().unwrap()).unwrap();
+            let path = format!("/org/freedesktop/portal/desktop/request/0/{token}");
+            server
+                .at(path.as_str(), PendingRequest(Arc::clone(&self.closed)))
+                .await?;
+            if let Some(called) = self.called.lock().unwrap().take() {
+                called.send(()).unwrap();
+            }
+            Ok(OwnedObjectPath::try_from(path).unwrap())
+        }
+    }
+
+    struct PendingRequest(Arc<Mutex<Option<oneshot::Sender<()>>>>);
+
+    #[zbus::interface(name = "org.freedesktop.portal.Request")]
+    impl PendingRequest {
+        fn close(&self) {
+            if let Some(closed) = self.0.lock().unwrap().take() {
+                closed.send(()).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn close_on_drop() {
+        let (called, called_rx) = oneshot::channel();
+        let (closed, closed_rx) = oneshot::channel();
+        let portal = SilentPortal {
+            called: Mutex::new(Some(called)),
+            closed: Arc::new(Mutex::new(Some(closed))),
+        };
+        let (server, client) = p2p_pair().await;
+        server
+            .object_server()
+            .at(super::DESKTOP_PATH, portal)
+            .await
+            .unwrap();
+
+        // Give up on the request once the portal shows its dialog.
+        let send = UserInformation::request().connection(client).send();
+        match future::select(Box::pin(send), called_rx).await {
+            Either::Left(_) => panic!("The portal never responds"),
+            Either::Right((called, _)) => called.unwrap(),
+        }
+        closed_rx.await.unwrap();
+    }
+}
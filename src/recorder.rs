@@ -0,0 +1,607 @@
+//! Record the interactions with the portals to a trace file, and replay them
+//! against a fake portal.
+//!
+//! This helps reproducing the bugs reported by the users of desktops the
+//! developers don't run: the user enables the recorder, reproduces the issue,
+//! and attaches the trace to the report. The developer then serves the
+//! recorded replies with a [`Replayer`] on a peer-to-peer connection, which
+//! is passed to the requests instead of the session bus.
+//!
+//! The trace is a [JSON Lines](https://jsonlines.org/) file, one [`Entry`] by
+//! line, with the body of every method call made through the portals, the
+//! reply of the portal, or the `Response` signal of the request it created,
+//! as well as the properties read.
+//!
+//! The traces hold the user data sent to, and received from, the portals,
+//! like the paths of the selected files. They should be reviewed before
+//! sharing them. The file descriptors aren't recorded, the entries passing
+//! some can't be replayed.
+//!
+//! ```rust,no_run
+//! use ashpd::{desktop::screenshot::Screenshot, recorder};
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     recorder::set_recorder(recorder::Recorder::create("portals.jsonl")?);
+//!     let response = Screenshot::request().send().await?.response()?;
+//!     recorder::set_recorder(None);
+//!     Ok(())
+//! }
+//! ```
+//!
+//! And to replay the trace:
+//!
+//! ```rust,no_run
+//! use ashpd::{desktop::screenshot::Screenshot, recorder::Replayer};
+//! use tokio::net::UnixStream;
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     let replayer = Replayer::open("portals.jsonl")?;
+//!     let (server, client) = UnixStream::pair()?;
+//!     let (server, client) = futures_util::try_join!(
+//!         zbus::connection::Builder::unix_stream(server)
+//!             .server(zbus::Guid::generate())?
+//!             .p2p()
+//!             .build(),
+//!         zbus::connection::Builder::unix_stream(client).p2p().build(),
+//!     )?;
+//!     tokio::spawn(async move { replayer.serve(&server).await });
+//!
+//!     let response = Screenshot::request()
+//!         .connection(client)
+//!         .send()
+//!         .await?
+//!         .response()?;
+//!     Ok(())
+//! }
+//! ```
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, LineWriter, Write},
+    path::Path,
+    sync::{Mutex, RwLock},
+};
+
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use zbus::{
+    message::Type as MessageType,
+    names::UniqueName,
+    zvariant::{
+        serialized::{Context, Data},
+        OwnedStructure, Signature, Structure, Value, LE,
+    },
+    Message,
+};
+
+use crate::{desktop::HandleToken, Error};
+
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+
+/// What an [`Entry`] of a trace records.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum EntryKind {
+    /// The arguments of a method call.
+    Call,
+    /// The reply of the portal to a method call.
+    Reply,
+    /// The `Response` signal of the request created by a method call.
+    Response,
+    /// The value of a property.
+    Property,
+}
+
+/// A line of a trace.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Entry {
+    kind: EntryKind,
+    interface: String,
+    member: String,
+    signature: String,
+    /// The little-endian D-Bus encoding of the body, in hexadecimal.
+    body: String,
+    /// The body in the GVariant text format, for humans reading the trace.
+    text: String,
+}
+
+impl Entry {
+    fn new<B>(kind: EntryKind, interface: &str, member: &str, body: &B) -> Result<Self, Error>
+    where
+        B: Serialize + zbus::zvariant::DynamicType,
+    {
+        let data = zbus::zvariant::to_bytes(Context::new_dbus(LE, 0), body)?;
+        let signature = body.dynamic_signature();
+        Self::from_data(kind, interface, member, &data, strip_parens(&signature))
+    }
+
+    fn from_message(
+        kind: EntryKind,
+        interface: &str,
+        member: &str,
+        message: &Message,
+    ) -> Result<Self, Error> {
+        let body = message.body();
+        let signature = body.signature().map(|s| s.to_string()).unwrap_or_default();
+        Self::from_data(kind, interface, member, body.data(), &signature)
+    }
+
+    fn from_data(
+        kind: EntryKind,
+        interface: &str,
+        member: &str,
+        data: &Data<'_, '_>,
+        signature: &str,
+    ) -> Result<Self, Error> {
+        let (body, text) = if signature.is_empty() {
+            (String::new(), String::from("()"))
+        } else {
+            let (structure, _) = data
+                .deserialize_for_dynamic_signature::<_, Structure<'_>>(format!("({signature})"))?;
+            // Normalized to little-endian, whatever the byte order of the message.
+            let bytes = zbus::zvariant::to_bytes(Context::new_dbus(LE, 0), &structure)?;
+            (to_hex(&bytes), structure.to_string())
+        };
+        Ok(Self {
+            kind,
+            interface: interface.to_owned(),
+            member: member.to_owned(),
+            signature: signature.to_owned(),
+            body,
+            text,
+        })
+    }
+
+    /// What the entry records.
+    pub fn kind(&self) -> EntryKind {
+        self.kind
+    }
+
+    /// The D-Bus interface of the portal, e.g.
+    /// `org.freedesktop.portal.Screenshot`.
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+
+    /// The method, or the property, e.g. `Screenshot`.
+    pub fn member(&self) -> &str {
+        &self.member
+    }
+
+    /// The D-Bus signature of the body.
+    pub fn signature(&self) -> &str {
+        &self.signature
+    }
+
+    /// The body in the GVariant text format.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The decoded body, `None` if it is empty.
+    pub fn body(&self) -> Result<Option<Structure<'static>>, Error> {
+        if self.signature.is_empty() {
+            return Ok(None);
+        }
+        let bytes = from_hex(&self.body).ok_or(Error::ParseError("Invalid trace body"))?;
+        let data = Data::new(bytes, Context::new_dbus(LE, 0));
+        let (OwnedStructure(structure), _) = data
+            .deserialize_for_dynamic_signature::<_, OwnedStructure>(format!(
+                "({})",
+                self.signature
+            ))?;
+        Ok(Some(structure))
+    }
+}
+
+impl fmt::Debug for Entry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Entry")
+            .field("kind", &self.kind)
+            .field("interface", &self.interface)
+            .field("member", &self.member)
+            .field("body", &crate::helpers::Redacted(&self.text))
+            .finish()
+    }
+}
+
+/// Zbus strips the parentheses of the structures sent as a message body.
+fn strip_parens<'a>(signature: &'a Signature<'_>) -> &'a str {
+    let signature = signature.as_str();
+    signature
+        .strip_prefix('(')
+        .and_then(|s| s.strip_suffix(')'))
+        .unwrap_or(signature)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Writes the interactions with the portals to a trace, once set with
+/// [`set_recorder`].
+pub struct Recorder(Mutex<Box<dyn Write + Send + Sync>>);
+
+impl Recorder {
+    /// Write the trace to `writer`.
+    pub fn new(writer: impl Write + Send + Sync + 'static) -> Self {
+        Self(Mutex::new(Box::new(writer)))
+    }
+
+    /// Write the trace to the file at `path`, replacing it if it exists.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(Self::new(LineWriter::new(File::create(path)?)))
+    }
+
+    fn write(&self, entry: Result<Entry, Error>) {
+        let result = entry.map_err(io::Error::other).and_then(|entry| {
+            let mut writer = self.0.lock().unwrap_or_else(|err| err.into_inner());
+            serde_json::to_writer(&mut *writer, &entry)?;
+            writer.write_all(b"\n")
+        });
+        if let Err(_err) = result {
+            #[cfg(feature = "tracing")]
+            tracing::warn!("Failed to record a portal interaction: {_err}");
+        }
+    }
+}
+
+impl fmt::Debug for Recorder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Recorder")
+    }
+}
+
+static RECORDER: RwLock<Option<Recorder>> = RwLock::new(None);
+
+/// Record the interactions with the portals using `recorder`, replacing the
+/// previous one. `None` stops recording.
+pub fn set_recorder(recorder: impl Into<Option<Recorder>>) {
+    *RECORDER.write().unwrap_or_else(|err| err.into_inner()) = recorder.into();
+}
+
+fn record(entry: impl FnOnce() -> Result<Entry, Error>) {
+    let recorder = RECORDER.read().unwrap_or_else(|err| err.into_inner());
+    if let Some(recorder) = recorder.as_ref() {
+        recorder.write(entry());
+    }
+}
+
+pub(crate) fn record_call<B>(interface: &str, member: &str, body: &B)
+where
+    B: Serialize + zbus::zvariant::DynamicType,
+{
+    record(|| Entry::new(EntryKind::Call, interface, member, body));
+}
+
+pub(crate) fn record_message(kind: EntryKind, interface: &str, member: &str, message: &Message) {
+    record(|| Entry::from_message(kind, interface, member, message));
+}
+
+pub(crate) fn record_property(interface: &str, name: &str, value: &Value<'_>) {
+    record(|| Entry::new(EntryKind::Property, interface, name, value));
+}
+
+/// A fake portal replying to the method calls with the replies recorded in a
+/// trace.
+///
+/// The replies of every method are sent in the order they were recorded. The
+/// recorded `Response` signals are emitted on the request the portal is
+/// expected to create, whatever the handle token used.
+#[derive(Debug)]
+pub struct Replayer {
+    replies: VecDeque<Entry>,
+    properties: HashMap<(String, String), Entry>,
+}
+
+impl Replayer {
+    /// Replay the `entries`, the calls being ignored.
+    pub fn new(entries: impl IntoIterator<Item = Entry>) -> Self {
+        let mut replies = VecDeque::new();
+        let mut properties = HashMap::new();
+        for entry in entries {
+            match entry.kind {
+                EntryKind::Call => (),
+                EntryKind::Reply | EntryKind::Response => replies.push_back(entry),
+                EntryKind::Property => {
+                    properties.insert((entry.interface.clone(), entry.member.clone()), entry);
+                }
+            }
+        }
+        Self {
+            replies,
+            properties,
+        }
+    }
+
+    /// Replay the trace written to the file at `path` by a [`Recorder`].
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::read(BufReader::new(File::open(path)?))
+    }
+
+    /// Replay the trace read from `reader`.
+    pub fn read(reader: impl BufRead) -> io::Result<Self> {
+        let entries = reader
+            .lines()
+            .filter(|line| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+            .map(|line| Ok(serde_json::from_str::<Entry>(&line?)?))
+            .collect::<io::Result<Vec<_>>>()?;
+        Ok(Self::new(entries))
+    }
+
+    /// Reply to the method calls received on `connection` until it is closed.
+    ///
+    /// The connection is the server side of a peer-to-peer connection, the
+    /// client side being passed to the requests.
+    pub async fn serve(mut self, connection: &zbus::Connection) -> Result<(), Error> {
+        let mut stream = zbus::MessageStream::from(connection);
+        while let Some(Ok(message)) = stream.next().await {
+            let header = message.header();
+            if header.message_type() != MessageType::MethodCall {
+                continue;
+            }
+            let (Some(interface), Some(member)) = (header.interface(), header.member()) else {
+                continue;
+            };
+            if interface.as_str() == PROPERTIES_INTERFACE {
+                self.reply_property(connection, &message, member).await?;
+                continue;
+            }
+            let position = self.replies.iter().position(|entry| {
+                entry.interface == interface.as_str() && entry.member == member.as_str()
+            });
+            let Some(entry) = position.and_then(|position| self.replies.remove(position)) else {
+                connection
+                    .reply_error(
+                        &message,
+                        "org.freedesktop.DBus.Error.UnknownMethod",
+                        &format!("No recorded reply to {interface}.{member}"),
+                    )
+                    .await?;
+                continue;
+            };
+            let body = entry.body()?;
+            if entry.kind == EntryKind::Response {
+                let Some(path) = request_path(&message) else {
+                    connection
+                        .reply_error(
+                            &message,
+                            "org.freedesktop.DBus.Error.InvalidArgs",
+                            &"No handle_token option",
+                        )
+                        .await?;
+                    continue;
+                };
+                connection.reply(&message, &path).await?;
+                let destination = header.sender().cloned();
+                match body {
+                    Some(body) => {
+                        connection
+                            .emit_signal(
+                                destination,
+                                path,
+                                "org.freedesktop.portal.Request",
+                                "Response",
+                                &body,
+                            )
+                            .await?
+                    }
+                    None => {
+                        connection
+                            .emit_signal(
+                                destination,
+                                path,
+                                "org.freedesktop.portal.Request",
+                                "Response",
+                                &(),
+                            )
+                            .await?
+                    }
+                }
+            } else {
+                match body {
+                    Some(body) => connection.reply(&message, &body).await?,
+                    None => connection.reply(&message, &()).await?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn reply_property(
+        &self,
+        connection: &zbus::Connection,
+        message: &Message,
+        member: &str,
+    ) -> Result<(), Error> {
+        let body = message.body();
+        match member {
+            "Get" => {
+                let (interface, name) = body.deserialize::<(String, String)>()?;
+                match self.property(&interface, &name)? {
+                    Some(value) => connection.reply(message, &value).await?,
+                    None => {
+                        connection
+                            .reply_error(
+                                message,
+                                "org.freedesktop.DBus.Error.UnknownProperty",
+                                &format!("No recorded value of {interface}.{name}"),
+                            )
+                            .await?
+                    }
+                }
+            }
+            "GetAll" => {
+                let interface = body.deserialize::<String>()?;
+                let mut values = HashMap::new();
+                for (entry_interface, name) in self.properties.keys() {
+                    if *entry_interface == interface {
+                        if let Some(value) = self.property(&interface, name)? {
+                            values.insert(name.as_str(), value);
+                        }
+                    }
+                }
+                connection.reply(message, &values).await?;
+            }
+            _ => {
+                connection
+                    .reply_error(
+                        message,
+                        "org.freedesktop.DBus.Error.UnknownMethod",
+                        &format!("Unsupported method {member}"),
+                    )
+                    .await?
+            }
+        }
+        Ok(())
+    }
+
+    fn property(&self, interface: &str, name: &str) -> Result<Option<Value<'static>>, Error> {
+        let Some(entry) = self
+            .properties
+            .get(&(interface.to_owned(), name.to_owned()))
+        else {
+            return Ok(None);
+        };
+        // The body of a property entry is a single variant holding the value.
+        Ok(entry.body()?.and_then(|body| {
+            body.into_fields()
+                .into_iter()
+                .next()
+                .map(|value| match value {
+                    Value::Value(value) => *value,
+                    value => value,
+                })
+        }))
+    }
+}
+
+/// The path of the request the portal creates for `message`, from the
+/// `handle_token` of its options.
+fn request_path(message: &Message) -> Option<zbus::zvariant::OwnedObjectPath> {
+    let header = message.header();
+    let body = message.body();
+    let structure = body.deserialize::<Structure<'_>>().ok()?;
+    let token = structure.fields().iter().find_map(|field| match field {
+        Value::Dict(dict) => dict.get::<&str, &str>(&"handle_token").ok().flatten(),
+        _ => None,
+    })?;
+    let token = HandleToken::try_from(token).ok()?;
+    // Peer-to-peer connections have no unique name, see `Proxy::unique_name`.
+    let sender = header
+        .sender()
+        .cloned()
+        .unwrap_or_else(|| UniqueName::from_static_str_unchecked(":0"));
+    Some(token.request_path(&sender))
+}
+
+#[cfg(all(test, feature = "account"))]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::net::UnixStream;
+
+    use super::*;
+    use crate::desktop::{account::UserInformation, ResponseType};
+
+    const ACCOUNT: &str = "org.freedesktop.portal.Account";
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn record_and_replay() {
+        let results = HashMap::from([
+            ("id", Value::from("user")),
+            ("name", Value::from("User")),
+            ("image", Value::from("file:///user.png")),
+        ]);
+        let trace = [
+            Entry::new(EntryKind::Property, ACCOUNT, "version", &Value::from(1u32)).unwrap(),
+            Entry::new(
+                EntryKind::Response,
+                ACCOUNT,
+                "GetUserInformation",
+                &(ResponseType::Success, results),
+            )
+            .unwrap(),
+        ];
+        let lines = trace
+            .iter()
+            .map(|entry| serde_json::to_string(entry).unwrap() + "\n")
+            .collect::<String>();
+        let replayer = Replayer::read(lines.as_bytes()).unwrap();
+
+        let (server, client) = UnixStream::pair().unwrap();
+        let (server, client) = futures_util::try_join!(
+            zbus::connection::Builder::unix_stream(server)
+                .server(zbus::Guid::generate())
+                .unwrap()
+                .p2p()
+                .build(),
+            zbus::connection::Builder::unix_stream(client).p2p().build(),
+        )
+        .unwrap();
+        tokio::spawn(async move { replayer.serve(&server).await });
+
+        let buffer = Buffer::default();
+        set_recorder(Recorder::new(buffer.clone()));
+        let user = UserInformation::request()
+            .reason("test")
+            .connection(client)
+            .send()
+            .await
+            .unwrap()
+            .response()
+            .unwrap();
+        set_recorder(None);
+        assert_eq!(user.id(), "user");
+        assert_eq!(user.name(), "User");
+
+        // Other tests might be talking to the portals at the same time.
+        let recorded = Replayer::read(buffer.0.lock().unwrap().as_slice())
+            .unwrap()
+            .replies;
+        let recorded = recorded
+            .iter()
+            .filter(|entry| entry.interface() == ACCOUNT)
+            .collect::<Vec<_>>();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].kind(), EntryKind::Response);
+        assert_eq!(recorded[0].signature(), "ua{sv}");
+        assert_eq!(recorded[0].body().unwrap(), trace[1].body().unwrap());
+
+        let lines = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let call = lines
+            .lines()
+            .map(|line| serde_json::from_str::<Entry>(line).unwrap())
+            .find(|entry| entry.interface() == ACCOUNT && entry.kind() == EntryKind::Call)
+            .unwrap();
+        assert_eq!(call.member(), "GetUserInformation");
+        assert_eq!(call.signature(), "sa{sv}");
+        assert!(call.text().contains(r#""reason": <"test">"#));
+    }
+}
@@ -0,0 +1,190 @@
+//! Persist the restore tokens of screen cast and remote desktop sessions.
+//!
+//! # Examples
+//!
+//! Restore the previous screen cast session, if any
+//!
+//! ```rust,no_run
+//! use ashpd::{
+//!     desktop::{
+//!         screencast::{CursorMode, Screencast, SourceType},
+//!         PersistMode,
+//!     },
+//!     restore_tokens::RestoreTokens,
+//!     AppID, WindowIdentifier,
+//! };
+//!
+//! async fn run() -> ashpd::Result<()> {
+//!     let app_id = AppID::try_from("org.example.Recorder").unwrap();
+//!     let tokens = RestoreTokens::for_app(&app_id)?;
+//!     let proxy = Screencast::new().await?;
+//!     let session = proxy.create_session().await?;
+//!     let streams = tokens
+//!         .with_restore("main-monitor", |token| {
+//!             let (proxy, session) = (&proxy, &session);
+//!             async move {
+//!                 proxy
+//!                     .select_sources(
+//!                         session,
+//!                         CursorMode::Metadata,
+//!                         SourceType::Monitor.into(),
+//!                         false,
+//!                         token.as_deref(),
+//!                         PersistMode::ExplicitlyRevoked,
+//!                     )
+//!                     .await?;
+//!                 proxy
+//!                     .start(session, &WindowIdentifier::default())
+//!                     .await?
+//!                     .response()
+//!             }
+//!         })
+//!         .await?;
+//!     println!("{:#?}", streams.streams());
+//!     Ok(())
+//! }
+//! ```
+
+use std::{
+    future::Future,
+    path::{Path, PathBuf},
+};
+
+use crate::{app_state, AppID, Error};
+
+/// A response carrying a restore token.
+pub trait HasRestoreToken {
+    /// The restore token, if the session can be restored.
+    fn restore_token(&self) -> Option<&str>;
+}
+
+#[cfg(feature = "screencast")]
+impl HasRestoreToken for crate::desktop::screencast::Streams {
+    fn restore_token(&self) -> Option<&str> {
+        self.restore_token()
+    }
+}
+
+#[cfg(feature = "remote_desktop")]
+impl HasRestoreToken for crate::desktop::remote_desktop::SelectedDevices {
+    fn restore_token(&self) -> Option<&str> {
+        self.restore_token()
+    }
+}
+
+#[cfg(feature = "remote_desktop")]
+impl HasRestoreToken for crate::desktop::remote_desktop::RemoteSession<'_> {
+    fn restore_token(&self) -> Option<&str> {
+        self.restore_token()
+    }
+}
+
+/// A store of the restore tokens of screen cast and remote desktop sessions,
+/// keyed by an application defined name.
+///
+/// The tokens are stored in files only readable by the current user, under
+/// the XDG state directory, which is private to the application when it is
+/// sandboxed.
+#[derive(Debug, Clone)]
+pub struct RestoreTokens {
+    dir: PathBuf,
+}
+
+impl RestoreTokens {
+    /// Store the tokens of the application `app_id` in
+    /// `$XDG_STATE_HOME/<app_id>/restore-tokens`.
+    pub fn for_app(app_id: &AppID) -> Result<Self, Error> {
+        Ok(Self::with_dir(app_state::app_dir(
+            app_id,
+            "restore-tokens",
+        )?))
+    }
+
+    /// Store the tokens in `dir`.
+    pub fn with_dir(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_owned(),
+        }
+    }
+
+    /// The directory the tokens are stored in.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The stored token of `name`, if any.
+    pub async fn get(&self, name: &str) -> Result<Option<String>, Error> {
+        match app_state::read(self.path(name)?).await? {
+            Some(token) => String::from_utf8(token)
+                .map(Some)
+                .map_err(|_| Error::ParseError("Invalid restore token")),
+            None => Ok(None),
+        }
+    }
+
+    /// Store the `token` of `name`, or forget it if `None`.
+    pub async fn set(&self, name: &str, token: Option<&str>) -> Result<(), Error> {
+        let path = self.path(name)?;
+        match token {
+            Some(token) => app_state::write(path, token.as_bytes().to_vec()).await,
+            None => app_state::remove(path).await,
+        }
+    }
+
+    /// Call `f` with the stored token of `name` then store the token of the
+    /// returned response.
+    ///
+    /// The stored token is forgotten if the response doesn't have one, as
+    /// restore tokens can only be used once.
+    pub async fn with_restore<F, Fut, T>(&self, name: &str, f: F) -> Result<T, Error>
+    where
+        F: FnOnce(Option<String>) -> Fut,
+        Fut: Future<Output = Result<T, Error>>,
+        T: HasRestoreToken,
+    {
+        let token = self.get(name).await?;
+        let response = f(token).await?;
+        self.set(name, response.restore_token()).await?;
+        Ok(response)
+    }
+
+    fn path(&self, name: &str) -> Result<PathBuf, Error> {
+        if name.is_empty() || name.starts_with('.') || name.contains('/') {
+            return Err(Error::ParseError("Invalid restore token name"));
+        }
+        Ok(self.dir.join(name))
+    }
+}
+
+#[cfg(all(test, feature = "tokio"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn store() {
+        let dir = std::env::temp_dir().join(format!("ashpd-restore-tokens-{}", std::process::id()));
+        let tokens = RestoreTokens::with_dir(&dir);
+        assert_eq!(tokens.get("monitor").await.unwrap(), None);
+
+        tokens.set("monitor", Some("token")).await.unwrap();
+        assert_eq!(
+            tokens.get("monitor").await.unwrap().as_deref(),
+            Some("token")
+        );
+        tokens.set("monitor", Some("other")).await.unwrap();
+        assert_eq!(
+            tokens.get("monitor").await.unwrap().as_deref(),
+            Some("other")
+        );
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        tokens.set("monitor", None).await.unwrap();
+        assert_eq!(tokens.get("monitor").await.unwrap(), None);
+        tokens.set("monitor", None).await.unwrap();
+
+        assert!(tokens.get("../escape").await.is_err());
+        assert!(tokens.set("", Some("token")).await.is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,113 @@
+use std::{
+    fs::File,
+    os::fd::{AsFd, OwnedFd},
+    path::{Path, PathBuf},
+};
+
+use url::Url;
+
+use crate::{documents::Documents, Error};
+
+/// How a host path is handed over to a portal.
+///
+/// Paths seen by a sandboxed application are not necessarily visible to the
+/// portal, which runs on the host. The policy decides how a path is made
+/// available to it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathPolicy {
+    /// Pass the path as-is when the application is not sandboxed, and a file
+    /// descriptor otherwise.
+    #[default]
+    Auto,
+    /// Pass the path as-is, as a `file://` URI.
+    PassThrough,
+    /// Export the file through the [`Documents`] portal first and pass the
+    /// path inside the document store.
+    Document,
+    /// Open the file and pass the file descriptor.
+    Fd,
+}
+
+/// A path prepared to be handed over to a portal, following a
+/// [`PathPolicy`].
+#[derive(Debug)]
+pub enum ExportedPath {
+    /// The path is passed as-is.
+    Host(PathBuf),
+    /// The path was exported through the [`Documents`] portal, the value is
+    /// the path of the file inside the document store.
+    Document(PathBuf),
+    /// The path was opened for reading.
+    Fd(OwnedFd),
+}
+
+impl ExportedPath {
+    /// Prepare `path` following `policy`.
+    pub async fn new(path: impl AsRef<Path>, policy: PathPolicy) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let policy = match policy {
+            PathPolicy::Auto if crate::is_sandboxed().await => PathPolicy::Fd,
+            PathPolicy::Auto => PathPolicy::PassThrough,
+            policy => policy,
+        };
+        match policy {
+            PathPolicy::PassThrough if path.is_absolute() => Ok(Self::Host(path.to_owned())),
+            PathPolicy::PassThrough => Ok(Self::Host(std::env::current_dir()?.join(path))),
+            PathPolicy::Document => {
+                let file_name = path.file_name().ok_or(Error::ParseError(
+                    "Failed to export path, it has no file name",
+                ))?;
+                let file = File::open(path)?;
+                let documents = Documents::new().await?;
+                let doc_id = documents.add(&file.as_fd(), true, false).await?;
+                let mount_point = documents.mount_point().await?;
+                Ok(Self::Document(
+                    mount_point.as_ref().join(&*doc_id).join(file_name),
+                ))
+            }
+            _ => Ok(Self::Fd(File::open(path)?.into())),
+        }
+    }
+
+    /// The `file://` URI of the path, or `None` if it was opened as a file
+    /// descriptor.
+    pub fn uri(&self) -> Option<Url> {
+        match self {
+            Self::Host(path) | Self::Document(path) => Url::from_file_path(path).ok(),
+            Self::Fd(_) => None,
+        }
+    }
+
+    /// Convert into a file descriptor, opening the path for reading if
+    /// needed.
+    pub fn into_fd(self) -> Result<OwnedFd, Error> {
+        match self {
+            Self::Host(path) | Self::Document(path) => Ok(File::open(path)?.into()),
+            Self::Fd(fd) => Ok(fd),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn pass_through_and_fd() {
+        let path = std::env::temp_dir().join("ashpd-sandbox-path-test.txt");
+        std::fs::write(&path, b"ashpd").unwrap();
+
+        let exported = ExportedPath::new(&path, PathPolicy::PassThrough)
+            .await
+            .unwrap();
+        assert_eq!(exported.uri(), Some(Url::from_file_path(&path).unwrap()));
+        assert!(exported.into_fd().is_ok());
+
+        let exported = ExportedPath::new(&path, PathPolicy::Fd).await.unwrap();
+        assert!(exported.uri().is_none());
+        assert!(exported.into_fd().is_ok());
+
+        std::fs::remove_file(&path).unwrap();
+        assert!(ExportedPath::new(&path, PathPolicy::Fd).await.is_err());
+    }
+}
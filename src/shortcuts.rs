@@ -0,0 +1,206 @@
+//! The triggers of the global shortcuts are described as defined by the
+//! [shortcuts specification](https://gitlab.freedesktop.org/xdg/xdg-specs/-/merge_requests/75):
+//! the modifiers followed by the name of an XKB keysym, separated by `+`,
+//! e.g. `CTRL+ALT+Delete` or `LOGO+XF86AudioPlay`.
+//!
+//! ```rust
+//! use ashpd::shortcuts::{Modifier, Trigger};
+//!
+//! let trigger: Trigger = "ctrl+shift+a".parse().unwrap();
+//! assert_eq!(trigger.modifiers(), Modifier::Ctrl | Modifier::Shift);
+//! assert_eq!(trigger.key(), "a");
+//! assert_eq!(trigger.to_string(), "CTRL+SHIFT+a");
+//! assert_eq!(trigger.label(), "Ctrl+Shift+A");
+//! ```
+
+use std::{fmt, str::FromStr};
+
+use enumflags2::{bitflags, BitFlags};
+
+use crate::Error;
+
+#[bitflags]
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+#[repr(u8)]
+/// A modifier of a [`Trigger`].
+pub enum Modifier {
+    /// The Control key, `CTRL`.
+    Ctrl,
+    /// The Alt key, `ALT`.
+    Alt,
+    /// The Shift key, `SHIFT`.
+    Shift,
+    /// The Num Lock key, `NUM`.
+    Num,
+    /// The Super key, `LOGO`.
+    Logo,
+}
+
+impl Modifier {
+    /// The name of the modifier in the trigger descriptions.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Ctrl => "CTRL",
+            Self::Alt => "ALT",
+            Self::Shift => "SHIFT",
+            Self::Num => "NUM",
+            Self::Logo => "LOGO",
+        }
+    }
+
+    /// The human readable name of the modifier.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Ctrl => "Ctrl",
+            Self::Alt => "Alt",
+            Self::Shift => "Shift",
+            Self::Num => "Num Lock",
+            Self::Logo => "Super",
+        }
+    }
+}
+
+impl FromStr for Modifier {
+    type Err = Error;
+
+    /// Parse a modifier name, ignoring the case. `CONTROL` and `SUPER` are
+    /// accepted as aliases of `CTRL` and `LOGO`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.to_ascii_uppercase().as_str() {
+            "CTRL" | "CONTROL" => Ok(Self::Ctrl),
+            "ALT" => Ok(Self::Alt),
+            "SHIFT" => Ok(Self::Shift),
+            "NUM" => Ok(Self::Num),
+            "LOGO" | "SUPER" => Ok(Self::Logo),
+            _ => Err(Error::ParseError("Unknown shortcut modifier")),
+        }
+    }
+}
+
+/// A global shortcut trigger, the modifiers to hold while pressing a key.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Trigger {
+    modifiers: BitFlags<Modifier>,
+    key: String,
+}
+
+impl Trigger {
+    /// A trigger pressing `key` while holding `modifiers`.
+    ///
+    /// `key` is the name of an XKB keysym, e.g. `a`, `Return` or
+    /// `XF86AudioPlay`.
+    pub fn new(modifiers: impl Into<BitFlags<Modifier>>, key: &str) -> Result<Self, Error> {
+        if !is_valid_keysym(key) {
+            return Err(Error::ParseError("Invalid shortcut keysym"));
+        }
+        Ok(Self {
+            modifiers: modifiers.into(),
+            key: key.to_owned(),
+        })
+    }
+
+    /// The modifiers to hold.
+    pub fn modifiers(&self) -> BitFlags<Modifier> {
+        self.modifiers
+    }
+
+    /// The name of the keysym to press.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Whether the key is one of the `XF86` keysyms, like the media keys.
+    pub fn is_xf86(&self) -> bool {
+        self.key.starts_with("XF86")
+    }
+
+    /// A human readable label of the trigger, e.g. `Ctrl+Shift+A` or
+    /// `Super+AudioPlay`.
+    pub fn label(&self) -> String {
+        let key = self.key.strip_prefix("XF86").unwrap_or(&self.key);
+        let key = if key.chars().count() == 1 {
+            key.to_uppercase()
+        } else {
+            key.replace('_', " ")
+        };
+        let mut parts = self
+            .modifiers
+            .iter()
+            .map(Modifier::label)
+            .collect::<Vec<_>>();
+        parts.push(&key);
+        parts.join("+")
+    }
+}
+
+impl fmt::Display for Trigger {
+    /// The trigger description, as sent to
+    /// [`GlobalShortcuts::bind_shortcuts`](crate::desktop::global_shortcuts::GlobalShortcuts::bind_shortcuts).
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for modifier in self.modifiers.iter() {
+            write!(f, "{}+", modifier.name())?;
+        }
+        f.write_str(&self.key)
+    }
+}
+
+impl FromStr for Trigger {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (modifiers, key) = match value.rsplit_once('+') {
+            Some((modifiers, key)) => (Some(modifiers), key),
+            None => (None, value),
+        };
+        let mut flags = BitFlags::empty();
+        for modifier in modifiers.into_iter().flat_map(|m| m.split('+')) {
+            let modifier = modifier.parse::<Modifier>()?;
+            if flags.contains(modifier) {
+                return Err(Error::ParseError("Duplicated shortcut modifier"));
+            }
+            flags |= modifier;
+        }
+        Self::new(flags, key)
+    }
+}
+
+fn is_valid_keysym(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let trigger: Trigger = "CTRL+ALT+Delete".parse().unwrap();
+        assert_eq!(trigger.modifiers(), Modifier::Ctrl | Modifier::Alt);
+        assert_eq!(trigger.key(), "Delete");
+        assert_eq!(trigger.to_string(), "CTRL+ALT+Delete");
+        assert_eq!(trigger.label(), "Ctrl+Alt+Delete");
+
+        let trigger: Trigger = "XF86AudioPlay".parse().unwrap();
+        assert!(trigger.modifiers().is_empty());
+        assert!(trigger.is_xf86());
+        assert_eq!(trigger.label(), "AudioPlay");
+
+        // The modifiers are sorted and their aliases normalized.
+        let trigger: Trigger = "shift+Super+KP_Add".parse().unwrap();
+        assert_eq!(trigger.to_string(), "SHIFT+LOGO+KP_Add");
+        assert_eq!(trigger.label(), "Shift+Super+KP Add");
+        assert_eq!(trigger.to_string().parse::<Trigger>().unwrap(), trigger);
+
+        for invalid in [
+            "",
+            "CTRL+",
+            "+a",
+            "CTRL+CTRL+a",
+            "HYPER+a",
+            "CTRL++",
+            "CTRL+a b",
+        ] {
+            assert!(invalid.parse::<Trigger>().is_err(), "{invalid}");
+        }
+    }
+}
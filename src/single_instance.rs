@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+
+use futures_channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures_util::Stream;
+use url::Url;
+use zbus::{
+    fdo::RequestNameReply,
+    names::WellKnownName,
+    zvariant::{OwnedObjectPath, OwnedValue, Value},
+};
+
+use crate::{proxy::Proxy, ActivationToken, AppID, Error};
+
+const APPLICATION_INTERFACE: &str = "org.freedesktop.Application";
+
+/// An activation request forwarded by a secondary instance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Activation {
+    activation_token: Option<ActivationToken>,
+    uris: Vec<Url>,
+}
+
+impl Activation {
+    fn from_platform_data(uris: Vec<Url>, platform_data: &HashMap<String, OwnedValue>) -> Self {
+        let activation_token = ["activation-token", "desktop-startup-id"]
+            .iter()
+            .find_map(|key| {
+                platform_data
+                    .get(*key)
+                    .and_then(|value| <&str>::try_from(&**value).ok())
+            })
+            .map(ActivationToken::from);
+        Self {
+            activation_token,
+            uris,
+        }
+    }
+
+    /// The token to use to focus the application window, if the secondary
+    /// instance was started with one.
+    pub fn activation_token(&self) -> Option<&ActivationToken> {
+        self.activation_token.as_ref()
+    }
+
+    /// The URIs the secondary instance was asked to open.
+    pub fn uris(&self) -> &[Url] {
+        &self.uris
+    }
+}
+
+struct Application(UnboundedSender<Activation>);
+
+#[zbus::interface(name = "org.freedesktop.Application")]
+impl Application {
+    fn activate(&self, platform_data: HashMap<String, OwnedValue>) {
+        let _ = self
+            .0
+            .unbounded_send(Activation::from_platform_data(vec![], &platform_data));
+    }
+
+    fn open(&self, uris: Vec<String>, platform_data: HashMap<String, OwnedValue>) {
+        let uris = uris.iter().filter_map(|uri| Url::parse(uri).ok()).collect();
+        let _ = self
+            .0
+            .unbounded_send(Activation::from_platform_data(uris, &platform_data));
+    }
+}
+
+/// The role of the running instance of an application, as returned by
+/// [`Instance::register`].
+#[derive(Debug)]
+pub enum Instance {
+    /// This is the first instance, it owns the application ID on the session
+    /// bus and receives the activations of the other instances.
+    Primary(PrimaryInstance),
+    /// Another instance is already running, it was asked to activate itself.
+    /// The current instance is expected to exit.
+    Secondary,
+}
+
+impl Instance {
+    /// Register the application on the session bus.
+    ///
+    /// The first instance owns the `app_id` name and serves the
+    /// `org.freedesktop.Application` interface. The next instances forward
+    /// `uris` along with their activation token, taken from the
+    /// `XDG_ACTIVATION_TOKEN` or `DESKTOP_STARTUP_ID` environment variables,
+    /// so the first instance can focus its window.
+    ///
+    /// This works inside a sandbox as an application is allowed to own its
+    /// own ID.
+    pub async fn register(app_id: &AppID, uris: &[Url]) -> Result<Self, Error> {
+        let cnx = Proxy::connection().await?;
+        let name = WellKnownName::try_from(app_id.as_ref()).map_err(zbus::Error::from)?;
+        let path = object_path(app_id)?;
+
+        let (sender, receiver) = unbounded();
+        // Serve the interface before owning the name to not miss any call.
+        cnx.object_server().at(&path, Application(sender)).await?;
+        let reply = cnx
+            .request_name_with_flags(&name, zbus::fdo::RequestNameFlags::DoNotQueue.into())
+            .await;
+        match reply {
+            Ok(RequestNameReply::PrimaryOwner | RequestNameReply::AlreadyOwner) => {
+                Ok(Self::Primary(PrimaryInstance { path, receiver }))
+            }
+            Ok(_) | Err(zbus::Error::NameTaken) => {
+                cnx.object_server().remove::<Application, _>(&path).await?;
+                activate_primary(&cnx, &name, &path, uris).await?;
+                Ok(Self::Secondary)
+            }
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+/// The first running instance of an application.
+#[derive(Debug)]
+pub struct PrimaryInstance {
+    path: OwnedObjectPath,
+    receiver: UnboundedReceiver<Activation>,
+}
+
+impl PrimaryInstance {
+    /// The object path the `org.freedesktop.Application` interface is served
+    /// at.
+    pub fn path(&self) -> &OwnedObjectPath {
+        &self.path
+    }
+
+    /// A stream of the activations forwarded by secondary instances.
+    pub fn receive_activations(&mut self) -> impl Stream<Item = Activation> + '_ {
+        &mut self.receiver
+    }
+}
+
+async fn activate_primary(
+    cnx: &zbus::Connection,
+    name: &WellKnownName<'_>,
+    path: &OwnedObjectPath,
+    uris: &[Url],
+) -> Result<(), Error> {
+    let mut platform_data = HashMap::new();
+    if let Some(token) = ["XDG_ACTIVATION_TOKEN", "DESKTOP_STARTUP_ID"]
+        .iter()
+        .find_map(|var| std::env::var(var).ok())
+    {
+        platform_data.insert("activation-token", Value::from(token.clone()));
+        platform_data.insert("desktop-startup-id", Value::from(token));
+    }
+    if uris.is_empty() {
+        cnx.call_method(
+            Some(name.clone()),
+            path.as_ref(),
+            Some(APPLICATION_INTERFACE),
+            "Activate",
+            &platform_data,
+        )
+        .await?;
+    } else {
+        let uris = uris.iter().map(Url::as_str).collect::<Vec<_>>();
+        cnx.call_method(
+            Some(name.clone()),
+            path.as_ref(),
+            Some(APPLICATION_INTERFACE),
+            "Open",
+            &(uris, platform_data),
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// The object path of an application, following the
+/// `org.freedesktop.Application` specification.
+fn object_path(app_id: &AppID) -> Result<OwnedObjectPath, Error> {
+    let path = format!("/{}", app_id.replace('.', "/").replace('-', "_"));
+    Ok(OwnedObjectPath::try_from(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn application_object_path() {
+        let app_id = AppID::try_from("org.example.my-app").unwrap();
+        assert_eq!(
+            object_path(&app_id).unwrap().as_str(),
+            "/org/example/my_app"
+        );
+    }
+
+    #[test]
+    fn activation_from_platform_data() {
+        let mut platform_data = HashMap::new();
+        platform_data.insert(
+            "desktop-startup-id".to_owned(),
+            OwnedValue::try_from(Value::from("token")).unwrap(),
+        );
+        let activation = Activation::from_platform_data(vec![], &platform_data);
+        assert_eq!(
+            activation.activation_token(),
+            Some(&ActivationToken::from("token"))
+        );
+
+        let activation = Activation::from_platform_data(vec![], &HashMap::new());
+        assert_eq!(activation.activation_token(), None);
+    }
+}
@@ -34,7 +34,9 @@ use zbus::zvariant::Type;
 /// ## From a Wayland Surface
 ///
 /// The `wayland` feature must be enabled. The exported surface handle will be
-/// unexported on `Drop`.
+/// unexported on `Drop`. Once given to a request builder, the identifier is
+/// kept alive until the response of the request is received, then dropped
+/// with the builder.
 ///
 /// ```text
 /// // let wl_surface = some_surface;
@@ -83,6 +85,22 @@ use zbus::zvariant::Type;
 /// /// Open some portals
 /// ```
 ///
+/// ## From an Exported Handle
+///
+/// Toolkits without a Rust binding, like Qt, can export the window themselves
+/// and hand over the resulting handle.
+///
+/// ```rust
+/// use ashpd::WindowIdentifier;
+///
+/// // Obtained with `KWaylandExtras::exportWindow` for example.
+/// let identifier = WindowIdentifier::from_wayland_handle("some-handle").unwrap();
+/// assert_eq!(identifier.to_string(), "wayland:some-handle");
+///
+/// let identifier = WindowIdentifier::from_exported("x11:0x400").unwrap();
+/// assert_eq!(identifier.to_string(), "x11:0x400");
+/// ```
+///
 /// In case you don't have access to a WindowIdentifier:
 /// ```rust
 /// use ashpd::WindowIdentifier;
@@ -103,6 +121,9 @@ pub enum WindowIdentifier {
     Wayland(WaylandWindowIdentifier),
     #[doc(hidden)]
     X11(WindowIdentifierType),
+    /// Wayland handle exported by a third party
+    #[doc(hidden)]
+    WaylandHandle(String),
     #[doc(hidden)]
     #[default]
     None,
@@ -128,6 +149,7 @@ impl std::fmt::Display for WindowIdentifier {
             #[cfg(feature = "wayland")]
             Self::Wayland(identifier) => f.write_str(&format!("{identifier}")),
             Self::X11(identifier) => f.write_str(&format!("{identifier}")),
+            Self::WaylandHandle(handle) => f.write_str(&format!("wayland:{handle}")),
             Self::None => f.write_str(""),
         }
     }
@@ -191,6 +213,28 @@ impl WindowIdentifier {
         Self::X11(WindowIdentifierType::X11(xid))
     }
 
+    /// Create an instance of [`WindowIdentifier`] from a handle exported with
+    /// the xdg-foreign protocol by a third party, for example
+    /// `KWaylandExtras::exportWindow` in Qt applications.
+    ///
+    /// Unlike [`WindowIdentifier::from_wayland`], the handle is not
+    /// unexported on `Drop`, that is left to whoever exported it.
+    pub fn from_wayland_handle(handle: &str) -> Result<Self, crate::Error> {
+        Self::from_exported(&format!("wayland:{handle}"))
+    }
+
+    /// Create an instance of [`WindowIdentifier`] from an already formatted
+    /// identifier, either `x11:XID` or `wayland:HANDLE`.
+    ///
+    /// This is useful for toolkits that don't have a Rust binding, such as
+    /// Qt through cxx-qt, which can hand over the identifier as a string.
+    pub fn from_exported(identifier: &str) -> Result<Self, crate::Error> {
+        match WindowIdentifierType::from_str(identifier)? {
+            WindowIdentifierType::X11(xid) => Ok(Self::from_xid(xid)),
+            WindowIdentifierType::Wayland(handle) => Ok(Self::WaylandHandle(handle)),
+        }
+    }
+
     #[cfg(feature = "wayland")]
     #[cfg_attr(docsrs, doc(cfg(feature = "wayland")))]
     /// Create an instance of [`WindowIdentifier`] from a Wayland surface.
@@ -293,7 +337,15 @@ impl FromStr for WindowIdentifierType {
                         .map_err(|_| PortalError::InvalidArgument(format!("Wrong XID {handle}")))?,
                 ))
             }
-            "wayland" => Ok(Self::Wayland(handle.to_owned())),
+            "wayland" => {
+                if handle.is_empty() || handle.chars().any(|c| c.is_whitespace() || c.is_control())
+                {
+                    return Err(PortalError::InvalidArgument(format!(
+                        "Invalid Wayland handle {handle:?}"
+                    )));
+                }
+                Ok(Self::Wayland(handle.to_owned()))
+            }
             t => Err(PortalError::InvalidArgument(format!(
                 "Invalid Window Identifier type {t}",
             ))),
@@ -349,6 +401,18 @@ mod tests {
             WindowIdentifierType::Wayland("Somerandomchars".to_owned())
         );
         assert!(WindowIdentifierType::from_str("some_handle").is_err());
+        assert!(WindowIdentifierType::from_str("wayland:").is_err());
+        assert!(WindowIdentifierType::from_str("wayland:some handle").is_err());
+        assert!(WindowIdentifier::from_wayland_handle("").is_err());
+        assert!(WindowIdentifier::from_exported("x11:zz").is_err());
+        assert!(matches!(
+            WindowIdentifier::from_wayland_handle("some-handle").unwrap(),
+            WindowIdentifier::WaylandHandle(handle) if handle == "some-handle"
+        ));
+        assert!(matches!(
+            WindowIdentifier::from_exported("x11:0x400").unwrap(),
+            WindowIdentifier::X11(WindowIdentifierType::X11(1024))
+        ));
         assert!(WindowIdentifierType::from_str("some_type:some_handle").is_err());
     }
 }
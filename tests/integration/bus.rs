@@ -0,0 +1,72 @@
+use std::{
+    io::{BufRead, BufReader},
+    path::PathBuf,
+    process::{Child, Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A session bus of its own, run by `dbus-daemon` for the duration of a test.
+pub struct PrivateBus {
+    daemon: Child,
+    address: String,
+    socket: PathBuf,
+}
+
+impl PrivateBus {
+    /// Start the bus, or `None` if `dbus-daemon` is not installed.
+    ///
+    /// # Panics
+    ///
+    /// If `dbus-daemon` is not installed on the CI, where the tests must not
+    /// be skipped.
+    pub fn spawn() -> Option<Self> {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let socket = std::env::temp_dir().join(format!(
+            "ashpd-integration-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address=1"])
+            .arg(format!("--address=unix:path={}", socket.display()))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn();
+        let Ok(mut daemon) = daemon else {
+            assert!(std::env::var_os("CI").is_none(), "dbus-daemon is missing");
+            eprintln!("Skipping the test, dbus-daemon is missing");
+            return None;
+        };
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        Some(Self {
+            daemon,
+            address: address.trim().to_owned(),
+            socket,
+        })
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// A new connection to the bus.
+    pub async fn connect(&self) -> zbus::Connection {
+        zbus::connection::Builder::address(self.address())
+            .unwrap()
+            .build()
+            .await
+            .unwrap()
+    }
+}
+
+impl Drop for PrivateBus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+        let _ = std::fs::remove_file(&self.socket);
+    }
+}
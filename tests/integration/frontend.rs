@@ -0,0 +1,180 @@
+//! A stand-in for xdg-desktop-portal: it exposes the frontend interfaces,
+//! forwards the requests to the backend and relays their responses, the way
+//! the real frontend does.
+
+use std::collections::HashMap;
+
+use zbus::{
+    message::Header,
+    zvariant::{ObjectPath, OwnedObjectPath, OwnedValue},
+    SignalContext,
+};
+
+pub const DESKTOP_DESTINATION: &str = "org.freedesktop.portal.Desktop";
+pub const DESKTOP_PATH: &str = "/org/freedesktop/portal/desktop";
+
+/// The response of the requests failing in the backend, like the real
+/// frontend does.
+const RESPONSE_OTHER: u32 = 2;
+
+#[derive(Clone)]
+struct Forwarder {
+    backend: String,
+}
+
+impl Forwarder {
+    /// Call `method` of the backend `interface` for the request made by the
+    /// sender of `header`, and emit its response on the request object.
+    ///
+    /// The request path is returned right away, the backend is called in the
+    /// background.
+    fn forward<A>(
+        &self,
+        ctxt: &SignalContext<'_>,
+        header: &Header<'_>,
+        interface: &'static str,
+        method: &'static str,
+        args: impl FnOnce(ObjectPath<'static>, HashMap<String, OwnedValue>) -> A,
+        mut options: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<OwnedObjectPath>
+    where
+        A: serde::Serialize + zbus::zvariant::DynamicType + Send + Sync + 'static,
+    {
+        let sender = header
+            .sender()
+            .ok_or_else(|| zbus::fdo::Error::Failed("No sender".to_owned()))?
+            .to_owned();
+        let token = options
+            .remove("handle_token")
+            .and_then(|token| String::try_from(token).ok())
+            .ok_or_else(|| zbus::fdo::Error::InvalidArgs("No handle_token".to_owned()))?;
+        let path = ObjectPath::try_from(format!(
+            "{DESKTOP_PATH}/request/{}/{token}",
+            sender.trim_start_matches(':').replace('.', "_")
+        ))
+        .map_err(zbus::Error::from)?;
+
+        let cnx = ctxt.connection().clone();
+        let backend = self.backend.clone();
+        let body = args(path.clone(), options);
+        let request = path.clone();
+        tokio::spawn(async move {
+            let reply = cnx
+                .call_method(
+                    Some(backend.as_str()),
+                    DESKTOP_PATH,
+                    Some(interface),
+                    method,
+                    &body,
+                )
+                .await;
+            let (response, results) = reply
+                .and_then(|reply| {
+                    reply
+                        .body()
+                        .deserialize::<(u32, HashMap<String, OwnedValue>)>()
+                })
+                .unwrap_or((RESPONSE_OTHER, HashMap::new()));
+            cnx.emit_signal(
+                Some(sender),
+                &request,
+                "org.freedesktop.portal.Request",
+                "Response",
+                &(response, results),
+            )
+            .await
+            .unwrap();
+        });
+        Ok(path.into())
+    }
+}
+
+pub struct Account(Forwarder);
+
+#[zbus::interface(name = "org.freedesktop.portal.Account")]
+impl Account {
+    #[zbus(property, name = "version")]
+    fn version(&self) -> u32 {
+        1
+    }
+
+    fn get_user_information(
+        &self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        #[zbus(header)] header: Header<'_>,
+        window: String,
+        options: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<OwnedObjectPath> {
+        self.0.forward(
+            &ctxt,
+            &header,
+            "org.freedesktop.impl.portal.Account",
+            "GetUserInformation",
+            |handle, options| (handle, "", window, options),
+            options,
+        )
+    }
+}
+
+pub struct FileChooser(Forwarder);
+
+#[zbus::interface(name = "org.freedesktop.portal.FileChooser")]
+impl FileChooser {
+    #[zbus(property, name = "version")]
+    fn version(&self) -> u32 {
+        4
+    }
+
+    fn open_file(
+        &self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        #[zbus(header)] header: Header<'_>,
+        window: String,
+        title: String,
+        options: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<OwnedObjectPath> {
+        self.0.forward(
+            &ctxt,
+            &header,
+            "org.freedesktop.impl.portal.FileChooser",
+            "OpenFile",
+            |handle, options| (handle, "", window, title, options),
+            options,
+        )
+    }
+
+    fn save_file(
+        &self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        #[zbus(header)] header: Header<'_>,
+        window: String,
+        title: String,
+        options: HashMap<String, OwnedValue>,
+    ) -> zbus::fdo::Result<OwnedObjectPath> {
+        self.0.forward(
+            &ctxt,
+            &header,
+            "org.freedesktop.impl.portal.FileChooser",
+            "SaveFile",
+            |handle, options| (handle, "", window, title, options),
+            options,
+        )
+    }
+}
+
+/// Serve the frontend on `cnx`, forwarding to the backend owning `backend`.
+pub async fn serve(cnx: &zbus::Connection, backend: &str) {
+    let forwarder = Forwarder {
+        backend: backend.to_owned(),
+    };
+    let object_server = cnx.object_server();
+    object_server
+        .at(DESKTOP_PATH, Account(forwarder.clone()))
+        .await
+        .unwrap();
+    object_server
+        .at(DESKTOP_PATH, FileChooser(forwarder))
+        .await
+        .unwrap();
+    cnx.request_name(DESKTOP_DESTINATION).await.unwrap();
+}
@@ -0,0 +1,176 @@
+//! End-to-end tests of the frontend API, going through a stub portal frontend
+//! to backends implemented with the `backend` module, on a private bus.
+//!
+//! The tests are skipped if `dbus-daemon` is not installed, except on the CI,
+//! i.e. when the `CI` environment variable is set, where they fail instead.
+
+mod bus;
+mod frontend;
+
+use ashpd::{
+    backend::{
+        account::{AccountImpl, AccountInterface, UserInformationOptions},
+        file_chooser::{
+            FileChooserImpl, FileChooserInterface, OpenFileOptions, SaveFileOptions,
+            SaveFilesOptions, SelectedFiles,
+        },
+        request::{RequestHandle, RequestImpl},
+        Backend, BusType, Result,
+    },
+    desktop::{account::UserInformation, file_chooser::SelectedFiles as Selection, ResponseError},
+    AppID, FilePath, PortalError, WindowIdentifierType,
+};
+use async_trait::async_trait;
+use bus::PrivateBus;
+
+const BACKEND_NAME: &str = "org.freedesktop.impl.portal.desktop.test";
+
+struct Portal;
+
+#[async_trait]
+impl RequestImpl for Portal {
+    async fn close(&self, _handle: RequestHandle) {}
+}
+
+impl AccountImpl for Portal {
+    async fn get_user_information(
+        &self,
+        _handle: RequestHandle,
+        _app_id: Option<AppID>,
+        _window_identifier: Option<WindowIdentifierType>,
+        options: UserInformationOptions,
+    ) -> Result<UserInformation> {
+        Ok(UserInformation::new(
+            "user",
+            options.reason().unwrap_or("User"),
+            "file:///user.png".parse().unwrap(),
+        ))
+    }
+}
+
+impl FileChooserImpl for Portal {
+    async fn open_file(
+        &self,
+        _handle: RequestHandle,
+        _app_id: Option<AppID>,
+        _window_identifier: Option<WindowIdentifierType>,
+        title: &str,
+        _options: OpenFileOptions,
+    ) -> Result<SelectedFiles> {
+        let uri = url::Url::from_file_path(format!("/tmp/{title}")).unwrap();
+        Ok(SelectedFiles::default()
+            .uri(uri)
+            .current_folder(FilePath::new("/tmp").unwrap()))
+    }
+
+    async fn save_file(
+        &self,
+        _handle: RequestHandle,
+        _app_id: Option<AppID>,
+        _window_identifier: Option<WindowIdentifierType>,
+        _title: &str,
+        _options: SaveFileOptions,
+    ) -> Result<SelectedFiles> {
+        Err(PortalError::NotAllowed("Saving is disabled".to_owned()))
+    }
+
+    async fn save_files(
+        &self,
+        _handle: RequestHandle,
+        _app_id: Option<AppID>,
+        _window_identifier: Option<WindowIdentifierType>,
+        _title: &str,
+        _options: SaveFilesOptions,
+    ) -> Result<SelectedFiles> {
+        unreachable!()
+    }
+}
+
+/// Start a bus with the backend and the stub frontend on it, and return a
+/// connection for the application.
+async fn setup() -> Option<(PrivateBus, Backend, zbus::Connection, zbus::Connection)> {
+    let bus = PrivateBus::spawn()?;
+    let backend = Backend::new_on_bus(BusType::Address(bus.address().to_owned()), BACKEND_NAME)
+        .await
+        .unwrap();
+    let cnx = backend.connection().clone();
+    backend
+        .serve(AccountInterface::new(Portal, cnx.clone()))
+        .await
+        .unwrap();
+    backend
+        .serve(FileChooserInterface::new(Portal, cnx))
+        .await
+        .unwrap();
+
+    let frontend = bus.connect().await;
+    frontend::serve(&frontend, BACKEND_NAME).await;
+    let app = bus.connect().await;
+    Some((bus, backend, frontend, app))
+}
+
+#[tokio::test]
+async fn user_information() {
+    let Some((_bus, _backend, _frontend, app)) = setup().await else {
+        return;
+    };
+
+    let user = UserInformation::request()
+        .reason("Tester")
+        .connection(app)
+        .send()
+        .await
+        .unwrap()
+        .response()
+        .unwrap();
+    assert_eq!(user.id(), "user");
+    assert_eq!(user.name(), "Tester");
+    assert_eq!(user.image().as_str(), "file:///user.png");
+}
+
+#[tokio::test]
+async fn open_file() {
+    let Some((_bus, _backend, _frontend, app)) = setup().await else {
+        return;
+    };
+
+    let selection = Selection::open_file()
+        .title("report.pdf")
+        .connection(app)
+        .send()
+        .await
+        .unwrap()
+        .response()
+        .unwrap();
+    assert_eq!(
+        selection
+            .uris()
+            .iter()
+            .map(|uri| uri.as_str())
+            .collect::<Vec<_>>(),
+        ["file:///tmp/report.pdf"]
+    );
+    assert_eq!(
+        selection.current_folder(),
+        Some(std::path::Path::new("/tmp"))
+    );
+}
+
+#[tokio::test]
+async fn failed_request() {
+    let Some((_bus, _backend, _frontend, app)) = setup().await else {
+        return;
+    };
+
+    let response = Selection::save_file()
+        .title("report.pdf")
+        .connection(app)
+        .send()
+        .await
+        .unwrap()
+        .response();
+    assert!(matches!(
+        response,
+        Err(ashpd::Error::Response(ResponseError::Other))
+    ));
+}